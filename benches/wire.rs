@@ -0,0 +1,177 @@
+//! Allocations and throughput of a peer handling a stream of messages, for `cargo bench --bench
+//! wire --features bench`.
+//!
+//! The stream is written into a read buffer a TCP segment at a time, and each read is split off
+//! into a packet like the node's socket reader does. A [Peer] handles the packets, and anything it
+//! sends back is drained from its channel straight away.
+//!
+//! Every allocation in the process is counted, including the ones made while handling each
+//! message, e.g. looking up the peer table for a keepalive or signing a telemetry ack, so the
+//! numbers are an upper bound for the framing and serialization.
+use bytes::BytesMut;
+use feeless::state::MemoryState;
+use feeless::{EarlyMessages, Network, Packet, Peer};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Instant;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+
+/// How much the socket reader reserves for each read.
+const READ_SIZE: usize = 10240;
+
+/// How much each read returns.
+const SEGMENT: usize = 1460;
+
+/// Where the message type is in a header, followed by the two bytes of extensions.
+const MESSAGE_TYPE: usize = 5;
+
+const KEEPALIVE: u8 = 2;
+const TELEMETRY_REQ: u8 = 12;
+
+/// Eight peers of 18 bytes each, which are all `[::]:0` here.
+const KEEPALIVE_LEN: usize = 8 * 18;
+
+struct Counting;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        ALLOCATED.fetch_add(layout.size(), Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        ALLOCATED.fetch_add(new_size, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+struct Run {
+    name: &'static str,
+    messages: usize,
+    seconds: f64,
+    allocations: usize,
+    allocated: usize,
+    sent: usize,
+}
+
+impl Run {
+    fn print(&self) {
+        let messages = self.messages as f64;
+        println!(
+            "{:<14} {:>9} {:>13.0} {:>13.2} {:>13.1} {:>13.1}",
+            self.name,
+            self.messages,
+            messages / self.seconds,
+            self.allocations as f64 / messages,
+            self.allocated as f64 / messages,
+            self.sent as f64 / messages,
+        );
+    }
+}
+
+/// A peer that doesn't need a handshake first, and a task counting the bytes it sends.
+fn peer(network: Network) -> (Peer, mpsc::Sender<Packet<BytesMut>>, JoinHandle<usize>) {
+    let state = Arc::new(MemoryState::new(network));
+    let (mut peer, tx, mut rx) =
+        Peer::new_with_channels(network, state, SocketAddr::from(([127, 0, 0, 1], 7075)));
+    peer.early_messages = EarlyMessages::Allow;
+    peer.probe_peers = false;
+    let sent = tokio::spawn(async move {
+        let mut sent = 0;
+        while let Some(packet) = rx.recv().await {
+            sent += packet.data.len();
+        }
+        sent
+    });
+    (peer, tx, sent)
+}
+
+/// The first thing a peer sends is the header of its handshake, which has the right magic number,
+/// network and versions for the other messages.
+async fn header(network: Network, message_type: u8) -> anyhow::Result<Vec<u8>> {
+    let state = Arc::new(MemoryState::new(network));
+    let (peer, _tx, mut rx) =
+        Peer::new_with_channels(network, state, SocketAddr::from(([127, 0, 0, 1], 7075)));
+    let task = tokio::spawn(peer.run());
+    let mut header = rx
+        .recv()
+        .await
+        .ok_or_else(|| anyhow::anyhow!("No handshake"))?
+        .data
+        .to_vec();
+    task.abort();
+    header[MESSAGE_TYPE] = message_type;
+    header[MESSAGE_TYPE + 1] = 0;
+    header[MESSAGE_TYPE + 2] = 0;
+    Ok(header)
+}
+
+async fn run(
+    name: &'static str,
+    network: Network,
+    message: Vec<u8>,
+    messages: usize,
+) -> anyhow::Result<Run> {
+    let stream = message.repeat(messages);
+    let (mut peer, tx, sent) = peer(network);
+    peer.init().await?;
+
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed);
+    let allocated = ALLOCATED.load(Ordering::Relaxed);
+    let started = Instant::now();
+    let task = tokio::spawn(peer.run());
+    let mut read_buffer = BytesMut::new();
+    for read in stream.chunks(SEGMENT) {
+        read_buffer.reserve(READ_SIZE);
+        read_buffer.extend_from_slice(read);
+        tx.send(Packet::new(read_buffer.split())).await?;
+    }
+    drop(tx);
+    task.await??;
+    let seconds = started.elapsed().as_secs_f64();
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed) - allocations;
+    let allocated = ALLOCATED.load(Ordering::Relaxed) - allocated;
+
+    Ok(Run {
+        name,
+        messages,
+        seconds,
+        allocations,
+        allocated,
+        sent: sent.await?,
+    })
+}
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() -> anyhow::Result<()> {
+    let network = Network::Live;
+
+    let mut keepalive = header(network, KEEPALIVE).await?;
+    keepalive.resize(keepalive.len() + KEEPALIVE_LEN, 0);
+    let telemetry_req = header(network, TELEMETRY_REQ).await?;
+
+    println!(
+        "{:<14} {:>9} {:>13} {:>13} {:>13} {:>13}",
+        "message", "messages", "messages/s", "allocs/msg", "bytes/msg", "sent/msg"
+    );
+    run("keepalive", network, keepalive, 100_000).await?.print();
+    run("telemetry_req", network, telemetry_req, 10_000)
+        .await?
+        .print();
+    Ok(())
+}
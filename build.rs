@@ -0,0 +1,74 @@
+//! Embeds the git commit and enabled features of this build, for `feeless::build_info`.
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-changed=Cargo.toml");
+
+    // Only from our own checkout, so a crates.io build inside someone else's repository doesn't
+    // pick up their commit.
+    let git = Path::new(&env::var("CARGO_MANIFEST_DIR").unwrap()).join(".git");
+    let commit = if git.exists() {
+        watch_head(&git);
+        Command::new("git")
+            .arg("rev-parse")
+            .arg("HEAD")
+            .output()
+            .ok()
+            .filter(|output| output.status.success())
+            .and_then(|output| String::from_utf8(output.stdout).ok())
+            .map(|commit| commit.trim().to_owned())
+            .unwrap_or_default()
+    } else {
+        String::new()
+    };
+    println!("cargo:rustc-env=FEELESS_GIT_COMMIT={}", commit);
+
+    // Optional dependencies show up as features too, so only keep the ones in `[features]`.
+    let declared = declared_features();
+    let mut features: Vec<String> = env::vars()
+        .filter_map(|(key, _)| {
+            key.strip_prefix("CARGO_FEATURE_")
+                .map(|feature| feature.to_lowercase())
+        })
+        .filter(|feature| declared.contains(feature))
+        .collect();
+    features.sort();
+    println!("cargo:rustc-env=FEELESS_FEATURES={}", features.join(","));
+    println!(
+        "cargo:rustc-env=FEELESS_TARGET={}",
+        env::var("TARGET").unwrap_or_default()
+    );
+}
+
+/// The names in the `[features]` table of Cargo.toml, as they are in `CARGO_FEATURE_*`.
+fn declared_features() -> Vec<String> {
+    let manifest = fs::read_to_string("Cargo.toml").unwrap();
+    manifest
+        .lines()
+        .map(str::trim)
+        .skip_while(|line| *line != "[features]")
+        .skip(1)
+        .take_while(|line| !line.starts_with('['))
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| line.split('=').next())
+        .map(|name| name.trim().replace('-', "_").to_lowercase())
+        .collect()
+}
+
+/// Rebuild when the checked out commit changes, whether `HEAD` moves or the branch it's on does.
+fn watch_head(git: &Path) {
+    let head = git.join("HEAD");
+    println!("cargo:rerun-if-changed={}", head.display());
+    if let Ok(head) = fs::read_to_string(&head) {
+        if let Some(branch) = head.trim().strip_prefix("ref: ") {
+            let branch = git.join(branch);
+            if branch.exists() {
+                println!("cargo:rerun-if-changed={}", branch.display());
+            }
+        }
+    }
+}
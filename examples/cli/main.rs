@@ -48,10 +48,24 @@ fn run() -> anyhow::Result<()> {
     })
     .contains("cryptocurrency");
 
-    keys::keys(&mut test, &feeless)?;
-    wallet::wallet(&mut test, &feeless)?;
-    signing::signing(&mut test, &feeless)?;
-    units::units(&mut test, &feeless)?;
+    test.run("Show the build info as JSON.", || {
+        Ok(run_fun!(
+            $feeless version --json
+        )?)
+    })
+    .contains("\"protocol\"");
+
+    test.run("Benchmark work generation as JSON.", || {
+        Ok(run_fun!(
+            $feeless work benchmark --duration 100ms
+        )?)
+    })
+    .contains("\"hashes_per_second\"");
+
+    keys::keys(&mut test, feeless)?;
+    wallet::wallet(&mut test, feeless)?;
+    signing::signing(&mut test, feeless)?;
+    units::units(&mut test, feeless)?;
 
     test.end()?;
 
@@ -188,7 +202,7 @@ impl Deref for DataDir {
 
 impl AsRef<OsStr> for DataDir {
     fn as_ref(&self) -> &OsStr {
-        &OsStr::new(&self.0)
+        OsStr::new(&self.0)
     }
 }
 
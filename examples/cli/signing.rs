@@ -45,5 +45,20 @@ pub fn signing(test: &mut Test, feeless: &str) -> anyhow::Result<()> {
 
     remove_file(armor_path)?;
 
+    let proof_path = "test.proof";
+    test.run("Create an address ownership proof", || {
+        Ok(run_fun!(
+            $feeless wallet prove "withdrawal address" --nonce 1234 > $proof_path
+        )?)
+    });
+
+    test.run("Verify an address ownership proof", || {
+        Ok(run_fun!(
+            $feeless verify --proof --nonce 1234 < $proof_path
+        )?)
+    });
+
+    remove_file(proof_path)?;
+
     Ok(())
 }
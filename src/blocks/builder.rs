@@ -0,0 +1,243 @@
+use crate::blocks::{Link, Previous, Root, StateBlock};
+use crate::pow::Difficulty;
+use crate::{Private, Public, Raw, Work};
+use anyhow::{anyhow, Context};
+
+/// Put together a signed state block with work, ready to publish, e.g. with
+/// [crate::rpc::calls::ProcessRequest].
+///
+/// The previous block defaults to [Previous::Open] and the link to [Link::Nothing]. Work is
+/// optional when signing, as it can be generated separately from the block.
+///
+/// ```
+/// use feeless::blocks::{Link, StateBlockBuilder};
+/// use feeless::{Difficulty, Raw, Seed};
+///
+/// # #[tokio::main]
+/// # async fn main() -> anyhow::Result<()> {
+/// let private = Seed::zero().derive(0);
+/// let account = private.to_public()?;
+/// let block = StateBlockBuilder::new(account.clone())
+///     .representative(account)
+///     .balance(Raw::from(1000))
+///     .link(Link::Nothing)
+///     .generate_work(&Difficulty::new(0))
+///     .await?
+///     .sign(&private)?;
+/// block.verify_self_signature()?;
+/// assert!(block.work.is_some());
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct StateBlockBuilder {
+    account: Public,
+    previous: Previous,
+    representative: Option<Public>,
+    balance: Option<Raw>,
+    link: Link,
+    work: Option<Work>,
+}
+
+impl StateBlockBuilder {
+    pub fn new(account: Public) -> Self {
+        Self {
+            account,
+            previous: Previous::Open,
+            representative: None,
+            balance: None,
+            link: Link::Nothing,
+            work: None,
+        }
+    }
+
+    pub fn previous(mut self, previous: Previous) -> Self {
+        self.previous = previous;
+        self
+    }
+
+    pub fn representative(mut self, representative: Public) -> Self {
+        self.representative = Some(representative);
+        self
+    }
+
+    /// The balance of the account after this block.
+    pub fn balance(mut self, balance: Raw) -> Self {
+        self.balance = Some(balance);
+        self
+    }
+
+    /// The destination account of a send, the source block of a receive, or nothing for a
+    /// change.
+    pub fn link(mut self, link: Link) -> Self {
+        self.link = link;
+        self
+    }
+
+    /// Work that has already been generated for the block.
+    pub fn work(mut self, work: Work) -> Self {
+        self.work = Some(work);
+        self
+    }
+
+    /// Generate work on this machine, in a blocking thread.
+    pub async fn generate_work(mut self, difficulty: &Difficulty) -> anyhow::Result<Self> {
+        let root = self.work_root();
+        let difficulty = difficulty.to_owned();
+        self.work = Some(
+            tokio::task::spawn_blocking(move || Work::generate(&root, &difficulty))
+                .await
+                .context("Generating work")??,
+        );
+        Ok(self)
+    }
+
+    /// Ask an RPC server to generate the work with `work_generate`, at the node's difficulty
+    /// unless `difficulty` is set.
+    #[cfg(feature = "rpc_client")]
+    pub async fn fetch_work(
+        mut self,
+        client: &crate::rpc::client::RPCClient,
+        difficulty: Option<Difficulty>,
+    ) -> anyhow::Result<Self> {
+        use crate::blocks::BlockHash;
+        use crate::rpc::calls::WorkGenerateRequest;
+        use crate::rpc::client::RPCRequest;
+        use crate::HexBytes;
+        use std::convert::TryFrom;
+
+        let root = self.work_root();
+        let request = WorkGenerateRequest {
+            difficulty: difficulty.clone(),
+            account: Some(self.account.to_address()),
+            ..WorkGenerateRequest::new(BlockHash::try_from(root.as_bytes())?)
+        };
+        let work = (&request).call(client).await?.work;
+        if let Some(difficulty) = difficulty {
+            if !work.verify(&root, &difficulty)? {
+                return Err(anyhow!(
+                    "Work {} from the RPC server is below difficulty {:?}",
+                    work.to_hex_lower(),
+                    difficulty
+                ));
+            }
+        }
+        self.work = Some(work);
+        Ok(self)
+    }
+
+    /// The block with its hash, without a signature.
+    pub fn build(&self) -> anyhow::Result<StateBlock> {
+        let representative = self
+            .representative
+            .to_owned()
+            .ok_or_else(|| anyhow!("The block needs a representative"))?;
+        let balance = self
+            .balance
+            .to_owned()
+            .ok_or_else(|| anyhow!("The block needs a balance"))?;
+        let mut block = StateBlock::new(
+            self.account.to_owned(),
+            self.previous.to_owned(),
+            representative,
+            balance,
+            self.link.to_owned(),
+        );
+        block.work = self.work.to_owned();
+        Ok(block)
+    }
+
+    /// The block signed by `private`, which has to be the key of the account.
+    pub fn sign(&self, private: &Private) -> anyhow::Result<StateBlock> {
+        if private.to_public()? != self.account {
+            return Err(anyhow!(
+                "The private key isn't for account {}",
+                self.account.to_address()
+            ));
+        }
+        let mut block = self.build()?;
+        block.signature = Some(private.sign(block.hash.as_bytes())?);
+        Ok(block)
+    }
+
+    fn work_root(&self) -> Root {
+        Root::new(&self.previous, &self.account)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blocks::BlockHash;
+    use crate::Seed;
+
+    #[test]
+    fn signs() {
+        let private = Seed::zero().derive(0);
+        let account = private.to_public().unwrap();
+        let previous = Previous::Block(BlockHash::zero());
+        let builder = StateBlockBuilder::new(account.clone())
+            .previous(previous.clone())
+            .representative(account.clone())
+            .balance(Raw::from(5))
+            .work(Work::zero());
+
+        let block = builder.sign(&private).unwrap();
+        block.verify_self_signature().unwrap();
+        assert_eq!(
+            block.hash,
+            StateBlock::new(
+                account.clone(),
+                previous,
+                account,
+                Raw::from(5),
+                Link::Nothing
+            )
+            .hash
+        );
+        assert_eq!(block.work, Some(Work::zero()));
+
+        assert!(builder.sign(&Seed::zero().derive(1)).is_err());
+        assert!(StateBlockBuilder::new(block.account).build().is_err());
+    }
+
+    #[cfg(feature = "rpc_server")]
+    #[tokio::test]
+    async fn fetches_work() {
+        use crate::rpc::calls::WorkGenerateResponse;
+        use crate::rpc::client::RPCClient;
+        use crate::Multiplier;
+        use serde_json::Value;
+        use warp::Filter;
+
+        let route = warp::post().and(warp::body::json()).map(|body: Value| {
+            assert_eq!(body["action"], "work_generate");
+            warp::reply::json(&WorkGenerateResponse {
+                work: Work::zero(),
+                difficulty: Difficulty::new(0),
+                multiplier: Multiplier::new(1.0).unwrap(),
+                hash: BlockHash::zero(),
+            })
+        });
+        let (address, server) = warp::serve(route).bind_ephemeral(([127, 0, 0, 1], 0));
+        tokio::spawn(server);
+        let client = RPCClient::new(format!("http://{}", address));
+
+        let private = Seed::zero().derive(0);
+        let account = private.to_public().unwrap();
+        let block = StateBlockBuilder::new(account.clone())
+            .representative(account)
+            .balance(Raw::zero())
+            .fetch_work(&client, Some(Difficulty::new(0)))
+            .await
+            .unwrap()
+            .sign(&private)
+            .unwrap();
+        assert_eq!(block.work, Some(Work::zero()));
+
+        assert!(StateBlockBuilder::new(block.account)
+            .fetch_work(&client, Some(Difficulty::new(u64::MAX)))
+            .await
+            .is_err());
+    }
+}
@@ -1,11 +1,83 @@
-use crate::blocks::BlockHash;
+#[cfg(feature = "node")]
+use crate::node::{Header, Wire};
+
+#[cfg(feature = "node")]
+use bytes::BytesMut;
+
+#[cfg(feature = "node")]
+use crate::bytes::Bytes;
+
+#[cfg(feature = "node")]
+use std::convert::TryFrom;
+
+use crate::blocks::{hash_block, BlockHash};
+use crate::keys::public::{from_address, to_address};
 use crate::{Public, Signature, Work};
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct ChangeBlock {
-    previous: BlockHash,
-    representative: Public,
+    /// The hash of the previous block in this account.
+    pub previous: BlockHash,
+
+    #[serde(serialize_with = "to_address", deserialize_with = "from_address")]
+    #[cfg_attr(feature = "schema", schemars(with = "crate::Address"))]
+    pub representative: Public,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub work: Option<Work>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub signature: Option<Signature>,
 }
+
+impl ChangeBlock {
+    pub const LEN: usize = 136;
+
+    pub fn new(previous: BlockHash, representative: Public) -> Self {
+        Self {
+            previous,
+            representative,
+            work: None,
+            signature: None,
+        }
+    }
+
+    pub fn hash(&self) -> BlockHash {
+        hash_block(&[self.previous.as_bytes(), self.representative.as_bytes()])
+    }
+}
+
+#[cfg(feature = "node")]
+impl Wire for ChangeBlock {
+    fn serialize_into(&self, buf: &mut BytesMut) {
+        buf.extend_from_slice(self.previous.as_bytes());
+        buf.extend_from_slice(self.representative.as_bytes());
+        super::serialize_legacy_tail(buf, &self.signature, &self.work);
+    }
+
+    fn deserialize(_: Option<&Header>, data: &[u8]) -> anyhow::Result<Self>
+    where
+        Self: Sized,
+    {
+        let mut data = Bytes::new(data);
+        let previous = BlockHash::try_from(data.slice(BlockHash::LEN)?)?;
+        let representative = Public::try_from(data.slice(Public::LEN)?)?;
+        let signature = Some(Signature::try_from(data.slice(Signature::LEN)?)?);
+        let work = Some(Work::from_le_bytes(data.slice(Work::LEN)?)?);
+
+        Ok(Self {
+            previous,
+            representative,
+            work,
+            signature,
+        })
+    }
+
+    fn len(_: Option<&Header>) -> anyhow::Result<usize>
+    where
+        Self: Sized,
+    {
+        Ok(ChangeBlock::LEN)
+    }
+}
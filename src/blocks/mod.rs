@@ -1,13 +1,19 @@
 //! Handling, creating and parsing blocks.
 mod block_hash;
+mod builder;
 mod change_block;
 mod open_block;
 mod receive_block;
+mod root;
 mod send_block;
 mod state_block;
+mod templates;
 
 #[cfg(feature = "node")]
-use crate::node::Wire;
+use bytes::BytesMut;
+
+#[cfg(feature = "node")]
+use crate::node::{decode, Field, Wire};
 
 #[cfg(feature = "node")]
 use crate::node::Header;
@@ -16,14 +22,16 @@ use crate::node::Header;
 pub(crate) use state_block::deserialize_to_unsure_link;
 
 use crate::encoding::blake2b;
-use crate::keys::public::to_address;
+use crate::keys::public::{from_address, to_address};
 use crate::network::Network;
-use crate::{Private, Public, Raw, Signature, Work};
+use crate::{Error, Private, Public, Raw, Signature, Work};
 use anyhow::{anyhow, Context};
 pub use block_hash::BlockHash;
+pub use builder::StateBlockBuilder;
 pub use change_block::ChangeBlock;
 pub use open_block::OpenBlock;
 pub use receive_block::ReceiveBlock;
+pub use root::Root;
 pub use send_block::SendBlock;
 use serde;
 use serde::{Deserialize, Serialize};
@@ -31,8 +39,12 @@ pub use state_block::{Link, StateBlock, Subtype};
 use std::convert::TryFrom;
 use std::str::FromStr;
 use strum_macros::EnumString;
+pub use templates::Epoch;
 
-#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize, EnumString)]
+#[derive(
+    Debug, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Serialize, Deserialize, EnumString,
+)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[serde(rename_all = "snake_case")]
 #[strum(serialize_all = "snake_case")]
 pub enum BlockType {
@@ -79,6 +91,7 @@ impl TryFrom<u8> for BlockType {
 
 /// For "holding" deserialized blocks that we can't convert to `Block` yet.
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[serde(tag = "type", rename_all = "snake_case")]
 pub enum BlockHolder {
     Send(SendBlock),
@@ -88,10 +101,52 @@ pub enum BlockHolder {
     State(StateBlock),
 }
 
+impl BlockHolder {
+    pub fn hash(&self) -> BlockHash {
+        match self {
+            BlockHolder::Send(block) => block.hash(),
+            BlockHolder::Receive(block) => block.hash(),
+            BlockHolder::Open(block) => block.hash(),
+            BlockHolder::Change(block) => block.hash(),
+            BlockHolder::State(block) => block.hash.to_owned(),
+        }
+    }
+
+    pub fn block_type(&self) -> BlockType {
+        match self {
+            BlockHolder::Send(_) => BlockType::Send,
+            BlockHolder::Receive(_) => BlockType::Receive,
+            BlockHolder::Open(_) => BlockType::Open,
+            BlockHolder::Change(_) => BlockType::Change,
+            BlockHolder::State(_) => BlockType::State,
+        }
+    }
+}
+
+/// Legacy blocks end with the signature, then the work in little-endian, with zeros in place of
+/// either when it's missing.
+#[cfg(feature = "node")]
+fn serialize_legacy_tail(buf: &mut BytesMut, signature: &Option<Signature>, work: &Option<Work>) {
+    match signature {
+        Some(signature) => buf.extend_from_slice(signature.as_bytes()),
+        None => buf.extend_from_slice(&[0u8; Signature::LEN]),
+    }
+    match work {
+        Some(work) => buf.extend_from_slice(&work.to_le_bytes()),
+        None => buf.extend_from_slice(&[0u8; Work::LEN]),
+    }
+}
+
 #[cfg(feature = "node")]
 impl Wire for BlockHolder {
-    fn serialize(&self) -> Vec<u8> {
-        unimplemented!()
+    fn serialize_into(&self, buf: &mut BytesMut) {
+        match self {
+            BlockHolder::Send(block) => block.serialize_into(buf),
+            BlockHolder::Receive(block) => block.serialize_into(buf),
+            BlockHolder::Open(block) => block.serialize_into(buf),
+            BlockHolder::Change(block) => block.serialize_into(buf),
+            BlockHolder::State(block) => block.serialize_into(buf),
+        }
     }
 
     fn deserialize(header: Option<&Header>, data: &[u8]) -> anyhow::Result<Self>
@@ -112,7 +167,14 @@ impl Wire for BlockHolder {
                 BlockHolder::State(Wire::deserialize(header, data).context(context)?)
             }
             BlockType::Send => BlockHolder::Send(Wire::deserialize(header, data).context(context)?),
-            _ => todo!(),
+            BlockType::Receive => {
+                BlockHolder::Receive(Wire::deserialize(header, data).context(context)?)
+            }
+            BlockType::Open => BlockHolder::Open(Wire::deserialize(header, data).context(context)?),
+            BlockType::Change => {
+                BlockHolder::Change(Wire::deserialize(header, data).context(context)?)
+            }
+            block_type => return Err(anyhow!("{:?} blocks aren't supported", block_type)),
         };
         Ok(holder)
     }
@@ -125,12 +187,40 @@ impl Wire for BlockHolder {
         match header.as_ref().unwrap().ext().block_type()? {
             BlockType::State => StateBlock::len(header),
             BlockType::Send => SendBlock::len(header),
-            _ => todo!(),
+            BlockType::Receive => ReceiveBlock::len(header),
+            BlockType::Open => OpenBlock::len(header),
+            BlockType::Change => ChangeBlock::len(header),
+            block_type => Err(anyhow!("{:?} doesn't have a length", block_type)),
         }
     }
+
+    fn fields(header: Option<&Header>) -> anyhow::Result<Vec<Field>> {
+        debug_assert!(header.is_some());
+        let hash = |name| Field::new(name, BlockHash::LEN);
+        let public = |name| Field::new(name, Public::LEN);
+        let balance = Field::new("balance", Raw::LEN).decoded(decode::raw);
+        let mut fields = match header.as_ref().unwrap().ext().block_type()? {
+            BlockType::State => vec![
+                public("account"),
+                hash("previous"),
+                public("representative"),
+                balance,
+                Field::new("link", Link::LEN),
+            ],
+            BlockType::Send => vec![hash("previous"), public("destination"), balance],
+            BlockType::Receive => vec![hash("previous"), hash("source")],
+            BlockType::Open => vec![hash("source"), public("representative"), public("account")],
+            BlockType::Change => vec![hash("previous"), public("representative")],
+            block_type => return Err(anyhow!("{:?} doesn't have a length", block_type)),
+        };
+        fields.push(Field::new("signature", Signature::LEN));
+        fields.push(Field::new("work", Work::LEN));
+        Ok(fields)
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub enum Previous {
     Block(BlockHash),
     Open,
@@ -175,7 +265,8 @@ impl FromStr for Previous {
 ///
 /// When processing blocks from the network, this should be created after going through the
 /// controller since certain fields such as "amount" won't be available immediately.
-#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct Block {
     #[serde(rename = "type")]
     block_type: BlockType,
@@ -185,6 +276,7 @@ pub struct Block {
 
     /// The account owner of this block.
     #[serde(serialize_with = "to_address", deserialize_with = "from_address")]
+    #[cfg_attr(feature = "schema", schemars(with = "crate::Address"))]
     account: Public,
 
     /// Previous block hash on this account.
@@ -192,6 +284,7 @@ pub struct Block {
 
     /// The representative this account is delegating to.
     #[serde(serialize_with = "to_address", deserialize_with = "from_address")]
+    #[cfg_attr(feature = "schema", schemars(with = "crate::Address"))]
     representative: Public,
 
     /// The new balance of this account.
@@ -201,16 +294,19 @@ pub struct Block {
     link: Link,
 
     /// The signed block's hash with the account's private key.
+    #[serde(skip_serializing_if = "Option::is_none")]
     signature: Option<Signature>,
 
     /// The proof of work applied to this block.
+    #[serde(skip_serializing_if = "Option::is_none")]
     work: Option<Work>,
 
     /// What level of trust do we have with this block?
     state: ValidationState,
 }
 
-#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub enum ValidationState {
     Published,
     PresumedValid,
@@ -279,6 +375,41 @@ impl Block {
         b
     }
 
+    pub fn from_receive_block(
+        receive_block: &ReceiveBlock,
+        account: &Public,
+        representative: &Public,
+        balance: &Raw,
+    ) -> Self {
+        let mut b = Self::new(
+            BlockType::Receive,
+            account.to_owned(),
+            Previous::Block(receive_block.previous.to_owned()),
+            representative.to_owned(),
+            balance.to_owned(),
+            Link::Source(receive_block.source.to_owned()),
+            ValidationState::Valid,
+        );
+        b.signature = receive_block.signature.to_owned();
+        b.work = receive_block.work.to_owned();
+        b
+    }
+
+    pub fn from_change_block(change_block: &ChangeBlock, account: &Public, balance: &Raw) -> Self {
+        let mut b = Self::new(
+            BlockType::Change,
+            account.to_owned(),
+            Previous::Block(change_block.previous.to_owned()),
+            change_block.representative.to_owned(),
+            balance.to_owned(),
+            Link::Nothing,
+            ValidationState::Valid,
+        );
+        b.signature = change_block.signature.to_owned();
+        b.work = change_block.work.to_owned();
+        b
+    }
+
     pub fn from_state_block(state_block: &StateBlock) -> Self {
         let mut b = Self::new(
             BlockType::State,
@@ -294,9 +425,58 @@ impl Block {
         b
     }
 
+    /// The block as it's sent over the wire, e.g. to serve a bulk pull. The reverse of the
+    /// `from_*_block` functions.
+    pub fn to_holder(&self) -> anyhow::Result<BlockHolder> {
+        let previous = || match &self.previous {
+            Previous::Block(hash) => Ok(hash.to_owned()),
+            Previous::Open => Err(anyhow!("{:?} block without a previous", self.block_type)),
+        };
+        let link = self.link.as_bytes();
+        let (signature, work) = (self.signature.to_owned(), self.work.to_owned());
+        Ok(match self.block_type {
+            BlockType::Send => {
+                let mut b = SendBlock::new(
+                    previous()?,
+                    Public::try_from(link)?,
+                    self.balance.to_owned(),
+                );
+                b.signature = signature;
+                b.work = work;
+                BlockHolder::Send(b)
+            }
+            BlockType::Receive => {
+                let mut b = ReceiveBlock::new(previous()?, BlockHash::try_from(link)?);
+                b.signature = signature;
+                b.work = work;
+                BlockHolder::Receive(b)
+            }
+            BlockType::Open => {
+                let mut b = OpenBlock::new(
+                    BlockHash::try_from(link)?,
+                    self.representative.to_owned(),
+                    self.account.to_owned(),
+                );
+                b.signature = signature;
+                b.work = work;
+                BlockHolder::Open(b)
+            }
+            BlockType::Change => {
+                let mut b = ChangeBlock::new(previous()?, self.representative.to_owned());
+                b.signature = signature;
+                b.work = work;
+                BlockHolder::Change(b)
+            }
+            BlockType::State => BlockHolder::State(StateBlock::from(self.to_owned())),
+            BlockType::Invalid | BlockType::NotABlock => {
+                return Err(anyhow!("Not a block: {:?}", self.block_type))
+            }
+        })
+    }
+
     pub fn hash(&self) -> anyhow::Result<&BlockHash> {
         match &self.hash {
-            Some(block_hash) => Ok(&block_hash),
+            Some(block_hash) => Ok(block_hash),
             None => Err(anyhow!("Block not hashable!")),
         }
     }
@@ -379,12 +559,22 @@ impl Block {
         Ok(&network.genesis_hash() == self.hash()?)
     }
 
+    /// The signature, or an error if the block hasn't been signed yet.
+    pub fn require_signed(&self) -> crate::Result<&Signature> {
+        self.signature.as_ref().ok_or(Error::MissingSignature)
+    }
+
+    /// The work, or an error if work hasn't been generated for the block yet.
+    pub fn require_work(&self) -> crate::Result<&Work> {
+        self.work.as_ref().ok_or(Error::MissingWork)
+    }
+
     pub fn verify_signature(&self, account: &Public) -> anyhow::Result<()> {
         let hash = self.hash()?;
-        let signature = self.signature().ok_or(anyhow!("Signature missing"))?;
-        Ok(account
+        let signature = self.require_signed()?;
+        account
             .verify(hash.as_bytes(), signature)
-            .context("Verify block")?)
+            .context("Verify block")
     }
 
     pub fn sign(&mut self, private: Private) -> anyhow::Result<()> {
@@ -398,13 +588,28 @@ impl Block {
         &self.balance
     }
 
+    /// What forks of this block have in common.
+    pub fn root(&self) -> Root {
+        Root::new(&self.previous, &self.account)
+    }
+
+    /// What work is generated and validated against: the account for the first block of an
+    /// account, otherwise the previous block. Never the hash of the block itself.
+    pub fn work_root(&self) -> Root {
+        Root::new(&self.previous, &self.account)
+    }
+
     pub fn previous(&self) -> &Previous {
         &self.previous
     }
 
+    pub fn link(&self) -> &Link {
+        &self.link
+    }
+
     /// For an open or recv block, get the sender's block hash, otherwise Err.
     pub fn source(&self) -> anyhow::Result<&BlockHash> {
-        if self.block_type != BlockType::Open {
+        if self.block_type != BlockType::Open && self.block_type != BlockType::Receive {
             return Err(anyhow!(
                 "Source requested for a {:?} block",
                 self.block_type
@@ -412,7 +617,7 @@ impl Block {
         }
 
         if let Link::Source(hash) = &self.link {
-            Ok(&hash)
+            Ok(hash)
         } else {
             Err(anyhow!(
                 "source requested for {:?} but the link is incorrect",
@@ -432,7 +637,7 @@ impl Block {
         }
 
         if let Link::DestinationAccount(account) = &self.link {
-            Ok(&account)
+            Ok(account)
         } else {
             Err(anyhow!(
                 "destination requested for {:?} but the link is incorrect",
@@ -486,6 +691,40 @@ mod tests {
         StateBlock::new(account, Previous::Open, representative, Raw(500), source)
     }
 
+    /// Real blocks from the live network, with work over the threshold from before epoch 2.
+    #[test]
+    fn work_root() {
+        use crate::blocks::{Root, SendBlock};
+        use crate::Difficulty;
+
+        let threshold = Difficulty::new(0xffffffc000000000);
+        let genesis = Network::Live.genesis_block();
+        assert_eq!(genesis.work_root().as_bytes(), genesis.account().as_bytes());
+
+        let send: SendBlock = serde_json::from_str(
+            r#"{
+                "previous": "991CF190094C00F0B68E2E5F75F6BEE95A2E0BD93CEAA4A6734DB9F19B728948",
+                "destination": "nano_13ezf4od79h1tgj9aiu4djzcmmguendtjfuhwfukhuucboua8cpoihmh8byo",
+                "balance": "FD89D89D89D89D89D89D89D89D89D89D",
+                "work": "3c82cc724905ee95"
+            }"#,
+        )
+        .unwrap();
+        let send = Block::from_send_block(&send, genesis.account(), genesis.representative());
+        assert_eq!(
+            send.work_root().as_bytes(),
+            genesis.hash().unwrap().as_bytes()
+        );
+
+        for block in &[&genesis, &send] {
+            let work = block.work().unwrap();
+            assert!(work.verify(&block.work_root(), &threshold).unwrap());
+            // The block's own hash is never the root.
+            let own_hash = Root::from(block.hash().unwrap());
+            assert!(!work.verify(&own_hash, &threshold).unwrap());
+        }
+    }
+
     #[test]
     fn round_trip_state_block() {
         let state_block_0 = test_state_block();
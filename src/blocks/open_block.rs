@@ -1,23 +1,42 @@
-use crate::blocks::BlockHash;
+#[cfg(feature = "node")]
+use crate::node::{Header, Wire};
+
+#[cfg(feature = "node")]
+use bytes::BytesMut;
+
+#[cfg(feature = "node")]
+use crate::bytes::Bytes;
+
+#[cfg(feature = "node")]
+use std::convert::TryFrom;
+
+use crate::blocks::{hash_block, BlockHash};
 use crate::keys::public::{from_address, to_address};
 use crate::{Public, Signature, Work};
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct OpenBlock {
     pub source: BlockHash,
 
     #[serde(serialize_with = "to_address", deserialize_with = "from_address")]
+    #[cfg_attr(feature = "schema", schemars(with = "crate::Address"))]
     pub representative: Public,
 
     #[serde(serialize_with = "to_address", deserialize_with = "from_address")]
+    #[cfg_attr(feature = "schema", schemars(with = "crate::Address"))]
     pub account: Public,
 
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub work: Option<Work>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub signature: Option<Signature>,
 }
 
 impl OpenBlock {
+    pub const LEN: usize = 168;
+
     pub fn new(source: BlockHash, representative: Public, account: Public) -> Self {
         Self {
             source,
@@ -27,4 +46,49 @@ impl OpenBlock {
             signature: None,
         }
     }
+
+    pub fn hash(&self) -> BlockHash {
+        hash_block(&[
+            self.source.as_bytes(),
+            self.representative.as_bytes(),
+            self.account.as_bytes(),
+        ])
+    }
+}
+
+#[cfg(feature = "node")]
+impl Wire for OpenBlock {
+    fn serialize_into(&self, buf: &mut BytesMut) {
+        buf.extend_from_slice(self.source.as_bytes());
+        buf.extend_from_slice(self.representative.as_bytes());
+        buf.extend_from_slice(self.account.as_bytes());
+        super::serialize_legacy_tail(buf, &self.signature, &self.work);
+    }
+
+    fn deserialize(_: Option<&Header>, data: &[u8]) -> anyhow::Result<Self>
+    where
+        Self: Sized,
+    {
+        let mut data = Bytes::new(data);
+        let source = BlockHash::try_from(data.slice(BlockHash::LEN)?)?;
+        let representative = Public::try_from(data.slice(Public::LEN)?)?;
+        let account = Public::try_from(data.slice(Public::LEN)?)?;
+        let signature = Some(Signature::try_from(data.slice(Signature::LEN)?)?);
+        let work = Some(Work::from_le_bytes(data.slice(Work::LEN)?)?);
+
+        Ok(Self {
+            source,
+            representative,
+            account,
+            work,
+            signature,
+        })
+    }
+
+    fn len(_: Option<&Header>) -> anyhow::Result<usize>
+    where
+        Self: Sized,
+    {
+        Ok(OpenBlock::LEN)
+    }
 }
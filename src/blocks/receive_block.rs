@@ -1,11 +1,81 @@
-use crate::blocks::BlockHash;
-use crate::{Public, Signature, Work};
+#[cfg(feature = "node")]
+use crate::node::{Header, Wire};
+
+#[cfg(feature = "node")]
+use bytes::BytesMut;
+
+#[cfg(feature = "node")]
+use crate::bytes::Bytes;
+
+#[cfg(feature = "node")]
+use std::convert::TryFrom;
+
+use crate::blocks::{hash_block, BlockHash};
+use crate::{Signature, Work};
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct ReceiveBlock {
-    previous: BlockHash,
-    source: Public,
+    /// The hash of the previous block in this account.
+    pub previous: BlockHash,
+
+    /// The hash of the send block being received.
+    pub source: BlockHash,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub work: Option<Work>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub signature: Option<Signature>,
 }
+
+impl ReceiveBlock {
+    pub const LEN: usize = 136;
+
+    pub fn new(previous: BlockHash, source: BlockHash) -> Self {
+        Self {
+            previous,
+            source,
+            work: None,
+            signature: None,
+        }
+    }
+
+    pub fn hash(&self) -> BlockHash {
+        hash_block(&[self.previous.as_bytes(), self.source.as_bytes()])
+    }
+}
+
+#[cfg(feature = "node")]
+impl Wire for ReceiveBlock {
+    fn serialize_into(&self, buf: &mut BytesMut) {
+        buf.extend_from_slice(self.previous.as_bytes());
+        buf.extend_from_slice(self.source.as_bytes());
+        super::serialize_legacy_tail(buf, &self.signature, &self.work);
+    }
+
+    fn deserialize(_: Option<&Header>, data: &[u8]) -> anyhow::Result<Self>
+    where
+        Self: Sized,
+    {
+        let mut data = Bytes::new(data);
+        let previous = BlockHash::try_from(data.slice(BlockHash::LEN)?)?;
+        let source = BlockHash::try_from(data.slice(BlockHash::LEN)?)?;
+        let signature = Some(Signature::try_from(data.slice(Signature::LEN)?)?);
+        let work = Some(Work::from_le_bytes(data.slice(Work::LEN)?)?);
+
+        Ok(Self {
+            previous,
+            source,
+            work,
+            signature,
+        })
+    }
+
+    fn len(_: Option<&Header>) -> anyhow::Result<usize>
+    where
+        Self: Sized,
+    {
+        Ok(ReceiveBlock::LEN)
+    }
+}
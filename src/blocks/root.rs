@@ -0,0 +1,77 @@
+use crate::blocks::{BlockHash, Previous};
+use crate::{hexify, Public};
+
+/// What a block builds on: its previous block, or its account for the first block.
+///
+/// Work is generated against the root, and blocks competing for the same root are forks of
+/// each other, so the root is what elections and confirm_reqs are keyed on.
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub struct Root([u8; Root::LEN]);
+
+hexify!(Root, "root");
+
+impl Root {
+    pub const LEN: usize = 32;
+
+    pub fn new(previous: &Previous, account: &Public) -> Self {
+        match previous {
+            Previous::Block(hash) => hash.into(),
+            Previous::Open => account.into(),
+        }
+    }
+}
+
+impl From<&BlockHash> for Root {
+    fn from(hash: &BlockHash) -> Self {
+        let mut root = [0u8; Root::LEN];
+        root.copy_from_slice(hash.as_bytes());
+        Self(root)
+    }
+}
+
+impl From<BlockHash> for Root {
+    fn from(hash: BlockHash) -> Self {
+        Self::from(&hash)
+    }
+}
+
+impl From<&Public> for Root {
+    fn from(public: &Public) -> Self {
+        let mut root = [0u8; Root::LEN];
+        root.copy_from_slice(public.as_bytes());
+        Self(root)
+    }
+}
+
+impl From<Public> for Root {
+    fn from(public: Public) -> Self {
+        Self::from(&public)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{HexBytes, Seed};
+
+    #[test]
+    fn from_previous() {
+        let account = Seed::zero().derive(0).to_public().unwrap();
+        let hash = BlockHash::zero();
+        assert_eq!(
+            Root::new(&Previous::Block(hash.clone()), &account).as_bytes(),
+            hash.as_bytes()
+        );
+        assert_eq!(
+            Root::new(&Previous::Open, &account).as_bytes(),
+            account.as_bytes()
+        );
+
+        let json = serde_json::to_string(&Root::from(&account)).unwrap();
+        assert_eq!(json, format!("\"{}\"", account.to_hex()));
+        assert_eq!(
+            serde_json::from_str::<Root>(&json).unwrap(),
+            Root::from(account)
+        );
+    }
+}
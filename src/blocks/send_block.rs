@@ -1,6 +1,9 @@
 #[cfg(feature = "node")]
 use crate::node::Header;
 
+#[cfg(feature = "node")]
+use bytes::BytesMut;
+
 #[cfg(feature = "node")]
 use crate::node::Wire;
 
@@ -13,27 +16,32 @@ use crate::bytes::Bytes;
 #[cfg(feature = "node")]
 use std::convert::TryFrom;
 
-use crate::blocks::BlockHash;
+use crate::blocks::{hash_block, BlockHash};
 use crate::keys::public::{from_address, to_address};
 use crate::units::raw::{deserialize_from_hex, serialize_to_hex};
 use crate::{Public, Raw, Signature, Work};
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct SendBlock {
     /// The hash of the previous block in this account.
     pub previous: BlockHash,
 
     #[serde(serialize_with = "to_address", deserialize_with = "from_address")]
+    #[cfg_attr(feature = "schema", schemars(with = "crate::Address"))]
     pub destination: Public,
 
     #[serde(
         serialize_with = "serialize_to_hex",
         deserialize_with = "deserialize_from_hex"
     )]
+    #[cfg_attr(feature = "schema", schemars(with = "String"))]
     pub balance: Raw,
 
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub work: Option<Work>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub signature: Option<Signature>,
 }
 
@@ -49,12 +57,23 @@ impl SendBlock {
             signature: None,
         }
     }
+
+    pub fn hash(&self) -> BlockHash {
+        hash_block(&[
+            self.previous.as_bytes(),
+            self.destination.as_bytes(),
+            &self.balance.to_vec(),
+        ])
+    }
 }
 
 #[cfg(feature = "node")]
 impl Wire for SendBlock {
-    fn serialize(&self) -> Vec<u8> {
-        unimplemented!()
+    fn serialize_into(&self, buf: &mut BytesMut) {
+        buf.extend_from_slice(self.previous.as_bytes());
+        buf.extend_from_slice(self.destination.as_bytes());
+        buf.extend_from_slice(&self.balance.to_vec());
+        super::serialize_legacy_tail(buf, &self.signature, &self.work);
     }
 
     fn deserialize(_: Option<&Header>, data: &[u8]) -> anyhow::Result<Self>
@@ -65,8 +84,9 @@ impl Wire for SendBlock {
         let previous = BlockHash::try_from(data.slice(BlockHash::LEN)?)?;
         let destination = Public::try_from(data.slice(Public::LEN)?)?;
         let balance = Raw::try_from(data.slice(Raw::LEN)?)?;
-        let work = Some(Work::try_from(data.slice(Work::LEN)?)?);
         let signature = Some(Signature::try_from(data.slice(Signature::LEN)?)?);
+        // Legacy blocks have little-endian work, unlike state blocks.
+        let work = Some(Work::from_le_bytes(data.slice(Work::LEN)?)?);
 
         Ok(Self {
             previous,
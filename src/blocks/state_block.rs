@@ -1,17 +1,18 @@
 #[cfg(feature = "node")]
 use crate::node::Header;
 
+#[cfg(feature = "node")]
+use bytes::BytesMut;
+
 #[cfg(feature = "node")]
 use crate::node::Wire;
 
 #[cfg(feature = "node")]
 use crate::bytes::Bytes;
 
-use crate::blocks::{hash_block, Block, BlockHash, BlockType, Previous};
-use crate::encoding::expect_len;
+use crate::blocks::{hash_block, Block, BlockHash, BlockType, Epoch, Previous, Root};
 use crate::keys::public::{from_address, to_address};
-use crate::{hexify, Error, Public, Raw, Result, Signature, Work};
-use anyhow::anyhow;
+use crate::{hexify, Error, HexBytes, Public, Raw, Result, Signature, Work};
 use anyhow::Context;
 use serde::{Deserialize, Deserializer, Serialize};
 use std::convert::TryFrom;
@@ -29,8 +30,9 @@ where
     Ok(Link::Unsure(unsure))
 }
 
-/// Not used within StateBlock yet.
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, EnumString)]
+/// What a state block does to its account. See [StateBlock::subtype].
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, EnumString)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[serde(rename_all = "snake_case")]
 #[strum(serialize_all = "snake_case")]
 pub enum Subtype {
@@ -42,13 +44,16 @@ pub enum Subtype {
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct StateBlock {
     #[serde(serialize_with = "to_address", deserialize_with = "from_address")]
+    #[cfg_attr(feature = "schema", schemars(with = "crate::Address"))]
     pub account: Public,
 
     pub previous: Previous,
 
     #[serde(serialize_with = "to_address", deserialize_with = "from_address")]
+    #[cfg_attr(feature = "schema", schemars(with = "crate::Address"))]
     pub representative: Public,
 
     pub balance: Raw,
@@ -56,14 +61,13 @@ pub struct StateBlock {
     #[serde(deserialize_with = "deserialize_to_unsure_link")]
     pub link: Link,
 
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub work: Option<Work>,
 
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub signature: Option<Signature>,
 
     pub hash: BlockHash,
-
-    #[serde(skip_serializing, skip_deserializing)] // only exists during processing
-    amount: Option<Amount>,
 }
 
 impl StateBlock {
@@ -96,61 +100,79 @@ impl StateBlock {
             work: None,
             signature: None,
             hash: block_hash,
-            amount: None,
         }
     }
 
-    pub fn set_link_type(&mut self, is_send: bool, amount: Raw) -> anyhow::Result<()> {
-        match &self.link {
-            Link::Nothing => {
-                tracing::trace!("set_link_type likely called twice by mistake.");
-                Ok(())
-            }
-            Link::Source(_) => {
-                tracing::trace!("set_link_type likely called twice by mistake.");
-                Ok(())
-            }
-            Link::DestinationAccount(_) => {
-                tracing::trace!("set_link_type likely called twice by mistake.");
-                Ok(())
-            }
-            Link::Unsure(unsure_link) => {
-                if is_send {
-                    self.link = Link::DestinationAccount(Public::try_from(unsure_link.as_bytes())?);
-                    self.amount = Some(Amount(amount))
-                } else {
-                    let is_receive = !is_send && !unsure_link.is_all_zeros();
-                    let is_change = !is_send && unsure_link.is_all_zeros();
-
-                    if is_receive {
-                        self.link = Link::Source(BlockHash::try_from(unsure_link.as_bytes())?);
-                        self.amount = Some(Amount(amount))
-                    } else if is_change {
-                        debug_assert_eq!(amount, Raw(0));
-                        self.link = Link::Nothing
-                    }
-                }
-                Ok(())
-            }
+    /// What the block does to its account, from the block before it. `previous` is `None` when
+    /// the block opens the account, which had a balance of zero until then.
+    ///
+    /// A balance that goes down is a send, even with an epoch link. A balance that stays the
+    /// same is a change when the link is zero, and an epoch upgrade when the link is an epoch's.
+    pub fn subtype(&self, previous: Option<&StateBlock>) -> Subtype {
+        let before = previous
+            .map(|p| p.balance.to_owned())
+            .unwrap_or_else(Raw::zero);
+        if self.balance < before {
+            return Subtype::Send;
+        }
+        if self.balance == before && Epoch::from_link(&self.link).is_some() {
+            return Subtype::Epoch;
+        }
+        if previous.is_none() {
+            return Subtype::Open;
         }
+        if self.balance == before && self.link.is_all_zeros() {
+            return Subtype::Change;
+        }
+        Subtype::Receive
+    }
+
+    /// The signature, or an error if the block hasn't been signed yet.
+    pub fn require_signed(&self) -> Result<&Signature> {
+        self.signature.as_ref().ok_or(Error::MissingSignature)
+    }
+
+    /// What forks of this block have in common.
+    pub fn root(&self) -> Root {
+        Root::new(&self.previous, &self.account)
+    }
+
+    /// What work is generated and validated against: the account for the first block of an
+    /// account, otherwise the previous block. Never the hash of the block itself.
+    pub fn work_root(&self) -> Root {
+        Root::new(&self.previous, &self.account)
+    }
+
+    /// The work, or an error if work hasn't been generated for the block yet.
+    pub fn require_work(&self) -> Result<&Work> {
+        self.work.as_ref().ok_or(Error::MissingWork)
     }
 
     pub fn verify_self_signature(&self) -> anyhow::Result<()> {
-        let signature = self
-            .signature
-            .as_ref()
-            .ok_or(anyhow!("Signature missing"))?;
-        Ok(self
-            .account
+        let signature = self.require_signed()?;
+        self.account
             .verify(self.hash.as_bytes(), signature)
-            .context("Verify block")?)
+            .context("Verify block")
     }
 }
 
 #[cfg(feature = "node")]
 impl Wire for StateBlock {
-    fn serialize(&self) -> Vec<u8> {
-        unimplemented!()
+    /// A block without a signature or work has zeros in their place.
+    fn serialize_into(&self, buf: &mut BytesMut) {
+        buf.extend_from_slice(self.account.as_bytes());
+        buf.extend_from_slice(&self.previous.to_bytes());
+        buf.extend_from_slice(self.representative.as_bytes());
+        buf.extend_from_slice(&self.balance.to_vec());
+        buf.extend_from_slice(self.link.as_bytes());
+        match &self.signature {
+            Some(signature) => buf.extend_from_slice(signature.as_bytes()),
+            None => buf.extend_from_slice(&[0u8; Signature::LEN]),
+        }
+        match &self.work {
+            Some(work) => buf.extend_from_slice(work.as_bytes()),
+            None => buf.extend_from_slice(&[0u8; Work::LEN]),
+        }
     }
 
     fn deserialize(_header: Option<&Header>, data: &[u8]) -> anyhow::Result<Self>
@@ -189,13 +211,16 @@ impl Wire for StateBlock {
 
 impl From<Block> for StateBlock {
     fn from(block: Block) -> Self {
-        StateBlock::new(
+        let mut state_block = StateBlock::new(
             block.account,
             block.previous,
             block.representative,
             block.balance,
             block.link,
-        )
+        );
+        state_block.work = block.work;
+        state_block.signature = block.signature;
+        state_block
     }
 }
 
@@ -221,9 +246,9 @@ impl Display for StateBlock {
 mod tests {
     use super::Raw;
     use super::StateBlock;
-    use crate::blocks::state_block::{Amount, Link, UnsureLink};
-    use crate::blocks::{Block, BlockHash, Previous};
-    use crate::{Address, Public, Signature, Work};
+    use crate::blocks::state_block::{Link, Subtype, UnsureLink};
+    use crate::blocks::{Block, BlockHash, Epoch, Previous};
+    use crate::{Address, Error, Public, Signature, Work};
     use std::str::FromStr;
 
     fn account_0() -> Public {
@@ -274,73 +299,114 @@ mod tests {
     }
 
     #[test]
-    fn set_destination_link() {
-        let unsure_link = Link::Unsure(
-            UnsureLink::from_str(
-                "6B523BCB57B0997C808D89BA30F78BF5E4E7DAE880BFDC4179B537F0D8ED726E",
-            )
-            .unwrap(),
-        );
-        let destination_account =
-            Public::from_str("6B523BCB57B0997C808D89BA30F78BF5E4E7DAE880BFDC4179B537F0D8ED726E")
-                .unwrap();
-        let mut state_block = StateBlock::new(
-            account_0(),
-            parent_0(),
-            representative_0(),
-            balance_0(),
-            unsure_link,
-        );
-
-        state_block.set_link_type(true, Raw(200)).unwrap();
-
-        assert_eq!(state_block.amount, Some(Amount(Raw(200))));
-        assert_eq!(
-            state_block.link,
-            Link::DestinationAccount(destination_account)
-        );
+    fn json_without_work_and_signature() {
+        let json = r#"{
+            "account": "nano_34prihdxwz3u4ps8qjnn14p7ujyewkoxkwyxm3u665it8rg5rdqw84qrypzk",
+            "previous": {"Block": "7837C80964CAD551DEABE162C7FC4BB58688A0C6EB6D9907C0D2A7C74A33C7EB"},
+            "representative": "nano_34prihdxwz3u4ps8qjnn14p7ujyewkoxkwyxm3u665it8rg5rdqw84qrypzk",
+            "balance": "2711469892748129430069222848295",
+            "link": "0399B19B022D260F3DDFBA26D0306D423F1890D3AE06136FAB16802D1F2B87A7",
+            "hash": "6F050D3D0B19C2C206046AAE2D46661B57E1B7D890DE8398D203A025E29A4AD9"
+        }"#;
+        let block: StateBlock = serde_json::from_str(json).unwrap();
+        assert!(block.work.is_none());
+        assert!(block.signature.is_none());
+        assert!(matches!(
+            block.require_signed(),
+            Err(Error::MissingSignature)
+        ));
+        assert!(matches!(block.require_work(), Err(Error::MissingWork)));
+
+        let json = serde_json::to_string(&block).unwrap();
+        assert!(!json.contains("work"));
+        assert!(!json.contains("signature"));
     }
 
+    #[cfg(feature = "node")]
     #[test]
-    fn set_source_link() {
-        let unsure_link = Link::Unsure(
-            UnsureLink::from_str(
-                "6B523BCB57B0997C808D89BA30F78BF5E4E7DAE880BFDC4179B537F0D8ED726E",
-            )
-            .unwrap(),
-        );
-        let source =
-            BlockHash::from_str("6B523BCB57B0997C808D89BA30F78BF5E4E7DAE880BFDC4179B537F0D8ED726E")
-                .unwrap();
-        let mut state_block = StateBlock::new(
+    fn wire_round_trip() {
+        use crate::node::Wire;
+        use crate::HexBytes;
+
+        let mut block = StateBlock::new(
             account_0(),
             parent_0(),
             representative_0(),
             balance_0(),
-            unsure_link,
+            Link::unsure_from_str(
+                "6B523BCB57B0997C808D89BA30F78BF5E4E7DAE880BFDC4179B537F0D8ED726E",
+            )
+            .unwrap(),
         );
-
-        state_block.set_link_type(false, Raw(200)).unwrap();
-
-        assert_eq!(state_block.amount, Some(Amount(Raw(200))));
-        assert_eq!(state_block.link, Link::Source(source));
+        block.work = Some(Work::from_hex("8a142e07a10996d5").unwrap());
+        block.signature = Some(Signature::zero());
+        let data = block.serialize();
+        assert_eq!(data.len(), StateBlock::LEN);
+
+        let decoded = <StateBlock as Wire>::deserialize(None, &data).unwrap();
+        assert_eq!(decoded.hash, block.hash);
+        assert_eq!(decoded.work, block.work);
     }
 
     #[test]
-    fn set_nothing_link() {
-        let unsure_link = Link::Unsure(UnsureLink([0u8; 32]));
-        let mut state_block = StateBlock::new(
-            account_0(),
-            parent_0(),
-            representative_0(),
-            balance_0(),
-            unsure_link,
-        );
+    fn subtypes() {
+        let link = "6B523BCB57B0997C808D89BA30F78BF5E4E7DAE880BFDC4179B537F0D8ED726E";
+        let block = |balance: u128, link: Link| {
+            StateBlock::new(
+                account_0(),
+                parent_0(),
+                representative_0(),
+                Raw(balance),
+                link,
+            )
+        };
+        let unsure = || Link::unsure_from_str(link).unwrap();
+        let zero = || Link::Unsure(UnsureLink([0u8; 32]));
+        let previous = block(200, zero());
+
+        let cases = vec![
+            (block(100, unsure()), Some(&previous), Subtype::Send),
+            (block(300, unsure()), Some(&previous), Subtype::Receive),
+            (block(200, zero()), Some(&previous), Subtype::Change),
+            (
+                block(200, Epoch::V2.link()),
+                Some(&previous),
+                Subtype::Epoch,
+            ),
+            // Sending with an epoch's link is still a send.
+            (block(100, Epoch::V1.link()), Some(&previous), Subtype::Send),
+            // Receiving nothing isn't a change when there's a source.
+            (block(200, unsure()), Some(&previous), Subtype::Receive),
+            (block(300, unsure()), None, Subtype::Open),
+            (block(0, Epoch::V2.link()), None, Subtype::Epoch),
+        ];
+        for (block, previous, expected) in cases {
+            assert_eq!(block.subtype(previous), expected, "{}", block);
+        }
+    }
 
-        state_block.set_link_type(false, Raw(0)).unwrap();
+    #[test]
+    fn links_for_subtypes() {
+        let hex = "6B523BCB57B0997C808D89BA30F78BF5E4E7DAE880BFDC4179B537F0D8ED726E";
+        let unsure = Link::unsure_from_str(hex).unwrap();
 
-        assert_eq!(state_block.amount, None);
-        assert_eq!(state_block.link, Link::Nothing);
+        assert_eq!(
+            unsure.for_subtype(Subtype::Send).unwrap(),
+            Link::DestinationAccount(Public::from_str(hex).unwrap())
+        );
+        let source = Link::Source(BlockHash::from_str(hex).unwrap());
+        assert_eq!(unsure.for_subtype(Subtype::Receive).unwrap(), source);
+        assert_eq!(unsure.for_subtype(Subtype::Open).unwrap(), source);
+        assert_eq!(
+            Link::Unsure(UnsureLink([0u8; 32]))
+                .for_subtype(Subtype::Change)
+                .unwrap(),
+            Link::Nothing
+        );
+        assert_eq!(
+            Epoch::V1.link().for_subtype(Subtype::Epoch).unwrap(),
+            Epoch::V1.link()
+        );
     }
 }
 
@@ -352,17 +418,18 @@ hexify!(UnsureLink, "link");
 impl UnsureLink {
     pub(crate) const LEN: usize = Link::LEN;
 
+    pub(crate) fn new(bytes: [u8; Self::LEN]) -> Self {
+        Self(bytes)
+    }
+
     pub fn is_all_zeros(&self) -> bool {
         self.0.iter().all(|&b| b == 0)
     }
 }
 
-/// Represent nanos transferred between account in send and receive sub-blocks
-#[derive(Debug, Clone, Eq, PartialEq)]
-pub struct Amount(Raw);
-
 /// Used in state block as a reference to either the previous block or a destination address.
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[serde(rename_all = "snake_case", untagged)]
 pub enum Link {
     /// For the change block type.
@@ -386,13 +453,7 @@ impl Link {
     }
 
     pub fn unsure_from_str(s: &str) -> Result<Self> {
-        expect_len(s.len(), Self::LEN * 2, "Link")?;
-        let mut slice = [0u8; Self::LEN];
-        hex::decode_to_slice(s, &mut slice).map_err(|source| Error::FromHexError {
-            source,
-            msg: "Decoding link hex".into(),
-        })?;
-        Ok(Link::Unsure(UnsureLink(slice)))
+        Ok(Link::Unsure(UnsureLink::from_hex(s)?))
     }
 
     pub fn as_bytes(&self) -> &[u8] {
@@ -403,6 +464,21 @@ impl Link {
             Link::Unsure(b) => b.as_bytes(),
         }
     }
+
+    pub fn is_all_zeros(&self) -> bool {
+        self.as_bytes().iter().all(|&b| b == 0)
+    }
+
+    /// The link as what it is for a block of `subtype`: the destination of a send, the source of
+    /// a receive or open, and nothing for a change. An epoch's link stays as it is.
+    pub fn for_subtype(&self, subtype: Subtype) -> Result<Link> {
+        Ok(match subtype {
+            Subtype::Send => Link::DestinationAccount(Public::try_from(self.as_bytes())?),
+            Subtype::Receive | Subtype::Open => Link::Source(BlockHash::try_from(self.as_bytes())?),
+            Subtype::Change => Link::Nothing,
+            Subtype::Epoch => self.to_owned(),
+        })
+    }
 }
 
 impl FromStr for Link {
@@ -0,0 +1,219 @@
+//! Blocks for the common flows, worked out from the account's frontier, so the balance and link
+//! math isn't repeated by everyone putting blocks together.
+//!
+//! Each one returns a [StateBlockBuilder], to add work and sign the same way as a block built by
+//! hand:
+//! ```
+//! use feeless::blocks::{BlockHash, StateBlock};
+//! use feeless::{Raw, Seed};
+//!
+//! # fn main() -> anyhow::Result<()> {
+//! let private = Seed::zero().derive(0);
+//! let account = private.to_public()?;
+//! let destination = Seed::zero().derive(1).to_public()?;
+//! let source = BlockHash::zero();
+//!
+//! let open = StateBlock::open(&account, &account, &source, &Raw::from(10)).sign(&private)?;
+//! let send = StateBlock::send(&open, &destination, &Raw::from(3))?.sign(&private)?;
+//! assert_eq!(send.balance, Raw::from(7));
+//! # Ok(())
+//! # }
+//! ```
+use crate::blocks::state_block::UnsureLink;
+use crate::blocks::{BlockHash, Link, Previous, StateBlock, StateBlockBuilder};
+use crate::{Public, Raw};
+use anyhow::anyhow;
+
+/// The epoch upgrades, which change the version of an account without changing its balance or
+/// representative.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Epoch {
+    V1,
+    V2,
+}
+
+impl Epoch {
+    /// The link of an epoch block, which is the name of the epoch padded with zeros.
+    pub fn link(&self) -> Link {
+        let name: &[u8] = match self {
+            Epoch::V1 => b"epoch v1 block",
+            Epoch::V2 => b"epoch v2 block",
+        };
+        let mut bytes = [0u8; Link::LEN];
+        bytes[..name.len()].copy_from_slice(name);
+        Link::Unsure(UnsureLink::new(bytes))
+    }
+
+    /// The epoch whose link this is, if any.
+    pub fn from_link(link: &Link) -> Option<Self> {
+        [Epoch::V1, Epoch::V2]
+            .iter()
+            .find(|epoch| epoch.link().as_bytes() == link.as_bytes())
+            .copied()
+    }
+}
+
+impl StateBlock {
+    /// Send `amount` from the account of `previous` to `destination`.
+    pub fn send(
+        previous: &StateBlock,
+        destination: &Public,
+        amount: &Raw,
+    ) -> anyhow::Result<StateBlockBuilder> {
+        let balance = previous.balance.checked_sub(amount).ok_or_else(|| {
+            anyhow!(
+                "Sending {} raw is more than the balance of {} raw",
+                amount,
+                previous.balance
+            )
+        })?;
+        Ok(Self::after(previous)
+            .balance(balance)
+            .link(Link::DestinationAccount(destination.to_owned())))
+    }
+
+    /// Receive `amount` from the send block `source` into the account of `previous`.
+    pub fn receive(
+        previous: &StateBlock,
+        source: &BlockHash,
+        amount: &Raw,
+    ) -> anyhow::Result<StateBlockBuilder> {
+        let balance = previous
+            .balance
+            .checked_add(amount)
+            .ok_or_else(|| anyhow!("Receiving {} raw would overflow the balance", amount))?;
+        Ok(Self::after(previous)
+            .balance(balance)
+            .link(Link::Source(source.to_owned())))
+    }
+
+    /// The first block of `account`, receiving `amount` from the send block `source`.
+    pub fn open(
+        account: &Public,
+        representative: &Public,
+        source: &BlockHash,
+        amount: &Raw,
+    ) -> StateBlockBuilder {
+        StateBlockBuilder::new(account.to_owned())
+            .representative(representative.to_owned())
+            .balance(amount.to_owned())
+            .link(Link::Source(source.to_owned()))
+    }
+
+    /// Change the representative of the account of `previous`.
+    pub fn change(previous: &StateBlock, representative: &Public) -> StateBlockBuilder {
+        Self::after(previous)
+            .representative(representative.to_owned())
+            .link(Link::Nothing)
+    }
+
+    /// Upgrade the account of `previous` to `epoch`. Epoch blocks are signed by the epoch signer
+    /// of the network instead of the account, so sign the hash of [StateBlockBuilder::build]
+    /// with that key.
+    pub fn epoch(previous: &StateBlock, epoch: Epoch) -> StateBlockBuilder {
+        Self::after(previous).link(epoch.link())
+    }
+
+    /// The same account, balance and representative, following `previous`.
+    fn after(previous: &StateBlock) -> StateBlockBuilder {
+        StateBlockBuilder::new(previous.account.to_owned())
+            .previous(Previous::Block(previous.hash.to_owned()))
+            .representative(previous.representative.to_owned())
+            .balance(previous.balance.to_owned())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Private, Seed};
+
+    fn key(index: u32) -> (Private, Public) {
+        let private = Seed::zero().derive(index);
+        let public = private.to_public().unwrap();
+        (private, public)
+    }
+
+    fn opened(amount: u128) -> (Private, StateBlock) {
+        let (private, account) = key(0);
+        let open = StateBlock::open(&account, &account, &BlockHash::zero(), &Raw::from(amount))
+            .sign(&private)
+            .unwrap();
+        (private, open)
+    }
+
+    #[test]
+    fn open() {
+        let (_, open) = opened(10);
+        assert_eq!(open.previous, Previous::Open);
+        assert_eq!(open.balance, Raw::from(10));
+        assert_eq!(open.link, Link::Source(BlockHash::zero()));
+        open.verify_self_signature().unwrap();
+    }
+
+    #[test]
+    fn send() {
+        let (private, open) = opened(10);
+        let (_, destination) = key(1);
+        let send = StateBlock::send(&open, &destination, &Raw::from(3))
+            .unwrap()
+            .sign(&private)
+            .unwrap();
+        assert_eq!(send.previous, Previous::Block(open.hash.to_owned()));
+        assert_eq!(send.representative, open.representative);
+        assert_eq!(send.balance, Raw::from(7));
+        assert_eq!(send.link, Link::DestinationAccount(destination.to_owned()));
+        assert_eq!(
+            send.hash,
+            StateBlock::new(
+                open.account.to_owned(),
+                Previous::Block(open.hash.to_owned()),
+                open.representative.to_owned(),
+                Raw::from(7),
+                Link::DestinationAccount(destination.to_owned())
+            )
+            .hash
+        );
+
+        assert!(StateBlock::send(&open, &destination, &Raw::from(11)).is_err());
+        let everything = StateBlock::send(&open, &destination, &Raw::from(10)).unwrap();
+        assert_eq!(everything.build().unwrap().balance, Raw::zero());
+    }
+
+    #[test]
+    fn receive() {
+        let (_, open) = opened(10);
+        let source = BlockHash::zero();
+        let receive = StateBlock::receive(&open, &source, &Raw::from(5))
+            .unwrap()
+            .build()
+            .unwrap();
+        assert_eq!(receive.balance, Raw::from(15));
+        assert_eq!(receive.link, Link::Source(source.to_owned()));
+
+        let (_, full) = opened(u128::MAX);
+        assert!(StateBlock::receive(&full, &source, &Raw::from(1)).is_err());
+    }
+
+    #[test]
+    fn change() {
+        let (_, open) = opened(10);
+        let (_, representative) = key(2);
+        let change = StateBlock::change(&open, &representative).build().unwrap();
+        assert_eq!(change.representative, representative);
+        assert_eq!(change.balance, open.balance);
+        assert_eq!(change.link, Link::Nothing);
+    }
+
+    #[test]
+    fn epoch() {
+        let (_, open) = opened(10);
+        let epoch = StateBlock::epoch(&open, Epoch::V2).build().unwrap();
+        assert_eq!(epoch.balance, open.balance);
+        assert_eq!(epoch.representative, open.representative);
+        assert_eq!(
+            hex::encode_upper(epoch.link.as_bytes()),
+            "65706F636820763220626C6F636B000000000000000000000000000000000000"
+        );
+    }
+}
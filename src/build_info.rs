@@ -0,0 +1,134 @@
+//! What this build of feeless is, for bug reports and tooling, see [build_info].
+//!
+//! The git commit and enabled features are embedded by the build script.
+use crate::blocks::BlockHash;
+use crate::{Difficulty, Network};
+use serde::Serialize;
+use std::fmt::{Display, Formatter};
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BuildInfo {
+    /// The crate version, e.g. `0.1.12`.
+    pub version: &'static str,
+
+    /// `None` when built outside of a git checkout, e.g. from crates.io.
+    pub git_commit: Option<&'static str>,
+
+    /// The target triple, e.g. `x86_64-unknown-linux-gnu`.
+    pub target: &'static str,
+
+    /// Cargo features, sorted, including ones enabled by other features.
+    pub features: Vec<&'static str>,
+
+    /// `None` without the `node` feature, since nothing talks to peers.
+    pub protocol: Option<ProtocolRange>,
+
+    pub networks: Vec<NetworkDefaults>,
+}
+
+/// The node protocol versions we talk.
+#[derive(Debug, Clone, Serialize)]
+pub struct ProtocolRange {
+    /// Peers using a lower version are rejected.
+    pub min: u8,
+
+    /// The newest version, sent in our headers.
+    pub max: u8,
+}
+
+/// What a node uses for a [Network] unless configured otherwise.
+#[derive(Debug, Clone, Serialize)]
+pub struct NetworkDefaults {
+    pub network: String,
+    pub port: u16,
+    pub peering_host: String,
+    pub genesis_hash: BlockHash,
+    pub send_difficulty: Difficulty,
+    pub receive_difficulty: Difficulty,
+}
+
+impl From<Network> for NetworkDefaults {
+    fn from(network: Network) -> Self {
+        Self {
+            network: network.to_string(),
+            port: network.default_port(),
+            peering_host: network.peering_host().to_owned(),
+            genesis_hash: network.genesis_hash(),
+            send_difficulty: network.send_difficulty(),
+            receive_difficulty: network.receive_difficulty(),
+        }
+    }
+}
+
+/// Describe this build.
+pub fn build_info() -> BuildInfo {
+    let commit = env!("FEELESS_GIT_COMMIT");
+    let features = env!("FEELESS_FEATURES");
+
+    #[cfg(feature = "node")]
+    let protocol = Some(ProtocolRange {
+        min: crate::node::ProtocolVersion::MIN.as_u8(),
+        max: crate::node::ProtocolVersion::CURRENT.as_u8(),
+    });
+    #[cfg(not(feature = "node"))]
+    let protocol = None;
+
+    BuildInfo {
+        version: env!("CARGO_PKG_VERSION"),
+        git_commit: Some(commit).filter(|c| !c.is_empty()),
+        target: env!("FEELESS_TARGET"),
+        features: features.split(',').filter(|f| !f.is_empty()).collect(),
+        protocol,
+        networks: [Network::Live, Network::Beta, Network::Test]
+            .iter()
+            .map(|network| NetworkDefaults::from(*network))
+            .collect(),
+    }
+}
+
+impl Display for BuildInfo {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "feeless {}", self.version)?;
+        writeln!(f, "Commit: {}", self.git_commit.unwrap_or("unknown"))?;
+        writeln!(f, "Target: {}", self.target)?;
+        writeln!(f, "Features: {}", self.features.join(", "))?;
+        match &self.protocol {
+            Some(protocol) => writeln!(f, "Protocol: {} to {}", protocol.min, protocol.max)?,
+            None => writeln!(f, "Protocol: none")?,
+        }
+        for network in &self.networks {
+            writeln!(
+                f,
+                "Network {}: port {}, peering {}, genesis {}",
+                network.network, network.port, network.peering_host, network.genesis_hash
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn describes_this_build() {
+        let info = build_info();
+        assert_eq!(info.version, env!("CARGO_PKG_VERSION"));
+        assert!(!info.target.is_empty());
+        #[cfg(feature = "node")]
+        {
+            assert!(info.features.contains(&"node"));
+            assert_eq!(info.protocol.as_ref().unwrap().min, 18);
+        }
+        assert_eq!(info.networks[0].network, "live");
+        assert_eq!(info.networks[0].port, crate::DEFAULT_PORT);
+
+        let json = serde_json::to_value(&info).unwrap();
+        assert_eq!(json["version"], info.version);
+        assert_eq!(
+            json["networks"][0]["genesis_hash"],
+            Network::Live.genesis_hash().to_string()
+        );
+    }
+}
@@ -0,0 +1,392 @@
+//! Experimental one-time payment addresses, where each payment goes to a fresh account that only
+//! the sender and the recipient can link to the recipient.
+//!
+//! **This is a playground for privacy experiments on test networks. It hasn't been reviewed,
+//! the protocol may change in incompatible ways, and funds sent with it on the live network can
+//! be lost.** It's only built with the `camo` feature.
+//!
+//! ## Protocol
+//!
+//! Everything is on the ed25519 curve Nano keys use, with `G` its base point and `H` blake2b
+//! with a 512 bit output reduced to a scalar.
+//!
+//! * The recipient has a spend key `a`, the usual scalar of a Nano private key, and a scan key
+//!   `s = H("camo scan" || private)`. They publish the camo address of `A = aG` and `S = sG`,
+//!   which is `camo_` followed by both keys and a checksum, encoded like a Nano address.
+//! * The sender picks an ephemeral key `r` with `R = rG`, and works out the shared secret
+//!   `t = H("camo" || rS || R)`. The payment goes to the one-time account `P = A + tG`.
+//! * The send block has `R` as its representative, so the recipient can find it. Setting the
+//!   representative this way means the sender's balance is delegated to a key nobody votes
+//!   with, which is another reason to stay on test networks.
+//! * The recipient scans send blocks for ones whose destination is `A + H("camo" || sR || R)G`,
+//!   using `R` from the representative. Only the scan key is needed to find payments.
+//! * The private scalar of the one-time account is `a + t`, which needs the spend key. It isn't
+//!   a Nano private key, as those are hashed into their scalar, so it's only used through
+//!   [CamoAccount::sign].
+//!
+//! ```
+//! use feeless::camo::CamoKeys;
+//! use feeless::blocks::{Link, Previous, StateBlock};
+//! use feeless::{Private, Raw, Seed};
+//!
+//! # fn main() -> anyhow::Result<()> {
+//! let recipient = CamoKeys::from_private(&Seed::zero().derive(0))?;
+//! let camo_address = recipient.address().to_string();
+//!
+//! // The sender only needs the camo address.
+//! let payment = camo_address.parse::<feeless::camo::CamoAddress>()?.pay()?;
+//! let sender = Seed::zero().derive(1);
+//! let send = StateBlock::new(
+//!     sender.to_public()?,
+//!     Previous::Open,
+//!     payment.ephemeral.to_owned(),
+//!     Raw::zero(),
+//!     Link::DestinationAccount(payment.account.to_owned()),
+//! );
+//!
+//! let found = recipient.scan(&[send]);
+//! assert_eq!(found[0].public(), &payment.account);
+//! # Ok(())
+//! # }
+//! ```
+use crate::blocks::StateBlock;
+use crate::{encoding, Address, Error, Private, Public, Signature};
+use bitvec::prelude::*;
+use curve25519_dalek::constants::ED25519_BASEPOINT_TABLE;
+use curve25519_dalek::edwards::{CompressedEdwardsY, EdwardsPoint};
+use curve25519_dalek::scalar::Scalar;
+use ed25519_dalek::ed25519::signature::Signature as InternalSignature;
+use ed25519_dalek::ExpandedSecretKey;
+use std::convert::TryFrom;
+use std::fmt::{Debug, Display, Formatter};
+use std::iter::FromIterator;
+use std::str::FromStr;
+
+const PREFIX: &str = "camo_";
+
+/// Padding in front of the two keys, to make a whole number of base 32 characters.
+const PADDED_BITS: usize = 3;
+
+/// Length of the encoded keys, `(3 + 512) / 5`.
+const ENCODED_KEYS_LEN: usize = 103;
+
+const CHECKSUM_LEN: usize = 5;
+
+/// What a recipient publishes to be paid, e.g. `camo_1...`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CamoAddress {
+    spend: Public,
+    scan: Public,
+}
+
+/// Where a sender sends a payment to a [CamoAddress].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CamoPayment {
+    /// The representative of the send block.
+    pub ephemeral: Public,
+
+    /// The destination of the send block.
+    pub account: Public,
+}
+
+/// The keys of a recipient, for finding and spending payments.
+#[derive(Clone)]
+pub struct CamoKeys {
+    spend: Scalar,
+    scan: Scalar,
+    address: CamoAddress,
+}
+
+/// A one-time account that was paid, with the scalar that signs for it.
+#[derive(Clone)]
+pub struct CamoAccount {
+    scalar: Scalar,
+    public: Public,
+    ephemeral: Public,
+}
+
+impl CamoAddress {
+    pub fn new(spend: Public, scan: Public) -> Self {
+        Self { spend, scan }
+    }
+
+    pub fn spend(&self) -> &Public {
+        &self.spend
+    }
+
+    pub fn scan(&self) -> &Public {
+        &self.scan
+    }
+
+    /// A payment to a new one-time account, with a random ephemeral key.
+    pub fn pay(&self) -> Result<CamoPayment, Error> {
+        self.pay_with(&Private::random())
+    }
+
+    /// A payment with the given ephemeral key, which should never be used again.
+    pub fn pay_with(&self, ephemeral: &Private) -> Result<CamoPayment, Error> {
+        let r = scalar_of(ephemeral)?;
+        let ephemeral = ephemeral.to_public()?;
+        let t = shared_scalar(&(r * point(&self.scan)?), &ephemeral);
+        let account = point(&self.spend)? + &t * &ED25519_BASEPOINT_TABLE;
+        Ok(CamoPayment {
+            ephemeral,
+            account: public(&account),
+        })
+    }
+
+    fn checksum(keys: &[u8]) -> String {
+        let hash = encoding::blake2b(CHECKSUM_LEN, keys);
+        encoding::encode_nano_base_32(&BitVec::from_iter(hash.iter().rev()))
+    }
+}
+
+impl Display for CamoAddress {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let keys = [self.spend.as_bytes(), self.scan.as_bytes()].concat();
+        let mut bits: BitVec<Msb0, u8> = bitvec![Msb0, u8; 0; PADDED_BITS];
+        bits.extend_from_raw_slice(&keys);
+        write!(
+            f,
+            "{}{}{}",
+            PREFIX,
+            encoding::encode_nano_base_32(&bits),
+            Self::checksum(&keys)
+        )
+    }
+}
+
+impl FromStr for CamoAddress {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let encoded = s.strip_prefix(PREFIX).ok_or(Error::InvalidAddress)?;
+        if !encoded.is_ascii() || encoded.len() != ENCODED_KEYS_LEN + 8 {
+            return Err(Error::InvalidAddress);
+        }
+        let (keys, checksum) = encoded.split_at(ENCODED_KEYS_LEN);
+        let bits = encoding::decode_nano_base_32(keys)?;
+        // Copied twice so the bits start on a byte again, like in [Address].
+        let bits: &BitVec<Msb0, u8> = &bits[PADDED_BITS..].to_owned();
+        let keys = bits.to_owned().into_vec();
+        if Self::checksum(&keys) != checksum {
+            return Err(Error::InvalidChecksum);
+        }
+        let (spend, scan) = keys.split_at(Public::LEN);
+        Ok(Self::new(Public::try_from(spend)?, Public::try_from(scan)?))
+    }
+}
+
+impl CamoKeys {
+    /// The camo keys of a Nano private key. The spend key is the account of the private key, so
+    /// the camo address shows whose it is, but the one-time accounts don't.
+    pub fn from_private(private: &Private) -> Result<Self, Error> {
+        let spend = scalar_of(private)?;
+        let scan = hash_to_scalar(&[&b"camo scan"[..], private.as_bytes()]);
+        let address = CamoAddress::new(
+            public(&(&spend * &ED25519_BASEPOINT_TABLE)),
+            public(&(&scan * &ED25519_BASEPOINT_TABLE)),
+        );
+        Ok(Self {
+            spend,
+            scan,
+            address,
+        })
+    }
+
+    pub fn address(&self) -> &CamoAddress {
+        &self.address
+    }
+
+    /// The one-time account of the payment with this ephemeral key.
+    pub fn recover(&self, ephemeral: &Public) -> Result<CamoAccount, Error> {
+        let t = self.shared(ephemeral)?;
+        let scalar = self.spend + t;
+        Ok(CamoAccount {
+            public: public(&(&scalar * &ED25519_BASEPOINT_TABLE)),
+            scalar,
+            ephemeral: ephemeral.to_owned(),
+        })
+    }
+
+    /// The one-time accounts paid by any of `blocks`, taking the representative of each block
+    /// as an ephemeral key. Blocks that aren't camo payments to us are skipped.
+    pub fn scan<'a, I>(&self, blocks: I) -> Vec<CamoAccount>
+    where
+        I: IntoIterator<Item = &'a StateBlock>,
+    {
+        let spend = match point(&self.address.spend) {
+            Ok(spend) => spend,
+            Err(_) => return vec![],
+        };
+        blocks
+            .into_iter()
+            .filter(|block| match self.shared(&block.representative) {
+                Ok(t) => {
+                    let account = spend + &t * &ED25519_BASEPOINT_TABLE;
+                    block.link.as_bytes() == public(&account).as_bytes()
+                }
+                Err(_) => false,
+            })
+            .filter_map(|block| self.recover(&block.representative).ok())
+            .collect()
+    }
+
+    fn shared(&self, ephemeral: &Public) -> Result<Scalar, Error> {
+        Ok(shared_scalar(&(self.scan * point(ephemeral)?), ephemeral))
+    }
+}
+
+impl Debug for CamoKeys {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CamoKeys")
+            .field("address", &self.address.to_string())
+            .finish()
+    }
+}
+
+impl CamoAccount {
+    pub fn public(&self) -> &Public {
+        &self.public
+    }
+
+    pub fn address(&self) -> Address {
+        self.public.to_address()
+    }
+
+    /// The ephemeral key of the payment to this account.
+    pub fn ephemeral(&self) -> &Public {
+        &self.ephemeral
+    }
+
+    /// Sign as the one-time account, e.g. the hash of a block spending the payment.
+    pub fn sign(&self, message: &[u8]) -> Result<Signature, Error> {
+        let nonce = encoding::blake2b(32, &[&b"camo nonce"[..], self.scalar.as_bytes()].concat());
+        let expanded = ExpandedSecretKey::from_bytes(
+            &[self.scalar.as_bytes(), &nonce[..]].concat(),
+        )
+        .map_err(|source| Error::SignatureError {
+            msg: String::from("Expanding the camo account scalar"),
+            source,
+        })?;
+        let dalek_public =
+            ed25519_dalek::PublicKey::from_bytes(self.public.as_bytes()).map_err(|source| {
+                Error::SignatureError {
+                    msg: String::from("Converting to PublicKey"),
+                    source,
+                }
+            })?;
+        Signature::try_from(expanded.sign(message, &dalek_public).as_bytes())
+    }
+}
+
+impl Debug for CamoAccount {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CamoAccount")
+            .field("address", &self.address())
+            .field("ephemeral", &self.ephemeral)
+            .finish()
+    }
+}
+
+/// The scalar a Nano private key signs with, which is also the scalar of its public key.
+fn scalar_of(private: &Private) -> Result<Scalar, Error> {
+    let mut bytes = [0u8; 32];
+    bytes.copy_from_slice(&private.to_expanded()?.to_bytes()[..32]);
+    Ok(Scalar::from_bits(bytes).reduce())
+}
+
+fn hash_to_scalar(parts: &[&[u8]]) -> Scalar {
+    let mut wide = [0u8; 64];
+    wide.copy_from_slice(&encoding::blake2b(64, &parts.concat()));
+    Scalar::from_bytes_mod_order_wide(&wide)
+}
+
+fn shared_scalar(shared: &EdwardsPoint, ephemeral: &Public) -> Scalar {
+    hash_to_scalar(&[b"camo", shared.compress().as_bytes(), ephemeral.as_bytes()])
+}
+
+fn point(public: &Public) -> Result<EdwardsPoint, Error> {
+    let mut bytes = [0u8; 32];
+    bytes.copy_from_slice(public.as_bytes());
+    CompressedEdwardsY(bytes)
+        .decompress()
+        .ok_or(Error::BadPublicKey)
+}
+
+fn public(point: &EdwardsPoint) -> Public {
+    Public::try_from(point.compress().as_bytes().as_ref()).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blocks::{Link, Previous};
+    use crate::{Raw, Seed};
+
+    fn send(representative: Public, destination: Public) -> StateBlock {
+        StateBlock::new(
+            Seed::zero().derive(9).to_public().unwrap(),
+            Previous::Open,
+            representative,
+            Raw::zero(),
+            Link::DestinationAccount(destination),
+        )
+    }
+
+    #[test]
+    fn spend_key_is_the_account() {
+        let private = Seed::zero().derive(0);
+        let keys = CamoKeys::from_private(&private).unwrap();
+        assert_eq!(keys.address().spend(), &private.to_public().unwrap());
+    }
+
+    #[test]
+    fn address_round_trip() {
+        let keys = CamoKeys::from_private(&Seed::zero().derive(0)).unwrap();
+        let s = keys.address().to_string();
+        assert!(s.starts_with("camo_"));
+        assert_eq!(s.len(), 5 + ENCODED_KEYS_LEN + 8);
+        assert_eq!(&CamoAddress::from_str(&s).unwrap(), keys.address());
+
+        let mut bad = s.clone();
+        bad.replace_range(10..11, if &s[10..11] == "1" { "3" } else { "1" });
+        assert!(CamoAddress::from_str(&bad).is_err());
+        assert!(CamoAddress::from_str(&s[1..]).is_err());
+        assert!(CamoAddress::from_str(&s[..s.len() - 1]).is_err());
+    }
+
+    #[test]
+    fn pays_and_signs() {
+        let keys = CamoKeys::from_private(&Seed::zero().derive(0)).unwrap();
+        let payment = keys.address().pay().unwrap();
+        assert_ne!(&payment.account, keys.address().spend());
+
+        let account = keys.recover(&payment.ephemeral).unwrap();
+        assert_eq!(account.public(), &payment.account);
+        let signature = account.sign(b"message").unwrap();
+        payment.account.verify(b"message", &signature).unwrap();
+
+        // Each payment goes to a different account.
+        assert_ne!(keys.address().pay().unwrap().account, payment.account);
+    }
+
+    #[test]
+    fn scan() {
+        let keys = CamoKeys::from_private(&Seed::zero().derive(0)).unwrap();
+        let other = CamoKeys::from_private(&Seed::zero().derive(1)).unwrap();
+        let ours = keys.address().pay().unwrap();
+        let theirs = other.address().pay().unwrap();
+
+        let blocks = vec![
+            send(ours.ephemeral.to_owned(), ours.account.to_owned()),
+            send(theirs.ephemeral.to_owned(), theirs.account.to_owned()),
+            send(ours.ephemeral.to_owned(), theirs.account.to_owned()),
+        ];
+        let found = keys.scan(&blocks);
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].public(), &ours.account);
+        assert_eq!(found[0].ephemeral(), &ours.ephemeral);
+        assert_eq!(other.scan(&blocks).len(), 1);
+    }
+}
@@ -25,5 +25,6 @@ pub enum Command {
 
 #[derive(Clap)]
 pub struct Public {
+    #[clap(parse(try_from_str = crate::cli::parse::address_or_stdin))]
     address: StringOrStdin<crate::Address>,
 }
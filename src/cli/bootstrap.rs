@@ -0,0 +1,140 @@
+use crate::cli::ndjson::Ndjson;
+use crate::node::{ArcState, ChainBootstrap, ChainStats, FrontierBootstrap, SledDiskState};
+use crate::{CancellationToken, Network};
+use anyhow::{anyhow, Context};
+use clap::Clap;
+use serde::Serialize;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{info, warn};
+
+/// How often to log the progress of the block pulls.
+const LOG_INTERVAL: Duration = Duration::from_secs(10);
+
+/// The database is opened directly, so it can't be in use by a running node.
+#[derive(Clap)]
+pub(crate) struct BootstrapOpts {
+    /// Comma separated list of IP:PORT pairs to bootstrap from. Defaults to the peering host of
+    /// the network.
+    #[clap(short, long)]
+    peers: Option<Vec<String>>,
+
+    #[clap(short = 'n', long, default_value = "live", env = "FEELESS_NETWORK")]
+    network: Network,
+
+    /// The database to add blocks to. Defaults to the database the node uses for this network.
+    #[clap(long)]
+    db: Option<PathBuf>,
+
+    /// Maximum number of peers to bootstrap from in parallel.
+    #[clap(long, default_value = "4")]
+    max_peers: usize,
+
+    /// Output the progress as lines of JSON instead of logging it.
+    #[clap(long)]
+    ndjson: bool,
+}
+
+/// A line of `--ndjson` output.
+#[derive(Serialize)]
+#[serde(tag = "record", rename_all = "snake_case")]
+enum Record {
+    Frontiers { accounts: usize },
+    Progress(ChainStats),
+    Done(ChainStats),
+}
+
+impl BootstrapOpts {
+    pub async fn handle(&self) -> anyhow::Result<()> {
+        let peers = self.peers().await?;
+        if peers.is_empty() {
+            return Err(anyhow!("No peers to bootstrap from"));
+        }
+        let path = self
+            .db
+            .to_owned()
+            .unwrap_or_else(|| SledDiskState::default_path(self.network));
+        let db = SledDiskState::open(self.network, &path)?;
+        let flush = db.clone();
+        let state: ArcState = Arc::new(db);
+
+        // Stop cleanly on ctrl-c, so the blocks added so far are flushed to the database.
+        let cancel = CancellationToken::new();
+        let on_ctrl_c = cancel.clone();
+        tokio::spawn(async move {
+            if tokio::signal::ctrl_c().await.is_ok() {
+                info!("Stopping the bootstrap");
+                on_ctrl_c.cancel();
+            }
+        });
+
+        info!("Bootstrapping frontiers from {} peers", peers.len());
+        let frontiers = FrontierBootstrap::new(self.network, peers.clone(), peers.len() * 4)
+            .run(&cancel)
+            .await?;
+
+        info!("Pulling the chains of {} accounts", frontiers.len());
+        let out = Arc::new(std::sync::Mutex::new(Ndjson::stdout()));
+        if self.ndjson {
+            let mut out = out.lock().unwrap();
+            out.write(&Record::Frontiers {
+                accounts: frontiers.len(),
+            })?;
+            out.flush()?;
+        }
+        let chains = Arc::new(ChainBootstrap::new(self.network, peers, state, frontiers));
+        let logger = chains.clone();
+        let ndjson = self.ndjson;
+        let progress = out.clone();
+        let log_task = tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(LOG_INTERVAL).await;
+                let stats = logger.stats();
+                if ndjson {
+                    let mut out = progress.lock().unwrap();
+                    if let Err(err) = out
+                        .write(&Record::Progress(stats))
+                        .and_then(|_| out.flush())
+                    {
+                        warn!("Could not write progress: {:#}", err);
+                    }
+                    continue;
+                }
+                info!(
+                    "Bootstrap: {}/{} accounts, {} blocks added",
+                    stats.accounts_done, stats.accounts, stats.blocks_added
+                );
+            }
+        });
+        let result = chains.run(&cancel).await;
+        log_task.abort();
+        flush.flush().await?;
+        let stats = result?;
+        if self.ndjson {
+            let mut out = out.lock().unwrap();
+            out.write(&Record::Done(stats))?;
+            return out.flush();
+        }
+        print!("{}", stats);
+        Ok(())
+    }
+
+    async fn peers(&self) -> anyhow::Result<Vec<SocketAddr>> {
+        let peers: Vec<SocketAddr> = match &self.peers {
+            Some(peers) => peers
+                .iter()
+                .map(|peer| {
+                    peer.parse()
+                        .with_context(|| format!("Could not parse host:port: {}", peer))
+                })
+                .collect::<anyhow::Result<_>>()?,
+            None => tokio::net::lookup_host(self.network.peering_host())
+                .await
+                .context("Error while trying to lookup default peers")?
+                .collect(),
+        };
+        Ok(peers.into_iter().take(self.max_peers).collect())
+    }
+}
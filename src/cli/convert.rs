@@ -0,0 +1,40 @@
+use crate::cli::StringOrStdin;
+use crate::Detected;
+use clap::Clap;
+
+#[derive(Clap)]
+pub struct ConvertOpts {
+    /// An address, 64 hex characters, or a quoted mnemonic phrase. `-` reads it from stdin.
+    input: StringOrStdin<Detected>,
+
+    /// Print the conversions as a JSON object.
+    #[clap(long)]
+    json: bool,
+}
+
+impl ConvertOpts {
+    pub fn handle(&self) -> anyhow::Result<()> {
+        let detected = self.input.to_owned().resolve()?;
+        let representations = detected.representations()?;
+        if self.json {
+            let mut object = serde_json::Map::new();
+            object.insert("kind".into(), detected.kind().into());
+            for (name, value) in representations {
+                object.insert(name.into(), value.into());
+            }
+            println!("{}", serde_json::to_string_pretty(&object)?);
+            return Ok(());
+        }
+
+        println!("Detected {}", detected.kind());
+        let width = representations
+            .iter()
+            .map(|(name, _)| name.len())
+            .max()
+            .unwrap_or_default();
+        for (name, value) in representations {
+            println!("{:<width$}  {}", name, value, width = width);
+        }
+        Ok(())
+    }
+}
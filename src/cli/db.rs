@@ -0,0 +1,136 @@
+use crate::cli::ndjson::Ndjson;
+use crate::node::{check_pending, SledDiskState};
+use crate::Network;
+use anyhow::anyhow;
+use clap::Clap;
+use std::path::PathBuf;
+
+#[derive(Clap)]
+pub(crate) struct DbOpts {
+    #[clap(subcommand)]
+    command: Command,
+}
+
+#[derive(Clap)]
+enum Command {
+    /// Show the size of each table and the number of blocks in the database.
+    Stats(StatsOpts),
+
+    /// Rewrite the database to reclaim space. The node must not be running.
+    Compact(CommonOpts),
+
+    /// Report pending entries whose send block is missing or doesn't match.
+    CheckPending(CheckPendingOpts),
+}
+
+#[derive(Clap)]
+struct CommonOpts {
+    #[clap(short = 'n', long, default_value = "live", env = "FEELESS_NETWORK")]
+    network: Network,
+
+    /// Path to the database. Defaults to the database the node uses for this network.
+    #[clap(long)]
+    db: Option<PathBuf>,
+}
+
+impl CommonOpts {
+    fn path(&self) -> PathBuf {
+        self.db
+            .to_owned()
+            .unwrap_or_else(|| SledDiskState::default_path(self.network))
+    }
+}
+
+/// For commands that only read the database.
+#[derive(Clap)]
+struct ReadOpts {
+    #[clap(flatten)]
+    opts: CommonOpts,
+
+    /// Refuse to change the database. It still can't be opened while the node is running.
+    #[clap(long)]
+    read_only: bool,
+}
+
+#[derive(Clap)]
+struct StatsOpts {
+    #[clap(flatten)]
+    read: ReadOpts,
+
+    /// Output as JSON.
+    #[clap(long)]
+    json: bool,
+}
+
+#[derive(Clap)]
+struct CheckPendingOpts {
+    #[clap(flatten)]
+    read: ReadOpts,
+
+    /// Output as JSON.
+    #[clap(long)]
+    json: bool,
+
+    /// Output each orphaned entry as a line of JSON.
+    #[clap(long, conflicts_with = "json")]
+    ndjson: bool,
+}
+
+impl DbOpts {
+    pub async fn handle(&self) -> anyhow::Result<()> {
+        match &self.command {
+            Command::Stats(o) => {
+                let stats = SledDiskState::stats(&o.read.opts.path())?;
+                if o.json {
+                    println!("{}", serde_json::to_string_pretty(&stats)?);
+                } else {
+                    print!("{}", stats);
+                }
+            }
+            Command::Compact(o) => {
+                let stats = SledDiskState::compact(&o.path())?;
+                println!("Compacted from {} to {} bytes", stats.before, stats.after);
+            }
+            Command::CheckPending(o) => {
+                let path = o.read.opts.path();
+                if !path.exists() {
+                    return Err(anyhow!("Database {:?} does not exist", path));
+                }
+                let network = o.read.opts.network;
+                let state = if o.read.read_only {
+                    SledDiskState::open_read_only(network, &path)?
+                } else {
+                    SledDiskState::open(network, &path)?
+                };
+                let check = check_pending(&state).await?;
+                if o.json {
+                    println!("{}", serde_json::to_string_pretty(&check)?);
+                } else if o.ndjson {
+                    let mut out = Ndjson::stdout();
+                    for orphan in &check.orphaned {
+                        out.write(orphan)?;
+                    }
+                    out.flush()?;
+                } else {
+                    for orphan in &check.orphaned {
+                        println!(
+                            "{:?} to {}: {}",
+                            orphan.pending.source,
+                            orphan.pending.destination.to_address(),
+                            orphan.reason
+                        );
+                    }
+                    println!(
+                        "{} of {} pending entries are orphaned",
+                        check.orphaned.len(),
+                        check.entries
+                    );
+                }
+                if !check.orphaned.is_empty() {
+                    return Err(anyhow!("Found orphaned pending entries"));
+                }
+            }
+        }
+        Ok(())
+    }
+}
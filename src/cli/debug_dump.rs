@@ -0,0 +1,75 @@
+use crate::node::{decode_capture, Decoded, WireDump};
+use crate::Network;
+use anyhow::Context;
+use clap::Clap;
+use std::path::PathBuf;
+
+#[derive(Clap)]
+pub(crate) struct DebugDumpOpts {
+    /// File with the TCP payload sent by one side of a connection, e.g. from Wireshark's
+    /// "Follow TCP Stream" saved as raw.
+    path: PathBuf,
+
+    #[clap(short = 'n', long, default_value = "live", env = "FEELESS_NETWORK")]
+    network: Network,
+
+    /// The file is hex instead of raw bytes. Whitespace is ignored.
+    #[clap(long)]
+    hex: bool,
+
+    /// Only print messages of these types. Either `all` or a comma separated list, e.g.
+    /// `keepalive,confirm_ack`.
+    #[clap(long)]
+    only: Option<WireDump>,
+
+    /// Also print a hexdump of each message with the name of each field, like `--dump-wire`.
+    #[clap(long)]
+    fields: bool,
+}
+
+impl DebugDumpOpts {
+    pub fn handle(&self) -> anyhow::Result<()> {
+        let data = std::fs::read(&self.path).with_context(|| format!("Reading {:?}", self.path))?;
+        let data = if self.hex {
+            let text: String = String::from_utf8(data)
+                .context("Hex capture is not text")?
+                .split_whitespace()
+                .collect();
+            hex::decode(text).context("Decoding hex capture")?
+        } else {
+            data
+        };
+
+        let only = self.only.to_owned().unwrap_or_else(WireDump::all);
+        let (mut messages, mut skipped) = (0, 0);
+        for decoded in decode_capture(self.network, &data) {
+            match &decoded {
+                Decoded::Message {
+                    header, payload, ..
+                } => {
+                    messages += 1;
+                    if !only.selects(header.message_type()) {
+                        continue;
+                    }
+                    println!("{}", decoded);
+                    if self.fields {
+                        if let Some(dump) = only.received(header, payload) {
+                            println!("{}", dump);
+                        }
+                    }
+                }
+                Decoded::Skipped { len, .. } => {
+                    skipped += len;
+                    println!("{}", decoded);
+                }
+            }
+        }
+        println!(
+            "{} messages in {} bytes, {} bytes skipped",
+            messages,
+            data.len(),
+            skipped
+        );
+        Ok(())
+    }
+}
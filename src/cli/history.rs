@@ -0,0 +1,77 @@
+use crate::cli::ndjson::Ndjson;
+use crate::node::{RepChange, SledDiskState, State};
+use crate::{Address, Network};
+use anyhow::anyhow;
+use chrono::{TimeZone, Utc};
+use clap::Clap;
+use std::path::PathBuf;
+
+#[derive(Clap)]
+pub(crate) struct HistoryOpts {
+    /// Show when the account changed representative, oldest first.
+    #[clap(long)]
+    reps: Address,
+
+    #[clap(short = 'n', long, default_value = "live", env = "FEELESS_NETWORK")]
+    network: Network,
+
+    /// Path to the database. Defaults to the database the node uses for this network.
+    #[clap(long)]
+    db: Option<PathBuf>,
+
+    /// Refuse to change the database. It still can't be opened while the node is running.
+    #[clap(long)]
+    read_only: bool,
+
+    /// Output each change as a line of JSON.
+    #[clap(long)]
+    ndjson: bool,
+}
+
+impl HistoryOpts {
+    pub async fn handle(&self) -> anyhow::Result<()> {
+        let path = self
+            .db
+            .to_owned()
+            .unwrap_or_else(|| SledDiskState::default_path(self.network));
+        if !path.exists() {
+            return Err(anyhow!("Database {:?} does not exist", path));
+        }
+        let state = if self.read_only {
+            SledDiskState::open_read_only(self.network, &path)?
+        } else {
+            SledDiskState::open(self.network, &path)?
+        };
+        let history = state.rep_history(&self.reps.to_public()).await?;
+        if self.ndjson {
+            let mut out = Ndjson::stdout();
+            for change in &history {
+                out.write(change)?;
+            }
+            return out.flush();
+        }
+        if history.is_empty() {
+            println!("No representative changes for {}", self.reps);
+        }
+        for RepChange {
+            hash,
+            timestamp,
+            old,
+            new,
+        } in &history
+        {
+            let old = match old {
+                Some(old) => old.to_address().to_string(),
+                None => "(open)".into(),
+            };
+            println!(
+                "{} {:?} {} -> {}",
+                Utc.timestamp(*timestamp as i64, 0).to_rfc3339(),
+                hash,
+                old,
+                new.to_address()
+            );
+        }
+        Ok(())
+    }
+}
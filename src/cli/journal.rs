@@ -0,0 +1,59 @@
+use crate::node::Journal;
+use crate::Network;
+use anyhow::anyhow;
+use clap::Clap;
+use std::path::PathBuf;
+
+#[derive(Clap)]
+pub(crate) struct JournalOpts {
+    #[clap(subcommand)]
+    command: Command,
+}
+
+#[derive(Clap)]
+enum Command {
+    /// Print the latest entries of the journal, oldest first.
+    Tail(TailOpts),
+}
+
+#[derive(Clap)]
+struct TailOpts {
+    #[clap(short = 'n', long, default_value = "live", env = "FEELESS_NETWORK")]
+    network: Network,
+
+    /// Path to the journal. Defaults to the one the node uses for this network.
+    #[clap(long)]
+    path: Option<PathBuf>,
+
+    /// How many entries to print.
+    #[clap(short, long, default_value = "100")]
+    count: usize,
+
+    /// Output one JSON object per line.
+    #[clap(long)]
+    json: bool,
+}
+
+impl JournalOpts {
+    pub fn handle(&self) -> anyhow::Result<()> {
+        match &self.command {
+            Command::Tail(o) => {
+                let path = o
+                    .path
+                    .to_owned()
+                    .unwrap_or_else(|| Journal::default_path(o.network));
+                if !path.exists() {
+                    return Err(anyhow!("Journal {:?} does not exist", path));
+                }
+                for entry in Journal::tail(&path, o.count)? {
+                    if o.json {
+                        println!("{}", serde_json::to_string(&entry)?);
+                    } else {
+                        println!("{}", entry);
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
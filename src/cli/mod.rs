@@ -1,15 +1,58 @@
 #[cfg(feature = "pcap")]
 mod pcap;
 
+#[cfg(feature = "node")]
+mod bootstrap;
+#[cfg(feature = "node")]
+mod db;
+#[cfg(feature = "node")]
+mod debug_dump;
+#[cfg(feature = "node")]
+mod history;
+#[cfg(feature = "node")]
+mod journal;
+#[cfg(feature = "node")]
+mod peers;
+#[cfg(feature = "node")]
+mod persist;
+#[cfg(feature = "node")]
+mod stats;
+#[cfg(feature = "node")]
+mod status;
+#[cfg(feature = "node")]
+mod stress;
+#[cfg(feature = "node")]
+mod sync;
+#[cfg(feature = "node")]
+mod telemetry;
+#[cfg(feature = "node")]
+mod votes;
+
 mod address;
+mod convert;
+mod ndjson;
+pub(crate) mod parse;
 mod phrase;
+#[cfg(feature = "rpc_client")]
+mod pos;
 mod private;
 mod public;
+#[cfg(feature = "schema")]
+mod schema;
 mod seed;
+mod selftest;
+mod snapshot;
+#[cfg(feature = "rpc_client")]
+mod transfer;
 mod unit;
 mod vanity;
 mod verify;
+mod version;
 mod wallet;
+#[cfg(feature = "rpc_client")]
+mod walletd;
+#[cfg(feature = "watch")]
+mod watch;
 mod work;
 
 #[cfg(feature = "rpc_client")]
@@ -19,7 +62,21 @@ use crate::rpc::client::RPCClientOpts;
 use crate::cli::pcap::PcapDumpOpts;
 
 #[cfg(feature = "node")]
-use crate::node::Node;
+use crate::node::{
+    DialerConfig, ForkWatch, HealthConfig, MemoryBudget, Node, PeerFilter, WireDump,
+};
+#[cfg(feature = "node")]
+use crate::paths::Paths;
+#[cfg(feature = "node")]
+use crate::rpc::server::RpcServerConfig;
+#[cfg(feature = "node")]
+use crate::Config;
+#[cfg(feature = "node")]
+use crate::{Address, Public};
+use crate::{Backend, Network};
+
+#[cfg(feature = "watch")]
+use crate::cli::watch::WatchOpts;
 
 use crate::cli::unit::UnitOpts;
 use crate::cli::vanity::VanityOpts;
@@ -53,9 +110,24 @@ struct Opts {
     /// Maximum level of logging to be displayed: trace, debug, info, warn, error.
     #[clap(short = 'l', long)]
     log_level: Option<Level>,
+
+    /// The network to use: live, beta or test. It can be given before or after the command.
+    #[clap(long, global = true, default_value = "live", env = "FEELESS_NETWORK")]
+    network: Network,
+
+    /// The ed25519 implementation to sign and verify with: dalek or donna.
+    #[clap(long, global = true, env = "FEELESS_ED25519_BACKEND")]
+    ed25519_backend: Option<Backend>,
+
+    /// Build, sign and generate work for blocks as usual, but print what would be sent instead
+    /// of broadcasting it. Works with `send`, `receive` and `call process`, and is refused by
+    /// commands that would change something otherwise.
+    #[clap(long, global = true)]
+    dry_run: bool,
 }
 
 #[derive(Clap)]
+#[allow(clippy::large_enum_variant)]
 enum Command {
     #[cfg(feature = "node")]
     /// Launches a node
@@ -70,6 +142,30 @@ enum Command {
     /// Manage wallet files.
     Wallet(WalletOpts),
 
+    #[cfg(feature = "rpc_client")]
+    /// Send Nano from a wallet, seed or private key through an RPC server, and show the hash of
+    /// the send block.
+    Send(transfer::SendOpts),
+    #[cfg(not(feature = "rpc_client"))]
+    /// Send Nano through an RPC server. (DISABLED)
+    Send,
+
+    #[cfg(feature = "rpc_client")]
+    /// Receive the blocks waiting for an account of a wallet, seed or private key, opening the
+    /// account if needed.
+    Receive(transfer::ReceiveOpts),
+    #[cfg(not(feature = "rpc_client"))]
+    /// Receive the blocks waiting for an account through an RPC server. (DISABLED)
+    Receive,
+
+    #[cfg(feature = "rpc_client")]
+    /// Take a payment to a fresh account of a wallet, showing a QR code of the payment request,
+    /// then sweep it to a cold address once it's confirmed.
+    Pos(pos::PosOpts),
+    #[cfg(not(feature = "rpc_client"))]
+    /// Take a payment to a fresh account of a wallet. (DISABLED)
+    Pos,
+
     /// Verify Nano signed messages.
     Verify(VerifyOpts),
 
@@ -88,12 +184,26 @@ enum Command {
     /// Address conversion.
     Address(AddressOpts),
 
+    /// Convert an address, public key, block hash, seed, private key or phrase to everything it
+    /// can be turned into, working out which kind of input it is.
+    Convert(convert::ConvertOpts),
+
     /// Generate proof of work.
     Work(WorkOpts),
 
     /// Find a secret that can generate a custom vanity address.
     Vanity(VanityOpts),
 
+    /// Save account balances, and compare them between two points in time.
+    Snapshot(snapshot::SnapshotOpts),
+
+    /// Check that this build hashes, signs and encodes addresses correctly, and measure how fast
+    /// it is.
+    Selftest(selftest::SelftestOpts),
+
+    /// The version, git commit, features and network defaults of this build.
+    Version(version::VersionOpts),
+
     #[cfg(feature = "rpc_client")]
     /// RPC client that can call a function against a Nano RPC server.
     Call(RPCClientOpts),
@@ -101,19 +211,189 @@ enum Command {
     /// RPC client that can call a function against a Nano RPC server. (DISABLED)
     Call,
 
+    #[cfg(feature = "rpc_client")]
+    /// Serve wallets to local clients over an authenticated control socket, unlocking each one
+    /// on request.
+    Walletd(walletd::WalletdOpts),
+    #[cfg(not(feature = "rpc_client"))]
+    /// Serve wallets to local clients over an authenticated control socket. (DISABLED)
+    Walletd,
+
+    #[cfg(feature = "watch")]
+    /// Alert when watched accounts cross balance thresholds or make unexpected sends.
+    Watch(WatchOpts),
+    #[cfg(not(feature = "watch"))]
+    /// Alert when watched accounts cross balance thresholds or make unexpected sends. (DISABLED)
+    Watch,
+
     #[cfg(feature = "pcap")]
     /// Tool to analyse network capture dumps for Nano packets.
     Pcap(PcapDumpOpts),
     #[cfg(not(feature = "pcap"))]
     /// Tool to analyse network capture dumps for Nano packets. (DISABLED)
     Pcap,
+
+    #[cfg(feature = "schema")]
+    /// JSON Schema documents of the JSON used by blocks, RPC and WebSocket messages.
+    Schema(schema::SchemaOpts),
+    #[cfg(not(feature = "schema"))]
+    /// JSON Schema documents of the JSON used by blocks, RPC and WebSocket messages. (DISABLED)
+    Schema,
 }
 
+impl Command {
+    /// Whether the command can be rehearsed with `--dry-run`.
+    fn can_dry_run(&self) -> bool {
+        match self {
+            #[cfg(feature = "rpc_client")]
+            Command::Send(_) | Command::Receive(_) => true,
+            #[cfg(feature = "rpc_client")]
+            Command::Call(o) => o.can_dry_run(),
+            _ => false,
+        }
+    }
+}
+
+#[cfg(feature = "node")]
 #[derive(Clap)]
 struct NodeOpts {
+    #[clap(subcommand)]
+    command: Option<NodeSubcommand>,
+
     /// Comma separated list of IP:PORT pairs. Overrides default initial nodes.
     #[clap(short, long)]
     override_peers: Option<Vec<String>>,
+
+    /// IP:PORT that other nodes can reach us on. Sent to peers in keepalives.
+    #[clap(short, long)]
+    advertise: Option<String>,
+
+    /// Number of peers to bootstrap frontiers from in parallel. 0 disables bootstrapping.
+    #[clap(long, default_value = "4")]
+    bootstrap_peers: usize,
+
+    /// Number of peers to keep outbound connections to. Peers are dialed from the peer table,
+    /// preferring the ones that have worked before.
+    #[clap(long, default_value = "8")]
+    outbound_peers: usize,
+
+    /// Maximum number of peers to be dialing at once.
+    #[clap(long, default_value = "4")]
+    dial_concurrency: usize,
+
+    /// Verify the signature of every stored block on startup, instead of trusting blocks that
+    /// have already been cemented.
+    #[clap(long)]
+    paranoid: bool,
+
+    /// Path to the config file, for the peer allow and deny lists in its `[peers]` section, the
+    /// `/health` thresholds in `[health]`, the representative key to vote with in `[voting]` and
+    /// the RPC actions to answer in `[rpc]`. Defaults to `feeless.toml` in the data directory, if
+    /// it exists. Changes to the peer lists are picked up while the node runs.
+    #[clap(long)]
+    config: Option<PathBuf>,
+
+    /// Log a hexdump of every message of these types sent to or received from peers, with the
+    /// name of each field. Either `all` or a comma separated list, e.g. `keepalive,confirm_ack`.
+    #[clap(long)]
+    dump_wire: Option<WireDump>,
+
+    /// Megabytes of memory to size the node's caches from. The options below override the
+    /// number of entries in individual caches.
+    #[clap(long, default_value = "512")]
+    memory_budget: usize,
+
+    /// Maximum number of blocks waiting for the block before them to arrive.
+    #[clap(long)]
+    unchecked_cap: Option<usize>,
+
+    /// Maximum number of blocks to keep votes for.
+    #[clap(long)]
+    vote_cache: Option<usize>,
+
+    /// Number of recently published block hashes to remember, so repeats are skipped.
+    #[clap(long)]
+    dedup_cache: Option<usize>,
+
+    /// Maximum number of blocks to keep in memory, not counting account frontiers.
+    #[clap(long)]
+    block_cache: Option<usize>,
+
+    /// Comma separated accounts, e.g. your own, whose blocks get confirmation requested from
+    /// peers as soon as they arrive. The accounts in the `[watch]` config section are added.
+    #[clap(long, use_delimiter = true, parse(try_from_str = crate::cli::parse::address))]
+    priority_accounts: Vec<Address>,
+}
+
+#[cfg(feature = "node")]
+impl NodeOpts {
+    fn memory_budget(&self) -> MemoryBudget {
+        let budget = MemoryBudget::from_megabytes(self.memory_budget);
+        MemoryBudget {
+            unchecked: self.unchecked_cap.unwrap_or(budget.unchecked),
+            votes: self.vote_cache.unwrap_or(budget.votes),
+            dedup: self.dedup_cache.unwrap_or(budget.dedup),
+            blocks: self.block_cache.unwrap_or(budget.blocks),
+        }
+    }
+
+    fn dialer(&self) -> DialerConfig {
+        DialerConfig {
+            target: self.outbound_peers,
+            concurrency: self.dial_concurrency,
+            ..Default::default()
+        }
+    }
+}
+
+#[cfg(feature = "node")]
+#[derive(Clap)]
+enum NodeSubcommand {
+    /// Bootstrap the node database from peers, pulling the frontier of every account and then
+    /// the blocks missing below each one.
+    Bootstrap(bootstrap::BootstrapOpts),
+
+    /// Inspect and maintain the node database.
+    Db(db::DbOpts),
+
+    /// Decode and print every message in a raw capture of what a peer sent, for looking into
+    /// protocol issues without connecting to anyone.
+    DebugDump(debug_dump::DebugDumpOpts),
+
+    /// Show the history of an account from the node database.
+    History(history::HistoryOpts),
+
+    /// Read the journal of blocks the node added and peers it disconnected, which is kept
+    /// outside the database for looking into crashes.
+    Journal(journal::JournalOpts),
+
+    /// Show the peer table of a running node, with when each peer was last seen.
+    Peers(peers::PeersOpts),
+
+    /// Copy the ledger a running node holds in memory into a database on disk, so it's kept
+    /// without syncing again. Only blocks the database is missing are copied.
+    Persist(persist::PersistOpts),
+
+    /// Statistics from a running node.
+    Stats(stats::StatsOpts),
+
+    /// How far a running node is from synced, estimated from the block counts in the telemetry
+    /// of its peers, and the progress of the frontier bootstrap.
+    Status(status::StatusOpts),
+
+    /// Benchmark block processing by sending between funded accounts at a steady rate.
+    Stress(stress::StressOpts),
+
+    /// Copy the blocks missing from the node database from another database or a running node,
+    /// checking each one. This is much faster than bootstrapping when moving a node or
+    /// changing backends.
+    SyncFrom(sync::SyncFromOpts),
+
+    /// Show the latest telemetry from each node a running node is connected to.
+    Telemetry(telemetry::TelemetryOpts),
+
+    /// Inspect the votes we've made as a representative.
+    Votes(votes::VotesOpts),
 }
 
 #[derive(Clap)]
@@ -131,37 +411,148 @@ pub async fn run() -> anyhow::Result<()> {
     } else if env::var_os("RUST_LOG").is_none() {
         filter = filter.add_directive("feeless=info".parse()?);
     }
+    // Logs go to stderr so they don't get mixed up with output like `--ndjson`.
     let subscriber = tracing_subscriber::fmt::Subscriber::builder()
         .with_env_filter(filter)
         .with_ansi(!opts.no_color)
+        .with_writer(io::stderr)
         .finish();
     tracing::subscriber::set_global_default(subscriber).expect("Could not initialize logger");
 
+    if let Some(backend) = opts.ed25519_backend {
+        backend.select();
+    }
+    let network = opts.network;
+    let dry_run = opts.dry_run;
+    if dry_run && !opts.command.can_dry_run() {
+        return Err(anyhow!(
+            "--dry-run only works with send, receive and call, except for call persist"
+        ));
+    }
     match opts.command {
         #[cfg(feature = "node")]
-        Command::Node(o) => Node::start(o.override_peers).await,
+        Command::Node(o) => match o.command {
+            Some(NodeSubcommand::Bootstrap(bootstrap)) => bootstrap.handle().await,
+            Some(NodeSubcommand::Db(db)) => db.handle().await,
+            Some(NodeSubcommand::DebugDump(dump)) => dump.handle(),
+            Some(NodeSubcommand::History(history)) => history.handle().await,
+            Some(NodeSubcommand::Journal(journal)) => journal.handle(),
+            Some(NodeSubcommand::Peers(peers)) => peers.handle().await,
+            Some(NodeSubcommand::Persist(persist)) => persist.handle().await,
+            Some(NodeSubcommand::Stats(stats)) => stats.handle().await,
+            Some(NodeSubcommand::Status(status)) => status.handle().await,
+            Some(NodeSubcommand::Stress(stress)) => stress.handle().await,
+            Some(NodeSubcommand::SyncFrom(sync)) => sync.handle().await,
+            Some(NodeSubcommand::Telemetry(telemetry)) => telemetry.handle().await,
+            Some(NodeSubcommand::Votes(votes)) => votes.handle(),
+            None => {
+                let budget = o.memory_budget();
+                let dialer = o.dialer();
+                let peer_filter = PeerFilter::default();
+                let config = o
+                    .config
+                    .to_owned()
+                    .unwrap_or_else(|| Paths::new(network).config_path());
+                let mut health = HealthConfig::default();
+                let mut voting = None;
+                let mut pruning = None;
+                let mut rpc = RpcServerConfig::default();
+                #[cfg_attr(not(feature = "watch"), allow(unused_mut))]
+                let mut priority: Vec<Public> =
+                    o.priority_accounts.iter().map(|a| a.to_public()).collect();
+                let forks = ForkWatch::new();
+                if o.config.is_some() || config.exists() {
+                    peer_filter.load(&config).await?;
+                    let loaded = Config::load(&config).await?;
+                    health = loaded.health.unwrap_or_default();
+                    voting = loaded.voting;
+                    pruning = loaded.pruning;
+                    rpc = loaded.rpc.unwrap_or_default();
+                    #[cfg(feature = "watch")]
+                    if let Some(watch) = &loaded.watch {
+                        priority.extend(watch.accounts.iter().map(|a| a.address.to_public()));
+                        let watcher = crate::watch::BalanceWatcher::new(watch.to_owned());
+                        let events = forks.subscribe();
+                        tokio::spawn(async move { watcher.notify_forks(events).await });
+                    }
+                    tokio::spawn(peer_filter.clone().watch(config));
+                }
+                Node::start(
+                    network,
+                    o.override_peers,
+                    o.advertise,
+                    o.bootstrap_peers,
+                    o.paranoid,
+                    budget,
+                    peer_filter,
+                    o.dump_wire,
+                    health,
+                    voting,
+                    rpc,
+                    dialer,
+                    priority,
+                    forks,
+                    pruning,
+                )
+                .await
+            }
+        },
         #[cfg(not(feature = "node"))]
         Command::Node => panic!("Compile with the `node` feature to enable this."),
 
         #[cfg(feature = "pcap")]
-        Command::Pcap(o) => o.handle().await,
+        Command::Pcap(o) => o.handle(network).await,
         #[cfg(not(feature = "pcap"))]
         Command::Pcap => panic!("Compile with the `pcap` feature to enable this."),
 
         #[cfg(feature = "rpc_client")]
-        Command::Call(o) => Ok(o.handle().await?),
+        Command::Call(o) => Ok(o.handle(dry_run).await?),
         #[cfg(not(feature = "rpc_client"))]
         Command::Call => panic!("Compile with the `rpc_client` feature to enable this."),
 
+        #[cfg(feature = "rpc_client")]
+        Command::Walletd(o) => o.handle().await,
+        #[cfg(not(feature = "rpc_client"))]
+        Command::Walletd => panic!("Compile with the `rpc_client` feature to enable this."),
+
+        #[cfg(feature = "rpc_client")]
+        Command::Send(o) => o.handle(dry_run).await,
+        #[cfg(not(feature = "rpc_client"))]
+        Command::Send => panic!("Compile with the `rpc_client` feature to enable this."),
+
+        #[cfg(feature = "rpc_client")]
+        Command::Receive(o) => o.handle(dry_run).await,
+        #[cfg(not(feature = "rpc_client"))]
+        Command::Receive => panic!("Compile with the `rpc_client` feature to enable this."),
+
+        #[cfg(feature = "rpc_client")]
+        Command::Pos(o) => o.handle().await,
+        #[cfg(not(feature = "rpc_client"))]
+        Command::Pos => panic!("Compile with the `rpc_client` feature to enable this."),
+
+        #[cfg(feature = "watch")]
+        Command::Watch(o) => o.handle().await,
+        #[cfg(not(feature = "watch"))]
+        Command::Watch => panic!("Compile with the `watch` feature to enable this."),
+
+        #[cfg(feature = "schema")]
+        Command::Schema(o) => o.handle(),
+        #[cfg(not(feature = "schema"))]
+        Command::Schema => panic!("Compile with the `schema` feature to enable this."),
+
         Command::Wallet(wallet) => wallet.handle().await,
         Command::Seed(seed) => seed.handle(),
         Command::Private(private) => private.handle(),
         Command::Public(public) => public.handle(),
         Command::Phrase(phrase) => phrase.handle(),
         Command::Address(address) => address.handle(),
+        Command::Convert(convert) => convert.handle(),
         Command::Unit(unit) => unit.handle(),
-        Command::Work(work) => work.handle(),
+        Command::Work(work) => work.handle(network).await,
         Command::Vanity(vanity) => vanity.handle().await,
+        Command::Selftest(selftest) => selftest.handle(),
+        Command::Version(version) => version.handle(),
+        Command::Snapshot(snapshot) => snapshot.handle().await,
         Command::Verify(verify) => verify.handle(),
     }
 }
@@ -173,7 +564,7 @@ pub async fn run() -> anyhow::Result<()> {
 enum StringOrStdin<T>
 where
     T: FromStr,
-    <T as FromStr>::Err: std::fmt::Debug,
+    <T as FromStr>::Err: std::fmt::Display,
 {
     String(T),
     Stdin,
@@ -182,13 +573,13 @@ where
 impl<T> StringOrStdin<T>
 where
     T: FromStr,
-    <T as FromStr>::Err: std::fmt::Debug,
+    <T as FromStr>::Err: std::fmt::Display,
 {
     /// Resolve `T` by reading from stdin if necessary.
     pub fn resolve(self) -> anyhow::Result<T>
     where
         T: FromStr,
-        <T as FromStr>::Err: std::fmt::Debug,
+        <T as FromStr>::Err: std::fmt::Display,
     {
         match self {
             StringOrStdin::String(t) => Ok(t),
@@ -196,7 +587,7 @@ where
                 let mut buffer = String::new();
                 io::stdin().read_to_string(&mut buffer)?;
                 Ok(T::from_str(buffer.trim())
-                    .map_err(|e| anyhow!("Conversion from string failed: {:?}", e))?)
+                    .map_err(|e| anyhow!("Could not parse stdin: {}", e))?)
             }
         }
     }
@@ -205,18 +596,18 @@ where
 impl<T> FromStr for StringOrStdin<T>
 where
     T: FromStr,
-    <T as FromStr>::Err: std::fmt::Debug,
+    <T as FromStr>::Err: std::fmt::Display,
 {
     type Err = anyhow::Error;
 
     // This wasn't done in one step because I think clap calls from_str twice, and the second time
     // around stdin is empty.
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        match s.as_ref() {
+        match s {
             "-" => Ok(StringOrStdin::Stdin),
             x => match T::from_str(x) {
                 Ok(x) => Ok(StringOrStdin::String(x)),
-                Err(e) => Err(anyhow!("Could not parse string: {:?}", e)),
+                Err(e) => Err(anyhow!("{}", e)),
             },
         }
     }
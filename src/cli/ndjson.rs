@@ -0,0 +1,68 @@
+//! Newline delimited JSON output for the `--ndjson` flag, with one record per line written as
+//! it's produced.
+use serde::Serialize;
+use std::io::{BufWriter, Stdout, Write};
+use std::time::{Duration, Instant};
+
+/// The longest a record sits in the buffer while more records are being written.
+const FLUSH_INTERVAL: Duration = Duration::from_millis(500);
+
+pub(crate) struct Ndjson<W: Write> {
+    writer: BufWriter<W>,
+    last_flush: Instant,
+}
+
+impl Ndjson<Stdout> {
+    pub fn stdout() -> Self {
+        Self::new(std::io::stdout())
+    }
+}
+
+impl<W: Write> Ndjson<W> {
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer: BufWriter::new(writer),
+            last_flush: Instant::now(),
+        }
+    }
+
+    /// Write a record, flushing when the last flush was a while ago. Call [Ndjson::flush] after
+    /// a batch of records so the last ones aren't held back.
+    pub fn write<T: Serialize>(&mut self, record: &T) -> anyhow::Result<()> {
+        serde_json::to_writer(&mut self.writer, record)?;
+        self.writer.write_all(b"\n")?;
+        if self.last_flush.elapsed() >= FLUSH_INTERVAL {
+            self.flush()?;
+        }
+        Ok(())
+    }
+
+    pub fn flush(&mut self) -> anyhow::Result<()> {
+        self.writer.flush()?;
+        self.last_flush = Instant::now();
+        Ok(())
+    }
+
+    #[cfg(test)]
+    fn into_inner(self) -> W {
+        self.writer.into_inner().ok().unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn lines() {
+        let mut out = Ndjson::new(vec![]);
+        out.write(&json!({"a": 1})).unwrap();
+        out.write(&json!({"b": "two\nlines"})).unwrap();
+        out.flush().unwrap();
+        assert_eq!(
+            String::from_utf8(out.into_inner()).unwrap(),
+            "{\"a\":1}\n{\"b\":\"two\\nlines\"}\n"
+        );
+    }
+}
@@ -0,0 +1,177 @@
+//! Value parsers for arguments, used with `#[clap(parse(try_from_str = ...))]`.
+//!
+//! These reject bad input while the arguments are parsed, with a message saying what was
+//! expected, instead of a command failing part way through.
+use crate::blocks::{BlockHash, Root};
+use crate::cli::StringOrStdin;
+use crate::units::parse_amount;
+use crate::{Address, Difficulty, Error, Private, Public, Raw, Seed, Work};
+use std::fmt::Display;
+use std::str::FromStr;
+use std::time::Duration;
+
+const ADDRESS_PREFIX: &str = "nano_";
+
+/// Characters after the prefix of an address.
+const ADDRESS_ENCODED_LEN: usize = 60;
+
+pub(crate) fn address(s: &str) -> Result<Address, String> {
+    let encoded = s
+        .strip_prefix(ADDRESS_PREFIX)
+        .ok_or_else(|| format!("{:?} should start with {}", s, ADDRESS_PREFIX))?;
+    if encoded.len() != ADDRESS_ENCODED_LEN {
+        return Err(format!(
+            "{:?} should have {} characters after {}, not {}",
+            s,
+            ADDRESS_ENCODED_LEN,
+            ADDRESS_PREFIX,
+            encoded.len()
+        ));
+    }
+    Address::from_str(s).map_err(|err| match err {
+        Error::InvalidChecksum => format!("{:?} has a bad checksum, check it for typos", s),
+        err => format!("{:?} isn't a valid address: {}", s, err),
+    })
+}
+
+pub(crate) fn public(s: &str) -> Result<Public, String> {
+    hex(s, "A public key", Public::LEN)
+}
+
+pub(crate) fn seed(s: &str) -> Result<Seed, String> {
+    hex(s, "A seed", 32)
+}
+
+pub(crate) fn private(s: &str) -> Result<Private, String> {
+    hex(s, "A private key", Private::LEN)
+}
+
+pub(crate) fn block_hash(s: &str) -> Result<BlockHash, String> {
+    hex(s, "A block hash", BlockHash::LEN)
+}
+
+pub(crate) fn root(s: &str) -> Result<Root, String> {
+    hex(s, "A root", Root::LEN)
+}
+
+pub(crate) fn work(s: &str) -> Result<Work, String> {
+    hex(s, "Work", Work::LEN)
+}
+
+pub(crate) fn difficulty(s: &str) -> Result<Difficulty, String> {
+    hex(s, "A difficulty", 8)
+}
+
+/// An amount with an optional unit, see [parse_amount].
+pub(crate) fn amount(s: &str) -> Result<Raw, String> {
+    parse_amount(s).map_err(|err| err.to_string())
+}
+
+/// An index for deriving keys from a seed or phrase.
+pub(crate) fn index(s: &str) -> Result<u32, String> {
+    u32::from_str(s).map_err(|_| match i128::from_str(s) {
+        Ok(n) if n < 0 => format!("An index can't be negative, got {}", n),
+        Ok(_) => format!("An index can be at most {}, got {}", u32::MAX, s),
+        Err(_) => format!("{:?} isn't a whole number", s),
+    })
+}
+
+/// A number of seconds, or a number followed by `ms`, `s`, `m` or `h`, e.g. `30s`.
+pub(crate) fn duration(s: &str) -> Result<Duration, String> {
+    let split = s.find(|c: char| c.is_ascii_alphabetic()).unwrap_or(s.len());
+    let (number, unit) = s.split_at(split);
+    let seconds = match unit {
+        "ms" => 0.001,
+        "" | "s" => 1.,
+        "m" => 60.,
+        "h" => 3600.,
+        _ => return Err(format!("{:?} isn't a unit, use ms, s, m or h", unit)),
+    };
+    match f64::from_str(number) {
+        Ok(n) if n.is_finite() && n >= 0. => Ok(Duration::from_secs_f64(n * seconds)),
+        _ => Err(format!("{:?} isn't a duration, e.g. 30s", s)),
+    }
+}
+
+pub(super) fn address_or_stdin(s: &str) -> Result<StringOrStdin<Address>, String> {
+    or_stdin(s, address)
+}
+
+pub(super) fn public_or_stdin(s: &str) -> Result<StringOrStdin<Public>, String> {
+    or_stdin(s, public)
+}
+
+pub(super) fn seed_or_stdin(s: &str) -> Result<StringOrStdin<Seed>, String> {
+    or_stdin(s, seed)
+}
+
+pub(super) fn private_or_stdin(s: &str) -> Result<StringOrStdin<Private>, String> {
+    or_stdin(s, private)
+}
+
+/// `-` reads the value from stdin when the command runs, otherwise it's parsed now.
+fn or_stdin<T>(s: &str, parse: fn(&str) -> Result<T, String>) -> Result<StringOrStdin<T>, String>
+where
+    T: FromStr,
+    <T as FromStr>::Err: Display,
+{
+    match s {
+        "-" => Ok(StringOrStdin::Stdin),
+        s => parse(s).map(StringOrStdin::String),
+    }
+}
+
+fn hex<T>(s: &str, what: &str, len: usize) -> Result<T, String>
+where
+    T: FromStr<Err = Error>,
+{
+    if s.len() != len * 2 {
+        return Err(format!(
+            "{} should be {} hex characters, not {}",
+            what,
+            len * 2,
+            s.len()
+        ));
+    }
+    if let Some(c) = s.chars().find(|c| !c.is_ascii_hexdigit()) {
+        return Err(format!("{} can't contain {:?}, only 0-9 and A-F", what, c));
+    }
+    T::from_str(s).map_err(|err| err.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn messages() {
+        let good = "nano_3i1aq1cchnmbn9x5rsbap8b15akfh7wj7pwskuzi7ahz8oq6cobd99d4r3b7";
+        assert!(address(good).is_ok());
+        assert!(address(&good.replace("nano_", "nan_"))
+            .unwrap_err()
+            .contains("should start with"));
+        assert!(address(&good[..60]).unwrap_err().contains("not 55"));
+        assert!(address(&good.replace("r3b7", "r3b8"))
+            .unwrap_err()
+            .contains("checksum"));
+
+        assert!(block_hash(&"0".repeat(63)).unwrap_err().contains("not 63"));
+        assert!(block_hash(&"g".repeat(64)).unwrap_err().contains("'g'"));
+        assert!(work("2bf29ef00786a6bc").is_ok());
+
+        assert_eq!(index("7"), Ok(7));
+        assert!(index("-1").unwrap_err().contains("negative"));
+        assert!(index("4294967296").unwrap_err().contains("at most"));
+        assert!(index("one").unwrap_err().contains("whole number"));
+
+        assert_eq!(amount("2raw"), Ok(Raw::from(2)));
+        assert!(amount("1.5raw").is_err());
+
+        assert_eq!(duration("30s"), Ok(Duration::from_secs(30)));
+        assert_eq!(duration("1.5"), Ok(Duration::from_millis(1500)));
+        assert_eq!(duration("250ms"), Ok(Duration::from_millis(250)));
+        assert_eq!(duration("2m"), Ok(Duration::from_secs(120)));
+        assert!(duration("3d").unwrap_err().contains("unit"));
+        assert!(duration("-1s").unwrap_err().contains("isn't a duration"));
+    }
+}
@@ -1,3 +1,4 @@
+use crate::Network;
 use anyhow::Context;
 use clap::Clap;
 use std::net::Ipv4Addr;
@@ -27,16 +28,17 @@ pub(crate) struct PcapDumpOpts {
 }
 
 impl PcapDumpOpts {
-    pub async fn handle(&self) -> anyhow::Result<()> {
+    pub async fn handle(&self, network: Network) -> anyhow::Result<()> {
         let subject = match &self.my_addr {
             Some(ip_addr) => crate::pcap::Subject::Specified(
-                Ipv4Addr::from_str(&ip_addr).context("Invalid IP address")?,
+                Ipv4Addr::from_str(ip_addr).context("Invalid IP address")?,
             ),
             None => crate::pcap::Subject::AutoFirstSource,
         };
         let mut p = crate::pcap::PcapDump::new(subject);
         p.start_at = self.start;
         p.end_at = self.end;
+        p.network = network;
         p.filter_addr = self
             .filter_addr
             .as_ref()
@@ -0,0 +1,41 @@
+use crate::rpc::calls::PersistRequest;
+use crate::rpc::client::{RPCClient, RPCRequest};
+use clap::Clap;
+use std::path::PathBuf;
+
+#[derive(Clap)]
+pub(crate) struct PersistOpts {
+    /// Directory of the database to write to, relative to `persist_dir` in the node's `[rpc]`
+    /// config. It's created if it doesn't exist, and can't be in use by another node.
+    #[clap(long)]
+    to: PathBuf,
+
+    /// The URL of the node's RPC server.
+    #[clap(
+        long,
+        short,
+        default_value = "http://localhost:7076",
+        env = "FEELESS_RPC_URL"
+    )]
+    url: String,
+
+    /// Output as JSON.
+    #[clap(long)]
+    json: bool,
+}
+
+impl PersistOpts {
+    pub async fn handle(&self) -> anyhow::Result<()> {
+        let response = (&PersistRequest {
+            to: self.to.to_owned(),
+        })
+            .call(&RPCClient::new(&self.url))
+            .await?;
+        if self.json {
+            println!("{}", serde_json::to_string_pretty(&response)?);
+        } else {
+            print!("{}", response);
+        }
+        Ok(())
+    }
+}
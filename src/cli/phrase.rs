@@ -28,6 +28,12 @@ impl PhraseOpts {
                 let address = x.opts.to_private()?.to_public()?.to_address();
                 println!("{}", address);
             }
+            Command::ToBip44(x) => {
+                let private = x.opts.to_private()?;
+                println!("{}", crate::Phrase::bip44_path(x.opts.account));
+                println!("{}", private);
+                println!("{}", private.to_public()?.to_address());
+            }
         }
         Ok(())
     }
@@ -51,6 +57,7 @@ pub enum Command {
     ToPrivate(Private),
     ToPublic(Public),
     ToAddress(Address),
+    ToBip44(Bip44),
 }
 
 // This is used with `#[clap(flatten)]` to prevent have duplicate code.
@@ -80,7 +87,7 @@ pub struct FromPhraseOpts {
     #[clap(flatten)]
     language: LanguageOpt,
 
-    #[clap(short, long, default_value = "0")]
+    #[clap(short, long, default_value = "0", parse(try_from_str = crate::cli::parse::index))]
     account: u32,
 
     // I tried using default_value = "" but clap still complained about the field being required.
@@ -120,3 +127,11 @@ pub struct Address {
     #[clap(flatten)]
     opts: FromPhraseOpts,
 }
+
+/// Show the BIP44 path, private key and address of an account, which match Ledger and Trust
+/// Wallet for the same phrase.
+#[derive(Clap)]
+pub struct Bip44 {
+    #[clap(flatten)]
+    opts: FromPhraseOpts,
+}
@@ -0,0 +1,178 @@
+use crate::cli::wallet::{work_thresholds, CommonOpts};
+use crate::qr::QrCode;
+use crate::rpc::calls::{AccountInfoRequest, AccountsPendingRequest, AccountsPendingResponse};
+use crate::rpc::client::{RPCClient, RPCRequest};
+use crate::units::parse_amount;
+use crate::wallet::{AuditEntry, AuditLog, AuditOperation, Payer, RPCPayer, Wallet};
+use crate::{Address, NanoUri, Raw, RpcErrorKind};
+use anyhow::{anyhow, Context};
+use clap::Clap;
+use std::time::{Duration, Instant};
+
+/// How many pending blocks to look at when adding up a payment.
+const PENDING_COUNT: u64 = 64;
+
+#[derive(Clap)]
+pub(crate) struct PosOpts {
+    /// Amount to charge with a unit, e.g. `25mnano`.
+    #[clap(parse(try_from_str = parse_amount))]
+    amount: Raw,
+
+    /// Address or `@contact` to sweep the payment to once it's confirmed.
+    #[clap(long)]
+    cold: String,
+
+    /// Shown by the payer's wallet, e.g. the name of the shop.
+    #[clap(long)]
+    label: Option<String>,
+
+    /// What the payment is for, also shown by the payer's wallet.
+    #[clap(long)]
+    message: Option<String>,
+
+    /// The first account index of the wallet to consider for the deposit. Accounts that were
+    /// ever opened or paid to are skipped, so every sale gets an account of its own.
+    #[clap(long, default_value = "1", parse(try_from_str = crate::cli::parse::index))]
+    first_index: u32,
+
+    /// Seconds between checks for the payment.
+    #[clap(long, default_value = "2")]
+    poll: u64,
+
+    /// Seconds to wait for the payment before giving up. Anything paid so far stays in the
+    /// deposit account, to be received or swept later.
+    #[clap(long, default_value = "600")]
+    timeout: u64,
+
+    /// The URL of the RPC server to watch for the payment and send the sweep through.
+    #[clap(
+        long,
+        short,
+        default_value = "http://localhost:7076",
+        env = "FEELESS_RPC_URL"
+    )]
+    url: String,
+
+    /// Send a string in the HTTP authorization header.
+    #[clap(long, env = "FEELESS_RPC_AUTH")]
+    auth: Option<String>,
+
+    #[clap(flatten)]
+    opts: CommonOpts,
+}
+
+impl PosOpts {
+    pub async fn handle(&self) -> anyhow::Result<()> {
+        let manager = self.opts.manager()?;
+        let cold = manager.resolve(&self.cold).await?;
+        let wallet_id = self.opts.wallet_id().await?;
+        let wallet = manager.wallet(&wallet_id).await?;
+        let client = self.client();
+
+        let index = fresh_index(&client, &wallet, self.first_index).await?;
+        let deposit = wallet.private(index)?;
+        let address = deposit.to_address()?;
+
+        let mut uri = NanoUri::new(address.to_owned()).amount(self.amount.to_owned());
+        uri.label = self.label.to_owned();
+        uri.message = self.message.to_owned();
+        let uri = uri.to_string();
+        println!("{}", QrCode::encode(uri.as_bytes())?.to_terminal_string());
+        println!("{}", uri);
+        println!("Waiting for {} raw to account {}", self.amount, index);
+
+        let started = Instant::now();
+        loop {
+            let paid = confirmed_pending(&client, &address).await?;
+            if paid >= self.amount {
+                println!("Paid {} raw", paid);
+                break;
+            }
+            if started.elapsed() >= Duration::from_secs(self.timeout) {
+                return Err(anyhow!(
+                    "Timed out with {} of {} raw paid to account {}",
+                    paid,
+                    self.amount,
+                    index
+                ));
+            }
+            tokio::time::sleep(Duration::from_secs(self.poll)).await;
+        }
+
+        let payer = RPCPayer::new(client, self.url.to_owned())
+            .thresholds(work_thresholds(&self.opts.paths_opts).await?);
+        let result = payer.sweep(&deposit, &cold).await;
+
+        let mut entry = AuditEntry::new(
+            wallet_id.to_owned(),
+            AuditOperation::Broadcast,
+            (&result).into(),
+        )
+        .destination(cold.to_owned());
+        entry.account = Some(address.to_owned());
+        if let Ok(Some((hash, amount))) = &result {
+            entry.block = Some(hash.to_owned());
+            entry.amount = Some(amount.to_owned());
+        }
+        entry.rpc = payer.describe();
+        AuditLog::new(self.opts.paths_opts.audit_log_path()?)
+            .record(&entry)
+            .await?;
+
+        match result? {
+            Some((hash, amount)) => println!("Swept {} raw to {} in {}", amount, cold, hash),
+            None => println!("Nothing to sweep from {}", address),
+        }
+        Ok(())
+    }
+
+    fn client(&self) -> RPCClient {
+        let mut client = RPCClient::new(&self.url);
+        if let Some(auth) = &self.auth {
+            client.authorization(auth);
+        }
+        client
+    }
+}
+
+/// The first account from `first` on that the network has never seen.
+async fn fresh_index(client: &RPCClient, wallet: &Wallet, first: u32) -> anyhow::Result<u32> {
+    for index in first..=u32::MAX {
+        let address = wallet.address(index)?;
+        match (&AccountInfoRequest::new(address.to_owned()))
+            .call(client)
+            .await
+        {
+            Err(crate::Error::RPCError(RpcErrorKind::AccountNotFound)) => {
+                if confirmed_pending(client, &address).await? == Raw::zero() {
+                    return Ok(index);
+                }
+            }
+            Err(err) => return Err(err).context("Looking for a fresh deposit account"),
+            Ok(_) => {}
+        }
+    }
+    Err(anyhow!("Every account of the wallet has been used"))
+}
+
+/// The total of the confirmed blocks waiting to be received by `address`.
+async fn confirmed_pending(client: &RPCClient, address: &Address) -> anyhow::Result<Raw> {
+    let request = AccountsPendingRequest::new(vec![address.to_owned()], PENDING_COUNT)
+        .threshold(Raw::from(1))
+        .only_confirmed();
+    let blocks = match (&request).call(client).await? {
+        AccountsPendingResponse::Threshold { mut blocks } => {
+            blocks.remove(address).unwrap_or_default()
+        }
+        AccountsPendingResponse::OnlyBlockHash { blocks }
+            if blocks.values().all(|hashes| hashes.is_empty()) =>
+        {
+            Default::default()
+        }
+        response => return Err(anyhow!("Pending blocks without amounts: {:?}", response)),
+    };
+    blocks
+        .values()
+        .try_fold(Raw::zero(), |total, amount| total.checked_add(amount))
+        .ok_or_else(|| anyhow!("Pending amounts overflow"))
+}
@@ -36,10 +36,12 @@ pub enum Command {
 
 #[derive(Clap)]
 pub struct Public {
+    #[clap(parse(try_from_str = crate::cli::parse::private_or_stdin))]
     private: StringOrStdin<crate::Private>,
 }
 
 #[derive(Clap)]
 pub struct Address {
+    #[clap(parse(try_from_str = crate::cli::parse::private_or_stdin))]
     private: StringOrStdin<crate::Private>,
 }
@@ -23,5 +23,6 @@ pub enum Command {
 
 #[derive(Clap)]
 pub struct Address {
+    #[clap(parse(try_from_str = crate::cli::parse::public_or_stdin))]
     public: StringOrStdin<crate::Public>,
 }
@@ -0,0 +1,72 @@
+use crate::schema;
+use anyhow::Context;
+use clap::Clap;
+use std::fs;
+use std::path::PathBuf;
+
+#[derive(Clap)]
+pub(crate) struct SchemaOpts {
+    #[clap(subcommand)]
+    command: Command,
+}
+
+#[derive(Clap)]
+enum Command {
+    /// Write JSON Schema documents for blocks, RPC requests and responses, and WebSocket
+    /// messages.
+    Export(ExportOpts),
+}
+
+#[derive(Clap)]
+struct ExportOpts {
+    /// Directory to write a `<name>.json` file for each schema into. Without it, every schema is
+    /// printed as one JSON object keyed by name.
+    #[clap(long, short)]
+    out: Option<PathBuf>,
+
+    /// Only export these schemas, e.g. `state_block` or `account_info_response`.
+    names: Vec<String>,
+}
+
+impl SchemaOpts {
+    pub fn handle(&self) -> anyhow::Result<()> {
+        match &self.command {
+            Command::Export(o) => o.handle(),
+        }
+    }
+}
+
+impl ExportOpts {
+    fn handle(&self) -> anyhow::Result<()> {
+        let mut schemas = schema::export();
+        if !self.names.is_empty() {
+            for name in &self.names {
+                if !schemas.contains_key(name.as_str()) {
+                    let known: Vec<&str> = schemas.keys().copied().collect();
+                    return Err(anyhow::anyhow!(
+                        "Unknown schema {}, expected one of: {}",
+                        name,
+                        known.join(", ")
+                    ));
+                }
+            }
+            schemas.retain(|name, _| self.names.iter().any(|n| n == name));
+        }
+
+        let out = match &self.out {
+            Some(out) => out,
+            None => {
+                println!("{}", serde_json::to_string_pretty(&schemas)?);
+                return Ok(());
+            }
+        };
+        fs::create_dir_all(out).with_context(|| format!("Creating {:?}", out))?;
+        for (name, schema) in &schemas {
+            let path = out.join(format!("{}.json", name));
+            fs::write(&path, serde_json::to_string_pretty(schema)?)
+                .with_context(|| format!("Writing {:?}", path))?;
+        }
+        println!("Wrote {} schemas to {:?}", schemas.len(), out);
+        Ok(())
+    }
+}
@@ -13,22 +13,19 @@ impl SeedOpts {
         match &self.command {
             Command::New => println!("{}", crate::Seed::random()),
             Command::ToPrivate(o) => {
-                let private = o.seed.to_owned().resolve()?.derive(o.index);
-                println!("{}", private)
+                for (_, private) in o.derive()? {
+                    println!("{}", private)
+                }
             }
             Command::ToPublic(o) => {
-                let public = o.seed.to_owned().resolve()?.derive(o.index).to_public()?;
-                println!("{}", public)
+                for (_, private) in o.derive()? {
+                    println!("{}", private.to_public()?)
+                }
             }
             Command::ToAddress(o) => {
-                let address = o
-                    .seed
-                    .to_owned()
-                    .resolve()?
-                    .derive(o.index)
-                    .to_public()?
-                    .to_address();
-                println!("{}", address)
+                for (_, private) in o.derive()? {
+                    println!("{}", private.to_public()?.to_address())
+                }
             }
         }
         Ok(())
@@ -45,8 +42,23 @@ pub enum Command {
 
 #[derive(Clap)]
 pub struct Opts {
+    #[clap(parse(try_from_str = crate::cli::parse::seed_or_stdin))]
     seed: StringOrStdin<crate::Seed>,
 
-    #[clap(short, long, default_value = "0")]
+    #[clap(short, long, default_value = "0", parse(try_from_str = crate::cli::parse::index))]
     index: u32,
+
+    /// Derive this many keys, starting at the index.
+    #[clap(short, long, default_value = "1")]
+    count: u32,
+}
+
+impl Opts {
+    fn derive(&self) -> anyhow::Result<Vec<(u32, crate::Private)>> {
+        let end = self
+            .index
+            .checked_add(self.count)
+            .ok_or_else(|| anyhow::anyhow!("The index plus the count is past the last index"))?;
+        Ok(self.seed.to_owned().resolve()?.derive_many(self.index..end))
+    }
 }
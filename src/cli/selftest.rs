@@ -0,0 +1,45 @@
+use crate::selftest::{known_answers, Throughput};
+use anyhow::anyhow;
+use clap::Clap;
+use std::time::Duration;
+
+#[derive(Clap)]
+pub(crate) struct SelftestOpts {
+    /// Seconds to measure each operation for.
+    #[clap(long, default_value = "1")]
+    seconds: f64,
+
+    /// Only run the known-answer tests.
+    #[clap(long)]
+    no_benchmark: bool,
+}
+
+impl SelftestOpts {
+    pub fn handle(&self) -> anyhow::Result<()> {
+        let checks = known_answers();
+        let mut failed = 0;
+        for check in &checks {
+            match &check.result {
+                Ok(()) => println!("ok      {}", check.name),
+                Err(err) => {
+                    failed += 1;
+                    println!("FAILED  {}: {:#}", check.name, err);
+                }
+            }
+        }
+        if failed > 0 {
+            return Err(anyhow!(
+                "{} of {} known-answer tests failed, so this build can't be trusted",
+                failed,
+                checks.len()
+            ));
+        }
+
+        if !self.no_benchmark {
+            println!();
+            let duration = Duration::from_secs_f64(self.seconds);
+            print!("{}", Throughput::measure(duration)?);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,141 @@
+use crate::cli::ndjson::Ndjson;
+use crate::snapshot::Snapshot;
+#[cfg(feature = "rpc_client")]
+use crate::Address;
+#[cfg(feature = "node")]
+use crate::Network;
+use clap::Clap;
+use std::path::PathBuf;
+
+#[derive(Clap)]
+pub(crate) struct SnapshotOpts {
+    #[clap(subcommand)]
+    command: Command,
+}
+
+#[derive(Clap)]
+enum Command {
+    /// Save the balance of every account in the node database, or of the given accounts over
+    /// RPC when `--url` is set.
+    Balances(BalancesOpts),
+
+    /// Show how much went in or out of each account between two saved snapshots.
+    Diff(DiffOpts),
+}
+
+#[derive(Clap)]
+struct BalancesOpts {
+    /// Where to save the snapshot as JSON.
+    #[clap(short, long)]
+    out: PathBuf,
+
+    /// The URL of an RPC server to take the balances from instead of the node database.
+    #[cfg(feature = "rpc_client")]
+    #[clap(long, short, env = "FEELESS_RPC_URL", requires = "accounts")]
+    url: Option<String>,
+
+    /// Comma separated accounts to take the balances of over RPC.
+    #[cfg(feature = "rpc_client")]
+    #[clap(long, use_delimiter = true, parse(try_from_str = crate::cli::parse::address))]
+    accounts: Vec<Address>,
+
+    #[cfg(feature = "node")]
+    #[clap(short = 'n', long, default_value = "live", env = "FEELESS_NETWORK")]
+    network: Network,
+
+    /// Path to the node database. Defaults to the database the node uses for this network.
+    #[cfg(feature = "node")]
+    #[clap(long)]
+    db: Option<PathBuf>,
+
+    /// Refuse to change the node database. It still can't be opened while the node is running.
+    #[cfg(feature = "node")]
+    #[clap(long)]
+    read_only: bool,
+}
+
+#[derive(Clap)]
+struct DiffOpts {
+    /// The earlier snapshot.
+    from: PathBuf,
+
+    /// The later snapshot.
+    to: PathBuf,
+
+    /// Output as JSON.
+    #[clap(long)]
+    json: bool,
+
+    /// Output each account that changed as a line of JSON.
+    #[clap(long, conflicts_with = "json")]
+    ndjson: bool,
+}
+
+impl SnapshotOpts {
+    pub async fn handle(&self) -> anyhow::Result<()> {
+        match &self.command {
+            Command::Balances(o) => {
+                let snapshot = o.snapshot().await?;
+                snapshot.save(&o.out).await?;
+                println!(
+                    "Saved the balances of {} accounts to {:?}",
+                    snapshot.balances.len(),
+                    o.out
+                );
+            }
+            Command::Diff(o) => {
+                let from = Snapshot::load(&o.from).await?;
+                let to = Snapshot::load(&o.to).await?;
+                let diff = from.diff(&to);
+                if o.json {
+                    println!("{}", serde_json::to_string_pretty(&diff)?);
+                } else if o.ndjson {
+                    let mut out = Ndjson::stdout();
+                    for flow in &diff.flows {
+                        out.write(flow)?;
+                    }
+                    out.flush()?;
+                } else {
+                    print!("{}", diff);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl BalancesOpts {
+    async fn snapshot(&self) -> anyhow::Result<Snapshot> {
+        #[cfg(feature = "rpc_client")]
+        if let Some(url) = &self.url {
+            let client = crate::rpc::client::RPCClient::new(url);
+            return Snapshot::from_rpc(&client, &self.accounts).await;
+        }
+
+        #[cfg(feature = "node")]
+        {
+            use crate::node::{ArcState, SledDiskState};
+            use std::sync::Arc;
+
+            let path = self
+                .db
+                .to_owned()
+                .unwrap_or_else(|| SledDiskState::default_path(self.network));
+            if !path.exists() {
+                return Err(anyhow::anyhow!("Database {:?} does not exist", path));
+            }
+            let state = if self.read_only {
+                SledDiskState::open_read_only(self.network, &path)?
+            } else {
+                SledDiskState::open(self.network, &path)?
+            };
+            let state: ArcState = Arc::new(state);
+            Snapshot::from_state(&state).await
+        }
+
+        #[cfg(not(feature = "node"))]
+        Err(anyhow::anyhow!(
+            "Compile with the `node` feature to take balances from a node database."
+        ))
+    }
+}
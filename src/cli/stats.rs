@@ -0,0 +1,71 @@
+use crate::rpc::calls::{DifficultyStatsRequest, LedgerStatsRequest};
+use crate::rpc::client::{RPCClient, RPCRequest};
+use clap::Clap;
+
+#[derive(Clap)]
+pub(crate) struct StatsOpts {
+    #[clap(subcommand)]
+    command: Command,
+}
+
+#[derive(Clap)]
+enum Command {
+    /// Check the supply and count accounts by balance, by how long they've been dormant, and
+    /// blocks by type. The ledger is walked one account at a time by the running node.
+    Ledger(CommonOpts),
+
+    /// Show the spread of work multipliers of recently added blocks, to help choose what
+    /// multiplier to generate work for.
+    Difficulty(DifficultyOpts),
+}
+
+#[derive(Clap)]
+struct CommonOpts {
+    /// The URL of the node's RPC server.
+    #[clap(
+        long,
+        short,
+        default_value = "http://localhost:7076",
+        env = "FEELESS_RPC_URL"
+    )]
+    url: String,
+
+    /// Output as JSON.
+    #[clap(long)]
+    json: bool,
+}
+
+#[derive(Clap)]
+struct DifficultyOpts {
+    #[clap(flatten)]
+    opts: CommonOpts,
+
+    #[clap(flatten)]
+    request: DifficultyStatsRequest,
+}
+
+impl StatsOpts {
+    pub async fn handle(&self) -> anyhow::Result<()> {
+        match &self.command {
+            Command::Ledger(o) => {
+                let stats = (&LedgerStatsRequest {})
+                    .call(&RPCClient::new(&o.url))
+                    .await?;
+                if o.json {
+                    println!("{}", serde_json::to_string_pretty(&stats)?);
+                } else {
+                    print!("{}", stats);
+                }
+            }
+            Command::Difficulty(o) => {
+                let stats = (&o.request).call(&RPCClient::new(&o.opts.url)).await?;
+                if o.opts.json {
+                    println!("{}", serde_json::to_string_pretty(&stats)?);
+                } else {
+                    print!("{}", stats);
+                }
+            }
+        }
+        Ok(())
+    }
+}
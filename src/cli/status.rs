@@ -0,0 +1,42 @@
+use crate::rpc::calls::{BootstrapStatusRequest, SyncStatusRequest};
+use crate::rpc::client::{RPCClient, RPCRequest};
+use clap::Clap;
+use serde_json::json;
+
+#[derive(Clap)]
+pub(crate) struct StatusOpts {
+    /// The URL of the node's RPC server.
+    #[clap(
+        long,
+        short,
+        default_value = "http://localhost:7076",
+        env = "FEELESS_RPC_URL"
+    )]
+    url: String,
+
+    /// Output as JSON.
+    #[clap(long)]
+    json: bool,
+}
+
+impl StatusOpts {
+    pub async fn handle(&self) -> anyhow::Result<()> {
+        let client = RPCClient::new(&self.url);
+        let sync = (&SyncStatusRequest {}).call(&client).await?;
+        let bootstrap = (&BootstrapStatusRequest {}).call(&client).await?;
+        if self.json {
+            let status = json!({ "sync": sync, "bootstrap": bootstrap });
+            println!("{}", serde_json::to_string_pretty(&status)?);
+            return Ok(());
+        }
+
+        print!("{}", sync);
+        if bootstrap.running {
+            println!(
+                "Bootstrap: {}/{} ranges, {} frontiers",
+                bootstrap.ranges_done, bootstrap.ranges_total, bootstrap.frontiers
+            );
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,34 @@
+use crate::node::StressTest;
+use clap::Clap;
+use std::time::Duration;
+
+/// Blocks are created on a private test network in memory, without generating work, so this
+/// measures validating and storing blocks rather than the network or elections.
+#[derive(Clap)]
+pub(crate) struct StressOpts {
+    /// Blocks to create per second, counting sends and receives.
+    #[clap(long, default_value = "500")]
+    tps: u32,
+
+    /// Number of funded accounts to send between.
+    #[clap(long, default_value = "1000")]
+    accounts: u32,
+
+    /// How many seconds to create blocks for.
+    #[clap(long, default_value = "30")]
+    duration: u64,
+}
+
+impl StressOpts {
+    pub async fn handle(&self) -> anyhow::Result<()> {
+        let report = StressTest {
+            tps: self.tps,
+            accounts: self.accounts,
+            duration: Duration::from_secs(self.duration),
+        }
+        .run()
+        .await?;
+        print!("{}", report);
+        Ok(())
+    }
+}
@@ -0,0 +1,78 @@
+use crate::node::{sync_from, ArcState, ChainBootstrap, FrontierBootstrap, SledDiskState};
+use crate::{CancellationToken, Network};
+use anyhow::anyhow;
+use clap::Clap;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tracing::info;
+
+/// The databases are opened directly, so neither can be in use by a running node.
+#[derive(Clap)]
+pub(crate) struct SyncFromOpts {
+    /// The database to copy blocks from, e.g. a data directory copied from another machine, or
+    /// the IP:PORT of a running node to pull them from, optionally starting with `tcp://`.
+    source: String,
+
+    #[clap(short = 'n', long, default_value = "live", env = "FEELESS_NETWORK")]
+    network: Network,
+
+    /// The database to copy blocks into. Defaults to the database the node uses for this
+    /// network.
+    #[clap(long)]
+    db: Option<PathBuf>,
+}
+
+impl SyncFromOpts {
+    pub async fn handle(&self) -> anyhow::Result<()> {
+        let source = self.source.trim_start_matches("tcp://");
+        let peer = if Path::new(source).exists() {
+            None
+        } else {
+            Some(source.parse::<SocketAddr>().map_err(|_| {
+                anyhow!(
+                    "{} is neither a database nor the IP:PORT of a node",
+                    self.source
+                )
+            })?)
+        };
+
+        let path = self
+            .db
+            .to_owned()
+            .unwrap_or_else(|| SledDiskState::default_path(self.network));
+        let destination = SledDiskState::open(self.network, &path)?;
+        let flush = destination.clone();
+        let destination: ArcState = Arc::new(destination);
+
+        let peer = match peer {
+            Some(peer) => peer,
+            None => {
+                let source: ArcState =
+                    Arc::new(SledDiskState::open(self.network, source.as_ref())?);
+                let stats = sync_from(&source, &destination, self.network).await?;
+                flush.flush().await?;
+                print!("{}", stats);
+                return Ok(());
+            }
+        };
+
+        // The node's frontiers are compared with ours, and the blocks above ours are pulled
+        // and checked, like a bootstrap from just this node.
+        let cancel = CancellationToken::new();
+        let frontiers = FrontierBootstrap::new(self.network, vec![peer], 4)
+            .run(&cancel)
+            .await?;
+        info!(
+            "Pulling the chains of {} accounts from {}",
+            frontiers.len(),
+            peer
+        );
+        let result = ChainBootstrap::new(self.network, vec![peer], destination, frontiers)
+            .run(&cancel)
+            .await;
+        flush.flush().await?;
+        print!("{}", result?);
+        Ok(())
+    }
+}
@@ -0,0 +1,33 @@
+use crate::rpc::calls::PeerTelemetryRequest;
+use crate::rpc::client::{RPCClient, RPCRequest};
+use clap::Clap;
+
+#[derive(Clap)]
+pub(crate) struct TelemetryOpts {
+    /// The URL of the node's RPC server.
+    #[clap(
+        long,
+        short,
+        default_value = "http://localhost:7076",
+        env = "FEELESS_RPC_URL"
+    )]
+    url: String,
+
+    /// Output as JSON.
+    #[clap(long)]
+    json: bool,
+}
+
+impl TelemetryOpts {
+    pub async fn handle(&self) -> anyhow::Result<()> {
+        let telemetry = (&PeerTelemetryRequest {})
+            .call(&RPCClient::new(&self.url))
+            .await?;
+        if self.json {
+            println!("{}", serde_json::to_string_pretty(&telemetry)?);
+        } else {
+            print!("{}", telemetry);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,191 @@
+use crate::cli::wallet::{work_thresholds, CommonOpts};
+use crate::rpc::client::RPCClient;
+use crate::units::parse_amount;
+use crate::wallet::{AuditEntry, AuditLog, AuditOperation, Payer, RPCPayer, WalletId};
+use crate::{Private, Raw, Seed};
+use clap::Clap;
+use colored_json::ToColoredJson;
+use std::str::FromStr;
+
+/// Pending blocks to receive in one go with `--dry-run`, since nothing changes between batches.
+const DRY_RUN_RECEIVE_LIMIT: u64 = 1000;
+
+#[derive(Clap)]
+pub(crate) struct SendOpts {
+    /// Address or `@contact` to send to.
+    to: String,
+
+    /// Amount with a unit, e.g. `10mnano`, `2.5nano` or `1000raw`.
+    #[clap(parse(try_from_str = parse_amount))]
+    amount: Raw,
+
+    #[clap(flatten)]
+    account: AccountOpts,
+}
+
+#[derive(Clap)]
+pub(crate) struct ReceiveOpts {
+    /// Leave blocks of less than this amount waiting, so dust isn't worth sending, e.g.
+    /// `1mnano`.
+    #[clap(long, default_value = "1raw", parse(try_from_str = parse_amount))]
+    minimum: Raw,
+
+    #[clap(flatten)]
+    account: AccountOpts,
+}
+
+/// The account to send from or receive to, and the RPC server to do it through.
+#[derive(Clap)]
+struct AccountOpts {
+    /// Index of the account in the wallet or seed.
+    #[clap(short, long, default_value = "0", parse(try_from_str = crate::cli::parse::index))]
+    address: u32,
+
+    /// Use an account of this seed instead of a wallet.
+    #[clap(long, env = "FEELESS_SEED", hide_env_values = true)]
+    seed: Option<String>,
+
+    /// Use this private key instead of a wallet.
+    #[clap(
+        long,
+        env = "FEELESS_PRIVATE_KEY",
+        hide_env_values = true,
+        conflicts_with = "seed"
+    )]
+    private: Option<String>,
+
+    /// The URL of the RPC server to get the account info from and send blocks through.
+    #[clap(
+        long,
+        short,
+        default_value = "http://localhost:7076",
+        env = "FEELESS_RPC_URL"
+    )]
+    url: String,
+
+    /// Send a string in the HTTP authorization header.
+    #[clap(long, env = "FEELESS_RPC_AUTH")]
+    auth: Option<String>,
+
+    #[clap(flatten)]
+    opts: CommonOpts,
+}
+
+impl SendOpts {
+    pub async fn handle(&self, dry_run: bool) -> anyhow::Result<()> {
+        let to = self.account.opts.manager()?.resolve(&self.to).await?;
+        let payer = self.account.payer().await?.dry_run(dry_run);
+        let (private, wallet_id) = self.account.private().await?;
+        let result = payer.pay(&private, &to, &self.amount).await;
+        if dry_run {
+            result?;
+            return show_rehearsed(&payer);
+        }
+
+        // Sends from a wallet are recorded in the audit log, whether they went through or not.
+        if let Some(wallet_id) = wallet_id {
+            let mut entry = AuditEntry::new(wallet_id, AuditOperation::Broadcast, (&result).into())
+                .amount(self.amount.to_owned())
+                .destination(to);
+            entry.account = private.to_address().ok();
+            entry.block = result.as_ref().ok().cloned();
+            entry.rpc = payer.describe();
+            self.account.audit(&entry).await?;
+        }
+
+        println!("{}", result?);
+        Ok(())
+    }
+}
+
+impl ReceiveOpts {
+    pub async fn handle(&self, dry_run: bool) -> anyhow::Result<()> {
+        let payer = self.account.payer().await?.dry_run(dry_run);
+        let (private, wallet_id) = self.account.private().await?;
+        if dry_run {
+            payer
+                .receive_pending(&private, &self.minimum, DRY_RUN_RECEIVE_LIMIT)
+                .await?;
+            return show_rehearsed(&payer);
+        }
+
+        // One block at a time, so each one can be recorded in the audit log as it's received.
+        let mut received = 0;
+        loop {
+            let result = payer.receive_pending(&private, &self.minimum, 1).await;
+            if let Some(wallet_id) = &wallet_id {
+                let mut entry = AuditEntry::new(
+                    wallet_id.to_owned(),
+                    AuditOperation::Broadcast,
+                    (&result).into(),
+                );
+                entry.account = private.to_address().ok();
+                entry.rpc = payer.describe();
+                match &result {
+                    Ok(blocks) if blocks.is_empty() => break,
+                    Ok(blocks) => {
+                        entry.block = Some(blocks[0].0.to_owned());
+                        entry.amount = Some(blocks[0].1.to_owned());
+                    }
+                    Err(_) => {}
+                }
+                self.account.audit(&entry).await?;
+            }
+
+            let blocks = result?;
+            if blocks.is_empty() {
+                break;
+            }
+            for (hash, amount) in blocks {
+                println!("{} {} raw", hash, amount);
+                received += 1;
+            }
+        }
+        println!("Received {} blocks", received);
+        Ok(())
+    }
+}
+
+/// Print the requests a dry run would have sent.
+fn show_rehearsed(payer: &RPCPayer) -> anyhow::Result<()> {
+    let rehearsed = payer.rehearsed();
+    for request in &rehearsed {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(request)?.to_colored_json_auto()?
+        );
+    }
+    eprintln!("Dry run: {} blocks weren't broadcast", rehearsed.len());
+    Ok(())
+}
+
+impl AccountOpts {
+    async fn payer(&self) -> anyhow::Result<RPCPayer> {
+        let mut client = RPCClient::new(&self.url);
+        if let Some(auth) = &self.auth {
+            client.authorization(auth);
+        }
+        Ok(RPCPayer::new(client, self.url.to_owned())
+            .thresholds(work_thresholds(&self.opts.paths_opts).await?))
+    }
+
+    /// The key of the account, and the wallet it's from when it isn't given as a seed or private
+    /// key.
+    async fn private(&self) -> anyhow::Result<(Private, Option<WalletId>)> {
+        if let Some(seed) = &self.seed {
+            return Ok((Seed::from_str(seed)?.derive(self.address), None));
+        }
+        if let Some(private) = &self.private {
+            return Ok((Private::from_str(private)?, None));
+        }
+        let wallet_id = self.opts.wallet_id().await?;
+        let wallet = self.opts.manager()?.wallet(&wallet_id).await?;
+        Ok((wallet.private(self.address)?, Some(wallet_id)))
+    }
+
+    async fn audit(&self, entry: &AuditEntry) -> anyhow::Result<()> {
+        AuditLog::new(self.opts.paths_opts.audit_log_path()?)
+            .record(entry)
+            .await
+    }
+}
@@ -1,26 +1,9 @@
 use crate::cli::StringOrStdin;
-use crate::units::{Mnano, Nano, UnboundedRaw};
+use crate::units::{AmountFormat, Knano, Locale, Mnano, Nano, UnboundedRaw};
+use crate::Error;
 use clap::Clap;
 use std::str::FromStr;
 
-macro_rules! raw {
-    ($dst:expr) => {
-        UnboundedRaw::from_str(&$dst.resolve()?)?
-    };
-}
-
-macro_rules! nano {
-    ($dst:expr) => {
-        Nano::from_str(&$dst.resolve()?)?
-    };
-}
-
-macro_rules! mnano {
-    ($dst:expr) => {
-        Mnano::from_str(&$dst.resolve()?)?
-    };
-}
-
 #[derive(Clap)]
 pub(crate) struct UnitOpts {
     #[clap(subcommand)]
@@ -29,29 +12,24 @@ pub(crate) struct UnitOpts {
 
 impl UnitOpts {
     pub fn handle(&self) -> anyhow::Result<()> {
-        // This is a bit unwieldy because of the "from to amount" structure.
-        // Not sure how to improve.
-        let src = match &self.command {
-            SrcUnit::Raw(src) => match &src.dst {
-                DstCommand::Raw(dst) => raw!(dst).to_unbounded_raw().to_string(),
-                DstCommand::Nano(dst) => raw!(dst).to_nano().to_string(),
-                DstCommand::Mnano(dst) => raw!(dst).to_mnano().to_string(),
-            }
-            .to_string(),
-            SrcUnit::Nano(src) => match &src.dst {
-                DstCommand::Raw(dst) => nano!(dst).to_unbounded_raw().to_string(),
-                DstCommand::Nano(dst) => nano!(dst).to_nano().to_string(),
-                DstCommand::Mnano(dst) => nano!(dst).to_mnano().to_string(),
-            }
-            .to_string(),
-            SrcUnit::Mnano(src) => match &src.dst {
-                DstCommand::Raw(dst) => mnano!(dst).to_unbounded_raw().to_string(),
-                DstCommand::Nano(dst) => mnano!(dst).to_nano().to_string(),
-                DstCommand::Mnano(dst) => mnano!(dst).to_mnano().to_string(),
-            }
-            .to_string(),
+        // Every source unit is converted through raw, so each destination only needs one arm.
+        type Parse = fn(&str) -> Result<UnboundedRaw, Error>;
+        let (dst, parse): (&DstCommand, Parse) = match &self.command {
+            SrcUnit::Raw(src) => (&src.dst, |s| UnboundedRaw::from_str(s)),
+            SrcUnit::Nano(src) => (&src.dst, |s| Ok(Nano::from_str(s)?.to_unbounded_raw())),
+            SrcUnit::Knano(src) => (&src.dst, |s| Ok(Knano::from_str(s)?.to_unbounded_raw())),
+            SrcUnit::Mnano(src) => (&src.dst, |s| Ok(Mnano::from_str(s)?.to_unbounded_raw())),
+        };
+        let opts = dst.opts();
+        let raw = parse(&opts.resolve()?)?;
+        let format = opts.format()?;
+        let amount = match dst {
+            DstCommand::Raw(_) => raw.format(&format),
+            DstCommand::Nano(_) => raw.to_nano().format(&format),
+            DstCommand::Knano(_) => raw.to_knano().format(&format),
+            DstCommand::Mnano(_) => raw.to_mnano().format(&format),
         };
-        println!("{}", src);
+        println!("{}", amount);
         Ok(())
     }
 }
@@ -61,10 +39,13 @@ enum SrcUnit {
     /// From a raw amount.
     Raw(Dst),
 
-    /// From a Nano amount
+    /// From a nano amount.
     Nano(Dst),
 
-    /// From a Cents amount
+    /// From a knano (1000 nano) amount.
+    Knano(Dst),
+
+    /// From a Mnano/NANO/Nano amount.
     Mnano(Dst),
 }
 
@@ -82,6 +63,9 @@ enum DstCommand {
     /// Convert to a nano amount.
     Nano(Opts),
 
+    /// Convert to a knano (1000 nano) amount.
+    Knano(Opts),
+
     /// Convert to a Mnano/NANO/Nano amount
     Mnano(Opts),
 }
@@ -89,10 +73,49 @@ enum DstCommand {
 #[derive(Clap)]
 struct Opts {
     amount: StringOrStdin<String>,
+
+    /// Show at most this many decimal places, cutting off the rest.
+    #[clap(long)]
+    decimals: Option<usize>,
+
+    /// Pad to `--decimals` places with zeros instead of trimming trailing zeros.
+    #[clap(long, requires = "decimals")]
+    keep_zeros: bool,
+
+    /// Group digits and separate the fraction for a locale: plain, en, de, fr, ch, or env to
+    /// use the locale in LC_ALL, LC_NUMERIC or LANG.
+    #[clap(long, default_value = "plain")]
+    locale: String,
+}
+
+impl DstCommand {
+    fn opts(&self) -> &Opts {
+        match self {
+            DstCommand::Raw(opts)
+            | DstCommand::Nano(opts)
+            | DstCommand::Knano(opts)
+            | DstCommand::Mnano(opts) => opts,
+        }
+    }
 }
 
 impl Opts {
     fn resolve(&self) -> anyhow::Result<String> {
         self.amount.to_owned().resolve()
     }
+
+    fn format(&self) -> anyhow::Result<AmountFormat> {
+        let locale = match self.locale.as_str() {
+            "env" => Locale::from_env(),
+            locale => Locale::from_str(locale)?,
+        };
+        let mut format = AmountFormat::new().locale(locale);
+        if let Some(decimals) = self.decimals {
+            format = format.decimals(decimals);
+        }
+        if self.keep_zeros {
+            format = format.keep_zeros();
+        }
+        Ok(format)
+    }
 }
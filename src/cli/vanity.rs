@@ -1,8 +1,15 @@
+use crate::cli::ndjson::Ndjson;
+use crate::cli::phrase::WrappedMnemonicType;
+use crate::phrase::Language;
 use crate::vanity;
-use crate::vanity::Secret;
+use crate::vanity::{Progress, Secret};
+use crate::{Address, CancellationToken};
+use anyhow::Context;
 use clap::Clap;
-use std::sync::{Arc, RwLock};
-use tokio::time::{timeout, Duration, Instant};
+use serde::Serialize;
+use std::path::PathBuf;
+use std::time::Duration;
+use tokio::sync::mpsc::Receiver;
 
 #[derive(Clap)]
 pub struct VanityOpts {
@@ -15,8 +22,8 @@ impl VanityOpts {
         let (secret_type, opts) = match &self.secret_type {
             VanitySecretType::Phrase(phrase) => {
                 let secret_type = vanity::SecretType::Phrase {
-                    language: phrase.phrase_opts.language.language.to_owned(),
-                    words: phrase.phrase_opts.words.0,
+                    language: phrase.language.to_owned(),
+                    words: phrase.words.0,
                 };
                 (secret_type, &phrase.common_opts)
             }
@@ -26,38 +33,87 @@ impl VanityOpts {
             }
         };
 
-        let matches = if opts.start {
-            vanity::Match::start(&opts.matching)
-        } else if opts.end {
-            vanity::Match::end(&opts.matching)
-        } else if opts.regex {
-            vanity::Match::regex(&opts.matching)?
-        } else {
-            vanity::Match::start_or_end(&opts.matching)
-        };
+        let mut patterns = opts.matching.to_owned();
+        if let Some(file) = &opts.file {
+            let words = std::fs::read_to_string(file)
+                .with_context(|| format!("Reading patterns from {:?}", file))?;
+            patterns.extend(
+                words
+                    .lines()
+                    .map(str::trim)
+                    .filter(|word| !word.is_empty())
+                    .map(String::from),
+            );
+        }
+        let mut matches = vec![];
+        for pattern in &patterns {
+            let exact = if opts.start {
+                vanity::Match::start(pattern)
+            } else if opts.end {
+                vanity::Match::end(pattern)
+            } else if opts.regex {
+                vanity::Match::regex(pattern)?
+            } else {
+                vanity::Match::start_or_end(pattern)
+            };
+            matches.push(if opts.fuzzy {
+                vanity::Match::fuzzy(exact)?
+            } else {
+                exact
+            });
+        }
 
-        let mut vanity = vanity::Vanity::new(secret_type, matches);
+        let mut vanity = vanity::Vanity::any_of(secret_type, matches);
         if let Some(tasks) = opts.tasks {
             vanity.tasks(tasks);
         }
         if opts.include_digit {
             vanity.include_first_digit(true);
         }
-
-        let (mut rx, attempts) = vanity.start().await?;
-        let started = Instant::now();
-        let mut last_log = Instant::now();
+        vanity.indexes(opts.indexes);
+
+        let mut out = Ndjson::stdout();
+        let cancel = CancellationToken::new();
+        let (mut rx, mut progress) = if opts.stats_interval > 0. {
+            let interval = Duration::from_secs_f64(opts.stats_interval);
+            let (rx, progress) = vanity.start_with_progress(interval, &cancel).await?;
+            (rx, Some(progress))
+        } else {
+            (vanity.start(&cancel).await?.0, None)
+        };
         let mut found = 0;
         loop {
-            match timeout(Duration::from_secs(1), rx.recv()).await {
-                Ok(Some(result)) => {
+            tokio::select! {
+                result = rx.recv() => {
+                    // Channel closed.
+                    let result = match result {
+                        Some(result) => result,
+                        None => break,
+                    };
                     let s = match result.secret {
                         Secret::Phrase(p) => p.to_string(),
                         Secret::Seed(s) => s.to_string(),
                         Secret::Private(p) => p.to_string(),
                     };
-                    println!("{},{}", result.address.to_string(), s);
-                    last_log = log(started, last_log, attempts.clone()).await;
+                    if opts.ndjson {
+                        out.write(&Found {
+                            address: &result.address,
+                            secret: &s,
+                            pattern: result.matched.as_str(),
+                            hit: &result.hit,
+                            index: result.index,
+                        })?;
+                        out.flush()?;
+                    } else {
+                        let mut line = format!("{},{}", result.address, s);
+                        if patterns.len() > 1 || opts.fuzzy {
+                            line = format!("{},{}", line, result.hit);
+                        }
+                        if opts.indexes > 1 {
+                            line = format!("{},{}", line, result.index);
+                        }
+                        println!("{}", line);
+                    }
                     found += 1;
                     if let Some(limit) = opts.limit {
                         if limit == found {
@@ -65,33 +121,35 @@ impl VanityOpts {
                         }
                     }
                 }
-                // Channel closed.
-                Ok(None) => {
-                    break;
-                }
-                // Timeout
-                Err(_) => {
-                    last_log = log(started, last_log, attempts.clone()).await;
+                Some(progress) = next_progress(&mut progress) => {
+                    eprintln!("{}", progress);
                 }
             }
         }
+        cancel.cancel();
         Ok(())
     }
 }
 
-async fn log(started: Instant, last_log: Instant, attempts: Arc<RwLock<usize>>) -> Instant {
-    let now = Instant::now();
-    let since_last_log = now.duration_since(last_log);
-    if since_last_log < Duration::from_secs(1) {
-        last_log
-    } else {
-        let total_taken = Instant::now().duration_since(started);
-        let attempts = *attempts
-            .read()
-            .expect("Could not unlock attempts for reading.");
-        let rate = (attempts as f64) / total_taken.as_secs_f64();
-        eprintln!("Attempted: {}, Rate: {:?} attempts/s", attempts, rate);
-        now
+/// A line of `--ndjson` output.
+#[derive(Serialize)]
+struct Found<'a> {
+    address: &'a Address,
+    secret: &'a str,
+    pattern: &'a str,
+
+    /// How a `--fuzzy` pattern was spelt in the address.
+    hit: &'a str,
+
+    /// The derivation index of the address.
+    index: u32,
+}
+
+/// The next progress report, or never when they're turned off.
+async fn next_progress(progress: &mut Option<Receiver<Progress>>) -> Option<Progress> {
+    match progress {
+        Some(progress) => progress.recv().await,
+        None => std::future::pending().await,
     }
 }
 
@@ -107,8 +165,14 @@ enum VanitySecretType {
 
 #[derive(Clap)]
 struct PhraseOpts {
-    #[clap(flatten)]
-    pub phrase_opts: super::phrase::New,
+    /// Number of words. Possible values are: 12, 15, 18, 21, 24.
+    #[clap(short, long, default_value = "24")]
+    pub words: WrappedMnemonicType,
+
+    /// Word list language: en, zh-hans, zh-hant, fr, it, ja, ko, es
+    // Not `-l`, which is `--limit` here.
+    #[clap(long, default_value = "en")]
+    pub language: Language,
 
     #[clap(flatten)]
     pub common_opts: CommonOpts,
@@ -128,8 +192,14 @@ struct PrivateOpts {
 
 #[derive(Clap)]
 struct CommonOpts {
-    /// Match on this string. By default will match the start and end.
-    matching: String,
+    /// Match on these strings, reporting which one each address matched. By default will match
+    /// the start and end.
+    #[clap(required_unless_present = "file")]
+    matching: Vec<String>,
+
+    /// Also match on each line of this file, e.g. a list of words.
+    #[clap(short, long)]
+    file: Option<PathBuf>,
 
     /// Match on start only. Default is start and end.
     #[clap(short, long, group = "match")]
@@ -143,10 +213,19 @@ struct CommonOpts {
     #[clap(short, long, group = "match")]
     regex: bool,
 
+    /// Also match spellings with lookalike characters in any case, e.g. `nano` matches `n4no`.
+    #[clap(long, conflicts_with = "regex")]
+    fuzzy: bool,
+
     /// Also match against the first digit (1 or 3) after `nano_`.
     #[clap(short, long)]
     include_digit: bool,
 
+    /// Check this many addresses of each seed or phrase, from derivation index 0. Much faster for
+    /// phrases, which are slow to generate.
+    #[clap(long, default_value = "1")]
+    indexes: u32,
+
     /// Number of parallel tasks to use. Default: Your logical processors minus one, or at least 1.
     #[clap(short, long)]
     tasks: Option<usize>,
@@ -154,4 +233,14 @@ struct CommonOpts {
     /// Stop after finding this many matches.
     #[clap(short, long)]
     limit: Option<usize>,
+
+    /// Seconds between progress reports on stderr, with the rate and an estimated time to the
+    /// next match. 0 turns them off.
+    #[clap(long, default_value = "1")]
+    stats_interval: f64,
+
+    /// Output each match as a line of JSON instead of `address,secret`, followed by `,match`
+    /// when there's more than one pattern or `--fuzzy`, and `,index` when `--indexes` is over 1.
+    #[clap(long)]
+    ndjson: bool,
 }
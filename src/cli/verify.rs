@@ -1,5 +1,5 @@
 use crate::keys::armor::Armor;
-use crate::{Address, Public, Signature};
+use crate::{Address, OwnershipProof, Public, Signature};
 use anyhow::anyhow;
 use clap::Clap;
 use std::io;
@@ -8,10 +8,10 @@ use std::str::FromStr;
 
 #[derive(Clap)]
 pub struct VerifyOpts {
-    #[clap(short, long, group = "pub")]
+    #[clap(short, long, group = "pub", parse(try_from_str = crate::cli::parse::address))]
     address: Option<Address>,
 
-    #[clap(short, long, group = "pub")]
+    #[clap(short, long, group = "pub", parse(try_from_str = crate::cli::parse::public))]
     public: Option<Public>,
 
     #[clap(short, long)]
@@ -22,12 +22,22 @@ pub struct VerifyOpts {
 
     #[clap(long)]
     armor: bool,
+
+    /// Verify a JSON address ownership proof from stdin.
+    #[clap(long, conflicts_with = "armor")]
+    proof: bool,
+
+    /// The nonce that the ownership proof must have been made for.
+    #[clap(long, requires = "proof")]
+    nonce: Option<String>,
 }
 
 impl VerifyOpts {
     pub(crate) fn handle(&self) -> anyhow::Result<()> {
         if self.armor {
             self.handle_armor()?;
+        } else if self.proof {
+            self.handle_proof()?;
         } else {
             self.handle_args()?;
         }
@@ -44,6 +54,17 @@ impl VerifyOpts {
         Ok(())
     }
 
+    fn handle_proof(&self) -> anyhow::Result<()> {
+        let mut buffer = String::new();
+        io::stdin().read_to_string(&mut buffer)?;
+        let proof: OwnershipProof = serde_json::from_str(&buffer)?;
+        match &self.nonce {
+            Some(nonce) => proof.verify_nonce(nonce)?,
+            None => proof.verify()?,
+        }
+        Ok(())
+    }
+
     fn handle_args(&self) -> anyhow::Result<()> {
         let message = if let Some(message) = &self.message {
             message
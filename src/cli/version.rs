@@ -0,0 +1,21 @@
+use crate::build_info;
+use clap::Clap;
+
+#[derive(Clap)]
+pub(crate) struct VersionOpts {
+    /// Print the build info as a JSON object.
+    #[clap(long)]
+    json: bool,
+}
+
+impl VersionOpts {
+    pub fn handle(&self) -> anyhow::Result<()> {
+        let info = build_info();
+        if self.json {
+            println!("{}", serde_json::to_string_pretty(&info)?);
+        } else {
+            print!("{}", info);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,68 @@
+use crate::node::VoteStore;
+use crate::Network;
+use anyhow::anyhow;
+use clap::Clap;
+use std::path::PathBuf;
+
+#[derive(Clap)]
+pub(crate) struct VotesOpts {
+    #[clap(subcommand)]
+    command: Command,
+}
+
+#[derive(Clap)]
+enum Command {
+    /// Print every vote we've made as a representative, oldest first, one JSON object per line.
+    Export(CommonOpts),
+
+    /// Check that no vote contradicts an earlier final vote. This also happens when the node
+    /// starts.
+    Check(CommonOpts),
+}
+
+#[derive(Clap)]
+struct CommonOpts {
+    #[clap(short = 'n', long, default_value = "live", env = "FEELESS_NETWORK")]
+    network: Network,
+
+    /// Path to the vote store. Defaults to the one the node uses for this network.
+    #[clap(long)]
+    db: Option<PathBuf>,
+}
+
+impl CommonOpts {
+    fn open(&self) -> anyhow::Result<VoteStore> {
+        let path = self
+            .db
+            .to_owned()
+            .unwrap_or_else(|| VoteStore::default_path(self.network));
+        if !path.exists() {
+            return Err(anyhow!("Vote store {:?} does not exist", path));
+        }
+        VoteStore::open(&path)
+    }
+}
+
+impl VotesOpts {
+    pub fn handle(&self) -> anyhow::Result<()> {
+        match &self.command {
+            Command::Export(o) => {
+                for vote in o.open()?.votes() {
+                    println!("{}", serde_json::to_string(&vote?)?);
+                }
+            }
+            Command::Check(o) => {
+                let check = o.open()?.check()?;
+                println!("Votes: {}", check.votes);
+                println!("Final votes: {}", check.final_votes);
+                for conflict in &check.conflicts {
+                    println!("Conflict: {}", conflict);
+                }
+                if !check.conflicts.is_empty() {
+                    return Err(anyhow!("Found {} conflicts", check.conflicts.len()));
+                }
+            }
+        }
+        Ok(())
+    }
+}
@@ -1,9 +1,17 @@
 use crate::cli::StringOrStdin;
 use crate::keys::armor::Armor;
 use crate::paths::PathsOpts;
-use crate::wallet::{Wallet, WalletId, WalletManager};
-use crate::Phrase;
+use crate::units::parse_amount;
+use crate::wallet::{
+    AuditEntry, AuditLog, AuditOperation, Interval, ScheduledPayment, Wallet, WalletId,
+    WalletManager,
+};
+use crate::{Address, OwnershipProof, Phrase, Raw};
+use anyhow::anyhow;
+use chrono::{DateTime, Duration, Utc};
 use clap::Clap;
+use std::fmt::Display;
+use std::str::FromStr;
 
 #[derive(Clap)]
 pub struct WalletOpts {
@@ -24,17 +32,17 @@ impl WalletOpts {
                             o.phrase_opts.language.language.to_owned(),
                         )
                         .await?;
-                    println!("{}", wallet_id);
+                    WalletOpts::created(&o.opts, &manager, &wallet_id).await?;
                 }
                 CreateType::Seed(o) => {
                     let (manager, wallet_id) = WalletOpts::create(&o.opts).await?;
                     manager.add_random_seed(wallet_id.to_owned()).await?;
-                    println!("{}", wallet_id);
+                    WalletOpts::created(&o.opts, &manager, &wallet_id).await?;
                 }
                 CreateType::Private(o) => {
                     let (manager, wallet_id) = WalletOpts::create(&o.opts).await?;
                     manager.add_random_private(wallet_id.to_owned()).await?;
-                    println!("{}", wallet_id);
+                    WalletOpts::created(&o.opts, &manager, &wallet_id).await?;
                 }
             },
             Command::Import(o) => match &o.create_type {
@@ -46,21 +54,37 @@ impl WalletOpts {
                     )?;
                     let wallet = Wallet::Phrase(phrase);
                     manager.add(wallet_id.to_owned(), wallet).await?;
-                    println!("{}", wallet_id);
+                    WalletOpts::created(&o.opts, &manager, &wallet_id).await?;
                 }
                 ImportType::Seed(o) => {
                     let (manager, wallet_id) = WalletOpts::create(&o.opts).await?;
                     let wallet = Wallet::Seed(o.seed.to_owned().resolve()?);
                     manager.add(wallet_id.to_owned(), wallet).await?;
-                    println!("{}", wallet_id);
+                    WalletOpts::created(&o.opts, &manager, &wallet_id).await?;
                 }
                 ImportType::Private(o) => {
                     let (manager, wallet_id) = WalletOpts::create(&o.opts).await?;
                     let wallet = Wallet::Private(o.private.to_owned().resolve()?);
                     manager.add(wallet_id.to_owned(), wallet).await?;
-                    println!("{}", wallet_id);
+                    WalletOpts::created(&o.opts, &manager, &wallet_id).await?;
                 }
             },
+            Command::List(o) => {
+                let manager = o.passphrase_opts.manager(&o.paths_opts)?;
+                for wallet in manager.list().await? {
+                    if o.json {
+                        println!("{}", serde_json::to_string(&wallet)?);
+                    } else {
+                        let line = format!(
+                            "{} {:7} {}",
+                            wallet.id,
+                            wallet.kind,
+                            wallet.name.as_deref().unwrap_or_default()
+                        );
+                        println!("{}", line.trim_end());
+                    }
+                }
+            }
             Command::Delete(o) => {
                 let (manager, wallet_id) = WalletOpts::delete(&o.opts).await?;
                 manager.delete(&wallet_id).await?;
@@ -82,38 +106,177 @@ impl WalletOpts {
                 let wallet = WalletOpts::read(&o.opts).await?;
                 let string = o.message.to_owned().resolve()?;
                 let message = string.as_bytes();
-                let signed = wallet.private(o.address)?.sign(message)?;
+                let signed = wallet.private(o.address).and_then(|p| p.sign(message));
+                WalletOpts::audit(&o.opts, &wallet, o.address, AuditOperation::Sign, &signed)
+                    .await?;
+                let signed = signed?;
                 if o.armor {
                     println!("{}", Armor::new(string, wallet.address(o.address)?, signed));
                 } else {
                     println!("{}", signed);
                 }
             }
+            Command::Prove(o) => {
+                let wallet = WalletOpts::read(&o.opts).await?;
+                let nonce = o
+                    .nonce
+                    .to_owned()
+                    .unwrap_or_else(OwnershipProof::random_nonce);
+                let expires = Utc::now() + Duration::seconds(o.valid_for as i64);
+                let message = o.message.to_owned().resolve()?;
+                let proof = wallet
+                    .private(o.address)
+                    .and_then(|p| OwnershipProof::create(&p, nonce, message, expires));
+                WalletOpts::audit(&o.opts, &wallet, o.address, AuditOperation::Prove, &proof)
+                    .await?;
+                let proof = proof?;
+                println!("{}", serde_json::to_string_pretty(&proof)?);
+            }
+            Command::Audit(o) => {
+                let log = AuditLog::new(o.paths_opts.audit_log_path()?);
+                let entries: Vec<AuditEntry> = log
+                    .entries()
+                    .await?
+                    .into_iter()
+                    .filter(|e| o.id.as_ref().is_none_or(|id| &e.wallet == id))
+                    .collect();
+                let skip = o.limit.map_or(0, |l| entries.len().saturating_sub(l));
+                for entry in &entries[skip..] {
+                    if o.json {
+                        println!("{}", serde_json::to_string(entry)?);
+                    } else {
+                        println!("{}", entry);
+                    }
+                }
+            }
+            Command::Passwd(o) => {
+                let new = match (&o.new_passphrase, o.decrypt) {
+                    (Some(_), true) => {
+                        return Err(anyhow!("Use either --new-passphrase or --decrypt"))
+                    }
+                    (Some(new), false) => Some(new.to_owned().resolve()?),
+                    (None, true) => None,
+                    (None, false) => {
+                        return Err(anyhow!("A --new-passphrase is needed, or --decrypt"))
+                    }
+                };
+                if matches!(&new, Some(new) if new.is_empty()) {
+                    return Err(anyhow!("The new passphrase is empty"));
+                }
+                let mut manager = o.passphrase_opts.manager(&o.paths_opts)?;
+                let encrypted = new.is_some();
+                manager.change_passphrase(new).await?;
+                if encrypted {
+                    println!("The wallet file is encrypted under the new passphrase");
+                } else {
+                    println!("The wallet file is no longer encrypted");
+                }
+            }
+            #[cfg(feature = "rpc_client")]
+            Command::Rotate(o) => {
+                use crate::rpc::client::RPCClient;
+                use crate::wallet::{RPCPayer, Rotation};
+
+                let mut client = RPCClient::new(&o.url);
+                if let Some(auth) = &o.auth {
+                    client.authorization(auth);
+                }
+                let payer = RPCPayer::new(client, o.url.to_owned())
+                    .thresholds(work_thresholds(&o.opts.paths_opts).await?);
+                let manager = o.opts.manager()?;
+                let wallet_id = o.opts.wallet_id().await?;
+                let moved = Rotation::new(&manager, &payer)
+                    .gap(o.gap)
+                    .batch(o.batch)
+                    .audit(AuditLog::new(o.opts.paths_opts.audit_log_path()?))
+                    .run(&wallet_id)
+                    .await?;
+                for account in &moved {
+                    println!(
+                        "{} {} -> {} {} raw",
+                        account.index, account.from, account.to, account.amount
+                    );
+                }
+                println!(
+                    "Wallet {:?} was rotated, {} accounts were moved and the old secret is archived",
+                    wallet_id,
+                    moved.len()
+                );
+            }
+            Command::Schedule(o) => o.handle().await?,
+            Command::Contact(o) => o.handle().await?,
         };
         Ok(())
     }
 
+    /// Record an operation done with the key at `index` into the audit log.
+    async fn audit<T, E: Display>(
+        o: &CommonOpts,
+        wallet: &Wallet,
+        index: u32,
+        operation: AuditOperation,
+        result: &Result<T, E>,
+    ) -> anyhow::Result<()> {
+        let mut entry = AuditEntry::new(o.wallet_id().await?, operation, result.into());
+        entry.account = wallet.address(index).ok();
+        AuditLog::new(o.paths_opts.audit_log_path()?)
+            .record(&entry)
+            .await
+    }
+
     async fn read(o: &CommonOpts) -> anyhow::Result<Wallet> {
-        let manager = WalletManager::new(&o.paths_opts.wallet_path()?);
-        let wallet = manager.wallet(&o.wallet_id()?).await?;
+        let manager = o.manager()?;
+        let wallet = manager.wallet(&o.wallet_id().await?).await?;
         Ok(wallet)
     }
 
     async fn create(o: &CommonOptsCreate) -> anyhow::Result<(WalletManager, WalletId)> {
-        let manager = WalletManager::new(&o.common_opts.paths_opts.wallet_path()?);
+        let manager = o.common_opts.manager()?;
         manager.ensure().await?;
         let wallet_id = o.wallet_id()?.to_owned();
         Ok((manager, wallet_id))
     }
 
+    /// Name a wallet that was just added, if it was given a name, and show its ID.
+    async fn created(
+        o: &CommonOptsCreate,
+        manager: &WalletManager,
+        wallet_id: &WalletId,
+    ) -> anyhow::Result<()> {
+        if let Some(name) = &o.name {
+            manager.set_name(wallet_id, name).await?;
+        }
+        println!("{}", wallet_id);
+        Ok(())
+    }
+
     async fn delete(o: &CommonOpts) -> anyhow::Result<(WalletManager, WalletId)> {
-        let manager = WalletManager::new(&o.paths_opts.wallet_path()?);
+        let manager = o.manager()?;
         manager.ensure().await?;
-        let wallet_id = o.wallet_id()?;
+        let wallet_id = o.wallet_id().await?;
         Ok((manager, wallet_id))
     }
 }
 
+/// The `[wallet.work]` thresholds from the config in the data directory, if there is one, over
+/// the base thresholds of the network.
+#[cfg(feature = "rpc_client")]
+pub(crate) async fn work_thresholds(
+    paths_opts: &PathsOpts,
+) -> anyhow::Result<crate::WorkThresholds> {
+    let path = paths_opts.config_path();
+    let thresholds = if path.exists() {
+        crate::Config::load(&path)
+            .await?
+            .wallet
+            .unwrap_or_default()
+            .work
+    } else {
+        crate::WorkThresholds::default()
+    };
+    Ok(thresholds.with_base(paths_opts.network()))
+}
+
 #[derive(Clap)]
 enum Command {
     /// Create a new wallet. If the wallet file doesn't exist, it will be created.
@@ -122,6 +285,9 @@ enum Command {
     /// Import an existing wallet. If the wallet file doesn't exist, it will be created.
     Import(ImportOpts),
 
+    /// List the wallets in the wallet file, with their names and kinds but not their secrets.
+    List(ListOpts),
+
     /// Output the private key of a wallet.
     Private(PrivateOpts),
 
@@ -134,30 +300,76 @@ enum Command {
     /// Sign a message using a key in this wallet.
     Sign(SignOpts),
 
+    /// Create a proof that you control an address, for example when an exchange asks for one.
+    Prove(ProveOpts),
+
     /// Delete an existing wallet.
     Delete(DeleteOpts),
+
+    /// Show the log of signing and broadcast operations done with wallet keys.
+    Audit(AuditOpts),
+
+    /// Encrypt the wallet file under a new passphrase. The file is replaced in one step, so it's
+    /// never left half written.
+    Passwd(PasswdOpts),
+
+    /// Move the funds of every account of a wallet to a fresh seed, which then replaces the old
+    /// seed. The old secret is archived in the wallet file, in case funds arrive after the
+    /// rotation. An interrupted rotation carries on with the same seed when run again.
+    #[cfg(feature = "rpc_client")]
+    Rotate(RotateOpts),
+
+    /// Manage recurring payments, and run the daemon that makes them.
+    Schedule(ScheduleOpts),
+
+    /// Manage named addresses that can be used as `@name` instead of an address.
+    Contact(ContactOpts),
 }
 
 #[derive(Clap)]
-struct CommonOpts {
+pub(crate) struct CommonOpts {
     #[clap(flatten)]
-    paths_opts: PathsOpts,
+    pub paths_opts: PathsOpts,
+
+    #[clap(flatten)]
+    passphrase_opts: PassphraseOpts,
 
-    /// Wallet ID.
+    /// Wallet ID, or the name of a wallet. Defaults to the default wallet.
     #[clap(short, long, env = "FEELESS_WALLET_ID")]
-    id: Option<WalletId>,
+    id: Option<String>,
 }
 
 impl CommonOpts {
-    fn wallet_id(&self) -> anyhow::Result<WalletId> {
-        if let Some(wallet_id) = &self.id {
-            Ok(wallet_id.to_owned())
-        } else {
-            Ok(WalletId::zero())
+    pub fn manager(&self) -> anyhow::Result<WalletManager> {
+        self.passphrase_opts.manager(&self.paths_opts)
+    }
+
+    pub async fn wallet_id(&self) -> anyhow::Result<WalletId> {
+        match &self.id {
+            Some(reference) => self.manager()?.find(reference).await,
+            None => Ok(WalletId::zero()),
         }
     }
 }
 
+/// The passphrase of an encrypted wallet file.
+#[derive(Clap)]
+pub(crate) struct PassphraseOpts {
+    /// Passphrase the wallet file is encrypted with.
+    #[clap(long, env = "FEELESS_WALLET_PASSPHRASE", hide_env_values = true)]
+    passphrase: Option<String>,
+}
+
+impl PassphraseOpts {
+    pub fn manager(&self, paths_opts: &PathsOpts) -> anyhow::Result<WalletManager> {
+        let manager = WalletManager::new(&paths_opts.wallet_path()?);
+        Ok(match &self.passphrase {
+            Some(passphrase) => manager.with_passphrase(passphrase.as_str()),
+            None => manager,
+        })
+    }
+}
+
 #[derive(Clap)]
 struct CommonOptsCreate {
     #[clap(flatten)]
@@ -165,6 +377,10 @@ struct CommonOptsCreate {
 
     #[clap(short, long)]
     default: bool,
+
+    /// A name to refer to the wallet by in other commands, instead of its ID.
+    #[clap(long)]
+    name: Option<String>,
 }
 
 impl CommonOptsCreate {
@@ -173,10 +389,14 @@ impl CommonOptsCreate {
             return Ok(WalletId::zero());
         }
 
-        if let Some(wallet_id) = &self.common_opts.id {
-            Ok(wallet_id.to_owned())
-        } else {
-            Ok(WalletId::random())
+        match &self.common_opts.id {
+            Some(id) => WalletId::from_str(id).map_err(|err| {
+                anyhow!(
+                    "A new wallet needs an ID in hex, use --name to name it: {}",
+                    err
+                )
+            }),
+            None => Ok(WalletId::random()),
         }
     }
 }
@@ -247,6 +467,7 @@ struct ImportPhraseOpts {
 
 #[derive(Clap)]
 struct ImportSeedOpts {
+    #[clap(parse(try_from_str = crate::cli::parse::seed_or_stdin))]
     seed: StringOrStdin<crate::Seed>,
 
     #[clap(flatten)]
@@ -255,6 +476,7 @@ struct ImportSeedOpts {
 
 #[derive(Clap)]
 struct ImportPrivateOpts {
+    #[clap(parse(try_from_str = crate::cli::parse::private_or_stdin))]
     private: StringOrStdin<crate::Private>,
 
     #[clap(flatten)]
@@ -263,7 +485,7 @@ struct ImportPrivateOpts {
 
 #[derive(Clap)]
 struct PrivateOpts {
-    #[clap(default_value = "0")]
+    #[clap(default_value = "0", parse(try_from_str = crate::cli::parse::index))]
     address: u32,
 
     #[clap(flatten)]
@@ -272,7 +494,7 @@ struct PrivateOpts {
 
 #[derive(Clap)]
 struct PublicOpts {
-    #[clap(default_value = "0")]
+    #[clap(default_value = "0", parse(try_from_str = crate::cli::parse::index))]
     address: u32,
 
     #[clap(flatten)]
@@ -281,19 +503,114 @@ struct PublicOpts {
 
 #[derive(Clap)]
 struct AddressOpts {
-    #[clap(default_value = "0")]
+    #[clap(default_value = "0", parse(try_from_str = crate::cli::parse::index))]
     address: u32,
 
     #[clap(flatten)]
     opts: CommonOpts,
 }
 
+#[derive(Clap)]
+struct ListOpts {
+    /// Output each wallet as a line of JSON.
+    #[clap(long)]
+    json: bool,
+
+    #[clap(flatten)]
+    paths_opts: PathsOpts,
+
+    #[clap(flatten)]
+    passphrase_opts: PassphraseOpts,
+}
+
 #[derive(Clap)]
 struct DeleteOpts {
     #[clap(flatten)]
     opts: CommonOpts,
 }
 
+#[derive(Clap)]
+struct PasswdOpts {
+    /// The passphrase to encrypt the wallet file under. Use `-` to read it from stdin.
+    #[clap(long, env = "FEELESS_WALLET_NEW_PASSPHRASE", hide_env_values = true)]
+    new_passphrase: Option<StringOrStdin<String>>,
+
+    /// Store the wallet file without encryption.
+    #[clap(long)]
+    decrypt: bool,
+
+    #[clap(flatten)]
+    paths_opts: PathsOpts,
+
+    #[clap(flatten)]
+    passphrase_opts: PassphraseOpts,
+}
+
+#[cfg(feature = "rpc_client")]
+#[derive(Clap)]
+struct RotateOpts {
+    /// The URL of the RPC server to send blocks through.
+    #[clap(
+        long,
+        short,
+        default_value = "http://localhost:7076",
+        env = "FEELESS_RPC_URL"
+    )]
+    url: String,
+
+    /// Send a string in the HTTP authorization header.
+    #[clap(long, env = "FEELESS_RPC_AUTH")]
+    auth: Option<String>,
+
+    /// Stop looking for accounts to move after this many unused accounts in a row.
+    #[clap(long, default_value = "20")]
+    gap: u32,
+
+    /// How many accounts to move at the same time.
+    #[clap(long, default_value = "8")]
+    batch: usize,
+
+    #[clap(flatten)]
+    opts: CommonOpts,
+}
+
+#[derive(Clap)]
+struct AuditOpts {
+    #[clap(flatten)]
+    paths_opts: PathsOpts,
+
+    /// Only show operations for this wallet.
+    #[clap(short, long)]
+    id: Option<WalletId>,
+
+    /// Only show the most recent entries.
+    #[clap(long)]
+    limit: Option<usize>,
+
+    /// Output each entry as a line of JSON.
+    #[clap(long)]
+    json: bool,
+}
+
+#[derive(Clap)]
+struct ProveOpts {
+    message: StringOrStdin<String>,
+
+    /// Nonce given by whoever is asking for the proof. A random one is used if not specified.
+    #[clap(long)]
+    nonce: Option<String>,
+
+    /// Number of seconds until the proof expires.
+    #[clap(long, default_value = "3600")]
+    valid_for: u32,
+
+    #[clap(short, long, default_value = "0", parse(try_from_str = crate::cli::parse::index))]
+    address: u32,
+
+    #[clap(flatten)]
+    opts: CommonOpts,
+}
+
 #[derive(Clap)]
 struct SignOpts {
     message: StringOrStdin<String>,
@@ -302,9 +619,241 @@ struct SignOpts {
     #[clap(long)]
     armor: bool,
 
-    #[clap(short, long, default_value = "0")]
+    #[clap(short, long, default_value = "0", parse(try_from_str = crate::cli::parse::index))]
     address: u32,
 
     #[clap(flatten)]
     opts: CommonOpts,
 }
+
+#[derive(Clap)]
+struct ScheduleOpts {
+    #[clap(subcommand)]
+    command: ScheduleCommand,
+}
+
+impl ScheduleOpts {
+    async fn handle(&self) -> anyhow::Result<()> {
+        match &self.command {
+            ScheduleCommand::Add(o) => {
+                let manager = o.opts.manager()?;
+                let to = manager.resolve(&o.to).await?;
+                let schedule = ScheduledPayment::new(
+                    o.opts.wallet_id().await?,
+                    o.address,
+                    to,
+                    o.amount.to_owned(),
+                    o.every,
+                    o.start.unwrap_or_else(Utc::now),
+                );
+                let id = manager.add_schedule(schedule).await?;
+                println!("{}", id);
+            }
+            ScheduleCommand::List(o) => {
+                let manager = o.passphrase_opts.manager(&o.paths_opts)?;
+                for schedule in manager.schedules().await? {
+                    if o.json {
+                        println!("{}", serde_json::to_string(&schedule)?);
+                    } else {
+                        println!("{}", schedule);
+                    }
+                }
+            }
+            ScheduleCommand::Remove(o) => {
+                let manager = o.passphrase_opts.manager(&o.paths_opts)?;
+                manager.delete_schedule(o.id).await?;
+            }
+            #[cfg(feature = "rpc_client")]
+            ScheduleCommand::Run(o) => {
+                use crate::rpc::client::RPCClient;
+                use crate::wallet::{PaymentDaemon, RPCPayer};
+
+                let mut client = RPCClient::new(&o.url);
+                if let Some(auth) = &o.auth {
+                    client.authorization(auth);
+                }
+                let payer = RPCPayer::new(client, o.url.to_owned())
+                    .thresholds(work_thresholds(&o.paths_opts).await?);
+                let daemon = PaymentDaemon::new(o.passphrase_opts.manager(&o.paths_opts)?, payer)
+                    .audit(AuditLog::new(o.paths_opts.audit_log_path()?));
+                if o.once {
+                    let made = daemon.run_due(Utc::now()).await?;
+                    println!("Made {} payments", made);
+                } else {
+                    daemon.run(std::time::Duration::from_secs(o.poll)).await?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clap)]
+enum ScheduleCommand {
+    /// Add a recurring payment, e.g. `--every 7d --amount 10mnano --to @alice`.
+    Add(ScheduleAddOpts),
+
+    /// List the scheduled payments.
+    List(ScheduleListOpts),
+
+    /// Remove a scheduled payment.
+    Remove(ScheduleRemoveOpts),
+
+    /// Make scheduled payments as they become due. Payments missed while this wasn't running
+    /// are caught up on, and failed payments are retried with a backoff.
+    #[cfg(feature = "rpc_client")]
+    Run(ScheduleRunOpts),
+}
+
+#[derive(Clap)]
+struct ScheduleAddOpts {
+    /// How often to pay, e.g. `7d`, `12h` or `2w`.
+    #[clap(long)]
+    every: Interval,
+
+    /// Amount with a unit, e.g. `10mnano`, `2.5nano` or `1000raw`.
+    #[clap(long, parse(try_from_str = parse_amount))]
+    amount: Raw,
+
+    /// Address or `@contact` to pay.
+    #[clap(long)]
+    to: String,
+
+    /// When to make the first payment, e.g. `2021-06-01T00:00:00Z`. Defaults to now.
+    #[clap(long)]
+    start: Option<DateTime<Utc>>,
+
+    #[clap(short, long, default_value = "0", parse(try_from_str = crate::cli::parse::index))]
+    address: u32,
+
+    #[clap(flatten)]
+    opts: CommonOpts,
+}
+
+#[derive(Clap)]
+struct ScheduleListOpts {
+    #[clap(flatten)]
+    paths_opts: PathsOpts,
+
+    #[clap(flatten)]
+    passphrase_opts: PassphraseOpts,
+
+    /// Output each scheduled payment as a line of JSON.
+    #[clap(long)]
+    json: bool,
+}
+
+#[derive(Clap)]
+struct ScheduleRemoveOpts {
+    id: u32,
+
+    #[clap(flatten)]
+    paths_opts: PathsOpts,
+
+    #[clap(flatten)]
+    passphrase_opts: PassphraseOpts,
+}
+
+#[cfg(feature = "rpc_client")]
+#[derive(Clap)]
+struct ScheduleRunOpts {
+    #[clap(flatten)]
+    paths_opts: PathsOpts,
+
+    #[clap(flatten)]
+    passphrase_opts: PassphraseOpts,
+
+    /// The URL of the RPC server to send payments through.
+    #[clap(
+        long,
+        short,
+        default_value = "http://localhost:7076",
+        env = "FEELESS_RPC_URL"
+    )]
+    url: String,
+
+    /// Send a string in the HTTP authorization header.
+    #[clap(long, env = "FEELESS_RPC_AUTH")]
+    auth: Option<String>,
+
+    /// Seconds between checking for due payments.
+    #[clap(long, default_value = "60")]
+    poll: u64,
+
+    /// Make the payments that are due now and exit.
+    #[clap(long)]
+    once: bool,
+}
+
+#[derive(Clap)]
+struct ContactOpts {
+    #[clap(subcommand)]
+    command: ContactCommand,
+}
+
+impl ContactOpts {
+    async fn handle(&self) -> anyhow::Result<()> {
+        match &self.command {
+            ContactCommand::Add(o) => {
+                o.passphrase_opts
+                    .manager(&o.paths_opts)?
+                    .add_contact(&o.name, o.address.to_owned())
+                    .await?
+            }
+            ContactCommand::List(o) => {
+                let contacts = o.passphrase_opts.manager(&o.paths_opts)?.contacts().await?;
+                for (name, address) in contacts {
+                    println!("@{} {}", name, address);
+                }
+            }
+            ContactCommand::Remove(o) => {
+                o.passphrase_opts
+                    .manager(&o.paths_opts)?
+                    .delete_contact(&o.name)
+                    .await?
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clap)]
+enum ContactCommand {
+    /// Add or replace a contact.
+    Add(ContactAddOpts),
+    List(ContactListOpts),
+    Remove(ContactRemoveOpts),
+}
+
+#[derive(Clap)]
+struct ContactAddOpts {
+    name: String,
+    #[clap(parse(try_from_str = crate::cli::parse::address))]
+    address: Address,
+
+    #[clap(flatten)]
+    paths_opts: PathsOpts,
+
+    #[clap(flatten)]
+    passphrase_opts: PassphraseOpts,
+}
+
+#[derive(Clap)]
+struct ContactListOpts {
+    #[clap(flatten)]
+    paths_opts: PathsOpts,
+
+    #[clap(flatten)]
+    passphrase_opts: PassphraseOpts,
+}
+
+#[derive(Clap)]
+struct ContactRemoveOpts {
+    name: String,
+
+    #[clap(flatten)]
+    paths_opts: PathsOpts,
+
+    #[clap(flatten)]
+    passphrase_opts: PassphraseOpts,
+}
@@ -0,0 +1,94 @@
+use crate::cli::wallet::PassphraseOpts;
+use crate::paths::PathsOpts;
+use crate::rpc::client::RPCClient;
+use crate::wallet::{load_or_create_token, AuditLog, RPCPayer, SendGuard, WalletDaemon};
+use crate::Config;
+use anyhow::anyhow;
+use clap::Clap;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::Arc;
+use tokio::net::TcpListener;
+
+#[derive(Clap)]
+pub(crate) struct WalletdOpts {
+    #[clap(flatten)]
+    paths_opts: PathsOpts,
+
+    #[clap(flatten)]
+    passphrase_opts: PassphraseOpts,
+
+    /// Path to the config file, for the send guard in its `[walletd]` section and the work
+    /// thresholds in `[wallet.work]`. Defaults to `feeless.toml` in the data directory, if it
+    /// exists.
+    #[clap(long)]
+    config: Option<PathBuf>,
+
+    /// Where to listen for control socket clients. Only loopback addresses are allowed, since
+    /// the token isn't encrypted.
+    #[clap(long, default_value = "127.0.0.1:7090")]
+    listen: SocketAddr,
+
+    /// The URL of the RPC server to send blocks through.
+    #[clap(
+        long,
+        short,
+        default_value = "http://localhost:7076",
+        env = "FEELESS_RPC_URL"
+    )]
+    url: String,
+
+    /// Send a string in the HTTP authorization header.
+    #[clap(long, env = "FEELESS_RPC_AUTH")]
+    auth: Option<String>,
+}
+
+impl WalletdOpts {
+    pub async fn handle(&self) -> anyhow::Result<()> {
+        if !self.listen.ip().is_loopback() {
+            return Err(anyhow!(
+                "Refusing to listen on {}, which isn't a loopback address",
+                self.listen
+            ));
+        }
+
+        let wallet_path = self.paths_opts.wallet_path()?;
+        let manager = self.passphrase_opts.manager(&self.paths_opts)?;
+        manager.ensure().await?;
+        let token_path = self.paths_opts.walletd_token_path()?;
+        let token = load_or_create_token(&token_path)?;
+
+        let mut client = RPCClient::new(&self.url);
+        if let Some(auth) = &self.auth {
+            client.authorization(auth);
+        }
+        let config_path = self
+            .config
+            .to_owned()
+            .unwrap_or_else(|| self.paths_opts.config_path());
+        let config = if self.config.is_some() || config_path.exists() {
+            Config::load(&config_path).await?
+        } else {
+            Config::default()
+        };
+
+        let payer = RPCPayer::new(client, self.url.to_owned())
+            .thresholds(config.wallet.unwrap_or_default().work);
+        let mut daemon = WalletDaemon::new(manager, payer, token)
+            .audit(AuditLog::new(self.paths_opts.audit_log_path()?));
+        if let Some(guard) = SendGuard::from_config(&config.walletd.unwrap_or_default())? {
+            println!(
+                "Sends of more than {} raw need a TOTP code or a confirmation",
+                guard.above()
+            );
+            daemon = daemon.guard(guard);
+        }
+
+        let listener = TcpListener::bind(self.listen).await?;
+        println!(
+            "Serving wallets in {:?} on {}, authenticate with the token in {:?}",
+            wallet_path, self.listen, token_path
+        );
+        Arc::new(daemon).serve(listener).await
+    }
+}
@@ -0,0 +1,121 @@
+use crate::cli::ndjson::Ndjson;
+use crate::paths::PathsOpts;
+use crate::rpc::client::RPCClient;
+use crate::watch::{AccountSnapshot, Alert, BalanceWatcher};
+use crate::{CancellationToken, Config};
+use anyhow::anyhow;
+use clap::Clap;
+use serde::Serialize;
+use std::path::PathBuf;
+use std::time::Duration;
+use tracing::warn;
+
+#[derive(Clap)]
+pub(crate) struct WatchOpts {
+    #[clap(flatten)]
+    paths_opts: PathsOpts,
+
+    /// Path to the config file. Defaults to `feeless.toml` in the data directory.
+    #[clap(long)]
+    config: Option<PathBuf>,
+
+    /// The URL of the RPC server. Overrides `rpc_url` in the config.
+    #[clap(long, short, env = "FEELESS_RPC_URL")]
+    url: Option<String>,
+
+    /// Send a string in the HTTP authorization header.
+    #[clap(long, env = "FEELESS_RPC_AUTH")]
+    auth: Option<String>,
+
+    /// Keep watching the accounts, instead of checking them once.
+    #[clap(long)]
+    daemon: bool,
+
+    /// Output each balance and alert as a line of JSON. With `--daemon`, only alerts are
+    /// output, as they happen.
+    #[clap(long)]
+    ndjson: bool,
+}
+
+/// A line of `--ndjson` output.
+#[derive(Serialize)]
+#[serde(tag = "record", rename_all = "snake_case")]
+enum Record<'a> {
+    Balance(&'a AccountSnapshot),
+    Alert(&'a Alert),
+}
+
+impl WatchOpts {
+    pub async fn handle(&self) -> anyhow::Result<()> {
+        let path = self
+            .config
+            .to_owned()
+            .unwrap_or_else(|| self.paths_opts.config_path());
+        let config = Config::load(&path)
+            .await?
+            .watch
+            .ok_or_else(|| anyhow!("There is no [watch] section in {:?}", path))?;
+        if config.accounts.is_empty() {
+            return Err(anyhow!("There are no accounts to watch in {:?}", path));
+        }
+
+        let url = self
+            .url
+            .to_owned()
+            .or_else(|| config.rpc_url.to_owned())
+            .unwrap_or_else(|| "http://localhost:7076".into());
+        let mut client = RPCClient::new(url);
+        if let Some(auth) = &self.auth {
+            client.authorization(auth);
+        }
+
+        let interval = Duration::from_secs(config.interval);
+        let mut watcher = BalanceWatcher::new(config);
+        if self.daemon && self.ndjson {
+            let mut out = Ndjson::stdout();
+            loop {
+                match watcher.poll(&client).await {
+                    Ok(alerts) => {
+                        for alert in &alerts {
+                            out.write(&Record::Alert(alert))?;
+                        }
+                        out.flush()?;
+                    }
+                    Err(err) => warn!("Checking watched accounts failed: {:#}", err),
+                }
+                tokio::time::sleep(interval).await;
+            }
+        }
+        if self.daemon {
+            watcher.run(&client, &CancellationToken::new()).await;
+            return Ok(());
+        }
+
+        let snapshots = watcher.snapshots(&client).await?;
+        if self.ndjson {
+            let mut out = Ndjson::stdout();
+            for snapshot in &snapshots {
+                out.write(&Record::Balance(snapshot))?;
+            }
+            let now = chrono::Utc::now();
+            for snapshot in &snapshots {
+                for alert in watcher.check(snapshot, now) {
+                    out.write(&Record::Alert(&alert))?;
+                    watcher.notify(&alert).await;
+                }
+            }
+            return out.flush();
+        }
+        for snapshot in &snapshots {
+            println!("{} {} raw", snapshot.address, snapshot.balance);
+        }
+        let now = chrono::Utc::now();
+        for snapshot in &snapshots {
+            for alert in watcher.check(snapshot, now) {
+                println!("{}", alert);
+                watcher.notify(&alert).await;
+            }
+        }
+        Ok(())
+    }
+}
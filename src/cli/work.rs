@@ -1,43 +1,118 @@
-use crate::blocks::BlockHash;
-use crate::pow::{Subject, Work};
-use crate::Difficulty;
+use crate::blocks::Root;
+use crate::pow::Work;
+#[cfg(feature = "rpc_client")]
+use crate::pow::{WorkConfig, WorkPeers};
+use crate::{Benchmark, CancellationToken, Difficulty, Network};
+use anyhow::anyhow;
 use clap::Clap;
+#[cfg(feature = "rpc_client")]
+use std::path::PathBuf;
+use std::time::Duration;
 use tracing::info;
 
 #[derive(Clap)]
 pub struct WorkOpts {
-    /// The public key hash or block hash to be worked on in hex.
-    hash: BlockHash,
+    #[clap(subcommand)]
+    command: Option<WorkCommand>,
 
-    /// Use the base difficulty for a normal block.
+    /// The root to be worked on in hex: the previous block hash, or the public key for the
+    /// first block of an account. Needed unless there's a subcommand.
+    #[clap(parse(try_from_str = crate::cli::parse::root))]
+    root: Option<Root>,
+
+    /// Use the base difficulty of the network for a normal block.
     #[clap(short, long, group = "base")]
     normal: bool,
 
-    /// Use the base difficulty for a receive block.
+    /// Use the base difficulty of the network for a receive block.
     #[clap(short, long, group = "base")]
     receive: bool,
 
     /// The base difficulty in hex.
-    #[clap(short, long, group = "base")]
+    #[clap(short, long, group = "base", parse(try_from_str = crate::cli::parse::difficulty))]
     difficulty: Option<Difficulty>,
+
+    /// Comma separated work servers to dispatch to instead of using the CPU, as `host:port` or
+    /// URLs. Overrides `work_peers` in the config.
+    #[cfg(feature = "rpc_client")]
+    #[clap(long, use_delimiter = true)]
+    work_peers: Option<Vec<String>>,
+
+    /// Path to a config file with a `[work]` section listing `work_peers`.
+    #[cfg(feature = "rpc_client")]
+    #[clap(long)]
+    config: Option<PathBuf>,
+}
+
+#[derive(Clap)]
+enum WorkCommand {
+    /// Measure the hashes per second of each way to generate work here, and how long send and
+    /// receive work would take on average, as JSON.
+    Benchmark(BenchmarkOpts),
+}
+
+#[derive(Clap)]
+struct BenchmarkOpts {
+    /// How long to measure each backend for, e.g. `30s`.
+    #[clap(long, default_value = "10s", parse(try_from_str = crate::cli::parse::duration))]
+    duration: Duration,
 }
 
 impl WorkOpts {
-    pub fn handle(&self) -> anyhow::Result<()> {
-        // This is a bit hacky. We don't know if the user is giving a public key or a block hash.
-        // It really doesn't matter which it is, pow doesn't care, so we just pick one.
-        let subject = Subject::Hash(self.hash.to_owned());
+    pub async fn handle(&self, network: Network) -> anyhow::Result<()> {
+        if let Some(WorkCommand::Benchmark(opts)) = &self.command {
+            let duration = opts.duration;
+            let benchmark =
+                tokio::task::spawn_blocking(move || Benchmark::run(network, duration)).await?;
+            eprint!("{}", benchmark);
+            println!("{}", serde_json::to_string_pretty(&benchmark)?);
+            return Ok(());
+        }
+        let root = self
+            .root
+            .as_ref()
+            .ok_or_else(|| anyhow!("Give a root to work on, or a subcommand"))?;
 
         let difficulty = if let Some(d) = &self.difficulty {
             d.to_owned()
         } else if self.receive {
-            Difficulty::receive()
+            network.receive_difficulty()
         } else {
-            Difficulty::normal()
+            network.send_difficulty()
         };
-        info!("Finding work for {:?} at {:?}", &subject, &difficulty);
-        let result = Work::generate(&subject, &difficulty)?;
-        dbg!(result);
+        info!("Finding work for {:?} at {:?}", root, &difficulty);
+
+        #[cfg(feature = "rpc_client")]
+        {
+            let peers = self.work_peers().await?;
+            if !peers.is_empty() {
+                let work = peers.generate(root, &difficulty).await?;
+                println!("{}", work);
+                return Ok(());
+            }
+        }
+
+        let cancel = CancellationToken::new();
+        let work = Work::generate_async(root, &difficulty, &cancel, |attempts| {
+            eprint!("\r{} attempts", attempts);
+        })
+        .await?;
+        eprintln!();
+        println!("{}", work);
         Ok(())
     }
+
+    #[cfg(feature = "rpc_client")]
+    async fn work_peers(&self) -> anyhow::Result<WorkPeers> {
+        if let Some(work_peers) = &self.work_peers {
+            return Ok(WorkPeers::new(&WorkConfig {
+                work_peers: work_peers.to_owned(),
+            }));
+        }
+        let config = match &self.config {
+            Some(path) => crate::Config::load(path).await?.work.unwrap_or_default(),
+            None => WorkConfig::default(),
+        };
+        Ok(WorkPeers::new(&config))
+    }
 }
@@ -0,0 +1,58 @@
+//! The `feeless.toml` config file, which lives in the data directory by default.
+//!
+//! Each tool has its own section, e.g. `[watch]`, and ignores the sections it doesn't use.
+use anyhow::Context;
+use serde::Deserialize;
+use std::path::Path;
+
+#[derive(Debug, Default, Deserialize)]
+pub struct Config {
+    #[cfg(feature = "watch")]
+    #[serde(default)]
+    pub watch: Option<crate::watch::WatchConfig>,
+
+    #[cfg(feature = "rpc_client")]
+    #[serde(default)]
+    pub work: Option<crate::pow::WorkConfig>,
+
+    #[cfg(feature = "node")]
+    #[serde(default)]
+    pub peers: Option<crate::node::PeerFilterConfig>,
+
+    #[cfg(feature = "node")]
+    #[serde(default)]
+    pub health: Option<crate::node::HealthConfig>,
+
+    #[cfg(feature = "node")]
+    #[serde(default)]
+    pub voting: Option<crate::node::VotingConfig>,
+
+    #[cfg(feature = "node")]
+    #[serde(default)]
+    pub pruning: Option<crate::node::PruningConfig>,
+
+    #[cfg(feature = "node")]
+    #[serde(default)]
+    pub rpc: Option<crate::rpc::server::RpcServerConfig>,
+
+    #[serde(default)]
+    pub wallet: Option<crate::wallet::WalletConfig>,
+
+    #[cfg(feature = "rpc_client")]
+    #[serde(default)]
+    pub walletd: Option<crate::wallet::WalletdConfig>,
+}
+
+impl Config {
+    pub async fn load<P: AsRef<Path>>(path: P) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let data = tokio::fs::read_to_string(path)
+            .await
+            .with_context(|| format!("Reading config {:?}", path))?;
+        Self::from_toml(&data).with_context(|| format!("Parsing config {:?}", path))
+    }
+
+    pub fn from_toml(s: &str) -> anyhow::Result<Self> {
+        Ok(toml::from_str(s)?)
+    }
+}
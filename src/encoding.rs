@@ -45,7 +45,7 @@ where
     <T as std::str::FromStr>::Err: std::fmt::Display,
 {
     let s: &str = Deserialize::deserialize(deserializer)?;
-    Ok(T::from_str(s).map_err(serde::de::Error::custom)?)
+    T::from_str(s).map_err(serde::de::Error::custom)
 }
 
 pub fn deserialize_from_string<'de, T, D>(
@@ -57,19 +57,19 @@ where
     <T as std::str::FromStr>::Err: std::fmt::Display,
 {
     let s: String = Deserialize::deserialize(deserializer)?;
-    Ok(T::from_str(s.as_str()).map_err(serde::de::Error::custom)?)
+    T::from_str(s.as_str()).map_err(serde::de::Error::custom)
 }
 
 pub fn blake2b(size: usize, data: &[u8]) -> Box<[u8]> {
     let mut blake = VarBlake2b::new(size).expect("Output size was zero");
-    blake.update(&data);
+    blake.update(data);
     blake.finalize_boxed()
 }
 
 /// Use this instead of [blake2b] to probably prevent an allocation.
 pub fn blake2b_callback(size: usize, data: &[u8], f: impl FnOnce(&[u8])) {
     let mut blake = VarBlake2b::new(size).expect("Output size was zero");
-    blake.update(&data);
+    blake.update(data);
     blake.finalize_variable(f)
 }
 
@@ -107,12 +107,47 @@ pub fn decode_nano_base_32(s: &str) -> Result<BitVec<Msb0, u8>, Error> {
     Ok(bits)
 }
 
+/// Fixed length bytes that are written as hex, e.g. keys, hashes and work. They're all
+/// implemented by [hexify], so they parse, print and fail the same way.
+pub trait HexBytes: Sized {
+    /// How many bytes there are.
+    const LEN: usize;
+
+    /// What the bytes are for errors, e.g. "block hash".
+    const DESCRIPTION: &'static str;
+
+    fn as_bytes(&self) -> &[u8];
+
+    /// Copy exactly [HexBytes::LEN] bytes.
+    fn try_from_slice(bytes: &[u8]) -> crate::Result<Self>;
+
+    /// Parse upper or lower case hex of exactly [HexBytes::LEN] bytes.
+    fn from_hex(s: &str) -> crate::Result<Self> {
+        expect_len(s.len(), Self::LEN * 2, Self::DESCRIPTION)?;
+        let bytes = hex::decode(s.as_bytes()).map_err(|source| Error::FromHexError {
+            msg: Self::DESCRIPTION.into(),
+            source,
+        })?;
+        Self::try_from_slice(&bytes)
+    }
+
+    /// Upper case hex, which is how the reference node writes everything but work.
+    fn to_hex(&self) -> String {
+        to_hex(self.as_bytes())
+    }
+
+    fn to_hex_lower(&self) -> String {
+        to_hex_lower(self.as_bytes())
+    }
+}
+
 /// This macro relies on the `struct` to be a newtype containing a slice of `[u8; $struct::LEN]`.
 ///
 /// It adds:
+/// * [HexBytes], with `$description` in its errors.
 /// * serde implementations to (de)serialize hex strings.
-/// * `pub fn as_bytes(&self) -> &[u8]`
-/// * `pub fn as_hex(&self) -> String`
+/// * A JSON Schema of the hex string, with the `schema` feature.
+/// * `pub fn as_bytes(&self) -> &[u8]`, so it can be used without importing [HexBytes].
 /// * `TryFrom<&[u8]>` implementation.
 /// * [FromStr] implementation, which parses hex into its type.
 /// * [Debug] implementation, which displays as StructName(H3XSTR1NG), e.g. Work(A1B2C3).
@@ -127,36 +162,35 @@ macro_rules! hexify {
             pub fn as_bytes(&self) -> &[u8] {
                 &self.0
             }
+        }
 
-            pub fn as_hex(&self) -> String {
-                crate::encoding::to_hex(&self.0)
+        impl $crate::encoding::HexBytes for $struct {
+            const LEN: usize = $struct::LEN;
+            const DESCRIPTION: &'static str = $description;
+
+            fn as_bytes(&self) -> &[u8] {
+                &self.0
             }
 
-            pub fn as_hex_lower(&self) -> String {
-                crate::encoding::to_hex_lower(&self.0)
+            fn try_from_slice(bytes: &[u8]) -> $crate::Result<Self> {
+                use ::std::convert::TryFrom;
+
+                $crate::encoding::expect_len(bytes.len(), Self::LEN, $description)?;
+                Ok(Self(<[u8; Self::LEN]>::try_from(bytes)?))
             }
         }
 
         impl ::std::str::FromStr for $struct {
-            type Err = crate::Error;
-
-            fn from_str(s: &str) -> crate::Result<Self> {
-                use ::std::convert::TryFrom;
+            type Err = $crate::Error;
 
-                crate::encoding::expect_len(s.len(), Self::LEN * 2, $description)?;
-                let vec = hex::decode(s.as_bytes()).map_err(|e| crate::Error::FromHexError {
-                    msg: String::from($description),
-                    source: e,
-                })?;
-                let bytes = vec.as_slice();
-                let x = <[u8; Self::LEN]>::try_from(bytes)?;
-                Ok(Self(x))
+            fn from_str(s: &str) -> $crate::Result<Self> {
+                <Self as $crate::encoding::HexBytes>::from_hex(s)
             }
         }
 
         impl ::std::fmt::Display for $struct {
             fn fmt(&self, f: &mut ::std::fmt::Formatter<'_>) -> ::std::fmt::Result {
-                write!(f, "{}", self.as_hex())
+                $crate::encoding::hex_formatter(f, &self.0)
             }
         }
 
@@ -166,28 +200,28 @@ macro_rules! hexify {
                     f,
                     "{}({})",
                     stringify!($struct),
-                    crate::encoding::to_hex(self.0.as_ref()),
+                    $crate::encoding::to_hex(self.0.as_ref()),
                 )
             }
         }
 
         impl ::std::convert::TryFrom<&[u8]> for $struct {
-            type Error = crate::Error;
+            type Error = $crate::Error;
 
-            fn try_from(v: &[u8]) -> crate::Result<Self> {
-                Ok(Self(<[u8; Self::LEN]>::try_from(v)?))
+            fn try_from(v: &[u8]) -> $crate::Result<Self> {
+                <Self as $crate::encoding::HexBytes>::try_from_slice(v)
             }
         }
 
         impl ::std::fmt::UpperHex for $struct {
             fn fmt(&self, f: &mut ::std::fmt::Formatter<'_>) -> ::std::fmt::Result {
-                write!(f, "{}", self.as_hex())
+                $crate::encoding::hex_formatter(f, &self.0)
             }
         }
 
         impl ::std::fmt::LowerHex for $struct {
             fn fmt(&self, f: &mut ::std::fmt::Formatter<'_>) -> ::std::fmt::Result {
-                write!(f, "{}", self.as_hex_lower())
+                $crate::encoding::hex_formatter_lower(f, &self.0)
             }
         }
 
@@ -199,7 +233,7 @@ macro_rules! hexify {
             where
                 S: serde::Serializer,
             {
-                serializer.serialize_str(self.as_hex().as_str())
+                serializer.serialize_str(&$crate::encoding::to_hex(&self.0))
             }
         }
 
@@ -212,12 +246,41 @@ macro_rules! hexify {
             {
                 use ::std::str::FromStr;
                 let s: String = serde::Deserialize::deserialize(deserializer)?;
-                Ok(Self::from_str(&s).map_err(serde::de::Error::custom)?)
+                Self::from_str(&s).map_err(serde::de::Error::custom)
+            }
+        }
+
+        #[cfg(feature = "schema")]
+        impl schemars::JsonSchema for $struct {
+            fn schema_name() -> String {
+                stringify!($struct).into()
+            }
+
+            fn json_schema(_: &mut schemars::gen::SchemaGenerator) -> schemars::schema::Schema {
+                $crate::schema::hex($description, Self::LEN)
             }
         }
     };
 }
 
+pub fn expect_len(got_len: usize, expected_len: usize, msg: &str) -> crate::Result<()> {
+    if got_len != expected_len {
+        return Err(crate::Error::WrongLength {
+            msg: msg.to_string(),
+            expected: expected_len,
+            found: got_len,
+        });
+    }
+    Ok(())
+}
+
+pub fn len_err_msg(got_len: usize, expected_len: usize, msg: &str) -> String {
+    format!(
+        "{} is the wrong length: got: {} expected: {}",
+        msg, got_len, expected_len,
+    )
+}
+
 #[cfg(test)]
 mod tests {
     use std::str::FromStr;
@@ -227,6 +290,7 @@ mod tests {
     use super::*;
 
     #[test]
+    #[allow(clippy::useless_transmute)]
     fn encode_decode() {
         let bits: BitVec<Msb0, u8> =
             bitvec![Msb0, u8; 1, 1, 1, 1, 1, 0, 0, 0, 0, 0, 1, 0, 1, 0, 1, 0, 1, 0, 1, 0];
@@ -292,22 +356,29 @@ mod tests {
             assert!(result.is_err())
         }
     }
-}
 
-pub fn expect_len(got_len: usize, expected_len: usize, msg: &str) -> crate::Result<()> {
-    if got_len != expected_len {
-        return Err(crate::Error::WrongLength {
-            msg: msg.to_string(),
-            expected: expected_len,
-            found: got_len,
-        });
+    #[test]
+    fn hex_bytes() {
+        use crate::blocks::BlockHash;
+        use crate::{Signature, Work};
+
+        let hash = BlockHash::from_hex(&"ab".repeat(32)).unwrap();
+        assert_eq!(hash.to_hex(), "AB".repeat(32));
+        assert_eq!(hash.to_hex_lower(), "ab".repeat(32));
+        assert_eq!(BlockHash::try_from_slice(hash.as_bytes()).unwrap(), hash);
+
+        // Every type fails the same way on the wrong length, from hex or bytes.
+        assert!(matches!(
+            BlockHash::from_hex("abcd"),
+            Err(Error::WrongLength { .. })
+        ));
+        assert!(matches!(
+            Signature::try_from_slice(&[0u8; 63]),
+            Err(Error::WrongLength { .. })
+        ));
+        assert!(matches!(
+            Work::from_hex("zz".repeat(8).as_str()),
+            Err(Error::FromHexError { .. })
+        ));
     }
-    Ok(())
-}
-
-pub fn len_err_msg(got_len: usize, expected_len: usize, msg: &str) -> String {
-    format!(
-        "{} is the wrong length: got: {} expected: {}",
-        msg, got_len, expected_len,
-    )
 }
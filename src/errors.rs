@@ -37,6 +37,9 @@ pub enum Error {
     #[error("Bad public key, can not verify")]
     BadPublicKey,
 
+    #[error("Invalid signature")]
+    InvalidSignature,
+
     #[error("Extended secret key error")]
     ExtendedSecretKeyError(#[from] ed25519_dalek_bip32::Error),
 
@@ -59,9 +62,25 @@ pub enum Error {
     #[error("Possible language codes are {0}")]
     LanguageError(String),
 
+    #[error("Block is missing a signature")]
+    MissingSignature,
+
+    #[error("Block is missing work")]
+    MissingWork,
+
+    /// A long running operation was stopped with its [crate::CancellationToken].
+    #[error("Cancelled")]
+    Cancelled,
+
     #[error("Invalid armor content: {0}")]
     InvalidArmor(String),
 
+    #[error("Invalid ownership proof: {0}")]
+    InvalidProof(String),
+
+    #[error("Ownership proof expired at {0}")]
+    ProofExpired(chrono::DateTime<chrono::Utc>),
+
     #[cfg(any(feature = "rpc_client", feature = "rpc_server"))]
     #[error("RPC request failed: {0}")]
     RPCRequestFailed(#[from] reqwest::Error),
@@ -73,5 +92,115 @@ pub enum Error {
     },
 
     #[error("RPC error: {0}")]
-    RPCError(String),
+    RPCError(RpcErrorKind),
+
+    #[error("Invalid output filter: {0}")]
+    InvalidOutputFilter(String),
+
+    #[error("Invalid amount: {0}")]
+    InvalidAmount(String),
+
+    #[error("Invalid locale: {0}")]
+    InvalidLocale(String),
+
+    #[error("Invalid interval: {0}")]
+    InvalidInterval(String),
+
+    #[error("Invalid multiplier: {0}")]
+    InvalidMultiplier(String),
+
+    #[error("Unrecognized input: {0}")]
+    UnrecognizedInput(String),
+}
+
+/// An error returned by a node over RPC. The errors that applications usually need to handle
+/// have their own variants, and the rest keep the node's message.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum RpcErrorKind {
+    /// The account has no blocks yet, e.g. from `account_info` before an open block.
+    #[error("Account not found")]
+    AccountNotFound,
+
+    #[error("Block not found")]
+    BlockNotFound,
+
+    /// A processed block did not have enough work for its subtype.
+    #[error("Work low")]
+    WorkLow,
+
+    /// A processed block has the same previous block as one the node already has.
+    #[error("Fork")]
+    Fork,
+
+    /// The node stopped, or dropped the request without answering it.
+    #[error("Node unavailable")]
+    NodeUnavailable,
+
+    #[error("{0}")]
+    Other(String),
+}
+
+impl<T> From<tokio::sync::mpsc::error::SendError<T>> for Error {
+    fn from(_: tokio::sync::mpsc::error::SendError<T>) -> Self {
+        Error::RPCError(RpcErrorKind::NodeUnavailable)
+    }
+}
+
+impl From<tokio::sync::oneshot::error::RecvError> for Error {
+    fn from(_: tokio::sync::oneshot::error::RecvError) -> Self {
+        Error::RPCError(RpcErrorKind::NodeUnavailable)
+    }
+}
+
+impl From<String> for RpcErrorKind {
+    fn from(message: String) -> Self {
+        match message.as_str() {
+            "Account not found" => RpcErrorKind::AccountNotFound,
+            "Block not found" => RpcErrorKind::BlockNotFound,
+            // Newer nodes spell it out.
+            "Work low" | "Block work is less than threshold" => RpcErrorKind::WorkLow,
+            "Fork" => RpcErrorKind::Fork,
+            "Node unavailable" => RpcErrorKind::NodeUnavailable,
+            _ => RpcErrorKind::Other(message),
+        }
+    }
+}
+
+impl From<&str> for RpcErrorKind {
+    fn from(message: &str) -> Self {
+        message.to_owned().into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rpc_error_kinds() {
+        assert_eq!(
+            RpcErrorKind::from("Account not found"),
+            RpcErrorKind::AccountNotFound
+        );
+        assert_eq!(
+            RpcErrorKind::from("Block work is less than threshold"),
+            RpcErrorKind::WorkLow
+        );
+        assert_eq!(RpcErrorKind::from("Fork"), RpcErrorKind::Fork);
+        assert_eq!(
+            RpcErrorKind::from(RpcErrorKind::NodeUnavailable.to_string()),
+            RpcErrorKind::NodeUnavailable
+        );
+
+        let other = RpcErrorKind::from("Bad link number");
+        assert_eq!(other, RpcErrorKind::Other("Bad link number".into()));
+        assert_eq!(
+            Error::RPCError(other).to_string(),
+            "RPC error: Bad link number"
+        );
+        assert_eq!(
+            Error::RPCError(RpcErrorKind::BlockNotFound).to_string(),
+            "RPC error: Block not found"
+        );
+    }
 }
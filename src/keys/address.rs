@@ -56,7 +56,7 @@ impl Address {
             &self.0[Self::PREFIX_LEN..(Self::PREFIX_LEN + Self::ENCODED_PUBLIC_KEY_LEN)];
         debug_assert_eq!(public_key_part.len(), Self::ENCODED_PUBLIC_KEY_LEN);
 
-        let bits = encoding::decode_nano_base_32(&public_key_part)?;
+        let bits = encoding::decode_nano_base_32(public_key_part)?;
         debug_assert_eq!(bits.len(), 8 * Public::LEN + Self::ENCODED_PADDED_BITS);
 
         // Remove padding.
@@ -80,10 +80,21 @@ impl Address {
     }
 }
 
-static ADDRESS_REGEX: Lazy<Regex> = Lazy::new(|| {
-    Regex::new("^nano_[13][13456789abcdefghijkmnopqrstuwxyz]{59}$")
-        .expect("Could not build regexp for nano address.")
-});
+const ADDRESS_PATTERN: &str = "^nano_[13][13456789abcdefghijkmnopqrstuwxyz]{59}$";
+
+static ADDRESS_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(ADDRESS_PATTERN).expect("Could not build regexp for nano address."));
+
+#[cfg(feature = "schema")]
+impl schemars::JsonSchema for Address {
+    fn schema_name() -> String {
+        "Address".into()
+    }
+
+    fn json_schema(_: &mut schemars::gen::SchemaGenerator) -> schemars::schema::Schema {
+        crate::schema::string("A Nano address", ADDRESS_PATTERN)
+    }
+}
 
 impl FromStr for Address {
     type Err = Error;
@@ -114,7 +125,7 @@ impl From<&Public> for Address {
         let mut bits: BitVec<Msb0, u8> = BitVec::with_capacity(PKP_CAPACITY);
         let pad: BitVec<Msb0, u8> = bitvec![Msb0, u8; 0; Self::ENCODED_PADDED_BITS];
         bits.extend_from_bitslice(&pad);
-        bits.extend_from_raw_slice(&public.as_bytes());
+        bits.extend_from_raw_slice(public.as_bytes());
         debug_assert_eq!(bits.capacity(), PKP_CAPACITY);
         debug_assert_eq!(bits.len(), PKP_LEN);
         let public_key_part = encoding::encode_nano_base_32(&bits);
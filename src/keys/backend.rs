@@ -0,0 +1,309 @@
+//! The ed25519 implementations keys are signed and verified with.
+//!
+//! Nano signs with ed25519, hashing with blake2b instead of sha512, so off the shelf libraries
+//! can't be used as they are, and small differences between implementations show up as
+//! signatures one side accepts and the other doesn't. There are two backends:
+//!
+//! * [Backend::Dalek] is the blake2b fork of ed25519-dalek. It only accepts signatures with a
+//!   fully reduced `s`.
+//! * [Backend::Donna] follows the ed25519-donna build the reference node uses, on top of
+//!   curve25519-dalek's arithmetic. Like the reference node, it only checks the top three bits
+//!   of `s`, so it accepts the same signatures the network does.
+//!
+//! Both sign the same way. Dalek is the default, or Donna with the `ed25519_donna` feature, and
+//! it can be changed while running with [Backend::select], e.g. if one misbehaves on a platform.
+use crate::{encoding, Error, Private, Public, Signature};
+use curve25519_dalek::constants::ED25519_BASEPOINT_TABLE;
+use curve25519_dalek::edwards::{CompressedEdwardsY, EdwardsPoint};
+use curve25519_dalek::scalar::Scalar;
+use ed25519_dalek::ed25519::signature::Signature as InternalSignature;
+use ed25519_dalek::Verifier;
+use std::convert::TryFrom;
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU8, Ordering};
+
+static SELECTED: AtomicU8 = AtomicU8::new(Backend::DEFAULT as u8);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum Backend {
+    Dalek = 0,
+    Donna = 1,
+}
+
+impl Backend {
+    pub const ALL: [Backend; 2] = [Backend::Dalek, Backend::Donna];
+
+    #[cfg(not(feature = "ed25519_donna"))]
+    pub const DEFAULT: Backend = Backend::Dalek;
+    #[cfg(feature = "ed25519_donna")]
+    pub const DEFAULT: Backend = Backend::Donna;
+
+    /// The backend [Private] and [Public] use.
+    pub fn current() -> Self {
+        match SELECTED.load(Ordering::Relaxed) {
+            0 => Backend::Dalek,
+            _ => Backend::Donna,
+        }
+    }
+
+    /// Use this backend for every key from now on.
+    pub fn select(self) {
+        SELECTED.store(self as u8, Ordering::Relaxed);
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Backend::Dalek => "dalek",
+            Backend::Donna => "donna",
+        }
+    }
+
+    pub fn public(&self, private: &Private) -> Result<Public, Error> {
+        match self {
+            Backend::Dalek => {
+                let secret = dalek_secret(private)?;
+                Ok(Public::from(ed25519_dalek::PublicKey::from(&secret)))
+            }
+            Backend::Donna => {
+                let (scalar, _) = donna::expand(private);
+                Ok(donna::public(&(&scalar * &ED25519_BASEPOINT_TABLE)))
+            }
+        }
+    }
+
+    pub fn sign(&self, private: &Private, message: &[u8]) -> Result<Signature, Error> {
+        match self {
+            Backend::Dalek => {
+                let secret = dalek_secret(private)?;
+                let public = ed25519_dalek::PublicKey::from(&secret);
+                let expanded = ed25519_dalek::ExpandedSecretKey::from(&secret);
+                Signature::try_from(expanded.sign(message, &public).as_bytes())
+            }
+            Backend::Donna => Ok(donna::sign(private, message)),
+        }
+    }
+
+    pub fn verify(
+        &self,
+        public: &Public,
+        message: &[u8],
+        signature: &Signature,
+    ) -> Result<(), Error> {
+        match self {
+            Backend::Dalek => {
+                // A key that isn't a point can't have signed anything.
+                let key = ed25519_dalek::PublicKey::from_bytes(public.as_bytes())
+                    .map_err(|_| Error::BadPublicKey)?;
+                key.verify(message, &signature.internal())
+                    .map_err(|e| Error::SignatureError {
+                        msg: format!(
+                            "Public verification failed: sig: {:?} message: {:?} key: {:?}",
+                            signature, message, key
+                        ),
+                        source: e,
+                    })
+            }
+            Backend::Donna => donna::verify(public, message, signature),
+        }
+    }
+}
+
+impl Default for Backend {
+    fn default() -> Self {
+        Backend::DEFAULT
+    }
+}
+
+impl Display for Backend {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for Backend {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Backend::ALL
+            .iter()
+            .find(|b| b.name() == s)
+            .copied()
+            .ok_or_else(|| {
+                anyhow::anyhow!("Unknown ed25519 backend {:?}, expected dalek or donna", s)
+            })
+    }
+}
+
+fn dalek_secret(private: &Private) -> Result<ed25519_dalek::SecretKey, Error> {
+    ed25519_dalek::SecretKey::from_bytes(private.as_bytes()).map_err(|e| Error::SignatureError {
+        msg: String::from("Converting to SecretKey"),
+        source: e,
+    })
+}
+
+mod donna {
+    use super::*;
+
+    fn hash(parts: &[&[u8]]) -> [u8; 64] {
+        let mut wide = [0u8; 64];
+        wide.copy_from_slice(&encoding::blake2b(64, &parts.concat()));
+        wide
+    }
+
+    fn hash_to_scalar(parts: &[&[u8]]) -> Scalar {
+        Scalar::from_bytes_mod_order_wide(&hash(parts))
+    }
+
+    pub(super) fn public(point: &EdwardsPoint) -> Public {
+        Public::try_from(point.compress().as_bytes().as_ref()).unwrap()
+    }
+
+    /// The clamped scalar of the key and the prefix its nonces are hashed with.
+    pub(super) fn expand(private: &Private) -> (Scalar, [u8; 32]) {
+        let hashed = hash(&[private.as_bytes()]);
+        let mut bits = [0u8; 32];
+        bits.copy_from_slice(&hashed[..32]);
+        bits[0] &= 248;
+        bits[31] &= 127;
+        bits[31] |= 64;
+        let mut prefix = [0u8; 32];
+        prefix.copy_from_slice(&hashed[32..]);
+        (Scalar::from_bits(bits), prefix)
+    }
+
+    pub(super) fn sign(private: &Private, message: &[u8]) -> Signature {
+        let (scalar, prefix) = expand(private);
+        let public = public(&(&scalar * &ED25519_BASEPOINT_TABLE));
+        let r = hash_to_scalar(&[&prefix, message]);
+        let big_r = (&r * &ED25519_BASEPOINT_TABLE).compress();
+        let k = hash_to_scalar(&[big_r.as_bytes(), public.as_bytes(), message]);
+        let s = k * scalar + r;
+        Signature::try_from(
+            [big_r.as_bytes().as_ref(), s.as_bytes()]
+                .concat()
+                .as_slice(),
+        )
+        .unwrap()
+    }
+
+    pub(super) fn verify(
+        public: &Public,
+        message: &[u8],
+        signature: &Signature,
+    ) -> Result<(), Error> {
+        let bytes = signature.as_bytes();
+        if bytes[63] & 224 != 0 {
+            return Err(Error::InvalidSignature);
+        }
+        let mut key = [0u8; 32];
+        key.copy_from_slice(public.as_bytes());
+        let point = CompressedEdwardsY(key)
+            .decompress()
+            .ok_or(Error::BadPublicKey)?;
+
+        let mut s = [0u8; 32];
+        s.copy_from_slice(&bytes[32..]);
+        let k = hash_to_scalar(&[&bytes[..32], public.as_bytes(), message]);
+        let big_r =
+            EdwardsPoint::vartime_double_scalar_mul_basepoint(&k, &-point, &Scalar::from_bits(s));
+        if big_r.compress().as_bytes()[..] != bytes[..32] {
+            return Err(Error::InvalidSignature);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Network;
+    use curve25519_dalek::constants::BASEPOINT_ORDER;
+
+    /// The key the test network's genesis block is signed with, from the reference node.
+    const TEST_GENESIS_PRIVATE: &str =
+        "34F0A37AAD20F4A260F0A5B3CB3D7FB50673212263E58A380BC10474BB039CE4";
+
+    #[test]
+    fn genesis_vectors() {
+        for network in &[Network::Live, Network::Beta, Network::Test] {
+            let genesis = network.genesis_block();
+            let hash = network.genesis_hash();
+            let signature = genesis.signature().unwrap();
+            for backend in &Backend::ALL {
+                backend
+                    .verify(genesis.account(), hash.as_bytes(), signature)
+                    .unwrap();
+                assert!(backend
+                    .verify(genesis.account(), &[0; 32], signature)
+                    .is_err());
+            }
+        }
+
+        let private = Private::from_str(TEST_GENESIS_PRIVATE).unwrap();
+        let genesis = Network::Test.genesis_block();
+        let hash = Network::Test.genesis_hash();
+        for backend in &Backend::ALL {
+            assert_eq!(&backend.public(&private).unwrap(), genesis.account());
+            assert_eq!(
+                &backend.sign(&private, hash.as_bytes()).unwrap(),
+                genesis.signature().unwrap(),
+                "{}",
+                backend
+            );
+        }
+    }
+
+    #[test]
+    fn cross_validate() {
+        for n in 0..16u8 {
+            let private = crate::Seed::random().derive(n as u32);
+            let message = vec![n; n as usize * 7];
+            let public = Backend::Dalek.public(&private).unwrap();
+            assert_eq!(Backend::Donna.public(&private).unwrap(), public);
+
+            let signature = Backend::Dalek.sign(&private, &message).unwrap();
+            assert_eq!(Backend::Donna.sign(&private, &message).unwrap(), signature);
+            for backend in &Backend::ALL {
+                backend.verify(&public, &message, &signature).unwrap();
+            }
+        }
+    }
+
+    /// `s + l` is the same signature to the reference node, but not to ed25519-dalek.
+    #[test]
+    fn unreduced_s() {
+        let private = Private::from_str(TEST_GENESIS_PRIVATE).unwrap();
+        let public = Backend::Donna.public(&private).unwrap();
+        let signature = Backend::Donna.sign(&private, b"feeless").unwrap();
+
+        let mut bytes = signature.as_bytes().to_vec();
+        let mut carry = 0u16;
+        for (byte, l) in bytes[32..].iter_mut().zip(BASEPOINT_ORDER.as_bytes()) {
+            let sum = *byte as u16 + *l as u16 + carry;
+            *byte = sum as u8;
+            carry = sum >> 8;
+        }
+        let unreduced = Signature::try_from(bytes.as_slice()).unwrap();
+        assert!(bytes[63] & 224 == 0);
+
+        Backend::Donna
+            .verify(&public, b"feeless", &unreduced)
+            .unwrap();
+        assert!(Backend::Dalek
+            .verify(&public, b"feeless", &unreduced)
+            .is_err());
+
+        bytes[63] |= 128;
+        let high = Signature::try_from(bytes.as_slice()).unwrap();
+        assert!(Backend::Donna.verify(&public, b"feeless", &high).is_err());
+    }
+
+    #[test]
+    fn names() {
+        assert_eq!(Backend::from_str("donna").unwrap(), Backend::Donna);
+        assert_eq!(Backend::Dalek.to_string(), "dalek");
+        assert!(Backend::from_str("sodium").is_err());
+    }
+}
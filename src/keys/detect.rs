@@ -0,0 +1,189 @@
+//! Working out what kind of key or hash a string is, for `feeless convert`.
+use crate::phrase::Language;
+use crate::{Address, Error, Phrase, Private, Public, Seed};
+use std::convert::TryFrom;
+use std::str::FromStr;
+
+/// How many words a BIP39 phrase can have.
+const PHRASE_WORDS: [usize; 5] = [12, 15, 18, 21, 24];
+
+/// A string recognised as an address, 32 bytes of hex, or an English mnemonic phrase.
+///
+/// 32 bytes of hex could be a public key, block hash, seed or private key, which look the same,
+/// so [Detected::representations] shows what each of them would convert to.
+#[derive(Debug, Clone)]
+pub enum Detected {
+    Address(Address),
+    Hex([u8; 32]),
+    Phrase(Phrase),
+}
+
+impl Detected {
+    pub fn parse(s: &str) -> crate::Result<Self> {
+        let s = s.trim();
+        if s.contains('_') {
+            return Ok(Detected::Address(Address::from_str(s)?));
+        }
+
+        let words = s.split_whitespace().count();
+        if words > 1 {
+            if !PHRASE_WORDS.contains(&words) {
+                return Err(Error::UnrecognizedInput(format!(
+                    "a phrase has 12, 15, 18, 21 or 24 words, not {}",
+                    words
+                )));
+            }
+            let words = s.split_whitespace().collect::<Vec<_>>().join(" ");
+            return Ok(Detected::Phrase(Phrase::from_words(
+                Language::English,
+                &words,
+            )?));
+        }
+
+        if s.len() == 64 {
+            let public = Public::from_str(s)?;
+            return Ok(Detected::Hex(<[u8; 32]>::try_from(public.as_bytes())?));
+        }
+
+        Err(Error::UnrecognizedInput(format!(
+            "expected an address, 64 hex characters or a phrase, got {:?}",
+            s
+        )))
+    }
+
+    /// The kind of input, e.g. `address`.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Detected::Address(_) => "address",
+            Detected::Hex(_) => "hex",
+            Detected::Phrase(_) => "phrase",
+        }
+    }
+
+    /// Everything the input converts to, as pairs of a name and a value.
+    ///
+    /// Hex is shown as a public key or block hash, then as a private key, then as a seed at
+    /// index 0. A phrase is shown at account 0 without a passphrase.
+    pub fn representations(&self) -> crate::Result<Vec<(&'static str, String)>> {
+        Ok(match self {
+            Detected::Address(address) => vec![
+                ("address", address.to_string()),
+                ("public_key", address.to_public().to_string()),
+            ],
+            Detected::Hex(bytes) => {
+                let public = Public::try_from(bytes.as_ref())?;
+                let private = Private::try_from(bytes.as_ref())?;
+                let derived = Seed::try_from(bytes.as_ref())?.derive(0);
+                let private_public = private.to_public()?;
+                let derived_public = derived.to_public()?;
+                vec![
+                    ("hex", public.to_string()),
+                    ("public_key_address", public.to_address().to_string()),
+                    ("private_key_public_key", private_public.to_string()),
+                    (
+                        "private_key_address",
+                        private_public.to_address().to_string(),
+                    ),
+                    ("seed_private_key", derived.to_string()),
+                    ("seed_public_key", derived_public.to_string()),
+                    ("seed_address", derived_public.to_address().to_string()),
+                ]
+            }
+            Detected::Phrase(phrase) => {
+                let private = phrase.to_private(0, "")?;
+                let public = private.to_public()?;
+                vec![
+                    ("private_key", private.to_string()),
+                    ("public_key", public.to_string()),
+                    ("address", public.to_address().to_string()),
+                ]
+            }
+        })
+    }
+}
+
+impl FromStr for Detected {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Detected::parse(s)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn value(detected: &Detected, name: &str) -> String {
+        detected
+            .representations()
+            .unwrap()
+            .into_iter()
+            .find(|(n, _)| *n == name)
+            .unwrap()
+            .1
+    }
+
+    #[test]
+    fn address() {
+        let detected =
+            Detected::parse("nano_3i1aq1cchnmbn9x5rsbap8b15akfh7wj7pwskuzi7ahz8oq6cobd99d4r3b7")
+                .unwrap();
+        assert_eq!(detected.kind(), "address");
+        assert_eq!(
+            value(&detected, "public_key"),
+            "C008B814A7D269A1FA3C6528B19201A24D797912DB9996FF02A1FF356E45552B"
+        );
+    }
+
+    #[test]
+    fn hex() {
+        let detected = Detected::parse(&"0".repeat(64)).unwrap();
+        assert_eq!(detected.kind(), "hex");
+        assert_eq!(
+            value(&detected, "seed_private_key"),
+            "9F0E444C69F77A49BD0BE89DB92C38FE713E0963165CCA12FAF5712D7657120F"
+        );
+        assert_eq!(
+            value(&detected, "seed_address"),
+            "nano_3i1aq1cchnmbn9x5rsbap8b15akfh7wj7pwskuzi7ahz8oq6cobd99d4r3b7"
+        );
+
+        let lower =
+            Detected::parse("c008b814a7d269a1fa3c6528b19201a24d797912db9996ff02a1ff356e45552b")
+                .unwrap();
+        assert_eq!(
+            value(&lower, "public_key_address"),
+            "nano_3i1aq1cchnmbn9x5rsbap8b15akfh7wj7pwskuzi7ahz8oq6cobd99d4r3b7"
+        );
+    }
+
+    #[test]
+    fn phrase() {
+        // From https://docs.nano.org/integration-guides/key-management/#mnemonic-seed
+        let detected = Detected::parse(
+            "edge defense waste choose enrich upon flee junk siren film clown finish \
+            luggage leader kid quick brick print evidence swap drill paddle truly occur",
+        )
+        .unwrap();
+        assert_eq!(detected.kind(), "phrase");
+        // The docs use a passphrase, which gives a different address.
+        assert_eq!(
+            value(&detected, "address"),
+            "nano_3yyipbgtnd7183k61nkh5mxnt9wpsfhto95mksdqj6s7p45mwj9osai7asad"
+        );
+    }
+
+    #[test]
+    fn unrecognized() {
+        assert!(matches!(
+            Detected::parse("edge defense waste"),
+            Err(Error::UnrecognizedInput(_))
+        ));
+        assert!(matches!(
+            Detected::parse("ABCD"),
+            Err(Error::UnrecognizedInput(_))
+        ));
+        assert!(Detected::parse(&"Z".repeat(64)).is_err());
+    }
+}
@@ -1,5 +1,8 @@
 pub mod address;
 pub mod armor;
+pub mod backend;
+pub mod detect;
+pub mod ownership;
 pub mod phrase;
 pub mod private;
 pub mod public;
@@ -64,4 +67,15 @@ mod tests {
             "nano_1gaki4rjgawxdx7338dsd81f6rebao5qefaonu61jjks6rm1zdrium1f994m"
         );
     }
+
+    #[test]
+    fn derive_many() {
+        let seed = Seed::random();
+        let derived = seed.derive_many(10..1010);
+        assert_eq!(derived.len(), 1000);
+        for (index, private) in derived {
+            assert_eq!(private.to_string(), seed.derive(index).to_string());
+        }
+        assert!(seed.derive_many(5..5).is_empty());
+    }
 }
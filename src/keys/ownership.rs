@@ -0,0 +1,168 @@
+use crate::{Address, Error, Private, Result, Signature};
+use chrono::{DateTime, SecondsFormat, TimeZone, Utc};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+
+/// A signed claim that the holder of an address's private key agreed to a message, for example
+/// for an exchange to check that a user controls a withdrawal address.
+///
+/// The verifier should hand out the nonce so that a proof can't be replayed to them. The proof
+/// is serialized as JSON, and the signature covers [OwnershipProof::payload].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OwnershipProof {
+    pub address: Address,
+    pub nonce: String,
+    pub message: String,
+    pub expires: DateTime<Utc>,
+    pub signature: Signature,
+}
+
+impl OwnershipProof {
+    const HEADER: &'static str = "Nano address ownership proof";
+
+    /// Sign a claim with `private`. The expiry is truncated to whole seconds.
+    pub fn create(
+        private: &Private,
+        nonce: String,
+        message: String,
+        expires: DateTime<Utc>,
+    ) -> Result<Self> {
+        if nonce.is_empty() || nonce.contains('\n') {
+            return Err(Error::InvalidProof(
+                "Nonce must be a single non-empty line".into(),
+            ));
+        }
+
+        let address = private.to_public()?.to_address();
+        let expires = Utc.timestamp(expires.timestamp(), 0);
+        let payload = payload(&address, &nonce, &message, &expires);
+        let signature = private.sign(payload.as_bytes())?;
+        Ok(Self {
+            address,
+            nonce,
+            message,
+            expires,
+            signature,
+        })
+    }
+
+    /// A random 128 bit hex nonce, for when the verifier didn't supply one.
+    pub fn random_nonce() -> String {
+        let mut nonce = [0u8; 16];
+        rand::thread_rng().fill_bytes(&mut nonce);
+        hex::encode_upper(nonce)
+    }
+
+    /// The exact text that is signed.
+    pub fn payload(&self) -> String {
+        payload(&self.address, &self.nonce, &self.message, &self.expires)
+    }
+
+    pub fn verify(&self) -> Result<()> {
+        self.verify_at(Utc::now())
+    }
+
+    /// Check the signature, and that the proof hasn't expired at `now`.
+    pub fn verify_at(&self, now: DateTime<Utc>) -> Result<()> {
+        if now >= self.expires {
+            return Err(Error::ProofExpired(self.expires));
+        }
+        self.address
+            .to_public()
+            .verify(self.payload().as_bytes(), &self.signature)
+    }
+
+    /// Like [OwnershipProof::verify], also checking that it was made for the nonce we issued.
+    pub fn verify_nonce(&self, nonce: &str) -> Result<()> {
+        if self.nonce != nonce {
+            return Err(Error::InvalidProof(format!(
+                "Nonce mismatch: Expecting: {} Got: {}",
+                nonce, self.nonce
+            )));
+        }
+        self.verify()
+    }
+}
+
+fn payload(address: &Address, nonce: &str, message: &str, expires: &DateTime<Utc>) -> String {
+    format!(
+        "{}\naddress: {}\nnonce: {}\nexpires: {}\nmessage: {}",
+        OwnershipProof::HEADER,
+        address,
+        nonce,
+        expires.to_rfc3339_opts(SecondsFormat::Secs, true),
+        message
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Seed;
+    use chrono::Duration;
+
+    fn signed() -> OwnershipProof {
+        let private = Seed::zero().derive(0);
+        OwnershipProof::create(
+            &private,
+            "abc123".into(),
+            "Withdrawals to this address".into(),
+            Utc.ymd(2030, 1, 1).and_hms_milli(12, 0, 0, 500),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn round_trip() {
+        let proof = signed();
+        assert_eq!(proof.expires, Utc.ymd(2030, 1, 1).and_hms(12, 0, 0));
+        assert!(proof
+            .payload()
+            .contains("\nexpires: 2030-01-01T12:00:00Z\n"));
+
+        let json = serde_json::to_string(&proof).unwrap();
+        let decoded: OwnershipProof = serde_json::from_str(&json).unwrap();
+        assert_eq!(decoded, proof);
+        decoded
+            .verify_at(Utc.ymd(2029, 1, 1).and_hms(0, 0, 0))
+            .unwrap();
+    }
+
+    #[test]
+    fn expired() {
+        let proof = signed();
+        let now = proof.expires + Duration::seconds(1);
+        assert!(matches!(proof.verify_at(now), Err(Error::ProofExpired(_))));
+    }
+
+    #[test]
+    fn tampered() {
+        let now = Utc.ymd(2029, 1, 1).and_hms(0, 0, 0);
+
+        let mut proof = signed();
+        proof.message.push('!');
+        assert!(proof.verify_at(now).is_err());
+
+        let mut proof = signed();
+        proof.expires = proof.expires + Duration::days(1);
+        assert!(proof.verify_at(now).is_err());
+
+        let mut proof = signed();
+        proof.address = Seed::zero().derive(1).to_public().unwrap().to_address();
+        assert!(proof.verify_at(now).is_err());
+    }
+
+    #[test]
+    fn nonce() {
+        let proof = signed();
+        assert!(matches!(
+            proof.verify_nonce("other"),
+            Err(Error::InvalidProof(_))
+        ));
+
+        let private = Seed::zero().derive(0);
+        let multi_line = "a\nb".to_string();
+        assert!(OwnershipProof::create(&private, multi_line, "".into(), Utc::now()).is_err());
+        assert_eq!(OwnershipProof::random_nonce().len(), 32);
+    }
+}
@@ -8,6 +8,7 @@ use ed25519_dalek_bip32::{DerivationPath, ExtendedSecretKey};
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use std::convert::TryFrom;
 use std::fmt::{Display, Formatter};
+use std::ops::Range;
 use std::str::FromStr;
 
 static LANGUAGES: &str = "en, zh-hans, zh-hant, fr, it, ja, ko, es";
@@ -59,9 +60,9 @@ impl From<bip39::Language> for Language {
     }
 }
 
-impl Into<bip39::Language> for Language {
-    fn into(self) -> bip39::Language {
-        match self {
+impl From<Language> for bip39::Language {
+    fn from(val: Language) -> Self {
+        match val {
             Language::English => bip39::Language::English,
             Language::ChineseSimplified => bip39::Language::ChineseSimplified,
             Language::ChineseTraditional => bip39::Language::ChineseTraditional,
@@ -112,7 +113,7 @@ impl Phrase {
         let m = Mnemonic::new(len, language.to_owned().into());
         Self {
             entropy: Entropy(m.entropy().to_vec()),
-            language: language.into(),
+            language,
         }
     }
 
@@ -134,17 +135,45 @@ impl Phrase {
     ) -> Result<ExtendedSecretKey, Error> {
         let bip39_seed = self.to_bip39_seed(passphrase)?;
         let key = ExtendedSecretKey::from_seed(bip39_seed.as_bytes())?;
-        let path = format!("m/44'/165'/{}'", account);
-        let path: DerivationPath = path.parse().unwrap();
+        let path: DerivationPath = Self::bip44_path(account).parse().unwrap();
         let derived = key.derive(&path)?;
 
         Ok(derived)
     }
 
+    /// The BIP44 path of an account, with Nano's coin type of 165. Every level is hardened,
+    /// since ed25519 keys can't be derived otherwise.
+    pub fn bip44_path(account: u32) -> String {
+        format!("m/44'/165'/{}'", account)
+    }
+
+    /// The key at [Phrase::bip44_path] without a passphrase, the same as Ledger and Trust Wallet
+    /// derive for the phrase.
+    pub fn to_bip44_private(&self, account: u32) -> Result<Private, Error> {
+        self.to_private(account, "")
+    }
+
     pub fn to_private(&self, account: u32, passphrase: &str) -> Result<Private, Error> {
         let ext_key = self.to_bip32_ext_key(account, passphrase)?;
         let bip39_seed = ext_key.secret_key.as_ref();
-        Ok(Private::try_from(bip39_seed)?)
+        Private::try_from(bip39_seed)
+    }
+
+    /// The keys of each of `accounts`, like [Phrase::to_private], stretching the phrase into a
+    /// seed only once, which is most of the work.
+    pub fn to_privates(
+        &self,
+        accounts: Range<u32>,
+        passphrase: &str,
+    ) -> Result<Vec<Private>, Error> {
+        let bip39_seed = self.to_bip39_seed(passphrase)?;
+        let key = ExtendedSecretKey::from_seed(bip39_seed.as_bytes())?;
+        accounts
+            .map(|account| {
+                let path: DerivationPath = Self::bip44_path(account).parse().unwrap();
+                Private::try_from(key.derive(&path)?.secret_key.as_ref())
+            })
+            .collect()
     }
 
     pub fn from_words(language: Language, words: &str) -> Result<Self, Error> {
@@ -199,4 +228,45 @@ mod tests {
             "nano_1pu7p5n3ghq1i1p4rhmek41f5add1uh34xpb94nkbxe8g4a6x1p69emk8y1d"
         );
     }
+
+    #[test]
+    fn bip44() {
+        // Test vectors from:
+        // https://docs.nano.org/integration-guides/key-management/#test-vectors
+        let phrase = Phrase::from_words(
+            Language::English,
+            "edge defense waste choose enrich upon flee junk siren film clown finish \
+            luggage leader kid quick brick print evidence swap drill paddle truly occur",
+        )
+        .unwrap();
+        assert_eq!(Phrase::bip44_path(1), "m/44'/165'/1'");
+
+        let hex = |private: &Private| format!("{:0X}", private);
+        let address = |private: &Private| private.to_public().unwrap().to_address().to_string();
+        let expected = [
+            (
+                "3BE4FC2EF3F3B7374E6FC4FB6E7BB153F8A2998B3B3DAB50853EABE128024143",
+                "nano_1pu7p5n3ghq1i1p4rhmek41f5add1uh34xpb94nkbxe8g4a6x1p69emk8y1d",
+            ),
+            (
+                "CE7E429E683D652446261C17A96DA9ED1897AEA96C8046F2B8036F6B05CB1A83",
+                "nano_3phqgrqbso99xojkb1bijmfryo7dy1k38ep1o3k3yrhb7rqu1h1k47yu78gz",
+            ),
+        ];
+        let privates = phrase.to_privates(0..2, "some password").unwrap();
+        for (account, (private, (hex_key, addr))) in privates.iter().zip(&expected).enumerate() {
+            assert_eq!(&hex(private), hex_key);
+            assert_eq!(&address(private), addr);
+            let single = phrase.to_private(account as u32, "some password").unwrap();
+            assert_eq!(&hex(&single), hex_key);
+        }
+
+        // Without a passphrase, as Ledger and Trust Wallet derive.
+        let first = phrase.to_bip44_private(0).unwrap();
+        assert_eq!(
+            address(&first),
+            "nano_3yyipbgtnd7183k61nkh5mxnt9wpsfhto95mksdqj6s7p45mwj9osai7asad"
+        );
+        assert_eq!(hex(&first), hex(&phrase.to_private(0, "").unwrap()));
+    }
 }
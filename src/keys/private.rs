@@ -1,8 +1,7 @@
+use crate::keys::backend::Backend;
 use crate::{hexify, Address, Error, Public, Signature};
-use ed25519_dalek::ed25519::signature::Signature as InternalSignature;
 use ed25519_dalek::ExpandedSecretKey;
 use rand::RngCore;
-use std::convert::TryFrom;
 
 /// 256 bit private key which can generate a public key.
 #[derive(Clone)]
@@ -34,23 +33,21 @@ impl Private {
     /// # }
     /// ```
     pub fn to_public(&self) -> Result<Public, Error> {
-        Ok(Public::from(self.internal_public()?))
-    }
-
-    pub(crate) fn internal_public(&self) -> Result<ed25519_dalek::PublicKey, Error> {
-        let dalek = self.to_ed25519_dalek()?;
-        Ok(ed25519_dalek::PublicKey::from(&dalek))
+        Backend::current().public(self)
     }
 
     pub fn to_address(&self) -> Result<Address, Error> {
         Ok(self.to_public()?.to_address())
     }
 
+    /// Sign with the [Backend] currently selected.
     pub fn sign(&self, message: &[u8]) -> Result<Signature, Error> {
-        let dalek = self.to_ed25519_dalek()?;
-        let expanded_secret = ExpandedSecretKey::from(&dalek);
-        let internal_signed = expanded_secret.sign(message, &self.internal_public()?);
-        Signature::try_from(internal_signed.as_bytes())
+        Backend::current().sign(self, message)
+    }
+
+    /// The scalar and signing nonce the key is expanded into, hashed with blake2b.
+    pub(crate) fn to_expanded(&self) -> Result<ExpandedSecretKey, Error> {
+        Ok(ExpandedSecretKey::from(&self.to_ed25519_dalek()?))
     }
 
     // Not public because we don't want users to accidentally generate this key.
@@ -59,12 +56,10 @@ impl Private {
     }
 
     fn to_ed25519_dalek(&self) -> Result<ed25519_dalek::SecretKey, Error> {
-        Ok(
-            ed25519_dalek::SecretKey::from_bytes(&self.0).map_err(|e| Error::SignatureError {
-                msg: String::from("Converting to SecretKey"),
-                source: e,
-            })?,
-        )
+        ed25519_dalek::SecretKey::from_bytes(&self.0).map_err(|e| Error::SignatureError {
+            msg: String::from("Converting to SecretKey"),
+            source: e,
+        })
     }
 }
 
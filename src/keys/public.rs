@@ -1,3 +1,6 @@
+#[cfg(feature = "node")]
+use bytes::BytesMut;
+
 #[cfg(feature = "node")]
 use crate::node::Wire;
 
@@ -5,16 +8,16 @@ use crate::node::Wire;
 use crate::node::Header;
 
 use crate::hexify;
+use crate::keys::backend::Backend;
 use crate::Error;
 use crate::{encoding, Address, Signature};
 use bitvec::prelude::*;
-use ed25519_dalek::Verifier;
 use serde::{Deserialize, Deserializer, Serializer};
 use std::iter::FromIterator;
 use std::str::FromStr;
 
 /// 256 bit public key which can be converted into an [Address](crate::Address) or verify a [Signature](crate::Signature).
-#[derive(Clone, Eq, PartialEq, Hash)]
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
 pub struct Public([u8; Public::LEN]);
 
 hexify!(Public, "public key");
@@ -23,13 +26,8 @@ impl Public {
     pub const LEN: usize = 32;
     const ADDRESS_CHECKSUM_LEN: usize = 5;
 
-    fn dalek_key(&self) -> Result<ed25519_dalek::PublicKey, Error> {
-        Ok(
-            ed25519_dalek::PublicKey::from_bytes(&self.0).map_err(|e| Error::SignatureError {
-                msg: String::from("Converting to PublicKey"),
-                source: e,
-            })?,
-        )
+    pub(crate) fn zero() -> Self {
+        Self([0u8; Public::LEN])
     }
 
     pub fn to_address(&self) -> Address {
@@ -43,25 +41,10 @@ impl Public {
         encoding::encode_nano_base_32(&bits)
     }
 
+    /// Verify with the [Backend] currently selected. A key that isn't a point on the curve
+    /// gives [Error::BadPublicKey].
     pub fn verify(&self, message: &[u8], signature: &Signature) -> Result<(), Error> {
-        let result = self.dalek_key();
-
-        match result {
-            Ok(key) => {
-                key.verify(message, &signature.internal())
-                    .map_err(|e| Error::SignatureError {
-                        msg: format!(
-                            "Public verification failed: sig: {:?} message: {:?} key: {:?}",
-                            signature, message, key
-                        ),
-                        source: e,
-                    })
-            }
-            // We're returning false here because someone we can be given a bad public key,
-            // but since we're not checking the key for how valid it is, only the signature,
-            // we just say that it does not pass validation.
-            _ => Err(Error::BadPublicKey),
-        }
+        Backend::current().verify(self, message, signature)
     }
 }
 
@@ -73,7 +56,7 @@ impl From<ed25519_dalek::PublicKey> for Public {
 
 #[cfg(feature = "node")]
 impl Wire for Public {
-    fn serialize(&self) -> Vec<u8> {
+    fn serialize_into(&self, _buf: &mut BytesMut) {
         unimplemented!()
     }
 
@@ -115,7 +98,7 @@ where
 #[cfg(test)]
 mod tests {
     use super::Public;
-    use crate::Private;
+    use crate::{HexBytes, Private};
     use std::convert::TryFrom;
     use std::str::FromStr;
 
@@ -138,6 +121,6 @@ mod tests {
     #[test]
     fn hex() {
         let s = "19D3D919475DEED4696B5D13018151D1AF88B2BD3BCFF048B45031C1F36D1858";
-        assert_eq!(s, &Public::from_str(&s).unwrap().as_hex());
+        assert_eq!(s, &Public::from_str(s).unwrap().to_hex());
     }
 }
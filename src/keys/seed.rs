@@ -3,7 +3,9 @@ use crate::hexify;
 use crate::Private;
 use bytes::{BufMut, BytesMut};
 use rand::RngCore;
+use rayon::prelude::*;
 use std::convert::TryFrom;
+use std::ops::Range;
 
 /// 256 bit seed used to derive multiple addresses.
 ///
@@ -40,4 +42,14 @@ impl Seed {
         // Expect this to work all the time because it's coming from known correct types.
         Private::try_from(result.as_ref()).expect("conversion from seed")
     }
+
+    /// Derive the private keys for a range of indexes, spread over all CPUs.
+    ///
+    /// The keys are returned in index order.
+    pub fn derive_many(&self, indexes: Range<u32>) -> Vec<(u32, Private)> {
+        indexes
+            .into_par_iter()
+            .map(|index| (index, self.derive(index)))
+            .collect()
+    }
 }
@@ -50,21 +50,40 @@ mod pcap;
 pub mod cli;
 
 pub mod blocks;
+pub mod build_info;
 mod bytes;
+#[cfg(feature = "camo")]
+pub mod camo;
+mod config;
 mod encoding;
 mod errors;
 mod keys;
 mod network;
 mod paths;
 mod pow;
+pub mod qr;
 pub mod rpc;
+#[cfg(feature = "schema")]
+pub mod schema;
+mod selftest;
+pub mod snapshot;
 pub mod units;
+pub mod uri;
 pub mod vanity;
 mod version;
 pub mod wallet;
 
-pub use errors::{Error, Result};
+#[cfg(feature = "watch")]
+pub mod watch;
+
+pub use build_info::{build_info, BuildInfo};
+pub use config::Config;
+pub use encoding::HexBytes;
+pub use errors::{Error, Result, RpcErrorKind};
 pub use keys::address::Address;
+pub use keys::backend::Backend;
+pub use keys::detect::Detected;
+pub use keys::ownership::OwnershipProof;
 pub use keys::phrase;
 pub use keys::phrase::Phrase;
 pub use keys::private::Private;
@@ -72,6 +91,25 @@ pub use keys::public::Public;
 pub use keys::seed::Seed;
 pub use keys::signature::Signature;
 pub use network::{Network, DEFAULT_PORT};
-pub use pow::{Difficulty, Subject, Work};
+#[cfg(feature = "test-util")]
+pub use node::script;
+#[cfg(feature = "node")]
+pub use node::{
+    state, Confirmation, ForkEvent, ForkWatch, FrontierEvents, Node, NodeClient, PruneStats,
+    PruningConfig, RetentionPolicy,
+};
+#[cfg(feature = "bench")]
+#[doc(hidden)]
+pub use node::{EarlyMessages, Packet, Peer};
+#[cfg(feature = "rpc_client")]
+pub use pow::RPCWorkPublisher;
+pub use pow::{
+    Benchmark, BenchmarkResult, Difficulty, Multiplier, Upgrade, WatchOutcome, Work, WorkManager,
+    WorkPublisher, WorkThresholds, WorkWatcher,
+};
+#[cfg(feature = "rpc_client")]
+pub use pow::{WorkPeers, WorkServerClient, WorkServerError};
+pub use tokio_util::sync::CancellationToken;
 pub use units::raw::Raw;
+pub use uri::NanoUri;
 pub use version::Version;
@@ -1,5 +1,5 @@
 use crate::blocks::{Block, BlockHash, OpenBlock, Previous};
-use crate::Raw;
+use crate::{Difficulty, Raw};
 use anyhow::anyhow;
 use std::convert::TryFrom;
 use std::str::FromStr;
@@ -9,6 +9,9 @@ use strum_macros::{Display, EnumString};
 pub const DEFAULT_PORT: u16 = 7075;
 
 /// Network to use: Test, Beta, Live.
+///
+/// `Test` is the reference node's local development network. Addresses are `nano_` on every
+/// network, so only the genesis, peering, work thresholds and message headers differ.
 #[derive(Debug, Clone, Copy, PartialEq, Display, EnumString)]
 #[strum(serialize_all = "snake_case")]
 pub enum Network {
@@ -17,9 +20,13 @@ pub enum Network {
     Live = 0x43,
 }
 
+fn genesis_open_block(json: &str) -> OpenBlock {
+    serde_json::from_str(json).unwrap()
+}
+
 fn live_genesis_block() -> OpenBlock {
-    serde_json::from_str(
-    r#"
+    genesis_open_block(
+        r#"
         {
             "type": "open",
             "source": "E89208DD038FBB269987689621D52292AE9C35941A7484756ECCED92A65093BA",
@@ -28,15 +35,46 @@ fn live_genesis_block() -> OpenBlock {
             "work": "62F05417DD3FB691",
             "signature": "9F0C933C8ADE004D808EA1985FA746A7E95BA2A38F867640F53EC8F180BDFE9E2C1268DEAD7C2664F356E37ABA362BC58E46DBA03E523A7B5A19E4B6EB12BB02"
         }
-        "#
-    ).unwrap()
+        "#,
+    )
+}
+
+fn beta_genesis_block() -> OpenBlock {
+    genesis_open_block(
+        r#"
+        {
+            "type": "open",
+            "source": "259A43ABDB779E97452E188BA3EB951B41C961D3318CA6B925380F4D99F0577A",
+            "representative": "nano_1betagoxpxwykx4kw86dnhosc8t3s7ix8eeentwkcg1hbpez1outjrcyg4n1",
+            "account": "nano_1betagoxpxwykx4kw86dnhosc8t3s7ix8eeentwkcg1hbpez1outjrcyg4n1",
+            "work": "79D4E27DC873C6F2",
+            "signature": "4BD7F96F9ED2721BCEE5EAED400EA50AD00524C629AE55E9AFF11220D2C1B00C3D4B3BB770BF67D4F8658023B677F91110193B6C101C2666931F57046A6DB806"
+        }
+        "#,
+    )
+}
+
+fn test_genesis_block() -> OpenBlock {
+    genesis_open_block(
+        r#"
+        {
+            "type": "open",
+            "source": "B0311EA55708D6A53C75CDBF88300259C6D018522FE3D4D0A242E431F9E8B6D0",
+            "representative": "nano_3e3j5tkog48pnny9dmfzj1r16pg8t1e76dz5tmac6iq689wyjfpiij4txtdo",
+            "account": "nano_3e3j5tkog48pnny9dmfzj1r16pg8t1e76dz5tmac6iq689wyjfpiij4txtdo",
+            "work": "7B42A00EE91D5810",
+            "signature": "ECDA914373A2F0CA1296475BAEE40500A7F0A7AD72A5A80C81D7FAB7F6C802B2CC7DB50F5DD0FB25B2EF11761FA7344A158DD5A700B21BD47DE5BD0F63153A02"
+        }
+        "#,
+    )
 }
 
 impl Network {
     pub fn genesis_block(&self) -> Block {
         let open_block = match self {
             Self::Live => live_genesis_block(),
-            _ => todo!(),
+            Self::Beta => beta_genesis_block(),
+            Self::Test => test_genesis_block(),
         };
 
         // Give the genesis block the maximum u128 value.
@@ -46,19 +84,57 @@ impl Network {
     }
 
     pub fn genesis_hash(&self) -> BlockHash {
-        match self {
-            Self::Live => BlockHash::from_str(
-                "991CF190094C00F0B68E2E5F75F6BEE95A2E0BD93CEAA4A6734DB9F19B728948",
-            )
-            .unwrap(),
-            _ => todo!(),
-        }
+        let hash = match self {
+            Self::Live => "991CF190094C00F0B68E2E5F75F6BEE95A2E0BD93CEAA4A6734DB9F19B728948",
+            Self::Beta => "01A92459E69440D5C1088D3B31F4CA678BE944BAB3776C2E6B7665E9BD99BD5A",
+            Self::Test => "04270D7F11C4B2B472F2854C5A59F2A7E84226CE9ED799DE75744BD7D85FC9D9",
+        };
+        BlockHash::from_str(hash).unwrap()
     }
 
+    /// Where to look up the initial peers. There's no public test network, so that looks for a
+    /// node on the same machine.
     pub fn peering_host(&self) -> &str {
         match self {
             Self::Live => "peering.nano.org:7075",
-            _ => todo!(),
+            Self::Beta => "peering-beta.nano.org:54000",
+            Self::Test => "localhost:44000",
+        }
+    }
+
+    /// The TCP port that nodes on this network use by default.
+    pub fn default_port(&self) -> u16 {
+        match self {
+            Self::Live => DEFAULT_PORT,
+            Self::Beta => 54000,
+            Self::Test => 44000,
+        }
+    }
+
+    /// The work threshold for send and change blocks.
+    pub fn send_difficulty(&self) -> Difficulty {
+        match self {
+            Self::Live => Difficulty::normal(),
+            Self::Beta => Difficulty::new(0xfffff00000000000),
+            Self::Test => Difficulty::new(0xffc0000000000000),
+        }
+    }
+
+    /// The work threshold for receive, open and epoch blocks.
+    pub fn receive_difficulty(&self) -> Difficulty {
+        match self {
+            Self::Live => Difficulty::receive(),
+            Self::Beta => Difficulty::new(0xffffe00000000000),
+            Self::Test => Difficulty::new(0xf000000000000000),
+        }
+    }
+
+    /// The work threshold before epoch 2, which every legacy block was made at.
+    pub fn legacy_difficulty(&self) -> Difficulty {
+        match self {
+            Self::Live => Difficulty::new(0xffffffc000000000),
+            Self::Beta => Difficulty::new(0xfffff00000000000),
+            Self::Test => Difficulty::new(0xfe00000000000000),
         }
     }
 }
@@ -88,4 +164,18 @@ mod tests {
         let hash = block.hash().unwrap();
         assert_eq!(hash, &net.genesis_hash());
     }
+
+    #[test]
+    fn genesis_blocks() {
+        for net in &[Network::Live, Network::Beta, Network::Test] {
+            let block = net.genesis_block();
+            assert_eq!(block.hash().unwrap(), &net.genesis_hash(), "{}", net);
+            block.verify_signature(block.account()).unwrap();
+            let work = block.work().unwrap();
+            assert!(work
+                .verify(&block.work_root(), &net.legacy_difficulty())
+                .unwrap());
+        }
+        assert_eq!(Network::from_str("beta").unwrap(), Network::Beta);
+    }
 }
@@ -0,0 +1,70 @@
+use crate::blocks::{BlockType, StateBlock};
+use crate::network::Network;
+use crate::node::bootstrap::frontiers::CONNECT_TIMEOUT;
+use crate::node::header::{Header, MessageType};
+use crate::node::messages::bulk_pull::BulkPull;
+use crate::node::wire::Wire;
+use anyhow::{anyhow, Context};
+use bytes::BytesMut;
+use std::convert::TryFrom;
+use std::net::SocketAddr;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+use tokio::time::timeout;
+
+/// How long to wait for each block before giving up on the peer.
+pub const BLOCK_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Stream the blocks asked for by `pull` from a peer, newest first unless it's ascending.
+///
+/// `on_block` is called with each block, and the stream is stopped early when it returns false.
+/// Only state blocks are supported, so a chain with legacy blocks is an error.
+pub async fn pull_blocks<F>(
+    network: Network,
+    address: SocketAddr,
+    pull: &BulkPull,
+    mut on_block: F,
+) -> anyhow::Result<()>
+where
+    F: FnMut(StateBlock) -> bool,
+{
+    let stream = timeout(CONNECT_TIMEOUT, TcpStream::connect(address))
+        .await
+        .with_context(|| format!("Timed out connecting to {}", address))?
+        .with_context(|| format!("Connecting to {}", address))?;
+    let (tcp_in, mut tcp_out) = stream.into_split();
+
+    let mut buf = BytesMut::new();
+    Header::new(network, MessageType::BulkPull, pull.extensions()).serialize_into(&mut buf);
+    pull.serialize_into(&mut buf);
+    tcp_out.write_all(&buf).await.context("Sending bulk pull")?;
+
+    let mut tcp_in = BufReader::new(tcp_in);
+    let mut data = [0u8; StateBlock::LEN];
+    loop {
+        let block_type = timeout(BLOCK_TIMEOUT, tcp_in.read_u8())
+            .await
+            .with_context(|| format!("Timed out waiting for a block from {}", address))?
+            .with_context(|| format!("Reading block type from {}", address))?;
+        match BlockType::try_from(block_type)? {
+            BlockType::NotABlock => return Ok(()),
+            BlockType::State => {}
+            block_type => {
+                return Err(anyhow!(
+                    "{:?} blocks aren't supported yet, from {}",
+                    block_type,
+                    address
+                ))
+            }
+        }
+        timeout(BLOCK_TIMEOUT, tcp_in.read_exact(&mut data))
+            .await
+            .with_context(|| format!("Timed out waiting for a block from {}", address))?
+            .with_context(|| format!("Reading block from {}", address))?;
+        let block = StateBlock::deserialize(None, &data)?;
+        if !on_block(block) {
+            return Ok(());
+        }
+    }
+}
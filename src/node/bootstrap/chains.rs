@@ -0,0 +1,501 @@
+use crate::blocks::{Block, BlockHash, Previous, StateBlock};
+use crate::network::Network;
+use crate::node::bootstrap::blocks::pull_blocks;
+use crate::node::events::FrontierEvents;
+use crate::node::intake::RejectReason;
+use crate::node::journal::Journal;
+use crate::node::messages::bulk_pull::BulkPull;
+use crate::node::process;
+use crate::node::state::ArcState;
+use crate::Public;
+use anyhow::anyhow;
+use std::collections::{HashMap, VecDeque};
+use std::fmt::{Display, Formatter};
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, warn};
+
+/// How many times an account goes back in the queue when one of its receives comes before the
+/// send it receives has been pulled from the other account.
+const GAP_RETRIES: usize = 3;
+
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize)]
+pub struct ChainStats {
+    /// Accounts with a frontier to pull.
+    pub accounts: usize,
+
+    /// Accounts that have been pulled, or skipped because there was nothing to pull.
+    pub accounts_done: usize,
+
+    /// Accounts that already had the same frontier in the state.
+    pub up_to_date: usize,
+
+    pub blocks_added: usize,
+
+    /// Accounts where the state has a block the peer's chain doesn't, from a fork or from
+    /// being ahead of the peer. They're left alone.
+    pub diverged: usize,
+
+    /// Accounts with a block that failed to verify, or a gap in the chain that was sent. The
+    /// blocks before the first bad one are kept.
+    pub invalid: usize,
+
+    /// Peers that were dropped because of an error.
+    pub failed_peers: usize,
+}
+
+impl Display for ChainStats {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "Pulled {} of {} accounts, {} already up to date",
+            self.accounts_done, self.accounts, self.up_to_date
+        )?;
+        writeln!(f, "Added {} blocks", self.blocks_added)?;
+        writeln!(
+            f,
+            "Skipped {} diverged and {} invalid accounts, dropped {} peers",
+            self.diverged, self.invalid, self.failed_peers
+        )
+    }
+}
+
+/// Pull the account chains below a set of frontiers into the state, from several peers.
+///
+/// Each peer takes the next account from a shared queue and bulk pulls the blocks between its
+/// frontier and the frontier in the state. The chain has to link up and be signed by the account
+/// before any of it is added. Each block is then checked like a processed block, for its work,
+/// the amount it moves and gaps, and stored along with its pending entry, so a peer can't put
+/// invalid blocks in the state. A peer that fails is dropped and the account it was pulling goes
+/// back in the queue for the others.
+///
+/// A receive can arrive before the send it receives, which is in another account. The account is
+/// then put at the back of the queue to be pulled again from that block, up to [GAP_RETRIES]
+/// times.
+pub struct ChainBootstrap {
+    network: Network,
+    peers: Vec<SocketAddr>,
+    store: Store,
+    queue: Arc<Mutex<VecDeque<Queued>>>,
+    stats: Arc<Mutex<ChainStats>>,
+}
+
+/// An account waiting to be pulled.
+struct Queued {
+    account: Public,
+    frontier: BlockHash,
+
+    /// How many times the account has been put back because of a missing send.
+    gaps: usize,
+}
+
+/// Where the pulled blocks go.
+#[derive(Clone)]
+struct Store {
+    state: ArcState,
+    journal: Journal,
+    events: FrontierEvents,
+}
+
+impl ChainBootstrap {
+    pub fn new(
+        network: Network,
+        peers: Vec<SocketAddr>,
+        state: ArcState,
+        frontiers: HashMap<Public, BlockHash>,
+    ) -> Self {
+        let stats = ChainStats {
+            accounts: frontiers.len(),
+            ..Default::default()
+        };
+        let queue = frontiers
+            .into_iter()
+            .map(|(account, frontier)| Queued {
+                account,
+                frontier,
+                gaps: 0,
+            })
+            .collect();
+        Self {
+            network,
+            peers,
+            store: Store {
+                state,
+                journal: Journal::disabled(),
+                events: FrontierEvents::new(),
+            },
+            queue: Arc::new(Mutex::new(queue)),
+            stats: Arc::new(Mutex::new(stats)),
+        }
+    }
+
+    /// Record each added block in `journal`.
+    pub fn set_journal(&mut self, journal: Journal) {
+        self.store.journal = journal;
+    }
+
+    /// Publish the new frontier of each added block to `events`.
+    pub fn set_events(&mut self, events: FrontierEvents) {
+        self.store.events = events;
+    }
+
+    pub fn stats(&self) -> ChainStats {
+        self.stats.lock().unwrap().clone()
+    }
+
+    /// Pull every account in the queue. If all the peers fail before the queue is empty, there
+    /// will be an error.
+    ///
+    /// When `cancel` is cancelled, the chains being added are finished and then it fails with
+    /// [crate::Error::Cancelled]. The blocks added until then stay in the state.
+    pub async fn run(&self, cancel: &CancellationToken) -> anyhow::Result<ChainStats> {
+        let workers: Vec<_> = self
+            .peers
+            .iter()
+            .map(|&peer| {
+                tokio::spawn(worker(
+                    self.network,
+                    peer,
+                    self.store.clone(),
+                    self.queue.clone(),
+                    self.stats.clone(),
+                    cancel.clone(),
+                ))
+            })
+            .collect();
+        for worker in workers {
+            worker.await?;
+        }
+        if cancel.is_cancelled() {
+            return Err(crate::Error::Cancelled.into());
+        }
+
+        let left = self.queue.lock().unwrap().len();
+        if left > 0 {
+            return Err(anyhow!(
+                "All bootstrap peers failed with {} accounts left",
+                left
+            ));
+        }
+        Ok(self.stats())
+    }
+}
+
+async fn worker(
+    network: Network,
+    peer: SocketAddr,
+    store: Store,
+    queue: Arc<Mutex<VecDeque<Queued>>>,
+    stats: Arc<Mutex<ChainStats>>,
+    cancel: CancellationToken,
+) {
+    let state = &store.state;
+    while !cancel.is_cancelled() {
+        let next = match queue.lock().unwrap().pop_front() {
+            Some(next) => next,
+            None => return,
+        };
+        let Queued {
+            account, frontier, ..
+        } = &next;
+
+        let ours = match state.get_latest_block_hash_for_account(account).await {
+            Ok(ours) => ours,
+            Err(err) => {
+                warn!("Reading the frontier of {:?}: {:?}", account, err);
+                queue.lock().unwrap().push_front(next);
+                return;
+            }
+        };
+        if ours.as_ref() == Some(frontier) {
+            let mut stats = stats.lock().unwrap();
+            stats.up_to_date += 1;
+            stats.accounts_done += 1;
+            continue;
+        }
+
+        debug!("Pulling {:?} from {}", account, peer);
+        let pull = BulkPull::account(account.to_owned(), ours.to_owned());
+        let mut blocks = vec![];
+        let pulled = pull_blocks(network, peer, &pull, |block| {
+            blocks.push(block);
+            true
+        });
+        let result = tokio::select! {
+            result = pulled => result,
+            _ = cancel.cancelled() => {
+                queue.lock().unwrap().push_front(next);
+                return;
+            }
+        };
+        if let Err(err) = result {
+            warn!("Dropping bootstrap peer {}: {:?}", peer, err);
+            queue.lock().unwrap().push_front(next);
+            stats.lock().unwrap().failed_peers += 1;
+            return;
+        }
+
+        let chain = match verify_chain(account, frontier, ours.as_ref(), blocks) {
+            Ok(Some(chain)) => chain,
+            Ok(None) => {
+                warn!("Chain of {:?} has diverged from {}", account, peer);
+                let mut stats = stats.lock().unwrap();
+                stats.diverged += 1;
+                stats.accounts_done += 1;
+                continue;
+            }
+            Err(err) => {
+                warn!("Not adding {:?} from {}: {:#}", account, peer, err);
+                let mut stats = stats.lock().unwrap();
+                stats.invalid += 1;
+                stats.accounts_done += 1;
+                continue;
+            }
+        };
+        for block in chain {
+            let hash = block.hash().ok().cloned();
+            match add_block(network, &store, block).await {
+                Ok(Ok(())) => stats.lock().unwrap().blocks_added += 1,
+                Ok(Err(RejectReason::UnknownSource(source))) if next.gaps < GAP_RETRIES => {
+                    debug!(
+                        "Pulling {:?} again once {:?} might have been added",
+                        account, source
+                    );
+                    queue.lock().unwrap().push_back(Queued {
+                        gaps: next.gaps + 1,
+                        ..next
+                    });
+                    break;
+                }
+                Ok(Err(reason)) => {
+                    warn!("Not adding {:?} from {}: {}", hash, peer, reason);
+                    let mut stats = stats.lock().unwrap();
+                    stats.invalid += 1;
+                    stats.accounts_done += 1;
+                    break;
+                }
+                Err(err) => {
+                    warn!("Adding {:?}: {:?}", hash, err);
+                    stats.lock().unwrap().accounts_done += 1;
+                    break;
+                }
+            }
+            if Some(frontier) == hash.as_ref() {
+                stats.lock().unwrap().accounts_done += 1;
+            }
+        }
+    }
+}
+
+/// Check `block` like a processed block and store it. The genesis block is trusted by its hash,
+/// since it has nothing to receive from. The outer error is for problems with the state.
+async fn add_block(
+    network: Network,
+    store: &Store,
+    block: Block,
+) -> anyhow::Result<Result<(), RejectReason>> {
+    let block = if block.hash()? == &network.genesis_hash() {
+        block
+    } else {
+        match process::check(&*store.state, network, StateBlock::from(block)).await? {
+            Ok(block) => block,
+            Err(reason) => return Ok(Err(reason)),
+        }
+    };
+    process::store_block(&store.state, &store.journal, &store.events, &block).await?;
+    Ok(Ok(()))
+}
+
+/// Check that `blocks`, newest first, are a chain of `account` that goes from `frontier` back
+/// to `ours`, returning them in the order they need to be added. Returns `None` when the chain
+/// reaches the open block without finding `ours`.
+fn verify_chain(
+    account: &Public,
+    frontier: &BlockHash,
+    ours: Option<&BlockHash>,
+    blocks: Vec<StateBlock>,
+) -> anyhow::Result<Option<Vec<Block>>> {
+    let mut chain = vec![];
+    let mut next = frontier.to_owned();
+    let mut blocks = blocks.into_iter();
+    loop {
+        if Some(&next) == ours {
+            break;
+        }
+        let block = blocks
+            .next()
+            .ok_or_else(|| anyhow!("Chain ended before {:?}", next))?;
+        if block.hash != next {
+            return Err(anyhow!("Expected block {:?}, got {:?}", next, block.hash));
+        }
+        let block = Block::from_state_block(&block);
+        if block.account() != account {
+            return Err(anyhow!("Block {:?} belongs to another account", next));
+        }
+        block
+            .verify_signature(account)
+            .map_err(|err| anyhow!("Block {:?}: {}", next, err))?;
+
+        let previous = block.previous().to_owned();
+        chain.push(block);
+        next = match previous {
+            Previous::Block(previous) => previous,
+            Previous::Open if ours.is_some() => return Ok(None),
+            Previous::Open => break,
+        };
+    }
+    chain.reverse();
+    Ok(Some(chain))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blocks::BlockType;
+    use crate::node::header::Header;
+    use crate::node::state::MemoryState;
+    use crate::node::test_util::{self, add, chain, worked, Faucet};
+    use crate::node::wire::Wire;
+    use crate::Work;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    /// Pretend to be a node serving bulk pulls of `chains`.
+    async fn fake_node(chains: Vec<Vec<StateBlock>>) -> SocketAddr {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            loop {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let chains = chains.clone();
+                tokio::spawn(async move {
+                    let mut request = [0u8; Header::LEN + BulkPull::LEN];
+                    stream.read_exact(&mut request).await.unwrap();
+                    let pull = BulkPull::deserialize(None, &request[Header::LEN..]).unwrap();
+                    let chain = chains.iter().find(|c| c[0].account == pull.start);
+                    let mut data = vec![];
+                    for block in chain.into_iter().flatten().rev() {
+                        if block.hash == pull.end {
+                            break;
+                        }
+                        data.push(BlockType::State.as_u8());
+                        data.extend_from_slice(&block.serialize());
+                    }
+                    data.push(BlockType::NotABlock.as_u8());
+                    let _ = stream.write_all(&data).await;
+                });
+            }
+        });
+        address
+    }
+
+    fn state_blocks(blocks: &[Block]) -> Vec<StateBlock> {
+        blocks.iter().cloned().map(StateBlock::from).collect()
+    }
+
+    /// Bootstrap `chains` from a fake node, into a state that only has the faucet's open block.
+    async fn bootstrap(
+        faucet: &Faucet,
+        mut chains: Vec<Vec<StateBlock>>,
+    ) -> (ArcState, ChainStats) {
+        let state: ArcState = Arc::new(MemoryState::new(Network::Test));
+        add(&state, &[faucet.open().to_owned()]).await;
+        chains.push(state_blocks(&faucet.blocks));
+        let frontiers = chains
+            .iter()
+            .map(|c| (c[0].account.to_owned(), c.last().unwrap().hash.to_owned()))
+            .collect();
+        let peer = fake_node(chains).await;
+        let bootstrap = ChainBootstrap::new(Network::Test, vec![peer], state.clone(), frontiers);
+        let stats = bootstrap.run(&CancellationToken::new()).await.unwrap();
+        (state, stats)
+    }
+
+    #[tokio::test]
+    async fn pulls_missing_blocks() {
+        let mut faucet = Faucet::new();
+        let behind = faucet.chain(0, 3);
+        let fresh = faucet.chain(1, 2);
+
+        let (state, stats) =
+            bootstrap(&faucet, vec![state_blocks(&behind), state_blocks(&fresh)]).await;
+        // The faucet's two sends, and the blocks of the other two accounts.
+        assert_eq!(stats.blocks_added, 7);
+        assert_eq!(stats.accounts_done, 3);
+        assert_eq!(stats.invalid, 0);
+        for chain in &[behind, fresh] {
+            let account = chain[0].account();
+            assert_eq!(
+                state
+                    .get_latest_block_hash_for_account(account)
+                    .await
+                    .unwrap()
+                    .as_ref(),
+                chain.last().unwrap().hash().ok()
+            );
+        }
+
+        // The faucet's sends were received, leaving the sends back to it pending.
+        let pending = state.all_pending().await.unwrap();
+        assert_eq!(pending.len(), 3);
+        assert!(pending.iter().all(|p| p.destination == faucet.public()));
+    }
+
+    #[tokio::test]
+    async fn rejects_low_work_and_inflated_balances() {
+        let mut faucet = Faucet::new();
+        let mut lazy = faucet.chain(0, 2);
+        lazy[1].set_work(Work::zero());
+        // Opened with more than the faucet sent it.
+        let send = faucet.chain(1, 2)[0].link().to_owned();
+        let greedy = worked(&test_util::private(1), None, 3, send);
+
+        let (state, stats) = bootstrap(
+            &faucet,
+            vec![state_blocks(&lazy), state_blocks(&[greedy.to_owned()])],
+        )
+        .await;
+        assert_eq!(stats.invalid, 2);
+        assert_eq!(stats.accounts_done, 3);
+        // Only the faucet's sends and the first block of the lazy account.
+        assert_eq!(stats.blocks_added, 3);
+        assert!(state
+            .get_block_by_hash(lazy[1].hash().unwrap())
+            .await
+            .unwrap()
+            .is_none());
+        assert!(state
+            .get_block_by_hash(greedy.hash().unwrap())
+            .await
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn rejects_bad_chains() {
+        let blocks = chain(0, 3);
+        let account = &blocks[0].account;
+        let frontier = &blocks[2].hash;
+        let newest_first = || blocks.iter().rev().cloned().collect::<Vec<_>>();
+
+        let verified = verify_chain(account, frontier, None, newest_first()).unwrap();
+        assert_eq!(verified.unwrap().len(), 3);
+
+        // A gap in the chain.
+        let mut gap = newest_first();
+        gap.remove(1);
+        assert!(verify_chain(account, frontier, None, gap).is_err());
+
+        // Signed by someone else.
+        let mut forged = newest_first();
+        forged[0].signature = chain(1, 1)[0].signature.to_owned();
+        assert!(verify_chain(account, frontier, None, forged).is_err());
+
+        // Our frontier isn't in their chain.
+        let ours = chain(0, 4)[3].hash.to_owned();
+        assert!(verify_chain(account, frontier, Some(&ours), newest_first())
+            .unwrap()
+            .is_none());
+    }
+}
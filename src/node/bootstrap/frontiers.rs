@@ -0,0 +1,64 @@
+use crate::blocks::BlockHash;
+use crate::network::Network;
+use crate::node::header::{Extensions, Header, MessageType};
+use crate::node::messages::frontier_req::FrontierReq;
+use crate::node::messages::frontier_resp::FrontierResp;
+use crate::node::wire::Wire;
+use crate::Public;
+use anyhow::Context;
+use bytes::BytesMut;
+use std::net::SocketAddr;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+use tokio::time::timeout;
+
+/// How long to wait to connect to a bootstrap peer.
+pub const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How long to wait for each frontier before giving up on the peer.
+pub const FRONTIER_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Stream frontiers from a peer, starting at the `start` account.
+///
+/// `on_frontier` is called with each account and its frontier in ascending order of account,
+/// and the stream is stopped early when it returns false.
+pub async fn pull_frontiers<F>(
+    network: Network,
+    address: SocketAddr,
+    start: &Public,
+    mut on_frontier: F,
+) -> anyhow::Result<()>
+where
+    F: FnMut(Public, BlockHash) -> bool,
+{
+    let stream = timeout(CONNECT_TIMEOUT, TcpStream::connect(address))
+        .await
+        .with_context(|| format!("Timed out connecting to {}", address))?
+        .with_context(|| format!("Connecting to {}", address))?;
+    let (tcp_in, mut tcp_out) = stream.into_split();
+
+    let mut buf = BytesMut::new();
+    Header::new(network, MessageType::FrontierReq, Extensions::new()).serialize_into(&mut buf);
+    FrontierReq::new(start.to_owned(), u32::MAX, u32::MAX).serialize_into(&mut buf);
+    tcp_out
+        .write_all(&buf)
+        .await
+        .context("Sending frontier request")?;
+
+    let mut tcp_in = BufReader::new(tcp_in);
+    let mut data = [0u8; FrontierResp::LEN];
+    loop {
+        timeout(FRONTIER_TIMEOUT, tcp_in.read_exact(&mut data))
+            .await
+            .with_context(|| format!("Timed out waiting for a frontier from {}", address))?
+            .with_context(|| format!("Reading frontier from {}", address))?;
+        let frontier = FrontierResp::deserialize(None, &data)?;
+        if frontier.is_end() {
+            return Ok(());
+        }
+        if !on_frontier(frontier.account, frontier.frontier_hash) {
+            return Ok(());
+        }
+    }
+}
@@ -0,0 +1,464 @@
+//! Bootstrapping frontiers in parallel from several peers.
+//!
+//! The account space is split into [AccountRange]s which are handed out to peers. Each peer
+//! streams the frontiers of its range over its own connection. When there are no ranges left to
+//! hand out, a peer that is idle steals the upper half of what's left of the range with the
+//! longest estimated time remaining, as long as the owner isn't faster.
+//!
+//! Once the frontiers are known, [ChainBootstrap] bulk pulls the blocks below them into the state.
+mod blocks;
+mod chains;
+mod frontiers;
+mod range;
+
+use crate::blocks::BlockHash;
+use crate::network::Network;
+use crate::node::events::FrontierEvents;
+use crate::rpc::calls::{BootstrapPeer, BootstrapStatusResponse};
+use crate::rpc::websocket::FrontierSource;
+use crate::Public;
+use anyhow::anyhow;
+pub use chains::{ChainBootstrap, ChainStats};
+pub use frontiers::pull_frontiers;
+use num::ToPrimitive;
+pub use range::{next_account, AccountRange};
+use std::cmp::Ordering;
+use std::collections::{HashMap, VecDeque};
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, info, warn};
+
+/// Bootstrap frontiers from a set of peers. See the [module docs](self).
+pub struct FrontierBootstrap {
+    network: Network,
+    peers: Vec<SocketAddr>,
+    scheduler: Arc<Mutex<Scheduler>>,
+}
+
+impl FrontierBootstrap {
+    /// Split the account space into `ranges` to start with. It's usually best to have a few
+    /// more ranges than peers, so that the initial split isn't only decided by stealing.
+    pub fn new(network: Network, peers: Vec<SocketAddr>, ranges: usize) -> Self {
+        Self {
+            network,
+            peers,
+            scheduler: Arc::new(Mutex::new(Scheduler::new(AccountRange::split_evenly(
+                ranges,
+            )))),
+        }
+    }
+
+    /// Publish each frontier to `events` as it's received.
+    pub fn set_events(&self, events: FrontierEvents) {
+        self.scheduler.lock().unwrap().events = events;
+    }
+
+    pub fn status(&self) -> BootstrapStatusResponse {
+        self.scheduler.lock().unwrap().status()
+    }
+
+    /// Pull frontiers from all peers until the whole account space is covered.
+    ///
+    /// Peers that fail are dropped and their unfinished range is given to the others. If all of
+    /// them fail before everything is covered, there will be an error. Fails with
+    /// [crate::Error::Cancelled] if `cancel` is cancelled first.
+    pub async fn run(
+        &self,
+        cancel: &CancellationToken,
+    ) -> anyhow::Result<HashMap<Public, BlockHash>> {
+        self.scheduler.lock().unwrap().running = true;
+
+        let workers: Vec<_> = self
+            .peers
+            .iter()
+            .map(|&peer| {
+                tokio::spawn(worker(
+                    self.network,
+                    peer,
+                    self.scheduler.clone(),
+                    cancel.clone(),
+                ))
+            })
+            .collect();
+        for worker in workers {
+            worker.await?;
+        }
+
+        let mut scheduler = self.scheduler.lock().unwrap();
+        scheduler.running = false;
+        if cancel.is_cancelled() {
+            return Err(crate::Error::Cancelled.into());
+        }
+        if !scheduler.queue.is_empty() {
+            return Err(anyhow!(
+                "All bootstrap peers failed with {} ranges left",
+                scheduler.queue.len()
+            ));
+        }
+        info!(
+            "Bootstrapped {} frontiers from {} peers with {} steals",
+            scheduler.frontiers.len(),
+            self.peers.len(),
+            scheduler.steals
+        );
+        Ok(std::mem::take(&mut scheduler.frontiers))
+    }
+}
+
+async fn worker(
+    network: Network,
+    peer: SocketAddr,
+    scheduler: Arc<Mutex<Scheduler>>,
+    cancel: CancellationToken,
+) {
+    while !cancel.is_cancelled() {
+        let range = match scheduler.lock().unwrap().take(peer) {
+            Some(range) => range,
+            None => return,
+        };
+        debug!("Bootstrapping {:?} from {}", range, peer);
+
+        let pull = pull_frontiers(network, peer, &range.start, |account, hash| {
+            scheduler.lock().unwrap().frontier(peer, account, hash)
+        });
+        let result = tokio::select! {
+            result = pull => result,
+            _ = cancel.cancelled() => return,
+        };
+
+        let mut scheduler = scheduler.lock().unwrap();
+        match result {
+            Ok(()) => scheduler.finish(peer),
+            Err(err) => {
+                warn!("Dropping bootstrap peer {}: {:?}", peer, err);
+                scheduler.fail(peer);
+                return;
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Assignment {
+    range: AccountRange,
+
+    /// Everything before this has been received.
+    position: Public,
+}
+
+#[derive(Debug, Default)]
+struct PeerProgress {
+    frontiers: usize,
+    started: Option<Instant>,
+    failed: bool,
+}
+
+impl PeerProgress {
+    /// Frontiers per second, or `None` if nothing has been received yet.
+    fn rate(&self) -> Option<f64> {
+        let elapsed = self.started?.elapsed().as_secs_f64();
+        if self.frontiers == 0 || elapsed == 0. {
+            return None;
+        }
+        Some(self.frontiers as f64 / elapsed)
+    }
+}
+
+/// Keeps track of which peer is working on which range.
+#[derive(Debug)]
+struct Scheduler {
+    queue: VecDeque<AccountRange>,
+    active: HashMap<SocketAddr, Assignment>,
+    peers: HashMap<SocketAddr, PeerProgress>,
+    frontiers: HashMap<Public, BlockHash>,
+    ranges_total: usize,
+    ranges_done: usize,
+    steals: usize,
+    running: bool,
+    events: FrontierEvents,
+}
+
+impl Scheduler {
+    fn new(ranges: Vec<AccountRange>) -> Self {
+        Self {
+            ranges_total: ranges.len(),
+            queue: ranges.into(),
+            active: HashMap::new(),
+            peers: HashMap::new(),
+            frontiers: HashMap::new(),
+            ranges_done: 0,
+            steals: 0,
+            running: false,
+            events: FrontierEvents::new(),
+        }
+    }
+
+    /// Give `peer` a range to work on, stealing one if needed. `None` means there's nothing
+    /// left that the peer should help with.
+    fn take(&mut self, peer: SocketAddr) -> Option<AccountRange> {
+        let range = match self.queue.pop_front() {
+            Some(range) => range,
+            None => self.steal(peer)?,
+        };
+        self.peers
+            .entry(peer)
+            .or_default()
+            .started
+            .get_or_insert_with(Instant::now);
+        self.active.insert(
+            peer,
+            Assignment {
+                position: range.start.clone(),
+                range: range.clone(),
+            },
+        );
+        Some(range)
+    }
+
+    fn steal(&mut self, thief: SocketAddr) -> Option<AccountRange> {
+        // A peer that hasn't been measured yet is given the benefit of the doubt.
+        let thief_rate = self.rate(&thief).unwrap_or(f64::INFINITY);
+
+        let victim = self
+            .active
+            .iter()
+            .filter_map(|(peer, assignment)| {
+                let rate = self.rate(peer).unwrap_or(0.);
+                if *peer == thief || rate > thief_rate {
+                    return None;
+                }
+                Some((*peer, assignment.remaining() / rate))
+            })
+            .max_by(|(_, a), (_, b)| a.partial_cmp(b).unwrap_or(Ordering::Equal))?
+            .0;
+
+        let assignment = self.active.get_mut(&victim)?;
+        let stolen = assignment.range.split_off(&assignment.position)?;
+        debug!("{} stole {:?} from {}", thief, stolen, victim);
+        self.steals += 1;
+        self.ranges_total += 1;
+        Some(stolen)
+    }
+
+    /// Record a frontier from `peer`, returning if it should keep going.
+    fn frontier(&mut self, peer: SocketAddr, account: Public, hash: BlockHash) -> bool {
+        let assignment = match self.active.get_mut(&peer) {
+            Some(assignment) => assignment,
+            None => return false,
+        };
+        if !assignment.range.contains(&account) {
+            // Either past the end, which might have been stolen from under it, or the peer is
+            // sending accounts before what we asked for, which we'll skip over.
+            return account.as_bytes() < assignment.range.start.as_bytes();
+        }
+
+        let next = next_account(&account);
+        self.events
+            .publish(&account, &hash, FrontierSource::Bootstrap);
+        self.frontiers.insert(account, hash);
+        self.peers.entry(peer).or_default().frontiers += 1;
+        match next {
+            Some(next) => {
+                assignment.position = next;
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn finish(&mut self, peer: SocketAddr) {
+        if self.active.remove(&peer).is_some() {
+            self.ranges_done += 1;
+        }
+    }
+
+    /// Put what's left of the peer's range back in the queue for another peer.
+    fn fail(&mut self, peer: SocketAddr) {
+        self.peers.entry(peer).or_default().failed = true;
+        if let Some(assignment) = self.active.remove(&peer) {
+            self.queue.push_front(AccountRange {
+                start: assignment.position,
+                end: assignment.range.end,
+            });
+        }
+    }
+
+    fn rate(&self, peer: &SocketAddr) -> Option<f64> {
+        self.peers.get(peer).and_then(|p| p.rate())
+    }
+
+    fn status(&self) -> BootstrapStatusResponse {
+        let mut peers: Vec<BootstrapPeer> = self
+            .peers
+            .iter()
+            .map(|(address, progress)| BootstrapPeer {
+                address: *address,
+                frontiers: progress.frontiers,
+                frontiers_per_second: progress.rate().unwrap_or(0.),
+                active: self.active.contains_key(address),
+                failed: progress.failed,
+            })
+            .collect();
+        peers.sort_by_key(|p| p.address);
+
+        BootstrapStatusResponse {
+            running: self.running,
+            ranges_total: self.ranges_total,
+            ranges_done: self.ranges_done,
+            frontiers: self.frontiers.len(),
+            steals: self.steals,
+            peers,
+        }
+    }
+}
+
+impl Assignment {
+    /// How much of the account space is left, as a fraction.
+    fn remaining(&self) -> f64 {
+        let position = self.position.as_bytes();
+        let end = self.range.end.as_ref().map(|e| e.as_bytes());
+        let to_f64 = |b: &[u8]| num::BigUint::from_bytes_be(b).to_f64().unwrap();
+        let end = end.map_or(2f64.powi(256), to_f64);
+        (end - to_f64(position)).max(0.) / 2f64.powi(256)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::node::header::Header;
+    use crate::node::messages::frontier_resp::FrontierResp;
+    use crate::node::wire::Wire;
+    use std::convert::TryFrom;
+    use std::time::Duration;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    /// Accounts spread evenly over the account space.
+    fn accounts(count: usize) -> Vec<Public> {
+        (0..count)
+            .map(|idx| {
+                let mut bytes = [0u8; Public::LEN];
+                bytes[..2].copy_from_slice(&((idx * 65536 / count) as u16).to_be_bytes());
+                bytes[31] = 1;
+                Public::try_from(bytes.as_ref()).unwrap()
+            })
+            .collect()
+    }
+
+    fn frontier_for(account: &Public) -> BlockHash {
+        BlockHash::try_from(account.as_bytes()).unwrap()
+    }
+
+    /// Pretend to be a node serving frontiers, sleeping `delay` between each one.
+    async fn fake_node(accounts: Vec<Public>, delay: Duration) -> SocketAddr {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            loop {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let accounts = accounts.clone();
+                tokio::spawn(async move {
+                    let mut request = [0u8; Header::LEN + 40];
+                    stream.read_exact(&mut request).await.unwrap();
+                    let start = &request[Header::LEN..Header::LEN + Public::LEN];
+                    for account in accounts.iter().filter(|a| a.as_bytes() >= start) {
+                        let resp = FrontierResp::new(account.clone(), frontier_for(account));
+                        if stream.write_all(&resp.serialize()).await.is_err() {
+                            // We were told to stop.
+                            return;
+                        }
+                        tokio::time::sleep(delay).await;
+                    }
+                    let _ = stream.write_all(&FrontierResp::end().serialize()).await;
+                });
+            }
+        });
+        address
+    }
+
+    #[tokio::test]
+    async fn slow_peer_has_work_stolen() {
+        let accounts = accounts(200);
+        let fast = fake_node(accounts.clone(), Duration::from_millis(0)).await;
+        let slow = fake_node(accounts.clone(), Duration::from_millis(20)).await;
+
+        let bootstrap = FrontierBootstrap::new(Network::Live, vec![fast, slow], 2);
+        let frontiers = bootstrap.run(&CancellationToken::new()).await.unwrap();
+
+        assert_eq!(frontiers.len(), accounts.len());
+        for account in &accounts {
+            assert_eq!(frontiers.get(account), Some(&frontier_for(account)));
+        }
+
+        let status = bootstrap.status();
+        assert!(!status.running);
+        assert!(status.steals > 0);
+        assert_eq!(status.ranges_done, status.ranges_total);
+        let fast = status.peers.iter().find(|p| p.address == fast).unwrap();
+        let slow = status.peers.iter().find(|p| p.address == slow).unwrap();
+        assert!(fast.frontiers > slow.frontiers);
+    }
+
+    #[tokio::test]
+    async fn failed_peer_range_is_reassigned() {
+        let accounts = accounts(50);
+        let good = fake_node(accounts.clone(), Duration::from_millis(0)).await;
+        // Nothing is listening here.
+        let bad = TcpListener::bind("127.0.0.1:0")
+            .await
+            .unwrap()
+            .local_addr()
+            .unwrap();
+
+        let bootstrap = FrontierBootstrap::new(Network::Live, vec![bad, good], 4);
+        let frontiers = bootstrap.run(&CancellationToken::new()).await.unwrap();
+        assert_eq!(frontiers.len(), accounts.len());
+
+        let status = bootstrap.status();
+        assert!(status.peers.iter().any(|p| p.address == bad && p.failed));
+    }
+
+    #[tokio::test]
+    async fn all_peers_fail() {
+        let bad = TcpListener::bind("127.0.0.1:0")
+            .await
+            .unwrap()
+            .local_addr()
+            .unwrap();
+        let bootstrap = FrontierBootstrap::new(Network::Live, vec![bad], 2);
+        assert!(bootstrap.run(&CancellationToken::new()).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn cancel() {
+        let slow = fake_node(accounts(100), Duration::from_millis(50)).await;
+        let bootstrap = FrontierBootstrap::new(Network::Live, vec![slow], 2);
+        let cancel = CancellationToken::new();
+        let canceller = cancel.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            canceller.cancel();
+        });
+        let err = bootstrap.run(&cancel).await.unwrap_err();
+        assert!(matches!(err.downcast_ref(), Some(crate::Error::Cancelled)));
+        assert!(!bootstrap.status().running);
+    }
+
+    #[test]
+    fn frontier_past_end_stops() {
+        let mut ranges = AccountRange::split_evenly(2);
+        let upper = ranges.pop().unwrap();
+        let mut scheduler = Scheduler::new(ranges);
+        let peer: SocketAddr = "127.0.0.1:7075".parse().unwrap();
+        scheduler.take(peer).unwrap();
+
+        let account = Public::zero();
+        assert!(scheduler.frontier(peer, account.clone(), frontier_for(&account)));
+        let hash = frontier_for(&upper.start);
+        assert!(!scheduler.frontier(peer, upper.start, hash));
+        assert_eq!(scheduler.frontiers.len(), 1);
+    }
+}
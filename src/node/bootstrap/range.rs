@@ -0,0 +1,167 @@
+use crate::Public;
+use num::{BigUint, One, Zero};
+use std::convert::TryFrom;
+
+/// Ranges with fewer accounts left than this aren't worth splitting with another peer, as the
+/// extra connection would cost more than it saves. It's 1/65536th of the account space.
+const MIN_SPLIT_SPAN_BITS: u64 = 240;
+
+/// A half open range of the account space, `[start, end)`. An `end` of `None` is the end of the
+/// account space.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AccountRange {
+    pub start: Public,
+    pub end: Option<Public>,
+}
+
+impl AccountRange {
+    pub fn all() -> Self {
+        Self {
+            start: Public::zero(),
+            end: None,
+        }
+    }
+
+    /// Divide the whole account space into `count` equally sized ranges.
+    pub fn split_evenly(count: usize) -> Vec<Self> {
+        let count = count.max(1);
+        let step = space() / BigUint::from(count);
+        let bounds: Vec<Public> = (0..count)
+            .map(|idx| from_uint(&(&step * BigUint::from(idx))))
+            .collect();
+
+        bounds
+            .iter()
+            .enumerate()
+            .map(|(idx, start)| Self {
+                start: start.to_owned(),
+                end: bounds.get(idx + 1).cloned(),
+            })
+            .collect()
+    }
+
+    pub fn contains(&self, account: &Public) -> bool {
+        account.as_bytes() >= self.start.as_bytes()
+            && self
+                .end
+                .as_ref()
+                .is_none_or(|end| account.as_bytes() < end.as_bytes())
+    }
+
+    /// Given that everything before `position` has been covered, give away the upper half of
+    /// what's left, shrinking this range to the lower half.
+    ///
+    /// Returns `None` if there isn't enough left to be worth splitting.
+    pub fn split_off(&mut self, position: &Public) -> Option<AccountRange> {
+        let position = to_uint(position).max(to_uint(&self.start));
+        let end = self.end.as_ref().map_or_else(space, to_uint);
+        if end <= position || (&end - &position).bits() <= MIN_SPLIT_SPAN_BITS {
+            return None;
+        }
+
+        let mid = from_uint(&((&position + &end) >> 1));
+        let upper = AccountRange {
+            start: mid.clone(),
+            end: self.end.take(),
+        };
+        self.end = Some(mid);
+        Some(upper)
+    }
+}
+
+/// The account after `account`, or `None` if it's the last possible account.
+pub fn next_account(account: &Public) -> Option<Public> {
+    let next = to_uint(account) + BigUint::one();
+    if next >= space() {
+        None
+    } else {
+        Some(from_uint(&next))
+    }
+}
+
+/// The size of the account space, 2^256.
+fn space() -> BigUint {
+    BigUint::one() << (Public::LEN * 8)
+}
+
+fn to_uint(public: &Public) -> BigUint {
+    BigUint::from_bytes_be(public.as_bytes())
+}
+
+fn from_uint(n: &BigUint) -> Public {
+    debug_assert!(*n < space());
+    let mut bytes = [0u8; Public::LEN];
+    if !n.is_zero() {
+        let be = n.to_bytes_be();
+        bytes[Public::LEN - be.len()..].copy_from_slice(&be);
+    }
+    Public::try_from(bytes.as_ref()).expect("32 bytes is a public key")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn public(s: &str) -> Public {
+        Public::from_str(s).unwrap()
+    }
+
+    #[test]
+    fn split_evenly() {
+        let ranges = AccountRange::split_evenly(4);
+        assert_eq!(ranges.len(), 4);
+        assert_eq!(ranges[0].start, Public::zero());
+        assert_eq!(
+            ranges[1].start,
+            public("4000000000000000000000000000000000000000000000000000000000000000")
+        );
+        assert_eq!(ranges[0].end.as_ref(), Some(&ranges[1].start));
+        assert_eq!(ranges[3].end, None);
+
+        let last = public("FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFF");
+        assert!(ranges[3].contains(&last));
+        assert!(!ranges[2].contains(&last));
+        assert!(!ranges[0].contains(&ranges[1].start));
+        assert!(ranges[1].contains(&ranges[1].start));
+
+        assert_eq!(AccountRange::split_evenly(1), vec![AccountRange::all()]);
+    }
+
+    #[test]
+    fn split_off() {
+        let mut range = AccountRange::all();
+        let position = public("8000000000000000000000000000000000000000000000000000000000000000");
+        let upper = range.split_off(&position).unwrap();
+        let mid = public("C000000000000000000000000000000000000000000000000000000000000000");
+        assert_eq!(range.end.as_ref(), Some(&mid));
+        assert_eq!(upper.start, mid);
+        assert_eq!(upper.end, None);
+
+        // Nearly finished.
+        let mut range = AccountRange {
+            start: Public::zero(),
+            end: Some(public(
+                "0000100000000000000000000000000000000000000000000000000000000000",
+            )),
+        };
+        assert!(range.split_off(&Public::zero()).is_none());
+        assert!(range.end.is_some());
+    }
+
+    #[test]
+    fn next() {
+        assert_eq!(
+            next_account(&Public::zero()),
+            Some(public(
+                "0000000000000000000000000000000000000000000000000000000000000001"
+            ))
+        );
+        assert_eq!(
+            next_account(&public(
+                "FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFF"
+            )),
+            None
+        );
+    }
+}
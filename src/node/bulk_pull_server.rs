@@ -0,0 +1,348 @@
+//! Serving bulk pulls from our own state, so other nodes can bootstrap from us.
+//!
+//! The start of a pull is an account, to start at its frontier, or a block hash, to start at
+//! that block. Blocks are sent newest first down to the open block, or oldest first up to the
+//! frontier when the pull is ascending, stopping before `end` or after `count` blocks. An `end`
+//! that isn't on the same chain can't be reached, so nothing is sent for it, like the reference
+//! node does.
+//!
+//! Each address can only have a few pulls running at once, and a pull that takes too long
+//! disconnects the peer, so a slow reader can't tie up the node.
+use crate::blocks::{BlockHash, BlockHolder, Previous};
+use crate::node::messages::bulk_pull::BulkPull;
+use crate::node::state::{ArcState, DynState};
+use crate::Public;
+use anyhow::anyhow;
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::net::IpAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// How many bulk pulls an address can have running at once.
+pub const MAX_PULLS_PER_PEER: usize = 2;
+
+/// How long a bulk pull can take before the peer is disconnected.
+pub const PULL_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Blocks are gathered into packets of about this many bytes.
+pub const PULL_PACKET_LEN: usize = 16 * 1024;
+
+/// Shared between peers, to limit the bulk pulls each address has running.
+#[derive(Debug, Clone)]
+pub struct BulkPullServer {
+    pub max_per_peer: usize,
+    pub timeout: Duration,
+    running: Arc<Mutex<HashMap<IpAddr, usize>>>,
+}
+
+impl Default for BulkPullServer {
+    fn default() -> Self {
+        Self::new(MAX_PULLS_PER_PEER, PULL_TIMEOUT)
+    }
+}
+
+impl BulkPullServer {
+    pub fn new(max_per_peer: usize, timeout: Duration) -> Self {
+        Self {
+            max_per_peer,
+            timeout,
+            running: Default::default(),
+        }
+    }
+
+    /// Count a pull for `ip` until the permit is dropped, or fail when it already has as many
+    /// running as it's allowed.
+    pub fn start(&self, ip: IpAddr) -> anyhow::Result<PullPermit> {
+        let mut running = self.running.lock().unwrap();
+        let count = running.entry(ip).or_insert(0);
+        if *count >= self.max_per_peer {
+            return Err(anyhow!(
+                "{} already has {} bulk pulls running",
+                ip,
+                self.max_per_peer
+            ));
+        }
+        *count += 1;
+        Ok(PullPermit {
+            running: self.running.clone(),
+            ip,
+        })
+    }
+
+    /// How many pulls `ip` has running.
+    pub fn running(&self, ip: &IpAddr) -> usize {
+        self.running.lock().unwrap().get(ip).copied().unwrap_or(0)
+    }
+}
+
+/// A running pull, see [BulkPullServer::start].
+#[derive(Debug)]
+pub struct PullPermit {
+    running: Arc<Mutex<HashMap<IpAddr, usize>>>,
+    ip: IpAddr,
+}
+
+impl Drop for PullPermit {
+    fn drop(&mut self) {
+        let mut running = self.running.lock().unwrap();
+        if let Some(count) = running.get_mut(&self.ip) {
+            *count -= 1;
+            if *count == 0 {
+                running.remove(&self.ip);
+            }
+        }
+    }
+}
+
+/// The blocks asked for by a [BulkPull], in the order to send them.
+///
+/// Blocks are read one at a time, so the state isn't locked for the whole pull. An ascending pull
+/// walks the chain down from the frontier first, to know the way back up.
+pub struct PullCursor {
+    state: ArcState,
+    order: Order,
+    end: BlockHash,
+
+    /// `None` for no limit.
+    remaining: Option<u32>,
+}
+
+enum Order {
+    /// The next block, following `previous` down.
+    Descending(Option<BlockHash>),
+
+    /// The blocks left to send, the next one last.
+    Ascending(Vec<BlockHash>),
+}
+
+impl PullCursor {
+    pub async fn new(state: ArcState, pull: &BulkPull) -> anyhow::Result<Self> {
+        let mut cursor = Self {
+            state: state.clone(),
+            order: Order::Descending(None),
+            end: pull.end.to_owned(),
+            // Zero means no limit, as with the reference node.
+            remaining: pull.count.filter(|count| *count > 0),
+        };
+        let start = BlockHash::try_from(pull.start.as_bytes())?;
+        let (account, start) = match state.get_latest_block_hash_for_account(&pull.start).await? {
+            Some(frontier) => (pull.start.to_owned(), Start::Frontier(frontier)),
+            None => match state.get_block_by_hash(&start).await? {
+                Some(block) => (block.account().to_owned(), Start::Block(start)),
+                None => return Ok(cursor),
+            },
+        };
+
+        if pull.end != BlockHash::zero() {
+            match state.get_block_by_hash(&pull.end).await? {
+                Some(end) if end.account() == &account => {}
+                _ => return Ok(cursor),
+            }
+        }
+
+        if !pull.ascending {
+            cursor.order = Order::Descending(Some(start.hash().to_owned()));
+            return Ok(cursor);
+        }
+
+        let (frontier, bottom) = match start {
+            Start::Frontier(frontier) => (frontier, None),
+            Start::Block(hash) => (frontier(&*state, &account).await?, Some(hash)),
+        };
+        let mut hashes = vec![];
+        let mut next = Some(frontier);
+        while let Some(hash) = next {
+            let block = state
+                .get_block_by_hash(&hash)
+                .await?
+                .ok_or_else(|| anyhow!("Missing block {:?} of {:?}", hash, account))?;
+            let done = bottom.as_ref() == Some(&hash);
+            hashes.push(hash);
+            if done {
+                break;
+            }
+            next = match block.previous() {
+                Previous::Block(previous) => Some(previous.to_owned()),
+                Previous::Open => None,
+            };
+        }
+        cursor.order = Order::Ascending(hashes);
+        Ok(cursor)
+    }
+
+    /// The next block to send, or `None` once the pull is done.
+    pub async fn next(&mut self) -> anyhow::Result<Option<BlockHolder>> {
+        if self.remaining == Some(0) {
+            return Ok(None);
+        }
+        let hash = match &mut self.order {
+            Order::Descending(next) => next.take(),
+            Order::Ascending(hashes) => hashes.pop(),
+        };
+        let hash = match hash {
+            Some(hash) if hash != self.end => hash,
+            _ => {
+                self.order = Order::Descending(None);
+                return Ok(None);
+            }
+        };
+
+        let block = self
+            .state
+            .get_block_by_hash(&hash)
+            .await?
+            .ok_or_else(|| anyhow!("Missing block {:?}", hash))?;
+        if let Order::Descending(next) = &mut self.order {
+            if let Previous::Block(previous) = block.previous() {
+                *next = Some(previous.to_owned());
+            }
+        }
+        if let Some(remaining) = &mut self.remaining {
+            *remaining -= 1;
+        }
+        Ok(Some(block.to_holder()?))
+    }
+}
+
+enum Start {
+    Frontier(BlockHash),
+    Block(BlockHash),
+}
+
+impl Start {
+    fn hash(&self) -> &BlockHash {
+        match self {
+            Start::Frontier(hash) | Start::Block(hash) => hash,
+        }
+    }
+}
+
+async fn frontier(state: &DynState, account: &Public) -> anyhow::Result<BlockHash> {
+    state
+        .get_latest_block_hash_for_account(account)
+        .await?
+        .ok_or_else(|| anyhow!("No frontier for {:?}", account))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::network::Network;
+    use crate::node::state::MemoryState;
+    use crate::node::test_util::{self, account, add};
+    use std::net::Ipv4Addr;
+
+    /// An account chain of `len` blocks, stored in a fresh state.
+    async fn chain(len: u128) -> (ArcState, Vec<BlockHash>) {
+        let state: ArcState = Arc::new(MemoryState::new(Network::Test));
+        let blocks = test_util::blocks(0, len);
+        add(&state, &blocks).await;
+        let hashes = blocks
+            .iter()
+            .map(|b| b.hash().unwrap().to_owned())
+            .collect();
+        (state, hashes)
+    }
+
+    async fn pulled(state: &ArcState, pull: &BulkPull) -> Vec<BlockHash> {
+        let mut cursor = PullCursor::new(state.clone(), pull).await.unwrap();
+        let mut hashes = vec![];
+        while let Some(block) = cursor.next().await.unwrap() {
+            hashes.push(block.hash());
+        }
+        hashes
+    }
+
+    fn pull(
+        start: &[u8],
+        end: Option<&BlockHash>,
+        count: Option<u32>,
+        ascending: bool,
+    ) -> BulkPull {
+        BulkPull {
+            start: Public::try_from(start).unwrap(),
+            end: end.cloned().unwrap_or_else(BlockHash::zero),
+            count,
+            ascending,
+        }
+    }
+
+    #[tokio::test]
+    async fn orders_and_limits() {
+        let (state, hashes) = chain(5).await;
+        let account = account(0);
+        let account = account.as_bytes();
+        let newest_first: Vec<_> = hashes.iter().rev().cloned().collect();
+
+        assert_eq!(
+            pulled(&state, &pull(account, None, None, false)).await,
+            newest_first
+        );
+        assert_eq!(
+            pulled(&state, &pull(account, None, Some(0), false)).await,
+            newest_first
+        );
+        assert_eq!(
+            pulled(&state, &pull(account, None, None, true)).await,
+            hashes
+        );
+        assert_eq!(
+            pulled(&state, &pull(account, Some(&hashes[1]), None, false)).await,
+            newest_first[..3]
+        );
+        assert_eq!(
+            pulled(&state, &pull(account, None, Some(2), true)).await,
+            hashes[..2]
+        );
+
+        // From a block instead of the frontier.
+        let start = hashes[3].as_bytes();
+        assert_eq!(
+            pulled(&state, &pull(start, None, None, false)).await,
+            newest_first[1..]
+        );
+        assert_eq!(
+            pulled(&state, &pull(start, Some(&hashes[4]), None, true)).await,
+            hashes[3..4]
+        );
+    }
+
+    #[tokio::test]
+    async fn unreachable_pulls_are_empty() {
+        let (state, hashes) = chain(2).await;
+        let account = account(0);
+        let other = test_util::account(1);
+
+        assert!(pulled(&state, &pull(other.as_bytes(), None, None, false))
+            .await
+            .is_empty());
+        let unknown = BlockHash::try_from([7u8; 32].as_ref()).unwrap();
+        assert!(pulled(
+            &state,
+            &pull(account.as_bytes(), Some(&unknown), None, false)
+        )
+        .await
+        .is_empty());
+        assert_eq!(
+            pulled(&state, &pull(account.as_bytes(), None, None, false))
+                .await
+                .len(),
+            hashes.len()
+        );
+    }
+
+    #[test]
+    fn limits_per_address() {
+        let server = BulkPullServer::new(2, PULL_TIMEOUT);
+        let ip = IpAddr::V4(Ipv4Addr::LOCALHOST);
+        let first = server.start(ip).unwrap();
+        let _second = server.start(ip).unwrap();
+        assert!(server.start(ip).is_err());
+        assert!(server.start(IpAddr::V4(Ipv4Addr::UNSPECIFIED)).is_ok());
+
+        drop(first);
+        assert_eq!(server.running(&ip), 1);
+        let _third = server.start(ip).unwrap();
+    }
+}
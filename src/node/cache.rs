@@ -0,0 +1,281 @@
+//! Caps on the node's in-memory structures, so that it can run with a fixed amount of memory.
+//!
+//! A [MemoryBudget] sets the number of entries each structure can hold. It's usually scaled
+//! from a single size with [MemoryBudget::from_megabytes], using a rough estimate of the size
+//! of each kind of entry. Once a structure is full, its least recently used entries are evicted.
+use crate::blocks::{BlockHash, StateBlock};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::{Display, Formatter};
+use std::hash::Hash;
+use std::sync::{Arc, Mutex};
+
+/// Rough bytes used by an entry in each structure, including the overhead of the maps they're in.
+const UNCHECKED_ENTRY_BYTES: usize = 600;
+const VOTE_ENTRY_BYTES: usize = 300;
+const DEDUP_ENTRY_BYTES: usize = 80;
+const BLOCK_ENTRY_BYTES: usize = 600;
+
+/// Percentage of the budget given to each structure.
+const UNCHECKED_PERCENT: usize = 15;
+const VOTE_PERCENT: usize = 15;
+const DEDUP_PERCENT: usize = 10;
+const BLOCK_PERCENT: usize = 60;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MemoryBudget {
+    /// Blocks waiting for their previous block to arrive.
+    pub unchecked: usize,
+
+    /// Block hashes with the representatives that have voted for them.
+    pub votes: usize,
+
+    /// Hashes of recently published blocks, so repeats aren't processed again.
+    pub dedup: usize,
+
+    /// Blocks kept by the in-memory state. The frontier of each account is always kept.
+    pub blocks: usize,
+}
+
+impl MemoryBudget {
+    pub const DEFAULT_MEGABYTES: usize = 512;
+
+    pub fn from_megabytes(megabytes: usize) -> Self {
+        let bytes = megabytes.saturating_mul(1024 * 1024);
+        let entries =
+            |percent: usize, entry_bytes: usize| (bytes / 100 * percent / entry_bytes).max(1);
+        Self {
+            unchecked: entries(UNCHECKED_PERCENT, UNCHECKED_ENTRY_BYTES),
+            votes: entries(VOTE_PERCENT, VOTE_ENTRY_BYTES),
+            dedup: entries(DEDUP_PERCENT, DEDUP_ENTRY_BYTES),
+            blocks: entries(BLOCK_PERCENT, BLOCK_ENTRY_BYTES),
+        }
+    }
+}
+
+impl Default for MemoryBudget {
+    fn default() -> Self {
+        Self::from_megabytes(Self::DEFAULT_MEGABYTES)
+    }
+}
+
+impl Display for MemoryBudget {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} unchecked, {} votes, {} recently published, {} blocks",
+            self.unchecked, self.votes, self.dedup, self.blocks
+        )
+    }
+}
+
+/// A map holding at most `cap` entries, evicting the least recently used when it's full.
+#[derive(Debug)]
+pub(crate) struct Lru<K, V> {
+    cap: usize,
+    entries: HashMap<K, (V, u64)>,
+
+    /// Keys by when they were last used, oldest first.
+    order: BTreeMap<u64, K>,
+    clock: u64,
+}
+
+impl<K: Hash + Eq + Clone, V> Lru<K, V> {
+    pub fn new(cap: usize) -> Self {
+        Self {
+            cap: cap.max(1),
+            entries: HashMap::new(),
+            order: BTreeMap::new(),
+            clock: 0,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.entries.contains_key(key)
+    }
+
+    /// Get an entry without counting it as used.
+    pub fn peek(&self, key: &K) -> Option<&V> {
+        self.entries.get(key).map(|(v, _)| v)
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = &V> {
+        self.entries.values().map(|(v, _)| v)
+    }
+
+    pub fn get_mut(&mut self, key: &K) -> Option<&mut V> {
+        self.touch(key);
+        self.entries.get_mut(key).map(|(v, _)| v)
+    }
+
+    pub fn get_or_insert_with(&mut self, key: K, f: impl FnOnce() -> V) -> &mut V {
+        if !self.entries.contains_key(&key) {
+            self.insert(key.clone(), f());
+        }
+        self.get_mut(&key).unwrap()
+    }
+
+    /// Insert or replace an entry, returning the entry that was evicted to make room for it.
+    pub fn insert(&mut self, key: K, value: V) -> Option<(K, V)> {
+        self.remove(&key);
+        let evicted = if self.entries.len() >= self.cap {
+            self.pop_oldest()
+        } else {
+            None
+        };
+        self.clock += 1;
+        self.order.insert(self.clock, key.clone());
+        self.entries.insert(key, (value, self.clock));
+        evicted
+    }
+
+    pub fn remove(&mut self, key: &K) -> Option<V> {
+        let (value, used) = self.entries.remove(key)?;
+        self.order.remove(&used);
+        Some(value)
+    }
+
+    pub fn pop_oldest(&mut self) -> Option<(K, V)> {
+        let used = *self.order.keys().next()?;
+        let key = self.order.remove(&used)?;
+        let (value, _) = self.entries.remove(&key)?;
+        Some((key, value))
+    }
+
+    fn touch(&mut self, key: &K) {
+        if let Some((_, used)) = self.entries.get_mut(key) {
+            let key = self.order.remove(used).unwrap();
+            self.clock += 1;
+            *used = self.clock;
+            self.order.insert(self.clock, key);
+        }
+    }
+}
+
+/// Shared by every [crate::node::Peer] to handle published blocks that have been seen before, or
+/// that arrived before the block they build on.
+#[derive(Clone)]
+pub struct PublishCache {
+    inner: Arc<Mutex<PublishInner>>,
+}
+
+struct PublishInner {
+    recent: Lru<BlockHash, ()>,
+
+    /// Blocks by the hash of the previous block they're waiting for.
+    unchecked: Lru<BlockHash, Vec<StateBlock>>,
+}
+
+impl PublishCache {
+    pub fn new(budget: &MemoryBudget) -> Self {
+        Self {
+            inner: Arc::new(Mutex::new(PublishInner {
+                recent: Lru::new(budget.dedup),
+                unchecked: Lru::new(budget.unchecked),
+            })),
+        }
+    }
+
+    /// Remember that a block was published. Returns false if it was published recently.
+    pub fn first_sighting(&self, hash: &BlockHash) -> bool {
+        let mut inner = self.inner.lock().unwrap();
+        if inner.recent.get_mut(hash).is_some() {
+            return false;
+        }
+        inner.recent.insert(hash.to_owned(), ());
+        true
+    }
+
+    /// Hold on to a block until `previous` has been stored.
+    pub fn add_unchecked(&self, previous: &BlockHash, block: StateBlock) {
+        let mut inner = self.inner.lock().unwrap();
+        let waiting = inner
+            .unchecked
+            .get_or_insert_with(previous.to_owned(), Vec::new);
+        if !waiting.iter().any(|b| b.hash == block.hash) {
+            waiting.push(block);
+        }
+    }
+
+    /// Blocks that were waiting for `previous`, which has now been stored.
+    pub fn take_unchecked(&self, previous: &BlockHash) -> Vec<StateBlock> {
+        self.inner
+            .lock()
+            .unwrap()
+            .unchecked
+            .remove(previous)
+            .unwrap_or_default()
+    }
+
+    pub fn unchecked_len(&self) -> usize {
+        self.inner.lock().unwrap().unchecked.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blocks::{Link, Previous};
+    use crate::{Raw, Seed};
+    use std::convert::TryFrom;
+
+    fn hash(byte: u8) -> BlockHash {
+        BlockHash::try_from([byte; BlockHash::LEN].as_ref()).unwrap()
+    }
+
+    #[test]
+    fn evicts_least_recently_used() {
+        let mut lru = Lru::new(2);
+        assert!(lru.insert(1, "one").is_none());
+        assert!(lru.insert(2, "two").is_none());
+        lru.get_mut(&1);
+        assert_eq!(lru.insert(3, "three"), Some((2, "two")));
+        assert_eq!(lru.peek(&1), Some(&"one"));
+        assert!(!lru.contains_key(&2));
+
+        // Peeking doesn't count as a use, so 1 is still older than 3.
+        assert_eq!(lru.insert(4, "four"), Some((1, "one")));
+        assert_eq!(lru.len(), 2);
+    }
+
+    #[test]
+    fn budget_scales() {
+        let small = MemoryBudget::from_megabytes(64);
+        let large = MemoryBudget::from_megabytes(1024);
+        assert_eq!(large.blocks / small.blocks, 16);
+        assert!(MemoryBudget::from_megabytes(0).dedup >= 1);
+    }
+
+    #[test]
+    fn publishes() {
+        let budget = MemoryBudget {
+            unchecked: 1,
+            votes: 1,
+            dedup: 2,
+            blocks: 1,
+        };
+        let cache = PublishCache::new(&budget);
+        assert!(cache.first_sighting(&hash(1)));
+        assert!(!cache.first_sighting(&hash(1)));
+
+        let account = Seed::zero().derive(0).to_public().unwrap();
+        let block = |previous: u8| {
+            StateBlock::new(
+                account.to_owned(),
+                Previous::Block(hash(previous)),
+                account.to_owned(),
+                Raw::zero(),
+                Link::Nothing,
+            )
+        };
+        cache.add_unchecked(&hash(1), block(1));
+        cache.add_unchecked(&hash(1), block(1));
+        cache.add_unchecked(&hash(2), block(2));
+        assert_eq!(cache.unchecked_len(), 1);
+        assert!(cache.take_unchecked(&hash(1)).is_empty());
+        assert_eq!(cache.take_unchecked(&hash(2)).len(), 1);
+    }
+}
@@ -0,0 +1,54 @@
+use crate::blocks::{BlockHash, Root};
+use crate::node::confirmation::{Confirmation, ConfirmationTracker};
+use crate::node::own_blocks::OwnBlocks;
+use crate::{Public, Raw};
+use std::collections::HashMap;
+use std::net::SocketAddr;
+
+/// A cheap, cloneable handle to a running [crate::node::Node] for applications that embed it.
+#[derive(Clone)]
+pub struct NodeClient {
+    confirmations: ConfirmationTracker,
+    own_blocks: OwnBlocks,
+}
+
+impl NodeClient {
+    pub(crate) fn new(confirmations: ConfirmationTracker, own_blocks: OwnBlocks) -> Self {
+        Self {
+            confirmations,
+            own_blocks,
+        }
+    }
+
+    /// Set the representative weights used to count votes, e.g. from the `representatives`
+    /// RPC. Confirmation needs votes from 67% of this weight.
+    pub fn set_representative_weights(&self, weights: HashMap<Public, Raw>) {
+        self.confirmations.set_weights(weights)
+    }
+
+    /// Ask the connected peers to vote on a block and wait until enough weight has voted for
+    /// it. The root of a block comes from [crate::blocks::StateBlock::root].
+    ///
+    /// This doesn't take part in elections, it only listens for votes. It waits forever if the
+    /// block never reaches quorum, so wrap it in a timeout if needed.
+    pub async fn request_confirmation(
+        &self,
+        block_hash: BlockHash,
+        root: Root,
+    ) -> anyhow::Result<Confirmation> {
+        self.confirmations.request(block_hash, root).await
+    }
+
+    /// Remember a block made by us before it's published, so that the node doesn't process it
+    /// again when peers flood it back.
+    pub fn track_own_block(&self, block_hash: &BlockHash) {
+        self.own_blocks.add(block_hash)
+    }
+
+    /// Wait for a peer to flood a block from [Self::track_own_block] back to us, which shows
+    /// that it reached the network. Returns the peer, or `None` if the block was forgotten
+    /// first. Wrap it in a timeout to give up sooner.
+    pub async fn echoed(&self, block_hash: &BlockHash) -> Option<SocketAddr> {
+        self.own_blocks.echoed(block_hash).await
+    }
+}
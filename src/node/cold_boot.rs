@@ -0,0 +1,157 @@
+//! Checking the stored ledger when the node starts.
+//!
+//! Blocks at or below the cemented height of their account were validated before they were
+//! confirmed, so only the uncemented tail of each account chain needs its signatures checked
+//! again, unless we're being paranoid.
+use crate::blocks::{Block, Previous};
+use crate::node::state::ArcState;
+use anyhow::{anyhow, Context};
+use tracing::{debug, info};
+
+#[derive(Debug, Default, PartialEq)]
+pub struct ColdBootStats {
+    pub accounts: usize,
+
+    /// Blocks that had their signature checked.
+    pub verified: usize,
+
+    /// Cemented blocks that were trusted without checking.
+    pub skipped: usize,
+}
+
+/// Walk every account chain in the state, verifying block signatures above the cemented height,
+/// or every block if `paranoid` is set.
+pub async fn verify_ledger(state: &ArcState, paranoid: bool) -> anyhow::Result<ColdBootStats> {
+    let mut stats = ColdBootStats::default();
+
+    for account in state.accounts().await? {
+        stats.accounts += 1;
+        let cemented = if paranoid {
+            0
+        } else {
+            state.cemented_height(&account).await?.unwrap_or(0)
+        };
+
+        // Walk back from the frontier to the open block, so we know the height of each block.
+        let mut chain: Vec<Block> = vec![];
+        let mut next = state.get_latest_block_hash_for_account(&account).await?;
+        while let Some(hash) = next {
+            let block = state.get_block_by_hash(&hash).await?.ok_or_else(|| {
+                anyhow!(
+                    "Missing block {:?} in the chain of {:?}",
+                    hash,
+                    account.to_address()
+                )
+            })?;
+            next = match block.previous() {
+                Previous::Block(previous) => Some(previous.to_owned()),
+                Previous::Open => None,
+            };
+            chain.push(block);
+        }
+
+        for (idx, block) in chain.iter().rev().enumerate() {
+            let height = idx as u64 + 1;
+            if height <= cemented {
+                stats.skipped += 1;
+                continue;
+            }
+            block.verify_signature(&account).with_context(|| {
+                format!(
+                    "Block {:?} at height {} of {:?}",
+                    block.hash(),
+                    height,
+                    account.to_address()
+                )
+            })?;
+            stats.verified += 1;
+        }
+        debug!(
+            "Checked {:?}: {} blocks, cemented at {}",
+            account.to_address(),
+            chain.len(),
+            cemented
+        );
+    }
+
+    info!(
+        "Checked the ledger: {} accounts, verified {} blocks, skipped {} cemented blocks",
+        stats.accounts, stats.verified, stats.skipped
+    );
+    Ok(stats)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::network::Network;
+    use crate::node::test_util::{add, blocks};
+    use crate::node::{MemoryState, SledDiskState};
+    use crate::Signature;
+    use std::convert::TryFrom;
+    use std::sync::Arc;
+
+    async fn state_with(blocks: &[Block], cemented: u64) -> ArcState {
+        with_blocks(Arc::new(MemoryState::new(Network::Test)), blocks, cemented).await
+    }
+
+    async fn with_blocks(state: ArcState, blocks: &[Block], cemented: u64) -> ArcState {
+        add(&state, blocks).await;
+        state
+            .set_cemented_height(blocks[0].account(), cemented)
+            .await
+            .unwrap();
+        state
+    }
+
+    fn corrupt(block: &mut Block) {
+        block.set_signature(Signature::try_from([1u8; 64].as_ref()).unwrap());
+    }
+
+    #[tokio::test]
+    async fn skips_cemented() {
+        let blocks = blocks(0, 3);
+        let state = state_with(&blocks, 2).await;
+        let stats = verify_ledger(&state, false).await.unwrap();
+        assert_eq!(
+            stats,
+            ColdBootStats {
+                accounts: 1,
+                verified: 1,
+                skipped: 2
+            }
+        );
+
+        let stats = verify_ledger(&state, true).await.unwrap();
+        assert_eq!(stats.verified, 3);
+        assert_eq!(stats.skipped, 0);
+    }
+
+    #[tokio::test]
+    async fn skips_cemented_on_disk() {
+        let blocks = blocks(0, 3);
+        let state = Arc::new(SledDiskState::temporary(Network::Test));
+        let state = with_blocks(state, &blocks, 2).await;
+        let stats = verify_ledger(&state, false).await.unwrap();
+        assert_eq!((stats.verified, stats.skipped), (1, 2));
+    }
+
+    #[tokio::test]
+    async fn paranoid_finds_bad_cemented_block() {
+        let mut blocks = blocks(0, 3);
+        corrupt(&mut blocks[0]);
+        let state = state_with(&blocks, 2).await;
+        verify_ledger(&state, false).await.unwrap();
+        let err = verify_ledger(&state, true).await.unwrap_err();
+        assert!(format!("{:?}", err).contains("at height 1"));
+    }
+
+    #[tokio::test]
+    async fn bad_uncemented_block() {
+        let mut blocks = blocks(0, 3);
+        corrupt(&mut blocks[2]);
+        let state = state_with(&blocks, 2).await;
+        let err = verify_ledger(&state, false).await.unwrap_err();
+        assert!(format!("{:?}", err).contains("at height 3"));
+    }
+}
@@ -5,9 +5,82 @@ pub type NodeCommandSender = mpsc::Sender<NodeCommand>;
 pub type NodeCommandReceiver = mpsc::Receiver<NodeCommand>;
 
 pub type PeerInfoResponseSender = oneshot::Sender<crate::rpc::calls::Peers>;
+pub type BootstrapStatusResponseSender =
+    oneshot::Sender<crate::rpc::calls::BootstrapStatusResponse>;
+pub type DroppedBlocksResponseSender = oneshot::Sender<crate::rpc::calls::DroppedBlocksResponse>;
+pub type DifficultyStatsResponseSender =
+    oneshot::Sender<anyhow::Result<crate::rpc::calls::DifficultyStatsResponse>>;
+pub type LedgerStatsResponseSender =
+    oneshot::Sender<anyhow::Result<crate::rpc::calls::LedgerStatsResponse>>;
+pub type SyncStatusResponseSender =
+    oneshot::Sender<anyhow::Result<crate::rpc::calls::SyncStatusResponse>>;
+pub type PeerTableResponseSender =
+    oneshot::Sender<anyhow::Result<crate::rpc::calls::PeerTableResponse>>;
+pub type PeerTelemetryResponseSender = oneshot::Sender<Vec<crate::rpc::calls::PeerTelemetry>>;
+pub type PersistResponseSender =
+    oneshot::Sender<anyhow::Result<crate::rpc::calls::PersistResponse>>;
+pub type BlockInfoResponseSender =
+    oneshot::Sender<anyhow::Result<crate::rpc::calls::BlockInfoResponse>>;
+pub type AccountInfoResponseSender =
+    oneshot::Sender<anyhow::Result<crate::rpc::calls::AccountInfoResponse>>;
+pub type PendingResponseSender =
+    oneshot::Sender<anyhow::Result<crate::rpc::calls::PendingResponse>>;
+pub type AccountsPendingResponseSender =
+    oneshot::Sender<anyhow::Result<crate::rpc::calls::AccountsPendingResponse>>;
+pub type ProcessResponseSender =
+    oneshot::Sender<anyhow::Result<crate::rpc::calls::ProcessResponse>>;
+pub type HealthResponseSender = oneshot::Sender<crate::node::HealthReport>;
 
 #[derive(Debug)]
 pub enum NodeCommand {
     /// Request all currently connected peers.
     PeerInfo(PeerInfoResponseSender),
+
+    /// Every known peer and when it was last seen.
+    PeerTable(PeerTableResponseSender),
+
+    /// The latest telemetry from each node.
+    PeerTelemetry(PeerTelemetryResponseSender),
+
+    /// Request the progress of the frontier bootstrap.
+    BootstrapStatus(BootstrapStatusResponseSender),
+
+    /// Counts of the blocks dropped when published by peers, and the latest ones.
+    DroppedBlocks(DroppedBlocksResponseSender),
+
+    /// Multipliers of the work of the blocks added in the last given number of seconds.
+    DifficultyStats(u64, DifficultyStatsResponseSender),
+
+    /// Walk the ledger to count accounts, blocks and balances.
+    LedgerStats(LedgerStatsResponseSender),
+
+    /// Our block counts next to the ones peers report in telemetry.
+    SyncStatus(SyncStatusResponseSender),
+
+    /// Copy the ledger into the database at the path, creating it if needed.
+    Persist(std::path::PathBuf, PersistResponseSender),
+
+    /// A block from the ledger, for the `block_info` RPC.
+    BlockInfo(crate::blocks::BlockHash, BlockInfoResponseSender),
+
+    /// An account from the ledger, for the `account_info` RPC.
+    AccountInfo(
+        crate::rpc::calls::AccountInfoRequest,
+        AccountInfoResponseSender,
+    ),
+
+    /// Blocks waiting to be received by an account, for the `pending` RPC.
+    Pending(crate::rpc::calls::PendingRequest, PendingResponseSender),
+
+    /// Blocks waiting to be received by several accounts, for the `accounts_pending` RPC.
+    AccountsPending(
+        crate::rpc::calls::AccountsPendingRequest,
+        AccountsPendingResponseSender,
+    ),
+
+    /// Check, store and flood a block, for the `process` RPC.
+    Process(Box<crate::blocks::StateBlock>, ProcessResponseSender),
+
+    /// Whether the node is healthy and ready, for the `/health` and `/ready` endpoints.
+    Health(HealthResponseSender),
 }
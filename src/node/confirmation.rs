@@ -0,0 +1,472 @@
+//! Waiting for blocks to be confirmed by representative votes, without running elections.
+//!
+//! A [ConfirmationTracker] is shared between the node and every connected [crate::node::Peer].
+//! Requesting confirmation sends a confirm_req to each connected peer, and the votes that come
+//! back in confirm_acks are tallied against the representative weights known to the tracker.
+//! Once the votes for a block add up to the quorum, everyone waiting on it is told.
+//!
+//! Blocks of priority accounts, e.g. the node operator's own, have confirm_reqs sent for them as
+//! soon as they're stored, before anyone asks. Their votes are counted until quorum and the
+//! confirmation is kept for a while, so a later [ConfirmationTracker::request] can be answered
+//! straight away.
+use crate::blocks::{Block, BlockHash, BlockHolder, BlockType, Root, StateBlock};
+use crate::node::header::{Extensions, Header, MessageType};
+use crate::node::messages::confirm_ack::{Confirm, ConfirmAck};
+use crate::node::messages::confirm_req::{ConfirmReq, RootHashPair};
+use crate::node::messages::publish::Publish;
+use crate::node::peer::Packet;
+use crate::node::wire::Wire;
+use crate::{Network, Public, Raw};
+use anyhow::anyhow;
+use bytes::{Bytes, BytesMut};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use tokio::sync::{mpsc, oneshot};
+use tracing::{debug, warn};
+
+/// Percentage of the total representative weight needed to consider a block confirmed, the
+/// same as the reference node's online weight quorum.
+const QUORUM_PERCENT: u128 = 67;
+
+/// The most priority blocks waiting on votes at once. Any more are left to be requested as usual.
+const MAX_PRIORITY_PENDING: usize = 1024;
+
+/// How many confirmations of priority blocks are kept for requests that come later.
+const RECENT_CONFIRMED: usize = 256;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Confirmation {
+    pub hash: BlockHash,
+
+    /// The total weight of the representatives that voted for the block.
+    pub weight: Raw,
+
+    /// How many representatives voted for the block.
+    pub voters: usize,
+}
+
+#[derive(Clone)]
+pub struct ConfirmationTracker {
+    network: Network,
+    inner: Arc<Mutex<Inner>>,
+}
+
+#[derive(Default)]
+struct Inner {
+    weights: HashMap<Public, Raw>,
+    quorum: u128,
+
+    /// Where to send confirm_reqs and published blocks to each connected peer.
+    peers: HashMap<SocketAddr, mpsc::Sender<Packet>>,
+
+    pending: HashMap<BlockHash, Pending>,
+
+    /// Accounts whose blocks are requested as soon as they're stored.
+    priority: HashSet<Public>,
+
+    /// Priority blocks that reached quorum before anyone asked, oldest first.
+    confirmed: VecDeque<Confirmation>,
+}
+
+struct Pending {
+    root: Root,
+    votes: HashMap<Public, Raw>,
+    waiters: Vec<oneshot::Sender<Confirmation>>,
+
+    /// Counted until quorum even when nobody is waiting.
+    priority: bool,
+}
+
+impl Pending {
+    fn new(root: Root, priority: bool) -> Self {
+        Self {
+            root,
+            votes: HashMap::new(),
+            waiters: vec![],
+            priority,
+        }
+    }
+}
+
+impl ConfirmationTracker {
+    pub fn new(network: Network) -> Self {
+        Self {
+            network,
+            inner: Arc::new(Mutex::new(Inner::default())),
+        }
+    }
+
+    /// Set the weight of each representative whose votes are counted. Representatives that
+    /// aren't in here are ignored.
+    pub fn set_weights(&self, weights: HashMap<Public, Raw>) {
+        let total = weights.values().fold(0u128, |total, w| total + w.to_u128());
+        let mut inner = self.inner.lock().unwrap();
+        inner.quorum = total / 100 * QUORUM_PERCENT + total % 100 * QUORUM_PERCENT / 100;
+        inner.weights = weights;
+    }
+
+    /// Send a confirm_req for `hash` to every connected peer and wait until enough votes have
+    /// been seen for it.
+    ///
+    /// This waits forever if the block never reaches quorum, so wrap it in a timeout if needed.
+    pub async fn request(&self, hash: BlockHash, root: Root) -> anyhow::Result<Confirmation> {
+        let (tx, rx) = oneshot::channel();
+        let peers: Vec<mpsc::Sender<Packet>> = {
+            let mut inner = self.inner.lock().unwrap();
+            if inner.weights.is_empty() {
+                return Err(anyhow!(
+                    "No representative weights to count the votes for {:?} with",
+                    hash
+                ));
+            }
+            if let Some(confirmation) = inner.confirmed.iter().find(|c| c.hash == hash) {
+                return Ok(confirmation.to_owned());
+            }
+            inner
+                .pending
+                .entry(hash.to_owned())
+                .or_insert_with(|| Pending::new(root.to_owned(), false))
+                .waiters
+                .push(tx);
+            inner.peers.values().cloned().collect()
+        };
+
+        debug!(
+            "Requesting confirmation of {:?} from {} peers",
+            hash,
+            peers.len()
+        );
+        let packet = self.confirm_req(vec![RootHashPair { hash, root }]);
+        for peer in peers {
+            // A peer that has just disconnected is fine, the others can still vote.
+            let _ = peer.send(Packet::new(packet.clone())).await;
+        }
+
+        rx.await
+            .map_err(|_| anyhow!("Confirmation tracker went away"))
+    }
+
+    /// Replace the accounts whose blocks are requested as soon as they're stored.
+    pub fn set_priority_accounts<I: IntoIterator<Item = Public>>(&self, accounts: I) {
+        self.inner.lock().unwrap().priority = accounts.into_iter().collect();
+    }
+
+    pub fn is_priority(&self, account: &Public) -> bool {
+        self.inner.lock().unwrap().priority.contains(account)
+    }
+
+    /// Send a confirm_req for a block straight away if it's from or to a priority account, and
+    /// count the votes for it. Returns how many peers it was sent to.
+    ///
+    /// Peers with a full queue are skipped rather than waited on, as this is called while
+    /// handling messages. They'll get the request when they reconnect, if it's still pending.
+    pub fn prioritize(&self, block: &Block) -> usize {
+        let hash = match block.hash() {
+            Ok(hash) => hash.to_owned(),
+            Err(_) => return 0,
+        };
+        let root = block.root();
+        let peers: Vec<mpsc::Sender<Packet>> = {
+            let mut inner = self.inner.lock().unwrap();
+            let relevant = inner.priority.contains(block.account())
+                || inner
+                    .priority
+                    .iter()
+                    .any(|account| account.as_bytes() == block.link().as_bytes());
+            if !relevant || inner.weights.is_empty() {
+                return 0;
+            }
+            let priority_pending = inner.pending.values().filter(|p| p.priority).count();
+            match inner.pending.get_mut(&hash) {
+                Some(pending) => pending.priority = true,
+                None if priority_pending >= MAX_PRIORITY_PENDING => {
+                    warn!(
+                        "Too many priority blocks waiting on votes to add {:?}",
+                        hash
+                    );
+                    return 0;
+                }
+                None => {
+                    inner
+                        .pending
+                        .insert(hash.to_owned(), Pending::new(root.to_owned(), true));
+                }
+            }
+            inner.peers.values().cloned().collect()
+        };
+
+        debug!(
+            "Requesting confirmation of priority block {:?} from {} peers",
+            hash,
+            peers.len()
+        );
+        let packet = self.confirm_req(vec![RootHashPair { hash, root }]);
+        peers
+            .iter()
+            .filter(|peer| peer.try_send(Packet::new(packet.clone())).is_ok())
+            .count()
+    }
+
+    /// Flood a block to every connected peer, e.g. one submitted with the `process` RPC.
+    /// Returns how many peers it was sent to.
+    pub async fn publish(&self, block: StateBlock) -> usize {
+        let peers: Vec<mpsc::Sender<Packet>> =
+            self.inner.lock().unwrap().peers.values().cloned().collect();
+
+        let mut ext = Extensions::new();
+        ext.set_block_type(BlockType::State);
+        let mut buf = BytesMut::new();
+        Header::new(self.network, MessageType::Publish, ext).serialize_into(&mut buf);
+        Publish(BlockHolder::State(block)).serialize_into(&mut buf);
+        let packet = buf.freeze();
+
+        let mut sent = 0;
+        for peer in peers {
+            if peer.send(Packet::new(packet.clone())).await.is_ok() {
+                sent += 1;
+            }
+        }
+        sent
+    }
+
+    /// Start sending confirm_reqs to a newly connected peer. Returns the requests that are
+    /// already waiting on votes, so they can be sent to the peer straight away.
+    pub fn add_peer(&self, address: SocketAddr, tx: mpsc::Sender<Packet>) -> Vec<Packet> {
+        let mut inner = self.inner.lock().unwrap();
+        inner.peers.insert(address, tx);
+        let pairs: Vec<RootHashPair> = inner
+            .pending
+            .iter()
+            .map(|(hash, pending)| RootHashPair {
+                hash: hash.to_owned(),
+                root: pending.root.to_owned(),
+            })
+            .collect();
+        pairs
+            .chunks(ConfirmReq::MAX_PAIRS)
+            .map(|chunk| Packet::new(self.confirm_req(chunk.to_vec())))
+            .collect()
+    }
+
+    pub fn remove_peer(&self, address: &SocketAddr) {
+        self.inner.lock().unwrap().peers.remove(address);
+    }
+
+    /// How many peers are connected.
+    pub fn peer_count(&self) -> usize {
+        self.inner.lock().unwrap().peers.len()
+    }
+
+    /// Count the votes in a confirm_ack towards any blocks waiting on confirmation.
+    pub fn observe(&self, confirm_ack: &ConfirmAck) {
+        let hashes = match &confirm_ack.confirm {
+            Confirm::VoteByHash(hashes) => hashes,
+            Confirm::Block(_) => return,
+        };
+
+        let mut inner = self.inner.lock().unwrap();
+        let weight = match inner.weights.get(&confirm_ack.account) {
+            Some(weight) => weight.to_owned(),
+            None => return,
+        };
+        if !hashes.iter().any(|hash| inner.pending.contains_key(hash)) {
+            return;
+        }
+        if let Err(err) = confirm_ack.verify_signature() {
+            warn!("Ignoring vote: {:?}", err);
+            return;
+        }
+
+        let quorum = inner.quorum;
+        for hash in hashes {
+            let pending = match inner.pending.get_mut(hash) {
+                Some(pending) => pending,
+                None => continue,
+            };
+            pending.waiters.retain(|waiter| !waiter.is_closed());
+            pending
+                .votes
+                .insert(confirm_ack.account.to_owned(), weight.to_owned());
+            let total = pending
+                .votes
+                .values()
+                .fold(0u128, |total, w| total + w.to_u128());
+
+            if (pending.waiters.is_empty() && !pending.priority) || total >= quorum {
+                let pending = inner.pending.remove(hash).unwrap();
+                let confirmation = Confirmation {
+                    hash: hash.to_owned(),
+                    weight: Raw::from(total),
+                    voters: pending.votes.len(),
+                };
+                if pending.priority && total >= quorum {
+                    if inner.confirmed.len() == RECENT_CONFIRMED {
+                        inner.confirmed.pop_front();
+                    }
+                    inner.confirmed.push_back(confirmation.clone());
+                }
+                for waiter in pending.waiters {
+                    let _ = waiter.send(confirmation.clone());
+                }
+            }
+        }
+    }
+
+    fn confirm_req(&self, pairs: Vec<RootHashPair>) -> Bytes {
+        let confirm_req = ConfirmReq::by_hash(pairs);
+        let mut buf = BytesMut::new();
+        Header::new(
+            self.network,
+            MessageType::ConfirmReq,
+            confirm_req.extensions(),
+        )
+        .serialize_into(&mut buf);
+        confirm_req.serialize_into(&mut buf);
+        buf.freeze()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::node::timestamp::Timestamp;
+    use crate::Seed;
+    use std::convert::TryFrom;
+    use std::net::{Ipv4Addr, SocketAddrV4};
+    use std::time::Duration;
+
+    fn hash(byte: u8) -> BlockHash {
+        BlockHash::try_from([byte; BlockHash::LEN].as_ref()).unwrap()
+    }
+
+    fn vote(index: u32, hashes: Vec<BlockHash>) -> ConfirmAck {
+        let private = Seed::zero().derive(index);
+        let mut ack = ConfirmAck::new(
+            private.to_public().unwrap(),
+            Seed::zero().derive(0).sign(&[]).unwrap(),
+            Timestamp::try_from([0u8; Timestamp::LEN].as_ref()).unwrap(),
+            Confirm::VoteByHash(hashes),
+        );
+        ack.signature = private.sign(&ack.inner_hash()).unwrap();
+        ack
+    }
+
+    fn tracker() -> ConfirmationTracker {
+        let tracker = ConfirmationTracker::new(Network::Live);
+        let weights = (0..3)
+            .map(|i| (Seed::zero().derive(i).to_public().unwrap(), Raw::from(100)))
+            .collect();
+        tracker.set_weights(weights);
+        tracker
+    }
+
+    async fn still_waiting<T>(request: &mut tokio::task::JoinHandle<T>) -> bool {
+        tokio::time::timeout(Duration::from_millis(10), request)
+            .await
+            .is_err()
+    }
+
+    #[tokio::test]
+    async fn confirms_at_quorum() {
+        let tracker = tracker();
+        let (tx, mut rx) = mpsc::channel(10);
+        let address = SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::LOCALHOST, 7075));
+        assert!(tracker.add_peer(address, tx).is_empty());
+
+        let mut request = tokio::spawn({
+            let tracker = tracker.clone();
+            async move { tracker.request(hash(1), hash(2).into()).await }
+        });
+        let packet = rx.recv().await.unwrap();
+        assert_eq!(packet.data.len(), Header::LEN + RootHashPair::LEN);
+
+        // 200 of 300 is just under the 67% quorum.
+        tracker.observe(&vote(0, vec![hash(1)]));
+        tracker.observe(&vote(0, vec![hash(1)]));
+        tracker.observe(&vote(1, vec![hash(1)]));
+        assert!(still_waiting(&mut request).await);
+
+        // Unknown representatives and bad signatures don't count.
+        tracker.observe(&vote(5, vec![hash(1)]));
+        let mut forged = vote(2, vec![hash(1)]);
+        forged.signature = vote(0, vec![hash(1)]).signature;
+        tracker.observe(&forged);
+        assert!(still_waiting(&mut request).await);
+
+        tracker.observe(&vote(2, vec![hash(3), hash(1)]));
+        let confirmation = request.await.unwrap().unwrap();
+        assert_eq!(confirmation.weight, Raw::from(300));
+        assert_eq!(confirmation.voters, 3);
+    }
+
+    #[tokio::test]
+    async fn new_peers_get_pending_requests() {
+        let tracker = tracker();
+        let request = tokio::spawn({
+            let tracker = tracker.clone();
+            async move { tracker.request(hash(1), hash(2).into()).await }
+        });
+        tokio::time::sleep(Duration::from_millis(10)).await;
+
+        let (tx, _rx) = mpsc::channel(10);
+        let address = SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::LOCALHOST, 7075));
+        assert_eq!(tracker.add_peer(address, tx).len(), 1);
+        request.abort();
+    }
+
+    #[tokio::test]
+    async fn needs_weights() {
+        let tracker = ConfirmationTracker::new(Network::Live);
+        assert!(tracker.request(hash(1), hash(2).into()).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn priority_blocks_are_requested_when_stored() {
+        use crate::blocks::{Link, Previous};
+        use futures::FutureExt;
+
+        let tracker = tracker();
+        let (tx, mut rx) = mpsc::channel(10);
+        let address = SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::LOCALHOST, 7075));
+        tracker.add_peer(address, tx);
+
+        let ours = Seed::zero().derive(10).to_public().unwrap();
+        let theirs = Seed::zero().derive(11).to_public().unwrap();
+        tracker.set_priority_accounts(vec![ours.to_owned()]);
+        let block = |account: &Public, link: Link| {
+            Block::from_state_block(&StateBlock::new(
+                account.to_owned(),
+                Previous::Open,
+                account.to_owned(),
+                Raw::from(1),
+                link,
+            ))
+        };
+
+        assert_eq!(tracker.prioritize(&block(&theirs, Link::Nothing)), 0);
+        assert!(rx.recv().now_or_never().is_none());
+
+        // Sends to a priority account count too.
+        let send = block(&theirs, Link::DestinationAccount(ours.to_owned()));
+        assert_eq!(tracker.prioritize(&send), 1);
+        assert!(rx.recv().now_or_never().is_some());
+
+        let open = block(&ours, Link::Nothing);
+        let hash = open.hash().unwrap().to_owned();
+        assert_eq!(tracker.prioritize(&open), 1);
+
+        // Nobody is waiting, but the votes are still counted up to quorum.
+        tracker.observe(&vote(0, vec![hash.to_owned()]));
+        tracker.observe(&vote(1, vec![hash.to_owned()]));
+        tracker.observe(&vote(2, vec![hash.to_owned()]));
+        let confirmation = tokio::time::timeout(
+            Duration::from_millis(100),
+            tracker.request(hash.to_owned(), open.root()),
+        )
+        .await
+        .unwrap()
+        .unwrap();
+        assert_eq!(confirmation.voters, 3);
+    }
+}
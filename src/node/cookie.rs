@@ -2,6 +2,7 @@ use crate::hexify;
 use crate::node::header::Header;
 use crate::node::wire::Wire;
 use anyhow::anyhow;
+use bytes::BytesMut;
 use rand::RngCore;
 use std::convert::TryFrom;
 
@@ -22,8 +23,8 @@ impl Cookie {
 }
 
 impl Wire for Cookie {
-    fn serialize(&self) -> Vec<u8> {
-        Vec::from(self.as_bytes())
+    fn serialize_into(&self, buf: &mut BytesMut) {
+        buf.extend_from_slice(self.as_bytes());
     }
 
     fn deserialize(_: Option<&Header>, data: &[u8]) -> anyhow::Result<Self>
@@ -0,0 +1,238 @@
+//! Decoding a raw capture of what one side of a connection sent, for `node debug-dump`.
+//!
+//! The capture is the TCP payload only, e.g. saved from Wireshark's "Follow TCP Stream" as raw
+//! bytes, so there's no pcap or IP framing to get through. Every message is decoded with the same
+//! [Wire] implementations the peers use, without a state or a connection behind them, so nothing
+//! is checked past whether the bytes make sense.
+//!
+//! Bytes that aren't a message are skipped up to the next header for the network, like a peer
+//! does after a framing error, and reported as [Decoded::Skipped].
+use crate::network::Network;
+use crate::node::header::{Header, MessageType};
+use crate::node::messages::bulk_pull::BulkPull;
+use crate::node::messages::confirm_ack::ConfirmAck;
+use crate::node::messages::confirm_req::ConfirmReq;
+use crate::node::messages::frontier_req::FrontierReq;
+use crate::node::messages::handshake::Handshake;
+use crate::node::messages::keepalive::Keepalive;
+use crate::node::messages::publish::Publish;
+use crate::node::messages::telemetry_ack::TelemetryAck;
+use crate::node::messages::telemetry_req::TelemetryReq;
+use crate::node::wire::Wire;
+use anyhow::anyhow;
+use std::fmt::{Debug, Display, Formatter};
+
+pub enum Decoded {
+    Message {
+        /// Where the header starts in the capture.
+        offset: usize,
+        header: Header,
+
+        /// The payload without the header.
+        payload: Vec<u8>,
+        message: Box<dyn Debug + Send>,
+    },
+
+    /// Bytes that couldn't be decoded.
+    Skipped {
+        offset: usize,
+        len: usize,
+        reason: String,
+    },
+}
+
+impl Display for Decoded {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Decoded::Message {
+                offset,
+                header,
+                payload,
+                message,
+            } => write!(
+                f,
+                "{:08x} {} ({} bytes) {:?}\n{:#?}",
+                offset,
+                header.message_type().name(),
+                Header::LEN + payload.len(),
+                header.ext(),
+                message
+            ),
+            Decoded::Skipped {
+                offset,
+                len,
+                reason,
+            } => write!(f, "{:08x} skipped {} bytes: {}", offset, len, reason),
+        }
+    }
+}
+
+/// Decode every message in `data`, in order.
+pub fn decode_capture(network: Network, data: &[u8]) -> Vec<Decoded> {
+    let mut decoded = vec![];
+    let mut offset = 0;
+    while offset < data.len() {
+        let rest = &data[offset..];
+        match Header::find_start(rest, network) {
+            Some(0) => {}
+            start => {
+                let len = start.unwrap_or(rest.len());
+                decoded.push(Decoded::Skipped {
+                    offset,
+                    len,
+                    reason: format!("Not a {:?} header", network),
+                });
+                offset += len;
+                continue;
+            }
+        }
+
+        let (len, entry) = decode_message(network, rest)
+            .map(|(len, header, message)| {
+                let entry = Decoded::Message {
+                    offset,
+                    header,
+                    payload: rest[Header::LEN..len].to_vec(),
+                    message,
+                };
+                (len, entry)
+            })
+            .unwrap_or_else(|(len, err)| {
+                // Past the magic number at least, so the next header is looked for after it.
+                let len = len.max(1);
+                let entry = Decoded::Skipped {
+                    offset,
+                    len,
+                    reason: format!("{:#}", err),
+                };
+                (len, entry)
+            });
+        decoded.push(entry);
+        offset += len;
+    }
+    decoded
+}
+
+type Message = (usize, Header, Box<dyn Debug + Send>);
+
+/// The message at the start of `data`, and its length with the header. On failure, how many
+/// bytes to skip: the whole message when only the payload is bad, otherwise none.
+fn decode_message(network: Network, data: &[u8]) -> Result<Message, (usize, anyhow::Error)> {
+    if data.len() < Header::LEN {
+        return Err((data.len(), anyhow!("Truncated header")));
+    }
+    let header = Header::deserialize(None, &data[..Header::LEN]).map_err(|err| (0, err))?;
+    header.validate(&network).map_err(|err| (0, err))?;
+
+    let h = Some(&header);
+    let len = match header.message_type() {
+        MessageType::Keepalive => Keepalive::len(h),
+        MessageType::Publish => Publish::len(h),
+        MessageType::ConfirmReq => ConfirmReq::len(h),
+        MessageType::ConfirmAck => ConfirmAck::len(h),
+        MessageType::FrontierReq => FrontierReq::len(h),
+        MessageType::Handshake => Handshake::len(h),
+        MessageType::TelemetryReq => TelemetryReq::len(h),
+        MessageType::TelemetryAck => TelemetryAck::len(h),
+        MessageType::BulkPull => BulkPull::len(h),
+        MessageType::BulkPush | MessageType::BulkPullAccount => {
+            Err(anyhow!("Can't decode {}", header.message_type().name()))
+        }
+    }
+    .map_err(|err| (0, err))?;
+
+    let end = Header::LEN + len;
+    if data.len() < end {
+        return Err((
+            data.len(),
+            anyhow!(
+                "Truncated {}: {} of {} payload bytes",
+                header.message_type().name(),
+                data.len() - Header::LEN,
+                len
+            ),
+        ));
+    }
+    let payload = &data[Header::LEN..end];
+    let message: anyhow::Result<Box<dyn Debug + Send>> = match header.message_type() {
+        MessageType::Keepalive => boxed::<Keepalive>(h, payload),
+        MessageType::Publish => boxed::<Publish>(h, payload),
+        MessageType::ConfirmReq => boxed::<ConfirmReq>(h, payload),
+        MessageType::ConfirmAck => boxed::<ConfirmAck>(h, payload),
+        MessageType::FrontierReq => boxed::<FrontierReq>(h, payload),
+        MessageType::Handshake => boxed::<Handshake>(h, payload),
+        MessageType::TelemetryReq => boxed::<TelemetryReq>(h, payload),
+        MessageType::TelemetryAck => boxed::<TelemetryAck>(h, payload),
+        MessageType::BulkPull => boxed::<BulkPull>(h, payload),
+        MessageType::BulkPush | MessageType::BulkPullAccount => unreachable!(),
+    };
+    let message = message.map_err(|err| (end, err))?;
+    Ok((end, header, message))
+}
+
+fn boxed<T: Wire + Send + 'static>(
+    header: Option<&Header>,
+    payload: &[u8],
+) -> anyhow::Result<Box<dyn Debug + Send>> {
+    Ok(Box::new(T::deserialize(header, payload)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::node::header::Extensions;
+    use crate::node::peer_info::PeerInfo;
+    use crate::Public;
+    use std::net::SocketAddr;
+    use std::str::FromStr;
+
+    fn message<T: Wire>(message_type: MessageType, ext: Extensions, message: &T) -> Vec<u8> {
+        let mut data = Header::new(Network::Live, message_type, ext).serialize();
+        data.extend(message.serialize());
+        data.to_vec()
+    }
+
+    #[test]
+    fn decodes_and_skips() {
+        let peer = PeerInfo::from(SocketAddr::from_str("127.0.0.1:7075").unwrap());
+        let keepalive = message(
+            MessageType::Keepalive,
+            Extensions::new(),
+            &Keepalive::new(vec![peer]),
+        );
+        let frontier_req = message(
+            MessageType::FrontierReq,
+            Extensions::new(),
+            &FrontierReq::new(Public::from_str(&"AB".repeat(32)).unwrap(), u32::MAX, 10),
+        );
+
+        let mut data = vec![1, 2, 3];
+        data.extend(&keepalive);
+        data.extend(
+            Header::new(Network::Beta, MessageType::Keepalive, Extensions::new()).serialize(),
+        );
+        data.extend(&frontier_req);
+
+        let decoded = decode_capture(Network::Live, &data);
+        let summary: Vec<String> = decoded
+            .iter()
+            .map(|d| d.to_string().lines().next().unwrap().to_owned())
+            .collect();
+        assert_eq!(summary.len(), 4, "{:#?}", summary);
+        assert_eq!(summary[0], "00000000 skipped 3 bytes: Not a Live header");
+        assert!(
+            summary[1].starts_with("00000003 keepalive (152 bytes)"),
+            "{}",
+            summary[1]
+        );
+        assert_eq!(summary[2], "0000009b skipped 8 bytes: Not a Live header");
+        assert!(summary[3].starts_with("000000a3 frontier_req (48 bytes)"));
+        assert!(matches!(decoded[3], Decoded::Message { .. }));
+
+        let truncated = decode_capture(Network::Live, &keepalive[..20]);
+        assert_eq!(
+            truncated[0].to_string(),
+            "00000000 skipped 20 bytes: Truncated keepalive: 12 of 144 payload bytes"
+        );
+    }
+}
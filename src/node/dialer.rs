@@ -0,0 +1,312 @@
+//! Keeping a target number of outbound connections to peers.
+//!
+//! Every [DIAL_INTERVAL] the node asks the dialer which of the known peers to dial, and dials
+//! them at the same time, up to a limit. Peers that failed to connect, or were disconnected for
+//! an error, lose score and wait longer before they're tried again, so peers that work are
+//! preferred over ones that didn't.
+//!
+//! An IPv4 peer is usually known by its IPv4-mapped IPv6 address, which is how the protocol
+//! sends addresses. That address is dialed first, and the plain IPv4 address is dialed too if
+//! it hasn't connected after [DialerConfig::fallback_delay], in the style of happy eyeballs
+//! (RFC 8305), so hosts without IPv6 still connect quickly. Whichever connects first is used.
+use anyhow::{anyhow, Context};
+use futures::stream::{FuturesUnordered, StreamExt};
+use std::cmp::Reverse;
+use std::collections::{HashMap, HashSet};
+use std::net::{IpAddr, SocketAddr};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::net::TcpStream;
+
+/// How often to top up the outbound connections.
+pub const DIAL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// How long a peer waits before it's dialed again, for each failure in a row.
+const RETRY_AFTER: Duration = Duration::from_secs(30);
+
+/// The longest a peer waits before it's dialed again.
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(30 * 60);
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DialerConfig {
+    /// How many peers to keep outbound connections to.
+    pub target: usize,
+
+    /// The most peers to be dialing at once.
+    pub concurrency: usize,
+
+    /// How long to wait for a peer to connect, including the fallback.
+    pub timeout: Duration,
+
+    /// How long to wait on the IPv6 address before also dialing the IPv4 one.
+    pub fallback_delay: Duration,
+}
+
+impl Default for DialerConfig {
+    fn default() -> Self {
+        Self {
+            target: 8,
+            concurrency: 4,
+            timeout: Duration::from_secs(5),
+            fallback_delay: Duration::from_millis(250),
+        }
+    }
+}
+
+/// Shared with every connection, to report how it went.
+#[derive(Debug, Clone)]
+pub struct Dialer {
+    config: DialerConfig,
+    inner: Arc<Mutex<Inner>>,
+}
+
+#[derive(Debug, Default)]
+struct Inner {
+    dialing: HashSet<SocketAddr>,
+    connected: HashSet<SocketAddr>,
+    scores: HashMap<SocketAddr, Score>,
+}
+
+#[derive(Debug, Default, Clone)]
+struct Score {
+    /// Up for each connection that ended well, down for each that didn't.
+    score: i64,
+
+    /// Failures since the last connection that ended well.
+    failures: u32,
+
+    /// Not to be dialed before this.
+    retry_at: Option<Instant>,
+}
+
+impl Dialer {
+    pub fn new(config: DialerConfig) -> Self {
+        Self {
+            config,
+            inner: Default::default(),
+        }
+    }
+
+    pub fn config(&self) -> &DialerConfig {
+        &self.config
+    }
+
+    /// Peers with a connection at the moment.
+    pub fn connected(&self) -> usize {
+        self.inner.lock().unwrap().connected.len()
+    }
+
+    /// Which of `peers` to dial now, best first, marking them as being dialed. `peers` should be
+    /// in order of preference otherwise, e.g. most recently seen first.
+    pub fn select(&self, peers: &[SocketAddr]) -> Vec<SocketAddr> {
+        let mut inner = self.inner.lock().unwrap();
+        let busy = inner.connected.len() + inner.dialing.len();
+        let wanted = self
+            .config
+            .target
+            .saturating_sub(busy)
+            .min(self.config.concurrency.saturating_sub(inner.dialing.len()));
+        if wanted == 0 {
+            return vec![];
+        }
+
+        let now = Instant::now();
+        let mut candidates: Vec<(usize, SocketAddr, i64)> = peers
+            .iter()
+            .enumerate()
+            .filter(|(_, address)| {
+                !inner.connected.contains(address) && !inner.dialing.contains(address)
+            })
+            .filter_map(|(order, address)| {
+                let score = inner.scores.get(address).cloned().unwrap_or_default();
+                if score.retry_at.is_some_and(|at| at > now) {
+                    return None;
+                }
+                Some((order, *address, score.score))
+            })
+            .collect();
+        candidates.sort_by_key(|(order, _, score)| (Reverse(*score), *order));
+
+        let selected: Vec<SocketAddr> = candidates
+            .into_iter()
+            .take(wanted)
+            .map(|(_, address, _)| address)
+            .collect();
+        inner.dialing.extend(selected.iter().cloned());
+        selected
+    }
+
+    /// Connect to a peer from [Dialer::select].
+    pub async fn dial(&self, address: SocketAddr) -> anyhow::Result<TcpStream> {
+        let result = tokio::time::timeout(
+            self.config.timeout,
+            happy_eyeballs(address, self.config.fallback_delay),
+        )
+        .await
+        .with_context(|| format!("Timed out connecting to {}", address))
+        .and_then(|result| result);
+
+        let mut inner = self.inner.lock().unwrap();
+        inner.dialing.remove(&address);
+        match &result {
+            Ok(_) => {
+                inner.connected.insert(address);
+            }
+            Err(_) => inner.failed(address),
+        }
+        result
+    }
+
+    /// A connection from [Dialer::dial] ended, after misbehaving or not.
+    pub fn disconnected(&self, address: SocketAddr, clean: bool) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.connected.remove(&address);
+        if clean {
+            let score = inner.scores.entry(address).or_default();
+            score.score += 1;
+            score.failures = 0;
+            score.retry_at = None;
+        } else {
+            inner.failed(address);
+        }
+    }
+
+    /// A peer from [Dialer::select] wasn't dialed after all, e.g. it's no longer allowed.
+    pub fn skipped(&self, address: SocketAddr) {
+        self.inner.lock().unwrap().dialing.remove(&address);
+    }
+}
+
+impl Inner {
+    fn failed(&mut self, address: SocketAddr) {
+        let score = self.scores.entry(address).or_default();
+        score.score -= 1;
+        score.failures += 1;
+        let wait = (RETRY_AFTER * score.failures).min(MAX_RETRY_AFTER);
+        score.retry_at = Some(Instant::now() + wait);
+    }
+}
+
+/// The addresses to try for a peer, in order.
+fn addresses(address: SocketAddr) -> Vec<SocketAddr> {
+    match address.ip() {
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(v4) => vec![address, SocketAddr::new(IpAddr::V4(v4), address.port())],
+            None => vec![address],
+        },
+        IpAddr::V4(_) => vec![address],
+    }
+}
+
+/// Dial each address of the peer, starting the next one after `delay` or as soon as the one
+/// before fails, and use the first to connect.
+async fn happy_eyeballs(address: SocketAddr, delay: Duration) -> anyhow::Result<TcpStream> {
+    let mut pending = addresses(address).into_iter();
+    let mut attempts = FuturesUnordered::new();
+    let mut last_err = None;
+    loop {
+        if attempts.is_empty() {
+            match pending.next() {
+                Some(next) => attempts.push(connect(next)),
+                None => {
+                    return Err(last_err.unwrap_or_else(|| anyhow!("No address for {}", address)))
+                }
+            }
+        }
+        let fallback = pending.len() > 0;
+        tokio::select! {
+            Some(result) = attempts.next() => match result {
+                Ok(stream) => return Ok(stream),
+                Err(err) => last_err = Some(err),
+            },
+            _ = tokio::time::sleep(delay), if fallback => {
+                if let Some(next) = pending.next() {
+                    attempts.push(connect(next));
+                }
+            }
+        }
+    }
+}
+
+async fn connect(address: SocketAddr) -> anyhow::Result<TcpStream> {
+    TcpStream::connect(address)
+        .await
+        .with_context(|| format!("Connecting to {}", address))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+
+    fn peer(n: u8) -> SocketAddr {
+        SocketAddr::from(([127, 0, 0, n], 7075))
+    }
+
+    #[test]
+    fn selects_up_to_the_limits() {
+        let dialer = Dialer::new(DialerConfig {
+            target: 3,
+            concurrency: 2,
+            ..Default::default()
+        });
+        let peers: Vec<SocketAddr> = (1..=5).map(peer).collect();
+        assert_eq!(dialer.select(&peers), vec![peer(1), peer(2)]);
+
+        // Both are still being dialed, so there's no room for more.
+        assert!(dialer.select(&peers).is_empty());
+        // A peer that wasn't dialed after all can be picked again.
+        dialer.skipped(peer(2));
+        assert_eq!(dialer.select(&peers), vec![peer(2)]);
+    }
+
+    #[test]
+    fn prefers_peers_that_worked() {
+        let dialer = Dialer::new(DialerConfig {
+            target: 2,
+            concurrency: 2,
+            ..Default::default()
+        });
+        let peers: Vec<SocketAddr> = (1..=3).map(peer).collect();
+        {
+            let mut inner = dialer.inner.lock().unwrap();
+            inner.failed(peer(1));
+            inner.scores.entry(peer(3)).or_default().score = 2;
+        }
+        // The first peer failed recently, so it waits.
+        assert_eq!(dialer.select(&peers), vec![peer(3), peer(2)]);
+    }
+
+    #[test]
+    fn mapped_addresses_fall_back_to_v4() {
+        let mapped: SocketAddr = "[::ffff:10.0.0.1]:7075".parse().unwrap();
+        assert_eq!(
+            addresses(mapped),
+            vec![mapped, "10.0.0.1:7075".parse().unwrap()]
+        );
+        let v6: SocketAddr = "[2001:db8::1]:7075".parse().unwrap();
+        assert_eq!(addresses(v6), vec![v6]);
+    }
+
+    #[tokio::test]
+    async fn dials_and_scores() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let dialer = Dialer::new(DialerConfig::default());
+
+        assert_eq!(dialer.select(&[address]), vec![address]);
+        dialer.dial(address).await.unwrap();
+        assert_eq!(dialer.connected(), 1);
+        assert!(dialer.select(&[address]).is_empty());
+
+        dialer.disconnected(address, false);
+        assert_eq!(dialer.connected(), 0);
+        // Waiting to be retried after misbehaving.
+        assert!(dialer.select(&[address]).is_empty());
+
+        // Nothing listens here once the listener is gone.
+        drop(listener);
+        assert!(dialer.dial(address).await.is_err());
+        assert_eq!(dialer.inner.lock().unwrap().scores[&address].failures, 2);
+    }
+}
@@ -0,0 +1,126 @@
+//! How much work went into recently added blocks, for `feeless node stats difficulty`.
+//!
+//! Every block's work difficulty is kept in its [Sideband] when it's added. Wallets only need
+//! their work over the threshold, but under load nodes prioritise blocks with more work, so the
+//! spread of recent multipliers shows what a wallet should aim for.
+use crate::node::state::{ArcState, Sideband};
+use crate::rpc::calls::{
+    DifficultyStatsResponse, MultiplierBucket, MultiplierPercentiles, MultiplierStats,
+};
+use crate::Network;
+use anyhow::Context;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Where each multiplier bucket starts.
+const MULTIPLIER_BUCKETS: [f64; 6] = [0., 1., 2., 4., 8., 16.];
+
+pub async fn difficulty_stats(
+    state: &ArcState,
+    network: Network,
+    seconds: u64,
+) -> anyhow::Result<DifficultyStatsResponse> {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .context("Difficulty stats")?
+        .as_secs();
+    let sidebands = state.sidebands_since(now.saturating_sub(seconds)).await?;
+    Ok(summarize(network, seconds, &sidebands))
+}
+
+fn summarize(network: Network, seconds: u64, sidebands: &[Sideband]) -> DifficultyStatsResponse {
+    let (mut send, mut receive) = (vec![], vec![]);
+    let mut without_work = 0;
+    for sideband in sidebands {
+        let difficulty = match &sideband.difficulty {
+            Some(difficulty) => difficulty,
+            None => {
+                without_work += 1;
+                continue;
+            }
+        };
+        if sideband.receive {
+            receive.push(difficulty.multiplier(&network.receive_difficulty()));
+        } else {
+            send.push(difficulty.multiplier(&network.send_difficulty()));
+        }
+    }
+    DifficultyStatsResponse {
+        seconds,
+        send: multiplier_stats(send),
+        receive: multiplier_stats(receive),
+        without_work,
+    }
+}
+
+fn multiplier_stats(mut multipliers: Vec<f64>) -> MultiplierStats {
+    multipliers.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    let percentiles = if multipliers.is_empty() {
+        None
+    } else {
+        let percentile = |p: usize| multipliers[(multipliers.len() - 1) * p / 100];
+        Some(MultiplierPercentiles {
+            min: multipliers[0],
+            p50: percentile(50),
+            p90: percentile(90),
+            max: multipliers[multipliers.len() - 1],
+        })
+    };
+    let mut buckets: Vec<MultiplierBucket> = MULTIPLIER_BUCKETS
+        .iter()
+        .map(|&min| MultiplierBucket { min, blocks: 0 })
+        .collect();
+    for multiplier in &multipliers {
+        let bucket = MULTIPLIER_BUCKETS
+            .iter()
+            .rposition(|&min| *multiplier >= min)
+            .unwrap_or(0);
+        buckets[bucket].blocks += 1;
+    }
+    MultiplierStats {
+        blocks: multipliers.len(),
+        percentiles,
+        buckets,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Difficulty;
+
+    fn sideband(multiplier: f64, receive: bool) -> Sideband {
+        let base = if receive {
+            Difficulty::receive()
+        } else {
+            Difficulty::normal()
+        };
+        Sideband {
+            timestamp: 0,
+            difficulty: Some(base.with_multiplier(multiplier)),
+            receive,
+            height: None,
+        }
+    }
+
+    #[test]
+    fn summarize_multipliers() {
+        let mut sidebands: Vec<_> = (1..=10).map(|m| sideband(m as f64 + 0.5, false)).collect();
+        sidebands.push(sideband(3., true));
+        sidebands.push(Sideband {
+            timestamp: 0,
+            difficulty: None,
+            receive: false,
+            height: None,
+        });
+
+        let stats = summarize(Network::Live, 60, &sidebands);
+        assert_eq!(stats.without_work, 1);
+        assert_eq!(stats.receive.blocks, 1);
+        assert_eq!(stats.send.blocks, 10);
+        let p = stats.send.percentiles.unwrap();
+        assert!((p.min - 1.5).abs() < 0.01, "{}", p.min);
+        assert!((p.p90 - 9.5).abs() < 0.01, "{}", p.p90);
+        let counts: Vec<_> = stats.send.buckets.iter().map(|b| b.blocks).collect();
+        assert_eq!(counts, vec![0, 1, 2, 4, 3, 0]);
+    }
+}
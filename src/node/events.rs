@@ -0,0 +1,43 @@
+use crate::blocks::BlockHash;
+use crate::rpc::websocket::{FrontierEvent, FrontierSource};
+use crate::Public;
+use tokio::sync::broadcast;
+
+/// Events that haven't been received yet before the slowest subscriber starts missing them.
+const CAPACITY: usize = 1024;
+
+/// Where frontier changes from peers and the bootstrap are sent, for the WebSocket server to
+/// pass on to subscribers.
+#[derive(Debug, Clone)]
+pub struct FrontierEvents {
+    tx: broadcast::Sender<FrontierEvent>,
+}
+
+impl FrontierEvents {
+    pub fn new() -> Self {
+        let (tx, _) = broadcast::channel(CAPACITY);
+        Self { tx }
+    }
+
+    pub fn publish(&self, account: &Public, hash: &BlockHash, source: FrontierSource) {
+        if self.tx.receiver_count() == 0 {
+            return;
+        }
+        // Nobody might be listening any more, which is fine.
+        let _ = self.tx.send(FrontierEvent {
+            account: account.to_address(),
+            hash: hash.to_owned(),
+            source,
+        });
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<FrontierEvent> {
+        self.tx.subscribe()
+    }
+}
+
+impl Default for FrontierEvents {
+    fn default() -> Self {
+        Self::new()
+    }
+}
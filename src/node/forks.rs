@@ -0,0 +1,301 @@
+//! Alerts for forks of watched accounts, e.g. someone trying to double spend a deposit.
+//!
+//! When a peer publishes a block of a watched account whose root is already taken by a stored
+//! block, a [ForkEvent] with both blocks is logged and sent to subscribers. Confirmation is then
+//! requested for both, and whichever reaches quorum first is sent in a second event as the
+//! winner. There are no elections, so the stored block is kept either way.
+//!
+//! The accounts watched are the priority accounts, see [ConfirmationTracker].
+use crate::blocks::{Block, BlockHash, Previous, Root, StateBlock};
+use crate::node::confirmation::ConfirmationTracker;
+use crate::node::state::{ArcState, DynState};
+use crate::{Address, Public};
+use serde::Serialize;
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::broadcast;
+use tracing::{info, warn};
+
+/// Events that haven't been received yet before the slowest subscriber starts missing them.
+const CAPACITY: usize = 64;
+
+/// How long to wait for either side of a fork to be confirmed.
+pub const FORK_CONFIRM_TIMEOUT: Duration = Duration::from_secs(300);
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ForkEvent {
+    pub account: Address,
+    pub root: Root,
+
+    /// The block we have.
+    pub existing: Block,
+
+    /// The block published for the same root.
+    pub competing: Block,
+
+    /// The block that reached quorum, in the second event for the fork.
+    pub winner: Option<BlockHash>,
+}
+
+/// Shared between the node and every peer to notice forks of watched accounts.
+#[derive(Debug, Clone)]
+pub struct ForkWatch {
+    accounts: Arc<Mutex<HashSet<Public>>>,
+    tx: broadcast::Sender<ForkEvent>,
+
+    /// Forks seen since starting.
+    count: Arc<AtomicUsize>,
+
+    timeout: Duration,
+}
+
+impl ForkWatch {
+    pub fn new() -> Self {
+        let (tx, _) = broadcast::channel(CAPACITY);
+        Self {
+            accounts: Default::default(),
+            tx,
+            count: Default::default(),
+            timeout: FORK_CONFIRM_TIMEOUT,
+        }
+    }
+
+    /// Replace the accounts to alert on.
+    pub fn set_accounts<I: IntoIterator<Item = Public>>(&self, accounts: I) {
+        *self.accounts.lock().unwrap() = accounts.into_iter().collect();
+    }
+
+    pub fn is_watched(&self, account: &Public) -> bool {
+        self.accounts.lock().unwrap().contains(account)
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<ForkEvent> {
+        self.tx.subscribe()
+    }
+
+    /// How many forks of watched accounts have been seen.
+    pub fn count(&self) -> usize {
+        self.count.load(Ordering::Relaxed)
+    }
+
+    /// Alert if `competing` is from a watched account and another block already has its root,
+    /// then wait for one of them to be confirmed in the background. Returns whether it was a
+    /// fork.
+    pub async fn check(
+        &self,
+        state: &ArcState,
+        confirmations: &ConfirmationTracker,
+        competing: &StateBlock,
+    ) -> anyhow::Result<bool> {
+        if !self.is_watched(&competing.account) {
+            return Ok(false);
+        }
+        let existing = successor(&**state, &competing.account, &competing.previous).await?;
+        let existing = match existing {
+            Some(existing) if existing.hash()? != &competing.hash => existing,
+            _ => return Ok(false),
+        };
+
+        let event = ForkEvent {
+            account: competing.account.to_address(),
+            root: competing.root(),
+            existing,
+            competing: Block::from_state_block(competing),
+            winner: None,
+        };
+        warn!(
+            "Fork of watched account {}: {:?} competes with our {:?}",
+            event.account,
+            event.competing.hash()?,
+            event.existing.hash()?
+        );
+        self.count.fetch_add(1, Ordering::Relaxed);
+        // Nobody might be listening, which is fine.
+        let _ = self.tx.send(event.clone());
+
+        tokio::spawn(self.clone().resolve(confirmations.clone(), event));
+        Ok(true)
+    }
+
+    /// Wait for either side of the fork in `event` to reach quorum, and send it again with the
+    /// winner.
+    async fn resolve(self, confirmations: ConfirmationTracker, mut event: ForkEvent) {
+        let existing = event.existing.hash().unwrap().to_owned();
+        let competing = event.competing.hash().unwrap().to_owned();
+        let either = async {
+            tokio::select! {
+                c = confirmations.request(existing, event.root.to_owned()) => c,
+                c = confirmations.request(competing, event.root.to_owned()) => c,
+            }
+        };
+        let winner = match tokio::time::timeout(self.timeout, either).await {
+            Ok(Ok(confirmation)) => confirmation.hash,
+            Ok(Err(err)) => {
+                warn!("Could not confirm either side of the fork: {:#}", err);
+                return;
+            }
+            Err(_) => {
+                warn!(
+                    "Neither side of the fork of {} at {:?} was confirmed in {:?}",
+                    event.account, event.root, self.timeout
+                );
+                return;
+            }
+        };
+        if event.existing.hash().unwrap() == &winner {
+            info!("Our block {:?} won the fork of {}", winner, event.account);
+        } else {
+            warn!(
+                "The competing block {:?} won the fork of {}",
+                winner, event.account
+            );
+        }
+        event.winner = Some(winner);
+        let _ = self.tx.send(event);
+    }
+}
+
+impl Default for ForkWatch {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The stored block of `account` that follows `previous`, or its open block.
+async fn successor(
+    state: &DynState,
+    account: &Public,
+    previous: &Previous,
+) -> anyhow::Result<Option<Block>> {
+    let mut next = state.get_latest_block_hash_for_account(account).await?;
+    while let Some(hash) = next {
+        let block = match state.get_block_by_hash(&hash).await? {
+            Some(block) => block,
+            None => return Ok(None),
+        };
+        if block.previous() == previous {
+            return Ok(Some(block));
+        }
+        next = match block.previous() {
+            Previous::Block(hash) => Some(hash.to_owned()),
+            Previous::Open => None,
+        };
+    }
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blocks::Link;
+    use crate::node::messages::confirm_ack::{Confirm, ConfirmAck};
+    use crate::node::state::MemoryState;
+    use crate::node::test_util;
+    use crate::node::timestamp::Timestamp;
+    use crate::{Network, Raw};
+    use std::convert::TryFrom;
+
+    fn block(account: &Public, previous: Previous, balance: u128) -> StateBlock {
+        StateBlock::new(
+            account.to_owned(),
+            previous,
+            account.to_owned(),
+            Raw::from(balance),
+            Link::Nothing,
+        )
+    }
+
+    /// A state with the test account at `index` opened and then sent from.
+    async fn chain(index: u32) -> (ArcState, StateBlock, StateBlock) {
+        let state: ArcState = Arc::new(MemoryState::new(Network::Test));
+        let chain = test_util::chain(index, 2);
+        for block in &chain {
+            state
+                .add_block(&Block::from_state_block(block))
+                .await
+                .unwrap();
+        }
+        (state, chain[0].to_owned(), chain[1].to_owned())
+    }
+
+    fn vote(index: u32, hash: &BlockHash) -> ConfirmAck {
+        let private = test_util::private(index);
+        let mut ack = ConfirmAck::new(
+            private.to_public().unwrap(),
+            private.sign(&[]).unwrap(),
+            Timestamp::try_from([0u8; Timestamp::LEN].as_ref()).unwrap(),
+            Confirm::VoteByHash(vec![hash.to_owned()]),
+        );
+        ack.signature = private.sign(&ack.inner_hash()).unwrap();
+        ack
+    }
+
+    #[tokio::test]
+    async fn alerts_on_watched_accounts() {
+        let account = test_util::account(0);
+        let (state, open, send) = chain(0).await;
+        let double_spend = block(&account, Previous::Block(open.hash.to_owned()), 0);
+        let next = block(&account, Previous::Block(send.hash.to_owned()), 0);
+
+        let forks = ForkWatch::new();
+        let confirmations = ConfirmationTracker::new(Network::Test);
+        let mut events = forks.subscribe();
+        assert!(!forks
+            .check(&state, &confirmations, &double_spend)
+            .await
+            .unwrap());
+
+        forks.set_accounts(vec![account.to_owned()]);
+        assert!(!forks.check(&state, &confirmations, &next).await.unwrap());
+        assert!(!forks.check(&state, &confirmations, &send).await.unwrap());
+        assert!(forks
+            .check(&state, &confirmations, &double_spend)
+            .await
+            .unwrap());
+        let event = events.recv().await.unwrap();
+        assert_eq!(event.existing.hash().unwrap(), &send.hash);
+        assert_eq!(event.competing.hash().unwrap(), &double_spend.hash);
+        assert_eq!(event.root, double_spend.root());
+        assert_eq!(event.winner, None);
+
+        let reopen = block(&account, Previous::Open, 20);
+        assert!(forks.check(&state, &confirmations, &reopen).await.unwrap());
+        assert_eq!(
+            events.recv().await.unwrap().existing.hash().unwrap(),
+            &open.hash
+        );
+        assert_eq!(forks.count(), 2);
+    }
+
+    #[tokio::test]
+    async fn reports_the_winner() {
+        let account = test_util::account(0);
+        let (state, open, send) = chain(0).await;
+        let double_spend = block(&account, Previous::Block(open.hash.to_owned()), 0);
+
+        let forks = ForkWatch::new();
+        forks.set_accounts(vec![account.to_owned()]);
+        let confirmations = ConfirmationTracker::new(Network::Test);
+        let rep = test_util::account(9);
+        confirmations.set_weights(vec![(rep, Raw::from(100))].into_iter().collect());
+        let mut events = forks.subscribe();
+        forks
+            .check(&state, &confirmations, &double_spend)
+            .await
+            .unwrap();
+        assert_eq!(events.recv().await.unwrap().winner, None);
+
+        // The confirm_reqs are sent in the background, so vote until they've been counted.
+        let event = loop {
+            confirmations.observe(&vote(9, &double_spend.hash));
+            let recv = tokio::time::timeout(Duration::from_millis(10), events.recv());
+            if let Ok(event) = recv.await {
+                break event.unwrap();
+            }
+        };
+        assert_eq!(event.winner, Some(double_spend.hash.to_owned()));
+        assert_eq!(event.existing.hash().unwrap(), &send.hash);
+    }
+}
@@ -1,12 +1,14 @@
 use crate::blocks::BlockType;
 use crate::encoding::expect_len;
 use crate::network::Network;
-use crate::node::wire::Wire;
-use crate::version::Version;
+use crate::node::wire::{decode, Field, Wire};
+use crate::node::ProtocolVersion;
 use anyhow::{anyhow, Context};
 use bitvec::prelude::*;
+use bytes::BytesMut;
 use std::convert::{TryFrom, TryInto};
 use std::result::Result;
+use std::str::FromStr;
 
 // TODO: Have header internally only contain [u8; 8] and use accessors, so that the header doesn't
 //       have to be encoded/decoded when sending/receiving.
@@ -21,9 +23,9 @@ pub struct Header {
 
     /// Protocol version
     /// https://github.com/nanocurrency/nano-node/blob/8c650ee8f537c3ded9a4a518f5f7df56c6a67904/nano/secure/common.hpp#L350
-    version_max: Version,
-    version_using: Version,
-    version_min: Version,
+    version_max: ProtocolVersion,
+    version_using: ProtocolVersion,
+    version_min: ProtocolVersion,
 
     /// Type of data in the payload.
     /// https://github.com/nanocurrency/nano-node/blob/8c650ee8f537c3ded9a4a518f5f7df56c6a67904/nano/node/common.hpp#L162
@@ -43,12 +45,19 @@ impl Header {
             ));
         }
 
-        // TODO: Check versions.
+        if self.version_using < ProtocolVersion::MIN {
+            return Err(anyhow!(
+                "version too old: They're using {}. We need at least {}",
+                self.version_using,
+                ProtocolVersion::MIN,
+            ));
+        }
 
+        self.ext.validate(self.message_type)?;
         Ok(())
     }
 
-    pub fn to_short_string(&self) -> String {
+    pub fn to_short_string(self) -> String {
         format!("{:?} {:?}", self.message_type, self.ext)
     }
 }
@@ -69,20 +78,40 @@ impl Header {
         Self {
             magic_number: MagicNumber::new(),
             network,
-            version_max: Version::V18,
-            version_using: Version::V18,
-            version_min: Version::V18,
+            version_max: ProtocolVersion::CURRENT,
+            version_using: ProtocolVersion::CURRENT,
+            version_min: ProtocolVersion::MIN,
             message_type,
             ext,
         }
     }
 
+    /// Where the next header might start in `data`, for skipping over garbage from a peer.
+    ///
+    /// A lone magic number at the end counts, since the rest of the header might not have
+    /// arrived yet.
+    pub fn find_start(data: &[u8], network: Network) -> Option<usize> {
+        (0..data.len()).find(|&idx| {
+            data[idx] == MagicNumber::MAGIC && data.get(idx + 1).is_none_or(|&n| n == network as u8)
+        })
+    }
+
     pub fn reset(&mut self, message_type: MessageType, ext: Extensions) -> &mut Self {
         self.message_type = message_type;
         self.ext = ext;
         self
     }
 
+    /// The version the message was encoded with.
+    pub fn version(&self) -> ProtocolVersion {
+        self.version_using
+    }
+
+    pub fn set_version(&mut self, version: ProtocolVersion) -> &mut Self {
+        self.version_using = version;
+        self
+    }
+
     pub fn message_type(&self) -> MessageType {
         self.message_type
     }
@@ -93,23 +122,23 @@ impl Header {
 }
 
 impl Wire for Header {
-    fn serialize(&self) -> Vec<u8> {
-        vec![
+    fn serialize_into(&self, buf: &mut BytesMut) {
+        buf.extend_from_slice(&[
             self.magic_number.0,
             self.network as u8,
-            self.version_max as u8,
-            self.version_using as u8,
-            self.version_min as u8,
+            self.version_max.as_u8(),
+            self.version_using.as_u8(),
+            self.version_min.as_u8(),
             self.message_type as u8,
             self.ext.0[0],
             self.ext.0[1],
-        ]
+        ]);
     }
 
     fn deserialize(header: Option<&Header>, data: &[u8]) -> anyhow::Result<Self> {
         debug_assert!(header.is_none());
 
-        let context = || format!("Deserializing header");
+        let context = || "Deserializing header".to_string();
 
         expect_len(data.len(), Header::LEN, "Header")?;
         MagicNumber::try_from(data[Self::MAGIC_NUMBER]).with_context(context)?;
@@ -119,12 +148,37 @@ impl Wire for Header {
         let ext =
             Extensions::try_from(&data[Self::EXTENSIONS..Self::EXTENSIONS + Extensions::LEN])?;
 
-        Ok(Header::new(network, message_type, ext))
+        let mut header = Header::new(network, message_type, ext);
+        header.version_max = data[Self::VERSION_MAX].into();
+        header.version_using = data[Self::VERSION_USING].into();
+        header.version_min = data[Self::VERSION_MIN].into();
+        Ok(header)
     }
 
     fn len(_: Option<&Header>) -> anyhow::Result<usize> {
         Ok(Header::LEN)
     }
+
+    fn fields(_: Option<&Header>) -> anyhow::Result<Vec<Field>> {
+        Ok(vec![
+            Field::new("magic", 1),
+            Field::new("network", 1).decoded(|d| match Network::try_from(d[0]) {
+                Ok(network) => format!("{:?}", network),
+                Err(_) => "unknown".into(),
+            }),
+            Field::new("version_max", 1).decoded(decode::u8),
+            Field::new("version_using", 1).decoded(decode::u8),
+            Field::new("version_min", 1).decoded(decode::u8),
+            Field::new("message_type", 1).decoded(|d| match MessageType::try_from(d[0]) {
+                Ok(message_type) => format!("{:?}", message_type),
+                Err(_) => "unknown".into(),
+            }),
+            Field::new("extensions", Extensions::LEN).decoded(|d| match Extensions::try_from(d) {
+                Ok(ext) => format!("{:?}", ext),
+                Err(_) => "unknown".into(),
+            }),
+        ])
+    }
 }
 
 #[derive(Clone, Copy, PartialEq)]
@@ -176,6 +230,87 @@ pub enum MessageType {
     TelemetryAck = 13,
 }
 
+impl MessageType {
+    /// The largest payload a well behaved peer would send with this type of message. Anything
+    /// claiming to be bigger is treated as a framing error.
+    pub fn max_payload_len(&self) -> usize {
+        use MessageType::*;
+        match self {
+            // Always exactly 8 peers.
+            Keepalive => 8 * 18,
+            // A state block is the largest block.
+            Publish => 216,
+            // Up to 7 hash and root pairs, or a block.
+            ConfirmReq => 7 * 64,
+            // The account, signature and timestamp, then up to 12 hashes or a block.
+            ConfirmAck => 104 + 12 * 32,
+            BulkPull => 72,
+            BulkPush => 0,
+            FrontierReq => 40,
+            // A cookie and the response to one.
+            Handshake => 32 + 96,
+            BulkPullAccount => 49,
+            TelemetryReq => 0,
+            // The size is in the 10 lower bits of the extensions.
+            TelemetryAck => (1 << 10) - 1,
+        }
+    }
+}
+
+impl MessageType {
+    pub const ALL: [MessageType; 11] = [
+        MessageType::Keepalive,
+        MessageType::Publish,
+        MessageType::ConfirmReq,
+        MessageType::ConfirmAck,
+        MessageType::BulkPull,
+        MessageType::BulkPush,
+        MessageType::FrontierReq,
+        MessageType::Handshake,
+        MessageType::BulkPullAccount,
+        MessageType::TelemetryReq,
+        MessageType::TelemetryAck,
+    ];
+
+    /// The name used by the C++ node, e.g. `confirm_ack`.
+    pub fn name(&self) -> &'static str {
+        use MessageType::*;
+        match self {
+            Keepalive => "keepalive",
+            Publish => "publish",
+            ConfirmReq => "confirm_req",
+            ConfirmAck => "confirm_ack",
+            BulkPull => "bulk_pull",
+            BulkPush => "bulk_push",
+            FrontierReq => "frontier_req",
+            Handshake => "node_id_handshake",
+            BulkPullAccount => "bulk_pull_account",
+            TelemetryReq => "telemetry_req",
+            TelemetryAck => "telemetry_ack",
+        }
+    }
+}
+
+impl FromStr for MessageType {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        MessageType::ALL
+            .iter()
+            .find(|t| t.name() == s || (**t == MessageType::Handshake && s == "handshake"))
+            .copied()
+            .ok_or_else(|| {
+                let names: Vec<_> = MessageType::ALL.iter().map(|t| t.name()).collect();
+                anyhow!(
+                    "Unknown message type {:?}, expected one of: {}",
+                    s,
+                    names.join(", ")
+                )
+            })
+    }
+}
+
 impl TryFrom<u8> for MessageType {
     type Error = anyhow::Error;
 
@@ -198,19 +333,51 @@ impl TryFrom<u8> for MessageType {
     }
 }
 
-#[derive(Clone, Copy, PartialEq)]
+/// Flags and small fields that change how a message's payload is read.
+///
+/// The bytes are kept as they were received, including bits this node doesn't know about, so a
+/// header that's read and written again is unchanged.
+#[derive(Clone, Copy, Default, PartialEq)]
 pub struct Extensions([u8; 2]);
 
+/// A bit set in the flag byte of [Extensions], from [Extensions::flags].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Flag {
+    Query,
+    Response,
+
+    /// A bit without a meaning to this node, by its offset.
+    Unknown(u8),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, thiserror::Error)]
+pub enum ExtensionsError {
+    #[error("Handshake is neither a query nor a response")]
+    EmptyHandshake,
+
+    #[error("{0:?} can't be both a query and a response")]
+    QueryAndResponse(MessageType),
+}
+
 impl Extensions {
     const LEN: usize = 2;
 
     // Bit offsets and lengths
     const QUERY: usize = 0;
     const RESPONSE: usize = 1;
+    /// Only for handshakes, where a response also has a salt and the genesis hash.
+    const V2: usize = 2;
+    /// Only for bulk pulls, where it shares the bit with [Self::QUERY].
+    const COUNT_PRESENT: usize = 0;
+    /// Only for bulk pulls, where it shares the bit with [Self::RESPONSE].
+    const ASCENDING: usize = 1;
     const ITEM_COUNT: usize = 12;
     const ITEM_COUNT_BITS: usize = 4;
     const BLOCK_TYPE: usize = 8;
     const BLOCK_TYPE_BITS: usize = 4;
+    const TELEMETRY_SIZE_BITS: usize = 10;
+    /// The lower byte holds single bit flags, the upper one the block type and item count.
+    const FLAG_BITS: usize = 8;
 
     pub fn new() -> Self {
         Self([0, 0])
@@ -234,16 +401,99 @@ impl Extensions {
         self.bits()[Self::RESPONSE]
     }
 
+    pub fn v2(&mut self) -> &mut Self {
+        self.mut_bits().set(Self::V2, true);
+        self
+    }
+
+    pub fn is_v2(&self) -> bool {
+        self.bits()[Self::V2]
+    }
+
+    /// A bulk pull with a count after the usual payload.
+    pub fn set_count_present(&mut self) -> &mut Self {
+        self.mut_bits().set(Self::COUNT_PRESENT, true);
+        self
+    }
+
+    pub fn is_count_present(&self) -> bool {
+        self.bits()[Self::COUNT_PRESENT]
+    }
+
+    /// A bulk pull going from the start block towards the frontier, instead of back from it.
+    pub fn set_ascending(&mut self) -> &mut Self {
+        self.mut_bits().set(Self::ASCENDING, true);
+        self
+    }
+
+    pub fn is_ascending(&self) -> bool {
+        self.bits()[Self::ASCENDING]
+    }
+
     pub fn item_count(&self) -> usize {
         self.bits()[Self::ITEM_COUNT..Self::ITEM_COUNT + Self::ITEM_COUNT_BITS].load_be()
     }
 
+    pub fn set_item_count(&mut self, count: usize) -> &mut Self {
+        debug_assert!(count < 1 << Self::ITEM_COUNT_BITS);
+        self.mut_bits()[Self::ITEM_COUNT..Self::ITEM_COUNT + Self::ITEM_COUNT_BITS].store_be(count);
+        self
+    }
+
+    /// Size of a telemetry ack payload.
+    pub fn telemetry_size(&self) -> usize {
+        self.bits()[..Self::TELEMETRY_SIZE_BITS].load_le()
+    }
+
+    pub fn set_telemetry_size(&mut self, size: usize) -> &mut Self {
+        debug_assert!(size < 1 << Self::TELEMETRY_SIZE_BITS);
+        self.mut_bits()[..Self::TELEMETRY_SIZE_BITS].store_le(size);
+        self
+    }
+
     pub fn block_type(&self) -> anyhow::Result<BlockType> {
         self.bits()[Self::BLOCK_TYPE..Self::BLOCK_TYPE + Self::BLOCK_TYPE_BITS]
             .load_be::<u8>()
             .try_into()
     }
 
+    pub fn set_block_type(&mut self, block_type: BlockType) -> &mut Self {
+        self.mut_bits()[Self::BLOCK_TYPE..Self::BLOCK_TYPE + Self::BLOCK_TYPE_BITS]
+            .store_be(block_type.as_u8());
+        self
+    }
+
+    /// Every bit set in the flag byte, lowest first. Some message types use these bits for
+    /// something else, e.g. the size of a telemetry ack, so this is mostly for logging.
+    pub fn flags(&self) -> impl Iterator<Item = Flag> + '_ {
+        self.bits()[..Self::FLAG_BITS]
+            .iter_ones()
+            .map(|bit| match bit {
+                Self::QUERY => Flag::Query,
+                Self::RESPONSE => Flag::Response,
+                bit => Flag::Unknown(bit as u8),
+            })
+    }
+
+    /// Whether the query and response flags make sense together for `message_type`.
+    ///
+    /// A handshake is a query, a response, or both at once. Other messages that use the flags
+    /// are one or the other. Bulk pulls and telemetry acks use the bits for something else, so
+    /// anything goes.
+    pub fn validate(&self, message_type: MessageType) -> Result<(), ExtensionsError> {
+        use MessageType::*;
+        match message_type {
+            Handshake if !self.is_query() && !self.is_response() => {
+                Err(ExtensionsError::EmptyHandshake)
+            }
+            Handshake | BulkPull | TelemetryAck => Ok(()),
+            _ if self.is_query() && self.is_response() => {
+                Err(ExtensionsError::QueryAndResponse(message_type))
+            }
+            _ => Ok(()),
+        }
+    }
+
     fn bits(&self) -> &BitSlice<Lsb0, u8> {
         self.0.view_bits()
     }
@@ -255,15 +505,11 @@ impl Extensions {
 
 impl std::fmt::Debug for Extensions {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let mut s = vec![];
-        if self.is_query() {
-            s.push("Query")
-        }
-        if self.is_response() {
-            s.push("Response")
+        let flags: Vec<String> = self.flags().map(|flag| format!("{:?}", flag)).collect();
+        write!(f, "[{}]", flags.join(", "))?;
+        if self.0[1] != 0 {
+            write!(f, " 0x{:02X}", self.0[1])?;
         }
-        write!(f, "[{}]", s.join(", "))?;
-
         Ok(())
     }
 }
@@ -310,8 +556,7 @@ mod tests {
                 return;
             }
         }
-        assert!(
-            false,
+        panic!(
             "Got error:\n{:?}\n\nExpecting: {}",
             &result.err().unwrap(),
             s
@@ -346,6 +591,56 @@ mod tests {
         assert_contains_err(Header::deserialize(None, &s), "message type");
     }
 
+    #[test]
+    fn versions() {
+        let s = vec![0x52, 0x43, 20, 19, 18, 2, 3, 0];
+        let h = Header::deserialize(None, &s).unwrap();
+        assert_eq!(h.version(), ProtocolVersion::V19);
+        assert_eq!(h.version_max, ProtocolVersion::V20);
+        assert_eq!(h.version_min, ProtocolVersion::V18);
+        assert_eq!(h.serialize(), s);
+    }
+
+    #[test]
+    fn too_old() {
+        let s = vec![0x52, 0x43, 17, 17, 17, 2, 3, 0];
+        let header = Header::deserialize(None, &s).unwrap();
+        assert_contains_err(header.validate(&Network::Live), "version too old");
+
+        // Supporting a newer version doesn't help when they're using an old one.
+        let s = vec![0x52, 0x43, 20, 17, 17, 2, 3, 0];
+        let header = Header::deserialize(None, &s).unwrap();
+        assert_contains_err(header.validate(&Network::Live), "version too old");
+
+        let s = vec![0x52, 0x43, 20, 18, 17, 2, 0, 0];
+        let header = Header::deserialize(None, &s).unwrap();
+        header.validate(&Network::Live).unwrap();
+    }
+
+    #[test]
+    fn telemetry_size() {
+        let ext = Extensions::try_from([0xca, 0xfc].as_ref()).unwrap();
+        assert_eq!(ext.telemetry_size(), 0xca);
+        let ext = Extensions::try_from([0x02, 0x01].as_ref()).unwrap();
+        assert_eq!(ext.telemetry_size(), 0x102);
+        assert_eq!(
+            Extensions::new().set_telemetry_size(0x102).telemetry_size(),
+            0x102
+        );
+    }
+
+    #[test]
+    fn find_start() {
+        let live = Network::Live;
+        assert_eq!(Header::find_start(&[0x52, 0x43, 18], live), Some(0));
+        assert_eq!(
+            Header::find_start(&[0, 0x52, 0x41, 0x52, 0x43], live),
+            Some(3)
+        );
+        assert_eq!(Header::find_start(&[0, 1, 0x52], live), Some(2));
+        assert_eq!(Header::find_start(&[0, 1, 2], live), None);
+    }
+
     #[test]
     fn message_type() {
         let s = vec![0x52, 0x43, 18, 18, 18, 3, 3, 0];
@@ -353,6 +648,52 @@ mod tests {
         assert_eq!(h.message_type, MessageType::Publish);
     }
 
+    #[test]
+    fn unknown_bits_round_trip() {
+        let s = vec![0x52, 0x43, 18, 18, 18, 10, 0b1010_0101, 0x5A];
+        let header = Header::deserialize(None, &s).unwrap();
+        assert_eq!(header.serialize(), s);
+
+        let flags: Vec<Flag> = header.ext().flags().collect();
+        assert_eq!(
+            flags,
+            vec![
+                Flag::Query,
+                Flag::Unknown(2),
+                Flag::Unknown(5),
+                Flag::Unknown(7)
+            ]
+        );
+        assert_eq!(
+            format!("{:?}", header.ext()),
+            "[Query, Unknown(2), Unknown(5), Unknown(7)] 0x5A"
+        );
+        header.validate(&Network::Live).unwrap();
+    }
+
+    #[test]
+    fn query_and_response() {
+        let both = *Extensions::new().query().response();
+        assert_eq!(both.validate(MessageType::Handshake), Ok(()));
+        assert_eq!(
+            both.validate(MessageType::Keepalive),
+            Err(ExtensionsError::QueryAndResponse(MessageType::Keepalive))
+        );
+        // The bits are the telemetry size here.
+        assert_eq!(both.validate(MessageType::TelemetryAck), Ok(()));
+        assert_eq!(
+            Extensions::new().validate(MessageType::Handshake),
+            Err(ExtensionsError::EmptyHandshake)
+        );
+
+        let header = Header::new(Network::Live, MessageType::Publish, both);
+        let err = header.validate(&Network::Live).unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<ExtensionsError>(),
+            Some(&ExtensionsError::QueryAndResponse(MessageType::Publish))
+        );
+    }
+
     #[test]
     fn item_count() {
         let fixtures: &[(u8, u8, u8)] = &[
@@ -0,0 +1,254 @@
+//! The `/health` and `/ready` endpoints of the RPC server, for orchestration like Kubernetes to
+//! probe the node with.
+//!
+//! A node is healthy while it's connected to enough peers and blocks keep being added to its
+//! ledger, and ready once it's healthy and the frontier bootstrap is done. The thresholds come
+//! from the `[health]` section of the config file, e.g.
+//! ```toml
+//! [health]
+//! min_peers = 4
+//! min_blocks_per_minute = 1.0
+//! ```
+//! The node doesn't cement blocks as they arrive, so the rate of blocks added to the ledger
+//! stands in for the cementing rate. It isn't checked during the first minute after starting.
+use crate::node::events::FrontierEvents;
+use crate::rpc::calls::BootstrapStatusResponse;
+use crate::rpc::websocket::FrontierSource;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::broadcast::error::RecvError;
+
+/// How far back the block rate looks, which is also how long after starting before it counts.
+const WINDOW: Duration = Duration::from_secs(60);
+
+/// The `[health]` section of the config file.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct HealthConfig {
+    /// Unhealthy with fewer connected peers than this.
+    #[serde(default = "default_min_peers")]
+    pub min_peers: usize,
+
+    /// Unhealthy when fewer blocks than this were added in the last minute. 0 turns the check
+    /// off, which suits quiet test networks.
+    #[serde(default)]
+    pub min_blocks_per_minute: f64,
+}
+
+fn default_min_peers() -> usize {
+    1
+}
+
+impl Default for HealthConfig {
+    fn default() -> Self {
+        Self {
+            min_peers: default_min_peers(),
+            min_blocks_per_minute: 0.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HealthReport {
+    pub healthy: bool,
+    pub ready: bool,
+
+    /// Seconds since the node started.
+    pub uptime: u64,
+
+    pub peers: usize,
+    pub blocks_per_minute: f64,
+
+    /// The frontier bootstrap is done, or wasn't started.
+    pub synced: bool,
+
+    /// How much of the network's blocks are in our ledger, estimated from the telemetry of
+    /// peers. `None` until a peer has sent telemetry. It doesn't count towards being ready,
+    /// since the estimate is only as good as the peers we happen to be connected to.
+    #[serde(default)]
+    pub sync_percentage: Option<f64>,
+
+    /// Why the node isn't healthy or ready, if it isn't.
+    pub problems: Vec<String>,
+}
+
+impl HealthConfig {
+    pub fn report(
+        &self,
+        uptime: Duration,
+        peers: usize,
+        blocks_per_minute: f64,
+        bootstrap: &BootstrapStatusResponse,
+    ) -> HealthReport {
+        let mut problems = vec![];
+        if peers < self.min_peers {
+            problems.push(format!(
+                "Connected to {} peers, needs at least {}",
+                peers, self.min_peers
+            ));
+        }
+        if uptime >= WINDOW && blocks_per_minute < self.min_blocks_per_minute {
+            problems.push(format!(
+                "{} blocks were added in the last minute, needs at least {}",
+                blocks_per_minute, self.min_blocks_per_minute
+            ));
+        }
+        let healthy = problems.is_empty();
+
+        let synced = !bootstrap.running && bootstrap.ranges_done >= bootstrap.ranges_total;
+        if !synced {
+            problems.push(format!(
+                "Bootstrapping, {}/{} ranges done",
+                bootstrap.ranges_done, bootstrap.ranges_total
+            ));
+        }
+        HealthReport {
+            healthy,
+            ready: healthy && synced,
+            uptime: uptime.as_secs(),
+            peers,
+            blocks_per_minute,
+            synced,
+            sync_percentage: None,
+            problems,
+        }
+    }
+}
+
+/// Counts the blocks added to the ledger in each second of the last minute.
+#[derive(Debug, Clone)]
+pub struct BlockRate {
+    started: Instant,
+
+    /// Seconds since `started`, and the blocks added in that second.
+    seconds: Arc<Mutex<VecDeque<(u64, u64)>>>,
+}
+
+impl BlockRate {
+    pub fn new() -> Self {
+        Self {
+            started: Instant::now(),
+            seconds: Default::default(),
+        }
+    }
+
+    pub fn uptime(&self) -> Duration {
+        self.started.elapsed()
+    }
+
+    pub fn record(&self, blocks: u64) {
+        self.record_at(self.uptime(), blocks);
+    }
+
+    fn record_at(&self, uptime: Duration, blocks: u64) {
+        let second = uptime.as_secs();
+        let mut seconds = self.seconds.lock().unwrap();
+        match seconds.back_mut() {
+            Some((last, count)) if *last == second => *count += blocks,
+            _ => seconds.push_back((second, blocks)),
+        }
+        while matches!(seconds.front(), Some((first, _)) if first + WINDOW.as_secs() <= second) {
+            seconds.pop_front();
+        }
+    }
+
+    pub fn per_minute(&self) -> f64 {
+        self.per_minute_at(self.uptime())
+    }
+
+    fn per_minute_at(&self, uptime: Duration) -> f64 {
+        let now = uptime.as_secs();
+        self.seconds
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(second, _)| second + WINDOW.as_secs() > now)
+            .map(|(_, count)| *count)
+            .sum::<u64>() as f64
+    }
+
+    /// Count every block added from `events`, until the node stops.
+    pub async fn count(self, events: FrontierEvents) {
+        let mut rx = events.subscribe();
+        loop {
+            match rx.recv().await {
+                Ok(event) if event.source == FrontierSource::Block => self.record(1),
+                Ok(_) => {}
+                Err(RecvError::Lagged(missed)) => self.record(missed),
+                Err(RecvError::Closed) => break,
+            }
+        }
+    }
+}
+
+impl Default for BlockRate {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bootstrap(
+        running: bool,
+        ranges_done: usize,
+        ranges_total: usize,
+    ) -> BootstrapStatusResponse {
+        BootstrapStatusResponse {
+            running,
+            ranges_total,
+            ranges_done,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn thresholds() {
+        let config = HealthConfig {
+            min_peers: 2,
+            min_blocks_per_minute: 10.0,
+        };
+        let minute = Duration::from_secs(60);
+
+        let report = config.report(minute, 2, 10.0, &bootstrap(false, 0, 0));
+        assert!(report.healthy && report.ready, "{:?}", report);
+
+        let report = config.report(minute, 1, 10.0, &bootstrap(false, 0, 0));
+        assert!(!report.healthy && !report.ready);
+        assert_eq!(report.problems.len(), 1);
+
+        // A slow minute right after starting doesn't count.
+        let slow = config.report(minute, 2, 1.0, &bootstrap(false, 0, 0));
+        assert!(!slow.healthy);
+        assert!(
+            config
+                .report(Duration::from_secs(5), 2, 1.0, &bootstrap(false, 0, 0))
+                .healthy
+        );
+
+        let report = config.report(minute, 2, 10.0, &bootstrap(true, 3, 16));
+        assert!(report.healthy && !report.ready && !report.synced);
+        assert!(
+            !config
+                .report(minute, 2, 10.0, &bootstrap(false, 3, 16))
+                .ready
+        );
+    }
+
+    #[test]
+    fn block_rate() {
+        let rate = BlockRate::new();
+        let at = Duration::from_secs;
+        rate.record_at(at(1), 5);
+        rate.record_at(at(1), 1);
+        rate.record_at(at(30), 4);
+        assert_eq!(rate.per_minute_at(at(30)), 10.0);
+        assert_eq!(rate.per_minute_at(at(61)), 4.0);
+        rate.record_at(at(100), 2);
+        assert_eq!(rate.per_minute_at(at(100)), 2.0);
+        assert_eq!(rate.seconds.lock().unwrap().len(), 1);
+    }
+}
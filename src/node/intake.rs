@@ -0,0 +1,448 @@
+//! Amount checks on blocks published by peers, and a record of the blocks that were dropped.
+//!
+//! A balance can't go over the genesis supply, since that's all the raw there is. A send has
+//! to send something, and a receive has to add exactly what its source block sent to the
+//! account. Blocks copied by sync don't get pending entries, so the sent amount is worked out
+//! from the source send block and the block before it instead.
+//!
+//! Legacy blocks only have some of the fields of a state block. [legacy_block] fills in the
+//! rest from the previous block, which has to be the frontier of a legacy chain, since the
+//! reference node doesn't allow legacy blocks after a state block.
+//!
+//! Every dropped block is counted by its [RejectReason], and the most recent ones are kept so
+//! that they can be looked at over RPC with `dropped_blocks`.
+use crate::blocks::{Block, BlockHash, BlockHolder, BlockType, Link, Previous, StateBlock};
+use crate::node::state::DynState;
+use crate::rpc::calls::{DroppedBlock, DroppedBlocksResponse};
+use crate::{Public, Raw};
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::{Arc, Mutex};
+
+/// How many of the latest dropped blocks to keep.
+const RECENT_DROPPED: usize = 100;
+
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum RejectReason {
+    #[error("Invalid signature")]
+    InvalidSignature,
+
+    #[error("Difficulty {difficulty:016x} is below the threshold of {threshold:016x}")]
+    InsufficientWork { difficulty: u64, threshold: u64 },
+
+    #[error("Send of zero raw")]
+    ZeroSend,
+
+    #[error("Balance would be over the maximum supply of {} raw", Raw::max())]
+    OverMaxSupply,
+
+    #[error("Source block {0:?} is unknown")]
+    UnknownSource(BlockHash),
+
+    #[error("Source block {0:?} isn't a send to this account")]
+    SourceNotSend(BlockHash),
+
+    #[error("Received {actual} raw, but the source sent {expected} raw")]
+    ReceiveAmountMismatch { expected: Raw, actual: Raw },
+
+    #[error("Previous block {0:?} is unknown")]
+    UnknownPrevious(BlockHash),
+
+    #[error("Another block already follows the previous block, or the account is already open")]
+    Fork,
+
+    #[error("Legacy block after a state block")]
+    LegacyAfterState,
+
+    #[error("Old block")]
+    Old,
+}
+
+impl RejectReason {
+    /// A name for the reason without the details, for counting.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            RejectReason::InvalidSignature => "invalid_signature",
+            RejectReason::InsufficientWork { .. } => "insufficient_work",
+            RejectReason::ZeroSend => "zero_send",
+            RejectReason::OverMaxSupply => "over_max_supply",
+            RejectReason::UnknownSource(_) => "unknown_source",
+            RejectReason::SourceNotSend(_) => "source_not_send",
+            RejectReason::ReceiveAmountMismatch { .. } => "receive_amount_mismatch",
+            RejectReason::UnknownPrevious(_) => "unknown_previous",
+            RejectReason::Fork => "fork",
+            RejectReason::LegacyAfterState => "legacy_after_state",
+            RejectReason::Old => "old",
+        }
+    }
+}
+
+/// Check the amount moved by `block`, which has a link type set, against the `previous` block
+/// of the account. The outer error is for problems reading the state.
+pub async fn check_amounts(
+    state: &DynState,
+    block: &StateBlock,
+    previous: &StateBlock,
+) -> anyhow::Result<Result<(), RejectReason>> {
+    match &block.link {
+        Link::DestinationAccount(_) if block.balance >= previous.balance => {
+            Ok(Err(RejectReason::ZeroSend))
+        }
+        Link::Source(source) => {
+            let expected = match sent_amount(state, source, &block.account).await? {
+                Ok(expected) => expected,
+                Err(reason) => return Ok(Err(reason)),
+            };
+            if previous.balance.checked_add(&expected).is_none() {
+                return Ok(Err(RejectReason::OverMaxSupply));
+            }
+            let actual = block
+                .balance
+                .checked_sub(&previous.balance)
+                .unwrap_or_else(Raw::zero);
+            if actual != expected {
+                return Ok(Err(RejectReason::ReceiveAmountMismatch {
+                    expected,
+                    actual,
+                }));
+            }
+            Ok(Ok(()))
+        }
+        _ => Ok(Ok(())),
+    }
+}
+
+/// Turn a legacy block into a [Block] with the account, representative and balance it leaves
+/// behind, without checking its signature or work. The outer error is for problems reading the
+/// state.
+pub async fn legacy_block(
+    state: &DynState,
+    holder: &BlockHolder,
+) -> anyhow::Result<Result<Block, RejectReason>> {
+    let previous = match holder {
+        BlockHolder::Send(block) => Some(&block.previous),
+        BlockHolder::Receive(block) => Some(&block.previous),
+        BlockHolder::Change(block) => Some(&block.previous),
+        BlockHolder::Open(_) => None,
+        BlockHolder::State(_) => return Err(anyhow::anyhow!("State block given as legacy")),
+    };
+    let previous = match previous {
+        Some(previous) => match legacy_frontier(state, previous).await? {
+            Ok(previous) => Some(previous),
+            Err(reason) => return Ok(Err(reason)),
+        },
+        None => None,
+    };
+
+    Ok(Ok(match (holder, previous) {
+        (BlockHolder::Send(block), Some(previous)) => {
+            if &block.balance >= previous.balance() {
+                return Ok(Err(RejectReason::ZeroSend));
+            }
+            Block::from_send_block(block, previous.account(), previous.representative())
+        }
+        (BlockHolder::Receive(block), Some(previous)) => {
+            let amount = match sent_amount(state, &block.source, previous.account()).await? {
+                Ok(amount) => amount,
+                Err(reason) => return Ok(Err(reason)),
+            };
+            let balance = match previous.balance().checked_add(&amount) {
+                Some(balance) => balance,
+                None => return Ok(Err(RejectReason::OverMaxSupply)),
+            };
+            Block::from_receive_block(
+                block,
+                previous.account(),
+                previous.representative(),
+                &balance,
+            )
+        }
+        (BlockHolder::Change(block), Some(previous)) => {
+            Block::from_change_block(block, previous.account(), previous.balance())
+        }
+        (BlockHolder::Open(block), _) => {
+            if state
+                .get_latest_block_hash_for_account(&block.account)
+                .await?
+                .is_some()
+            {
+                return Ok(Err(RejectReason::Fork));
+            }
+            let amount = match sent_amount(state, &block.source, &block.account).await? {
+                Ok(amount) => amount,
+                Err(reason) => return Ok(Err(reason)),
+            };
+            Block::from_open_block(block, &Previous::Open, &amount)
+        }
+        _ => unreachable!("Only open blocks are without a previous block"),
+    }))
+}
+
+/// The `previous` block, if it's the frontier of its account and not a state block.
+async fn legacy_frontier(
+    state: &DynState,
+    previous: &BlockHash,
+) -> anyhow::Result<Result<Block, RejectReason>> {
+    let block = match state.get_block_by_hash(previous).await? {
+        Some(block) => block,
+        None => return Ok(Err(RejectReason::UnknownPrevious(previous.to_owned()))),
+    };
+    let frontier = state
+        .get_latest_block_hash_for_account(block.account())
+        .await?;
+    if frontier.as_ref() != Some(previous) {
+        return Ok(Err(RejectReason::Fork));
+    }
+    if block.block_type() == &BlockType::State {
+        return Ok(Err(RejectReason::LegacyAfterState));
+    }
+    Ok(Ok(block))
+}
+
+/// How much the `source` block sent to `account`.
+async fn sent_amount(
+    state: &DynState,
+    source: &BlockHash,
+    account: &Public,
+) -> anyhow::Result<Result<Raw, RejectReason>> {
+    let not_send = || Ok(Err(RejectReason::SourceNotSend(source.to_owned())));
+    let block = match state.get_block_by_hash(source).await? {
+        Some(block) => block,
+        None => return Ok(Err(RejectReason::UnknownSource(source.to_owned()))),
+    };
+    // Stored state blocks might not have their link type set, so compare the bytes.
+    if block.link().as_bytes() != account.as_bytes() {
+        return not_send();
+    }
+    let previous = match block.previous() {
+        Previous::Block(previous) => previous,
+        Previous::Open => return not_send(),
+    };
+    let previous = match state.get_block_by_hash(previous).await? {
+        Some(previous) => previous,
+        None => return Ok(Err(RejectReason::UnknownSource(source.to_owned()))),
+    };
+    match previous.balance().checked_sub(block.balance()) {
+        Some(amount) if amount > 0 => Ok(Ok(amount)),
+        _ => not_send(),
+    }
+}
+
+/// Counts of dropped blocks by reason, shared between peers.
+#[derive(Debug, Clone, Default)]
+pub struct DroppedBlocks {
+    inner: Arc<Mutex<DroppedInner>>,
+}
+
+#[derive(Debug, Default)]
+struct DroppedInner {
+    counts: BTreeMap<&'static str, usize>,
+    recent: VecDeque<DroppedBlock>,
+}
+
+impl DroppedBlocks {
+    pub fn record(&self, hash: &BlockHash, reason: &RejectReason) {
+        let mut inner = self.inner.lock().unwrap();
+        *inner.counts.entry(reason.kind()).or_insert(0) += 1;
+        if inner.recent.len() == RECENT_DROPPED {
+            inner.recent.pop_front();
+        }
+        inner.recent.push_back(DroppedBlock {
+            hash: hash.to_owned(),
+            kind: reason.kind().to_owned(),
+            reason: reason.to_string(),
+        });
+    }
+
+    pub fn snapshot(&self) -> DroppedBlocksResponse {
+        let inner = self.inner.lock().unwrap();
+        DroppedBlocksResponse {
+            counts: inner
+                .counts
+                .iter()
+                .map(|(kind, count)| (kind.to_string(), *count))
+                .collect(),
+            recent: inner.recent.iter().rev().cloned().collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blocks::Block;
+    use crate::node::state::{MemoryState, State};
+    use crate::node::test_util::account;
+    use crate::Network;
+
+    fn block(account: &Public, previous: Previous, balance: u128, link: Link) -> StateBlock {
+        StateBlock::new(
+            account.to_owned(),
+            previous,
+            account.to_owned(),
+            Raw::from(balance),
+            link,
+        )
+    }
+
+    #[tokio::test]
+    async fn amounts() {
+        let state = MemoryState::new(Network::Test);
+        let (sender, receiver) = (account(0), account(1));
+
+        let open = block(&sender, Previous::Open, 100, Link::Nothing);
+        let send = block(
+            &sender,
+            Previous::Block(open.hash.to_owned()),
+            60,
+            Link::DestinationAccount(receiver.to_owned()),
+        );
+        let receiver_open = block(&receiver, Previous::Open, 5, Link::Nothing);
+        for b in &[&open, &send, &receiver_open] {
+            state.add_block(&Block::from_state_block(b)).await.unwrap();
+        }
+
+        let receive = |balance, source: &BlockHash| {
+            block(
+                &receiver,
+                Previous::Block(receiver_open.hash.to_owned()),
+                balance,
+                Link::Source(source.to_owned()),
+            )
+        };
+        let check = |b: StateBlock| {
+            let state = &state;
+            let previous = receiver_open.to_owned();
+            async move { check_amounts(state, &b, &previous).await.unwrap() }
+        };
+
+        assert_eq!(check(receive(45, &send.hash)).await, Ok(()));
+        assert_eq!(
+            check(receive(50, &send.hash)).await,
+            Err(RejectReason::ReceiveAmountMismatch {
+                expected: Raw::from(40),
+                actual: Raw::from(45),
+            })
+        );
+        assert_eq!(
+            check(receive(45, &open.hash)).await,
+            Err(RejectReason::SourceNotSend(open.hash.to_owned()))
+        );
+        assert_eq!(
+            check(receive(45, &BlockHash::zero())).await,
+            Err(RejectReason::UnknownSource(BlockHash::zero()))
+        );
+
+        let zero_send = block(
+            &receiver,
+            Previous::Block(receiver_open.hash.to_owned()),
+            5,
+            Link::DestinationAccount(sender.to_owned()),
+        );
+        assert_eq!(check(zero_send).await, Err(RejectReason::ZeroSend));
+    }
+
+    #[tokio::test]
+    async fn over_max_supply() {
+        let state = MemoryState::new(Network::Test);
+        let (sender, receiver) = (account(0), account(1));
+        let open = block(&sender, Previous::Open, 10, Link::Nothing);
+        let send = block(
+            &sender,
+            Previous::Block(open.hash.to_owned()),
+            0,
+            Link::DestinationAccount(receiver.to_owned()),
+        );
+        for b in &[&open, &send] {
+            state.add_block(&Block::from_state_block(b)).await.unwrap();
+        }
+
+        let previous = block(&receiver, Previous::Open, u128::MAX - 5, Link::Nothing);
+        let receive = block(
+            &receiver,
+            Previous::Block(previous.hash.to_owned()),
+            u128::MAX,
+            Link::Source(send.hash.to_owned()),
+        );
+        assert_eq!(
+            check_amounts(&state, &receive, &previous).await.unwrap(),
+            Err(RejectReason::OverMaxSupply)
+        );
+    }
+
+    #[tokio::test]
+    async fn legacy() {
+        use crate::blocks::{ChangeBlock, OpenBlock, ReceiveBlock};
+
+        let state = MemoryState::new(Network::Test);
+        let (sender, receiver) = (account(0), account(1));
+        let open = block(&sender, Previous::Open, 100, Link::Nothing);
+        let send = block(
+            &sender,
+            Previous::Block(open.hash.to_owned()),
+            60,
+            Link::DestinationAccount(receiver.to_owned()),
+        );
+        let second_send = block(
+            &sender,
+            Previous::Block(send.hash.to_owned()),
+            50,
+            Link::DestinationAccount(receiver.to_owned()),
+        );
+        for b in &[&open, &send, &second_send] {
+            state.add_block(&Block::from_state_block(b)).await.unwrap();
+        }
+        async fn resolve(state: &MemoryState, holder: BlockHolder) -> Result<Block, RejectReason> {
+            legacy_block(state, &holder).await.unwrap()
+        }
+
+        let receiver_open = OpenBlock::new(send.hash.to_owned(), sender.to_owned(), receiver);
+        let resolved = resolve(&state, BlockHolder::Open(receiver_open.clone()))
+            .await
+            .unwrap();
+        assert_eq!(resolved.balance(), &Raw::from(40));
+        assert_eq!(resolved.hash().unwrap(), &receiver_open.hash());
+        state.add_block(&resolved).await.unwrap();
+
+        let receive = ReceiveBlock::new(receiver_open.hash(), second_send.hash.to_owned());
+        let resolved = resolve(&state, BlockHolder::Receive(receive))
+            .await
+            .unwrap();
+        assert_eq!(resolved.balance(), &Raw::from(50));
+        assert_eq!(resolved.representative(), &sender);
+
+        assert_eq!(
+            resolve(&state, BlockHolder::Open(receiver_open)).await,
+            Err(RejectReason::Fork)
+        );
+        let change = |previous: &BlockHash| {
+            BlockHolder::Change(ChangeBlock::new(previous.to_owned(), sender.to_owned()))
+        };
+        assert_eq!(
+            resolve(&state, change(&second_send.hash)).await,
+            Err(RejectReason::LegacyAfterState)
+        );
+        assert_eq!(
+            resolve(&state, change(&send.hash)).await,
+            Err(RejectReason::Fork)
+        );
+        assert_eq!(
+            resolve(&state, change(&BlockHash::zero())).await,
+            Err(RejectReason::UnknownPrevious(BlockHash::zero()))
+        );
+    }
+
+    #[test]
+    fn records() {
+        let dropped = DroppedBlocks::default();
+        for _ in 0..RECENT_DROPPED {
+            dropped.record(&BlockHash::zero(), &RejectReason::ZeroSend);
+        }
+        dropped.record(&BlockHash::zero(), &RejectReason::InvalidSignature);
+
+        let snapshot = dropped.snapshot();
+        assert_eq!(snapshot.counts.get("zero_send"), Some(&RECENT_DROPPED));
+        assert_eq!(snapshot.counts.get("invalid_signature"), Some(&1));
+        assert_eq!(snapshot.recent.len(), RECENT_DROPPED);
+        assert_eq!(snapshot.recent[0].kind, "invalid_signature");
+    }
+}
@@ -0,0 +1,246 @@
+//! An append-only journal of what the node did to its ledger and its peers, for working out what
+//! led up to a crash or a ledger anomaly without having run with debug logs.
+//!
+//! Entries are lines of JSON in their own file instead of the database, so the journal can still
+//! be read when the database can't be opened. Once a file has [Journal::capacity] entries it's
+//! moved to `<path>.1`, replacing the one before, so the latest entries are always in those two
+//! files and the journal never grows past twice the capacity.
+use crate::blocks::BlockHash;
+use crate::{Address, Network};
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter};
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tracing::warn;
+
+/// How many entries to write before rotating.
+pub const DEFAULT_CAPACITY: usize = 10_000;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum JournalEvent {
+    /// A block was added to the ledger.
+    Inserted { hash: BlockHash, account: Address },
+
+    /// A block was removed from the ledger, making its previous block the frontier again.
+    RolledBack { hash: BlockHash, account: Address },
+
+    /// The blocks of an account were confirmed up to `height`.
+    Cemented { account: Address, height: u64 },
+
+    /// A peer was disconnected for misbehaving, e.g. too many framing errors.
+    PeerDisconnected { address: SocketAddr, reason: String },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JournalEntry {
+    pub at: DateTime<Utc>,
+
+    #[serde(flatten)]
+    pub event: JournalEvent,
+}
+
+impl Display for JournalEntry {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} ", self.at.format("%Y-%m-%d %H:%M:%S%.3f"))?;
+        match &self.event {
+            JournalEvent::Inserted { hash, account } => {
+                write!(f, "inserted {} for {}", hash, account)
+            }
+            JournalEvent::RolledBack { hash, account } => {
+                write!(f, "rolled back {} for {}", hash, account)
+            }
+            JournalEvent::Cemented { account, height } => {
+                write!(f, "cemented {} up to height {}", account, height)
+            }
+            JournalEvent::PeerDisconnected { address, reason } => {
+                write!(f, "disconnected {}: {}", address, reason)
+            }
+        }
+    }
+}
+
+/// Shared by the node and its peers. Writes go straight to the file, so entries made just
+/// before a crash aren't lost in a buffer.
+#[derive(Debug, Clone, Default)]
+pub struct Journal {
+    inner: Option<Arc<Mutex<Inner>>>,
+}
+
+#[derive(Debug)]
+struct Inner {
+    path: PathBuf,
+    capacity: usize,
+    file: File,
+
+    /// Entries in the current file.
+    entries: usize,
+}
+
+impl Journal {
+    /// Where the node keeps its journal, next to its database.
+    pub fn default_path(network: Network) -> PathBuf {
+        format!("{:?}-journal.jsonl", network)
+            .to_ascii_lowercase()
+            .into()
+    }
+
+    /// Append to the journal at `path`, creating it if it doesn't exist.
+    pub fn open(path: &Path, capacity: usize) -> anyhow::Result<Self> {
+        let entries = match std::fs::read_to_string(path) {
+            Ok(data) => data.lines().count(),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => 0,
+            Err(err) => return Err(err).with_context(|| format!("Reading the journal {:?}", path)),
+        };
+        Ok(Self {
+            inner: Some(Arc::new(Mutex::new(Inner {
+                path: path.to_owned(),
+                capacity,
+                file: Self::append(path)?,
+                entries,
+            }))),
+        })
+    }
+
+    /// A journal that doesn't keep anything, e.g. for tests.
+    pub fn disabled() -> Self {
+        Self { inner: None }
+    }
+
+    pub fn capacity(&self) -> Option<usize> {
+        self.inner
+            .as_ref()
+            .map(|inner| inner.lock().unwrap().capacity)
+    }
+
+    /// Record an event. A journal that can't be written to is logged rather than failing
+    /// whatever is being recorded.
+    pub fn record(&self, event: JournalEvent) {
+        let inner = match &self.inner {
+            Some(inner) => inner,
+            None => return,
+        };
+        let entry = JournalEntry {
+            at: Utc::now(),
+            event,
+        };
+        if let Err(err) = inner.lock().unwrap().write(&entry) {
+            warn!("Could not write to the journal: {:#}", err);
+        }
+    }
+
+    /// The last `count` entries of the journal at `path`, oldest first.
+    pub fn tail(path: &Path, count: usize) -> anyhow::Result<Vec<JournalEntry>> {
+        let mut lines = vec![];
+        for path in &[rotated_path(path), path.to_owned()] {
+            match std::fs::read_to_string(path) {
+                Ok(data) => lines.extend(data.lines().map(|line| line.to_owned())),
+                Err(err) if err.kind() == std::io::ErrorKind::NotFound => {}
+                Err(err) => {
+                    return Err(err).with_context(|| format!("Reading the journal {:?}", path))
+                }
+            }
+        }
+        let skip = lines.len().saturating_sub(count);
+        lines
+            .iter()
+            .skip(skip)
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| {
+                serde_json::from_str(line).with_context(|| format!("Journal entry: {}", line))
+            })
+            .collect()
+    }
+
+    fn append(path: &Path) -> anyhow::Result<File> {
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .with_context(|| format!("Opening the journal {:?}", path))
+    }
+}
+
+impl Inner {
+    fn write(&mut self, entry: &JournalEntry) -> anyhow::Result<()> {
+        if self.entries >= self.capacity {
+            std::fs::rename(&self.path, rotated_path(&self.path))
+                .with_context(|| format!("Rotating the journal {:?}", &self.path))?;
+            self.file = Journal::append(&self.path)?;
+            self.entries = 0;
+        }
+        let mut line = serde_json::to_string(entry)?;
+        line.push('\n');
+        self.file.write_all(line.as_bytes())?;
+        self.entries += 1;
+        Ok(())
+    }
+}
+
+fn rotated_path(path: &Path) -> PathBuf {
+    let mut rotated = path.as_os_str().to_owned();
+    rotated.push(".1");
+    rotated.into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Public;
+    use std::str::FromStr;
+
+    fn inserted(n: u8) -> JournalEvent {
+        JournalEvent::Inserted {
+            hash: BlockHash::from_str(&format!("{:02X}", n).repeat(32)).unwrap(),
+            account: Public::from_str(&"AB".repeat(32)).unwrap().to_address(),
+        }
+    }
+
+    #[test]
+    fn rotates_and_tails() {
+        let path = PathBuf::from("rotates_and_tails.jsonl");
+        for path in &[path.to_owned(), rotated_path(&path)] {
+            if path.exists() {
+                std::fs::remove_file(path).unwrap();
+            }
+        }
+
+        let journal = Journal::open(&path, 3).unwrap();
+        for n in 0..5 {
+            journal.record(inserted(n));
+        }
+        // Reopening carries on counting the entries already in the file.
+        let journal = Journal::open(&path, 3).unwrap();
+        journal.record(JournalEvent::PeerDisconnected {
+            address: "[::1]:7075".parse().unwrap(),
+            reason: "Too many framing errors".into(),
+        });
+        journal.record(inserted(6));
+
+        // The first three entries were replaced when the journal rotated the second time.
+        let entries = Journal::tail(&path, 100).unwrap();
+        let events: Vec<_> = entries.into_iter().map(|e| e.event).collect();
+        assert_eq!(events.len(), 4);
+        assert_eq!(events[0], inserted(3));
+        assert!(matches!(events[2], JournalEvent::PeerDisconnected { .. }));
+
+        let last = Journal::tail(&path, 2).unwrap();
+        assert_eq!(last[1].event, inserted(6));
+        assert!(last[1].to_string().contains("inserted 0606"));
+
+        std::fs::remove_file(rotated_path(&path)).unwrap();
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn disabled() {
+        let journal = Journal::disabled();
+        journal.record(inserted(0));
+        assert_eq!(journal.capacity(), None);
+    }
+}
@@ -0,0 +1,520 @@
+//! Answers to `block_info` and `account_info` from the node's ledger, so the node can stand in
+//! for the RPC of a reference node for light wallets.
+//!
+//! Block heights are counted by walking back to the open block, which fails for an account whose
+//! older blocks have been evicted from memory or pruned. Accounts are answered from their
+//! [crate::node::state::AccountInfo], only walking back as far as the cemented frontier.
+//!
+//! `pending` and `accounts_pending` are answered from the pending entries in the ledger. A send
+//! is added to them as soon as it's in the ledger, so `include_only_confirmed` leaves out the
+//! ones above the cemented height of the sender. `include_active` is ignored, since there are no
+//! elections to look at.
+use crate::blocks::{Block, BlockHash, BlockType, Previous, Subtype};
+use crate::node::pending::Pending;
+use crate::node::state::{ArcState, DynState};
+use crate::rpc::calls::{
+    AccountInfoRequest, AccountInfoResponse, AccountsPendingRequest, AccountsPendingResponse,
+    BlockEntry, BlockInfoResponse, JsonBlock, PendingRequest, PendingResponse,
+};
+use crate::{Address, Public, Raw, RpcErrorKind};
+use anyhow::anyhow;
+use chrono::{TimeZone, Utc};
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+pub async fn block_info(state: &ArcState, hash: &BlockHash) -> anyhow::Result<BlockInfoResponse> {
+    let block = state
+        .get_block_by_hash(hash)
+        .await?
+        .ok_or(RpcErrorKind::BlockNotFound)?;
+    let previous_balance = match block.previous() {
+        Previous::Block(previous) => state
+            .get_block_by_hash(previous)
+            .await?
+            .ok_or_else(|| anyhow!("The block before {:?} is missing", hash))?
+            .balance()
+            .to_owned(),
+        Previous::Open => Raw::zero(),
+    };
+    let (amount, subtype) = amount_and_subtype(&block, &previous_balance);
+
+    let chain = chain(&**state, hash).await?;
+    let height = chain.len() as u64;
+    let cemented = state.cemented_height(block.account()).await?.unwrap_or(0);
+    let timestamp = state
+        .sideband(hash)
+        .await?
+        .map(|sideband| sideband.timestamp)
+        .unwrap_or(0);
+    Ok(BlockInfoResponse {
+        block_account: block.account().to_address(),
+        amount,
+        balance: block.balance().to_owned(),
+        height,
+        local_timestamp: Utc.timestamp(timestamp as i64, 0),
+        confirmed: height <= cemented,
+        subtype,
+        contents: JsonBlock::from_block(&block)?,
+    })
+}
+
+pub async fn account_info(
+    state: &ArcState,
+    request: &AccountInfoRequest,
+) -> anyhow::Result<AccountInfoResponse> {
+    let account = request.account.to_public();
+    let info = state
+        .account_info(&account)
+        .await?
+        .ok_or(RpcErrorKind::AccountNotFound)?;
+    let open_block = info
+        .open_block
+        .to_owned()
+        .ok_or_else(|| anyhow!("The open block of {:?} isn't known", request.account))?;
+    let confirmation_height = state
+        .cemented_height(&account)
+        .await?
+        .unwrap_or(0)
+        .min(info.block_count);
+    let confirmation_height_frontier = match confirmation_height {
+        0 => BlockHash::zero(),
+        height => walk_back(&**state, &info.head, info.block_count - height).await?,
+    };
+    let representative_block = match state.rep_history(&account).await?.pop() {
+        Some(change) => change.hash,
+        None => info.head.to_owned(),
+    };
+
+    let pending = if request.pending {
+        let mut sum = Raw::zero();
+        for pending in state.pending(&account).await? {
+            sum = sum
+                .checked_add(&pending.amount)
+                .ok_or_else(|| anyhow!("Pending of {:?} overflowed", request.account))?;
+        }
+        Some(sum)
+    } else {
+        None
+    };
+
+    Ok(AccountInfoResponse {
+        frontier: info.head,
+        open_block,
+        representative_block,
+        balance: info.balance,
+        modified_timestamp: Utc.timestamp(info.modified as i64, 0),
+        block_count: info.block_count,
+        confirmation_height,
+        confirmation_height_frontier,
+        // Epochs aren't tracked.
+        account_version: 0,
+        representative: if request.representative {
+            Some(info.representative.to_address())
+        } else {
+            None
+        },
+        // Nor are representative weights.
+        weight: None,
+        pending,
+    })
+}
+
+pub async fn pending(
+    state: &ArcState,
+    request: &PendingRequest,
+) -> anyhow::Result<PendingResponse> {
+    let filter = ReceivableFilter {
+        count: request.count,
+        threshold: request.threshold.to_owned(),
+        sorting: request.sorting,
+        only_confirmed: request.include_only_confirmed,
+    };
+    let entries = receivable(&**state, &request.account.to_public(), &filter).await?;
+    Ok(if request.source {
+        PendingResponse::Source {
+            blocks: with_sources(entries)?,
+        }
+    } else if request.threshold.is_some() {
+        PendingResponse::Threshold {
+            blocks: entries
+                .into_iter()
+                .map(|(pending, _)| (pending.source, pending.amount))
+                .collect(),
+        }
+    } else {
+        PendingResponse::OnlyBlockHash {
+            blocks: entries
+                .into_iter()
+                .map(|(pending, _)| pending.source)
+                .collect(),
+        }
+    })
+}
+
+/// Like [pending] for each account, with the `count` applying to each one.
+pub async fn accounts_pending(
+    state: &ArcState,
+    request: &AccountsPendingRequest,
+) -> anyhow::Result<AccountsPendingResponse> {
+    let filter = ReceivableFilter {
+        count: request.count,
+        threshold: request.threshold.to_owned(),
+        sorting: request.sorting,
+        only_confirmed: request.include_only_confirmed,
+    };
+    let mut all = HashMap::new();
+    for account in &request.accounts {
+        let entries = receivable(&**state, &account.to_public(), &filter).await?;
+        all.insert(account.to_owned(), entries);
+    }
+    Ok(if request.source {
+        AccountsPendingResponse::Source {
+            blocks: all
+                .into_iter()
+                .map(|(account, entries)| Ok((account, with_sources(entries)?)))
+                .collect::<anyhow::Result<_>>()?,
+        }
+    } else if request.threshold.is_some() {
+        AccountsPendingResponse::Threshold {
+            blocks: all
+                .into_iter()
+                .map(|(account, entries)| {
+                    let blocks = entries
+                        .into_iter()
+                        .map(|(pending, _)| (pending.source, pending.amount))
+                        .collect();
+                    (account, blocks)
+                })
+                .collect(),
+        }
+    } else {
+        AccountsPendingResponse::OnlyBlockHash {
+            blocks: all
+                .into_iter()
+                .map(|(account, entries)| {
+                    let blocks = entries
+                        .into_iter()
+                        .map(|(pending, _)| pending.source)
+                        .collect();
+                    (account, blocks)
+                })
+                .collect(),
+        }
+    })
+}
+
+struct ReceivableFilter {
+    count: u64,
+    threshold: Option<Raw>,
+    sorting: bool,
+    only_confirmed: bool,
+}
+
+/// The pending entries of `account` passing `filter`, with the account that sent each one if
+/// its send block is still stored. Entries are ordered by their send hash, or largest first
+/// when sorting, before taking `count` of them.
+async fn receivable(
+    state: &DynState,
+    account: &Public,
+    filter: &ReceivableFilter,
+) -> anyhow::Result<Vec<(Pending, Option<Public>)>> {
+    let mut entries = vec![];
+    for pending in state.pending(account).await? {
+        if let Some(threshold) = &filter.threshold {
+            if &pending.amount < threshold {
+                continue;
+            }
+        }
+        let sender = state.account_for_block_hash(&pending.source).await?;
+        if filter.only_confirmed && !is_cemented(state, &pending.source, sender.as_ref()).await? {
+            continue;
+        }
+        entries.push((pending, sender));
+    }
+    entries.sort_by(|(a, _), (b, _)| a.source.as_bytes().cmp(b.source.as_bytes()));
+    if filter.sorting {
+        entries
+            .sort_by(|(a, _), (b, _)| b.amount.partial_cmp(&a.amount).unwrap_or(Ordering::Equal));
+    }
+    entries.truncate(filter.count as usize);
+    Ok(entries)
+}
+
+/// Whether the send block `hash` of `sender` is at or below its cemented height. Pruned blocks
+/// always are.
+async fn is_cemented(
+    state: &DynState,
+    hash: &BlockHash,
+    sender: Option<&Public>,
+) -> anyhow::Result<bool> {
+    if state.is_pruned(hash).await? {
+        return Ok(true);
+    }
+    let sender = match sender {
+        Some(sender) => sender,
+        None => return Ok(false),
+    };
+    let height = state.sideband(hash).await?.and_then(|s| s.height);
+    let cemented = state.cemented_height(sender).await?;
+    Ok(matches!((height, cemented), (Some(h), Some(c)) if h <= c))
+}
+
+fn with_sources(
+    entries: Vec<(Pending, Option<Public>)>,
+) -> anyhow::Result<HashMap<BlockHash, BlockEntry>> {
+    entries
+        .into_iter()
+        .map(|(pending, sender)| {
+            let sender = sender.ok_or_else(|| {
+                anyhow!(
+                    "The sender of {:?} isn't known since its send block has been pruned",
+                    pending.source
+                )
+            })?;
+            let entry = BlockEntry {
+                amount: pending.amount,
+                source: Address::from(&sender),
+            };
+            Ok((pending.source, entry))
+        })
+        .collect()
+}
+
+/// The amount a block moved and its subtype, which is only shown for state blocks.
+fn amount_and_subtype(block: &Block, previous_balance: &Raw) -> (Raw, Option<Subtype>) {
+    let balance = block.balance();
+    let (amount, subtype) = if balance < previous_balance {
+        (previous_balance.checked_sub(balance), Subtype::Send)
+    } else if balance > previous_balance {
+        let subtype = match block.previous() {
+            Previous::Open => Subtype::Open,
+            Previous::Block(_) => Subtype::Receive,
+        };
+        (balance.checked_sub(previous_balance), subtype)
+    } else {
+        (Some(Raw::zero()), Subtype::Change)
+    };
+    let subtype = match block.block_type() {
+        BlockType::State => Some(subtype),
+        _ => None,
+    };
+    (amount.unwrap_or_else(Raw::zero), subtype)
+}
+
+/// The hash `steps` blocks before `hash` in its account's chain.
+async fn walk_back(state: &DynState, hash: &BlockHash, steps: u64) -> anyhow::Result<BlockHash> {
+    let mut hash = hash.to_owned();
+    for _ in 0..steps {
+        let block = state
+            .get_block_by_hash(&hash)
+            .await?
+            .ok_or_else(|| anyhow!("Block {:?} is missing", hash))?;
+        hash = match block.previous() {
+            Previous::Block(previous) => previous.to_owned(),
+            Previous::Open => return Err(anyhow!("Block {:?} is an open block", hash)),
+        };
+    }
+    Ok(hash)
+}
+
+/// The hashes from `hash` back to the open block of its account.
+async fn chain(state: &DynState, hash: &BlockHash) -> anyhow::Result<Vec<BlockHash>> {
+    let mut hashes = vec![];
+    let mut next = hash.to_owned();
+    loop {
+        let block = state.get_block_by_hash(&next).await?.ok_or_else(|| {
+            anyhow!(
+                "Block {:?} is missing, so the height of {:?} isn't known",
+                next,
+                hash
+            )
+        })?;
+        hashes.push(next);
+        next = match block.previous() {
+            Previous::Block(previous) => previous.to_owned(),
+            Previous::Open => return Ok(hashes),
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blocks::{Link, StateBlock};
+    use crate::node::state::{MemoryState, State};
+    use crate::{Network, Public, Seed, Work};
+    use std::str::FromStr;
+    use std::sync::Arc;
+
+    fn state_block(account: &Public, previous: Previous, balance: u128, link: Link) -> Block {
+        let mut block = StateBlock::new(
+            account.to_owned(),
+            previous,
+            account.to_owned(),
+            Raw::from(balance),
+            link,
+        );
+        block.signature = Some(Seed::zero().derive(0).sign(block.hash.as_bytes()).unwrap());
+        block.work = Some(Work::zero());
+        Block::from_state_block(&block)
+    }
+
+    #[tokio::test]
+    async fn answers_from_the_ledger() {
+        let account = Seed::zero().derive(0).to_public().unwrap();
+        let other = Public::from_str(&"AB".repeat(32)).unwrap();
+        let open = state_block(
+            &account,
+            Previous::Open,
+            10,
+            Link::Source(BlockHash::zero()),
+        );
+        let open_hash = open.hash().unwrap().to_owned();
+        let send = state_block(
+            &account,
+            Previous::Block(open_hash.to_owned()),
+            4,
+            Link::DestinationAccount(other.to_owned()),
+        );
+        let send_hash = send.hash().unwrap().to_owned();
+
+        let memory = MemoryState::new(Network::Test);
+        memory.add_block(&open).await.unwrap();
+        memory.add_block(&send).await.unwrap();
+        let state: ArcState = Arc::new(memory);
+
+        let info = block_info(&state, &send_hash).await.unwrap();
+        assert_eq!(info.block_account, account.to_address());
+        assert_eq!(info.amount, Raw::from(6));
+        assert_eq!(info.balance, Raw::from(4));
+        assert_eq!(info.height, 2);
+        assert_eq!(info.subtype, Some(Subtype::Send));
+        assert!(!info.confirmed);
+        assert!(matches!(info.contents, JsonBlock::State { .. }));
+
+        let open_info = block_info(&state, &open_hash).await.unwrap();
+        assert_eq!(open_info.height, 1);
+        assert_eq!(open_info.subtype, Some(Subtype::Open));
+
+        let err = block_info(&state, &BlockHash::zero()).await.unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<RpcErrorKind>(),
+            Some(&RpcErrorKind::BlockNotFound)
+        );
+
+        let info = account_info(&state, &AccountInfoRequest::new(account.to_address()))
+            .await
+            .unwrap();
+        assert_eq!(info.frontier, send_hash);
+        assert_eq!(info.open_block, open_hash);
+        assert_eq!(info.block_count, 2);
+        assert_eq!(info.balance, Raw::from(4));
+        assert_eq!(info.confirmation_height, 0);
+        assert_eq!(info.representative, Some(account.to_address()));
+        assert_eq!(info.confirmation_height_frontier, BlockHash::zero());
+
+        state.set_cemented_height(&account, 1).await.unwrap();
+        let info = account_info(&state, &AccountInfoRequest::new(account.to_address()))
+            .await
+            .unwrap();
+        assert_eq!(info.confirmation_height, 1);
+        assert_eq!(info.confirmation_height_frontier, open_hash);
+
+        let err = account_info(&state, &AccountInfoRequest::new(other.to_address()))
+            .await
+            .unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<RpcErrorKind>(),
+            Some(&RpcErrorKind::AccountNotFound)
+        );
+    }
+
+    #[tokio::test]
+    async fn answers_pending() {
+        let account = Seed::zero().derive(0).to_public().unwrap();
+        let other = Public::from_str(&"AB".repeat(32)).unwrap();
+        let open = state_block(
+            &account,
+            Previous::Open,
+            10,
+            Link::Source(BlockHash::zero()),
+        );
+        let big = state_block(
+            &account,
+            Previous::Block(open.hash().unwrap().to_owned()),
+            4,
+            Link::DestinationAccount(other.to_owned()),
+        );
+        let small = state_block(
+            &account,
+            Previous::Block(big.hash().unwrap().to_owned()),
+            3,
+            Link::DestinationAccount(other.to_owned()),
+        );
+        let (big_hash, small_hash) = (big.hash().unwrap(), small.hash().unwrap());
+
+        let state: ArcState = Arc::new(MemoryState::new(Network::Test));
+        for block in &[&open, &big, &small] {
+            state.add_block(block).await.unwrap();
+            crate::node::pending::block_added(&*state, block)
+                .await
+                .unwrap();
+        }
+
+        let mut request = PendingRequest::new(other.to_address(), 10);
+        match pending(&state, &request).await.unwrap() {
+            PendingResponse::OnlyBlockHash { blocks } => assert_eq!(blocks.len(), 2),
+            response => panic!("{:?}", response),
+        }
+
+        request.threshold = Some(Raw::from(2));
+        let mut expected = HashMap::new();
+        expected.insert(big_hash.to_owned(), Raw::from(6));
+        assert_eq!(
+            pending(&state, &request).await.unwrap(),
+            PendingResponse::Threshold { blocks: expected }
+        );
+
+        let request = PendingRequest {
+            count: 1,
+            sorting: true,
+            source: true,
+            ..PendingRequest::new(other.to_address(), 1)
+        };
+        let mut expected = HashMap::new();
+        expected.insert(
+            big_hash.to_owned(),
+            BlockEntry {
+                amount: Raw::from(6),
+                source: account.to_address(),
+            },
+        );
+        assert_eq!(
+            pending(&state, &request).await.unwrap(),
+            PendingResponse::Source { blocks: expected }
+        );
+
+        // Only the first send is cemented.
+        state.set_cemented_height(&account, 2).await.unwrap();
+        let request = AccountsPendingRequest::new(vec![other.to_address()], 10).only_confirmed();
+        let mut expected = HashMap::new();
+        expected.insert(other.to_address(), vec![big_hash.to_owned()]);
+        assert_eq!(
+            accounts_pending(&state, &request).await.unwrap(),
+            AccountsPendingResponse::OnlyBlockHash { blocks: expected }
+        );
+
+        let receive = state_block(&other, Previous::Open, 6, Link::Source(big_hash.to_owned()));
+        state.add_block(&receive).await.unwrap();
+        crate::node::pending::block_added(&*state, &receive)
+            .await
+            .unwrap();
+        let request = AccountsPendingRequest::new(vec![other.to_address()], 10);
+        let mut expected = HashMap::new();
+        expected.insert(other.to_address(), vec![small_hash.to_owned()]);
+        assert_eq!(
+            accounts_pending(&state, &request).await.unwrap(),
+            AccountsPendingResponse::OnlyBlockHash { blocks: expected }
+        );
+    }
+}
@@ -0,0 +1,245 @@
+//! Statistics over the whole ledger, for `feeless node stats ledger`.
+//!
+//! Accounts are fetched in batches of [ACCOUNT_BATCH] and visited one at a time, walking back
+//! from the frontier to the open block, so only the counters, one batch of accounts and the
+//! block being looked at are held in memory. The state is locked for one account at a time,
+//! which lets the node keep processing blocks in between.
+use crate::blocks::{BlockHash, Previous};
+use crate::node::state::ArcState;
+use crate::rpc::calls::{
+    BalanceBucket, DormantBucket, DormantStats, LedgerStatsResponse, SupplyStats,
+};
+use crate::{Public, Raw};
+use anyhow::Context;
+use std::collections::BTreeMap;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tracing::debug;
+
+/// Where each balance bucket starts: 0, 0.000001, 0.001, 1, 1,000 and 1,000,000 Nano.
+const BALANCE_BUCKETS: [u128; 6] = [
+    0,
+    1_000_000_000_000_000_000_000_000,
+    1_000_000_000_000_000_000_000_000_000,
+    1_000_000_000_000_000_000_000_000_000_000,
+    1_000_000_000_000_000_000_000_000_000_000_000,
+    1_000_000_000_000_000_000_000_000_000_000_000_000,
+];
+
+/// Accounts count as dormant after a month, a year and five years without a new block.
+const DORMANT_DAYS: [u64; 3] = [30, 365, 5 * 365];
+
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+/// How many accounts to fetch from the state at a time.
+const ACCOUNT_BATCH: usize = 1000;
+
+/// Sums of raw that might add up to more than a u128 if the ledger is invalid.
+#[derive(Default)]
+struct Sum {
+    raw: u128,
+    overflowed: bool,
+}
+
+impl Sum {
+    fn add(&mut self, raw: &Raw) {
+        match self.raw.checked_add(raw.to_u128()) {
+            Some(sum) => self.raw = sum,
+            None => {
+                self.raw = u128::MAX;
+                self.overflowed = true;
+            }
+        }
+    }
+}
+
+pub async fn ledger_stats(state: &ArcState) -> anyhow::Result<LedgerStatsResponse> {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .context("Ledger stats")?
+        .as_secs();
+    ledger_stats_at(state, now).await
+}
+
+/// Collect the stats as if it was `now`, in seconds since the Unix epoch.
+async fn ledger_stats_at(state: &ArcState, now: u64) -> anyhow::Result<LedgerStatsResponse> {
+    let burn = Public::zero();
+    let mut accounts = 0;
+
+    let mut blocks = 0;
+    let mut block_types = BTreeMap::new();
+    let mut incomplete_chains = 0;
+    let mut balances = Sum::default();
+    let mut burned = Raw::zero();
+    let mut buckets: Vec<(usize, Sum)> = BALANCE_BUCKETS
+        .iter()
+        .map(|_| (0, Sum::default()))
+        .collect();
+    let mut dormant = DormantStats {
+        buckets: DORMANT_DAYS
+            .iter()
+            .map(|&days| DormantBucket { days, accounts: 0 })
+            .collect(),
+        unknown: 0,
+    };
+
+    let mut after: Option<Public> = None;
+    loop {
+        let batch = state.accounts_after(after.as_ref(), ACCOUNT_BATCH).await?;
+        if batch.is_empty() {
+            break;
+        }
+        for account in &batch {
+            accounts += 1;
+            let frontier = match state.get_latest_block_hash_for_account(account).await? {
+                Some(frontier) => frontier,
+                None => continue,
+            };
+
+            let mut next: Option<BlockHash> = Some(frontier.to_owned());
+            let mut balance = None;
+            while let Some(hash) = next {
+                let block = match state.get_block_by_hash(&hash).await? {
+                    Some(block) => block,
+                    None => {
+                        debug!("Chain of {:?} is missing {:?}", account.to_address(), hash);
+                        incomplete_chains += 1;
+                        break;
+                    }
+                };
+                if hash == frontier {
+                    balance = Some(block.balance().to_owned());
+                }
+                blocks += 1;
+                *block_types
+                    .entry(block.block_type().to_owned())
+                    .or_insert(0) += 1;
+                next = match block.previous() {
+                    Previous::Block(previous) => Some(previous.to_owned()),
+                    Previous::Open => None,
+                };
+            }
+
+            if let Some(balance) = balance {
+                balances.add(&balance);
+                let bucket = BALANCE_BUCKETS
+                    .iter()
+                    .rposition(|&min| balance >= min)
+                    .unwrap_or(0);
+                buckets[bucket].0 += 1;
+                buckets[bucket].1.add(&balance);
+                if account == &burn {
+                    burned = balance;
+                }
+            }
+
+            // The frontier was added last, so its sideband says when the account last changed.
+            match state.sideband(&frontier).await?.map(|s| s.timestamp) {
+                Some(modified) => {
+                    let days = now.saturating_sub(modified) / SECONDS_PER_DAY;
+                    for bucket in &mut dormant.buckets {
+                        if days >= bucket.days {
+                            bucket.accounts += 1;
+                        }
+                    }
+                }
+                None => dormant.unknown += 1,
+            }
+        }
+        after = batch.last().cloned();
+    }
+
+    let genesis = Raw::max();
+    let total = Raw::from(balances.raw);
+    let supply = SupplyStats {
+        valid: !balances.overflowed && total <= genesis,
+        circulating: total.checked_sub(&burned).unwrap_or_else(Raw::zero),
+        unaccounted: genesis.checked_sub(&total).unwrap_or_else(Raw::zero),
+        balances: total,
+        burned,
+        genesis,
+    };
+
+    Ok(LedgerStatsResponse {
+        accounts,
+        blocks,
+        block_types,
+        supply,
+        balances: BALANCE_BUCKETS
+            .iter()
+            .zip(buckets)
+            .map(|(&min, (accounts, total))| BalanceBucket {
+                min: Raw::from(min),
+                accounts,
+                total: Raw::from(total.raw),
+            })
+            .collect(),
+        dormant,
+        incomplete_chains,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blocks::{Block, BlockType, Link, StateBlock};
+    use crate::node::state::{MemoryState, SledDiskState};
+    use crate::{Network, Seed};
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn counts() {
+        counts_in(Arc::new(MemoryState::new(Network::Test))).await;
+    }
+
+    #[tokio::test]
+    async fn counts_on_disk() {
+        counts_in(Arc::new(SledDiskState::temporary(Network::Test))).await;
+    }
+
+    async fn counts_in(state: ArcState) {
+        let account = |index| Seed::zero().derive(index).to_public().unwrap();
+        let add = |account: Public, previous, balance: u128| {
+            let block = StateBlock::new(
+                account.to_owned(),
+                previous,
+                account,
+                Raw::from(balance),
+                Link::Nothing,
+            );
+            let state = state.clone();
+            async move {
+                let block = Block::from_state_block(&block);
+                state.add_block(&block).await.unwrap();
+                block.hash().unwrap().to_owned()
+            }
+        };
+
+        let one_nano = BALANCE_BUCKETS[3];
+        let open = add(account(0), Previous::Open, 5 * one_nano).await;
+        add(account(0), Previous::Block(open), 2 * one_nano).await;
+        add(account(1), Previous::Open, 10).await;
+        add(Public::zero(), Previous::Open, one_nano).await;
+
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        let stats = ledger_stats_at(&state, now + 40 * SECONDS_PER_DAY)
+            .await
+            .unwrap();
+        assert_eq!(stats.accounts, 3);
+        assert_eq!(stats.blocks, 4);
+        assert_eq!(stats.block_types.get(&BlockType::State), Some(&4));
+        assert_eq!(stats.incomplete_chains, 0);
+
+        assert!(stats.supply.valid);
+        assert_eq!(stats.supply.balances, Raw::from(3 * one_nano + 10));
+        assert_eq!(stats.supply.burned, Raw::from(one_nano));
+        assert_eq!(stats.supply.circulating, Raw::from(2 * one_nano + 10));
+
+        let accounts: Vec<usize> = stats.balances.iter().map(|b| b.accounts).collect();
+        assert_eq!(accounts, vec![1, 0, 0, 2, 0, 0]);
+        assert_eq!(stats.dormant.buckets[0].accounts, 3);
+        assert_eq!(stats.dormant.buckets[1].accounts, 0);
+    }
+}
@@ -0,0 +1,154 @@
+use crate::blocks::BlockHash;
+use crate::bytes::Bytes;
+use crate::node::header::{Extensions, Header};
+use crate::node::wire::{decode, Field, Wire};
+use crate::Public;
+use anyhow::anyhow;
+use bytes::BytesMut;
+use std::convert::TryFrom;
+
+/// Ask for the blocks of an account chain, starting at its frontier and going back to `end`.
+///
+/// The blocks come back newest first, or oldest first when [BulkPull::ascending] is set, each
+/// after a byte of its [crate::blocks::BlockType], and the stream ends with
+/// [crate::blocks::BlockType::NotABlock].
+#[derive(Debug)]
+pub struct BulkPull {
+    /// The account. A block hash also works, to start from that block instead of the frontier.
+    pub start: Public,
+
+    /// The block to stop before, or zero to go all the way back to the open block.
+    pub end: BlockHash,
+
+    /// The most blocks to send. Sent in an extended payload, flagged in the extensions.
+    pub count: Option<u32>,
+
+    /// Go from the start towards the frontier. Only flagged in the extensions.
+    pub ascending: bool,
+}
+
+impl BulkPull {
+    pub const LEN: usize = Public::LEN + BlockHash::LEN;
+
+    /// The extended payload with the count is a zero byte, the count, then three zero bytes.
+    pub const EXTENDED_LEN: usize = 8;
+
+    /// Every block of `account` after `end`, or the whole chain when `end` is `None`.
+    pub fn account(account: Public, end: Option<BlockHash>) -> Self {
+        Self {
+            start: account,
+            end: end.unwrap_or_else(BlockHash::zero),
+            count: None,
+            ascending: false,
+        }
+    }
+
+    /// The extensions of the header to send this with.
+    pub fn extensions(&self) -> Extensions {
+        let mut ext = Extensions::new();
+        if self.count.is_some() {
+            ext.set_count_present();
+        }
+        if self.ascending {
+            ext.set_ascending();
+        }
+        ext
+    }
+}
+
+impl Wire for BulkPull {
+    fn serialize_into(&self, buf: &mut BytesMut) {
+        buf.extend_from_slice(self.start.as_bytes());
+        buf.extend_from_slice(self.end.as_bytes());
+        if let Some(count) = self.count {
+            buf.extend_from_slice(&[0]);
+            buf.extend_from_slice(&count.to_le_bytes());
+            buf.extend_from_slice(&[0, 0, 0]);
+        }
+    }
+
+    fn deserialize(header: Option<&Header>, data: &[u8]) -> anyhow::Result<Self>
+    where
+        Self: Sized,
+    {
+        let mut bytes = Bytes::new(data);
+        let start = Public::try_from(bytes.slice(Public::LEN)?)?;
+        let end = BlockHash::try_from(bytes.slice(BlockHash::LEN)?)?;
+        let count = match header.map(|h| h.ext().is_count_present()) {
+            Some(true) => {
+                if bytes.u8()? != 0 {
+                    return Err(anyhow!("Bulk pull count should start with a zero byte"));
+                }
+                let mut s32 = [0u8; 4];
+                s32.copy_from_slice(bytes.slice(4)?);
+                Some(u32::from_le_bytes(s32))
+            }
+            _ => None,
+        };
+        let ascending = header.is_some_and(|h| h.ext().is_ascending());
+        Ok(Self {
+            start,
+            end,
+            count,
+            ascending,
+        })
+    }
+
+    fn len(header: Option<&Header>) -> anyhow::Result<usize>
+    where
+        Self: Sized,
+    {
+        match header.map(|h| h.ext().is_count_present()) {
+            Some(true) => Ok(Self::LEN + Self::EXTENDED_LEN),
+            _ => Ok(Self::LEN),
+        }
+    }
+
+    fn fields(header: Option<&Header>) -> anyhow::Result<Vec<Field>> {
+        let mut fields = vec![
+            Field::new("start", Public::LEN),
+            Field::new("end", BlockHash::LEN),
+        ];
+        if header.is_some_and(|h| h.ext().is_count_present()) {
+            fields.push(Field::new("zero", 1));
+            fields.push(Field::new("count", 4).decoded(decode::u32_le));
+            fields.push(Field::new("reserved", 3));
+        }
+        Ok(fields)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::network::Network;
+    use crate::node::header::MessageType;
+
+    #[test]
+    fn count() {
+        let pull = BulkPull {
+            start: Public::zero(),
+            end: BlockHash::zero(),
+            count: Some(3),
+            ascending: true,
+        };
+        let data = pull.serialize();
+        assert_eq!(data.len(), BulkPull::LEN + BulkPull::EXTENDED_LEN);
+
+        let header = Header::new(Network::Live, MessageType::BulkPull, pull.extensions());
+        assert_eq!(BulkPull::len(Some(&header)).unwrap(), data.len());
+        let pulled = BulkPull::deserialize(Some(&header), &data).unwrap();
+        assert_eq!(pulled.count, Some(3));
+        assert!(pulled.ascending);
+
+        let header = Header::new(Network::Live, MessageType::BulkPull, Extensions::new());
+        let pull = BulkPull::account(Public::zero(), None);
+        assert_eq!(BulkPull::len(Some(&header)).unwrap(), BulkPull::LEN);
+        assert_eq!(
+            BulkPull::deserialize(Some(&header), &pull.serialize())
+                .unwrap()
+                .count,
+            None
+        );
+    }
+}
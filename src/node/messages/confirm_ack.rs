@@ -1,12 +1,14 @@
-use crate::blocks::{Block, BlockHash, BlockType};
+use crate::blocks::{BlockHash, BlockHolder, BlockType};
 use crate::bytes::Bytes;
 use crate::encoding::blake2b;
-use crate::node::header::Header;
+use crate::node::header::{Extensions, Header};
 use crate::node::timestamp::Timestamp;
-use crate::node::wire::Wire;
+use crate::node::wire::{decode, Field, Wire};
 use crate::{Public, Signature};
 use anyhow::Context;
+use bytes::BytesMut;
 use std::convert::TryFrom;
+use std::time::Duration;
 
 /// This is a vote on the network by a representative for one or more block hashes.
 #[derive(Debug)]
@@ -16,14 +18,18 @@ pub struct ConfirmAck {
 
     pub timestamp: Timestamp,
     pub confirm: Confirm,
+
+    /// How long the vote is valid for, from [Timestamp::vote_duration].
+    pub duration: Duration,
 }
 
 #[derive(Debug)]
+#[allow(clippy::large_enum_variant)]
 pub enum Confirm {
     VoteByHash(Vec<BlockHash>),
 
     // TODO: This looks like it isn't used on the live network.
-    Block(Block),
+    Block(BlockHolder),
 }
 
 impl ConfirmAck {
@@ -38,11 +44,27 @@ impl ConfirmAck {
         Self {
             account,
             signature,
+            duration: timestamp.vote_duration(),
             timestamp,
             confirm,
         }
     }
 
+    /// The header extensions that need to be sent along with this vote.
+    pub fn extensions(&self) -> Extensions {
+        let mut ext = Extensions::new();
+        match &self.confirm {
+            Confirm::VoteByHash(hashes) => {
+                ext.set_block_type(BlockType::NotABlock)
+                    .set_item_count(hashes.len());
+            }
+            Confirm::Block(block) => {
+                ext.set_block_type(block.block_type());
+            }
+        }
+        ext
+    }
+
     pub fn verify_signature(&self) -> anyhow::Result<()> {
         self.account
             .verify(&self.inner_hash(), &self.signature)
@@ -53,25 +75,35 @@ impl ConfirmAck {
     pub fn inner_hash(&self) -> Vec<u8> {
         let mut v = Vec::new();
 
-        // TODO: Only add this prefix if there's data. See nano::vote::hash()
-        v.extend_from_slice("vote ".as_bytes());
-
-        if let Confirm::VoteByHash(hashes) = &self.confirm {
-            for hash in hashes {
-                v.extend_from_slice(hash.as_bytes())
+        // Only votes by hash have the prefix. See nano::vote::hash()
+        match &self.confirm {
+            Confirm::VoteByHash(hashes) => {
+                v.extend_from_slice("vote ".as_bytes());
+                for hash in hashes {
+                    v.extend_from_slice(hash.as_bytes())
+                }
             }
-            v.extend_from_slice(&self.timestamp.to_bytes())
-        } else {
-            todo!("handle block hash");
+            Confirm::Block(block) => v.extend_from_slice(block.hash().as_bytes()),
         }
+        v.extend_from_slice(&self.timestamp.to_bytes());
 
         blake2b(BlockHash::LEN, &v).to_vec()
     }
 }
 
 impl Wire for ConfirmAck {
-    fn serialize(&self) -> Vec<u8> {
-        unimplemented!()
+    fn serialize_into(&self, buf: &mut BytesMut) {
+        buf.extend_from_slice(self.account.as_bytes());
+        buf.extend_from_slice(self.signature.as_bytes());
+        buf.extend_from_slice(&self.timestamp.to_bytes());
+        match &self.confirm {
+            Confirm::VoteByHash(hashes) => {
+                for hash in hashes {
+                    buf.extend_from_slice(hash.as_bytes());
+                }
+            }
+            Confirm::Block(block) => block.serialize_into(buf),
+        }
     }
 
     fn deserialize(header: Option<&Header>, data: &[u8]) -> anyhow::Result<Self>
@@ -94,9 +126,10 @@ impl Wire for ConfirmAck {
             }
             Confirm::VoteByHash(block_hashes)
         } else {
-            // let block = Block;
-            dbg!("block!!!!!!!", header.ext().block_type().unwrap());
-            todo!()
+            Confirm::Block(BlockHolder::deserialize(
+                Some(header),
+                data.slice(data.remain())?,
+            )?)
         };
 
         Ok(Self::new(account, signature, timestamp, confirm))
@@ -109,10 +142,28 @@ impl Wire for ConfirmAck {
         if header.ext().block_type()? == BlockType::NotABlock {
             Ok(Self::VOTE_COMMON_LEN + header.ext().item_count() * BlockHash::LEN)
         } else {
-            todo!("got a block in confirm ack {:#?}", header);
-            // Ok(Self::VOTE_COMMON_LEN + FullBlock::LEN)
+            Ok(Self::VOTE_COMMON_LEN + BlockHolder::len(Some(header))?)
         }
     }
+
+    fn fields(header: Option<&Header>) -> anyhow::Result<Vec<Field>> {
+        debug_assert!(header.is_some());
+        let header = header.unwrap();
+
+        let mut fields = vec![
+            Field::new("account", Public::LEN),
+            Field::new("signature", Signature::LEN),
+            Field::new("timestamp", Timestamp::LEN).decoded(decode::u64_le),
+        ];
+        if header.ext().block_type()? == BlockType::NotABlock {
+            for i in 0..header.ext().item_count() {
+                fields.push(Field::new(format!("hash[{}]", i), BlockHash::LEN));
+            }
+        } else {
+            fields.extend(Field::within("block", BlockHolder::fields(Some(header))?));
+        }
+        Ok(fields)
+    }
 }
 
 #[cfg(test)]
@@ -141,4 +192,61 @@ mod tests {
         );
         assert!(confirm_ack.verify_signature().is_ok());
     }
+
+    #[test]
+    fn by_hash_round_trip() {
+        use crate::node::header::MessageType;
+        use crate::Network;
+
+        let hashes: Vec<BlockHash> = (0..3u8)
+            .map(|i| BlockHash::try_from([i; BlockHash::LEN].as_ref()).unwrap())
+            .collect();
+        let ack = ConfirmAck::new(
+            Public::zero(),
+            Signature::zero(),
+            Timestamp::from_u64(1234),
+            Confirm::VoteByHash(hashes.clone()),
+        );
+        let header = Header::new(Network::Live, MessageType::ConfirmAck, ack.extensions());
+        let data = ack.serialize();
+        assert_eq!(data.len(), ConfirmAck::len(Some(&header)).unwrap());
+
+        let decoded = ConfirmAck::deserialize(Some(&header), &data).unwrap();
+        assert_eq!(decoded.timestamp, ack.timestamp);
+        match decoded.confirm {
+            Confirm::VoteByHash(decoded) => assert_eq!(decoded, hashes),
+            other => panic!("Unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn block_round_trip() {
+        use crate::blocks::ChangeBlock;
+        use crate::node::header::MessageType;
+        use crate::Network;
+        use crate::Work;
+
+        let previous = BlockHash::try_from([1; BlockHash::LEN].as_ref()).unwrap();
+        let mut block = ChangeBlock::new(previous, Public::zero());
+        block.signature = Some(Signature::zero());
+        block.work = Some(Work::zero());
+        let block = BlockHolder::Change(block);
+        let ack = ConfirmAck::new(
+            Public::zero(),
+            Signature::zero(),
+            Timestamp::from_u64(1234),
+            Confirm::Block(block.clone()),
+        );
+        let header = Header::new(Network::Live, MessageType::ConfirmAck, ack.extensions());
+        assert_eq!(header.ext().block_type().unwrap(), BlockType::Change);
+        let data = ack.serialize();
+        assert_eq!(data.len(), ConfirmAck::len(Some(&header)).unwrap());
+
+        let decoded = ConfirmAck::deserialize(Some(&header), &data).unwrap();
+        assert_eq!(decoded.inner_hash(), ack.inner_hash());
+        match decoded.confirm {
+            Confirm::Block(decoded) => assert_eq!(decoded, block),
+            other => panic!("Unexpected {:?}", other),
+        }
+    }
 }
@@ -1,9 +1,10 @@
-use crate::blocks::{BlockHash, BlockHolder, BlockType};
+use crate::blocks::{BlockHash, BlockHolder, BlockType, Root};
 use crate::bytes::Bytes;
 use crate::encoding::expect_len;
-use crate::node::header::Header;
-use crate::node::wire::Wire;
+use crate::node::header::{Extensions, Header};
+use crate::node::wire::{Field, Wire};
 use anyhow::Context;
+use bytes::BytesMut;
 use std::convert::TryFrom;
 use tracing::info;
 
@@ -17,6 +18,7 @@ use tracing::info;
 //    if: _root.header.block_type != enum_blocktype::not_a_block
 //    type: block_selector(_root.header.block_type_int)
 #[derive(Debug)]
+#[allow(clippy::large_enum_variant)]
 pub enum ConfirmReq {
     ConfirmReqByHash(Vec<RootHashPair>),
     BlockSelector(BlockHolder),
@@ -24,11 +26,43 @@ pub enum ConfirmReq {
 
 impl ConfirmReq {
     pub const CONFIRM_REQ_BY_HASH_LEN: usize = BlockHash::LEN * 2;
+
+    /// The most root/hash pairs that fit in a single request, limited by the item count bits
+    /// in the header.
+    pub const MAX_PAIRS: usize = 15;
+
+    pub fn by_hash(pairs: Vec<RootHashPair>) -> Self {
+        debug_assert!(pairs.len() <= Self::MAX_PAIRS);
+        Self::ConfirmReqByHash(pairs)
+    }
+
+    /// The header extensions that need to be sent along with this request.
+    pub fn extensions(&self) -> Extensions {
+        let mut ext = Extensions::new();
+        match self {
+            Self::ConfirmReqByHash(pairs) => {
+                ext.set_block_type(BlockType::NotABlock)
+                    .set_item_count(pairs.len());
+            }
+            Self::BlockSelector(block) => {
+                ext.set_block_type(block.block_type());
+            }
+        }
+        ext
+    }
 }
 
 impl Wire for ConfirmReq {
-    fn serialize(&self) -> Vec<u8> {
-        unimplemented!()
+    fn serialize_into(&self, buf: &mut BytesMut) {
+        match self {
+            Self::ConfirmReqByHash(pairs) => {
+                for pair in pairs {
+                    buf.extend_from_slice(pair.hash.as_bytes());
+                    buf.extend_from_slice(pair.root.as_bytes());
+                }
+            }
+            Self::BlockSelector(block) => block.serialize_into(buf),
+        }
     }
 
     fn deserialize(header: Option<&Header>, data: &[u8]) -> anyhow::Result<Self>
@@ -41,7 +75,7 @@ impl Wire for ConfirmReq {
         let mut bytes = Bytes::new(data);
 
         if header.ext().block_type()? == BlockType::NotABlock {
-            let count = header.ext().item_count() as usize;
+            let count = header.ext().item_count();
             let expected_capacity = RootHashPair::LEN * count;
             expect_len(
                 data.len(),
@@ -65,16 +99,6 @@ impl Wire for ConfirmReq {
                 Some(header),
                 data,
             )?))
-
-            // todo!("handle state block")
-            //
-            // let value = bytes
-            //     .slice(FullBlock::LEN)
-            //     .context("Confirm req slice state block")?;
-            // Ok(Self::BlockSelector(
-            //     FullBlock::deserialize(Some(header), value)
-            //         .context("Confirm req block selector state block deserialize")?,
-            // ))
         }
     }
 
@@ -88,16 +112,34 @@ impl Wire for ConfirmReq {
             BlockHolder::len(Some(header))
         }
     }
+
+    fn fields(header: Option<&Header>) -> anyhow::Result<Vec<Field>> {
+        debug_assert!(header.is_some());
+        let header = header.unwrap();
+
+        if header.ext().block_type()? == BlockType::NotABlock {
+            Ok((0..header.ext().item_count())
+                .flat_map(|i| {
+                    vec![
+                        Field::new(format!("pair[{}].hash", i), BlockHash::LEN),
+                        Field::new(format!("pair[{}].root", i), Root::LEN),
+                    ]
+                })
+                .collect())
+        } else {
+            Ok(Field::within("block", BlockHolder::fields(Some(header))?))
+        }
+    }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct RootHashPair {
     pub hash: BlockHash,
-    pub root: BlockHash,
+    pub root: Root,
 }
 
 impl RootHashPair {
-    const LEN: usize = BlockHash::LEN * 2;
+    pub const LEN: usize = BlockHash::LEN + Root::LEN;
 }
 
 impl TryFrom<&[u8]> for RootHashPair {
@@ -107,7 +149,57 @@ impl TryFrom<&[u8]> for RootHashPair {
         expect_len(value.len(), Self::LEN, "Root hash pair")?;
         Ok(Self {
             hash: BlockHash::try_from(&value[0..BlockHash::LEN])?,
-            root: BlockHash::try_from(&value[BlockHash::LEN..])?,
+            root: Root::try_from(&value[BlockHash::LEN..])?,
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::node::header::MessageType;
+    use crate::Network;
+
+    #[test]
+    fn by_hash_round_trip() {
+        let pairs: Vec<RootHashPair> = (0..3u8)
+            .map(|i| RootHashPair {
+                hash: BlockHash::try_from([i; BlockHash::LEN].as_ref()).unwrap(),
+                root: Root::try_from([i + 100; Root::LEN].as_ref()).unwrap(),
+            })
+            .collect();
+        let req = ConfirmReq::by_hash(pairs.clone());
+        let header = Header::new(Network::Live, MessageType::ConfirmReq, req.extensions());
+        assert_eq!(header.ext().item_count(), 3);
+        assert_eq!(header.ext().block_type().unwrap(), BlockType::NotABlock);
+
+        let data = req.serialize();
+        assert_eq!(data.len(), ConfirmReq::len(Some(&header)).unwrap());
+        match ConfirmReq::deserialize(Some(&header), &data).unwrap() {
+            ConfirmReq::ConfirmReqByHash(decoded) => assert_eq!(decoded, pairs),
+            other => panic!("Unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn block_round_trip() {
+        use crate::blocks::ChangeBlock;
+        use crate::{Public, Signature, Work};
+
+        let previous = BlockHash::try_from([1; BlockHash::LEN].as_ref()).unwrap();
+        let mut block = ChangeBlock::new(previous, Public::zero());
+        block.signature = Some(Signature::zero());
+        block.work = Some(Work::zero());
+        let block = BlockHolder::Change(block);
+        let req = ConfirmReq::BlockSelector(block.clone());
+        let header = Header::new(Network::Live, MessageType::ConfirmReq, req.extensions());
+        assert_eq!(header.ext().block_type().unwrap(), BlockType::Change);
+
+        let data = req.serialize();
+        assert_eq!(data.len(), ConfirmReq::len(Some(&header)).unwrap());
+        match ConfirmReq::deserialize(Some(&header), &data).unwrap() {
+            ConfirmReq::BlockSelector(decoded) => assert_eq!(decoded, block),
+            other => panic!("Unexpected {:?}", other),
+        }
+    }
+}
@@ -1,7 +1,8 @@
 use crate::bytes::Bytes;
 use crate::node::header::Header;
-use crate::node::wire::Wire;
+use crate::node::wire::{decode, Field, Wire};
 use crate::Public;
+use bytes::BytesMut;
 use std::convert::TryFrom;
 
 #[derive(Debug)]
@@ -13,11 +14,19 @@ pub struct FrontierReq {
 
 impl FrontierReq {
     pub const LEN: usize = 40;
+
+    /// Request frontiers of accounts from `start` onwards, in ascending order. An `age` and
+    /// `count` of `u32::MAX` means there are no limits.
+    pub fn new(start: Public, age: u32, count: u32) -> Self {
+        Self { start, age, count }
+    }
 }
 
 impl Wire for FrontierReq {
-    fn serialize(&self) -> Vec<u8> {
-        vec![]
+    fn serialize_into(&self, buf: &mut BytesMut) {
+        buf.extend_from_slice(self.start.as_bytes());
+        buf.extend_from_slice(&self.age.to_le_bytes());
+        buf.extend_from_slice(&self.count.to_le_bytes());
     }
 
     fn deserialize(_: Option<&Header>, data: &[u8]) -> Result<Self, anyhow::Error>
@@ -37,6 +46,14 @@ impl Wire for FrontierReq {
         Ok(Self { start, age, count })
     }
 
+    fn fields(_: Option<&Header>) -> anyhow::Result<Vec<Field>> {
+        Ok(vec![
+            Field::new("start", Public::LEN),
+            Field::new("age", 4).decoded(decode::u32_le),
+            Field::new("count", 4).decoded(decode::u32_le),
+        ])
+    }
+
     fn len(_: Option<&Header>) -> Result<usize, anyhow::Error> {
         Ok(Self::LEN)
     }
@@ -4,21 +4,39 @@ use crate::node::header::Header;
 use crate::node::wire::Wire;
 use crate::Public;
 use anyhow::Context;
+use bytes::BytesMut;
 use std::convert::TryFrom;
 
 #[derive(Debug)]
 pub struct FrontierResp {
-    account: Public,
-    frontier_hash: BlockHash,
+    pub account: Public,
+    pub frontier_hash: BlockHash,
 }
 
 impl FrontierResp {
     pub const LEN: usize = Public::LEN + BlockHash::LEN;
+
+    pub fn new(account: Public, frontier_hash: BlockHash) -> Self {
+        Self {
+            account,
+            frontier_hash,
+        }
+    }
+
+    /// The end of a frontier stream is marked by a response with a zeroed account.
+    pub fn end() -> Self {
+        Self::new(Public::zero(), BlockHash::zero())
+    }
+
+    pub fn is_end(&self) -> bool {
+        self.account == Public::zero()
+    }
 }
 
 impl Wire for FrontierResp {
-    fn serialize(&self) -> Vec<u8> {
-        todo!()
+    fn serialize_into(&self, buf: &mut BytesMut) {
+        buf.extend_from_slice(self.account.as_bytes());
+        buf.extend_from_slice(self.frontier_hash.as_bytes());
     }
 
     fn deserialize(header: Option<&Header>, data: &[u8]) -> Result<Self, anyhow::Error>
@@ -26,7 +44,7 @@ impl Wire for FrontierResp {
         Self: Sized,
     {
         debug_assert!(header.is_none());
-        let context = || format!("Deserialize frontier response");
+        let context = || "Deserialize frontier response".to_string();
         let mut bytes = Bytes::new(data);
 
         let account = bytes.slice(Public::LEN).with_context(context)?;
@@ -1,8 +1,11 @@
+use crate::blocks::BlockHash;
 use crate::bytes::Bytes;
 use crate::node::cookie::Cookie;
 use crate::node::header::Header;
-use crate::node::wire::Wire;
+use crate::node::wire::{Field, Wire};
+use crate::node::ProtocolVersion;
 use crate::{Public, Signature};
+use bytes::BytesMut;
 use std::convert::TryFrom;
 
 #[derive(Debug)]
@@ -12,7 +15,7 @@ pub struct Handshake {
 }
 
 impl Wire for Handshake {
-    fn serialize(&self) -> Vec<u8> {
+    fn serialize_into(&self, _buf: &mut BytesMut) {
         unimplemented!()
     }
 
@@ -37,7 +40,7 @@ impl Wire for Handshake {
         if header.ext().is_response() {
             s.response = Some(HandshakeResponse::deserialize(
                 Some(header),
-                bytes.slice(HandshakeResponse::LEN)?,
+                bytes.slice(HandshakeResponse::len(Some(header))?)?,
             )?);
         }
         Ok(s)
@@ -51,16 +54,35 @@ impl Wire for Handshake {
             size += HandshakeQuery::LEN
         }
         if header.ext().is_response() {
-            size += HandshakeResponse::LEN
+            size += HandshakeResponse::len(Some(header))?
         };
         Ok(size)
     }
+
+    fn fields(header: Option<&Header>) -> anyhow::Result<Vec<Field>> {
+        debug_assert!(header.is_some());
+        let header = header.unwrap();
+        let mut fields = vec![];
+        if header.ext().is_query() {
+            fields.extend(Field::within(
+                "query",
+                HandshakeQuery::fields(Some(header))?,
+            ));
+        }
+        if header.ext().is_response() {
+            fields.extend(Field::within(
+                "response",
+                HandshakeResponse::fields(Some(header))?,
+            ));
+        }
+        Ok(fields)
+    }
 }
 
 #[derive(Debug)]
 pub struct HandshakeQuery(pub Cookie);
 
-impl<'a> HandshakeQuery {
+impl HandshakeQuery {
     const LEN: usize = Cookie::LEN;
 
     pub fn new(cookie: Cookie) -> Self {
@@ -73,8 +95,8 @@ impl<'a> HandshakeQuery {
 }
 
 impl Wire for HandshakeQuery {
-    fn serialize(&self) -> Vec<u8> {
-        self.0.serialize()
+    fn serialize_into(&self, buf: &mut BytesMut) {
+        self.0.serialize_into(buf);
     }
 
     fn deserialize(header: Option<&Header>, data: &[u8]) -> anyhow::Result<Self>
@@ -88,41 +110,152 @@ impl Wire for HandshakeQuery {
     fn len(_: Option<&Header>) -> anyhow::Result<usize> {
         Ok(Self::LEN)
     }
+
+    fn fields(_: Option<&Header>) -> anyhow::Result<Vec<Field>> {
+        Ok(vec![Field::new("cookie", Cookie::LEN)])
+    }
 }
 
 #[derive(Debug)]
 pub struct HandshakeResponse {
     pub public: Public,
     pub signature: Signature,
+
+    /// Only sent with the v2 flag in the header extensions, by peers using V19 or later.
+    pub v2: Option<HandshakeResponseV2>,
+}
+
+#[derive(Debug)]
+pub struct HandshakeResponseV2 {
+    pub salt: Cookie,
+    pub genesis: BlockHash,
 }
 
 impl HandshakeResponse {
     pub const LEN: usize = Public::LEN + Signature::LEN;
+    pub const V2_LEN: usize = Self::LEN + Cookie::LEN + BlockHash::LEN;
 
     pub fn new(public: Public, signature: Signature) -> Self {
-        Self { public, signature }
+        Self {
+            public,
+            signature,
+            v2: None,
+        }
     }
 }
 
 impl Wire for HandshakeResponse {
-    fn serialize(&self) -> Vec<u8> {
-        let mut v = Vec::with_capacity(Self::LEN);
-        v.extend_from_slice(&self.public.as_bytes());
-        v.extend_from_slice(&self.signature.as_bytes());
-        v
+    fn serialize_into(&self, buf: &mut BytesMut) {
+        buf.extend_from_slice(self.public.as_bytes());
+        if let Some(v2) = &self.v2 {
+            buf.extend_from_slice(v2.salt.as_bytes());
+            buf.extend_from_slice(v2.genesis.as_bytes());
+        }
+        buf.extend_from_slice(self.signature.as_bytes());
     }
 
-    fn deserialize(_: Option<&Header>, data: &[u8]) -> anyhow::Result<Self>
+    fn deserialize(header: Option<&Header>, data: &[u8]) -> anyhow::Result<Self>
     where
         Self: Sized,
     {
+        let mut bytes = Bytes::new(data);
+        let public = Public::try_from(bytes.slice(Public::LEN)?)?;
+        let v2 = if is_v2(header) {
+            Some(HandshakeResponseV2 {
+                salt: Cookie::try_from(bytes.slice(Cookie::LEN)?)?,
+                genesis: BlockHash::try_from(bytes.slice(BlockHash::LEN)?)?,
+            })
+        } else {
+            None
+        };
+        let signature = Signature::try_from(bytes.slice(Signature::LEN)?)?;
         Ok(Self {
-            public: Public::try_from(&data[0..Public::LEN])?,
-            signature: Signature::try_from(&data[Public::LEN..])?,
+            public,
+            signature,
+            v2,
         })
     }
 
-    fn len(_: Option<&Header>) -> anyhow::Result<usize> {
-        Ok(Self::LEN)
+    fn len(header: Option<&Header>) -> anyhow::Result<usize> {
+        if is_v2(header) {
+            Ok(Self::V2_LEN)
+        } else {
+            Ok(Self::LEN)
+        }
+    }
+
+    fn fields(header: Option<&Header>) -> anyhow::Result<Vec<Field>> {
+        let mut fields = vec![Field::new("account", Public::LEN)];
+        if is_v2(header) {
+            fields.push(Field::new("salt", Cookie::LEN));
+            fields.push(Field::new("genesis", BlockHash::LEN));
+        }
+        fields.push(Field::new("signature", Signature::LEN));
+        Ok(fields)
+    }
+}
+
+/// Older peers don't know about the v2 flag, so it's only trusted from V19.
+fn is_v2(header: Option<&Header>) -> bool {
+    header.is_some_and(|h| h.ext().is_v2() && h.version() >= ProtocolVersion::V19)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::node::header::{Extensions, MessageType};
+    use crate::Network;
+
+    fn header(ext: &Extensions) -> Header {
+        let mut header = Header::new(Network::Live, MessageType::Handshake, *ext);
+        header.set_version(ProtocolVersion::V19);
+        header
+    }
+
+    #[test]
+    fn response_layout_per_flag() {
+        let v1 = header(Extensions::new().response());
+        assert_eq!(Handshake::len(Some(&v1)).unwrap(), HandshakeResponse::LEN);
+
+        let v2 = header(Extensions::new().response().v2());
+        assert_eq!(
+            Handshake::len(Some(&v2)).unwrap(),
+            HandshakeResponse::V2_LEN
+        );
+
+        let mut data = vec![1u8; Public::LEN];
+        data.extend_from_slice(&[2u8; Cookie::LEN]);
+        data.extend_from_slice(&[3u8; BlockHash::LEN]);
+        data.extend_from_slice(&[4u8; Signature::LEN]);
+        let handshake = Handshake::deserialize(Some(&v2), &data).unwrap();
+        let response = handshake.response.unwrap();
+        let v2 = response.v2.as_ref().unwrap();
+        assert_eq!(v2.salt.as_bytes(), &[2u8; Cookie::LEN]);
+        assert_eq!(v2.genesis.as_bytes(), &[3u8; BlockHash::LEN]);
+        assert_eq!(response.signature.as_bytes(), &[4u8; Signature::LEN]);
+        assert_eq!(response.serialize(), data);
+    }
+
+    #[test]
+    fn response_layout_per_version() {
+        let mut data = vec![1u8; Public::LEN];
+        data.extend_from_slice(&[4u8; Signature::LEN]);
+
+        let mut v18 = header(Extensions::new().response().v2());
+        v18.set_version(ProtocolVersion::V18);
+        assert_eq!(Handshake::len(Some(&v18)).unwrap(), HandshakeResponse::LEN);
+        let response = Handshake::deserialize(Some(&v18), &data)
+            .unwrap()
+            .response
+            .unwrap();
+        assert!(response.v2.is_none());
+        assert_eq!(response.signature.as_bytes(), &[4u8; Signature::LEN]);
+
+        let v19 = header(Extensions::new().response().v2());
+        assert_eq!(
+            Handshake::len(Some(&v19)).unwrap(),
+            HandshakeResponse::V2_LEN
+        );
+        assert!(Handshake::deserialize(Some(&v19), &data).is_err());
     }
 }
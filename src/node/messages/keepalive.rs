@@ -1,18 +1,40 @@
 use crate::bytes::Bytes;
 use crate::node::header::Header;
 use crate::node::peer_info::PeerInfo;
-use crate::node::wire::Wire;
+use crate::node::wire::{decode, Field, Wire};
+use bytes::BytesMut;
+use std::time::Duration;
+
+/// How often a keepalive is sent to each connected peer, the same as the reference node.
+pub const KEEPALIVE_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Peers that haven't been seen for this long are removed from the peer table.
+pub const PEER_CUTOFF: Duration = Duration::from_secs(5 * 60);
 
 #[derive(Debug)]
 pub struct Keepalive(Vec<PeerInfo>);
 
 impl Keepalive {
     pub const PEERS: usize = 8;
+
+    /// Only the first [Keepalive::PEERS] peers are kept.
+    pub fn new(mut peers: Vec<PeerInfo>) -> Self {
+        peers.truncate(Self::PEERS);
+        Self(peers)
+    }
+
+    pub fn peers(&self) -> &[PeerInfo] {
+        &self.0
+    }
 }
 
 impl Wire for Keepalive {
-    fn serialize(&self) -> Vec<u8> {
-        unimplemented!()
+    fn serialize_into(&self, buf: &mut BytesMut) {
+        let start = buf.len();
+        for peer in &self.0 {
+            peer.serialize_into(buf);
+        }
+        buf.resize(start + PeerInfo::LEN * Keepalive::PEERS, 0);
     }
 
     fn deserialize(header: Option<&Header>, data: &[u8]) -> anyhow::Result<Self>
@@ -35,4 +57,41 @@ impl Wire for Keepalive {
     fn len(_: Option<&Header>) -> anyhow::Result<usize> {
         Ok(PeerInfo::LEN * Keepalive::PEERS)
     }
+
+    fn fields(_: Option<&Header>) -> anyhow::Result<Vec<Field>> {
+        Ok((0..Keepalive::PEERS)
+            .flat_map(|i| {
+                vec![
+                    Field::new(format!("peer[{}].ip", i), PeerInfo::ADDR_LEN).decoded(decode::ipv6),
+                    Field::new(format!("peer[{}].port", i), 2).decoded(decode::u16_le),
+                ]
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::SocketAddr;
+    use std::str::FromStr;
+
+    #[test]
+    fn serialize() {
+        let peers = vec![
+            PeerInfo::from(SocketAddr::from_str("1.2.3.4:7075").unwrap()),
+            PeerInfo::from(SocketAddr::from_str("[::1]:1234").unwrap()),
+        ];
+        let keepalive = Keepalive::new(peers);
+        let data = keepalive.serialize();
+        assert_eq!(data.len(), Keepalive::len(None).unwrap());
+
+        let keepalive = Keepalive::deserialize(None, &data).unwrap();
+        let addrs: Vec<String> = keepalive
+            .peers()
+            .iter()
+            .map(|p| p.socket_addr().to_string())
+            .collect();
+        assert_eq!(addrs, vec!["1.2.3.4:7075", "[::1]:1234"]);
+    }
 }
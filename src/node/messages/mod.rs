@@ -1,3 +1,4 @@
+pub mod bulk_pull;
 pub mod confirm_ack;
 pub mod confirm_req;
 pub mod empty;
@@ -1,13 +1,14 @@
 use crate::blocks::BlockHolder;
 use crate::node::header::Header;
-use crate::node::wire::Wire;
+use crate::node::wire::{Field, Wire};
+use bytes::BytesMut;
 
 #[derive(Debug)]
 pub struct Publish(pub(crate) BlockHolder);
 
 impl Wire for Publish {
-    fn serialize(&self) -> Vec<u8> {
-        unimplemented!()
+    fn serialize_into(&self, buf: &mut BytesMut) {
+        self.0.serialize_into(buf)
     }
 
     fn deserialize(header: Option<&Header>, data: &[u8]) -> anyhow::Result<Self>
@@ -20,4 +21,63 @@ impl Wire for Publish {
     fn len(header: Option<&Header>) -> Result<usize, anyhow::Error> {
         BlockHolder::len(header)
     }
+
+    fn fields(header: Option<&Header>) -> anyhow::Result<Vec<Field>> {
+        Ok(Field::within("block", BlockHolder::fields(header)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blocks::{BlockHash, BlockType, ChangeBlock, OpenBlock, ReceiveBlock};
+    use crate::node::header::{Extensions, MessageType};
+    use crate::{Network, Public, Signature, Work};
+    use std::str::FromStr;
+
+    #[test]
+    fn legacy_blocks() {
+        let hash = BlockHash::from_str(&"AB".repeat(32)).unwrap();
+        let public = Public::from_str(&"CD".repeat(32)).unwrap();
+        let mut blocks = vec![
+            BlockHolder::Receive(ReceiveBlock::new(hash.to_owned(), hash.to_owned())),
+            BlockHolder::Open(OpenBlock::new(
+                hash.to_owned(),
+                public.to_owned(),
+                public.to_owned(),
+            )),
+            BlockHolder::Change(ChangeBlock::new(hash, public)),
+        ];
+        for holder in &mut blocks {
+            let (work, signature) = match holder {
+                BlockHolder::Receive(b) => (&mut b.work, &mut b.signature),
+                BlockHolder::Open(b) => (&mut b.work, &mut b.signature),
+                BlockHolder::Change(b) => (&mut b.work, &mut b.signature),
+                _ => unreachable!(),
+            };
+            *work = Some(Work::from_str("0123456789abcdef").unwrap());
+            *signature = Some(Signature::from_str(&"EF".repeat(64)).unwrap());
+        }
+
+        for (holder, block_type) in
+            blocks
+                .iter()
+                .zip(&[BlockType::Receive, BlockType::Open, BlockType::Change])
+        {
+            let mut ext = Extensions::new();
+            ext.set_block_type(block_type.to_owned());
+            let header = Header::new(Network::Live, MessageType::Publish, ext);
+            let data = Publish(holder.to_owned()).serialize();
+            assert_eq!(data.len(), Publish::len(Some(&header)).unwrap());
+            // The work is little-endian at the end.
+            assert_eq!(
+                &data[data.len() - 8..],
+                &[0xef, 0xcd, 0xab, 0x89, 0x67, 0x45, 0x23, 0x01]
+            );
+            assert_eq!(
+                &Publish::deserialize(Some(&header), &data).unwrap().0,
+                holder
+            );
+        }
+    }
 }
@@ -1,41 +1,76 @@
 use crate::blocks::BlockHash;
 use crate::bytes::Bytes;
 use crate::node::header::Header;
-use crate::node::wire::Wire;
+use crate::node::wire::{decode, Field, Wire};
 use crate::{Public, Signature};
-use anyhow::Context;
+use anyhow::{anyhow, Context};
+use bytes::BytesMut;
 use std::convert::TryFrom;
-use tracing::warn;
 
 #[derive(Debug)]
 pub struct TelemetryAck {
-    signature: Signature,
-    node_id: Public,
-    block_count: u64,
-    cemented_count: u64,
-    unchecked_count: u64,
-    account_count: u64,
-    bandwidth_cap: u64,
-    uptime: u64,
-    peer_count: u32,
-    protocol_version: u8,
-    genesis_block: BlockHash,
-    major_version: u8,
-    minor_version: u8,
-    patch_version: u8,
-    prerelease_version: u8,
-    maker: u8,
-    timestamp: [u8; 8],
-    active_difficulty: [u8; 8],
+    pub signature: Signature,
+    pub node_id: Public,
+    pub block_count: u64,
+    pub cemented_count: u64,
+    pub unchecked_count: u64,
+    pub account_count: u64,
+    pub bandwidth_cap: u64,
+    pub uptime: u64,
+    pub peer_count: u32,
+    pub protocol_version: u8,
+    pub genesis_block: BlockHash,
+    pub major_version: u8,
+    pub minor_version: u8,
+    pub patch_version: u8,
+    pub prerelease_version: u8,
+    pub maker: u8,
+    pub timestamp: [u8; 8],
+    pub active_difficulty: [u8; 8],
+
+    /// Fields after [TelemetryAck::LEN] from newer nodes, which are covered by the signature.
+    pub unknown_data: Vec<u8>,
 }
 
 impl TelemetryAck {
     pub const LEN: usize = 202;
+
+    /// Everything after the signature, which is what the node ID signs.
+    pub fn signed_bytes(&self) -> Vec<u8> {
+        let mut buf = BytesMut::with_capacity(Self::LEN - Signature::LEN);
+        buf.extend_from_slice(self.node_id.as_bytes());
+        buf.extend_from_slice(&self.block_count.to_be_bytes());
+        buf.extend_from_slice(&self.cemented_count.to_be_bytes());
+        buf.extend_from_slice(&self.unchecked_count.to_be_bytes());
+        buf.extend_from_slice(&self.account_count.to_be_bytes());
+        buf.extend_from_slice(&self.bandwidth_cap.to_be_bytes());
+        buf.extend_from_slice(&self.uptime.to_be_bytes());
+        buf.extend_from_slice(&self.peer_count.to_be_bytes());
+        buf.extend_from_slice(&[self.protocol_version]);
+        buf.extend_from_slice(self.genesis_block.as_bytes());
+        buf.extend_from_slice(&[
+            self.major_version,
+            self.minor_version,
+            self.patch_version,
+            self.prerelease_version,
+            self.maker,
+        ]);
+        buf.extend_from_slice(&self.timestamp);
+        buf.extend_from_slice(&self.active_difficulty);
+        buf.extend_from_slice(&self.unknown_data);
+        buf.to_vec()
+    }
+
+    /// Check that the telemetry was signed by the node ID in it.
+    pub fn verify(&self) -> crate::Result<()> {
+        self.node_id.verify(&self.signed_bytes(), &self.signature)
+    }
 }
 
 impl Wire for TelemetryAck {
-    fn serialize(&self) -> Vec<u8> {
-        unimplemented!()
+    fn serialize_into(&self, buf: &mut BytesMut) {
+        buf.extend_from_slice(self.signature.as_bytes());
+        buf.extend_from_slice(&self.signed_bytes());
     }
 
     fn deserialize(_header: Option<&Header>, data: &[u8]) -> Result<Self, anyhow::Error>
@@ -65,6 +100,7 @@ impl Wire for TelemetryAck {
             maker: 0,
             timestamp: [0u8; 8],
             active_difficulty: [0u8; 8],
+            unknown_data: vec![],
         };
 
         let mut s32 = [0u8; 4];
@@ -95,16 +131,117 @@ impl Wire for TelemetryAck {
         s.prerelease_version = bytes.u8()?;
         s.maker = bytes.u8()?;
 
-        warn!("TODO: telemetry ack timestamp");
-        warn!("TODO: telemetry ack active_difficulty");
+        s.timestamp.copy_from_slice(bytes.slice(8)?);
+        s.active_difficulty.copy_from_slice(bytes.slice(8)?);
+        s.unknown_data = bytes.slice(bytes.remain())?.to_vec();
 
         Ok(s)
     }
 
-    fn len(_header: Option<&Header>) -> Result<usize, anyhow::Error>
+    fn len(header: Option<&Header>) -> Result<usize, anyhow::Error>
     where
         Self: Sized,
     {
-        Ok(TelemetryAck::LEN)
+        debug_assert!(header.is_some());
+        let header = header.unwrap();
+
+        let size = header.ext().telemetry_size();
+        if size < TelemetryAck::LEN {
+            return Err(anyhow!(
+                "Telemetry ack size {} is smaller than {}",
+                size,
+                TelemetryAck::LEN
+            ));
+        }
+        Ok(size)
+    }
+
+    fn fields(_: Option<&Header>) -> anyhow::Result<Vec<Field>> {
+        let u64_be = |name| Field::new(name, 8).decoded(decode::u64_be);
+        let u8 = |name| Field::new(name, 1).decoded(decode::u8);
+        Ok(vec![
+            Field::new("signature", Signature::LEN),
+            Field::new("node_id", Public::LEN),
+            u64_be("block_count"),
+            u64_be("cemented_count"),
+            u64_be("unchecked_count"),
+            u64_be("account_count"),
+            u64_be("bandwidth_cap"),
+            u64_be("uptime"),
+            Field::new("peer_count", 4).decoded(decode::u32_be),
+            u8("protocol_version"),
+            Field::new("genesis_block", BlockHash::LEN),
+            u8("major_version"),
+            u8("minor_version"),
+            u8("patch_version"),
+            u8("prerelease_version"),
+            u8("maker"),
+            u64_be("timestamp"),
+            u64_be("active_difficulty"),
+        ])
+    }
+}
+
+#[cfg(test)]
+impl TelemetryAck {
+    /// Telemetry with block counts, signed by `private` as its node ID.
+    pub fn signed(
+        private: &crate::Private,
+        genesis_block: BlockHash,
+        block_count: u64,
+        cemented_count: u64,
+    ) -> Self {
+        let mut ack = Self {
+            signature: Signature::zero(),
+            node_id: private.to_public().unwrap(),
+            block_count,
+            cemented_count,
+            unchecked_count: 0,
+            account_count: 0,
+            bandwidth_cap: 0,
+            uptime: 0,
+            peer_count: 0,
+            protocol_version: 18,
+            genesis_block,
+            major_version: 0,
+            minor_version: 0,
+            patch_version: 0,
+            prerelease_version: 0,
+            maker: 0,
+            timestamp: [0u8; 8],
+            active_difficulty: [0u8; 8],
+            unknown_data: vec![],
+        };
+        ack.signature = private.sign(&ack.signed_bytes()).unwrap();
+        ack
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Seed;
+
+    #[test]
+    fn signature() {
+        let private = Seed::zero().derive(0);
+        let mut ack = TelemetryAck::signed(&private, BlockHash::zero(), 500, 400);
+        ack.unknown_data = vec![1, 2, 3];
+        ack.signature = private.sign(&ack.signed_bytes()).unwrap();
+
+        let data = ack.serialize();
+        assert_eq!(data.len(), TelemetryAck::LEN + 3);
+        let decoded = TelemetryAck::deserialize(None, &data).unwrap();
+        assert_eq!(decoded.block_count, 500);
+        assert_eq!(decoded.unknown_data, vec![1, 2, 3]);
+        assert!(decoded.verify().is_ok());
+
+        // Any change to the signed fields breaks the signature.
+        let mut spoofed = TelemetryAck::deserialize(None, &data).unwrap();
+        spoofed.block_count = 5000;
+        assert!(spoofed.verify().is_err());
+        let mut spoofed = TelemetryAck::deserialize(None, &data).unwrap();
+        spoofed.unknown_data.clear();
+        assert!(spoofed.verify().is_err());
     }
 }
@@ -1,13 +1,12 @@
 use crate::node::header::Header;
 use crate::node::wire::Wire;
+use bytes::BytesMut;
 
 #[derive(Debug)]
 pub struct TelemetryReq;
 
 impl Wire for TelemetryReq {
-    fn serialize(&self) -> Vec<u8> {
-        vec![]
-    }
+    fn serialize_into(&self, _buf: &mut BytesMut) {}
 
     fn deserialize(_: Option<&Header>, _data: &[u8]) -> Result<Self, anyhow::Error>
     where
@@ -1,40 +1,240 @@
+mod bootstrap;
+mod bulk_pull_server;
+mod cache;
+mod client;
+mod cold_boot;
 mod command;
+mod confirmation;
 mod cookie;
+mod debug_dump;
+mod dialer;
+mod difficulty_stats;
+mod events;
+mod forks;
 mod header;
+mod health;
+mod intake;
+mod journal;
+mod ledger_info;
+mod ledger_stats;
 mod messages;
+mod own_blocks;
 mod peer;
+mod peer_filter;
 mod peer_info;
-mod state;
+mod pending;
+mod probe;
+mod process;
+mod protocol_version;
+mod pruning;
+pub mod state;
+mod stress;
+mod sync;
+mod telemetry;
+#[cfg(test)]
+mod test_util;
 mod timestamp;
+mod votes;
+mod voting;
 mod wire;
+mod wire_dump;
 
-use crate::rpc::server::RPCServer;
-use crate::Network;
-pub use crate::Version;
+use crate::rpc::calls::PeerTableResponse;
+use crate::rpc::server::{RPCServer, RpcServerConfig};
+use crate::rpc::websocket::WebSocketServer;
+use crate::{Network, Public};
 use anyhow::Context;
+pub use bootstrap::{ChainBootstrap, ChainStats, FrontierBootstrap};
+pub use bulk_pull_server::BulkPullServer;
+use bytes::BytesMut;
+pub use cache::MemoryBudget;
+use cache::PublishCache;
+use chrono::Utc;
+pub use client::NodeClient;
 pub use command::{NodeCommand, NodeCommandReceiver, NodeCommandSender};
+pub use confirmation::{Confirmation, ConfirmationTracker};
+pub use debug_dump::{decode_capture, Decoded};
+pub use dialer::{Dialer, DialerConfig};
+pub use events::FrontierEvents;
+pub use forks::{ForkEvent, ForkWatch};
 pub use header::Header;
+use health::BlockRate;
+pub use health::{HealthConfig, HealthReport};
+use intake::DroppedBlocks;
+pub use journal::Journal;
+use messages::keepalive::{KEEPALIVE_INTERVAL, PEER_CUTOFF};
+use own_blocks::OwnBlocks;
+#[cfg(feature = "test-util")]
+pub use peer::script;
+#[cfg(any(feature = "pcap", feature = "bench"))]
+pub use peer::EarlyMessages;
 pub use peer::{Packet, Peer};
-pub use state::{ArcState, MemoryState, SledDiskState};
+pub use peer_filter::{PeerFilter, PeerFilterConfig};
+pub use pending::check as check_pending;
+pub use protocol_version::ProtocolVersion;
+pub use pruning::{PruneStats, PruningConfig, RetentionPolicy};
+pub use state::{ArcState, MemoryState, RepChange, SledDiskState, State};
 use std::net::SocketAddr;
 use std::str::FromStr;
 use std::sync::Arc;
+use std::time::Duration;
+pub use stress::StressTest;
+pub use sync::sync_from;
+use telemetry::NetworkTelemetry;
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
-use tokio::net::TcpStream;
-use tokio::sync::Mutex;
 use tokio::task::JoinHandle;
-use tracing::{debug, error, info, instrument};
-pub use wire::Wire;
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, error, info, instrument, trace, warn};
+pub use votes::VoteStore;
+use voting::Voter;
+pub use voting::VotingConfig;
+pub(crate) use wire::decode;
+pub use wire::{Field, Wire};
+pub use wire_dump::WireDump;
 
 pub struct Node {
     network: Network,
     state: ArcState,
+
+    /// Our own endpoint, advertised to peers in keepalives.
+    advertise: Option<SocketAddr>,
+
+    bootstrap: Option<Arc<FrontierBootstrap>>,
+
+    /// Shared with every peer to request and count votes for [NodeClient::request_confirmation].
+    confirmations: ConfirmationTracker,
+
+    /// Shared with every peer to skip repeated publishes and hold blocks that arrive early.
+    publishes: PublishCache,
+
+    /// Shared with every peer to count the blocks they drop.
+    dropped: DroppedBlocks,
+
+    /// Blocks published through [NodeClient], shared with every peer to skip them when they're
+    /// flooded back to us.
+    own_blocks: OwnBlocks,
+
+    /// Frontier changes for the WebSocket server to send to subscribers.
+    frontiers: FrontierEvents,
+
+    /// Subnets we may connect to, shared with every peer for the peers they learn about.
+    peer_filter: PeerFilter,
+
+    /// The messages each peer logs hexdumps of.
+    wire_dump: Option<WireDump>,
+
+    /// Thresholds for the `/health` and `/ready` endpoints.
+    health: HealthConfig,
+
+    /// Blocks added to the ledger in the last minute, for [Node::health].
+    block_rate: BlockRate,
+
+    /// Block counts from the telemetry of every peer, to estimate how far we are from synced.
+    telemetry: NetworkTelemetry,
+
+    /// Shared with every peer to vote with, when we're a representative.
+    voter: Option<Voter>,
+
+    /// Shared with every peer to record blocks added and peers disconnected.
+    journal: Journal,
+
+    /// Which actions the RPC server answers.
+    rpc: RpcServerConfig,
+
+    /// Keeps up the outbound connections to peers.
+    dialer: Dialer,
+
+    /// Shared with every peer to limit the bulk pulls served to each address.
+    bulk_pulls: BulkPullServer,
+
+    /// Shared with every peer to alert on forks of the priority accounts.
+    forks: ForkWatch,
 }
 
+/// Refuse to start if our own stored votes contradict each other, since voting again could
+/// make it worse.
+fn check_votes(network: Network) -> anyhow::Result<()> {
+    let path = VoteStore::default_path(network);
+    if !path.exists() {
+        return Ok(());
+    }
+    let check = VoteStore::open(&path)?.check()?;
+    info!(
+        "Checked {} stored votes, {} final",
+        check.votes, check.final_votes
+    );
+    if !check.conflicts.is_empty() {
+        for conflict in &check.conflicts {
+            error!("{}", conflict);
+        }
+        return Err(anyhow::anyhow!(
+            "The vote store {:?} has {} conflicts",
+            path,
+            check.conflicts.len()
+        ));
+    }
+    Ok(())
+}
+
+/// How often to log the progress of the frontier bootstrap.
+const BOOTSTRAP_LOG_INTERVAL: Duration = Duration::from_secs(10);
+
 impl Node {
-    pub async fn start(override_peers: Option<Vec<String>>) -> anyhow::Result<()> {
-        let mut node = Node::new(Network::Live);
+    #[allow(clippy::too_many_arguments)]
+    pub async fn start(
+        network: Network,
+        override_peers: Option<Vec<String>>,
+        advertise: Option<String>,
+        bootstrap_peers: usize,
+        paranoid: bool,
+        budget: MemoryBudget,
+        peer_filter: PeerFilter,
+        wire_dump: Option<WireDump>,
+        health: HealthConfig,
+        voting: Option<VotingConfig>,
+        rpc: RpcServerConfig,
+        dialer: DialerConfig,
+        priority: Vec<Public>,
+        forks: ForkWatch,
+        pruning: Option<PruningConfig>,
+    ) -> anyhow::Result<()> {
+        info!("Memory budget: {}", budget);
+        info!("Network: {}", network);
+        let mut node = Node::with_budget(network, budget);
+        node.peer_filter = peer_filter;
+        node.wire_dump = wire_dump;
+        node.health = health;
+        node.rpc = rpc;
+        node.dialer = Dialer::new(dialer);
+        if !priority.is_empty() {
+            info!("Priority accounts: {}", priority.len());
+        }
+        forks.set_accounts(priority.iter().cloned());
+        node.forks = forks;
+        if let Some(pruning) = pruning {
+            node.start_pruning(pruning.interval(), pruning.policy(&priority));
+        }
+        node.confirmations.set_priority_accounts(priority);
+        tokio::spawn(node.block_rate.clone().count(node.frontiers.clone()));
+        cold_boot::verify_ledger(&node.state, paranoid).await?;
+        check_votes(network)?;
+        node.journal = Journal::open(&Journal::default_path(network), journal::DEFAULT_CAPACITY)?;
+        if let Some(voting) = voting {
+            let path = voting
+                .vote_store
+                .unwrap_or_else(|| VoteStore::default_path(network));
+            let voter = Voter::new(voting.private_key, Some(VoteStore::open(&path)?))?;
+            info!("Voting as {}", voter.account().to_address());
+            node.voter = Some(voter);
+        }
+        if let Some(advertise) = advertise {
+            node.advertise = Some(
+                SocketAddr::from_str(&advertise)
+                    .with_context(|| format!("Could not parse host:port: {}", advertise))?,
+            );
+        }
         let rpc_rx = node.start_rpc_server().await?;
+        node.start_websocket_server();
         if let Some(str_addrs) = override_peers {
             let mut socket_addrs = vec![];
             for str_addr in str_addrs {
@@ -46,35 +246,320 @@ impl Node {
         } else {
             node.peer_autodiscovery().await?;
         }
+        if bootstrap_peers > 0 {
+            node.start_bootstrap(bootstrap_peers, CancellationToken::new())
+                .await?;
+        }
 
         node.run(rpc_rx).await
     }
 
     pub fn new(network: Network) -> Self {
-        // let state = SledDiskState::new(Network::Live);
-        let state = MemoryState::new(network);
-        let state = Arc::new(Mutex::new(state));
-        Self { state, network }
+        Self::with_budget(network, MemoryBudget::default())
+    }
+
+    pub fn with_budget(network: Network, budget: MemoryBudget) -> Self {
+        let state = MemoryState::with_budget(network, &budget);
+        Self::with_state(network, budget, Arc::new(state))
+    }
+
+    /// A node keeping the ledger and peers in `state`, which can be any [State] implementation.
+    pub fn with_state(network: Network, budget: MemoryBudget, state: ArcState) -> Self {
+        Self {
+            state,
+            network,
+            advertise: None,
+            bootstrap: None,
+            confirmations: ConfirmationTracker::new(network),
+            publishes: PublishCache::new(&budget),
+            dropped: DroppedBlocks::default(),
+            own_blocks: OwnBlocks::new(),
+            frontiers: FrontierEvents::new(),
+            peer_filter: PeerFilter::default(),
+            wire_dump: None,
+            health: HealthConfig::default(),
+            block_rate: BlockRate::new(),
+            telemetry: NetworkTelemetry::new(),
+            voter: None,
+            journal: Journal::disabled(),
+            rpc: RpcServerConfig::default(),
+            dialer: Dialer::new(DialerConfig::default()),
+            bulk_pulls: BulkPullServer::default(),
+            forks: ForkWatch::new(),
+        }
+    }
+
+    /// A handle for applications to use the node while it's running.
+    /// Where the node sends frontier changes, e.g. for a [crate::watch::BalanceTracker] to
+    /// follow.
+    pub fn frontier_events(&self) -> FrontierEvents {
+        self.frontiers.clone()
+    }
+
+    pub fn client(&self) -> NodeClient {
+        NodeClient::new(self.confirmations.clone(), self.own_blocks.clone())
+    }
+
+    /// Pull frontiers from up to `max_peers` of the known peers in the background, until done
+    /// or `cancel` is cancelled.
+    pub async fn start_bootstrap(
+        &mut self,
+        max_peers: usize,
+        cancel: CancellationToken,
+    ) -> anyhow::Result<()> {
+        let peers: Vec<SocketAddr> = self
+            .state
+            .peers()
+            .await?
+            .into_iter()
+            .take(max_peers)
+            .collect();
+        info!("Bootstrapping frontiers from {} peers", peers.len());
+
+        // A few ranges per peer so that stealing only has to even out the differences.
+        let ranges = peers.len() * 4;
+        let bootstrap = Arc::new(FrontierBootstrap::new(self.network, peers, ranges));
+        bootstrap.set_events(self.frontiers.clone());
+        self.bootstrap = Some(bootstrap.clone());
+
+        let logger = bootstrap.clone();
+        tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(BOOTSTRAP_LOG_INTERVAL).await;
+                let status = logger.status();
+                if !status.running {
+                    break;
+                }
+                info!(
+                    "Bootstrap: {}/{} ranges, {} frontiers, {} steals",
+                    status.ranges_done, status.ranges_total, status.frontiers, status.steals
+                );
+            }
+        });
+        tokio::spawn(async move {
+            match bootstrap.run(&cancel).await {
+                Ok(_) => {}
+                Err(err) if matches!(err.downcast_ref(), Some(crate::Error::Cancelled)) => {
+                    info!("Bootstrap cancelled")
+                }
+                Err(err) => error!("Bootstrap failed: {:?}", err),
+            }
+        });
+        Ok(())
     }
 
     pub async fn start_rpc_server(&self) -> anyhow::Result<NodeCommandReceiver> {
         let (rpc_server, rx) = RPCServer::new_with_channel(self.state.clone());
-        tokio::spawn(rpc_server.run());
+        tokio::spawn(rpc_server.config(self.rpc.clone()).run());
         Ok(rx)
     }
 
+    pub fn start_websocket_server(&self) {
+        let server = WebSocketServer::new(self.frontiers.clone());
+        // TODO: Configurable
+        tokio::spawn(server.run(([127, 0, 0, 1], 7078).into()));
+    }
+
+    /// Remove peers from the peer table once they haven't been seen for [PEER_CUTOFF].
+    fn start_peer_expiry(&self) {
+        let state = self.state.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(KEEPALIVE_INTERVAL);
+            loop {
+                interval.tick().await;
+                let cutoff = Utc::now() - chrono::Duration::from_std(PEER_CUTOFF).unwrap();
+                match state.expire_peers(cutoff).await {
+                    Ok(expired) if !expired.is_empty() => {
+                        debug!("Expired {} peers: {:?}", expired.len(), expired)
+                    }
+                    Ok(_) => {}
+                    Err(err) => error!("Could not expire peers: {:?}", err),
+                }
+            }
+        });
+    }
+
+    /// Prune the ledger every `interval` following `policy`, starting straight away.
+    fn start_pruning(&self, interval: Duration, policy: RetentionPolicy) {
+        let state = self.state.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(interval);
+            loop {
+                interval.tick().await;
+                match pruning::prune(&*state, &policy).await {
+                    Ok(stats) if stats.blocks > 0 => info!(
+                        "Pruned {} blocks from {} accounts",
+                        stats.blocks, stats.accounts
+                    ),
+                    Ok(_) => {}
+                    Err(err) => error!("Could not prune the ledger: {:?}", err),
+                }
+            }
+        });
+    }
+
+    /// Dial known peers every [dialer::DIAL_INTERVAL] until there are enough connections.
+    fn start_dialer(&self) {
+        let state = self.state.clone();
+        let network = self.network;
+        let advertise = self.advertise;
+        let confirmations = self.confirmations.clone();
+        let publishes = self.publishes.clone();
+        let dropped = self.dropped.clone();
+        let own_blocks = self.own_blocks.clone();
+        let frontiers = self.frontiers.clone();
+        let peer_filter = self.peer_filter.clone();
+        let wire_dump = self.wire_dump.clone();
+        let telemetry = self.telemetry.clone();
+        let voter = self.voter.clone();
+        let journal = self.journal.clone();
+        let dialer = self.dialer.clone();
+        let bulk_pulls = self.bulk_pulls.clone();
+        let forks = self.forks.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(dialer::DIAL_INTERVAL);
+            loop {
+                interval.tick().await;
+                let peers: Vec<SocketAddr> = match state.peer_table().await {
+                    Ok(table) => table.into_iter().map(|peer| peer.address).collect(),
+                    Err(err) => {
+                        error!("Could not read the peer table: {:?}", err);
+                        continue;
+                    }
+                };
+                for address in dialer.select(&peers) {
+                    tokio::spawn(Self::connection(
+                        network,
+                        state.clone(),
+                        address,
+                        advertise,
+                        confirmations.clone(),
+                        publishes.clone(),
+                        dropped.clone(),
+                        own_blocks.clone(),
+                        frontiers.clone(),
+                        peer_filter.clone(),
+                        wire_dump.clone(),
+                        telemetry.clone(),
+                        voter.clone(),
+                        journal.clone(),
+                        dialer.clone(),
+                        bulk_pulls.clone(),
+                        forks.clone(),
+                    ));
+                }
+            }
+        });
+    }
+
     pub async fn run(self, mut node_rx: NodeCommandReceiver) -> anyhow::Result<()> {
-        let initial_peers = self.state.lock().await.peers().await?;
-        for address in initial_peers {
-            let state = self.state.clone();
-            let network = self.network.clone();
-            Self::connection(network, state, address).await?;
-        }
+        self.start_peer_expiry();
+        self.start_dialer();
 
         while let Some(node_command) = node_rx.recv().await {
-            dbg!("todo node command", &node_command);
+            trace!("Node command: {:?}", &node_command);
             match node_command {
                 NodeCommand::PeerInfo(_tx) => todo!("get_active_peers()"),
+                NodeCommand::PeerTable(tx) => {
+                    let peers = self.state.peer_table().await;
+                    let _ = tx.send(peers.map(|peers| PeerTableResponse { peers }));
+                }
+                NodeCommand::PeerTelemetry(tx) => {
+                    let _ = tx.send(self.telemetry.reports());
+                }
+                NodeCommand::BootstrapStatus(tx) => {
+                    let status = self
+                        .bootstrap
+                        .as_ref()
+                        .map(|b| b.status())
+                        .unwrap_or_default();
+                    // The requester might have gone away, which is fine.
+                    let _ = tx.send(status);
+                }
+                NodeCommand::DroppedBlocks(tx) => {
+                    let _ = tx.send(self.dropped.snapshot());
+                }
+                NodeCommand::DifficultyStats(seconds, tx) => {
+                    let state = self.state.clone();
+                    let network = self.network;
+                    tokio::spawn(async move {
+                        let stats = difficulty_stats::difficulty_stats(&state, network, seconds);
+                        let _ = tx.send(stats.await);
+                    });
+                }
+                NodeCommand::SyncStatus(tx) => {
+                    let _ = tx.send(telemetry::sync_status(&self.state, &self.telemetry).await);
+                }
+                NodeCommand::Health(tx) => {
+                    let _ = tx.send(self.health().await);
+                }
+                NodeCommand::LedgerStats(tx) => {
+                    // Walking the ledger takes a while, so don't hold up other commands.
+                    let state = self.state.clone();
+                    tokio::spawn(async move {
+                        let _ = tx.send(ledger_stats::ledger_stats(&state).await);
+                    });
+                }
+                NodeCommand::Persist(to, tx) => match self.rpc.persist_path(&to) {
+                    Ok(path) => {
+                        let state = self.state.clone();
+                        let network = self.network;
+                        tokio::spawn(async move {
+                            info!("Persisting the ledger to {:?}", path);
+                            let _ = tx.send(sync::persist(&state, network, &path).await);
+                        });
+                    }
+                    Err(err) => {
+                        warn!("Refusing to persist to {:?}: {:#}", to, err);
+                        let _ = tx.send(Err(err));
+                    }
+                },
+                NodeCommand::BlockInfo(hash, tx) => {
+                    let state = self.state.clone();
+                    tokio::spawn(async move {
+                        let _ = tx.send(ledger_info::block_info(&state, &hash).await);
+                    });
+                }
+                NodeCommand::AccountInfo(request, tx) => {
+                    let state = self.state.clone();
+                    tokio::spawn(async move {
+                        let _ = tx.send(ledger_info::account_info(&state, &request).await);
+                    });
+                }
+                NodeCommand::Pending(request, tx) => {
+                    let state = self.state.clone();
+                    tokio::spawn(async move {
+                        let _ = tx.send(ledger_info::pending(&state, &request).await);
+                    });
+                }
+                NodeCommand::AccountsPending(request, tx) => {
+                    let state = self.state.clone();
+                    tokio::spawn(async move {
+                        let _ = tx.send(ledger_info::accounts_pending(&state, &request).await);
+                    });
+                }
+                NodeCommand::Process(block, tx) => {
+                    let state = self.state.clone();
+                    let network = self.network;
+                    let journal = self.journal.clone();
+                    let frontiers = self.frontiers.clone();
+                    let own_blocks = self.own_blocks.clone();
+                    let confirmations = self.confirmations.clone();
+                    tokio::spawn(async move {
+                        let _ = tx.send(
+                            process::submit(
+                                &state,
+                                network,
+                                &journal,
+                                &frontiers,
+                                &own_blocks,
+                                &confirmations,
+                                *block,
+                            )
+                            .await,
+                        );
+                    });
+                }
             };
         }
 
@@ -82,22 +567,92 @@ impl Node {
         Ok(())
     }
 
-    #[instrument(skip(network, state))]
+    pub async fn health(&self) -> HealthReport {
+        let bootstrap = self
+            .bootstrap
+            .as_ref()
+            .map(|b| b.status())
+            .unwrap_or_default();
+        let mut report = self.health.report(
+            self.block_rate.uptime(),
+            self.confirmations.peer_count(),
+            self.block_rate.per_minute(),
+            &bootstrap,
+        );
+        match telemetry::sync_status(&self.state, &self.telemetry).await {
+            Ok(status) => report.sync_percentage = status.sync_percentage,
+            Err(err) => error!("Estimating the sync percentage: {:#}", err),
+        }
+        report
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    #[instrument(skip(
+        network,
+        state,
+        advertise,
+        confirmations,
+        publishes,
+        dropped,
+        own_blocks,
+        frontiers,
+        peer_filter,
+        wire_dump,
+        telemetry,
+        voter,
+        journal,
+        dialer,
+        bulk_pulls,
+        forks
+    ))]
     pub async fn connection(
         network: Network,
         state: ArcState,
         address: SocketAddr,
+        advertise: Option<SocketAddr>,
+        confirmations: ConfirmationTracker,
+        publishes: PublishCache,
+        dropped: DroppedBlocks,
+        own_blocks: OwnBlocks,
+        frontiers: FrontierEvents,
+        peer_filter: PeerFilter,
+        wire_dump: Option<WireDump>,
+        telemetry: NetworkTelemetry,
+        voter: Option<Voter>,
+        journal: Journal,
+        dialer: Dialer,
+        bulk_pulls: BulkPullServer,
+        forks: ForkWatch,
     ) -> anyhow::Result<()> {
+        // The lists might have changed since the peer was stored.
+        if !peer_filter.allows(&address) {
+            info!("Not connecting to a peer outside of the peer lists.");
+            dialer.skipped(address);
+            return Ok(());
+        }
         info!("Connecting.");
-        let stream = match TcpStream::connect(address).await {
+        let stream = match dialer.dial(address).await {
             Ok(s) => s,
             Err(err) => {
-                error!("Could not connect: {:?}", err);
+                error!("Could not connect: {:#}", err);
                 return Ok(());
             }
         };
 
-        let (peer, tx, mut rx) = Peer::new_with_channels(network, state.clone(), address);
+        let (mut peer, tx, mut rx) = Peer::new_with_channels(network, state.clone(), address);
+        peer.advertise = advertise;
+        peer.confirmations = confirmations;
+        peer.publishes = publishes;
+        peer.dropped = dropped;
+        peer.own_blocks = own_blocks;
+        peer.frontiers = frontiers;
+        peer.peer_filter = peer_filter;
+        peer.wire_dump = wire_dump;
+        peer.telemetry = telemetry;
+        peer.voter = voter;
+        peer.journal = journal;
+        peer.bulk_pulls = bulk_pulls;
+        peer.forks = forks;
 
         // Task for the Peer handler.
         let peer_task = tokio::spawn(peer.run());
@@ -106,14 +661,20 @@ impl Node {
 
         // Handle reads in a separate task.
         let reader_task: JoinHandle<anyhow::Result<()>> = tokio::spawn(async move {
-            let mut buffer: [u8; 10240] = [0; 10240];
+            let mut buffer = BytesMut::with_capacity(10240);
             loop {
+                // Reclaims the allocation once the previous packets have been dropped by Peer.
+                buffer.reserve(10240);
                 let bytes = tcp_in
-                    .read(&mut buffer)
+                    .read_buf(&mut buffer)
                     .await
                     .with_context(|| format!("Could not read from socket at {}", address))?;
+                if bytes == 0 {
+                    debug!("Socket closed by peer");
+                    break;
+                }
 
-                let result = tx.send(Packet::new(Vec::from(&buffer[0..bytes]))).await;
+                let result = tx.send(Packet::new(buffer.split())).await;
                 if result.is_err() {
                     // When the channel disconnects from Peer, we rely on Peer to report the error.
                     break;
@@ -141,7 +702,10 @@ impl Node {
             Ok(())
         });
 
-        let (peer, reader, writer) = tokio::try_join!(peer_task, reader_task, writer_task)?;
+        let joined = tokio::try_join!(peer_task, reader_task, writer_task);
+        let clean = matches!(&joined, Ok((Ok(_), Ok(_), Ok(_))));
+        dialer.disconnected(address, clean);
+        let (peer, reader, writer) = joined?;
         if let Err(err) = peer {
             error!("Disconnected because of peer: {:?}", err);
         };
@@ -155,8 +719,14 @@ impl Node {
     }
 
     pub async fn add_peers(&mut self, socket_addrs: &[SocketAddr]) -> anyhow::Result<()> {
-        debug!("Adding peers to state: {:?}", socket_addrs);
-        self.state.lock().await.add_peers(socket_addrs).await?;
+        let (allowed, denied): (Vec<SocketAddr>, Vec<SocketAddr>) = socket_addrs
+            .iter()
+            .partition(|addr| self.peer_filter.allows(addr));
+        if !denied.is_empty() {
+            info!("Skipping peers outside of the peer lists: {:?}", denied);
+        }
+        debug!("Adding peers to state: {:?}", allowed);
+        self.state.add_peers(&allowed).await?;
         Ok(())
     }
 
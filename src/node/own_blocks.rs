@@ -0,0 +1,151 @@
+//! Blocks that were made by us, so that we recognise them when peers flood them back to us.
+//!
+//! A block we publish comes back from the peers that flood it on. Those copies are skipped
+//! instead of being processed and flooded again, and whoever is waiting on the block is told
+//! that it was echoed, which shows that it made it out to the network. Blocks are forgotten
+//! after [OWN_BLOCK_TTL], by when every echo should have arrived.
+use crate::blocks::BlockHash;
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::oneshot;
+
+/// How long to remember a block of ours.
+pub const OWN_BLOCK_TTL: Duration = Duration::from_secs(600);
+
+#[derive(Clone)]
+pub struct OwnBlocks {
+    ttl: Duration,
+    inner: Arc<Mutex<HashMap<BlockHash, OwnBlock>>>,
+}
+
+struct OwnBlock {
+    added: Instant,
+
+    /// The first peer that sent the block back to us.
+    echoed_by: Option<SocketAddr>,
+
+    waiters: Vec<oneshot::Sender<SocketAddr>>,
+}
+
+impl OwnBlocks {
+    pub fn new() -> Self {
+        Self::with_ttl(OWN_BLOCK_TTL)
+    }
+
+    pub fn with_ttl(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            inner: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Remember a block that we're about to publish.
+    pub fn add(&self, hash: &BlockHash) {
+        let mut inner = self.inner.lock().unwrap();
+        self.expire(&mut inner);
+        inner.entry(hash.to_owned()).or_insert_with(|| OwnBlock {
+            added: Instant::now(),
+            echoed_by: None,
+            waiters: vec![],
+        });
+    }
+
+    /// Note that `peer` published `hash`. Returns true if the block is ours, in which case it
+    /// shouldn't be processed again.
+    pub fn echo(&self, hash: &BlockHash, peer: SocketAddr) -> bool {
+        let mut inner = self.inner.lock().unwrap();
+        self.expire(&mut inner);
+        let block = match inner.get_mut(hash) {
+            Some(block) => block,
+            None => return false,
+        };
+        if block.echoed_by.is_none() {
+            block.echoed_by = Some(peer);
+            for waiter in block.waiters.drain(..) {
+                // The waiter might have given up, which is fine.
+                let _ = waiter.send(peer);
+            }
+        }
+        true
+    }
+
+    /// Wait until a peer sends `hash` back to us, returning the peer. Returns `None` if the
+    /// block isn't ours, or it's forgotten before being echoed.
+    ///
+    /// This waits until the block expires at most, so wrap it in a timeout to give up sooner.
+    pub async fn echoed(&self, hash: &BlockHash) -> Option<SocketAddr> {
+        let (rx, left) = {
+            let mut inner = self.inner.lock().unwrap();
+            let block = inner.get_mut(hash)?;
+            if let Some(peer) = block.echoed_by {
+                return Some(peer);
+            }
+            let (tx, rx) = oneshot::channel();
+            block.waiters.push(tx);
+            (rx, self.ttl.checked_sub(block.added.elapsed())?)
+        };
+        tokio::time::timeout(left, rx).await.ok()?.ok()
+    }
+
+    pub fn len(&self) -> usize {
+        self.inner.lock().unwrap().len()
+    }
+
+    fn expire(&self, inner: &mut HashMap<BlockHash, OwnBlock>) {
+        let ttl = self.ttl;
+        inner.retain(|_, block| block.added.elapsed() < ttl);
+    }
+}
+
+impl Default for OwnBlocks {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::convert::TryFrom;
+
+    fn hash(byte: u8) -> BlockHash {
+        BlockHash::try_from([byte; BlockHash::LEN].as_ref()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn echoes() {
+        let own = OwnBlocks::new();
+        let peer: SocketAddr = "127.0.0.1:7075".parse().unwrap();
+        own.add(&hash(1));
+        assert!(!own.echo(&hash(2), peer));
+
+        let waiter = {
+            let own = own.clone();
+            tokio::spawn(async move { own.echoed(&hash(1)).await })
+        };
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        assert!(own.echo(&hash(1), peer));
+        assert_eq!(waiter.await.unwrap(), Some(peer));
+
+        // Still ours after the first echo, and the first peer is kept.
+        let other: SocketAddr = "127.0.0.2:7075".parse().unwrap();
+        assert!(own.echo(&hash(1), other));
+        assert_eq!(own.echoed(&hash(1)).await, Some(peer));
+        assert_eq!(own.echoed(&hash(2)).await, None);
+    }
+
+    #[tokio::test]
+    async fn expires() {
+        let own = OwnBlocks::with_ttl(Duration::from_millis(20));
+        own.add(&hash(1));
+        assert_eq!(own.echoed(&hash(1)).await, None);
+
+        own.add(&hash(2));
+        tokio::time::sleep(Duration::from_millis(30)).await;
+        let peer: SocketAddr = "127.0.0.1:7075".parse().unwrap();
+        assert!(!own.echo(&hash(2), peer));
+        assert_eq!(own.len(), 0);
+    }
+}
@@ -28,8 +28,6 @@ impl Peer {
                 .with_context(context)?;
 
             self.state
-                .lock()
-                .await
                 .add_vote(hash, &confirm_ack.account)
                 .await
                 .with_context(context)?;
@@ -72,9 +70,7 @@ impl Peer {
         // This function should only have the chance to be called once per block.
         if self
             .state
-            .lock()
-            .await
-            .get_block_by_hash(&block_hash)
+            .get_block_by_hash(block_hash)
             .await
             .with_context(context)?
             .is_some()
@@ -84,14 +80,11 @@ impl Peer {
 
         let context = || format!("Block {:?}", block);
         block
-            .verify_signature(&block.account())
+            .verify_signature(block.account())
             .context("Incorrect signature")
             .with_context(context)?;
 
-        let work = block.work();
-        if work.is_none() {
-            return Err(anyhow!("Work is missing from block")).with_context(context);
-        }
+        block.require_work().with_context(context)?;
         // TODO: Verify work
 
         // TODO: For now just assume this is a send block
@@ -109,8 +102,6 @@ impl Peer {
 
                 let prev_block = self
                     .state
-                    .lock()
-                    .await
                     .get_block_by_hash(previous_hash)
                     .await
                     .context("Previous block")
@@ -151,12 +142,7 @@ impl Peer {
             _ => todo!(),
         }
 
-        self.state
-            .lock()
-            .await
-            .add_block(block)
-            .await
-            .with_context(context)?;
+        self.state.add_block(block).await.with_context(context)?;
 
         // self.balance_rep_weights(block)
         //     .await
@@ -168,8 +154,6 @@ impl Peer {
     pub async fn get_latest_block(&self, account: &Public) -> anyhow::Result<Option<Block>> {
         let block_hash = self
             .state
-            .lock()
-            .await
             .get_latest_block_hash_for_account(account)
             .await
             .with_context(|| format!("Account: {:?}", account))?;
@@ -178,10 +162,7 @@ impl Peer {
             None => return Ok(None),
         };
 
-        Ok(self
-            .state
-            .lock()
-            .await
+        self.state
             .get_block_by_hash(&block_hash)
             .await
             .with_context(|| {
@@ -189,6 +170,6 @@ impl Peer {
                     "Could not get block for latest hash for account: {:?}",
                     account
                 )
-            })?)
+            })
     }
 }
@@ -0,0 +1,48 @@
+//! Recovering from messages that don't line up with their headers.
+//!
+//! The payload length of a message isn't sent, it's worked out from the header. When a peer
+//! gets that wrong, or sends a header we can't make sense of, the rest of the stream would be
+//! read from the wrong offset. Instead of disconnecting straight away, the peer gets a strike:
+//! - A payload that was the expected length but didn't deserialize has already been read, so
+//!   the next message is read as usual.
+//! - When the length can't be worked out, is over the limit for the message type, or the header
+//!   itself is bad, bytes are skipped until something that looks like a header for our network.
+//!
+//! After [MAX_STRIKES] the peer is disconnected.
+use crate::node::header::{Header, MessageType};
+
+/// Framing errors a peer can make before it's disconnected.
+pub const MAX_STRIKES: usize = 5;
+
+#[derive(Debug, thiserror::Error)]
+pub enum FramingError {
+    #[error("Could not read header: {0:#}")]
+    BadHeader(anyhow::Error),
+
+    #[error("Could not work out the payload length for {header:?}: {source:#}")]
+    UnknownLength {
+        header: Header,
+        source: anyhow::Error,
+    },
+
+    #[error("Payload of {len} bytes is over the {max} byte limit for {message_type:?}")]
+    TooLong {
+        message_type: MessageType,
+        len: usize,
+        max: usize,
+    },
+
+    #[error("Could not read payload for {header:?}: {source:#}")]
+    BadPayload {
+        header: Header,
+        source: anyhow::Error,
+    },
+}
+
+impl FramingError {
+    /// Whether the stream needs to be scanned for the next header, rather than carrying on
+    /// from where the bad message ended.
+    pub fn needs_resync(&self) -> bool {
+        !matches!(self, FramingError::BadPayload { .. })
+    }
+}
@@ -6,9 +6,9 @@ use tracing::info;
 impl Peer {
     pub async fn ensure_genesis(&mut self) -> anyhow::Result<()> {
         info!("Ensuring genesis");
-        let mut block = self.network.genesis_block();
+        let block = self.network.genesis_block();
 
-        self.add_elected_block(&mut block)
+        self.add_elected_block(&block)
             .await
             .context("Adding genesis block")?;
 
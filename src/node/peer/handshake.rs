@@ -0,0 +1,178 @@
+//! The order of the node ID handshake.
+//!
+//! Both sides send a query with a random cookie straight after connecting, and answer the other
+//! side's query by signing its cookie with their node ID. A peer sends its query before its
+//! response, or along with it in the same message:
+//! ```text
+//! AwaitingQuery --query--> AwaitingResponse --response--> Established
+//! ```
+//! Other messages that arrive before the handshake is established are queued until it is, or
+//! dropped, as set by [EarlyMessages].
+//!
+//! Only the first query is answered. A response before the peer's query is rejected, and a
+//! response once established is ignored. The cookie is forgotten once the handshake is
+//! established, so a late response can't be checked against it.
+//!
+//! Each query's cookie is also recorded in the state against the peer's address, replacing the
+//! cookie of any earlier connection to it, and a response only counts for the latest one. Once a
+//! node ID is established for an address, later handshakes with that address have to present
+//! the same node ID until the peer expires. A response replayed from another session is signed
+//! over a cookie that isn't ours, so it doesn't verify.
+use crate::node::cookie::Cookie;
+use crate::Public;
+
+/// The most messages queued by [EarlyMessages::Queue] before the peer is disconnected.
+pub const MAX_EARLY_MESSAGES: usize = 64;
+
+/// What to do with messages other than handshakes that arrive before the handshake is
+/// established.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EarlyMessages {
+    /// Hold them back, and handle them in order once the handshake is established.
+    Queue,
+
+    /// Drop them with a warning.
+    Reject,
+
+    /// Handle them straight away. For pcap dumps, where the capture might start after the
+    /// handshake.
+    Allow,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum HandshakeError {
+    #[error("Handshake response before the peer's query")]
+    ResponseBeforeQuery,
+
+    #[error("Handshake response, but we haven't sent a query")]
+    NoCookie,
+
+    #[error("Handshake response after the handshake was established")]
+    Late,
+
+    #[error("Handshake response to a cookie that was replaced by another connection")]
+    StaleCookie,
+
+    #[error("Handshake response with node id {got:?}, but {expected:?} was established")]
+    NodeIdChanged { expected: Public, got: Public },
+}
+
+#[derive(Debug, Clone)]
+pub enum HandshakeState {
+    /// Waiting for the peer's query. Our own query was sent with `cookie`, if it's been sent.
+    AwaitingQuery { cookie: Option<Cookie> },
+
+    /// The peer's query was answered, and its response to our `cookie` is next.
+    AwaitingResponse { cookie: Option<Cookie> },
+
+    /// The peer signed our cookie with its node ID.
+    Established { node_id: Public },
+}
+
+impl Default for HandshakeState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl HandshakeState {
+    pub fn new() -> Self {
+        HandshakeState::AwaitingQuery { cookie: None }
+    }
+
+    /// Our query was sent with `cookie`.
+    pub fn sent_query(&mut self, sent: Cookie) {
+        match self {
+            HandshakeState::AwaitingQuery { cookie }
+            | HandshakeState::AwaitingResponse { cookie } => *cookie = Some(sent),
+            HandshakeState::Established { .. } => {}
+        }
+    }
+
+    /// The peer sent a query. Returns whether to answer it, which is only for the first one.
+    pub fn received_query(&mut self) -> bool {
+        match self {
+            HandshakeState::AwaitingQuery { cookie } => {
+                *self = HandshakeState::AwaitingResponse {
+                    cookie: cookie.take(),
+                };
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// The cookie that a response from the peer has to be a signature of.
+    pub fn expecting_response(&self) -> Result<&Cookie, HandshakeError> {
+        match self {
+            HandshakeState::AwaitingQuery { .. } => Err(HandshakeError::ResponseBeforeQuery),
+            HandshakeState::AwaitingResponse { cookie: None } => Err(HandshakeError::NoCookie),
+            HandshakeState::AwaitingResponse {
+                cookie: Some(cookie),
+            } => Ok(cookie),
+            HandshakeState::Established { .. } => Err(HandshakeError::Late),
+        }
+    }
+
+    /// The peer's response checked out.
+    pub fn establish(&mut self, node_id: Public) {
+        *self = HandshakeState::Established { node_id };
+    }
+
+    pub fn is_established(&self) -> bool {
+        matches!(self, HandshakeState::Established { .. })
+    }
+
+    pub fn node_id(&self) -> Option<&Public> {
+        match self {
+            HandshakeState::Established { node_id } => Some(node_id),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Seed;
+
+    #[test]
+    fn order() {
+        let mut state = HandshakeState::new();
+        assert!(matches!(
+            state.expecting_response(),
+            Err(HandshakeError::ResponseBeforeQuery)
+        ));
+
+        let cookie = Cookie::random();
+        state.sent_query(cookie.clone());
+        assert!(state.received_query());
+        assert!(!state.received_query(), "Only the first query is answered");
+        assert_eq!(
+            state.expecting_response().unwrap().as_bytes(),
+            cookie.as_bytes()
+        );
+
+        let node_id = Seed::zero().derive(0).to_public().unwrap();
+        state.establish(node_id.clone());
+        assert!(state.is_established());
+        assert_eq!(state.node_id(), Some(&node_id));
+        assert!(matches!(
+            state.expecting_response(),
+            Err(HandshakeError::Late)
+        ));
+        assert!(!state.received_query());
+    }
+
+    #[test]
+    fn query_before_our_own() {
+        let mut state = HandshakeState::new();
+        assert!(state.received_query());
+        assert!(matches!(
+            state.expecting_response(),
+            Err(HandshakeError::NoCookie)
+        ));
+        state.sent_query(Cookie::random());
+        assert!(state.expecting_response().is_ok());
+    }
+}
@@ -1,7 +1,13 @@
+use super::handshake::HandshakeError;
 use super::Peer;
-use crate::blocks::{Block, BlockHash, BlockHolder, BlockType, Link, Previous, StateBlock};
+use crate::blocks::{
+    Block, BlockHash, BlockHolder, BlockType, Link, Previous, StateBlock, Subtype,
+};
+use crate::node::bulk_pull_server::{PullCursor, PULL_PACKET_LEN};
 use crate::node::cookie::Cookie;
 use crate::node::header::{Extensions, Header, MessageType};
+use crate::node::intake::{self, RejectReason};
+use crate::node::messages::bulk_pull::BulkPull;
 use crate::node::messages::confirm_ack::ConfirmAck;
 use crate::node::messages::confirm_req::ConfirmReq;
 use crate::node::messages::frontier_req::FrontierReq;
@@ -11,10 +17,16 @@ use crate::node::messages::keepalive::Keepalive;
 use crate::node::messages::publish::Publish;
 use crate::node::messages::telemetry_ack::TelemetryAck;
 use crate::node::messages::telemetry_req::TelemetryReq;
-use crate::{Difficulty, Public, Seed, Signature};
+use crate::node::peer_info::PeerInfo;
+use crate::node::probe::{probe, ProbeStatus};
+use crate::node::process;
+use crate::node::wire::Wire;
+use crate::node::Packet;
+use crate::{Difficulty, Public, Raw};
 use anyhow::anyhow;
 use anyhow::Context;
-use std::convert::TryFrom;
+use rand::seq::IteratorRandom;
+use std::net::SocketAddr;
 use tracing::{debug, info, instrument, trace, warn};
 
 impl Peer {
@@ -24,11 +36,9 @@ impl Peer {
         self.send_header(MessageType::Handshake, *Extensions::new().query())
             .await?;
 
-        // TODO: Track our own cookie?
         let cookie = Cookie::random();
+        self.handshake.sent_query(cookie.clone());
         self.state
-            .lock()
-            .await
             .set_cookie(self.peer_addr, cookie.clone())
             .await?;
         let handshake_query = HandshakeQuery::new(cookie);
@@ -37,67 +47,79 @@ impl Peer {
         Ok(())
     }
 
+    /// Check that `cookie` is still the one for this peer's address, and that the node ID is the
+    /// one established for the address before, if there was one.
+    async fn check_endpoint(&self, cookie: &Cookie, node_id: &Public) -> anyhow::Result<()> {
+        let state = &self.state;
+        match state.cookie_for_socket_addr(&self.peer_addr).await? {
+            Some(current) if current.as_bytes() == cookie.as_bytes() => {}
+            _ => return Err(HandshakeError::StaleCookie.into()),
+        }
+        if let Some(expected) = state.node_id_for_socket_addr(&self.peer_addr).await? {
+            if &expected != node_id {
+                return Err(HandshakeError::NodeIdChanged {
+                    expected,
+                    got: node_id.to_owned(),
+                }
+                .into());
+            }
+        }
+        Ok(())
+    }
+
+    /// Handle a handshake in the order described in [super::handshake].
     #[instrument(skip(self, header, handshake))]
     pub async fn handle_handshake(
         &mut self,
         header: &Header,
         handshake: Handshake,
     ) -> anyhow::Result<()> {
-        enum ShouldRespond {
-            No,
-            Yes(Public, Signature),
-        }
-
-        let mut should_respond = ShouldRespond::No;
+        let mut answer = None;
         if header.ext().is_query() {
             // This would probably be a programming error if it panicked.
             let query = handshake.query.expect("query is None but is_query is True");
 
-            // XXX: Hacky code here just to see if it works!
-            // TODO: Move into state
-            let seed = Seed::random();
-            let private = seed.derive(0);
-            let public = private.to_public()?;
-            let signature = private.sign(query.cookie().as_bytes())?;
-            public
-                .verify(query.cookie().as_bytes(), &signature)
-                .context("Verify recv handshake signature.")?;
+            if self.handshake.received_query() {
+                let private = self.telemetry.node_key();
+                let public = private.to_public()?;
+                let signature = private.sign(query.cookie().as_bytes())?;
+                public
+                    .verify(query.cookie().as_bytes(), &signature)
+                    .context("Verify recv handshake signature.")?;
 
-            // Respond at the end because we mess with the header buffer.
-            should_respond = ShouldRespond::Yes(public, signature);
+                // Respond at the end because we mess with the header buffer.
+                answer = Some((public, signature));
+            } else {
+                warn!("Ignoring another handshake query from {}", self.peer_addr);
+            }
         }
 
+        let mut established = None;
         if header.ext().is_response() {
             let response = handshake
                 .response
                 .context("response is None but is_response is True.")?;
-            let public = response.public;
-            let signature = response.signature;
 
-            let cookie = &self
-                .state
-                .lock()
-                .await
-                .cookie_for_socket_addr(&self.peer_addr)
-                .await
-                .context("Could not lookup cookie for socket addr.")?;
-            if cookie.is_none() {
-                warn!(
-                    "Peer {:?} has no cookie. Can't verify handshake.",
-                    self.peer_addr
-                );
-                return Ok(());
-            }
-            let cookie = cookie.as_ref().unwrap();
-
-            if self.validate_handshakes {
-                public
-                    .verify(&cookie.as_bytes(), &signature)
-                    .context("Invalid signature in handshake response")?;
+            match self.handshake.expecting_response() {
+                Ok(cookie) => {
+                    if self.validate_handshakes {
+                        response
+                            .public
+                            .verify(cookie.as_bytes(), &response.signature)
+                            .context("Invalid signature in handshake response")?;
+                        self.check_endpoint(cookie, &response.public).await?;
+                    }
+                    established = Some(response.public);
+                }
+                Err(HandshakeError::Late) => {
+                    warn!("Ignoring a late handshake response from {}", self.peer_addr)
+                }
+                Err(err) if self.validate_handshakes => return Err(err.into()),
+                Err(err) => warn!("{}", err),
             }
         }
 
-        if let ShouldRespond::Yes(public, signature) = should_respond {
+        if let Some((public, signature)) = answer {
             self.send_header(MessageType::Handshake, *Extensions::new().response())
                 .await?;
 
@@ -107,6 +129,36 @@ impl Peer {
                 .context("Could not send response to peer.")?;
         }
 
+        if let Some(node_id) = established {
+            debug!("Handshake established with node id {:?}", node_id);
+            if self.validate_handshakes {
+                self.state
+                    .set_node_id(self.peer_addr, node_id.clone())
+                    .await?;
+            }
+            self.handshake.establish(node_id);
+            self.release_early();
+            self.send_telemetry_req().await?;
+        }
+
+        Ok(())
+    }
+
+    /// Send a keepalive with our own endpoint (if we have one to advertise) and a random sample
+    /// of the peers we know about.
+    #[instrument(skip(self))]
+    pub async fn send_keepalive(&mut self) -> anyhow::Result<()> {
+        let mut peers: Vec<PeerInfo> = self.advertise.into_iter().map(PeerInfo::from).collect();
+        let known = self.state.peers().await?;
+        let sample = known
+            .into_iter()
+            .filter(|addr| addr != &self.peer_addr && Some(*addr) != self.advertise)
+            .choose_multiple(&mut rand::thread_rng(), Keepalive::PEERS - peers.len());
+        peers.extend(sample.into_iter().map(PeerInfo::from));
+
+        self.send_header(MessageType::Keepalive, Extensions::new())
+            .await?;
+        self.send(&Keepalive::new(peers)).await?;
         Ok(())
     }
 
@@ -116,26 +168,135 @@ impl Peer {
         _header: &Header,
         keepalive: Keepalive,
     ) -> anyhow::Result<()> {
-        // dbg!(keepalive);
         debug!("{:?}", keepalive);
+
+        // The sender and the peers it lists are alive, so they're kept in the peer table.
+        {
+            let state = &self.state;
+            let known = state.peers().await?;
+            let seen: Vec<SocketAddr> = keepalive
+                .peers()
+                .iter()
+                .map(|peer| peer.socket_addr())
+                .chain(std::iter::once(self.peer_addr))
+                .filter(|address| known.contains(address))
+                .collect();
+            state.add_peers(&seen).await?;
+        }
+
+        if !self.probe_peers {
+            return Ok(());
+        }
+
+        for peer in keepalive.peers() {
+            let address = peer.socket_addr();
+            if Some(address) == self.advertise
+                || !self.peer_filter.allows(&address)
+                || !self.should_probe(&address).await?
+            {
+                continue;
+            }
+
+            // Mark as probed straight away, so that other keepalives don't probe it again while
+            // this one is in progress.
+            self.state
+                .set_probe_status(address, ProbeStatus::new(false))
+                .await?;
+
+            let network = self.network;
+            let state = self.state.clone();
+            tokio::spawn(async move {
+                let reachable = match probe(network, address).await {
+                    Ok(node_id) => {
+                        debug!("Probed {} with node id {:?}", address, node_id);
+                        true
+                    }
+                    Err(err) => {
+                        debug!("Probe failed for {}: {:?}", address, err);
+                        false
+                    }
+                };
+                if let Err(err) = state
+                    .set_probe_status(address, ProbeStatus::new(reachable))
+                    .await
+                {
+                    warn!("Could not set probe status for {}: {:?}", address, err);
+                }
+                if reachable {
+                    if let Err(err) = state.add_peers(&[address]).await {
+                        warn!("Could not add probed peer {}: {:?}", address, err);
+                    }
+                }
+            });
+        }
         Ok(())
     }
 
+    /// A learned peer is probed if it isn't already active and it hasn't been probed recently.
+    async fn should_probe(&self, address: &SocketAddr) -> anyhow::Result<bool> {
+        let state = &self.state;
+        if state.peers().await?.contains(address) {
+            return Ok(false);
+        }
+        Ok(state
+            .probe_status_for_socket_addr(address)
+            .await?
+            .is_none_or(|status| status.is_stale()))
+    }
+
+    /// Answer with our own telemetry, signed with the node ID from our handshake response.
     pub async fn handle_telemetry_req(
         &mut self,
         _header: &Header,
         _telemetry_req: TelemetryReq,
     ) -> anyhow::Result<()> {
-        // dbg!(telemetry_req);
-        Ok(())
+        let ack = self.telemetry.ack(self.network, &self.state).await?;
+        trace!("Sending telemetry ack");
+        self.send_header(
+            MessageType::TelemetryAck,
+            *Extensions::new().set_telemetry_size(TelemetryAck::LEN),
+        )
+        .await?;
+        self.send(&ack).await
+    }
+
+    pub async fn send_telemetry_req(&mut self) -> anyhow::Result<()> {
+        trace!("Sending telemetry request");
+        self.send_header(MessageType::TelemetryReq, Extensions::new())
+            .await
     }
 
+    /// Keep the telemetry of a peer for [crate::node::telemetry], if it was signed by the node we
+    /// did the handshake with, on our network.
     pub async fn handle_telemetry_ack(
         &mut self,
         _header: &Header,
-        _telemetry_ack: TelemetryAck,
+        telemetry_ack: TelemetryAck,
     ) -> anyhow::Result<()> {
-        // dbg!(telemetry_ack);
+        let node_id = match self.handshake.node_id() {
+            Some(node_id) => node_id.to_owned(),
+            None => {
+                debug!("Ignoring telemetry from before the handshake");
+                return Ok(());
+            }
+        };
+        if telemetry_ack.node_id != node_id {
+            return self.spoofed(&format!(
+                "for node id {:?}, but the handshake was with {:?}",
+                telemetry_ack.node_id, node_id
+            ));
+        }
+        if let Err(err) = telemetry_ack.verify() {
+            return self.spoofed(&format!("bad signature: {}", err));
+        }
+        if telemetry_ack.genesis_block != self.network.genesis_hash() {
+            warn!(
+                "Ignoring telemetry for the genesis block {:?}",
+                telemetry_ack.genesis_block
+            );
+            return Ok(());
+        }
+        self.telemetry.record(self.peer_addr, &telemetry_ack);
         Ok(())
     }
 
@@ -144,42 +305,59 @@ impl Peer {
         _header: &Header,
         publish: Publish,
     ) -> anyhow::Result<()> {
-        let _block = match publish.0 {
-            BlockHolder::Send(_) => {
-                todo!("Received a send block")
-            }
-            BlockHolder::Receive(_) => {
-                todo!("Received a receive block")
-            }
-            BlockHolder::Open(_) => {
-                todo!("Received an open block")
-            }
-            BlockHolder::Change(_) => {
-                todo!("Received a change block")
-            }
+        match publish.0 {
             BlockHolder::State(state_block) => {
                 self.state_block_handler(state_block).await?;
             }
+            legacy => self.legacy_block_handler(legacy).await?,
         };
 
         Ok(())
     }
 
+    /// Vote for the requested blocks that we have, if we're a representative.
     pub async fn handle_confirm_req(
         &mut self,
         _header: &Header,
-        _confirm_req: ConfirmReq,
+        confirm_req: ConfirmReq,
     ) -> anyhow::Result<()> {
-        // dbg!(confirm_req);
+        let voter = match &self.voter {
+            Some(voter) => voter.clone(),
+            None => return Ok(()),
+        };
+        let pairs = match confirm_req {
+            ConfirmReq::ConfirmReqByHash(pairs) => pairs,
+            ConfirmReq::BlockSelector(_) => {
+                debug!("Not voting on a confirm_req with a block");
+                return Ok(());
+            }
+        };
+
+        let mut votable = vec![];
+        for pair in pairs {
+            match self.block_by_hash(&pair.hash).await? {
+                Some(block) if block.root() == pair.root => votable.push(pair),
+                _ => trace!("Not voting for {:?}, which we don't have", pair.hash),
+            }
+        }
+        if votable.is_empty() {
+            return Ok(());
+        }
+
+        for confirm_ack in voter.vote(&votable).await? {
+            self.send_header(MessageType::ConfirmAck, confirm_ack.extensions())
+                .await?;
+            self.send(&confirm_ack).await?;
+        }
         Ok(())
     }
 
     pub async fn handle_confirm_ack(
         &mut self,
         _header: &Header,
-        _confirm_ack: ConfirmAck,
+        confirm_ack: ConfirmAck,
     ) -> anyhow::Result<()> {
-        // dbg!(confirm_ack);
+        self.confirmations.observe(&confirm_ack);
         Ok(())
     }
 
@@ -204,158 +382,334 @@ impl Peer {
         Ok(())
     }
 
-    /// Returns the previous block if is a head block AND is a state_block
+    /// Stream the blocks asked for from our state, then [BlockType::NotABlock]. See
+    /// [crate::node::bulk_pull_server].
+    pub async fn handle_bulk_pull(
+        &mut self,
+        _header: &Header,
+        pull: BulkPull,
+    ) -> anyhow::Result<()> {
+        let _permit = self.bulk_pulls.start(self.peer_addr.ip())?;
+        let limit = self.bulk_pulls.timeout;
+        tokio::time::timeout(limit, self.serve_bulk_pull(&pull))
+            .await
+            .map_err(|_| anyhow!("Bulk pull took longer than {:?}: {:?}", limit, pull))?
+    }
+
+    async fn serve_bulk_pull(&mut self, pull: &BulkPull) -> anyhow::Result<()> {
+        let mut cursor = PullCursor::new(self.state.clone(), pull).await?;
+        let mut sent = 0;
+        while let Some(block) = cursor.next().await? {
+            self.outgoing_buffer
+                .extend_from_slice(&[block.block_type().as_u8()]);
+            block.serialize_into(&mut self.outgoing_buffer);
+            sent += 1;
+            if self.outgoing_buffer.len() >= PULL_PACKET_LEN {
+                self.send_pulled().await?;
+            }
+        }
+        self.outgoing_buffer
+            .extend_from_slice(&[BlockType::NotABlock.as_u8()]);
+        self.send_pulled().await?;
+        debug!("Served {} blocks for {:?}", sent, pull);
+        Ok(())
+    }
+
+    /// Send the blocks gathered so far, which don't have a header of their own.
+    async fn send_pulled(&mut self) -> anyhow::Result<()> {
+        let data = self.outgoing_buffer.split().freeze();
+        self.peer_tx
+            .send(Packet::new(data))
+            .await
+            .context("Sending pulled blocks")
+    }
+
+    /// Returns the previous block if is a head block AND is a state_block, going by the
+    /// [crate::node::state::AccountInfo] of its account.
     /// Note: the returned block won't have Work, Amount or Signature
     async fn previous_as_account_info(
         &self,
         previous_block_hash: &BlockHash,
     ) -> anyhow::Result<Option<StateBlock>> {
-        let previous_block = Peer::block_by_hash(self, previous_block_hash).await?;
-        if let Some(previous_block) = previous_block {
-            let is_head = self
-                .get_latest_block(previous_block.account())
-                .await?
-                .map_or(false, |block| {
-                    let block_hash = block.hash().context("hash for is_head").unwrap();
-                    block_hash == previous_block_hash
-                });
-
-            dbg!(is_head);
-            return if is_head && *previous_block.block_type() == BlockType::State {
-                Ok(Some(StateBlock::try_from(previous_block)?))
-            } else if !is_head && *previous_block.block_type() == BlockType::State {
-                Err(anyhow!("The block referred as previous is not head!"))
-            } else {
-                Err(anyhow!(
-                    "Previous block existed but is not currently supported!"
-                ))
-                // in future versions this should build the account information by
-                // backtracing. No attack vector is possible here to make it slower
-                // because these blocks are not supported anymore and should be
-                // discarded.
-            };
+        let previous_block = match Peer::block_by_hash(self, previous_block_hash).await? {
+            Some(previous_block) => previous_block,
+            None => return Ok(None),
+        };
+        let is_head = self
+            .state
+            .account_info(previous_block.account())
+            .await?
+            .is_some_and(|info| &info.head == previous_block_hash);
+
+        if *previous_block.block_type() != BlockType::State {
+            // In future versions this should build the account information by backtracing. No
+            // attack vector is possible here to make it slower because these blocks are not
+            // supported anymore and should be discarded.
+            Err(anyhow!(
+                "Previous block existed but is not currently supported!"
+            ))
+        } else if is_head {
+            Ok(Some(StateBlock::from(previous_block)))
+        } else {
+            Err(anyhow!("The block referred as previous is not head!"))
         }
-        Ok(None)
     }
 
-    /// Shorthand for waiting a lock on the state and getting a block by hash
+    /// Shorthand for getting a block by hash from the state
     async fn block_by_hash(&self, block_hash: &BlockHash) -> anyhow::Result<Option<Block>> {
-        self.state.lock().await.get_block_by_hash(block_hash).await
+        self.state.get_block_by_hash(block_hash).await
     }
 
     /// Actions to be performed to validate and store a state block
     /// TODO: this assumes we will never get a live epoch block
     async fn state_block_handler(&self, state_block: StateBlock) -> anyhow::Result<()> {
-        // TODO: here there should be a check for epoch blocks
-        if self.block_existed(&state_block.hash).await? {
-            info!("Block {} already exists!", state_block)
-        } else if state_block.verify_self_signature().is_err() {
-            info!("Block {} has invalid signature!", state_block)
-        } else {
-            self.process_valid_existing_state_block(state_block).await?
+        if self.own_blocks.echo(&state_block.hash, self.peer_addr) {
+            debug!("Block {} of ours was echoed back", state_block);
+            return Ok(());
+        }
+        if !self.publishes.first_sighting(&state_block.hash) {
+            trace!("Block {} was published recently", state_block);
+            return Ok(());
+        }
+
+        self.process_state_blocks(vec![state_block]).await
+    }
+
+    /// Storing a block can let blocks in the unchecked table through, so they're processed
+    /// along with the `queue`.
+    async fn process_state_blocks(&self, mut queue: Vec<StateBlock>) -> anyhow::Result<()> {
+        while let Some(state_block) = queue.pop() {
+            let hash = state_block.hash.to_owned();
+            // TODO: here there should be a check for epoch blocks
+            if self.block_existed(&hash).await? {
+                info!("Block {} already exists!", state_block)
+            } else if state_block.verify_self_signature().is_err() {
+                self.drop_block(&state_block, RejectReason::InvalidSignature);
+            } else {
+                self.process_valid_existing_state_block(state_block).await?
+            }
+            if self.block_existed(&hash).await? {
+                queue.extend(self.publishes.take_unchecked(&hash));
+            }
         }
         Ok(())
     }
 
+    /// Send, receive, open and change blocks from before state blocks, which still turn up
+    /// while bootstrapping. All of them were made before epoch 2, so they have the same work
+    /// threshold.
+    async fn legacy_block_handler(&self, holder: BlockHolder) -> anyhow::Result<()> {
+        let hash = holder.hash();
+        if self.own_blocks.echo(&hash, self.peer_addr) {
+            debug!("Block {:?} of ours was echoed back", hash);
+            return Ok(());
+        }
+        if !self.publishes.first_sighting(&hash) {
+            trace!("Block {:?} was published recently", hash);
+            return Ok(());
+        }
+        if self.block_existed(&hash).await? {
+            info!("Block {:?} already exists!", hash);
+            return Ok(());
+        }
+
+        let resolved = intake::legacy_block(&*self.state, &holder).await?;
+        let block = match resolved {
+            Ok(block) => block,
+            Err(reason) => {
+                self.drop_legacy_block(&hash, reason);
+                return Ok(());
+            }
+        };
+        if block.verify_signature(block.account()).is_err() {
+            self.drop_legacy_block(&hash, RejectReason::InvalidSignature);
+            return Ok(());
+        }
+        let threshold = self.network.legacy_difficulty();
+        let difficulty = block
+            .work()
+            .ok_or_else(|| anyhow!("Legacy block {:?} has no work!", hash))?
+            .difficulty(&block.work_root())?;
+        if difficulty < threshold {
+            self.drop_legacy_block(
+                &hash,
+                RejectReason::InsufficientWork {
+                    difficulty: difficulty.as_u64(),
+                    threshold: threshold.as_u64(),
+                },
+            );
+            return Ok(());
+        }
+
+        self.store_block(&block).await?;
+        self.process_state_blocks(self.publishes.take_unchecked(&hash))
+            .await
+    }
+
     async fn process_valid_existing_state_block(
         &self,
         state_block: StateBlock,
     ) -> anyhow::Result<()> {
+        if self
+            .forks
+            .check(&self.state, &self.confirmations, &state_block)
+            .await?
+        {
+            self.drop_block(&state_block, RejectReason::Fork);
+            return Ok(());
+        }
         match &state_block.previous {
             Previous::Block(previous_hash) => {
                 // Either wants to send, receive or change
                 let maybe_previous_block = self.previous_as_account_info(previous_hash).await?;
                 if let Some(previous_state_block) = maybe_previous_block {
-                    self.process_block_with_previous(state_block, previous_state_block)
+                    self.process_block_with_previous(state_block, Some(previous_state_block))
                         .await?
+                } else if self.block_existed(previous_hash).await? {
+                    info!(
+                        "Block before {} isn't the frontier of a state chain!",
+                        state_block
+                    )
                 } else {
-                    info!("Block before {} not found!", state_block)
+                    debug!(
+                        "Block before {} not found, adding to unchecked",
+                        state_block
+                    );
+                    self.publishes
+                        .add_unchecked(&previous_hash.to_owned(), state_block);
                 }
             }
             Previous::Open => {
-                todo!("Received an open sub-block")
+                let frontier = self
+                    .state
+                    .get_latest_block_hash_for_account(&state_block.account)
+                    .await?;
+                if frontier.is_some() {
+                    self.drop_block(&state_block, RejectReason::Fork);
+                } else {
+                    self.process_block_with_previous(state_block, None).await?
+                }
             }
         }
         Ok(())
     }
 
+    /// Check the amounts of a block on top of `previous_state_block`, which is `None` for an
+    /// open block, and then its work for the subtype.
     async fn process_block_with_previous(
         &self,
         mut state_block: StateBlock,
-        previous_state_block: StateBlock,
+        previous_state_block: Option<StateBlock>,
     ) -> anyhow::Result<()> {
-        let is_send = state_block.balance < previous_state_block.balance;
-        let amount = if is_send {
-            previous_state_block
-                .balance
-                .checked_sub(&state_block.balance)
-        } else {
-            state_block
-                .balance
-                .checked_sub(&previous_state_block.balance)
-        };
-        let amount = amount.ok_or(anyhow!("Could not calculate amount!"))?;
-        state_block
-            .set_link_type(is_send, amount)
+        let subtype = state_block.subtype(previous_state_block.as_ref());
+        state_block.link = state_block
+            .link
+            .for_subtype(subtype)
             .context("Could not decide link type!")?;
-        match state_block.link {
-            Link::Nothing => {
-                let _live_epoch_2_change_threshold = 0xfffffff800000000u64;
-                todo!("Received a change sub-block")
+
+        // An open block receives its whole balance, like a receive on top of an empty account.
+        let previous_state_block = previous_state_block.unwrap_or_else(|| {
+            StateBlock::new(
+                state_block.account.to_owned(),
+                Previous::Open,
+                state_block.representative.to_owned(),
+                Raw::zero(),
+                Link::Nothing,
+            )
+        });
+
+        let checked =
+            intake::check_amounts(&*self.state, &state_block, &previous_state_block).await?;
+        if let Err(reason) = checked {
+            self.drop_block(&state_block, reason);
+            return Ok(());
+        }
+
+        match subtype {
+            Subtype::Change => {
+                self.process_good_sub_block(state_block, self.network.send_difficulty())
+                    .await
             }
-            Link::Source(_) => {
-                let _live_epoch_2_receive_threshold = 0xfffffe0000000000u64;
-                todo!("Received a receive sub-block")
+            Subtype::Epoch => {
+                debug!(
+                    "Skipping epoch block {}, which can't be checked yet",
+                    state_block
+                );
+                Ok(())
             }
-            Link::DestinationAccount(_) => self.process_good_send_sub_block(state_block).await,
-            Link::Unsure(_) => {
-                panic!("Unexpected error! Was `decide_link_type` called on this block?")
+            Subtype::Receive | Subtype::Open => {
+                self.process_good_receive_sub_block(state_block).await
             }
+            Subtype::Send => self.process_good_send_sub_block(state_block).await,
         }
     }
 
     async fn process_good_send_sub_block(&self, send_block: StateBlock) -> anyhow::Result<()> {
-        let live_epoch_2_send_threshold = 0xfffffff800000000u64;
-        let block_difficulty = send_block
+        self.process_good_sub_block(send_block, self.network.send_difficulty())
+            .await
+    }
+
+    async fn process_good_receive_sub_block(
+        &self,
+        receive_block: StateBlock,
+    ) -> anyhow::Result<()> {
+        self.process_good_sub_block(receive_block, self.network.receive_difficulty())
+            .await
+    }
+
+    /// Store a sub-block once its work is over `threshold`.
+    async fn process_good_sub_block(
+        &self,
+        state_block: StateBlock,
+        threshold: Difficulty,
+    ) -> anyhow::Result<()> {
+        let block_difficulty = state_block
             .work
             .as_ref()
-            .ok_or(anyhow!("Send sub-block {} has no work!", &send_block))?
-            .difficulty_block_hash(&send_block.hash)?;
-        let work_ok = block_difficulty >= Difficulty::new(live_epoch_2_send_threshold);
+            .ok_or(anyhow!("Sub-block {} has no work!", &state_block))?
+            .difficulty(&state_block.work_root())?;
+        let work_ok = block_difficulty >= threshold;
         if !work_ok {
-            info!("Send sub-block {} has insufficient difficulty!", send_block);
-            debug!(
-                "Send sub-block {} had difficulty {}",
-                send_block,
-                block_difficulty.as_u64()
+            self.drop_block(
+                &state_block,
+                RejectReason::InsufficientWork {
+                    difficulty: block_difficulty.as_u64(),
+                    threshold: threshold.as_u64(),
+                },
             );
         } else {
-            self.store_block(&Block::from_state_block(&send_block))
+            self.store_block(&Block::from_state_block(&state_block))
                 .await?
             // TODO: Update rep weight cache
-            // TODO: Add to pending transactions
         }
         Ok(())
     }
 
+    fn drop_block(&self, state_block: &StateBlock, reason: RejectReason) {
+        info!("Dropping block {}: {}", state_block, reason);
+        self.dropped.record(&state_block.hash, &reason);
+    }
+
+    fn drop_legacy_block(&self, hash: &BlockHash, reason: RejectReason) {
+        info!("Dropping legacy block {:?}: {}", hash, reason);
+        self.dropped.record(hash, &reason);
+    }
+
     async fn store_block(&self, block: &Block) -> anyhow::Result<()> {
         // 1. if this block already exists, this operation is idempotent (but incurs in resource waste)
         // 2. if this block was added and rolled back this could generate an invalid state
         // 3. if we got a rollback request for this block and it didn't go through because it was missing
         //    this could generate an invalid state
         // 4. ???
-        self.state.lock().await.add_block(block).await
+        process::store_block(&self.state, &self.journal, &self.frontiers, block).await?;
+        self.confirmations.prioritize(block);
+        Ok(())
     }
 
     /// Checks if the block exists in the database _or_ if it existed but was pruned
     async fn block_existed(&self, block_hash: &BlockHash) -> anyhow::Result<bool> {
-        Ok(self
-            .state
-            .lock()
-            .await
-            .get_block_by_hash(block_hash)
-            .await?
-            .is_some())
+        Ok(self.state.get_block_by_hash(block_hash).await?.is_some()
+            || self.state.is_pruned(block_hash).await?)
     }
 
     /// For history nodes this has the same semantics as `Peer::block_existed`
@@ -372,12 +726,12 @@ mod tests {
     use crate::blocks::{Link, Previous, StateBlock};
     use crate::network::Network;
     use crate::node::state::State;
-    use crate::node::MemoryState;
-    use crate::{Raw, Work};
+    use crate::node::test_util::{self, Faucet};
+    use crate::node::{MemoryState, Wire};
+    use crate::{Public, Raw, Work};
     use std::net::SocketAddr;
     use std::str::FromStr;
     use std::sync::Arc;
-    use tokio::sync::Mutex;
 
     fn root_block() -> (StateBlock, Block) {
         let source = Link::Source(
@@ -409,7 +763,7 @@ mod tests {
             root_block.balance().checked_sub(&Raw(200)).unwrap(),
             destination,
         );
-        frontier.work = Some(Work::from_str("8073a2031b9a3a6a").unwrap());
+        frontier.work = Some(Work::from_str("051d7421b5019aa9").unwrap());
         let frontier_block = Block::from_state_block(&frontier);
         (frontier, frontier_block)
     }
@@ -427,12 +781,12 @@ mod tests {
 
     async fn test_peer_with_blocks(blocks: &[&Block]) -> Peer {
         let network = Network::Test;
-        let mut state_raw = MemoryState::new(network);
+        let state_raw = MemoryState::new(network);
         for block in blocks {
-            state_raw.add_block(*block).await.unwrap();
+            state_raw.add_block(block).await.unwrap();
         }
         let test_socket_addr = SocketAddr::from_str("[::1]:1").unwrap();
-        let state = Arc::new(Mutex::new(state_raw));
+        let state = Arc::new(state_raw);
         let (peer, _, _) = Peer::new_with_channels(network, state, test_socket_addr);
         peer
     }
@@ -477,7 +831,8 @@ mod tests {
 
     #[tokio::test]
     async fn should_process_good_send_sub_block_when_block_is_good() {
-        let peer = test_peer_with_blocks(&[]).await;
+        let (_, root_block) = root_block();
+        let peer = test_peer_with_blocks(&[&root_block]).await;
         let good_send_block = good_send_block();
         let good_send_block_hash = good_send_block.hash.clone();
 
@@ -488,7 +843,7 @@ mod tests {
         let block_was_stored = Peer::block_exists(&peer, &good_send_block_hash)
             .await
             .unwrap();
-        assert_eq!(block_was_stored, true)
+        assert!(block_was_stored)
     }
 
     #[tokio::test]
@@ -504,7 +859,7 @@ mod tests {
         let block_was_stored = Peer::block_exists(&peer, &bad_send_block_hash)
             .await
             .unwrap();
-        assert_eq!(block_was_stored, false)
+        assert!(!block_was_stored)
     }
 
     #[tokio::test]
@@ -513,12 +868,12 @@ mod tests {
         let (frontier, _) = frontier_block();
         let peer = test_peer_with_blocks(&[&root_block]).await;
 
-        Peer::process_block_with_previous(&peer, frontier.clone(), root)
+        Peer::process_block_with_previous(&peer, frontier.clone(), Some(root))
             .await
             .unwrap();
 
         let block_was_stored = Peer::block_exists(&peer, &frontier.hash).await.unwrap();
-        assert_eq!(block_was_stored, true)
+        assert!(block_was_stored)
     }
 
     #[tokio::test]
@@ -531,6 +886,239 @@ mod tests {
             .unwrap();
 
         let block_was_stored = Peer::block_exists(&peer, &frontier.hash).await.unwrap();
-        assert_eq!(block_was_stored, false)
+        assert!(!block_was_stored)
+    }
+
+    #[tokio::test]
+    async fn should_skip_own_blocks_echoed_back() {
+        let (frontier, _) = frontier_block();
+        let peer = test_peer_with_blocks(&[]).await;
+        peer.own_blocks.add(&frontier.hash);
+
+        Peer::state_block_handler(&peer, frontier.clone())
+            .await
+            .unwrap();
+
+        // It didn't get as far as the publish cache.
+        assert!(peer.publishes.first_sighting(&frontier.hash));
+        assert_eq!(
+            peer.own_blocks.echoed(&frontier.hash).await,
+            Some(peer.peer_addr)
+        );
+    }
+
+    #[tokio::test]
+    async fn should_vote_for_blocks_we_have() {
+        use crate::node::messages::confirm_ack::Confirm;
+        use crate::node::messages::confirm_req::RootHashPair;
+        use crate::node::voting::Voter;
+        use crate::node::wire::Wire;
+        use crate::Seed;
+
+        let (root, root_block) = root_block();
+        let (frontier, _) = frontier_block();
+        let state = Arc::new(MemoryState::new(Network::Test));
+        state.add_block(&root_block).await.unwrap();
+        let (mut peer, _tx, mut rx) = Peer::new_with_channels(
+            Network::Test,
+            state,
+            SocketAddr::from_str("[::1]:1").unwrap(),
+        );
+        let voter = Voter::new(Seed::zero().derive(0), None).unwrap();
+        peer.voter = Some(voter.clone());
+
+        let pair = |block: &StateBlock| RootHashPair {
+            hash: block.hash.to_owned(),
+            root: block.root(),
+        };
+        let confirm_req = ConfirmReq::by_hash(vec![pair(&root), pair(&frontier)]);
+        let header = Header::new(
+            Network::Test,
+            MessageType::ConfirmReq,
+            confirm_req.extensions(),
+        );
+        peer.handle_confirm_req(&header, confirm_req).await.unwrap();
+
+        let header = Header::deserialize(None, &rx.recv().await.unwrap().data).unwrap();
+        assert_eq!(header.message_type(), MessageType::ConfirmAck);
+        let data = rx.recv().await.unwrap().data;
+        let confirm_ack = ConfirmAck::deserialize(Some(&header), &data).unwrap();
+        confirm_ack.verify_signature().unwrap();
+        assert_eq!(&confirm_ack.account, voter.account());
+        match confirm_ack.confirm {
+            // Only the block that we have.
+            Confirm::VoteByHash(hashes) => assert_eq!(hashes, vec![root.hash]),
+            other => panic!("Unexpected {:?}", other),
+        }
+    }
+
+    /// Publish a state block to `peer` as it'd arrive from the wire.
+    async fn publish(peer: &mut Peer, block: &Block) {
+        let holder = BlockHolder::State(StateBlock::from(block.to_owned()));
+        let mut ext = Extensions::new();
+        ext.set_block_type(BlockType::State);
+        let header = Header::new(Network::Test, MessageType::Publish, ext);
+        let publish = Publish::deserialize(Some(&header), &holder.serialize()).unwrap();
+        peer.handle_publish(&header, publish).await.unwrap();
+    }
+
+    /// A block of the test account at 1, which opens with 1 raw from the faucet, signed and
+    /// with work at `threshold`.
+    fn landing_block(
+        previous: Previous,
+        representative: u32,
+        link: Link,
+        threshold: &Difficulty,
+    ) -> StateBlock {
+        let private = test_util::private(1);
+        let mut block = StateBlock::new(
+            test_util::account(1),
+            previous,
+            test_util::account(representative),
+            Raw::from(1),
+            link,
+        );
+        block.signature = Some(private.sign(block.hash.as_bytes()).unwrap());
+        block.work = Some(Work::generate(&block.work_root(), threshold).unwrap());
+        block
+    }
+
+    #[tokio::test]
+    async fn should_process_published_open_blocks() {
+        let mut faucet = Faucet::new();
+        let open = faucet.chain(1, 1).remove(0);
+        let blocks: Vec<&Block> = faucet.blocks.iter().collect();
+        let mut peer = test_peer_with_blocks(&blocks).await;
+
+        let source = Link::Source(faucet.blocks[1].hash().unwrap().to_owned());
+        let inflated = test_util::worked(&test_util::private(1), None, 2, source.to_owned());
+        publish(&mut peer, &inflated).await;
+        publish(&mut peer, &open).await;
+        let threshold = Network::Test.receive_difficulty();
+        let reopen = landing_block(Previous::Open, 2, source, &threshold);
+        publish(&mut peer, &Block::from_state_block(&reopen)).await;
+
+        assert!(peer.block_exists(open.hash().unwrap()).await.unwrap());
+        assert!(!peer.block_exists(inflated.hash().unwrap()).await.unwrap());
+        assert!(!peer.block_exists(&reopen.hash).await.unwrap());
+        let dropped = peer.dropped.snapshot().counts;
+        assert_eq!(dropped.get("receive_amount_mismatch"), Some(&1));
+        assert_eq!(dropped.get("fork"), Some(&1));
+        assert_eq!(
+            peer.account_balance(&test_util::account(1)).await.unwrap(),
+            Raw::from(1)
+        );
+    }
+
+    #[tokio::test]
+    async fn should_process_published_change_blocks() {
+        let mut faucet = Faucet::new();
+        let open = faucet.chain(1, 1).remove(0);
+        let mut blocks: Vec<&Block> = faucet.blocks.iter().collect();
+        blocks.push(&open);
+        let mut peer = test_peer_with_blocks(&blocks).await;
+
+        let previous = Previous::Block(open.hash().unwrap().to_owned());
+        let link = Link::unsure_from_str(&"0".repeat(64)).unwrap();
+
+        // Enough work for a receive, but a change needs as much as a send.
+        let threshold = Network::Test.send_difficulty();
+        let receive_threshold = Network::Test.receive_difficulty();
+        let mut low_work = landing_block(previous.to_owned(), 2, link.to_owned(), &threshold);
+        low_work.work = (0u64..)
+            .map(|nonce| Work::from_le_bytes(&nonce.to_le_bytes()).unwrap())
+            .find(|work| {
+                let difficulty = work.difficulty(&low_work.work_root()).unwrap();
+                difficulty >= receive_threshold && difficulty < threshold
+            });
+        publish(&mut peer, &Block::from_state_block(&low_work)).await;
+        let change = landing_block(previous, 3, link, &threshold);
+        publish(&mut peer, &Block::from_state_block(&change)).await;
+
+        assert!(!peer.block_exists(&low_work.hash).await.unwrap());
+        assert!(peer.block_exists(&change.hash).await.unwrap());
+        assert_eq!(
+            peer.dropped.snapshot().counts.get("insufficient_work"),
+            Some(&1)
+        );
+        assert_eq!(
+            peer.account_balance(&test_util::account(1)).await.unwrap(),
+            Raw::from(1)
+        );
+    }
+
+    /// Real blocks from the start of the live network: the genesis account sends to a new
+    /// account, which opens and then sends some of it on.
+    #[tokio::test]
+    async fn should_process_legacy_blocks_from_the_wire() {
+        let network = Network::Live;
+        let genesis = network.genesis_block();
+        let state = MemoryState::new(network);
+        state.add_block(&genesis).await.unwrap();
+        let state = Arc::new(state);
+        let (mut peer, _, _) =
+            Peer::new_with_channels(network, state, SocketAddr::from_str("[::1]:1").unwrap());
+
+        let gen_send = BlockHolder::Send(serde_json::from_str(r#"{
+            "previous": "991CF190094C00F0B68E2E5F75F6BEE95A2E0BD93CEAA4A6734DB9F19B728948",
+            "destination": "nano_13ezf4od79h1tgj9aiu4djzcmmguendtjfuhwfukhuucboua8cpoihmh8byo",
+            "balance": "FD89D89D89D89D89D89D89D89D89D89D",
+            "work": "3c82cc724905ee95",
+            "signature": "5B11B17DB9C8FE0CC58CAC6A6EECEF9CB122DA8A81C6D3DB1B5EE3AB065AA8F8CB1D6765C8EB91B58530C5FF5987AD95E6D34BB57F44257E20795EE412E61600"
+        }"#).unwrap());
+        let land_open = BlockHolder::Open(serde_json::from_str(r#"{
+            "source": "A170D51B94E00371ACE76E35AC81DC9405D5D04D4CEBC399AEACE07AE05DD293",
+            "representative": "nano_1awsn43we17c1oshdru4azeqjz9wii41dy8npubm4rg11so7dx3jtqgoeahy",
+            "account": "nano_13ezf4od79h1tgj9aiu4djzcmmguendtjfuhwfukhuucboua8cpoihmh8byo",
+            "work": "e997c097a452a1b1",
+            "signature": "E950FFDF0C9C4DAF43C27AE3993378E4D8AD6FA591C24497C53E07A3BC80468539B0A467992A916F0DDA6F267AD764A3C1A5BDBD8F489DFAE8175EEE0E337402"
+        }"#).unwrap());
+        let land_send = BlockHolder::Send(serde_json::from_str(r#"{
+            "previous": "90D0C16AC92DD35814E84BFBCC739A039615D0A42A76EF44ADAEF1D99E9F8A35",
+            "destination": "nano_35jjmmmh81kydepzeuf9oec8hzkay7msr6yxagzxpcht7thwa5bus5tomgz9",
+            "balance": "02761762762762762762762762762762",
+            "work": "6d6d59ca60cab77d",
+            "signature": "434CF7E7B2C2CAA3E3910CC711B29498870636C1247EA8C72BD5C0A7BB15A7BACFEC9CF289B92E4BD56F56E68277B45B3A3FF9339D2547038B87DE38C851B70B"
+        }"#).unwrap());
+
+        let mut tampered = land_send.clone();
+        if let BlockHolder::Send(send) = &mut tampered {
+            send.balance = Raw::from(1);
+        }
+
+        // Publish the blocks as they'd arrive from a peer, tampered one first.
+        for (holder, block_type) in &[
+            (&tampered, BlockType::Send),
+            (&gen_send, BlockType::Send),
+            (&land_open, BlockType::Open),
+            (&land_send, BlockType::Send),
+        ] {
+            let mut ext = Extensions::new();
+            ext.set_block_type(block_type.to_owned());
+            let header = Header::new(network, MessageType::Publish, ext);
+            let publish = Publish::deserialize(Some(&header), &holder.serialize()).unwrap();
+            peer.handle_publish(&header, publish).await.unwrap();
+        }
+
+        for holder in &[&gen_send, &land_open, &land_send] {
+            assert!(peer.block_exists(&holder.hash()).await.unwrap());
+        }
+        assert!(!peer.block_exists(&tampered.hash()).await.unwrap());
+        assert_eq!(
+            peer.dropped.snapshot().counts.get("unknown_previous"),
+            Some(&1)
+        );
+
+        let given = Raw::from(3271945835778254456378601994536232802u128);
+        let landing_account = match &land_open {
+            BlockHolder::Open(open) => open.account.to_owned(),
+            _ => unreachable!(),
+        };
+        assert_eq!(
+            peer.account_balance(&landing_account).await.unwrap(),
+            given
+                .checked_sub(&Raw::from(324518553658426726783156020576256))
+                .unwrap()
+        );
     }
 }
@@ -1,41 +1,66 @@
 mod blocks;
+mod framing;
 mod genesis;
+mod handshake;
 mod messages;
+#[cfg(any(test, feature = "test-util"))]
+pub mod script;
 
 use crate::blocks::Block;
 use crate::encoding::to_hex;
 use crate::network::Network;
+use crate::node::bulk_pull_server::BulkPullServer;
+use crate::node::cache::{MemoryBudget, PublishCache};
+use crate::node::confirmation::ConfirmationTracker;
+use crate::node::events::FrontierEvents;
+use crate::node::forks::ForkWatch;
 use crate::node::header::{Extensions, Header, MessageType};
+use crate::node::intake::DroppedBlocks;
+use crate::node::journal::{Journal, JournalEvent};
+use crate::node::messages::keepalive::KEEPALIVE_INTERVAL;
+use crate::node::own_blocks::OwnBlocks;
+use crate::node::peer_filter::PeerFilter;
 use crate::node::state::ArcState;
+use crate::node::telemetry::{NetworkTelemetry, MAX_SPOOFED, TELEMETRY_INTERVAL};
+use crate::node::voting::Voter;
 use crate::node::wire::Wire;
+use crate::node::wire_dump::WireDump;
 use crate::{Public, Raw};
 use anyhow::{anyhow, Context};
+use bytes::{Buf, Bytes, BytesMut};
+use framing::{FramingError, MAX_STRIKES};
+pub use handshake::EarlyMessages;
+use handshake::{HandshakeState, MAX_EARLY_MESSAGES};
 use std::fmt::Debug;
 use std::net::SocketAddr;
 use tokio::sync::mpsc;
-use tracing::{debug, info, instrument, trace};
+use tracing::{debug, info, instrument, trace, warn};
 
 /// A message sent between channels that contains a peer's network data.
+///
+/// Outgoing data is [Bytes], so that one message can be shared between peers. Incoming data is
+/// [BytesMut], split off the socket's read buffer, so that [Peer] can take it over as its own
+/// buffer instead of copying it.
 #[derive(Debug)]
-pub struct Packet {
+pub struct Packet<B = Bytes> {
     /// Used by pcap to annotate direction and packet number, etc.
     pub annotation: Option<String>,
 
     /// The data sent to/from a peer.
-    pub data: Vec<u8>,
+    pub data: B,
 }
 
-impl Packet {
-    pub fn new(data: Vec<u8>) -> Self {
+impl<B> Packet<B> {
+    pub fn new(data: impl Into<B>) -> Self {
         Self {
-            data,
+            data: data.into(),
             annotation: None,
         }
     }
 
-    pub fn new_with_annotation(data: Vec<u8>, annotation: String) -> Self {
+    pub fn new_with_annotation(data: impl Into<B>, annotation: String) -> Self {
         Self {
-            data,
+            data: data.into(),
             annotation: Some(annotation),
         }
     }
@@ -47,6 +72,9 @@ enum RecvState {
 
     /// Waiting for the payload.
     Payload(Header),
+
+    /// Skipping bytes until the start of a header, after a framing error.
+    Resync,
 }
 
 /// Handles the logic of one peer. It handles and emits messages, as well as time
@@ -55,6 +83,52 @@ pub struct Peer {
     /// Disable when used for pcap dump, where might have our own different cookie.
     pub validate_handshakes: bool,
 
+    /// Probe peers learned from keepalives before adding them to the active set. Disable when
+    /// used for pcap dump, so that we don't connect to peers found in the capture.
+    pub probe_peers: bool,
+
+    /// Our own endpoint, included in the keepalives we send.
+    pub advertise: Option<SocketAddr>,
+
+    /// Where votes from this peer are counted, and where confirm_reqs for it come from.
+    pub confirmations: ConfirmationTracker,
+
+    /// Recently published blocks and the unchecked table, shared with the other peers.
+    pub publishes: PublishCache,
+
+    /// Where blocks from this peer that fail validation are counted, shared with the other peers.
+    pub dropped: DroppedBlocks,
+
+    /// Blocks made by us, which are skipped when this peer floods them back.
+    pub own_blocks: OwnBlocks,
+
+    /// Told about every block that's stored.
+    pub frontiers: FrontierEvents,
+
+    /// Peers learned from keepalives outside of these subnets are ignored.
+    pub peer_filter: PeerFilter,
+
+    /// Log hexdumps of the selected message types, for `--dump-wire`.
+    pub wire_dump: Option<WireDump>,
+
+    /// Where the block counts in this peer's telemetry go, shared with the other peers.
+    pub telemetry: NetworkTelemetry,
+
+    /// What to do with messages that arrive before the handshake is established.
+    pub early_messages: EarlyMessages,
+
+    /// Answers confirm_reqs with our votes, when we're a representative.
+    pub voter: Option<Voter>,
+
+    /// Where blocks we add and why we disconnect are recorded, shared with the other peers.
+    pub journal: Journal,
+
+    /// Limits the bulk pulls served to each address, shared with the other peers.
+    pub bulk_pulls: BulkPullServer,
+
+    /// Alerts on published blocks that fork a watched account, shared with the other peers.
+    pub forks: ForkWatch,
+
     network: Network,
     state: ArcState,
     peer_addr: SocketAddr,
@@ -64,15 +138,33 @@ pub struct Peer {
     frontier_stream: bool,
 
     /// Internal buffer for incoming data.
-    incoming_buffer: Vec<u8>,
+    incoming_buffer: BytesMut,
+
+    /// Outgoing messages are serialized into this buffer, which is split off and sent as a
+    /// [Packet]. The allocation is reused once the packet has been written out and dropped.
+    outgoing_buffer: BytesMut,
 
     /// Incoming data from the connected peer.
-    peer_rx: mpsc::Receiver<Packet>,
+    peer_rx: mpsc::Receiver<Packet<BytesMut>>,
 
     /// Data to be sent to the other peer.
     peer_tx: mpsc::Sender<Packet>,
 
     last_annotation: Option<String>,
+
+    /// Framing errors so far. See [framing].
+    strikes: usize,
+
+    /// Telemetry acks that weren't signed by the node from the handshake. See [telemetry].
+    ///
+    /// [telemetry]: crate::node::telemetry
+    spoofed_telemetry: usize,
+
+    /// See [handshake].
+    handshake: HandshakeState,
+
+    /// Messages held back by [EarlyMessages::Queue], with their headers.
+    early: Vec<Bytes>,
 }
 
 impl Peer {
@@ -80,23 +172,43 @@ impl Peer {
         network: Network,
         state: ArcState,
         peer_addr: SocketAddr,
-    ) -> (Self, mpsc::Sender<Packet>, mpsc::Receiver<Packet>) {
+    ) -> (Self, mpsc::Sender<Packet<BytesMut>>, mpsc::Receiver<Packet>) {
         // Packets coming in from a remote host.
-        let (incoming_tx, incoming_rx) = mpsc::channel::<Packet>(100);
+        let (incoming_tx, incoming_rx) = mpsc::channel::<Packet<BytesMut>>(100);
         // Packets to be sent out to a remote host.
         let (outgoing_tx, outgoing_rx) = mpsc::channel::<Packet>(100);
 
         let s = Self {
             validate_handshakes: true,
+            probe_peers: true,
+            advertise: None,
+            confirmations: ConfirmationTracker::new(network),
+            publishes: PublishCache::new(&MemoryBudget::default()),
+            dropped: DroppedBlocks::default(),
+            own_blocks: OwnBlocks::new(),
+            frontiers: FrontierEvents::new(),
+            peer_filter: PeerFilter::default(),
+            wire_dump: None,
+            telemetry: NetworkTelemetry::new(),
+            early_messages: EarlyMessages::Queue,
+            voter: None,
+            journal: Journal::disabled(),
+            bulk_pulls: BulkPullServer::default(),
+            forks: ForkWatch::new(),
             network,
             state,
             peer_addr,
             recv_state: RecvState::Header,
             frontier_stream: false,
-            incoming_buffer: Vec::with_capacity(10_000),
+            incoming_buffer: BytesMut::new(),
+            outgoing_buffer: BytesMut::with_capacity(1_000),
             peer_rx: incoming_rx,
             peer_tx: outgoing_tx,
             last_annotation: None,
+            strikes: 0,
+            spoofed_telemetry: 0,
+            handshake: HandshakeState::new(),
+            early: vec![],
         };
 
         (s, incoming_tx, outgoing_rx)
@@ -106,25 +218,112 @@ impl Peer {
     /// is closed.
     #[instrument(name = "node", skip(self), fields(address = %self.peer_addr))]
     pub async fn run(mut self) -> anyhow::Result<()> {
-        trace!("Initial handshake");
-        self.send_handshake().await?;
+        let result = self.serve().await;
+        self.confirmations.remove_peer(&self.peer_addr);
+        if let Err(err) = &result {
+            self.journal.record(JournalEvent::PeerDisconnected {
+                address: self.peer_addr,
+                reason: format!("{:#}", err),
+            });
+        }
+        trace!("Disconnecting peer");
+        result
+    }
+
+    async fn serve(&mut self) -> anyhow::Result<()> {
+        self.greet().await?;
 
-        // TODO: Send and handle telemetry
-        // trace!("Initial telemetry request");
-        // self.send_telemetry_req().await?;
+        let confirm_reqs = self
+            .confirmations
+            .add_peer(self.peer_addr, self.peer_tx.clone());
+        for packet in confirm_reqs {
+            self.peer_tx
+                .send(packet)
+                .await
+                .context("Sending pending confirm_req")?;
+        }
 
-        while let Some(packet) = self.peer_rx.recv().await {
-            self.handle_packet(packet).await?;
+        let mut telemetry = tokio::time::interval(TELEMETRY_INTERVAL);
+        // The first tick is straight away, and the first request is sent with the handshake.
+        telemetry.tick().await;
+        // Likewise the first keepalive is sent when greeting.
+        let mut keepalive = tokio::time::interval(KEEPALIVE_INTERVAL);
+        keepalive.tick().await;
+        loop {
+            tokio::select! {
+                packet = self.peer_rx.recv() => match packet {
+                    Some(packet) => self.handle_packet(packet).await?,
+                    None => break,
+                },
+                _ = telemetry.tick() => {
+                    if self.handshake.is_established() {
+                        self.send_telemetry_req().await?;
+                    }
+                }
+                _ = keepalive.tick() => self.send_keepalive().await?,
+            }
         }
-        trace!("Disconnecting peer");
+        Ok(())
+    }
+
+    /// The messages sent to a peer as soon as we're connected.
+    async fn greet(&mut self) -> anyhow::Result<()> {
+        trace!("Initial handshake");
+        self.send_handshake().await?;
+
+        trace!("Initial keepalive");
+        self.send_keepalive().await?;
 
         Ok(())
     }
 
     #[instrument(skip(self, packet))]
-    async fn handle_packet(&mut self, packet: Packet) -> anyhow::Result<()> {
+    async fn handle_packet(&mut self, packet: Packet<BytesMut>) -> anyhow::Result<()> {
         trace!("handle_packet");
 
+        if let Some(annotation) = packet.annotation {
+            self.last_annotation = Some(annotation);
+        }
+        // Takes the packet over when everything before it has been handled, and joins it back on
+        // when it follows straight on in the same read buffer. Only a partial message left over
+        // from a different read buffer is copied.
+        self.incoming_buffer.unsplit(packet.data);
+
+        // TODO: Handle frontier stream
+        // if self.frontier_stream {
+        //     let payload = self.recv::<FrontierResp>(None).await?;
+        //     self.handle_frontier_resp(payload).await?;
+        // } else {
+
+        loop {
+            let (new_state, process) = match self.step().await {
+                Ok(step) => step,
+                Err(err) => match err.downcast_ref::<FramingError>() {
+                    Some(framing) => {
+                        let state = if framing.needs_resync() {
+                            RecvState::Resync
+                        } else {
+                            RecvState::Header
+                        };
+                        self.strike(&err)?;
+                        (state, true)
+                    }
+                    None => return Err(err),
+                },
+            };
+            self.recv_state = new_state;
+            if !process {
+                break;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Handle the next header or payload from the incoming buffer. Returns the state to move to,
+    /// and whether there might be more to handle.
+    async fn step(&mut self) -> anyhow::Result<(RecvState, bool)> {
+        // Evaluates to true if the payload was handled, or false if there aren't enough bytes yet.
         macro_rules! handle {
             ($self: ident, $fun:ident, $header:expr) => {{
                 let sh = Some(&$header);
@@ -142,68 +341,142 @@ impl Peer {
                         .$fun(&$header, payload)
                         .await
                         .with_context(|| format!("Handling payload for {:?}", $header))?;
+                    true
                 } else {
+                    false
                 }
-            };};
-        }
-
-        if let Some(annotation) = packet.annotation {
-            self.last_annotation = Some(annotation);
+            }};
         }
-        self.incoming_buffer.extend(packet.data);
-
-        // TODO: Handle frontier stream
-        // if self.frontier_stream {
-        //     let payload = self.recv::<FrontierResp>(None).await?;
-        //     self.handle_frontier_resp(payload).await?;
-        // } else {
 
-        loop {
-            let (new_state, process) = match self.recv_state {
-                RecvState::Header => {
-                    if let Some(header) = self.recv::<Header>(None)? {
-                        header.validate(&self.network)?;
-                        (RecvState::Payload(header), true)
-                    } else {
-                        (RecvState::Header, false)
+        Ok(match self.recv_state {
+            RecvState::Header => {
+                // Without the magic number it's not a header, so don't throw away what might be
+                // the start of the next one by reading a whole header's worth of bytes.
+                if let Some(&first) = self.incoming_buffer.first() {
+                    if Header::find_start(&[first], self.network).is_none() {
+                        return Err(FramingError::BadHeader(anyhow!(
+                            "Expected a header, got 0x{:02X}",
+                            first
+                        ))
+                        .into());
                     }
                 }
-                RecvState::Payload(header) => {
-                    trace!(
-                        "Attempt to handle message of type: {:?}",
-                        header.message_type()
-                    );
-                    match header.message_type() {
-                        MessageType::Keepalive => handle!(self, handle_keepalive, header),
-                        MessageType::Publish => handle!(self, handle_publish, header),
-                        MessageType::ConfirmReq => handle!(self, handle_confirm_req, header),
-                        MessageType::ConfirmAck => handle!(self, handle_confirm_ack, header),
-                        MessageType::FrontierReq => handle!(self, handle_frontier_req, header),
-                        MessageType::Handshake => handle!(self, handle_handshake, header),
-                        MessageType::TelemetryReq => handle!(self, handle_telemetry_req, header),
-                        MessageType::TelemetryAck => handle!(self, handle_telemetry_ack, header),
-                        // MessageType::BulkPull => {}
-                        // MessageType::BulkPush => {}
-                        // MessageType::BulkPullAccount => {}
-                        _ => return Err(anyhow!("Unhandled message: {:?}", header)),
-                    };
+                if let Some(header) = self.recv::<Header>(None)? {
+                    header.validate(&self.network)?;
+                    (RecvState::Payload(header), true)
+                } else {
                     (RecvState::Header, false)
                 }
-            };
-            self.recv_state = new_state;
-            if !process {
-                break;
             }
+            // Bootstrap clients pull without a handshake.
+            RecvState::Payload(header)
+                if !matches!(
+                    header.message_type(),
+                    MessageType::Handshake | MessageType::BulkPull
+                ) && self.early_messages != EarlyMessages::Allow
+                    && !self.handshake.is_established() =>
+            {
+                if self.hold_early(header)? {
+                    (RecvState::Header, true)
+                } else {
+                    (RecvState::Payload(header), false)
+                }
+            }
+            RecvState::Payload(header) => {
+                trace!(
+                    "Attempt to handle message of type: {:?}",
+                    header.message_type()
+                );
+                let handled = match header.message_type() {
+                    MessageType::Keepalive => handle!(self, handle_keepalive, header),
+                    MessageType::Publish => handle!(self, handle_publish, header),
+                    MessageType::ConfirmReq => handle!(self, handle_confirm_req, header),
+                    MessageType::ConfirmAck => handle!(self, handle_confirm_ack, header),
+                    MessageType::FrontierReq => handle!(self, handle_frontier_req, header),
+                    MessageType::Handshake => handle!(self, handle_handshake, header),
+                    MessageType::TelemetryReq => handle!(self, handle_telemetry_req, header),
+                    MessageType::TelemetryAck => handle!(self, handle_telemetry_ack, header),
+                    MessageType::BulkPull => handle!(self, handle_bulk_pull, header),
+                    // MessageType::BulkPush => {}
+                    // MessageType::BulkPullAccount => {}
+                    _ => return Err(anyhow!("Unhandled message: {:?}", header)),
+                };
+                if handled {
+                    // There might be another message waiting in the buffer.
+                    (RecvState::Header, true)
+                } else {
+                    (RecvState::Payload(header), false)
+                }
+            }
+            RecvState::Resync => match Header::find_start(&self.incoming_buffer, self.network) {
+                Some(start) => {
+                    self.incoming_buffer.advance(start);
+                    (RecvState::Header, true)
+                }
+                None => {
+                    self.incoming_buffer.clear();
+                    (RecvState::Resync, false)
+                }
+            },
+        })
+    }
+
+    /// Count a framing error against the peer, failing once it has made too many.
+    fn strike(&mut self, err: &anyhow::Error) -> anyhow::Result<()> {
+        self.strikes += 1;
+        warn!(
+            "Framing error {} of {}: {:#}",
+            self.strikes, MAX_STRIKES, err
+        );
+        if self.strikes >= MAX_STRIKES {
+            return Err(anyhow!(
+                "Disconnecting after {} framing errors, the last being: {:#}",
+                self.strikes,
+                err
+            ));
         }
+        Ok(())
+    }
 
+    pub fn strikes(&self) -> usize {
+        self.strikes
+    }
+
+    /// Count spoofed telemetry against the peer, failing once it has sent too much.
+    fn spoofed(&mut self, reason: &str) -> anyhow::Result<()> {
+        self.spoofed_telemetry += 1;
+        warn!(
+            "Spoofed telemetry {} of {}: {}",
+            self.spoofed_telemetry, MAX_SPOOFED, reason
+        );
+        if self.spoofed_telemetry >= MAX_SPOOFED {
+            return Err(anyhow!(
+                "Disconnecting after {} spoofed telemetry acks, the last being: {}",
+                self.spoofed_telemetry,
+                reason
+            ));
+        }
         Ok(())
     }
 
+    pub fn spoofed_telemetry(&self) -> usize {
+        self.spoofed_telemetry
+    }
+
     /// Receive from the incoming buffer for type `T`. Will return None if there aren't enough
     /// bytes available.
     #[instrument(skip(self, header))]
     fn recv<T: Wire + Debug>(&mut self, header: Option<&Header>) -> anyhow::Result<Option<T>> {
-        let bytes = T::len(header)?;
+        let bytes = T::len(header).map_err(|source| match header {
+            Some(header) => FramingError::UnknownLength {
+                header: *header,
+                source,
+            },
+            None => FramingError::BadHeader(source),
+        })?;
+        if let Some(header) = header {
+            check_len(header, bytes)?;
+        }
         if self.incoming_buffer.len() < bytes {
             trace!(
                 "Not enough bytes. Got {}, expected {}.",
@@ -213,32 +486,95 @@ impl Peer {
             return Ok(None);
         }
 
-        let buffer = self.incoming_buffer[0..bytes].to_owned();
-        self.incoming_buffer = Vec::from(&self.incoming_buffer[bytes..]);
+        let buffer = self.incoming_buffer.split_to(bytes);
         trace!("HEX: {}", to_hex(&buffer));
-        let result = T::deserialize(header, &buffer)?;
+        if let (Some(dump), Some(header)) = (&self.wire_dump, header) {
+            if let Some(dump) = dump.received(header, &buffer) {
+                info!("Received {}", dump);
+            }
+        }
+        let result = T::deserialize(header, &buffer).map_err(|source| match header {
+            Some(header) => FramingError::BadPayload {
+                header: *header,
+                source,
+            },
+            None => FramingError::BadHeader(source),
+        })?;
         Ok(Some(result))
     }
 
-    fn recv_immediate(&mut self, size: usize) -> anyhow::Result<Vec<u8>> {
-        debug_assert!(self.incoming_buffer.len() >= size);
+    /// Queue or drop a message that arrived before the handshake was established, according to
+    /// [Peer::early_messages]. Returns false if the payload hasn't all arrived yet.
+    fn hold_early(&mut self, header: Header) -> anyhow::Result<bool> {
+        let len = payload_len(&header)
+            .ok_or_else(|| anyhow!("Unhandled message: {:?}", header))?
+            .map_err(|source| FramingError::UnknownLength { header, source })?;
+        check_len(&header, len)?;
+        if self.incoming_buffer.len() < len {
+            return Ok(false);
+        }
+        let payload = self.incoming_buffer.split_to(len);
+
+        match self.early_messages {
+            EarlyMessages::Queue => {
+                if self.early.len() >= MAX_EARLY_MESSAGES {
+                    return Err(anyhow!(
+                        "Disconnecting after {} messages without a handshake",
+                        self.early.len() + 1
+                    ));
+                }
+                debug!(
+                    "Queueing {:?} until the handshake is established",
+                    header.message_type()
+                );
+                let mut message = BytesMut::with_capacity(Header::LEN + payload.len());
+                header.serialize_into(&mut message);
+                message.extend_from_slice(&payload);
+                self.early.push(message.freeze());
+            }
+            EarlyMessages::Reject | EarlyMessages::Allow => warn!(
+                "Dropping {:?} received before the handshake was established",
+                header.message_type()
+            ),
+        }
+        Ok(true)
+    }
 
-        // This is super inefficient. Need to use something like
-        // https://crates.io/crates/slice-deque
-        // Might not work in wasm later.
+    /// Put the queued early messages back in front of whatever else has arrived, to be handled
+    /// now that the handshake is established.
+    fn release_early(&mut self) {
+        if self.early.is_empty() {
+            return;
+        }
+        let mut buffer = BytesMut::with_capacity(self.incoming_buffer.capacity());
+        for message in self.early.drain(..) {
+            buffer.extend_from_slice(&message);
+        }
+        buffer.extend_from_slice(&self.incoming_buffer);
+        self.incoming_buffer = buffer;
+    }
 
-        let buf = self.incoming_buffer[0..size].to_owned();
-        self.incoming_buffer = Vec::from(&self.incoming_buffer[size..]);
-        Ok(buf)
+    /// The node ID of the peer, once the handshake is established.
+    pub fn node_id(&self) -> Option<&Public> {
+        self.handshake.node_id()
+    }
+
+    fn recv_immediate(&mut self, size: usize) -> anyhow::Result<Bytes> {
+        debug_assert!(self.incoming_buffer.len() >= size);
+        Ok(self.incoming_buffer.split_to(size).freeze())
     }
 
     #[instrument(skip(self, message))]
     async fn send<T: Wire + Debug>(&mut self, message: &T) -> anyhow::Result<()> {
-        let data = message.serialize();
+        message.serialize_into(&mut self.outgoing_buffer);
+        let data = self.outgoing_buffer.split().freeze();
         trace!("HEX {}", to_hex(&data));
         debug!("OBJ {:?}", &message);
+        if let Some(dump) = self.wire_dump.as_mut().and_then(|dump| dump.sent(&data)) {
+            info!("Sent {}", dump);
+        }
         self.peer_tx
-            .send(Packet::new(Vec::from(data)))
+            .send(Packet::new(data))
             .await
             .with_context(|| format!("Sending to peer: {:?}", &message))?;
         Ok(())
@@ -252,7 +588,10 @@ impl Peer {
     ) -> anyhow::Result<()> {
         let header = Header::new(self.network, message_type, ext);
         trace!("{:?}", header);
-        Ok(self.send(&header).await.context("Sending header")?)
+        if let Some(dump) = &mut self.wire_dump {
+            dump.sending(&header);
+        }
+        self.send(&header).await.context("Sending header")
     }
 
     /// Set up the genesis block if it hasn't already.
@@ -285,6 +624,47 @@ impl Peer {
     }
 }
 
+/// Payloads over the limit for their message type are a framing error.
+fn check_len(header: &Header, len: usize) -> Result<(), FramingError> {
+    let max = header.message_type().max_payload_len();
+    if len > max {
+        return Err(FramingError::TooLong {
+            message_type: header.message_type(),
+            len,
+            max,
+        });
+    }
+    Ok(())
+}
+
+/// The payload length of a message, or `None` for a type that peers don't handle.
+fn payload_len(header: &Header) -> Option<anyhow::Result<usize>> {
+    use crate::node::messages::bulk_pull::BulkPull;
+    use crate::node::messages::confirm_ack::ConfirmAck;
+    use crate::node::messages::confirm_req::ConfirmReq;
+    use crate::node::messages::frontier_req::FrontierReq;
+    use crate::node::messages::handshake::Handshake;
+    use crate::node::messages::keepalive::Keepalive;
+    use crate::node::messages::publish::Publish;
+    use crate::node::messages::telemetry_ack::TelemetryAck;
+    use crate::node::messages::telemetry_req::TelemetryReq;
+
+    let message_type = header.message_type();
+    let header = Some(header);
+    Some(match message_type {
+        MessageType::Keepalive => Keepalive::len(header),
+        MessageType::Publish => Publish::len(header),
+        MessageType::ConfirmReq => ConfirmReq::len(header),
+        MessageType::ConfirmAck => ConfirmAck::len(header),
+        MessageType::FrontierReq => FrontierReq::len(header),
+        MessageType::Handshake => Handshake::len(header),
+        MessageType::TelemetryReq => TelemetryReq::len(header),
+        MessageType::TelemetryAck => TelemetryAck::len(header),
+        MessageType::BulkPull => BulkPull::len(header),
+        MessageType::BulkPush | MessageType::BulkPullAccount => return None,
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -295,10 +675,9 @@ mod tests {
     use std::net::{Ipv4Addr, SocketAddrV4};
     use std::str::FromStr;
     use std::sync::Arc;
-    use tokio::sync::Mutex;
 
     async fn empty_lattice(network: Network) -> Peer {
-        let state = Arc::new(Mutex::new(MemoryState::new(network)));
+        let state = Arc::new(MemoryState::new(network));
         let (mut peer, _rx, _tx) = Peer::new_with_channels(
             network,
             state,
@@ -308,6 +687,65 @@ mod tests {
         peer
     }
 
+    #[tokio::test]
+    async fn send_reuses_buffer() {
+        let network = Network::Live;
+        let state = Arc::new(MemoryState::new(network));
+        let (mut peer, _tx, mut rx) = Peer::new_with_channels(
+            network,
+            state,
+            SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::LOCALHOST, DEFAULT_PORT)),
+        );
+
+        let start = peer.outgoing_buffer.as_ptr() as usize;
+        let end = start + peer.outgoing_buffer.capacity();
+        for _ in 0..1000 {
+            peer.send_header(MessageType::TelemetryReq, Extensions::new())
+                .await
+                .unwrap();
+            let packet = rx.recv().await.unwrap();
+            assert_eq!(packet.data.len(), Header::LEN);
+
+            // Packets are dropped after sending, so every message should be written into the same
+            // allocation.
+            let ptr = packet.data.as_ptr() as usize;
+            assert!(ptr >= start && ptr < end);
+        }
+    }
+
+    #[tokio::test]
+    async fn recv_takes_over_packets() {
+        let network = Network::Live;
+        let state = Arc::new(MemoryState::new(network));
+        let (mut peer, _tx, _rx) = Peer::new_with_channels(
+            network,
+            state,
+            SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::LOCALHOST, DEFAULT_PORT)),
+        );
+        peer.early_messages = EarlyMessages::Allow;
+
+        // Like the socket reader, split packets off one read buffer, with the messages straddling
+        // the packets.
+        let mut read_buffer = BytesMut::with_capacity(1000);
+        for _ in 0..3 {
+            Header::new(network, MessageType::TelemetryReq, Extensions::new())
+                .serialize_into(&mut read_buffer);
+        }
+        let start = read_buffer.as_ptr() as usize;
+        let end = start + read_buffer.capacity();
+        while !read_buffer.is_empty() {
+            let len = read_buffer.len().min(5);
+            peer.handle_packet(Packet::new(read_buffer.split_to(len)))
+                .await
+                .unwrap();
+
+            // Whatever is left over is still in the read buffer, rather than a copy of it.
+            let ptr = peer.incoming_buffer.as_ptr() as usize;
+            assert!(ptr >= start && ptr <= end);
+        }
+        assert!(peer.incoming_buffer.is_empty());
+    }
+
     #[tokio::test]
     async fn genesis() {
         let network = Network::Live;
@@ -363,7 +801,7 @@ mod tests {
 
         // The genesis account has a reduced amount because they've created a send block.
         assert_eq!(
-            peer.account_balance(&genesis.account()).await.unwrap(),
+            peer.account_balance(genesis.account()).await.unwrap(),
             genesis_balance
         );
 
@@ -412,7 +850,7 @@ mod tests {
   }"#).unwrap();
 
         let land_send =
-            Block::from_send_block(&land_send, &landing_account, &land_open.representative());
+            Block::from_send_block(&land_send, &landing_account, land_open.representative());
 
         peer.add_elected_block(&land_send).await.unwrap();
 
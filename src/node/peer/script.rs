@@ -0,0 +1,846 @@
+//! Drives a [Peer] with a scripted sequence of incoming messages and checks what it sends back,
+//! without any sockets or sleeps.
+//!
+//! This is available to other crates with the `test-util` feature.
+//!
+//! ```
+//! use feeless::script::{Extensions, Header, MessageType, ScriptedPeer, Step, Wire};
+//! use feeless::Network;
+//!
+//! # #[tokio::main]
+//! # async fn main() {
+//! let network = Network::Live;
+//! let mut s = ScriptedPeer::new(network).await;
+//! s.greet().await.unwrap();
+//!
+//! let telemetry_req = Header::new(network, MessageType::TelemetryReq, Extensions::new());
+//! s.run(&[
+//!     Step::Sent(MessageType::Handshake),
+//!     Step::Sent(MessageType::Keepalive),
+//!     Step::Recv(telemetry_req.serialize().to_vec()),
+//!     Step::Sent(MessageType::TelemetryAck),
+//!     Step::NothingSent,
+//! ])
+//! .await;
+//! # }
+//! ```
+pub use super::Peer;
+use super::{EarlyMessages, Packet};
+use crate::network::{Network, DEFAULT_PORT};
+pub use crate::node::header::{Extensions, Header, MessageType};
+use crate::node::state::{ArcState, MemoryState};
+pub use crate::node::wire::Wire;
+use bytes::BytesMut;
+use futures::FutureExt;
+use std::net::{Ipv4Addr, SocketAddr, SocketAddrV4};
+use std::sync::Arc;
+use tokio::sync::mpsc;
+
+/// One step of a script. See [ScriptedPeer::run].
+#[derive(Debug)]
+pub enum Step {
+    /// Feed the bytes to the peer as a single packet.
+    Recv(Vec<u8>),
+
+    /// Feed the bytes to the peer and expect an error containing the text.
+    RecvErr(Vec<u8>, &'static str),
+
+    /// Expect the next message the peer sent to be of this type. The payload is skipped.
+    Sent(MessageType),
+
+    /// Expect that the peer hasn't sent anything else.
+    NothingSent,
+}
+
+/// Serialize a header and payload, as a remote peer would send it.
+pub fn message<T: Wire>(
+    network: Network,
+    message_type: MessageType,
+    ext: Extensions,
+    payload: &T,
+) -> Vec<u8> {
+    let mut buf = BytesMut::new();
+    Header::new(network, message_type, ext).serialize_into(&mut buf);
+    payload.serialize_into(&mut buf);
+    buf.to_vec()
+}
+
+pub struct ScriptedPeer {
+    pub peer: Peer,
+
+    /// Packets the peer has sent.
+    outgoing: mpsc::Receiver<Packet>,
+
+    /// Received from `outgoing` but not yet checked.
+    sent: BytesMut,
+
+    // Held so that the peer's incoming channel stays open.
+    _incoming: mpsc::Sender<Packet<BytesMut>>,
+}
+
+impl ScriptedPeer {
+    pub async fn new(network: Network) -> Self {
+        let state = Arc::new(MemoryState::new(network));
+        let mut s = Self::with_state(network, state);
+        s.peer.init().await.unwrap();
+        s
+    }
+
+    /// A peer sharing `state`, such as another connection to the same address. The state is
+    /// expected to have the genesis block already.
+    pub fn with_state(network: Network, state: ArcState) -> Self {
+        let (mut peer, incoming, outgoing) = Peer::new_with_channels(
+            network,
+            state,
+            SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::LOCALHOST, DEFAULT_PORT)),
+        );
+        peer.probe_peers = false;
+        // Most scripts are about a single message, so there's no handshake to wait for.
+        peer.early_messages = EarlyMessages::Allow;
+        Self {
+            peer,
+            outgoing,
+            sent: BytesMut::new(),
+            _incoming: incoming,
+        }
+    }
+
+    /// Have the peer send its initial messages, as it would when a connection is made.
+    pub async fn greet(&mut self) -> anyhow::Result<()> {
+        self.peer.greet().await
+    }
+
+    pub async fn recv(&mut self, data: &[u8]) -> anyhow::Result<()> {
+        self.peer.handle_packet(Packet::new(data)).await
+    }
+
+    /// The next message sent by the peer, which must be of `message_type`.
+    pub fn sent<T: Wire>(&mut self, message_type: MessageType) -> (Header, T) {
+        let header = self.sent_header(message_type);
+        let payload = self.take(T::len(Some(&header)).unwrap());
+        let payload = T::deserialize(Some(&header), &payload).unwrap();
+        (header, payload)
+    }
+
+    /// Check the type of the next message sent by the peer, skipping over its payload.
+    pub fn expect_sent(&mut self, message_type: MessageType) -> Header {
+        let header = self.sent_header(message_type);
+        self.take(payload_len(&header));
+        header
+    }
+
+    /// Everything the peer has sent and that hasn't been checked yet, e.g. pulled blocks that
+    /// don't have headers.
+    pub fn sent_bytes(&mut self) -> BytesMut {
+        self.drain();
+        self.sent.split()
+    }
+
+    pub fn expect_nothing_sent(&mut self) {
+        self.drain();
+        assert!(
+            self.sent.is_empty(),
+            "Expected nothing sent, got {} bytes",
+            self.sent.len()
+        );
+    }
+
+    /// Run through the steps in order, panicking with the index of the first one that fails.
+    pub async fn run(&mut self, steps: &[Step]) {
+        for (idx, step) in steps.iter().enumerate() {
+            match step {
+                Step::Recv(data) => {
+                    if let Err(err) = self.recv(data).await {
+                        panic!("Step {}: {:?} failed: {:?}", idx, step, err);
+                    }
+                }
+                Step::RecvErr(data, expected) => match self.recv(data).await {
+                    Ok(_) => panic!("Step {}: expected an error containing {}", idx, expected),
+                    Err(err) => assert!(
+                        err.chain().any(|e| e.to_string().contains(expected)),
+                        "Step {}: expected an error containing {}, got {:?}",
+                        idx,
+                        expected,
+                        err
+                    ),
+                },
+                Step::Sent(message_type) => {
+                    self.expect_sent(*message_type);
+                }
+                Step::NothingSent => self.expect_nothing_sent(),
+            }
+        }
+    }
+
+    fn sent_header(&mut self, message_type: MessageType) -> Header {
+        let header = self.take(Header::LEN);
+        let header = Header::deserialize(None, &header).unwrap();
+        assert_eq!(header.message_type(), message_type);
+        header
+    }
+
+    fn take(&mut self, len: usize) -> BytesMut {
+        self.drain();
+        assert!(
+            self.sent.len() >= len,
+            "Expected {} bytes sent, only {} available",
+            len,
+            self.sent.len()
+        );
+        self.sent.split_to(len)
+    }
+
+    fn drain(&mut self) {
+        // Everything the peer sends is already in the channel, so there's no need to wait.
+        while let Some(Some(packet)) = self.outgoing.recv().now_or_never() {
+            self.sent.extend_from_slice(&packet.data);
+        }
+    }
+}
+
+fn payload_len(header: &Header) -> usize {
+    super::payload_len(header)
+        .unwrap_or_else(|| panic!("No payload length for {:?}", header.message_type()))
+        .unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blocks::{BlockHash, BlockType, OpenBlock};
+    use crate::node::cookie::Cookie;
+    use crate::node::messages::bulk_pull::BulkPull;
+    use crate::node::messages::frontier_req::FrontierReq;
+    use crate::node::messages::handshake::{Handshake, HandshakeQuery, HandshakeResponse};
+    use crate::node::messages::telemetry_ack::TelemetryAck;
+    use crate::node::messages::telemetry_req::TelemetryReq;
+    use crate::{Public, Seed, Signature};
+    use std::convert::TryFrom;
+
+    fn handshake_query(network: Network, cookie: &Cookie) -> Vec<u8> {
+        message(
+            network,
+            MessageType::Handshake,
+            *Extensions::new().query(),
+            &HandshakeQuery::new(cookie.clone()),
+        )
+    }
+
+    #[tokio::test]
+    async fn greeting_order() {
+        let mut s = ScriptedPeer::new(Network::Live).await;
+        s.greet().await.unwrap();
+        s.run(&[
+            Step::Sent(MessageType::Handshake),
+            Step::Sent(MessageType::Keepalive),
+            Step::NothingSent,
+        ])
+        .await;
+    }
+
+    #[tokio::test]
+    async fn responds_to_handshake_query() {
+        let network = Network::Live;
+        let mut s = ScriptedPeer::new(network).await;
+        let cookie = Cookie::random();
+        s.recv(&handshake_query(network, &cookie)).await.unwrap();
+
+        let (header, handshake) = s.sent::<Handshake>(MessageType::Handshake);
+        assert!(header.ext().is_response());
+        let response = handshake.response.unwrap();
+        response
+            .public
+            .verify(cookie.as_bytes(), &response.signature)
+            .unwrap();
+        s.expect_nothing_sent();
+    }
+
+    /// A query and response from the peer in one message, as the reference node sends them.
+    fn handshake_reply(network: Network, signature: Signature) -> Vec<u8> {
+        let public = Seed::zero().derive(0).to_public().unwrap();
+        handshake_reply_from(network, public, signature)
+    }
+
+    fn handshake_reply_from(network: Network, public: Public, signature: Signature) -> Vec<u8> {
+        let mut data = Header::new(
+            network,
+            MessageType::Handshake,
+            *Extensions::new().query().response(),
+        )
+        .serialize();
+        data.extend(HandshakeQuery::new(Cookie::random()).serialize());
+        data.extend(HandshakeResponse::new(public, signature).serialize());
+        data.to_vec()
+    }
+
+    /// Greet, and return the cookie of our query.
+    async fn greeted(s: &mut ScriptedPeer) -> Cookie {
+        s.greet().await.unwrap();
+        let (_, query) = s.sent::<Handshake>(MessageType::Handshake);
+        s.expect_sent(MessageType::Keepalive);
+        query.query.unwrap().cookie().to_owned()
+    }
+
+    fn sign(cookie: &Cookie) -> Signature {
+        Seed::zero().derive(0).sign(cookie.as_bytes()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn bad_handshake_response() {
+        let network = Network::Live;
+        let mut s = ScriptedPeer::new(network).await;
+        greeted(&mut s).await;
+
+        let signature = Signature::try_from([0u8; Signature::LEN].as_ref()).unwrap();
+        s.run(&[
+            Step::RecvErr(
+                handshake_reply(network, signature),
+                "Invalid signature in handshake response",
+            ),
+            Step::NothingSent,
+        ])
+        .await;
+        assert_eq!(s.peer.node_id(), None);
+    }
+
+    /// A second connection to the same address as `s`.
+    fn reconnect(s: &ScriptedPeer) -> ScriptedPeer {
+        ScriptedPeer::with_state(s.peer.network, s.peer.state.clone())
+    }
+
+    #[tokio::test]
+    async fn replayed_handshake_response() {
+        let network = Network::Live;
+        let mut first = ScriptedPeer::new(network).await;
+        let cookie = greeted(&mut first).await;
+        let reply = handshake_reply(network, sign(&cookie));
+        first.recv(&reply).await.unwrap();
+        assert!(first.peer.node_id().is_some());
+
+        let mut second = reconnect(&first);
+        greeted(&mut second).await;
+        second
+            .run(&[
+                Step::RecvErr(reply, "Invalid signature in handshake response"),
+                Step::NothingSent,
+            ])
+            .await;
+        assert_eq!(second.peer.node_id(), None);
+    }
+
+    #[tokio::test]
+    async fn response_to_a_replaced_cookie() {
+        let network = Network::Live;
+        let mut first = ScriptedPeer::new(network).await;
+        let cookie = greeted(&mut first).await;
+
+        // Another connection to the same address sends a query, replacing our cookie.
+        let mut second = reconnect(&first);
+        greeted(&mut second).await;
+
+        first
+            .run(&[
+                Step::RecvErr(handshake_reply(network, sign(&cookie)), "replaced"),
+                Step::NothingSent,
+            ])
+            .await;
+        assert_eq!(first.peer.node_id(), None);
+    }
+
+    #[tokio::test]
+    async fn node_id_changed_for_address() {
+        let network = Network::Live;
+        let mut first = ScriptedPeer::new(network).await;
+        let cookie = greeted(&mut first).await;
+        first
+            .recv(&handshake_reply(network, sign(&cookie)))
+            .await
+            .unwrap();
+
+        // A valid signature of the new cookie, but by another node ID.
+        let mut second = reconnect(&first);
+        let cookie = greeted(&mut second).await;
+        let other = Seed::zero().derive(1);
+        let reply = handshake_reply_from(
+            network,
+            other.to_public().unwrap(),
+            other.sign(cookie.as_bytes()).unwrap(),
+        );
+        second
+            .run(&[Step::RecvErr(reply, "was established"), Step::NothingSent])
+            .await;
+        assert_eq!(second.peer.node_id(), None);
+
+        // The same node ID is fine.
+        let mut third = reconnect(&first);
+        let cookie = greeted(&mut third).await;
+        third
+            .recv(&handshake_reply(network, sign(&cookie)))
+            .await
+            .unwrap();
+        assert!(third.peer.node_id().is_some());
+    }
+
+    #[tokio::test]
+    async fn response_before_query() {
+        let network = Network::Live;
+        let mut s = ScriptedPeer::new(network).await;
+        let cookie = greeted(&mut s).await;
+        let response = message(
+            network,
+            MessageType::Handshake,
+            *Extensions::new().response(),
+            &HandshakeResponse::new(Seed::zero().derive(0).to_public().unwrap(), sign(&cookie)),
+        );
+        s.run(&[
+            Step::RecvErr(response, "before the peer's query"),
+            Step::NothingSent,
+        ])
+        .await;
+    }
+
+    #[tokio::test]
+    async fn early_messages_are_queued() {
+        let network = Network::Live;
+        let mut s = ScriptedPeer::new(network).await;
+        s.peer.early_messages = EarlyMessages::Queue;
+        let cookie = greeted(&mut s).await;
+
+        let telemetry_req = Header::new(network, MessageType::TelemetryReq, Extensions::new())
+            .serialize()
+            .to_vec();
+        let mut frontier_req = Header::new(network, MessageType::FrontierReq, Extensions::new())
+            .serialize()
+            .to_vec();
+        frontier_req.extend_from_slice(&[0u8; FrontierReq::LEN]);
+
+        s.run(&[
+            Step::Recv(frontier_req),
+            Step::Recv(telemetry_req.clone()),
+            Step::NothingSent,
+        ])
+        .await;
+        assert!(!s.peer.frontier_stream);
+
+        // The queued messages are handled as soon as the handshake is established.
+        s.run(&[
+            Step::Recv(handshake_reply(network, sign(&cookie))),
+            Step::Sent(MessageType::Handshake),
+            Step::Sent(MessageType::TelemetryReq),
+            Step::Sent(MessageType::TelemetryAck),
+            Step::NothingSent,
+        ])
+        .await;
+        assert!(s.peer.frontier_stream);
+        assert_eq!(
+            s.peer.node_id(),
+            Some(&Seed::zero().derive(0).to_public().unwrap())
+        );
+
+        // A late response is ignored, and doesn't change the node id.
+        let late = message(
+            network,
+            MessageType::Handshake,
+            *Extensions::new().response(),
+            &HandshakeResponse::new(Seed::zero().derive(1).to_public().unwrap(), sign(&cookie)),
+        );
+        s.run(&[
+            Step::Recv(late),
+            Step::Recv(telemetry_req),
+            Step::Sent(MessageType::TelemetryAck),
+            Step::NothingSent,
+        ])
+        .await;
+        assert_eq!(
+            s.peer.node_id(),
+            Some(&Seed::zero().derive(0).to_public().unwrap())
+        );
+    }
+
+    #[tokio::test]
+    async fn early_messages_are_rejected() {
+        let network = Network::Live;
+        let mut s = ScriptedPeer::new(network).await;
+        s.peer.early_messages = EarlyMessages::Reject;
+        let cookie = greeted(&mut s).await;
+
+        let mut frontier_req = Header::new(network, MessageType::FrontierReq, Extensions::new())
+            .serialize()
+            .to_vec();
+        frontier_req.extend_from_slice(&[0u8; FrontierReq::LEN]);
+        s.run(&[
+            Step::Recv(frontier_req),
+            Step::Recv(handshake_reply(network, sign(&cookie))),
+            Step::Sent(MessageType::Handshake),
+            Step::Sent(MessageType::TelemetryReq),
+            Step::NothingSent,
+        ])
+        .await;
+        assert!(!s.peer.frontier_stream);
+    }
+
+    /// A telemetry ack from the peer.
+    fn telemetry_ack(network: Network, ack: &TelemetryAck) -> Vec<u8> {
+        message(
+            network,
+            MessageType::TelemetryAck,
+            *Extensions::new().set_telemetry_size(TelemetryAck::LEN),
+            ack,
+        )
+    }
+
+    #[tokio::test]
+    async fn telemetry_from_the_handshake_node() {
+        let network = Network::Live;
+        let mut s = ScriptedPeer::new(network).await;
+        let cookie = greeted(&mut s).await;
+        let private = Seed::zero().derive(0);
+        let genesis = network.genesis_hash();
+        let ack = TelemetryAck::signed(&private, genesis.to_owned(), 500, 400);
+
+        // Before the handshake, and for another network.
+        s.peer.early_messages = EarlyMessages::Allow;
+        s.run(&[
+            Step::Recv(telemetry_ack(network, &ack)),
+            Step::Recv(handshake_reply(network, sign(&cookie))),
+            Step::Sent(MessageType::Handshake),
+            Step::Sent(MessageType::TelemetryReq),
+            Step::Recv(telemetry_ack(
+                network,
+                &TelemetryAck::signed(&private, BlockHash::zero(), 500, 400),
+            )),
+            Step::NothingSent,
+        ])
+        .await;
+        assert_eq!(s.peer.telemetry.estimate(), None);
+        assert_eq!(s.peer.spoofed_telemetry(), 0);
+
+        s.run(&[Step::Recv(telemetry_ack(network, &ack)), Step::NothingSent])
+            .await;
+        let estimate = s.peer.telemetry.estimate().unwrap();
+        assert_eq!(estimate.block_count, 500);
+        assert_eq!(estimate.cemented_count, 400);
+
+        let reports = s.peer.telemetry.reports();
+        assert_eq!(reports.len(), 1);
+        assert_eq!(&reports[0].address, s.peer.peer_addr());
+    }
+
+    #[tokio::test]
+    async fn answers_telemetry_req() {
+        let network = Network::Live;
+        let mut s = ScriptedPeer::new(network).await;
+        s.recv(&handshake_query(network, &Cookie::random()))
+            .await
+            .unwrap();
+        let (_, handshake) = s.sent::<Handshake>(MessageType::Handshake);
+        let node_id = handshake.response.unwrap().public;
+
+        s.recv(&message(
+            network,
+            MessageType::TelemetryReq,
+            Extensions::new(),
+            &TelemetryReq,
+        ))
+        .await
+        .unwrap();
+        let (_, ack) = s.sent::<TelemetryAck>(MessageType::TelemetryAck);
+        ack.verify().unwrap();
+        assert_eq!(
+            ack.node_id, node_id,
+            "Signed by the node ID of the handshake"
+        );
+        assert_eq!(ack.genesis_block, network.genesis_hash());
+        assert_eq!(ack.block_count, 1);
+        s.expect_nothing_sent();
+    }
+
+    #[tokio::test]
+    async fn spoofed_telemetry() {
+        let network = Network::Live;
+        let mut s = ScriptedPeer::new(network).await;
+        let cookie = greeted(&mut s).await;
+        let genesis = network.genesis_hash();
+        s.run(&[
+            Step::Recv(handshake_reply(network, sign(&cookie))),
+            Step::Sent(MessageType::Handshake),
+            Step::Sent(MessageType::TelemetryReq),
+        ])
+        .await;
+
+        // Properly signed, but by another node.
+        let other = TelemetryAck::signed(&Seed::zero().derive(1), genesis.to_owned(), 500, 400);
+        // Our peer's node ID, with the counts changed after signing.
+        let mut tampered =
+            TelemetryAck::signed(&Seed::zero().derive(0), genesis.to_owned(), 500, 400);
+        tampered.block_count = 1_000_000;
+
+        s.run(&[
+            Step::Recv(telemetry_ack(network, &other)),
+            Step::Recv(telemetry_ack(network, &tampered)),
+            Step::NothingSent,
+        ])
+        .await;
+        assert_eq!(s.peer.spoofed_telemetry(), 2);
+        assert_eq!(s.peer.telemetry.estimate(), None);
+
+        s.run(&[Step::RecvErr(
+            telemetry_ack(network, &tampered),
+            "spoofed telemetry",
+        )])
+        .await;
+    }
+
+    #[tokio::test]
+    async fn split_across_packets() {
+        let network = Network::Live;
+        let mut s = ScriptedPeer::new(network).await;
+        let query = handshake_query(network, &Cookie::random());
+        let (a, b) = query.split_at(Header::LEN + 3);
+
+        s.run(&[
+            Step::Recv(a[..3].to_vec()),
+            Step::NothingSent,
+            Step::Recv(a[3..].to_vec()),
+            Step::NothingSent,
+            Step::Recv(b.to_vec()),
+            Step::Sent(MessageType::Handshake),
+            Step::NothingSent,
+        ])
+        .await;
+    }
+
+    #[tokio::test]
+    async fn two_messages_in_one_packet() {
+        let network = Network::Live;
+        let mut s = ScriptedPeer::new(network).await;
+        let cookie = greeted(&mut s).await;
+        let mut data = handshake_query(network, &Cookie::random());
+        data.extend(message(
+            network,
+            MessageType::Handshake,
+            *Extensions::new().response(),
+            &HandshakeResponse::new(Seed::zero().derive(0).to_public().unwrap(), sign(&cookie)),
+        ));
+
+        s.run(&[
+            Step::Recv(data),
+            Step::Sent(MessageType::Handshake),
+            Step::Sent(MessageType::TelemetryReq),
+            Step::NothingSent,
+        ])
+        .await;
+        assert!(s.peer.node_id().is_some());
+    }
+
+    /// Only the first query is answered.
+    #[tokio::test]
+    async fn repeated_query() {
+        let network = Network::Live;
+        let mut s = ScriptedPeer::new(network).await;
+        let mut data = handshake_query(network, &Cookie::random());
+        data.extend(handshake_query(network, &Cookie::random()));
+
+        s.run(&[
+            Step::Recv(data),
+            Step::Sent(MessageType::Handshake),
+            Step::NothingSent,
+        ])
+        .await;
+    }
+
+    #[tokio::test]
+    async fn wrong_network() {
+        let mut s = ScriptedPeer::new(Network::Live).await;
+        let query = handshake_query(Network::Beta, &Cookie::random());
+        s.run(&[Step::RecvErr(query, "network mismatch"), Step::NothingSent])
+            .await;
+    }
+
+    #[tokio::test]
+    async fn frontier_req_starts_stream() {
+        let network = Network::Live;
+        let mut s = ScriptedPeer::new(network).await;
+        let mut data = Header::new(network, MessageType::FrontierReq, Extensions::new())
+            .serialize()
+            .to_vec();
+        data.extend_from_slice(&[0u8; Public::LEN]);
+        data.extend_from_slice(&0u32.to_le_bytes());
+        data.extend_from_slice(&u32::MAX.to_le_bytes());
+
+        s.run(&[Step::Recv(data), Step::NothingSent]).await;
+        assert!(s.peer.frontier_stream);
+    }
+
+    /// A bulk pull with a count that doesn't start with a zero byte, which has a known length
+    /// but can't be deserialized.
+    fn bad_bulk_pull(network: Network) -> Vec<u8> {
+        let header = Header::new(
+            network,
+            MessageType::BulkPull,
+            *Extensions::new().set_count_present(),
+        );
+        let mut data = header.serialize();
+        data.resize(
+            Header::LEN + crate::node::messages::bulk_pull::BulkPull::len(Some(&header)).unwrap(),
+            1,
+        );
+        data.to_vec()
+    }
+
+    #[tokio::test]
+    async fn skips_bad_payload() {
+        let network = Network::Live;
+        let mut s = ScriptedPeer::new(network).await;
+        let mut data = bad_bulk_pull(network);
+        data.extend(handshake_query(network, &Cookie::random()));
+
+        s.run(&[
+            Step::Recv(data),
+            Step::Sent(MessageType::Handshake),
+            Step::NothingSent,
+        ])
+        .await;
+        assert_eq!(s.peer.strikes(), 1);
+    }
+
+    #[tokio::test]
+    async fn resyncs_after_garbage() {
+        let network = Network::Live;
+        let mut s = ScriptedPeer::new(network).await;
+        let mut data = vec![1, 2, 0x52, 0x41, 3];
+        data.extend(handshake_query(network, &Cookie::random()));
+        let (a, b) = data.split_at(8);
+
+        s.run(&[
+            Step::Recv(a.to_vec()),
+            Step::NothingSent,
+            Step::Recv(b.to_vec()),
+            Step::Sent(MessageType::Handshake),
+            Step::NothingSent,
+        ])
+        .await;
+        assert_eq!(s.peer.strikes(), 1);
+    }
+
+    #[tokio::test]
+    async fn too_long_payload() {
+        let network = Network::Live;
+        let mut s = ScriptedPeer::new(network).await;
+        let mut ext = Extensions::new();
+        ext.set_item_count(15);
+        let mut data = Header::new(network, MessageType::ConfirmReq, ext)
+            .serialize()
+            .to_vec();
+        data.extend_from_slice(&[0u8; 15 * 64]);
+        data.extend(handshake_query(network, &Cookie::random()));
+
+        s.run(&[
+            Step::Recv(data),
+            Step::Sent(MessageType::Handshake),
+            Step::NothingSent,
+        ])
+        .await;
+        assert_eq!(s.peer.strikes(), 1);
+    }
+
+    #[tokio::test]
+    async fn disconnects_after_strikes() {
+        let network = Network::Live;
+        let mut s = ScriptedPeer::new(network).await;
+        let data = bad_bulk_pull(network).repeat(crate::node::peer::framing::MAX_STRIKES);
+        s.run(&[Step::RecvErr(data, "framing errors")]).await;
+    }
+
+    fn peer(index: u16) -> SocketAddr {
+        SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::new(10, 0, 0, 1), 1000 + index))
+    }
+
+    #[tokio::test]
+    async fn keepalive_samples_known_peers() {
+        use crate::node::messages::keepalive::Keepalive;
+
+        let mut s = ScriptedPeer::new(Network::Live).await;
+        let known: Vec<SocketAddr> = (0..20).map(peer).collect();
+        s.peer.state.add_peers(&known).await.unwrap();
+
+        s.peer.send_keepalive().await.unwrap();
+        let (_, keepalive) = s.sent::<Keepalive>(MessageType::Keepalive);
+        let sent: std::collections::HashSet<SocketAddr> =
+            keepalive.peers().iter().map(|p| p.socket_addr()).collect();
+        assert_eq!(sent.len(), Keepalive::PEERS);
+        assert!(sent.iter().all(|address| known.contains(address)));
+    }
+
+    #[tokio::test]
+    async fn keepalive_refreshes_known_peers() {
+        use crate::node::messages::keepalive::Keepalive;
+        use crate::node::peer_info::PeerInfo;
+
+        let network = Network::Live;
+        let mut s = ScriptedPeer::new(network).await;
+        let state = s.peer.state.clone();
+        state.add_peers(&[peer(0), peer(1)]).await.unwrap();
+        tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+        let cutoff = chrono::Utc::now();
+
+        // Lists a known peer and one we haven't heard of, which isn't added without a probe.
+        let keepalive = Keepalive::new(vec![PeerInfo::from(peer(0)), PeerInfo::from(peer(2))]);
+        s.recv(&message(
+            network,
+            MessageType::Keepalive,
+            Extensions::new(),
+            &keepalive,
+        ))
+        .await
+        .unwrap();
+        assert_eq!(state.expire_peers(cutoff).await.unwrap(), vec![peer(1)]);
+        let peers = state.peers().await.unwrap();
+        assert_eq!(peers.len(), 1);
+        assert!(peers.contains(&peer(0)));
+    }
+
+    fn bulk_pull(network: Network, start: &Public) -> Vec<u8> {
+        let pull = BulkPull::account(start.to_owned(), None);
+        message(network, MessageType::BulkPull, pull.extensions(), &pull)
+    }
+
+    #[tokio::test]
+    async fn serves_bulk_pulls_without_a_handshake() {
+        let network = Network::Test;
+        let mut s = ScriptedPeer::new(network).await;
+        s.peer.early_messages = EarlyMessages::Reject;
+        let genesis = network.genesis_block();
+        s.recv(&bulk_pull(network, genesis.account()))
+            .await
+            .unwrap();
+
+        let sent = s.sent_bytes();
+        assert_eq!(sent[0], BlockType::Open.as_u8());
+        let open = OpenBlock::deserialize(None, &sent[1..sent.len() - 1]).unwrap();
+        assert_eq!(&open.hash(), genesis.hash().unwrap());
+        assert_eq!(sent[sent.len() - 1], BlockType::NotABlock.as_u8());
+
+        // Nothing is known about this account, so only the end of the stream is sent.
+        let unknown = Seed::zero().derive(0).to_public().unwrap();
+        s.recv(&bulk_pull(network, &unknown)).await.unwrap();
+        assert_eq!(&s.sent_bytes()[..], &[BlockType::NotABlock.as_u8()]);
+    }
+
+    #[tokio::test]
+    async fn refuses_bulk_pulls_over_the_limit() {
+        let network = Network::Test;
+        let mut s = ScriptedPeer::new(network).await;
+        let address = s.peer.peer_addr().ip();
+        let _running = s.peer.bulk_pulls.start(address).unwrap();
+        s.peer.bulk_pulls.max_per_peer = 1;
+        s.run(&[
+            Step::RecvErr(
+                bulk_pull(network, network.genesis_block().account()),
+                "bulk pulls running",
+            ),
+            Step::NothingSent,
+        ])
+        .await;
+    }
+}
@@ -0,0 +1,239 @@
+//! Allow and deny lists of subnets for the peers we connect to.
+//!
+//! The lists come from the `[peers]` section of the config file, e.g.
+//! ```toml
+//! [peers]
+//! allow = ["10.0.0.0/8", "2001:db8::/32"]
+//! deny = ["10.1.2.3"]
+//! ```
+//! A peer in a denied subnet is never dialled or probed. When there is an allow list, only peers
+//! in one of its subnets are. Peers send IPv4 addresses as IPv4 mapped IPv6 addresses, so those
+//! are matched against the IPv4 subnets.
+//!
+//! The config file is checked for changes while the node runs, so the lists can be changed
+//! without a restart. Peers that are already connected stay connected.
+use crate::Config;
+use anyhow::{anyhow, Context};
+use serde::de::Error as _;
+use serde::{Deserialize, Deserializer};
+use std::fmt::{Display, Formatter};
+use std::net::{IpAddr, SocketAddr};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, SystemTime};
+use tracing::{info, warn};
+
+/// How often to check the config file for changes.
+const RELOAD_INTERVAL: Duration = Duration::from_secs(5);
+
+/// A subnet, e.g. `192.168.0.0/16`. A single address is a subnet of just that address.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cidr {
+    network: IpAddr,
+    prefix: u8,
+}
+
+impl Cidr {
+    pub fn contains(&self, ip: &IpAddr) -> bool {
+        match (self.network, canonical(*ip)) {
+            (IpAddr::V4(network), IpAddr::V4(ip)) => {
+                masked(u32::from(network) as u128, self.prefix, 32)
+                    == masked(u32::from(ip) as u128, self.prefix, 32)
+            }
+            (IpAddr::V6(network), IpAddr::V6(ip)) => {
+                masked(u128::from(network), self.prefix, 128)
+                    == masked(u128::from(ip), self.prefix, 128)
+            }
+            _ => false,
+        }
+    }
+}
+
+/// The top `prefix` bits of a `bits` wide address.
+fn masked(address: u128, prefix: u8, bits: u8) -> u128 {
+    if prefix == 0 {
+        return 0;
+    }
+    address >> (bits - prefix)
+}
+
+/// IPv4 mapped IPv6 addresses as IPv4.
+fn canonical(ip: IpAddr) -> IpAddr {
+    match ip {
+        IpAddr::V6(v6) => match v6.to_ipv4_mapped() {
+            Some(v4) => IpAddr::V4(v4),
+            None => IpAddr::V6(v6),
+        },
+        v4 => v4,
+    }
+}
+
+impl FromStr for Cidr {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (address, prefix) = match s.split_once('/') {
+            Some((address, prefix)) => (address, Some(prefix)),
+            None => (s, None),
+        };
+        let network = canonical(
+            IpAddr::from_str(address.trim())
+                .with_context(|| format!("{:?} isn't an IP address or subnet", s))?,
+        );
+        let bits = if network.is_ipv4() { 32 } else { 128 };
+        let prefix = match prefix {
+            Some(prefix) => prefix
+                .trim()
+                .parse::<u8>()
+                .ok()
+                .filter(|p| *p <= bits)
+                .ok_or_else(|| anyhow!("{:?} should have a prefix of 0 to {}", s, bits))?,
+            None => bits,
+        };
+        Ok(Self { network, prefix })
+    }
+}
+
+impl Display for Cidr {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}/{}", self.network, self.prefix)
+    }
+}
+
+impl<'de> Deserialize<'de> for Cidr {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let s = String::deserialize(deserializer)?;
+        Cidr::from_str(&s).map_err(|err| D::Error::custom(format!("{:#}", err)))
+    }
+}
+
+/// The `[peers]` section of the config file.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+pub struct PeerFilterConfig {
+    #[serde(default)]
+    pub allow: Vec<Cidr>,
+
+    #[serde(default)]
+    pub deny: Vec<Cidr>,
+}
+
+impl PeerFilterConfig {
+    pub fn allows(&self, ip: &IpAddr) -> bool {
+        if self.deny.iter().any(|cidr| cidr.contains(ip)) {
+            return false;
+        }
+        self.allow.is_empty() || self.allow.iter().any(|cidr| cidr.contains(ip))
+    }
+}
+
+/// The current peer lists, shared between everything that connects to peers.
+#[derive(Debug, Clone, Default)]
+pub struct PeerFilter {
+    config: Arc<RwLock<PeerFilterConfig>>,
+}
+
+impl PeerFilter {
+    pub fn new(config: PeerFilterConfig) -> Self {
+        Self {
+            config: Arc::new(RwLock::new(config)),
+        }
+    }
+
+    pub fn allows(&self, address: &SocketAddr) -> bool {
+        self.config.read().unwrap().allows(&address.ip())
+    }
+
+    pub fn set(&self, config: PeerFilterConfig) {
+        *self.config.write().unwrap() = config;
+    }
+
+    /// Load the lists from the config file at `path`. Without a `[peers]` section every peer is
+    /// allowed.
+    pub async fn load(&self, path: &Path) -> anyhow::Result<()> {
+        let config = Config::load(path).await?.peers.unwrap_or_default();
+        info!(
+            "Peer lists: {} allowed subnets, {} denied subnets",
+            config.allow.len(),
+            config.deny.len()
+        );
+        self.set(config);
+        Ok(())
+    }
+
+    /// Load the lists again whenever the config file at `path` changes, forever. A config that
+    /// doesn't parse is logged, and the lists before it are kept.
+    pub async fn watch(self, path: PathBuf) {
+        let modified = |path: &Path| -> Option<SystemTime> {
+            std::fs::metadata(path).and_then(|m| m.modified()).ok()
+        };
+        let mut last = modified(&path);
+        loop {
+            tokio::time::sleep(RELOAD_INTERVAL).await;
+            let current = modified(&path);
+            if current == last {
+                continue;
+            }
+            last = current;
+            if let Err(err) = self.load(&path).await {
+                warn!("Keeping the previous peer lists: {:#}", err);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ip(s: &str) -> IpAddr {
+        IpAddr::from_str(s).unwrap()
+    }
+
+    #[test]
+    fn cidr() {
+        let cidr = Cidr::from_str("10.1.0.0/16").unwrap();
+        assert!(cidr.contains(&ip("10.1.255.1")));
+        assert!(!cidr.contains(&ip("10.2.0.1")));
+        assert!(cidr.contains(&ip("::ffff:10.1.2.3")));
+        assert!(!cidr.contains(&ip("2001:db8::1")));
+
+        let single = Cidr::from_str("::ffff:1.2.3.4").unwrap();
+        assert_eq!(single.to_string(), "1.2.3.4/32");
+        assert!(single.contains(&ip("1.2.3.4")));
+        assert!(!single.contains(&ip("1.2.3.5")));
+
+        assert!(Cidr::from_str("0.0.0.0/0")
+            .unwrap()
+            .contains(&ip("8.8.8.8")));
+        assert!(Cidr::from_str("2001:db8::/32")
+            .unwrap()
+            .contains(&ip("2001:db8:ffff::1")));
+        assert!(Cidr::from_str("10.0.0.0/33").is_err());
+        assert!(Cidr::from_str("nope/8").is_err());
+    }
+
+    #[test]
+    fn deny_wins() {
+        let config: Config = Config::from_toml(
+            r#"
+            [peers]
+            allow = ["10.0.0.0/8"]
+            deny = ["10.1.2.3"]
+            "#,
+        )
+        .unwrap();
+        let filter = PeerFilter::new(config.peers.unwrap());
+        let allows = |s: &str| filter.allows(&SocketAddr::from_str(s).unwrap());
+        assert!(allows("10.9.9.9:7075"));
+        assert!(!allows("10.1.2.3:7075"));
+        assert!(!allows("[::ffff:10.1.2.3]:7075"));
+        assert!(!allows("192.168.1.1:7075"));
+
+        filter.set(PeerFilterConfig::default());
+        assert!(allows("192.168.1.1:7075"));
+    }
+}
@@ -1,7 +1,8 @@
 use crate::encoding::expect_len;
 use crate::node::header::Header;
 use crate::node::wire::Wire;
-use std::net::{Ipv6Addr, SocketAddrV6};
+use bytes::{BufMut, BytesMut};
+use std::net::{IpAddr, Ipv6Addr, SocketAddr, SocketAddrV6};
 use std::str::FromStr;
 
 pub struct PeerInfo(SocketAddrV6);
@@ -13,6 +14,25 @@ impl PeerInfo {
     pub fn socket_addr_v6(&self) -> SocketAddrV6 {
         self.0
     }
+
+    /// The address of the peer, with IPv4 mapped addresses converted back to IPv4.
+    pub fn socket_addr(&self) -> SocketAddr {
+        match self.0.ip().to_ipv4_mapped() {
+            Some(ipv4) => SocketAddr::new(IpAddr::V4(ipv4), self.0.port()),
+            None => SocketAddr::V6(self.0),
+        }
+    }
+}
+
+impl From<SocketAddr> for PeerInfo {
+    fn from(socket_addr: SocketAddr) -> Self {
+        match socket_addr {
+            SocketAddr::V4(v4) => {
+                Self(SocketAddrV6::new(v4.ip().to_ipv6_mapped(), v4.port(), 0, 0))
+            }
+            SocketAddr::V6(v6) => Self(v6),
+        }
+    }
 }
 
 impl FromStr for PeerInfo {
@@ -24,12 +44,9 @@ impl FromStr for PeerInfo {
 }
 
 impl Wire for PeerInfo {
-    fn serialize(&self) -> Vec<u8> {
-        let mut v = Vec::with_capacity(Self::LEN);
-        v.extend_from_slice(&self.0.ip().octets());
-        v.push(self.0.port() as u8);
-        v.push((self.0.port() >> 8) as u8);
-        v
+    fn serialize_into(&self, buf: &mut BytesMut) {
+        buf.extend_from_slice(&self.0.ip().octets());
+        buf.put_u16_le(self.0.port());
     }
 
     fn deserialize(_: Option<&Header>, data: &[u8]) -> anyhow::Result<Self>
@@ -65,8 +82,16 @@ mod tests {
         let addr = "[::ffff:255.254.253.252]:7075";
         let peer = PeerInfo::from_str(addr).unwrap();
         let v = peer.serialize();
-        let peer2 = PeerInfo::deserialize(None, v.as_slice()).unwrap();
+        let peer2 = PeerInfo::deserialize(None, &v).unwrap();
         let addr2 = peer2.socket_addr_v6().to_string();
         assert_eq!(addr, addr2);
     }
+
+    #[test]
+    fn ipv4_mapped() {
+        let addr = SocketAddr::from_str("1.2.3.4:7075").unwrap();
+        let peer = PeerInfo::from(addr);
+        assert_eq!(peer.socket_addr_v6().to_string(), "[::ffff:1.2.3.4]:7075");
+        assert_eq!(peer.socket_addr(), addr);
+    }
 }
@@ -0,0 +1,286 @@
+//! Pending entries, which are amounts sent to an account that it hasn't received yet.
+//!
+//! An entry is keyed by its destination and the hash of its send block, so seeing the same send
+//! twice leaves a single entry. An entry only makes sense while its send block is in the ledger:
+//! - Adding a send adds its entry, and adding a receive removes the entry it received.
+//! - Rolling back a send, e.g. after losing a fork, removes its entry. A send that has already
+//!   been received can't be rolled back until the receive is.
+//! - Rolling back a receive puts its entry back, since the amount can be received again.
+//!
+//! [check] looks for entries whose send block is gone or doesn't match, which would otherwise
+//! let an account receive raw that was never sent.
+use crate::blocks::{Block, BlockHash, Previous};
+use crate::keys::public::to_address;
+use crate::node::state::DynState;
+use crate::{Public, Raw};
+use anyhow::anyhow;
+use serde::Serialize;
+use std::convert::TryFrom;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Pending {
+    #[serde(serialize_with = "to_address")]
+    pub destination: Public,
+
+    /// The hash of the send block.
+    pub source: BlockHash,
+    pub amount: Raw,
+}
+
+/// What a block did to the balance of its account.
+#[derive(Debug, PartialEq)]
+enum Movement {
+    Sent(Public, Raw),
+    Received(BlockHash, Raw),
+    Nothing,
+}
+
+/// Work out the movement of a stored block by comparing it with its previous block, since
+/// stored state blocks might not have their link type set.
+async fn movement(state: &DynState, block: &Block) -> anyhow::Result<Movement> {
+    let link = block.link().as_bytes();
+    let previous_balance = match block.previous() {
+        // The genesis open block has its own account as the source.
+        Previous::Open if link == block.account().as_bytes() => return Ok(Movement::Nothing),
+        Previous::Open => Raw::zero(),
+        Previous::Block(previous) => state
+            .get_block_by_hash(previous)
+            .await?
+            .ok_or_else(|| anyhow!("Block {:?} before {:?} isn't stored", previous, block))?
+            .balance()
+            .to_owned(),
+    };
+    if let Some(amount) = previous_balance.checked_sub(block.balance()) {
+        if amount > 0 {
+            return Ok(Movement::Sent(Public::try_from(link)?, amount));
+        }
+    }
+    match block.balance().checked_sub(&previous_balance) {
+        Some(amount) if amount > 0 => Ok(Movement::Received(BlockHash::try_from(link)?, amount)),
+        _ => Ok(Movement::Nothing),
+    }
+}
+
+/// Bring the pending entries up to date with `block`, which has just been added.
+pub async fn block_added(state: &DynState, block: &Block) -> anyhow::Result<()> {
+    match movement(state, block).await? {
+        Movement::Sent(destination, amount) => {
+            state
+                .add_pending(&Pending {
+                    destination,
+                    source: block.hash()?.to_owned(),
+                    amount,
+                })
+                .await
+        }
+        Movement::Received(source, _) => {
+            state.remove_pending(block.account(), &source).await?;
+            Ok(())
+        }
+        Movement::Nothing => Ok(()),
+    }
+}
+
+/// Roll back `hash`, which has to be the frontier of its account, along with the pending entry
+/// it added or removed.
+pub async fn rollback(state: &DynState, hash: &BlockHash) -> anyhow::Result<Block> {
+    let block = state
+        .get_block_by_hash(hash)
+        .await?
+        .ok_or_else(|| anyhow!("Can't roll back {:?} since it isn't stored", hash))?;
+    let movement = movement(state, &block).await?;
+    if let Movement::Sent(destination, _) = &movement {
+        if state.remove_pending(destination, hash).await?.is_none() {
+            return Err(anyhow!(
+                "Can't roll back send {:?} since {} has received it",
+                hash,
+                destination.to_address()
+            ));
+        }
+    }
+    if let Err(err) = state.rollback_block(hash).await {
+        if let Movement::Sent(destination, amount) = movement {
+            state
+                .add_pending(&Pending {
+                    destination,
+                    source: hash.to_owned(),
+                    amount,
+                })
+                .await?;
+        }
+        return Err(err);
+    }
+    if let Movement::Received(source, amount) = movement {
+        state
+            .add_pending(&Pending {
+                destination: block.account().to_owned(),
+                source,
+                amount,
+            })
+            .await?;
+    }
+    Ok(block)
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct PendingCheck {
+    pub entries: usize,
+    pub orphaned: Vec<OrphanedPending>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct OrphanedPending {
+    #[serde(flatten)]
+    pub pending: Pending,
+    pub reason: String,
+}
+
+/// Check that every pending entry has a send block for the same destination and amount.
+pub async fn check(state: &DynState) -> anyhow::Result<PendingCheck> {
+    let mut check = PendingCheck::default();
+    for pending in state.all_pending().await? {
+        check.entries += 1;
+        let reason = match state.get_block_by_hash(&pending.source).await? {
+            None => "The send block isn't stored".to_owned(),
+            Some(block) => match movement(state, &block).await? {
+                Movement::Sent(destination, amount)
+                    if destination == pending.destination && amount == pending.amount =>
+                {
+                    continue
+                }
+                Movement::Sent(destination, amount) => format!(
+                    "The send block sends {} raw to {}",
+                    amount,
+                    destination.to_address()
+                ),
+                _ => "The source block isn't a send".to_owned(),
+            },
+        };
+        check.orphaned.push(OrphanedPending { pending, reason });
+    }
+    Ok(check)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blocks::{Link, StateBlock};
+    use crate::node::state::{MemoryState, State};
+    use crate::node::test_util::account;
+    use crate::Network;
+
+    fn block(account: &Public, previous: Option<&Block>, balance: u128, link: Link) -> Block {
+        let previous = match previous {
+            Some(b) => Previous::Block(b.hash().unwrap().to_owned()),
+            None => Previous::Open,
+        };
+        Block::from_state_block(&StateBlock::new(
+            account.to_owned(),
+            previous,
+            account.to_owned(),
+            Raw::from(balance),
+            link,
+        ))
+    }
+
+    async fn add(state: &MemoryState, block: &Block) {
+        state.add_block(block).await.unwrap();
+        block_added(state, block).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn rollback_keeps_pending_in_step() {
+        let state = MemoryState::new(Network::Test);
+        let (sender, receiver) = (account(0), account(1));
+        let open = block(&sender, None, 100, Link::Source(BlockHash::zero()));
+        let send = block(
+            &sender,
+            Some(&open),
+            60,
+            Link::DestinationAccount(receiver.to_owned()),
+        );
+        let send_hash = send.hash().unwrap().to_owned();
+        add(&state, &open).await;
+        add(&state, &send).await;
+        // Seeing the send again doesn't add another entry.
+        block_added(&state, &send).await.unwrap();
+        let expected = Pending {
+            destination: receiver.to_owned(),
+            source: send_hash.to_owned(),
+            amount: Raw::from(40),
+        };
+        assert_eq!(state.all_pending().await.unwrap(), vec![expected.clone()]);
+
+        let receive = block(&receiver, None, 40, Link::Source(send_hash.to_owned()));
+        add(&state, &receive).await;
+        assert!(state.all_pending().await.unwrap().is_empty());
+
+        let err = rollback(&state, &send_hash).await.unwrap_err();
+        assert!(err.to_string().contains("has received it"), "{}", err);
+        assert!(state.all_pending().await.unwrap().is_empty());
+
+        rollback(&state, receive.hash().unwrap()).await.unwrap();
+        assert_eq!(state.all_pending().await.unwrap(), vec![expected]);
+        assert_eq!(
+            state
+                .get_latest_block_hash_for_account(&receiver)
+                .await
+                .unwrap(),
+            None
+        );
+
+        rollback(&state, &send_hash).await.unwrap();
+        assert!(state.all_pending().await.unwrap().is_empty());
+        assert_eq!(
+            state
+                .get_latest_block_hash_for_account(&sender)
+                .await
+                .unwrap()
+                .as_ref(),
+            Some(open.hash().unwrap())
+        );
+
+        // Only frontiers can be rolled back.
+        add(&state, &send).await;
+        assert!(rollback(&state, open.hash().unwrap()).await.is_err());
+        assert_eq!(state.all_pending().await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn finds_orphans() {
+        let state = MemoryState::new(Network::Test);
+        let (sender, receiver) = (account(0), account(1));
+        let open = block(&sender, None, 100, Link::Source(BlockHash::zero()));
+        let send = block(
+            &sender,
+            Some(&open),
+            60,
+            Link::DestinationAccount(receiver.to_owned()),
+        );
+        add(&state, &open).await;
+        add(&state, &send).await;
+        for (source, amount) in &[
+            (send.hash().unwrap(), 41),
+            (open.hash().unwrap(), 1),
+            (&BlockHash::zero(), 1),
+        ] {
+            state
+                .add_pending(&Pending {
+                    destination: receiver.to_owned(),
+                    source: (*source).to_owned(),
+                    amount: Raw::from(*amount),
+                })
+                .await
+                .unwrap();
+        }
+
+        let check = check(&state).await.unwrap();
+        assert_eq!(check.entries, 3);
+        let mut reasons: Vec<_> = check.orphaned.iter().map(|o| &o.reason[..12]).collect();
+        reasons.sort_unstable();
+        assert_eq!(
+            reasons,
+            vec!["The send blo", "The send blo", "The source b"]
+        );
+    }
+}
@@ -0,0 +1,146 @@
+use crate::network::Network;
+use crate::node::cookie::Cookie;
+use crate::node::header::{Extensions, Header, MessageType};
+use crate::node::messages::handshake::{Handshake, HandshakeQuery};
+use crate::node::wire::Wire;
+use crate::Public;
+use anyhow::{anyhow, Context};
+use serde::{Deserialize, Serialize};
+use std::net::SocketAddr;
+use std::time::{Duration, SystemTime};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio::time::timeout;
+
+/// How long to wait for a learned peer to connect and respond to our handshake.
+pub const PROBE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Don't probe the same learned peer more often than this.
+pub const PROBE_INTERVAL: Duration = Duration::from_secs(10 * 60);
+
+/// The outcome of the last liveness probe of a peer that was advertised to us.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProbeStatus {
+    pub at: SystemTime,
+    pub reachable: bool,
+}
+
+impl ProbeStatus {
+    pub fn new(reachable: bool) -> Self {
+        Self {
+            at: SystemTime::now(),
+            reachable,
+        }
+    }
+
+    /// If the last probe was long enough ago that it's worth trying again.
+    pub fn is_stale(&self) -> bool {
+        self.at.elapsed().map_or(true, |age| age >= PROBE_INTERVAL)
+    }
+}
+
+/// Connect to a peer and check that it responds to a handshake with a valid signature of our
+/// cookie, returning the node id of the peer.
+pub async fn probe(network: Network, address: SocketAddr) -> anyhow::Result<Public> {
+    timeout(PROBE_TIMEOUT, probe_inner(network, address))
+        .await
+        .with_context(|| format!("Timed out probing {}", address))?
+}
+
+async fn probe_inner(network: Network, address: SocketAddr) -> anyhow::Result<Public> {
+    let mut stream = TcpStream::connect(address)
+        .await
+        .with_context(|| format!("Connecting to {}", address))?;
+
+    let cookie = Cookie::random();
+    let header = Header::new(network, MessageType::Handshake, *Extensions::new().query());
+    let mut data = header.serialize();
+    data.extend(HandshakeQuery::new(cookie.clone()).serialize());
+    stream.write_all(&data).await.context("Sending handshake")?;
+
+    // Skip over anything else the peer sends us until we see its handshake response.
+    loop {
+        let mut buffer = vec![0u8; Header::LEN];
+        stream.read_exact(&mut buffer).await?;
+        let header = Header::deserialize(None, &buffer)?;
+        header.validate(&network)?;
+        if header.message_type() != MessageType::Handshake {
+            return Err(anyhow!(
+                "Expecting a handshake, got {:?}",
+                header.message_type()
+            ));
+        }
+
+        let mut buffer = vec![0u8; Handshake::len(Some(&header))?];
+        stream.read_exact(&mut buffer).await?;
+        let handshake = Handshake::deserialize(Some(&header), &buffer)?;
+        if let Some(response) = handshake.response {
+            response
+                .public
+                .verify(cookie.as_bytes(), &response.signature)
+                .context("Invalid signature in probe handshake response")?;
+            return Ok(response.public);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::node::messages::handshake::HandshakeResponse;
+    use crate::Seed;
+    use tokio::net::TcpListener;
+
+    /// Pretend to be a node that responds to a handshake, optionally with a bad signature.
+    async fn fake_node(good_signature: bool) -> (SocketAddr, Public) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let private = Seed::random().derive(0);
+        let public = private.to_public().unwrap();
+
+        let public_cloned = public.clone();
+        tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut buffer = vec![0u8; Header::LEN + Cookie::LEN];
+            stream.read_exact(&mut buffer).await.unwrap();
+            let mut cookie = buffer[Header::LEN..].to_vec();
+            if !good_signature {
+                cookie[0] ^= 0xff;
+            }
+
+            let signature = private.sign(&cookie).unwrap();
+            let header = Header::new(
+                Network::Live,
+                MessageType::Handshake,
+                *Extensions::new().response(),
+            );
+            let mut data = header.serialize();
+            data.extend(HandshakeResponse::new(public_cloned, signature).serialize());
+            stream.write_all(&data).await.unwrap();
+        });
+
+        (address, public)
+    }
+
+    #[tokio::test]
+    async fn reachable() {
+        let (address, public) = fake_node(true).await;
+        assert_eq!(probe(Network::Live, address).await.unwrap(), public);
+    }
+
+    #[tokio::test]
+    async fn bad_signature() {
+        let (address, _) = fake_node(false).await;
+        assert!(probe(Network::Live, address).await.is_err());
+    }
+
+    #[test]
+    fn stale() {
+        assert!(!ProbeStatus::new(true).is_stale());
+        let old = ProbeStatus {
+            at: SystemTime::now() - PROBE_INTERVAL,
+            reachable: false,
+        };
+        assert!(old.is_stale());
+    }
+}
@@ -0,0 +1,210 @@
+//! Blocks submitted with the `process` RPC, e.g. by a wallet using the node as its RPC server.
+//!
+//! They're checked like state blocks published by peers, except that the previous block has to
+//! be the frontier already, since there's no one to wait on for a missing block. Blocks that
+//! pass are stored and flooded to the connected peers.
+use crate::blocks::{Block, Link, Previous, StateBlock, Subtype};
+use crate::node::confirmation::ConfirmationTracker;
+use crate::node::events::FrontierEvents;
+use crate::node::intake::{self, RejectReason};
+use crate::node::journal::{Journal, JournalEvent};
+use crate::node::own_blocks::OwnBlocks;
+use crate::node::pending;
+use crate::node::state::{ArcState, DynState};
+use crate::rpc::calls::ProcessResponse;
+use crate::rpc::websocket::FrontierSource;
+use crate::{Network, Raw, RpcErrorKind};
+use anyhow::anyhow;
+use tracing::{debug, info};
+
+/// Check a block, deciding its subtype from the previous block. The outer error is for
+/// problems reading the state.
+pub async fn check(
+    state: &DynState,
+    network: Network,
+    mut block: StateBlock,
+) -> anyhow::Result<Result<Block, RejectReason>> {
+    if state.get_block_by_hash(&block.hash).await?.is_some() {
+        return Ok(Err(RejectReason::Old));
+    }
+    if block.verify_self_signature().is_err() {
+        return Ok(Err(RejectReason::InvalidSignature));
+    }
+
+    let frontier = state
+        .get_latest_block_hash_for_account(&block.account)
+        .await?;
+    let previous = match &block.previous {
+        Previous::Open if frontier.is_some() => return Ok(Err(RejectReason::Fork)),
+        Previous::Open => None,
+        Previous::Block(hash) => match state.get_block_by_hash(hash).await? {
+            None => return Ok(Err(RejectReason::UnknownPrevious(hash.to_owned()))),
+            Some(_) if frontier.as_ref() != Some(hash) => return Ok(Err(RejectReason::Fork)),
+            Some(previous) => Some(StateBlock::from(previous)),
+        },
+    };
+
+    let subtype = block.subtype(previous.as_ref());
+    block.link = block.link.for_subtype(subtype)?;
+    // Opening an account is receiving onto an empty chain.
+    let previous = previous.unwrap_or_else(|| {
+        StateBlock::new(
+            block.account.to_owned(),
+            Previous::Open,
+            block.representative.to_owned(),
+            Raw::zero(),
+            Link::Nothing,
+        )
+    });
+    if let Err(reason) = intake::check_amounts(state, &block, &previous).await? {
+        return Ok(Err(reason));
+    }
+
+    let threshold = match subtype {
+        Subtype::Receive | Subtype::Open => network.receive_difficulty(),
+        _ => network.send_difficulty(),
+    };
+    let difficulty = block.require_work()?.difficulty(&block.work_root())?;
+    if difficulty < threshold {
+        return Ok(Err(RejectReason::InsufficientWork {
+            difficulty: difficulty.as_u64(),
+            threshold: threshold.as_u64(),
+        }));
+    }
+    Ok(Ok(Block::from_state_block(&block)))
+}
+
+/// Check, store and flood a block, answering like the reference node does.
+pub async fn submit(
+    state: &ArcState,
+    network: Network,
+    journal: &Journal,
+    frontiers: &FrontierEvents,
+    own_blocks: &OwnBlocks,
+    confirmations: &ConfirmationTracker,
+    block: StateBlock,
+) -> anyhow::Result<ProcessResponse> {
+    let checked = check(&**state, network, block).await?;
+    let block = checked.map_err(|reason| {
+        info!("Refusing processed block: {}", reason);
+        anyhow!(rpc_error(&reason))
+    })?;
+    let hash = block.hash()?.to_owned();
+
+    store_block(state, journal, frontiers, &block).await?;
+    own_blocks.add(&hash);
+    let peers = confirmations.publish(StateBlock::from(block)).await;
+    debug!("Published processed block {:?} to {} peers", hash, peers);
+    Ok(ProcessResponse { hash: Some(hash) })
+}
+
+/// The reference node's error for a block it won't process, where there's one.
+fn rpc_error(reason: &RejectReason) -> String {
+    match reason {
+        RejectReason::Fork => RpcErrorKind::Fork.to_string(),
+        RejectReason::InsufficientWork { .. } => "Block work is less than threshold".into(),
+        RejectReason::InvalidSignature => "Bad signature".into(),
+        RejectReason::Old => "Old block".into(),
+        RejectReason::UnknownPrevious(_) => "Gap previous block".into(),
+        RejectReason::UnknownSource(_) => "Gap source block".into(),
+        reason => reason.to_string(),
+    }
+}
+
+/// Add a block and its pending entry to the ledger, and tell the journal and anyone following
+/// the frontiers.
+pub async fn store_block(
+    state: &ArcState,
+    journal: &Journal,
+    frontiers: &FrontierEvents,
+    block: &Block,
+) -> anyhow::Result<()> {
+    state.add_block(block).await?;
+    pending::block_added(&**state, block).await?;
+    let hash = block.hash()?;
+    journal.record(JournalEvent::Inserted {
+        hash: hash.to_owned(),
+        account: block.account().to_address(),
+    });
+    frontiers.publish(block.account(), hash, FrontierSource::Block);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blocks::BlockHash;
+    use crate::node::state::{MemoryState, State};
+    use crate::{Seed, Signature, Work};
+
+    fn signed(mut block: StateBlock) -> StateBlock {
+        let private = Seed::zero().derive(0);
+        block.signature = Some(private.sign(block.hash.as_bytes()).unwrap());
+        block.work = Some(Work::zero());
+        block
+    }
+
+    fn open() -> Block {
+        let account = Seed::zero().derive(0).to_public().unwrap();
+        Block::from_state_block(&signed(StateBlock::new(
+            account.to_owned(),
+            Previous::Open,
+            account,
+            Raw::from(5),
+            Link::Source(BlockHash::zero()),
+        )))
+    }
+
+    /// A change of representative on top of `previous`.
+    fn change(previous: &Block) -> StateBlock {
+        signed(StateBlock::new(
+            previous.account().to_owned(),
+            Previous::Block(previous.hash().unwrap().to_owned()),
+            Seed::zero().derive(1).to_public().unwrap(),
+            previous.balance().to_owned(),
+            Link::unsure_from_str(&"0".repeat(64)).unwrap(),
+        ))
+    }
+
+    #[tokio::test]
+    async fn checks() {
+        let state = MemoryState::new(Network::Test);
+        let open = open();
+        state.add_block(&open).await.unwrap();
+
+        // Everything about the change is fine but the work, which isn't worth finding here.
+        let block = change(&open);
+        assert!(matches!(
+            check(&state, Network::Live, block.to_owned())
+                .await
+                .unwrap(),
+            Err(RejectReason::InsufficientWork { .. })
+        ));
+
+        let mut forged = block.to_owned();
+        forged.signature = Some(Signature::zero());
+        assert_eq!(
+            check(&state, Network::Live, forged).await.unwrap(),
+            Err(RejectReason::InvalidSignature)
+        );
+
+        assert_eq!(
+            check(&state, Network::Live, StateBlock::from(open.to_owned()))
+                .await
+                .unwrap(),
+            Err(RejectReason::Old)
+        );
+
+        let reopen = signed(StateBlock::new(
+            open.account().to_owned(),
+            Previous::Open,
+            open.account().to_owned(),
+            Raw::from(6),
+            Link::Source(BlockHash::zero()),
+        ));
+        assert_eq!(
+            check(&state, Network::Live, reopen).await.unwrap(),
+            Err(RejectReason::Fork)
+        );
+    }
+}
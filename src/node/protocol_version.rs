@@ -0,0 +1,67 @@
+use crate::Version;
+use std::fmt::{Display, Formatter};
+
+/// The protocol version a message was encoded with.
+///
+/// This is `version_using` in the [Header](crate::node::Header), which is passed through
+/// (de)serialization so that messages can change their layout between versions, e.g. the handshake
+/// response only has the v2 fields from V19. Unlike
+/// [Version], this accepts any version number, so that we can still talk to nodes that are newer
+/// than us.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ProtocolVersion(u8);
+
+impl ProtocolVersion {
+    pub const V18: ProtocolVersion = ProtocolVersion(18);
+    pub const V19: ProtocolVersion = ProtocolVersion(19);
+    pub const V20: ProtocolVersion = ProtocolVersion(20);
+
+    /// The version we send in our own headers.
+    pub const CURRENT: ProtocolVersion = Self::V18;
+
+    /// Peers using a version lower than this are rejected.
+    pub const MIN: ProtocolVersion = Self::V18;
+
+    pub const fn new(v: u8) -> Self {
+        Self(v)
+    }
+
+    pub const fn as_u8(self) -> u8 {
+        self.0
+    }
+}
+
+impl Default for ProtocolVersion {
+    fn default() -> Self {
+        Self::CURRENT
+    }
+}
+
+impl From<Version> for ProtocolVersion {
+    fn from(v: Version) -> Self {
+        Self(v as u8)
+    }
+}
+
+impl From<u8> for ProtocolVersion {
+    fn from(v: u8) -> Self {
+        Self(v)
+    }
+}
+
+impl Display for ProtocolVersion {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from_version() {
+        assert_eq!(ProtocolVersion::from(Version::V19), ProtocolVersion::V19);
+        assert!(ProtocolVersion::from(Version::V18) < ProtocolVersion::V19);
+    }
+}
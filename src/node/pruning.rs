@@ -0,0 +1,238 @@
+//! Removing old blocks from the ledger to save space, following the retention policy in the
+//! `[pruning]` section of the config file, e.g.
+//! ```toml
+//! [pruning]
+//! keep_blocks = 100
+//! keep_accounts = ["nano_1111111111111111111111111111111111111111111111111111hifc8npp"]
+//! interval = 3600
+//! ```
+//! Without the section nothing is pruned.
+//!
+//! Each pass keeps:
+//! - Every block of the accounts in `keep_accounts`, e.g. wallet accounts, and of the priority
+//!   accounts, which include the watched ones.
+//! - The last `keep_blocks` blocks of every other account, and always its frontier.
+//! - Every block that isn't cemented, since it could still be rolled back, and the highest
+//!   cemented block of an account with uncemented blocks, since it becomes the frontier again
+//!   if they're all rolled back.
+//!
+//! Accounts without a cemented height, or whose frontier doesn't have a height in its sideband,
+//! are left alone.
+use crate::blocks::{BlockHash, Previous};
+use crate::node::state::DynState;
+use crate::{Address, Public};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::time::Duration;
+
+/// The `[pruning]` section of the config file.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct PruningConfig {
+    /// Blocks to keep at the end of each account's chain. At least the frontier is always kept.
+    #[serde(default = "default_keep_blocks")]
+    pub keep_blocks: u64,
+
+    /// Accounts whose whole chain is kept.
+    #[serde(default)]
+    pub keep_accounts: Vec<Address>,
+
+    /// Seconds between pruning passes.
+    #[serde(default = "default_interval")]
+    pub interval: u64,
+}
+
+fn default_keep_blocks() -> u64 {
+    100
+}
+
+fn default_interval() -> u64 {
+    3600
+}
+
+impl Default for PruningConfig {
+    fn default() -> Self {
+        Self {
+            keep_blocks: default_keep_blocks(),
+            keep_accounts: vec![],
+            interval: default_interval(),
+        }
+    }
+}
+
+impl PruningConfig {
+    pub fn interval(&self) -> Duration {
+        Duration::from_secs(self.interval)
+    }
+
+    /// The policy for this config, also keeping every block of the `priority` accounts.
+    pub fn policy(&self, priority: &[Public]) -> RetentionPolicy {
+        RetentionPolicy {
+            keep_blocks: self.keep_blocks.max(1),
+            full: self
+                .keep_accounts
+                .iter()
+                .map(|a| a.to_public())
+                .chain(priority.iter().cloned())
+                .collect(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RetentionPolicy {
+    keep_blocks: u64,
+    full: HashSet<Public>,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize)]
+pub struct PruneStats {
+    /// Accounts that had blocks pruned.
+    pub accounts: u64,
+    pub blocks: u64,
+}
+
+/// Prune every account in `state` following `policy`.
+pub async fn prune(state: &DynState, policy: &RetentionPolicy) -> anyhow::Result<PruneStats> {
+    let mut stats = PruneStats::default();
+    for account in state.accounts().await? {
+        if policy.full.contains(&account) {
+            continue;
+        }
+        let pruned = prune_account(state, policy, &account).await?;
+        if pruned > 0 {
+            stats.accounts += 1;
+            stats.blocks += pruned;
+        }
+    }
+    Ok(stats)
+}
+
+async fn prune_account(
+    state: &DynState,
+    policy: &RetentionPolicy,
+    account: &Public,
+) -> anyhow::Result<u64> {
+    let frontier = match state.get_latest_block_hash_for_account(account).await? {
+        Some(frontier) => frontier,
+        None => return Ok(0),
+    };
+    let height = match state.sideband(&frontier).await?.and_then(|s| s.height) {
+        Some(height) => height,
+        None => return Ok(0),
+    };
+    let cemented = match state.cemented_height(account).await? {
+        Some(cemented) if cemented < height => cemented.saturating_sub(1),
+        Some(cemented) => cemented,
+        None => return Ok(0),
+    };
+    let highest = cemented.min(height.saturating_sub(policy.keep_blocks));
+
+    let mut pruned = 0;
+    let mut hash: BlockHash = frontier;
+    for h in (1..=height).rev() {
+        let block = match state.get_block_by_hash(&hash).await? {
+            Some(block) => block,
+            // Pruned by an earlier pass, or evicted from a cache.
+            None => break,
+        };
+        if h <= highest {
+            state.prune_block(&hash).await?;
+            pruned += 1;
+        }
+        hash = match block.previous() {
+            Previous::Block(previous) => previous.to_owned(),
+            Previous::Open => break,
+        };
+    }
+    Ok(pruned)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::node::state::{ArcState, MemoryState};
+    use crate::node::test_util::{self, account, add};
+    use crate::Network;
+    use std::sync::Arc;
+
+    /// Add a chain of `len` blocks for the test account at `index`, returning their hashes from
+    /// the open block up.
+    async fn chain(state: &ArcState, index: u32, len: u128) -> Vec<BlockHash> {
+        let blocks = test_util::blocks(index, len);
+        add(state, &blocks).await;
+        blocks
+            .iter()
+            .map(|b| b.hash().unwrap().to_owned())
+            .collect()
+    }
+
+    async fn stored(state: &ArcState, hashes: &[BlockHash]) -> Vec<bool> {
+        let mut stored = vec![];
+        for hash in hashes {
+            stored.push(state.get_block_by_hash(hash).await.unwrap().is_some());
+        }
+        stored
+    }
+
+    #[tokio::test]
+    async fn retention() {
+        let state: ArcState = Arc::new(MemoryState::new(Network::Test));
+        let (pruned, kept, uncemented) = (account(0), account(1), account(2));
+        let pruned_chain = chain(&state, 0, 6).await;
+        let kept_chain = chain(&state, 1, 6).await;
+        let uncemented_chain = chain(&state, 2, 6).await;
+        state.set_cemented_height(&pruned, 6).await.unwrap();
+        state.set_cemented_height(&kept, 6).await.unwrap();
+        state.set_cemented_height(&uncemented, 3).await.unwrap();
+        assert_eq!(
+            state
+                .sideband(&pruned_chain[5])
+                .await
+                .unwrap()
+                .unwrap()
+                .height,
+            Some(6)
+        );
+
+        let config = PruningConfig {
+            keep_blocks: 2,
+            keep_accounts: vec![Address::from(&kept)],
+            interval: 60,
+        };
+        let policy = config.policy(&[]);
+        let stats = prune(&*state, &policy).await.unwrap();
+        assert_eq!(
+            stats,
+            PruneStats {
+                accounts: 2,
+                blocks: 6
+            }
+        );
+
+        assert_eq!(
+            stored(&state, &pruned_chain).await,
+            vec![false, false, false, false, true, true]
+        );
+        assert!(stored(&state, &kept_chain).await.iter().all(|s| *s));
+        // Blocks 4 to 6 aren't cemented and block 3 is the highest cemented one.
+        assert_eq!(
+            stored(&state, &uncemented_chain).await,
+            vec![false, false, true, true, true, true]
+        );
+        assert!(state.is_pruned(&pruned_chain[0]).await.unwrap());
+        assert!(!state.is_pruned(&pruned_chain[5]).await.unwrap());
+        assert_eq!(state.block_count().await.unwrap(), 18);
+
+        // Nothing is left to prune, and frontiers can't be.
+        assert_eq!(prune(&*state, &policy).await.unwrap().blocks, 0);
+        assert!(state.prune_block(&pruned_chain[5]).await.is_err());
+    }
+
+    #[test]
+    fn config() {
+        let config: crate::Config = toml::from_str("[pruning]\nkeep_blocks = 0").unwrap();
+        let config = config.pruning.unwrap();
+        assert_eq!(config.interval(), Duration::from_secs(3600));
+        assert_eq!(config.policy(&[]).keep_blocks, 1);
+    }
+}
@@ -1,79 +1,378 @@
-use crate::blocks::{Block, BlockHash};
+use crate::blocks::{Block, BlockHash, Previous};
 use crate::network::Network;
+use crate::node::cache::{Lru, MemoryBudget};
 use crate::node::cookie::Cookie;
-use crate::node::state::State;
-use crate::Public;
-use anyhow::Context;
+use crate::node::pending::Pending;
+use crate::node::probe::ProbeStatus;
+use crate::node::state::{AccountInfo, RepChange, Sideband, State};
+use crate::rpc::calls::KnownPeer;
+use crate::{Public, Raw};
+use anyhow::{anyhow, Context};
 use async_trait::async_trait;
-use std::collections::{HashMap, HashSet};
+use chrono::{DateTime, Utc};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::net::SocketAddr;
+use std::ops::Bound;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
 
+/// Keeps everything in memory behind a lock, so it's lost when the node stops.
 #[derive(Debug)]
 pub struct MemoryState {
+    inner: Mutex<Inner>,
+}
+
+#[derive(Debug)]
+struct Inner {
     network: Network,
     cookies: HashMap<SocketAddr, Cookie>,
-    blocks: HashMap<BlockHash, Block>,
+    node_ids: HashMap<SocketAddr, Public>,
+
+    /// Capped by [MemoryBudget::blocks], except for the frontier of each account.
+    blocks: Lru<BlockHash, Block>,
+
+    /// Capped by [MemoryBudget::blocks] too, but kept separately so frontiers are left out.
+    sidebands: Lru<BlockHash, Sideband>,
     block_hash_to_account: HashMap<BlockHash, Public>,
-    latest_block_hash: HashMap<Public, BlockHash>,
-    votes: HashMap<BlockHash, HashSet<Public>>,
-    peers: HashSet<SocketAddr>,
+    /// Ordered so [State::accounts_after] can start part way through.
+    latest_block_hash: BTreeMap<Public, BlockHash>,
+    cemented_heights: HashMap<Public, u64>,
+
+    /// Blocks added and not rolled back, since [Inner::blocks] forgets some of them.
+    block_count: u64,
+
+    /// Blocks removed by pruning, which still count as added.
+    pruned: HashSet<BlockHash>,
+
+    account_infos: HashMap<Public, AccountInfo>,
+    pending: HashMap<(Public, BlockHash), Raw>,
+    rep_history: HashMap<Public, Vec<RepChange>>,
+    votes: Lru<BlockHash, HashSet<Public>>,
+    /// When each peer was last seen.
+    peers: HashMap<SocketAddr, DateTime<Utc>>,
+    probes: HashMap<SocketAddr, ProbeStatus>,
 }
 
 impl MemoryState {
     pub fn new(network: Network) -> Self {
-        Self {
+        Self::with_budget(network, &MemoryBudget::default())
+    }
+
+    pub fn with_budget(network: Network, budget: &MemoryBudget) -> Self {
+        let inner = Inner {
             network,
             cookies: HashMap::new(),
-            blocks: HashMap::new(),
+            node_ids: HashMap::new(),
+            blocks: Lru::new(budget.blocks),
+            sidebands: Lru::new(budget.blocks),
             block_hash_to_account: HashMap::new(),
-            latest_block_hash: HashMap::new(),
-            votes: HashMap::new(),
-            peers: HashSet::new(),
+            latest_block_hash: BTreeMap::new(),
+            cemented_heights: HashMap::new(),
+            block_count: 0,
+            pruned: HashSet::new(),
+            account_infos: HashMap::new(),
+            pending: HashMap::new(),
+            rep_history: HashMap::new(),
+            votes: Lru::new(budget.votes),
+            peers: HashMap::new(),
+            probes: HashMap::new(),
+        };
+        Self {
+            inner: Mutex::new(inner),
         }
     }
 }
 
 #[async_trait]
 impl State for MemoryState {
-    async fn add_block(&mut self, block: &Block) -> anyhow::Result<()> {
-        self.blocks.insert(
-            block.hash().context("Add block")?.to_owned(),
-            block.to_owned(),
-        );
-        self.block_hash_to_account
-            .insert(block.hash()?.to_owned(), block.account().to_owned());
-        self.latest_block_hash
-            .insert(block.account().to_owned(), block.hash()?.to_owned());
+    async fn add_block(&self, block: &Block) -> anyhow::Result<()> {
+        let mut inner = self.inner.lock().unwrap();
+        let hash = block.hash().context("Add block")?.to_owned();
+        let added = inner
+            .block_hash_to_account
+            .insert(hash.to_owned(), block.account().to_owned())
+            .is_none();
+        if !inner.pruned.remove(&hash) && added {
+            inner.block_count += 1;
+        }
+        inner
+            .latest_block_hash
+            .insert(block.account().to_owned(), hash.to_owned());
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .context("Add block")?
+            .as_secs();
+        let info =
+            AccountInfo::for_block(inner.account_infos.get(block.account()), block, &hash, now);
+        inner.account_infos.insert(block.account().to_owned(), info);
+
+        let previous = match block.previous() {
+            Previous::Block(previous) => inner.blocks.peek(previous),
+            Previous::Open => None,
+        };
+        let previous_height = match block.previous() {
+            Previous::Block(previous) => inner.sidebands.peek(previous).and_then(|s| s.height),
+            Previous::Open => None,
+        };
+        let sideband = Sideband::for_block(block, previous, previous_height, now)?;
+        if let Some(change) = RepChange::for_block(block, previous, now) {
+            let history = inner
+                .rep_history
+                .entry(block.account().to_owned())
+                .or_default();
+            // Adding the same block again doesn't change anything.
+            if history.last().map(|c| &c.hash) != Some(&hash) {
+                history.push(change);
+            }
+        }
+
+        inner.sidebands.insert(hash.to_owned(), sideband);
+
+        // Frontiers are needed to validate the next block of each account, so they're put back
+        // unless every block left is a frontier.
+        let mut evicted = inner.blocks.insert(hash, block.to_owned());
+        for _ in 0..inner.blocks.len() {
+            let (hash, block) = match evicted.take() {
+                Some(evicted) => evicted,
+                None => break,
+            };
+            if inner.latest_block_hash.get(block.account()) == Some(&hash) {
+                evicted = inner.blocks.insert(hash, block);
+            } else {
+                inner.block_hash_to_account.remove(&hash);
+            }
+        }
         Ok(())
     }
 
     async fn get_block_by_hash(&self, hash: &BlockHash) -> anyhow::Result<Option<Block>> {
-        Ok(self.blocks.get(hash).map(|b| b.to_owned()))
+        let inner = self.inner.lock().unwrap();
+        Ok(inner.blocks.peek(hash).map(|b| b.to_owned()))
     }
 
     async fn get_latest_block_hash_for_account(
         &self,
         account: &Public,
     ) -> anyhow::Result<Option<BlockHash>> {
-        Ok(self.latest_block_hash.get(account).map(|b| b.to_owned()))
+        let inner = self.inner.lock().unwrap();
+        Ok(inner.latest_block_hash.get(account).map(|b| b.to_owned()))
     }
 
     async fn account_for_block_hash(
-        &mut self,
+        &self,
         block_hash: &BlockHash,
     ) -> Result<Option<Public>, anyhow::Error> {
-        Ok(self
+        let inner = self.inner.lock().unwrap();
+        Ok(inner
             .block_hash_to_account
             .get(block_hash)
             .map(|a| a.to_owned()))
     }
 
-    async fn add_vote(&mut self, hash: &BlockHash, representative: &Public) -> anyhow::Result<()> {
-        let entry = self
+    async fn account_info(&self, account: &Public) -> anyhow::Result<Option<AccountInfo>> {
+        let inner = self.inner.lock().unwrap();
+        Ok(inner.account_infos.get(account).cloned())
+    }
+
+    async fn accounts(&self) -> anyhow::Result<Vec<Public>> {
+        let inner = self.inner.lock().unwrap();
+        Ok(inner.latest_block_hash.keys().cloned().collect())
+    }
+
+    async fn accounts_after(
+        &self,
+        after: Option<&Public>,
+        limit: usize,
+    ) -> anyhow::Result<Vec<Public>> {
+        let inner = self.inner.lock().unwrap();
+        let start = match after {
+            Some(after) => Bound::Excluded(after),
+            None => Bound::Unbounded,
+        };
+        Ok(inner
+            .latest_block_hash
+            .range::<Public, _>((start, Bound::Unbounded))
+            .take(limit)
+            .map(|(account, _)| account.to_owned())
+            .collect())
+    }
+
+    async fn cemented_height(&self, account: &Public) -> anyhow::Result<Option<u64>> {
+        let inner = self.inner.lock().unwrap();
+        Ok(inner.cemented_heights.get(account).copied())
+    }
+
+    async fn set_cemented_height(&self, account: &Public, height: u64) -> anyhow::Result<()> {
+        let mut inner = self.inner.lock().unwrap();
+        inner.cemented_heights.insert(account.to_owned(), height);
+        Ok(())
+    }
+
+    async fn block_count(&self) -> anyhow::Result<u64> {
+        let inner = self.inner.lock().unwrap();
+        Ok(inner.block_count)
+    }
+
+    async fn cemented_count(&self) -> anyhow::Result<u64> {
+        let inner = self.inner.lock().unwrap();
+        Ok(inner.cemented_heights.values().sum())
+    }
+
+    async fn account_modified(&self, account: &Public) -> anyhow::Result<Option<u64>> {
+        let inner = self.inner.lock().unwrap();
+        Ok(inner.account_infos.get(account).map(|info| info.modified))
+    }
+
+    async fn sideband(&self, hash: &BlockHash) -> anyhow::Result<Option<Sideband>> {
+        let inner = self.inner.lock().unwrap();
+        Ok(inner.sidebands.peek(hash).cloned())
+    }
+
+    async fn sidebands_since(&self, timestamp: u64) -> anyhow::Result<Vec<Sideband>> {
+        let inner = self.inner.lock().unwrap();
+        Ok(inner
+            .sidebands
+            .values()
+            .filter(|s| s.timestamp >= timestamp)
+            .cloned()
+            .collect())
+    }
+
+    async fn rep_history(&self, account: &Public) -> anyhow::Result<Vec<RepChange>> {
+        let inner = self.inner.lock().unwrap();
+        Ok(inner.rep_history.get(account).cloned().unwrap_or_default())
+    }
+
+    async fn prune_block(&self, hash: &BlockHash) -> anyhow::Result<()> {
+        let mut inner = self.inner.lock().unwrap();
+        let account = inner
+            .blocks
+            .peek(hash)
+            .ok_or_else(|| anyhow!("Block {:?} isn't stored", hash))?
+            .account()
+            .to_owned();
+        if inner.latest_block_hash.get(&account) == Some(hash) {
+            return Err(anyhow!("Block {:?} is the frontier of its account", hash));
+        }
+        inner.blocks.remove(hash);
+        inner.sidebands.remove(hash);
+        inner.block_hash_to_account.remove(hash);
+        inner.pruned.insert(hash.to_owned());
+        Ok(())
+    }
+
+    async fn is_pruned(&self, hash: &BlockHash) -> anyhow::Result<bool> {
+        let inner = self.inner.lock().unwrap();
+        Ok(inner.pruned.contains(hash))
+    }
+
+    async fn rollback_block(&self, hash: &BlockHash) -> anyhow::Result<()> {
+        let mut inner = self.inner.lock().unwrap();
+        let block = inner
+            .blocks
+            .peek(hash)
+            .ok_or_else(|| anyhow!("Block {:?} isn't stored", hash))?
+            .to_owned();
+        let account = block.account();
+        if inner.latest_block_hash.get(account) != Some(hash) {
+            return Err(anyhow!(
+                "Block {:?} isn't the frontier of its account",
+                hash
+            ));
+        }
+        inner.blocks.remove(hash);
+        inner.sidebands.remove(hash);
+        inner.block_hash_to_account.remove(hash);
+        inner.block_count = inner.block_count.saturating_sub(1);
+        if let Some(history) = inner.rep_history.get_mut(account) {
+            if history.last().map(|c| &c.hash) == Some(hash) {
+                history.pop();
+            }
+        }
+        let previous = match block.previous() {
+            Previous::Block(previous) => {
+                inner
+                    .latest_block_hash
+                    .insert(account.to_owned(), previous.to_owned());
+                inner
+                    .blocks
+                    .peek(previous)
+                    .map(|b| (previous, b.to_owned()))
+            }
+            Previous::Open => {
+                inner.latest_block_hash.remove(account);
+                None
+            }
+        };
+        let info = inner
+            .account_infos
+            .get(account)
+            .and_then(|info| info.rolled_back(previous.as_ref().map(|(h, b)| (*h, b))));
+        match info {
+            Some(info) => inner.account_infos.insert(account.to_owned(), info),
+            None => inner.account_infos.remove(account),
+        };
+        Ok(())
+    }
+
+    async fn add_pending(&self, pending: &Pending) -> anyhow::Result<()> {
+        let mut inner = self.inner.lock().unwrap();
+        inner.pending.insert(
+            (pending.destination.to_owned(), pending.source.to_owned()),
+            pending.amount.to_owned(),
+        );
+        Ok(())
+    }
+
+    async fn remove_pending(
+        &self,
+        destination: &Public,
+        source: &BlockHash,
+    ) -> anyhow::Result<Option<Pending>> {
+        let mut inner = self.inner.lock().unwrap();
+        Ok(inner
+            .pending
+            .remove(&(destination.to_owned(), source.to_owned()))
+            .map(|amount| Pending {
+                destination: destination.to_owned(),
+                source: source.to_owned(),
+                amount,
+            }))
+    }
+
+    async fn pending(&self, destination: &Public) -> anyhow::Result<Vec<Pending>> {
+        let inner = self.inner.lock().unwrap();
+        Ok(inner
+            .pending
+            .iter()
+            .filter(|((d, _), _)| d == destination)
+            .map(|((destination, source), amount)| Pending {
+                destination: destination.to_owned(),
+                source: source.to_owned(),
+                amount: amount.to_owned(),
+            })
+            .collect())
+    }
+
+    async fn all_pending(&self) -> anyhow::Result<Vec<Pending>> {
+        let inner = self.inner.lock().unwrap();
+        Ok(inner
+            .pending
+            .iter()
+            .map(|((destination, source), amount)| Pending {
+                destination: destination.to_owned(),
+                source: source.to_owned(),
+                amount: amount.to_owned(),
+            })
+            .collect())
+    }
+
+    async fn add_vote(&self, hash: &BlockHash, representative: &Public) -> anyhow::Result<()> {
+        let mut inner = self.inner.lock().unwrap();
+        inner
             .votes
-            .entry(hash.to_owned())
-            .or_insert_with(|| HashSet::new());
-        entry.insert(representative.to_owned());
+            .get_or_insert_with(hash.to_owned(), HashSet::new)
+            .insert(representative.to_owned());
 
         // dbg!(&self
         //     .votes
@@ -81,7 +380,7 @@ impl State for MemoryState {
         //     .map(|v| format!("{} {}", v.0, v.1.len()))
         //     .collect::<Vec<_>>());
 
-        // dbg!(&self.votes);
+        // dbg!(&inner.votes);
 
         // println!("XXXXXX {:?} {:?}", hash, representative);
 
@@ -89,11 +388,12 @@ impl State for MemoryState {
     }
 
     async fn set_cookie(
-        &mut self,
+        &self,
         socket_addr: SocketAddr,
         cookie: Cookie,
     ) -> Result<(), anyhow::Error> {
-        self.cookies.insert(socket_addr, cookie);
+        let mut inner = self.inner.lock().unwrap();
+        inner.cookies.insert(socket_addr, cookie);
         Ok(())
     }
 
@@ -101,17 +401,275 @@ impl State for MemoryState {
         &self,
         socket_addr: &SocketAddr,
     ) -> Result<Option<Cookie>, anyhow::Error> {
-        Ok(self.cookies.get(&socket_addr).map(|c| c.to_owned()))
+        let inner = self.inner.lock().unwrap();
+        Ok(inner.cookies.get(socket_addr).map(|c| c.to_owned()))
+    }
+
+    async fn set_node_id(&self, socket_addr: SocketAddr, node_id: Public) -> anyhow::Result<()> {
+        let mut inner = self.inner.lock().unwrap();
+        inner.node_ids.insert(socket_addr, node_id);
+        Ok(())
     }
 
-    async fn add_peers(&mut self, addresses: &[SocketAddr]) -> Result<(), anyhow::Error> {
+    async fn node_id_for_socket_addr(
+        &self,
+        socket_addr: &SocketAddr,
+    ) -> anyhow::Result<Option<Public>> {
+        let inner = self.inner.lock().unwrap();
+        Ok(inner.node_ids.get(socket_addr).cloned())
+    }
+
+    async fn add_peers(&self, addresses: &[SocketAddr]) -> Result<(), anyhow::Error> {
+        let mut inner = self.inner.lock().unwrap();
+        let now = Utc::now();
         for address in addresses {
-            self.peers.insert(address.to_owned());
+            inner.peers.insert(address.to_owned(), now);
         }
         Ok(())
     }
 
     async fn peers(&self) -> Result<HashSet<SocketAddr>, anyhow::Error> {
-        Ok(self.peers.clone())
+        let inner = self.inner.lock().unwrap();
+        Ok(inner.peers.keys().cloned().collect())
+    }
+
+    async fn peer_table(&self) -> anyhow::Result<Vec<KnownPeer>> {
+        let inner = self.inner.lock().unwrap();
+        let mut peers: Vec<KnownPeer> = inner
+            .peers
+            .iter()
+            .map(|(address, last_seen)| KnownPeer {
+                address: address.to_owned(),
+                last_seen: last_seen.to_owned(),
+            })
+            .collect();
+        peers.sort_by_key(|peer| std::cmp::Reverse(peer.last_seen));
+        Ok(peers)
+    }
+
+    async fn expire_peers(&self, cutoff: DateTime<Utc>) -> anyhow::Result<Vec<SocketAddr>> {
+        let mut inner = self.inner.lock().unwrap();
+        let expired: Vec<SocketAddr> = inner
+            .peers
+            .iter()
+            .filter(|(_, last_seen)| **last_seen < cutoff)
+            .map(|(address, _)| address.to_owned())
+            .collect();
+        // An expired peer might come back as a restarted node with a new node ID.
+        for address in &expired {
+            inner.peers.remove(address);
+            inner.cookies.remove(address);
+            inner.node_ids.remove(address);
+        }
+        Ok(expired)
+    }
+
+    async fn set_probe_status(
+        &self,
+        socket_addr: SocketAddr,
+        status: ProbeStatus,
+    ) -> anyhow::Result<()> {
+        let mut inner = self.inner.lock().unwrap();
+        inner.probes.insert(socket_addr, status);
+        Ok(())
+    }
+
+    async fn probe_status_for_socket_addr(
+        &self,
+        socket_addr: &SocketAddr,
+    ) -> anyhow::Result<Option<ProbeStatus>> {
+        let inner = self.inner.lock().unwrap();
+        Ok(inner.probes.get(socket_addr).cloned())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blocks::{Link, Previous, StateBlock};
+    use crate::node::state::ArcState;
+    use crate::{Raw, Seed};
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn peer_table() {
+        let state = MemoryState::new(Network::Test);
+        let first: SocketAddr = "127.0.0.1:7075".parse().unwrap();
+        let second: SocketAddr = "127.0.0.2:7075".parse().unwrap();
+        state.add_peers(&[first]).await.unwrap();
+        tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+        let cutoff = Utc::now();
+        state.add_peers(&[second]).await.unwrap();
+
+        let table = state.peer_table().await.unwrap();
+        assert_eq!(table[0].address, second);
+        assert_eq!(table[1].address, first);
+
+        assert_eq!(state.expire_peers(cutoff).await.unwrap(), vec![first]);
+        assert_eq!(state.peers().await.unwrap().len(), 1);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn shared_without_a_lock() {
+        let state: ArcState = Arc::new(MemoryState::new(Network::Test));
+        let tasks: Vec<_> = (0..8)
+            .map(|index| {
+                let state = state.clone();
+                tokio::spawn(async move {
+                    let account = Seed::zero().derive(index).to_public().unwrap();
+                    let open = Block::from_state_block(&StateBlock::new(
+                        account.to_owned(),
+                        Previous::Open,
+                        account,
+                        Raw::zero(),
+                        Link::Nothing,
+                    ));
+                    state.add_block(&open).await.unwrap();
+                })
+            })
+            .collect();
+        for task in tasks {
+            task.await.unwrap();
+        }
+        assert_eq!(state.block_count().await.unwrap(), 8);
+        assert_eq!(state.accounts().await.unwrap().len(), 8);
+    }
+
+    #[tokio::test]
+    async fn keeps_frontiers() {
+        let budget = MemoryBudget {
+            blocks: 2,
+            ..MemoryBudget::default()
+        };
+        let state = MemoryState::with_budget(Network::Test, &budget);
+        let account = |index| Seed::zero().derive(index).to_public().unwrap();
+        let block = |account: &Public, previous| {
+            Block::from_state_block(&StateBlock::new(
+                account.to_owned(),
+                previous,
+                account.to_owned(),
+                Raw::zero(),
+                Link::Nothing,
+            ))
+        };
+
+        let first = block(&account(0), Previous::Open);
+        let frontier = block(&account(1), Previous::Open);
+        let second = block(
+            &account(0),
+            Previous::Block(first.hash().unwrap().to_owned()),
+        );
+        for b in &[&frontier, &first, &second] {
+            state.add_block(b).await.unwrap();
+        }
+
+        // The oldest block is a frontier so the next oldest goes instead.
+        let has = |b: &Block| {
+            state
+                .inner
+                .lock()
+                .unwrap()
+                .blocks
+                .contains_key(b.hash().unwrap())
+        };
+        assert!(has(&frontier));
+        assert!(!has(&first));
+        assert!(has(&second));
+        assert!(state
+            .account_for_block_hash(first.hash().unwrap())
+            .await
+            .unwrap()
+            .is_none());
+    }
+
+    #[tokio::test]
+    async fn rep_history() {
+        let state = MemoryState::new(Network::Test);
+        let public = |index| Seed::zero().derive(index).to_public().unwrap();
+        let (account, rep) = (public(0), public(1));
+        let block = |previous: Option<&Block>, representative: &Public| {
+            let previous = match previous {
+                Some(b) => Previous::Block(b.hash().unwrap().to_owned()),
+                None => Previous::Open,
+            };
+            Block::from_state_block(&StateBlock::new(
+                account.to_owned(),
+                previous,
+                representative.to_owned(),
+                Raw::zero(),
+                Link::Nothing,
+            ))
+        };
+
+        let open = block(None, &account);
+        let same = block(Some(&open), &account);
+        let change = block(Some(&same), &rep);
+        for b in &[&open, &same, &change, &change] {
+            state.add_block(b).await.unwrap();
+        }
+        let history = state.rep_history(&account).await.unwrap();
+        let summary: Vec<_> = history
+            .iter()
+            .map(|c| (&c.hash, c.old.as_ref(), &c.new))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (open.hash().unwrap(), None, &account),
+                (change.hash().unwrap(), Some(&account), &rep),
+            ]
+        );
+
+        state.rollback_block(change.hash().unwrap()).await.unwrap();
+        assert_eq!(state.rep_history(&account).await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn account_info() {
+        let state = MemoryState::new(Network::Test);
+        let public = |index| Seed::zero().derive(index).to_public().unwrap();
+        let (account, rep) = (public(0), public(1));
+        let block = |previous: Option<&Block>, representative: &Public, balance: u128| {
+            let previous = match previous {
+                Some(b) => Previous::Block(b.hash().unwrap().to_owned()),
+                None => Previous::Open,
+            };
+            Block::from_state_block(&StateBlock::new(
+                account.to_owned(),
+                previous,
+                representative.to_owned(),
+                Raw::from(balance),
+                Link::Nothing,
+            ))
+        };
+
+        let open = block(None, &account, 10);
+        let change = block(Some(&open), &rep, 10);
+        let send = block(Some(&change), &rep, 4);
+        for b in &[&open, &change, &send, &send] {
+            state.add_block(b).await.unwrap();
+        }
+        let info = state.account_info(&account).await.unwrap().unwrap();
+        assert_eq!(&info.head, send.hash().unwrap());
+        assert_eq!(info.open_block.as_ref(), Some(open.hash().unwrap()));
+        assert_eq!(info.balance, Raw::from(4));
+        assert_eq!(info.representative, rep);
+        assert_eq!(info.block_count, 3);
+
+        state.rollback_block(send.hash().unwrap()).await.unwrap();
+        let info = state.account_info(&account).await.unwrap().unwrap();
+        assert_eq!(&info.head, change.hash().unwrap());
+        assert_eq!(info.balance, Raw::from(10));
+        assert_eq!(info.block_count, 2);
+
+        state.rollback_block(change.hash().unwrap()).await.unwrap();
+        state.rollback_block(open.hash().unwrap()).await.unwrap();
+        assert_eq!(state.account_info(&account).await.unwrap(), None);
+
+        // Without the open block the start of the chain isn't known.
+        state.add_block(&send).await.unwrap();
+        let info = state.account_info(&account).await.unwrap().unwrap();
+        assert_eq!(info.open_block, None);
+        assert_eq!(info.block_count, 1);
     }
 }
@@ -1,26 +1,192 @@
 mod memory;
 mod sled_disk;
 
-use crate::blocks::{Block, BlockHash};
-use crate::node::cookie::Cookie;
-use crate::Public;
+use crate::blocks::{Block, BlockHash, Previous};
+pub use crate::node::cookie::Cookie;
+pub use crate::node::pending::Pending;
+pub use crate::node::probe::ProbeStatus;
+pub use crate::rpc::calls::KnownPeer;
+use crate::{Difficulty, Public, Raw};
 use async_trait::async_trait;
+use chrono::{DateTime, Utc};
 pub use memory::MemoryState;
+use serde::{Deserialize, Serialize};
 pub use sled_disk::SledDiskState;
 use std::collections::HashSet;
 use std::fmt::Debug;
 use std::net::SocketAddr;
 use std::sync::Arc;
-use tokio::sync::Mutex;
 
-pub type DynState = dyn State + Send + Sync;
-pub type ArcState = Arc<Mutex<DynState>>;
+pub type DynState = dyn State;
+
+/// The state shared by the node, its peers and the RPC server.
+pub type ArcState = Arc<DynState>;
+
+/// A change of representative made by a block, including the first one set by an open block.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RepChange {
+    pub hash: BlockHash,
+
+    /// Seconds since the Unix epoch of when the block was added.
+    pub timestamp: u64,
+
+    /// `None` for an open block.
+    pub old: Option<Public>,
+    pub new: Public,
+}
+
+impl RepChange {
+    /// The change made by `block`, or `None` when it didn't change the representative. Without
+    /// the `previous` block of a non-open block there's no way to tell.
+    fn for_block(block: &Block, previous: Option<&Block>, timestamp: u64) -> Option<Self> {
+        let old = match (block.previous(), previous) {
+            (Previous::Open, _) => None,
+            (Previous::Block(_), Some(previous))
+                if previous.representative() != block.representative() =>
+            {
+                Some(previous.representative().to_owned())
+            }
+            (Previous::Block(_), _) => return None,
+        };
+        Some(Self {
+            hash: block.hash().ok()?.to_owned(),
+            timestamp,
+            old,
+            new: block.representative().to_owned(),
+        })
+    }
+}
+
+/// What we work out about a block when it's added, like the sideband of the reference node.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Sideband {
+    /// Seconds since the Unix epoch of when the block was added.
+    pub timestamp: u64,
+
+    /// The difficulty of the block's work against its root, which can be well over the
+    /// threshold. `None` for blocks stored without work.
+    pub difficulty: Option<Difficulty>,
+
+    /// Whether the block added to the balance, so needed less work since epoch 2.
+    pub receive: bool,
+
+    /// Where the block is in its account's chain, with the open block at 1. `None` when the
+    /// previous block's height wasn't known, e.g. for blocks stored before heights were.
+    #[serde(default)]
+    pub height: Option<u64>,
+}
+
+impl Sideband {
+    /// `previous_height` is the height in the sideband of the previous block, if it has one.
+    fn for_block(
+        block: &Block,
+        previous: Option<&Block>,
+        previous_height: Option<u64>,
+        timestamp: u64,
+    ) -> anyhow::Result<Self> {
+        let difficulty = match block.work() {
+            Some(work) => Some(work.difficulty(&block.work_root())?),
+            None => None,
+        };
+        let receive = match previous {
+            Some(previous) => block.balance() > previous.balance(),
+            // Open blocks always receive.
+            None => *block.previous() == Previous::Open,
+        };
+        let height = match block.previous() {
+            Previous::Open => Some(1),
+            Previous::Block(_) => previous_height.map(|h| h + 1),
+        };
+        Ok(Self {
+            timestamp,
+            difficulty,
+            receive,
+            height,
+        })
+    }
+}
+
+/// What we know about an account, kept up to date as its blocks are added and rolled back, like
+/// the account info of the reference node.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AccountInfo {
+    /// The frontier.
+    pub head: BlockHash,
+
+    /// `None` when the account's blocks were added starting after its open block, e.g. when
+    /// they came from a frontier bootstrap.
+    pub open_block: Option<BlockHash>,
+
+    /// The balance and representative as of the head block.
+    pub balance: Raw,
+    pub representative: Public,
+
+    /// Blocks from the open block to the head. Only counts from the first block added when
+    /// the open block isn't known.
+    pub block_count: u64,
+
+    /// Seconds since the Unix epoch of when the head was added.
+    pub modified: u64,
+}
+
+impl AccountInfo {
+    /// The info after `block` is added, given the `existing` info of its account.
+    fn for_block(
+        existing: Option<&AccountInfo>,
+        block: &Block,
+        hash: &BlockHash,
+        modified: u64,
+    ) -> Self {
+        let (open_block, block_count) = match (block.previous(), existing) {
+            (Previous::Open, _) => (Some(hash.to_owned()), 1),
+            // Adding the same block again doesn't change anything.
+            (_, Some(existing)) if &existing.head == hash => {
+                (existing.open_block.to_owned(), existing.block_count)
+            }
+            (Previous::Block(previous), Some(existing)) if &existing.head == previous => {
+                (existing.open_block.to_owned(), existing.block_count + 1)
+            }
+            (Previous::Block(_), _) => (None, 1),
+        };
+        Self {
+            head: hash.to_owned(),
+            open_block,
+            balance: block.balance().to_owned(),
+            representative: block.representative().to_owned(),
+            block_count,
+            modified,
+        }
+    }
+
+    /// The info after the head is rolled back to `previous`, or `None` when there's nothing
+    /// left of the account that we know of.
+    fn rolled_back(&self, previous: Option<(&BlockHash, &Block)>) -> Option<Self> {
+        let (hash, block) = previous?;
+        if self.block_count <= 1 {
+            return None;
+        }
+        Some(Self {
+            head: hash.to_owned(),
+            open_block: self.open_block.to_owned(),
+            balance: block.balance().to_owned(),
+            representative: block.representative().to_owned(),
+            block_count: self.block_count - 1,
+            modified: self.modified,
+        })
+    }
+}
 
 /// State contains a state of the Nano block lattice 🥬,
 /// it also contains ephemeral information like peers.
+///
+/// Every method takes `&self` so the state can be shared as an [ArcState] without a lock around
+/// it, which means implementations handle their own locking, like [MemoryState] does, or use a
+/// store that's safe to share, like [SledDiskState]. Calls aren't atomic with each other. Another
+/// backend, e.g. Postgres, only needs to implement this trait and be given to
+/// [crate::Node::with_state].
 #[async_trait]
 pub trait State: Debug + Sync + Send + 'static {
-    async fn add_block(&mut self, block: &Block) -> anyhow::Result<()>;
+    async fn add_block(&self, block: &Block) -> anyhow::Result<()>;
 
     async fn get_block_by_hash(&self, hash: &BlockHash) -> anyhow::Result<Option<Block>>;
 
@@ -30,20 +196,336 @@ pub trait State: Debug + Sync + Send + 'static {
     ) -> anyhow::Result<Option<BlockHash>>;
 
     async fn account_for_block_hash(
-        &mut self,
+        &self,
         block_hash: &BlockHash,
     ) -> anyhow::Result<Option<Public>>;
 
-    async fn add_vote(&mut self, hash: &BlockHash, representative: &Public) -> anyhow::Result<()>;
+    async fn account_info(&self, account: &Public) -> anyhow::Result<Option<AccountInfo>>;
+
+    /// Accounts that have at least one block.
+    async fn accounts(&self) -> anyhow::Result<Vec<Public>>;
+
+    /// Up to `limit` of [State::accounts] in order, starting after `after`, for walking the
+    /// ledger without holding every account at once.
+    async fn accounts_after(
+        &self,
+        after: Option<&Public>,
+        limit: usize,
+    ) -> anyhow::Result<Vec<Public>>;
+
+    /// The height of the highest confirmed block of an account, where the open block has a
+    /// height of 1. Blocks at or below this height have already been validated.
+    async fn cemented_height(&self, account: &Public) -> anyhow::Result<Option<u64>>;
+
+    async fn set_cemented_height(&self, account: &Public, height: u64) -> anyhow::Result<()>;
+
+    /// Number of blocks in the ledger, including ones evicted from a cache.
+    async fn block_count(&self) -> anyhow::Result<u64>;
+
+    /// Number of blocks at or below the cemented height of their account.
+    async fn cemented_count(&self) -> anyhow::Result<u64>;
+
+    /// When the frontier of an account last changed, in seconds since the Unix epoch, like the
+    /// timestamp in the block sideband of the reference node.
+    async fn account_modified(&self, account: &Public) -> anyhow::Result<Option<u64>>;
+
+    async fn sideband(&self, hash: &BlockHash) -> anyhow::Result<Option<Sideband>>;
+
+    /// The sidebands of the blocks added at or after `timestamp`, in seconds since the Unix
+    /// epoch, in no particular order.
+    async fn sidebands_since(&self, timestamp: u64) -> anyhow::Result<Vec<Sideband>>;
+
+    /// Every change of representative of an account, oldest first. Kept up to date as blocks
+    /// are added and rolled back.
+    async fn rep_history(&self, account: &Public) -> anyhow::Result<Vec<RepChange>>;
+
+    /// Remove a block that isn't the frontier of its account, along with its sideband, to save
+    /// space. Unlike a rollback the account's chain doesn't change, and the block still counts
+    /// as existing. See [crate::node::pruning].
+    async fn prune_block(&self, hash: &BlockHash) -> anyhow::Result<()>;
+
+    /// Whether `hash` was removed by [State::prune_block].
+    async fn is_pruned(&self, hash: &BlockHash) -> anyhow::Result<bool>;
 
-    async fn set_cookie(&mut self, socket_addr: SocketAddr, cookie: Cookie) -> anyhow::Result<()>;
+    /// Remove the frontier block of an account, making its previous block the frontier again.
+    /// Fails for any other block. Use [crate::node::pending::rollback] to keep the pending
+    /// entries in step.
+    async fn rollback_block(&self, hash: &BlockHash) -> anyhow::Result<()>;
+
+    /// Add an entry for an amount that hasn't been received yet, replacing any entry with the
+    /// same destination and source.
+    async fn add_pending(&self, pending: &Pending) -> anyhow::Result<()>;
+
+    /// Remove the entry for `source` sent to `destination`, returning it if there was one.
+    async fn remove_pending(
+        &self,
+        destination: &Public,
+        source: &BlockHash,
+    ) -> anyhow::Result<Option<Pending>>;
+
+    /// The entries waiting to be received by `destination`.
+    async fn pending(&self, destination: &Public) -> anyhow::Result<Vec<Pending>>;
+
+    async fn all_pending(&self) -> anyhow::Result<Vec<Pending>>;
+
+    async fn add_vote(&self, hash: &BlockHash, representative: &Public) -> anyhow::Result<()>;
+
+    async fn set_cookie(&self, socket_addr: SocketAddr, cookie: Cookie) -> anyhow::Result<()>;
 
     async fn cookie_for_socket_addr(
         &self,
         socket_addr: &SocketAddr,
     ) -> anyhow::Result<Option<Cookie>>;
 
-    async fn add_peers(&mut self, addresses: &[SocketAddr]) -> anyhow::Result<()>;
+    /// The node ID a peer at `socket_addr` established a handshake with.
+    async fn set_node_id(&self, socket_addr: SocketAddr, node_id: Public) -> anyhow::Result<()>;
+
+    async fn node_id_for_socket_addr(
+        &self,
+        socket_addr: &SocketAddr,
+    ) -> anyhow::Result<Option<Public>>;
+
+    /// Add peers to the peer table, or mark them as seen now if they're already in it.
+    async fn add_peers(&self, addresses: &[SocketAddr]) -> anyhow::Result<()>;
 
     async fn peers(&self) -> anyhow::Result<HashSet<SocketAddr>>;
+
+    /// Every peer with when it was last seen, most recent first.
+    async fn peer_table(&self) -> anyhow::Result<Vec<KnownPeer>>;
+
+    /// Remove the peers that haven't been seen since `cutoff`, returning them.
+    async fn expire_peers(&self, cutoff: DateTime<Utc>) -> anyhow::Result<Vec<SocketAddr>>;
+
+    async fn set_probe_status(
+        &self,
+        socket_addr: SocketAddr,
+        status: ProbeStatus,
+    ) -> anyhow::Result<()>;
+
+    async fn probe_status_for_socket_addr(
+        &self,
+        socket_addr: &SocketAddr,
+    ) -> anyhow::Result<Option<ProbeStatus>>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blocks::{Link, StateBlock};
+    use crate::{Network, Seed};
+    use std::time::Duration;
+
+    /// Every backend, so each test checks they behave the same.
+    fn backends() -> Vec<ArcState> {
+        vec![
+            Arc::new(MemoryState::new(Network::Test)),
+            Arc::new(SledDiskState::temporary(Network::Test)),
+        ]
+    }
+
+    fn public(index: u32) -> Public {
+        Seed::zero().derive(index).to_public().unwrap()
+    }
+
+    fn block(account: &Public, previous: Option<&Block>, balance: u128) -> Block {
+        let previous = match previous {
+            Some(b) => Previous::Block(b.hash().unwrap().to_owned()),
+            None => Previous::Open,
+        };
+        Block::from_state_block(&StateBlock::new(
+            account.to_owned(),
+            previous,
+            account.to_owned(),
+            Raw::from(balance),
+            Link::Nothing,
+        ))
+    }
+
+    #[tokio::test]
+    async fn blocks_and_accounts() {
+        for state in backends() {
+            let (first, second) = (public(0), public(1));
+            let open = block(&first, None, 10);
+            let send = block(&first, Some(&open), 4);
+            let other = block(&second, None, 1);
+            for b in &[&open, &send, &other] {
+                state.add_block(b).await.unwrap();
+            }
+            let (open_hash, send_hash) = (open.hash().unwrap(), send.hash().unwrap());
+
+            assert_eq!(
+                state.get_block_by_hash(send_hash).await.unwrap(),
+                Some(send.clone())
+            );
+            assert_eq!(
+                state
+                    .get_latest_block_hash_for_account(&first)
+                    .await
+                    .unwrap()
+                    .as_ref(),
+                Some(send_hash)
+            );
+            assert_eq!(
+                state.account_for_block_hash(open_hash).await.unwrap(),
+                Some(first.clone())
+            );
+            assert_eq!(state.block_count().await.unwrap(), 3);
+            assert_eq!(
+                state.sideband(send_hash).await.unwrap().unwrap().height,
+                Some(2)
+            );
+
+            let info = state.account_info(&first).await.unwrap().unwrap();
+            assert_eq!((&info.head, info.block_count), (send_hash, 2));
+            assert_eq!(info.balance, Raw::from(4));
+            assert_eq!(
+                state.account_modified(&first).await.unwrap(),
+                Some(info.modified)
+            );
+
+            let mut accounts = vec![first.clone(), second.clone()];
+            accounts.sort();
+            let mut all = state.accounts().await.unwrap();
+            all.sort();
+            assert_eq!(all, accounts);
+            assert_eq!(state.accounts_after(None, 1).await.unwrap(), &accounts[..1]);
+            assert_eq!(
+                state.accounts_after(Some(&accounts[0]), 5).await.unwrap(),
+                &accounts[1..]
+            );
+            assert!(state
+                .accounts_after(Some(&accounts[1]), 5)
+                .await
+                .unwrap()
+                .is_empty());
+
+            assert!(state.rollback_block(open_hash).await.is_err());
+            state.rollback_block(send_hash).await.unwrap();
+            assert!(state.get_block_by_hash(send_hash).await.unwrap().is_none());
+            let info = state.account_info(&first).await.unwrap().unwrap();
+            assert_eq!((&info.head, info.balance), (open_hash, Raw::from(10)));
+        }
+    }
+
+    #[tokio::test]
+    async fn cemented_and_pruned() {
+        for state in backends() {
+            let account = public(0);
+            let open = block(&account, None, 10);
+            let send = block(&account, Some(&open), 4);
+            state.add_block(&open).await.unwrap();
+            state.add_block(&send).await.unwrap();
+
+            assert_eq!(state.cemented_height(&account).await.unwrap(), None);
+            state.set_cemented_height(&account, 1).await.unwrap();
+            assert_eq!(state.cemented_height(&account).await.unwrap(), Some(1));
+            assert_eq!(state.cemented_count().await.unwrap(), 1);
+
+            assert!(state.prune_block(send.hash().unwrap()).await.is_err());
+            let open_hash = open.hash().unwrap();
+            state.prune_block(open_hash).await.unwrap();
+            assert!(state.is_pruned(open_hash).await.unwrap());
+            assert!(state.get_block_by_hash(open_hash).await.unwrap().is_none());
+            assert_eq!(state.block_count().await.unwrap(), 2);
+        }
+    }
+
+    #[tokio::test]
+    async fn pending() {
+        for state in backends() {
+            let source = block(&public(0), None, 10);
+            let source = source.hash().unwrap();
+            let pending = Pending {
+                destination: public(1),
+                source: source.to_owned(),
+                amount: Raw::from(5),
+            };
+            let other = Pending {
+                destination: public(2),
+                ..pending.clone()
+            };
+            state.add_pending(&pending).await.unwrap();
+            state.add_pending(&pending).await.unwrap();
+            state.add_pending(&other).await.unwrap();
+            assert_eq!(state.all_pending().await.unwrap().len(), 2);
+            assert_eq!(
+                state.pending(&public(1)).await.unwrap(),
+                vec![pending.clone()]
+            );
+            assert_eq!(
+                state.remove_pending(&public(1), source).await.unwrap(),
+                Some(pending)
+            );
+            assert_eq!(
+                state.remove_pending(&public(1), source).await.unwrap(),
+                None
+            );
+            assert_eq!(state.all_pending().await.unwrap(), vec![other]);
+        }
+    }
+
+    #[tokio::test]
+    async fn peers() {
+        for state in backends() {
+            let first: SocketAddr = "127.0.0.1:7075".parse().unwrap();
+            let second: SocketAddr = "[::1]:7075".parse().unwrap();
+            state.add_peers(&[first]).await.unwrap();
+            state.set_cookie(first, Cookie::random()).await.unwrap();
+            state.set_node_id(first, public(0)).await.unwrap();
+            state
+                .set_probe_status(first, ProbeStatus::new(true))
+                .await
+                .unwrap();
+            tokio::time::sleep(Duration::from_millis(5)).await;
+            let cutoff = Utc::now();
+            state.add_peers(&[second]).await.unwrap();
+
+            assert!(state
+                .cookie_for_socket_addr(&first)
+                .await
+                .unwrap()
+                .is_some());
+            assert_eq!(
+                state.node_id_for_socket_addr(&first).await.unwrap(),
+                Some(public(0))
+            );
+            let table: Vec<_> = state
+                .peer_table()
+                .await
+                .unwrap()
+                .into_iter()
+                .map(|peer| peer.address)
+                .collect();
+            assert_eq!(table, vec![second, first]);
+
+            assert_eq!(state.expire_peers(cutoff).await.unwrap(), vec![first]);
+            assert_eq!(
+                state.peers().await.unwrap(),
+                vec![second].into_iter().collect()
+            );
+            assert!(state
+                .cookie_for_socket_addr(&first)
+                .await
+                .unwrap()
+                .is_none());
+            assert_eq!(state.node_id_for_socket_addr(&first).await.unwrap(), None);
+            assert!(
+                state
+                    .probe_status_for_socket_addr(&first)
+                    .await
+                    .unwrap()
+                    .unwrap()
+                    .reachable
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn votes() {
+        for state in backends() {
+            let hash = block(&public(0), None, 1).hash().unwrap().to_owned();
+            state.add_vote(&hash, &public(1)).await.unwrap();
+            state.add_vote(&hash, &public(1)).await.unwrap();
+        }
+    }
 }
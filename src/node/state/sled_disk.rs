@@ -1,12 +1,24 @@
-use crate::blocks::{Block, BlockHash};
+use crate::blocks::{Block, BlockHash, Previous};
 use crate::network::Network;
 use crate::node::cookie::Cookie;
-use crate::node::state::State;
-use crate::Public;
+use crate::node::pending::Pending;
+use crate::node::probe::ProbeStatus;
+use crate::node::state::{AccountInfo, RepChange, Sideband, State};
+use crate::rpc::calls::KnownPeer;
+use crate::{Public, Raw};
+use anyhow::{anyhow, Context};
 use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sled::transaction::{ConflictableTransactionError, TransactionError, Transactional};
 use std::collections::HashSet;
 use std::convert::TryFrom;
+use std::fmt::{Display, Formatter};
+use std::fs;
 use std::net::SocketAddr;
+use std::ops::Bound;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
 
 /// Sled is an on disk key value pair.
 #[derive(Clone, Debug)]
@@ -14,58 +26,502 @@ pub struct SledDiskState {
     network: Network,
     db: sled::Db,
     cookies: sled::Tree,
+
+    /// The node ID each peer proved in its handshake, by address.
+    node_ids: sled::Tree,
+
+    /// When each peer was last seen as JSON, by address.
     peers: sled::Tree,
+
+    /// Blocks and their sidebands as JSON by their hash.
+    blocks: sled::Tree,
+    sidebands: sled::Tree,
+
+    /// The hash of the latest block of each account.
+    frontiers: sled::Tree,
+
+    /// Big endian heights by account.
+    cemented_heights: sled::Tree,
+
+    /// Each account's [AccountInfo] as JSON.
+    account_infos: sled::Tree,
+
+    /// Big endian amounts by destination account followed by the hash of the send block.
+    pending: sled::Tree,
+
+    /// Each account's changes of representative, as a JSON array.
+    rep_history: sled::Tree,
+
+    /// The hashes of blocks removed by pruning, with empty values.
+    pruned: sled::Tree,
+
+    /// The last [ProbeStatus] of each learned peer as JSON, by address.
+    probes: sled::Tree,
+
+    /// Block hashes followed by the representative that voted for them, with empty values.
+    votes: sled::Tree,
+
+    /// Opened with [SledDiskState::open_read_only], so every write fails.
+    read_only: bool,
 }
 
+/// Trees that only hold data for the lifetime of a running node, which compaction drops.
+const EPHEMERAL_TREES: &[&str] = &["cookies", "node_ids", "votes"];
+
 impl SledDiskState {
     pub fn new(network: Network) -> Self {
-        let path = format!("{:?}.db", network).to_ascii_lowercase();
-        let db: sled::Db =
-            sled::open(&path).unwrap_or_else(|_| panic!("Could not open database: {}", &path));
-        let cookies = db.open_tree("cookies").unwrap();
-        let peers = db.open_tree("peers").unwrap();
-        Self {
+        Self::open(network, &Self::default_path(network)).unwrap_or_else(|err| panic!("{:?}", err))
+    }
+
+    /// Open or create the database at `path`.
+    pub fn open(network: Network, path: &Path) -> anyhow::Result<Self> {
+        Self::from_db(network, open(path)?, false)
+    }
+
+    /// Open the existing database at `path` in place, failing every write instead of changing
+    /// it.
+    ///
+    /// Sled only lets one process open a database, so this fails while a node is running on it.
+    /// A consistent copy of a running node's ledger can be exported with `feeless node persist`
+    /// and read instead.
+    pub fn open_read_only(network: Network, path: &Path) -> anyhow::Result<Self> {
+        if !path.exists() {
+            return Err(anyhow!("Database {:?} does not exist", path));
+        }
+        Self::from_db(network, open(path)?, true)
+    }
+
+    /// A database that's removed when it's dropped.
+    #[cfg(test)]
+    pub(crate) fn temporary(network: Network) -> Self {
+        let db = sled::Config::new().temporary(true).open().unwrap();
+        Self::from_db(network, db, false).unwrap()
+    }
+
+    fn from_db(network: Network, db: sled::Db, read_only: bool) -> anyhow::Result<Self> {
+        Ok(Self {
             network,
+            cookies: db.open_tree("cookies")?,
+            node_ids: db.open_tree("node_ids")?,
+            peers: db.open_tree("peers")?,
+            blocks: db.open_tree("blocks")?,
+            sidebands: db.open_tree("sidebands")?,
+            frontiers: db.open_tree("frontiers")?,
+            cemented_heights: db.open_tree("cemented_heights")?,
+            account_infos: db.open_tree("account_info")?,
+            pending: db.open_tree("pending")?,
+            rep_history: db.open_tree("rep_history")?,
+            pruned: db.open_tree("pruned")?,
+            probes: db.open_tree("probes")?,
+            votes: db.open_tree("votes")?,
             db,
-            cookies,
-            peers,
+            read_only,
+        })
+    }
+
+    fn writable(&self) -> anyhow::Result<()> {
+        if self.read_only {
+            return Err(anyhow!("Database is open read-only"));
+        }
+        Ok(())
+    }
+
+    /// Write everything to disk, which otherwise happens in the background every so often.
+    pub async fn flush(&self) -> anyhow::Result<()> {
+        self.db.flush_async().await?;
+        Ok(())
+    }
+
+    pub fn default_path(network: Network) -> PathBuf {
+        format!("{:?}.db", network).to_ascii_lowercase().into()
+    }
+
+    /// Collect statistics about each tree in the database at `path`, without changing it.
+    pub fn stats(path: &Path) -> anyhow::Result<DbStats> {
+        if !path.exists() {
+            return Err(anyhow::anyhow!("Database {:?} does not exist", path));
         }
+        let db = open(path)?;
+
+        let mut trees = vec![];
+        for name in db.tree_names() {
+            let tree = db.open_tree(&name)?;
+            let mut stats = TreeStats {
+                name: String::from_utf8_lossy(&name).into_owned(),
+                entries: 0,
+                key_bytes: 0,
+                value_bytes: 0,
+            };
+            for kv in tree.iter() {
+                let (k, v) = kv?;
+                stats.entries += 1;
+                stats.key_bytes += k.len() as u64;
+                stats.value_bytes += v.len() as u64;
+            }
+            trees.push(stats);
+        }
+
+        let count = |names: &[&str]| {
+            trees
+                .iter()
+                .filter(|t| names.contains(&t.name.as_str()))
+                .map(|t| t.entries)
+                .sum()
+        };
+        Ok(DbStats {
+            path: path.to_owned(),
+            size_on_disk: db.size_on_disk()?,
+            blocks: count(&["blocks"]),
+            pruning_candidates: count(EPHEMERAL_TREES),
+            trees,
+        })
+    }
+
+    /// Rewrite the database at `path` into a fresh one, reclaiming space from deleted entries and
+    /// dropping ephemeral data.
+    ///
+    /// The original is only replaced once the copy has been fully written.
+    pub fn compact(path: &Path) -> anyhow::Result<CompactStats> {
+        if !path.exists() {
+            return Err(anyhow::anyhow!("Database {:?} does not exist", path));
+        }
+        let compacted_path = path.with_extension("db.compact");
+        let old_path = path.with_extension("db.old");
+        for p in &[&compacted_path, &old_path] {
+            if p.exists() {
+                fs::remove_dir_all(p).with_context(|| format!("Removing leftover {:?}", p))?;
+            }
+        }
+
+        let (before, after) = {
+            let db = open(path)?;
+            let compacted = open(&compacted_path)?;
+            let export = db
+                .export()
+                .into_iter()
+                .filter(|(_, name, _)| !EPHEMERAL_TREES.iter().any(|e| e.as_bytes() == &name[..]))
+                .collect();
+            compacted.import(export);
+            compacted.flush()?;
+            (db.size_on_disk()?, compacted.size_on_disk()?)
+        };
+
+        fs::rename(path, &old_path).with_context(|| format!("Moving {:?} aside", path))?;
+        fs::rename(&compacted_path, path)
+            .with_context(|| format!("Moving {:?} to {:?}", compacted_path, path))?;
+        fs::remove_dir_all(&old_path).with_context(|| format!("Removing {:?}", old_path))?;
+        Ok(CompactStats { before, after })
     }
 }
 
 #[async_trait]
 impl State for SledDiskState {
-    async fn add_block(&mut self, _block_holder: &Block) -> anyhow::Result<()> {
-        unimplemented!()
+    async fn add_block(&self, block: &Block) -> anyhow::Result<()> {
+        self.writable()?;
+        let hash = block.hash().context("Add block")?;
+        let json = serde_json::to_vec(block)?;
+        let account = block.account().as_bytes();
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .context("Add block")?
+            .as_secs();
+        let previous = match block.previous() {
+            Previous::Block(previous) => self.get_block_by_hash(previous).await?,
+            Previous::Open => None,
+        };
+        let previous_height = match block.previous() {
+            Previous::Block(previous) => self.sideband(previous).await?.and_then(|s| s.height),
+            Previous::Open => None,
+        };
+        let change = RepChange::for_block(block, previous.as_ref(), now);
+        let sideband = Sideband::for_block(block, previous.as_ref(), previous_height, now)?;
+        let sideband = serde_json::to_vec(&sideband)?;
+
+        (
+            &self.blocks,
+            &self.sidebands,
+            &self.frontiers,
+            &self.account_infos,
+            &self.rep_history,
+            &self.pruned,
+        )
+            .transaction(
+                |(blocks, sidebands, frontiers, account_infos, rep_history, pruned)| {
+                    blocks.insert(hash.as_bytes(), json.as_slice())?;
+                    pruned.remove(hash.as_bytes())?;
+                    sidebands.insert(hash.as_bytes(), sideband.as_slice())?;
+                    frontiers.insert(account, hash.as_bytes())?;
+                    let existing = read_account_info(account_infos.get(account)?)
+                        .map_err(ConflictableTransactionError::Abort)?;
+                    let info = AccountInfo::for_block(existing.as_ref(), block, hash, now);
+                    account_infos.insert(account, write_account_info(&info))?;
+                    if let Some(change) = &change {
+                        let mut history = read_rep_history(rep_history.get(account)?)
+                            .map_err(ConflictableTransactionError::Abort)?;
+                        // Adding the same block again doesn't change anything.
+                        if history.last().map(|c| &c.hash) != Some(hash) {
+                            history.push(change.to_owned());
+                            rep_history.insert(account, write_rep_history(&history))?;
+                        }
+                    }
+                    Ok(())
+                },
+            )
+            .map_err(|err| match err {
+                TransactionError::Abort(err) => err.context("Add block"),
+                TransactionError::Storage(err) => anyhow!("Add block: {:?}", err),
+            })
     }
 
-    async fn get_block_by_hash(&self, _hash: &BlockHash) -> anyhow::Result<Option<Block>> {
-        unimplemented!()
+    async fn get_block_by_hash(&self, hash: &BlockHash) -> anyhow::Result<Option<Block>> {
+        match self.blocks.get(hash.as_bytes())? {
+            Some(json) => Ok(Some(
+                serde_json::from_slice(&json)
+                    .with_context(|| format!("Stored block {:?}", hash))?,
+            )),
+            None => Ok(None),
+        }
     }
 
     async fn get_latest_block_hash_for_account(
         &self,
-        _account: &Public,
+        account: &Public,
     ) -> anyhow::Result<Option<BlockHash>> {
-        unimplemented!()
+        match self.frontiers.get(account.as_bytes())? {
+            Some(hash) => Ok(Some(BlockHash::try_from(hash.as_ref())?)),
+            None => Ok(None),
+        }
     }
 
     async fn account_for_block_hash(
-        &mut self,
-        _block_hash: &BlockHash,
+        &self,
+        block_hash: &BlockHash,
     ) -> Result<Option<Public>, anyhow::Error> {
-        unimplemented!()
+        Ok(self
+            .get_block_by_hash(block_hash)
+            .await?
+            .map(|block| block.account().to_owned()))
     }
 
-    async fn add_vote(
-        &mut self,
-        _hash: &BlockHash,
-        _representative: &Public,
-    ) -> anyhow::Result<()> {
-        unimplemented!()
+    async fn account_info(&self, account: &Public) -> anyhow::Result<Option<AccountInfo>> {
+        read_account_info(self.account_infos.get(account.as_bytes())?)
+    }
+
+    async fn accounts(&self) -> anyhow::Result<Vec<Public>> {
+        let mut accounts = vec![];
+        for key in self.frontiers.iter().keys() {
+            accounts.push(Public::try_from(key?.as_ref())?);
+        }
+        Ok(accounts)
+    }
+
+    async fn accounts_after(
+        &self,
+        after: Option<&Public>,
+        limit: usize,
+    ) -> anyhow::Result<Vec<Public>> {
+        let keys = match after {
+            Some(after) => self
+                .frontiers
+                .range::<&[u8], _>((Bound::Excluded(after.as_bytes()), Bound::Unbounded)),
+            None => self.frontiers.iter(),
+        };
+        keys.keys()
+            .take(limit)
+            .map(|key| Ok(Public::try_from(key?.as_ref())?))
+            .collect()
+    }
+
+    async fn cemented_height(&self, account: &Public) -> anyhow::Result<Option<u64>> {
+        get_u64(&self.cemented_heights, account)
+    }
+
+    async fn set_cemented_height(&self, account: &Public, height: u64) -> anyhow::Result<()> {
+        self.writable()?;
+        self.cemented_heights
+            .insert(account.as_bytes(), &height.to_be_bytes())?;
+        Ok(())
+    }
+
+    async fn block_count(&self) -> anyhow::Result<u64> {
+        Ok((self.blocks.len() + self.pruned.len()) as u64)
+    }
+
+    async fn cemented_count(&self) -> anyhow::Result<u64> {
+        let mut count = 0;
+        for height in self.cemented_heights.iter().values() {
+            let height = height?;
+            let bytes = <[u8; 8]>::try_from(height.as_ref()).context("Stored cemented height")?;
+            count += u64::from_be_bytes(bytes);
+        }
+        Ok(count)
+    }
+
+    async fn account_modified(&self, account: &Public) -> anyhow::Result<Option<u64>> {
+        Ok(self.account_info(account).await?.map(|info| info.modified))
+    }
+
+    async fn sideband(&self, hash: &BlockHash) -> anyhow::Result<Option<Sideband>> {
+        match self.sidebands.get(hash.as_bytes())? {
+            Some(json) => Ok(Some(
+                serde_json::from_slice(&json)
+                    .with_context(|| format!("Stored sideband {:?}", hash))?,
+            )),
+            None => Ok(None),
+        }
+    }
+
+    async fn sidebands_since(&self, timestamp: u64) -> anyhow::Result<Vec<Sideband>> {
+        let mut found = vec![];
+        for json in self.sidebands.iter().values() {
+            let sideband: Sideband = serde_json::from_slice(&json?).context("Stored sideband")?;
+            if sideband.timestamp >= timestamp {
+                found.push(sideband);
+            }
+        }
+        Ok(found)
+    }
+
+    async fn rep_history(&self, account: &Public) -> anyhow::Result<Vec<RepChange>> {
+        read_rep_history(self.rep_history.get(account.as_bytes())?)
+    }
+
+    async fn prune_block(&self, hash: &BlockHash) -> anyhow::Result<()> {
+        self.writable()?;
+        let block = self
+            .get_block_by_hash(hash)
+            .await?
+            .ok_or_else(|| anyhow!("Block {:?} isn't stored", hash))?;
+        let account = block.account().as_bytes();
+        (&self.blocks, &self.sidebands, &self.frontiers, &self.pruned)
+            .transaction(|(blocks, sidebands, frontiers, pruned)| {
+                if frontiers.get(account)?.as_deref() == Some(hash.as_bytes()) {
+                    return Err(ConflictableTransactionError::Abort(anyhow!(
+                        "Block {:?} is the frontier of its account",
+                        hash
+                    )));
+                }
+                blocks.remove(hash.as_bytes())?;
+                sidebands.remove(hash.as_bytes())?;
+                pruned.insert(hash.as_bytes(), &[])?;
+                Ok(())
+            })
+            .map_err(|err| match err {
+                TransactionError::Abort(err) => err,
+                TransactionError::Storage(err) => anyhow!("Prune block: {:?}", err),
+            })
+    }
+
+    async fn is_pruned(&self, hash: &BlockHash) -> anyhow::Result<bool> {
+        Ok(self.pruned.contains_key(hash.as_bytes())?)
+    }
+
+    async fn rollback_block(&self, hash: &BlockHash) -> anyhow::Result<()> {
+        self.writable()?;
+        let block = self
+            .get_block_by_hash(hash)
+            .await?
+            .ok_or_else(|| anyhow!("Block {:?} isn't stored", hash))?;
+        let account = block.account().as_bytes();
+        let previous = match block.previous() {
+            Previous::Block(previous) => self
+                .get_block_by_hash(previous)
+                .await?
+                .map(|b| (previous, b)),
+            Previous::Open => None,
+        };
+        (
+            &self.blocks,
+            &self.sidebands,
+            &self.frontiers,
+            &self.account_infos,
+            &self.rep_history,
+        )
+            .transaction(
+                |(blocks, sidebands, frontiers, account_infos, rep_history)| {
+                    if frontiers.get(account)?.as_deref() != Some(hash.as_bytes()) {
+                        return Err(ConflictableTransactionError::Abort(anyhow!(
+                            "Block {:?} isn't the frontier of its account",
+                            hash
+                        )));
+                    }
+                    blocks.remove(hash.as_bytes())?;
+                    sidebands.remove(hash.as_bytes())?;
+                    let mut history = read_rep_history(rep_history.get(account)?)
+                        .map_err(ConflictableTransactionError::Abort)?;
+                    if history.last().map(|c| &c.hash) == Some(hash) {
+                        history.pop();
+                        rep_history.insert(account, write_rep_history(&history))?;
+                    }
+                    match block.previous() {
+                        Previous::Block(previous) => {
+                            frontiers.insert(account, previous.as_bytes())?;
+                        }
+                        Previous::Open => {
+                            frontiers.remove(account)?;
+                        }
+                    }
+                    let info = read_account_info(account_infos.get(account)?)
+                        .map_err(ConflictableTransactionError::Abort)?
+                        .and_then(|info| info.rolled_back(previous.as_ref().map(|(h, b)| (*h, b))));
+                    match info {
+                        Some(info) => account_infos.insert(account, write_account_info(&info))?,
+                        None => account_infos.remove(account)?,
+                    };
+                    Ok(())
+                },
+            )
+            .map_err(|err| match err {
+                TransactionError::Abort(err) => err,
+                TransactionError::Storage(err) => anyhow!("Roll back block: {:?}", err),
+            })
+    }
+
+    async fn add_pending(&self, pending: &Pending) -> anyhow::Result<()> {
+        self.writable()?;
+        self.pending.insert(
+            pending_key(&pending.destination, &pending.source),
+            pending.amount.to_vec(),
+        )?;
+        Ok(())
+    }
+
+    async fn remove_pending(
+        &self,
+        destination: &Public,
+        source: &BlockHash,
+    ) -> anyhow::Result<Option<Pending>> {
+        self.writable()?;
+        match self.pending.remove(pending_key(destination, source))? {
+            Some(amount) => Ok(Some(Pending {
+                destination: destination.to_owned(),
+                source: source.to_owned(),
+                amount: Raw::try_from(amount.as_ref())?,
+            })),
+            None => Ok(None),
+        }
+    }
+
+    async fn pending(&self, destination: &Public) -> anyhow::Result<Vec<Pending>> {
+        self.pending
+            .scan_prefix(destination.as_bytes())
+            .map(|kv| read_pending(kv?))
+            .collect()
     }
 
-    async fn set_cookie(&mut self, socket_addr: SocketAddr, cookie: Cookie) -> anyhow::Result<()> {
+    async fn all_pending(&self) -> anyhow::Result<Vec<Pending>> {
+        self.pending.iter().map(|kv| read_pending(kv?)).collect()
+    }
+
+    async fn add_vote(&self, hash: &BlockHash, representative: &Public) -> anyhow::Result<()> {
+        self.writable()?;
+        self.votes
+            .insert([hash.as_bytes(), representative.as_bytes()].concat(), &[])?;
+        Ok(())
+    }
+
+    async fn set_cookie(&self, socket_addr: SocketAddr, cookie: Cookie) -> anyhow::Result<()> {
+        self.writable()?;
         self.cookies
             .insert(format!("{}", socket_addr), cookie.as_bytes())?;
         Ok(())
@@ -82,11 +538,357 @@ impl State for SledDiskState {
         })
     }
 
-    async fn add_peers(&mut self, _addresses: &[SocketAddr]) -> Result<(), anyhow::Error> {
-        unimplemented!()
+    async fn set_node_id(&self, socket_addr: SocketAddr, node_id: Public) -> anyhow::Result<()> {
+        self.writable()?;
+        self.node_ids
+            .insert(format!("{}", socket_addr), node_id.as_bytes())?;
+        Ok(())
+    }
+
+    async fn node_id_for_socket_addr(
+        &self,
+        socket_addr: &SocketAddr,
+    ) -> anyhow::Result<Option<Public>> {
+        match self.node_ids.get(format!("{}", socket_addr))? {
+            Some(node_id) => Ok(Some(Public::try_from(node_id.as_ref())?)),
+            None => Ok(None),
+        }
+    }
+
+    async fn add_peers(&self, addresses: &[SocketAddr]) -> Result<(), anyhow::Error> {
+        self.writable()?;
+        let now = serde_json::to_vec(&Utc::now())?;
+        for address in addresses {
+            self.peers.insert(format!("{}", address), now.as_slice())?;
+        }
+        Ok(())
     }
 
     async fn peers(&self) -> Result<HashSet<SocketAddr>, anyhow::Error> {
-        unimplemented!()
+        self.peer_table()
+            .await
+            .map(|peers| peers.into_iter().map(|peer| peer.address).collect())
+    }
+
+    async fn peer_table(&self) -> anyhow::Result<Vec<KnownPeer>> {
+        let mut peers = self
+            .peers
+            .iter()
+            .map(|kv| read_peer(kv?))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        peers.sort_by_key(|peer| std::cmp::Reverse(peer.last_seen));
+        Ok(peers)
+    }
+
+    async fn expire_peers(&self, cutoff: DateTime<Utc>) -> anyhow::Result<Vec<SocketAddr>> {
+        self.writable()?;
+        let mut expired = vec![];
+        for peer in self.peer_table().await? {
+            if peer.last_seen >= cutoff {
+                continue;
+            }
+            // An expired peer might come back as a restarted node with a new node ID.
+            let key = format!("{}", peer.address);
+            self.peers.remove(&key)?;
+            self.cookies.remove(&key)?;
+            self.node_ids.remove(&key)?;
+            expired.push(peer.address);
+        }
+        Ok(expired)
+    }
+
+    async fn set_probe_status(
+        &self,
+        socket_addr: SocketAddr,
+        status: ProbeStatus,
+    ) -> anyhow::Result<()> {
+        self.writable()?;
+        self.probes
+            .insert(format!("{}", socket_addr), serde_json::to_vec(&status)?)?;
+        Ok(())
+    }
+
+    async fn probe_status_for_socket_addr(
+        &self,
+        socket_addr: &SocketAddr,
+    ) -> anyhow::Result<Option<ProbeStatus>> {
+        match self.probes.get(format!("{}", socket_addr))? {
+            Some(json) => Ok(Some(
+                serde_json::from_slice(&json).context("Stored probe status")?,
+            )),
+            None => Ok(None),
+        }
+    }
+}
+
+fn read_rep_history(json: Option<sled::IVec>) -> anyhow::Result<Vec<RepChange>> {
+    match json {
+        Some(json) => serde_json::from_slice(&json).context("Stored representative history"),
+        None => Ok(vec![]),
+    }
+}
+
+fn write_rep_history(history: &[RepChange]) -> Vec<u8> {
+    serde_json::to_vec(history).expect("Representative history is always serializable")
+}
+
+fn read_account_info(json: Option<sled::IVec>) -> anyhow::Result<Option<AccountInfo>> {
+    match json {
+        Some(json) => Ok(Some(
+            serde_json::from_slice(&json).context("Stored account info")?,
+        )),
+        None => Ok(None),
+    }
+}
+
+fn write_account_info(info: &AccountInfo) -> Vec<u8> {
+    serde_json::to_vec(info).expect("Account info is always serializable")
+}
+
+fn pending_key(destination: &Public, source: &BlockHash) -> Vec<u8> {
+    [destination.as_bytes(), source.as_bytes()].concat()
+}
+
+fn read_pending((key, amount): (sled::IVec, sled::IVec)) -> anyhow::Result<Pending> {
+    let (destination, source) = key.split_at(Public::LEN);
+    Ok(Pending {
+        destination: Public::try_from(destination)?,
+        source: BlockHash::try_from(source)?,
+        amount: Raw::try_from(amount.as_ref())?,
+    })
+}
+
+fn read_peer((address, last_seen): (sled::IVec, sled::IVec)) -> anyhow::Result<KnownPeer> {
+    let address = std::str::from_utf8(&address).context("Stored peer address")?;
+    Ok(KnownPeer {
+        address: address.parse().context("Stored peer address")?,
+        last_seen: serde_json::from_slice(&last_seen).context("Stored peer last seen")?,
+    })
+}
+
+fn get_u64(tree: &sled::Tree, account: &Public) -> anyhow::Result<Option<u64>> {
+    match tree.get(account.as_bytes())? {
+        Some(bytes) => Ok(Some(u64::from_be_bytes(<[u8; 8]>::try_from(
+            bytes.as_ref(),
+        )?))),
+        None => Ok(None),
+    }
+}
+
+/// Open a database, failing if it's in use by a running node.
+fn open(path: &Path) -> anyhow::Result<sled::Db> {
+    sled::open(path).map_err(|err| {
+        let err = anyhow::Error::from(err);
+        if err.to_string().contains("could not acquire lock") {
+            err.context(format!(
+                "Database {:?} is in use. Stop the node before running this.",
+                path
+            ))
+        } else {
+            err.context(format!("Could not open database {:?}", path))
+        }
+    })
+}
+
+#[derive(Debug, Serialize)]
+pub struct TreeStats {
+    pub name: String,
+    pub entries: usize,
+    pub key_bytes: u64,
+    pub value_bytes: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DbStats {
+    pub path: PathBuf,
+    pub size_on_disk: u64,
+    pub trees: Vec<TreeStats>,
+
+    /// Number of stored blocks.
+    pub blocks: usize,
+
+    /// Entries that are only useful while a node is running, which `compact` will remove.
+    pub pruning_candidates: usize,
+}
+
+impl Display for DbStats {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Database: {:?}", self.path)?;
+        writeln!(f, "Size on disk: {} bytes", self.size_on_disk)?;
+        writeln!(f, "Blocks: {}", self.blocks)?;
+        writeln!(f, "Pruning candidates: {}", self.pruning_candidates)?;
+        writeln!(
+            f,
+            "{:<20} {:>10} {:>12} {:>12}",
+            "Tree", "Entries", "Key bytes", "Value bytes"
+        )?;
+        for tree in &self.trees {
+            writeln!(
+                f,
+                "{:<20} {:>10} {:>12} {:>12}",
+                tree.name, tree.entries, tree.key_bytes, tree.value_bytes
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Sizes of the database before and after compaction, in bytes.
+#[derive(Debug, Serialize)]
+pub struct CompactStats {
+    pub before: u64,
+    pub after: u64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    /// Sled's background threads can hold the lock for a moment after the last handle is dropped.
+    fn retry(open: impl Fn() -> anyhow::Result<SledDiskState>) -> SledDiskState {
+        for _ in 0..100 {
+            if let Ok(state) = open() {
+                return state;
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        open().unwrap()
+    }
+
+    #[tokio::test]
+    async fn blocks() {
+        use crate::blocks::{Link, Previous, StateBlock};
+        use crate::{Raw, Seed};
+
+        let path = PathBuf::from("sled_blocks.db");
+        if path.exists() {
+            fs::remove_dir_all(&path).unwrap();
+        }
+        let account = Seed::zero().derive(0).to_public().unwrap();
+        let block = Block::from_state_block(&StateBlock::new(
+            account.to_owned(),
+            Previous::Open,
+            account.to_owned(),
+            Raw::from(1),
+            Link::Nothing,
+        ));
+        let hash = block.hash().unwrap();
+        {
+            let state = SledDiskState::open(Network::Test, &path).unwrap();
+            state.add_block(&block).await.unwrap();
+            state.set_cemented_height(&account, 1).await.unwrap();
+            state.flush().await.unwrap();
+        }
+
+        // A read-only handle can't change anything.
+        {
+            let read_only = retry(|| SledDiskState::open_read_only(Network::Test, &path));
+            assert_eq!(read_only.accounts().await.unwrap(), vec![account.clone()]);
+            assert!(read_only.rollback_block(hash).await.is_err());
+            assert!(read_only.set_cemented_height(&account, 0).await.is_err());
+        }
+
+        let state = retry(|| SledDiskState::open(Network::Test, &path));
+        let err = SledDiskState::open_read_only(Network::Test, &path).unwrap_err();
+        assert!(format!("{:?}", err).contains("Stop the node"));
+
+        assert_eq!(
+            state.get_block_by_hash(hash).await.unwrap(),
+            Some(block.clone())
+        );
+        assert_eq!(
+            state
+                .get_latest_block_hash_for_account(&account)
+                .await
+                .unwrap()
+                .as_ref(),
+            Some(hash)
+        );
+        assert_eq!(
+            state.account_for_block_hash(hash).await.unwrap(),
+            Some(account.clone())
+        );
+        assert_eq!(state.accounts().await.unwrap(), vec![account.clone()]);
+        assert_eq!(state.cemented_height(&account).await.unwrap(), Some(1));
+        assert!(state.account_modified(&account).await.unwrap().is_some());
+        let sideband = state.sideband(hash).await.unwrap().unwrap();
+        assert!(sideband.receive);
+        assert_eq!(sideband.difficulty, None);
+        assert_eq!(state.sidebands_since(0).await.unwrap(), vec![sideband]);
+        let history = state.rep_history(&account).await.unwrap();
+        assert_eq!(history.len(), 1);
+        assert_eq!((&history[0].hash, &history[0].old), (hash, &None));
+
+        let pending = Pending {
+            destination: account.to_owned(),
+            source: hash.to_owned(),
+            amount: Raw::from(5),
+        };
+        let other = Pending {
+            destination: crate::Seed::zero().derive(1).to_public().unwrap(),
+            ..pending.clone()
+        };
+        state.add_pending(&pending).await.unwrap();
+        state.add_pending(&pending).await.unwrap();
+        state.add_pending(&other).await.unwrap();
+        assert_eq!(state.all_pending().await.unwrap().len(), 2);
+        assert_eq!(
+            state.pending(&account).await.unwrap(),
+            vec![pending.clone()]
+        );
+        assert_eq!(
+            state.remove_pending(&account, hash).await.unwrap(),
+            Some(pending)
+        );
+        assert!(state.pending(&account).await.unwrap().is_empty());
+        assert_eq!(state.all_pending().await.unwrap(), vec![other]);
+
+        state.rollback_block(hash).await.unwrap();
+        assert!(state.get_block_by_hash(hash).await.unwrap().is_none());
+        assert!(state.accounts().await.unwrap().is_empty());
+        assert!(state.rep_history(&account).await.unwrap().is_empty());
+        assert!(state.sideband(hash).await.unwrap().is_none());
+        assert!(state.rollback_block(hash).await.is_err());
+        drop(state);
+        fs::remove_dir_all(&path).unwrap();
+    }
+
+    #[test]
+    fn stats_and_compact() {
+        let path = PathBuf::from("stats_and_compact.db");
+        if path.exists() {
+            fs::remove_dir_all(&path).unwrap();
+        }
+        {
+            let db = open(&path).unwrap();
+            let blocks = db.open_tree("blocks").unwrap();
+            for i in 0u32..100 {
+                blocks.insert(i.to_be_bytes(), vec![0u8; 100]).unwrap();
+            }
+            for i in 0u32..90 {
+                blocks.remove(i.to_be_bytes()).unwrap();
+            }
+            db.open_tree("cookies").unwrap().insert("a", "b").unwrap();
+
+            db.flush().unwrap();
+
+            // The node holds the lock while running.
+            let err = SledDiskState::stats(&path).unwrap_err();
+            assert!(format!("{:?}", err).contains("Stop the node"));
+        }
+
+        let before = SledDiskState::stats(&path).unwrap();
+        assert_eq!(before.blocks, 10);
+        assert_eq!(before.path, path);
+        assert_eq!(before.pruning_candidates, 1);
+        let blocks = before.trees.iter().find(|t| t.name == "blocks").unwrap();
+        assert_eq!(blocks.value_bytes, 1000);
+
+        SledDiskState::compact(&path).unwrap();
+        let after = SledDiskState::stats(&path).unwrap();
+        fs::remove_dir_all(&path).unwrap();
+        assert_eq!(after.blocks, 10);
+        assert_eq!(after.pruning_candidates, 0);
     }
 }
@@ -0,0 +1,330 @@
+//! Synthetic load for benchmarking how fast blocks are validated and stored.
+//!
+//! A [StressTest] creates funded accounts directly in a fresh state, then sends signed state
+//! blocks between them at a steady rate. Each send is followed by a receive of it on the
+//! destination account. A separate task validates each block against the state and stores it,
+//! like blocks published by peers, and the time from a block being created until it has been
+//! stored is recorded.
+//!
+//! Work isn't generated, since it would limit the rate far more than anything being measured,
+//! and there are no elections, so the latencies are for processing rather than confirmation.
+use crate::blocks::{Block, BlockHash, Link, Previous, StateBlock};
+use crate::node::state::{ArcState, MemoryState};
+use crate::{Network, Private, Public, Raw, Seed};
+use anyhow::anyhow;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::collections::VecDeque;
+use std::fmt::{Display, Formatter};
+use std::sync::Arc;
+use tokio::sync::mpsc;
+use tokio::time::{Duration, Instant};
+use tracing::{info, warn};
+
+/// Every account starts with this much, which is 1 Mnano.
+const FUNDING: u128 = 1_000_000_000_000_000_000_000_000_000_000;
+
+/// Blocks waiting to be processed before block creation is held back.
+const QUEUE_LEN: usize = 10_000;
+
+/// How often to log progress.
+const LOG_INTERVAL: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone)]
+pub struct StressTest {
+    /// Blocks to create per second, counting sends and receives.
+    pub tps: u32,
+
+    /// How many funded accounts to send between.
+    pub accounts: u32,
+
+    /// How long to keep creating blocks for.
+    pub duration: Duration,
+}
+
+struct Account {
+    private: Private,
+    public: Public,
+    frontier: BlockHash,
+    balance: Raw,
+}
+
+impl Account {
+    fn next_block(&mut self, balance: Raw, link: Link) -> anyhow::Result<StateBlock> {
+        let mut block = StateBlock::new(
+            self.public.to_owned(),
+            Previous::Block(self.frontier.to_owned()),
+            self.public.to_owned(),
+            balance.to_owned(),
+            link,
+        );
+        block.signature = Some(self.private.sign(block.hash.as_bytes())?);
+        self.frontier = block.hash.to_owned();
+        self.balance = balance;
+        Ok(block)
+    }
+}
+
+impl StressTest {
+    pub async fn run(&self) -> anyhow::Result<StressReport> {
+        if self.tps == 0 || self.accounts < 2 {
+            return Err(anyhow!(
+                "A stress test needs a rate and at least two accounts"
+            ));
+        }
+
+        let state: ArcState = Arc::new(MemoryState::new(Network::Test));
+        info!("Creating {} funded accounts", self.accounts);
+        let accounts = fund(&state, self.accounts).await?;
+
+        let (tx, rx) = mpsc::channel(QUEUE_LEN);
+        let processor = tokio::spawn(process_all(state, rx));
+
+        info!(
+            "Sending {} blocks per second for {:?}",
+            self.tps, self.duration
+        );
+        let (sends, receives) = self.generate(accounts, tx).await?;
+        let mut report = processor.await??;
+        report.sends = sends;
+        report.receives = receives;
+        Ok(report)
+    }
+
+    /// Create blocks at the configured rate until the duration is up. Returns how many sends
+    /// and receives were made.
+    async fn generate(
+        &self,
+        mut accounts: Vec<Account>,
+        tx: mpsc::Sender<(Instant, StateBlock)>,
+    ) -> anyhow::Result<(usize, usize)> {
+        let mut rng = StdRng::from_entropy();
+        let mut interval = tokio::time::interval(Duration::from_secs_f64(1.0 / self.tps as f64));
+        let started = Instant::now();
+        let mut unreceived: VecDeque<(usize, BlockHash, Raw)> = VecDeque::new();
+        let (mut sends, mut receives) = (0, 0);
+
+        while started.elapsed() < self.duration {
+            interval.tick().await;
+
+            // Alternate so that every send is received soon after.
+            let block = match unreceived.pop_front() {
+                Some((to, source, amount)) => {
+                    let account = &mut accounts[to];
+                    let balance = account
+                        .balance
+                        .checked_add(&amount)
+                        .ok_or_else(|| anyhow!("Stress test account balance overflowed"))?;
+                    receives += 1;
+                    account.next_block(balance, Link::Source(source))?
+                }
+                None => {
+                    let from = rng.gen_range(0..accounts.len());
+                    let to = (from + rng.gen_range(1..accounts.len())) % accounts.len();
+                    let amount = Raw::from(rng.gen_range(1..=1000u128));
+                    let destination = accounts[to].public.to_owned();
+                    let account = &mut accounts[from];
+                    let balance = account
+                        .balance
+                        .checked_sub(&amount)
+                        .ok_or_else(|| anyhow!("Stress test account ran out of funds"))?;
+                    let block =
+                        account.next_block(balance, Link::DestinationAccount(destination))?;
+                    unreceived.push_back((to, block.hash.to_owned(), amount));
+                    sends += 1;
+                    block
+                }
+            };
+
+            if tx.send((Instant::now(), block)).await.is_err() {
+                return Err(anyhow!("Stress test processor stopped"));
+            }
+        }
+        Ok((sends, receives))
+    }
+}
+
+/// Add an open block with [FUNDING] for each account straight into the state.
+async fn fund(state: &ArcState, count: u32) -> anyhow::Result<Vec<Account>> {
+    let mut accounts = Vec::with_capacity(count as usize);
+    for (_, private) in Seed::random().derive_many(0..count) {
+        let public = private.to_public()?;
+        let mut open = StateBlock::new(
+            public.to_owned(),
+            Previous::Open,
+            public.to_owned(),
+            Raw::from(FUNDING),
+            Link::Source(BlockHash::zero()),
+        );
+        open.signature = Some(private.sign(open.hash.as_bytes())?);
+        state.add_block(&Block::from_state_block(&open)).await?;
+        accounts.push(Account {
+            private,
+            public,
+            frontier: open.hash,
+            balance: Raw::from(FUNDING),
+        });
+    }
+    Ok(accounts)
+}
+
+/// Validate and store blocks until the sender is dropped.
+async fn process_all(
+    state: ArcState,
+    mut rx: mpsc::Receiver<(Instant, StateBlock)>,
+) -> anyhow::Result<StressReport> {
+    let started = Instant::now();
+    let mut last_log = started;
+    let mut latencies = vec![];
+    let mut rejected = 0;
+
+    while let Some((created, block)) = rx.recv().await {
+        match process(&state, &block).await {
+            Ok(()) => latencies.push(created.elapsed()),
+            Err(err) => {
+                warn!("Rejected {:?}: {:?}", block.hash, err);
+                rejected += 1;
+            }
+        }
+        if last_log.elapsed() >= LOG_INTERVAL {
+            last_log = Instant::now();
+            info!("Processed {} blocks", latencies.len());
+        }
+    }
+
+    Ok(StressReport {
+        sends: 0,
+        receives: 0,
+        processed: latencies.len(),
+        rejected,
+        elapsed: started.elapsed(),
+        latency: LatencyStats::new(latencies),
+    })
+}
+
+/// The checks a node does on a published state block before storing it, apart from work.
+async fn process(state: &ArcState, block: &StateBlock) -> anyhow::Result<()> {
+    block.verify_self_signature()?;
+    let previous = match &block.previous {
+        Previous::Block(hash) => hash,
+        Previous::Open => return Err(anyhow!("Unexpected open block")),
+    };
+    if state
+        .get_latest_block_hash_for_account(&block.account)
+        .await?
+        .as_ref()
+        != Some(previous)
+    {
+        return Err(anyhow!("Previous block isn't the frontier of the account"));
+    }
+    let previous = state
+        .get_block_by_hash(previous)
+        .await?
+        .ok_or_else(|| anyhow!("Previous block is missing"))?;
+
+    match &block.link {
+        Link::DestinationAccount(_) if &block.balance < previous.balance() => {}
+        Link::Source(source) if &block.balance > previous.balance() => {
+            if state.get_block_by_hash(source).await?.is_none() {
+                return Err(anyhow!("Source block is missing"));
+            }
+        }
+        link => return Err(anyhow!("Balance doesn't match {:?}", link)),
+    }
+
+    state.add_block(&Block::from_state_block(block)).await
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct LatencyStats {
+    pub p50: Duration,
+    pub p90: Duration,
+    pub p99: Duration,
+    pub max: Duration,
+}
+
+impl LatencyStats {
+    fn new(mut latencies: Vec<Duration>) -> Self {
+        if latencies.is_empty() {
+            return Self::default();
+        }
+        latencies.sort();
+        let percentile = |p: usize| latencies[(latencies.len() - 1) * p / 100];
+        Self {
+            p50: percentile(50),
+            p90: percentile(90),
+            p99: percentile(99),
+            max: latencies[latencies.len() - 1],
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct StressReport {
+    pub sends: usize,
+    pub receives: usize,
+    pub processed: usize,
+    pub rejected: usize,
+    pub elapsed: Duration,
+
+    /// Time from creating each block until it was stored.
+    pub latency: LatencyStats,
+}
+
+impl StressReport {
+    pub fn blocks_per_second(&self) -> f64 {
+        self.processed as f64 / self.elapsed.as_secs_f64()
+    }
+}
+
+impl Display for StressReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "Created {} sends and {} receives",
+            self.sends, self.receives
+        )?;
+        writeln!(
+            f,
+            "Processed {} blocks in {:.1?} ({:.1} blocks/s), {} rejected",
+            self.processed,
+            self.elapsed,
+            self.blocks_per_second(),
+            self.rejected
+        )?;
+        writeln!(
+            f,
+            "Latency p50 {:.2?}, p90 {:.2?}, p99 {:.2?}, max {:.2?}",
+            self.latency.p50, self.latency.p90, self.latency.p99, self.latency.max
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn run() {
+        let test = StressTest {
+            tps: 500,
+            accounts: 3,
+            duration: Duration::from_millis(200),
+        };
+        let report = test.run().await.unwrap();
+        assert!(report.sends > 0);
+        assert!(report.receives > 0);
+        assert_eq!(report.rejected, 0);
+        assert_eq!(report.processed, report.sends + report.receives);
+        assert!(report.latency.p50 <= report.latency.max);
+    }
+
+    #[test]
+    fn percentiles() {
+        let latencies = (1..=100).map(Duration::from_millis).collect();
+        let stats = LatencyStats::new(latencies);
+        assert_eq!(stats.p50, Duration::from_millis(50));
+        assert_eq!(stats.p99, Duration::from_millis(99));
+        assert_eq!(stats.max, Duration::from_millis(100));
+    }
+}
@@ -0,0 +1,488 @@
+//! Copying blocks straight from one state store to another.
+//!
+//! The frontier of each account in the source is compared with the destination, and only the
+//! blocks above the destination's frontier are copied. Each copied block has its signature,
+//! work and link to the previous block checked, along with the amount it sends or receives
+//! against the source, so a corrupt or tampered source can't put invalid blocks in the
+//! destination. This is much faster than bootstrapping from the network when
+//! moving a ledger to another machine or backend.
+//!
+//! [persist] does the same from a running node's in memory ledger into a database, along with
+//! the pending entries, so the ledger outlives the node.
+use crate::blocks::{Block, BlockHash, BlockType, Link, Previous, StateBlock, Subtype};
+use crate::node::intake;
+use crate::node::pending::Pending;
+use crate::node::state::{ArcState, SledDiskState};
+use crate::rpc::calls::PersistResponse;
+use crate::{Network, Public, Raw};
+use anyhow::{anyhow, Context};
+use std::collections::HashSet;
+use std::fmt::{Display, Formatter};
+use std::path::Path;
+use std::sync::Arc;
+use tracing::{info, warn};
+
+/// How often to log progress, in accounts.
+const LOG_EVERY: usize = 10_000;
+
+#[derive(Debug, Default, PartialEq)]
+pub struct SyncStats {
+    pub accounts: usize,
+
+    /// Accounts that already had the same frontier in the destination.
+    pub up_to_date: usize,
+
+    pub blocks_copied: usize,
+
+    /// Accounts where the destination has a block the source doesn't, from a fork or from
+    /// being ahead of the source. They're left alone.
+    pub diverged: usize,
+
+    /// Accounts with a block that failed to verify, or a gap in their chain in the source.
+    pub invalid: usize,
+}
+
+impl Display for SyncStats {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "Compared {} accounts, {} already up to date",
+            self.accounts, self.up_to_date
+        )?;
+        writeln!(f, "Copied {} blocks", self.blocks_copied)?;
+        writeln!(
+            f,
+            "Skipped {} diverged and {} invalid accounts",
+            self.diverged, self.invalid
+        )
+    }
+}
+
+/// Copy every block that `source` has and `destination` is missing.
+pub async fn sync_from(
+    source: &ArcState,
+    destination: &ArcState,
+    network: Network,
+) -> anyhow::Result<SyncStats> {
+    let accounts = source.accounts().await?;
+    let mut stats = SyncStats::default();
+
+    for account in &accounts {
+        stats.accounts += 1;
+        if stats.accounts % LOG_EVERY == 0 {
+            info!(
+                "Compared {} of {} accounts, copied {} blocks",
+                stats.accounts,
+                accounts.len(),
+                stats.blocks_copied
+            );
+        }
+
+        let theirs = source.get_latest_block_hash_for_account(account).await?;
+        let ours = destination
+            .get_latest_block_hash_for_account(account)
+            .await?;
+        let theirs = match theirs {
+            Some(theirs) if Some(&theirs) != ours.as_ref() => theirs,
+            _ => {
+                stats.up_to_date += 1;
+                continue;
+            }
+        };
+
+        let missing = match missing_blocks(source, network, account, &theirs, ours.as_ref()).await {
+            Ok(Some(missing)) => missing,
+            Ok(None) => {
+                warn!(
+                    "Chain of {:?} has diverged from the source",
+                    account.to_address()
+                );
+                stats.diverged += 1;
+                continue;
+            }
+            Err(err) => {
+                warn!("Not copying {:?}: {:#}", account.to_address(), err);
+                stats.invalid += 1;
+                continue;
+            }
+        };
+        for block in &missing {
+            destination.add_block(block).await?;
+        }
+        stats.blocks_copied += missing.len();
+
+        // Our blocks are a prefix of theirs, so their cemented height applies to ours too.
+        if let Some(height) = source.cemented_height(account).await? {
+            if destination.cemented_height(account).await? < Some(height) {
+                destination.set_cemented_height(account, height).await?;
+            }
+        }
+    }
+
+    Ok(stats)
+}
+
+/// Copy the ledger in `source` into the database at `path` with [sync_from], then bring the
+/// database's pending entries in line with the source's.
+pub async fn persist(
+    source: &ArcState,
+    network: Network,
+    path: &Path,
+) -> anyhow::Result<PersistResponse> {
+    let disk = SledDiskState::open(network, path)?;
+    let flush = disk.clone();
+    let destination: ArcState = Arc::new(disk);
+
+    let stats = sync_from(source, &destination, network).await?;
+    let pending_copied = sync_pending(source, &destination).await?;
+    flush.flush().await?;
+    Ok(PersistResponse {
+        path: path.to_owned(),
+        accounts: stats.accounts,
+        up_to_date: stats.up_to_date,
+        blocks_copied: stats.blocks_copied,
+        pending_copied,
+        diverged: stats.diverged,
+        invalid: stats.invalid,
+    })
+}
+
+/// Add the source's pending entries whose send block is in the destination, and remove the
+/// destination's entries that have been received in the source. Returns how many were added.
+///
+/// An entry is only removed when the receiving account's chain is the same in both, since
+/// otherwise the destination doesn't have the block that received it.
+async fn sync_pending(source: &ArcState, destination: &ArcState) -> anyhow::Result<usize> {
+    let theirs = source.all_pending().await?;
+    let ours = destination.all_pending().await?;
+
+    let key = |p: &Pending| (p.destination.to_owned(), p.source.to_owned());
+    let ours_keys: HashSet<_> = ours.iter().map(key).collect();
+    let theirs_keys: HashSet<_> = theirs.iter().map(key).collect();
+
+    let mut copied = 0;
+    for pending in &theirs {
+        if ours_keys.contains(&key(pending))
+            || destination
+                .get_block_by_hash(&pending.source)
+                .await?
+                .is_none()
+        {
+            continue;
+        }
+        destination.add_pending(pending).await?;
+        copied += 1;
+    }
+
+    for pending in &ours {
+        if theirs_keys.contains(&key(pending)) {
+            continue;
+        }
+        let account = &pending.destination;
+        let frontier = source.get_latest_block_hash_for_account(account).await?;
+        if frontier.is_some()
+            && frontier
+                == destination
+                    .get_latest_block_hash_for_account(account)
+                    .await?
+        {
+            destination.remove_pending(account, &pending.source).await?;
+        }
+    }
+    Ok(copied)
+}
+
+/// Walk back from the source frontier until reaching `ours`, returning the verified blocks in
+/// the order they need to be added. Returns `None` when the source chain doesn't include `ours`.
+async fn missing_blocks(
+    source: &ArcState,
+    network: Network,
+    account: &Public,
+    frontier: &BlockHash,
+    ours: Option<&BlockHash>,
+) -> anyhow::Result<Option<Vec<Block>>> {
+    let mut missing = vec![];
+    let mut next = frontier.to_owned();
+    loop {
+        if Some(&next) == ours {
+            break;
+        }
+        let block = source
+            .get_block_by_hash(&next)
+            .await?
+            .ok_or_else(|| anyhow!("Missing block {:?}", next))?;
+        if block.hash()? != &next {
+            return Err(anyhow!("Block stored as {:?} has a different hash", next));
+        }
+        if block.account() != account {
+            return Err(anyhow!("Block {:?} belongs to another account", next));
+        }
+        block
+            .verify_signature(account)
+            .with_context(|| format!("Block {:?}", next))?;
+
+        let previous = block.previous().to_owned();
+        missing.push(block);
+        next = match previous {
+            Previous::Block(previous) => previous,
+            // Reached the open block without finding our frontier.
+            Previous::Open if ours.is_some() => return Ok(None),
+            Previous::Open => break,
+        };
+    }
+    missing.reverse();
+
+    let mut previous = match ours {
+        Some(ours) => source.get_block_by_hash(ours).await?,
+        None => None,
+    };
+    for block in &missing {
+        check_block(source, network, block, previous.as_ref())
+            .await
+            .with_context(|| format!("Block {:?}", block.hash()))?;
+        previous = Some(block.to_owned());
+    }
+    Ok(Some(missing))
+}
+
+/// Check the work of `block` and the amount it moves, like [crate::node::process::check] does
+/// for a new block. A receive has to match a send in `source`.
+async fn check_block(
+    source: &ArcState,
+    network: Network,
+    block: &Block,
+    previous: Option<&Block>,
+) -> anyhow::Result<()> {
+    // The genesis block has nothing to receive from.
+    if block.hash()? == &network.genesis_hash() {
+        return Ok(());
+    }
+    let mut state_block = StateBlock::from(block.to_owned());
+    let previous = previous.map(|previous| StateBlock::from(previous.to_owned()));
+    let subtype = state_block.subtype(previous.as_ref());
+
+    let threshold = match (block.block_type(), subtype) {
+        (BlockType::State, Subtype::Receive | Subtype::Open | Subtype::Epoch) => {
+            network.receive_difficulty()
+        }
+        (BlockType::State, _) => network.send_difficulty(),
+        _ => network.legacy_difficulty(),
+    };
+    let difficulty = block.require_work()?.difficulty(&block.work_root())?;
+    if difficulty < threshold {
+        return Err(anyhow!(
+            "Work difficulty {} is below the threshold of {}",
+            difficulty.as_u64(),
+            threshold.as_u64()
+        ));
+    }
+
+    state_block.link = state_block.link.for_subtype(subtype)?;
+    // Opening an account is receiving onto an empty chain.
+    let previous = previous.unwrap_or_else(|| {
+        StateBlock::new(
+            state_block.account.to_owned(),
+            Previous::Open,
+            state_block.representative.to_owned(),
+            Raw::zero(),
+            Link::Nothing,
+        )
+    });
+    intake::check_amounts(&**source, &state_block, &previous)
+        .await?
+        .map_err(|reason| anyhow!("{}", reason))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::node::state::{MemoryState, State};
+    use crate::node::test_util::{self, add, worked as block, Faucet};
+    use crate::Work;
+    use std::sync::Arc;
+
+    fn state() -> ArcState {
+        Arc::new(MemoryState::new(Network::Test))
+    }
+
+    /// A source with every block of the faucet, and a destination with its open block.
+    async fn states(faucet: &Faucet) -> (ArcState, ArcState) {
+        let (source, destination) = (state(), state());
+        add(&source, &faucet.blocks).await;
+        add(&destination, &[faucet.open().to_owned()]).await;
+        (source, destination)
+    }
+
+    #[tokio::test]
+    async fn copies_missing_blocks() {
+        let mut faucet = Faucet::new();
+        let behind = faucet.chain(0, 3);
+        let fresh = faucet.chain(1, 2);
+        let same = faucet.chain(2, 1);
+        // The destination opened this account with a different block.
+        let theirs = faucet.chain(3, 2);
+        let ours = faucet.chain(3, 3);
+
+        let (source, destination) = states(&faucet).await;
+        for blocks in &[&behind, &fresh, &same, &theirs] {
+            add(&source, blocks).await;
+        }
+        add(&destination, &behind[..1]).await;
+        add(&destination, &same).await;
+        add(&destination, &ours[..1]).await;
+        source
+            .set_cemented_height(behind[0].account(), 2)
+            .await
+            .unwrap();
+
+        let stats = sync_from(&source, &destination, Network::Test)
+            .await
+            .unwrap();
+        assert_eq!(
+            stats,
+            SyncStats {
+                accounts: 5,
+                up_to_date: 1,
+                blocks_copied: 9,
+                diverged: 1,
+                invalid: 0,
+            }
+        );
+        let account = behind[0].account();
+        assert_eq!(
+            destination
+                .get_latest_block_hash_for_account(account)
+                .await
+                .unwrap()
+                .as_ref(),
+            Some(behind[2].hash().unwrap())
+        );
+        assert_eq!(destination.cemented_height(account).await.unwrap(), Some(2));
+    }
+
+    #[tokio::test]
+    async fn rejects_bad_signatures() {
+        let mut faucet = Faucet::new();
+        let mut blocks = faucet.chain(0, 2);
+        let other = faucet.chain(1, 1);
+        blocks[1].set_signature(other[0].signature().unwrap().to_owned());
+        let (source, destination) = states(&faucet).await;
+        add(&source, &blocks).await;
+
+        let stats = sync_from(&source, &destination, Network::Test)
+            .await
+            .unwrap();
+        assert_eq!(stats.invalid, 1);
+        assert!(destination
+            .get_latest_block_hash_for_account(blocks[0].account())
+            .await
+            .unwrap()
+            .is_none());
+    }
+
+    #[tokio::test]
+    async fn rejects_low_work() {
+        let mut faucet = Faucet::new();
+        let mut blocks = faucet.chain(0, 2);
+        blocks[1].set_work(Work::zero());
+        let (source, destination) = states(&faucet).await;
+        add(&source, &blocks).await;
+
+        let stats = sync_from(&source, &destination, Network::Test)
+            .await
+            .unwrap();
+        assert_eq!(stats.invalid, 1);
+    }
+
+    #[tokio::test]
+    async fn rejects_wrong_amounts() {
+        let mut faucet = Faucet::new();
+        let private = test_util::private(0);
+        faucet.chain(0, 2);
+
+        // Receiving more than was sent.
+        let greedy = block(
+            &private,
+            None,
+            3,
+            Link::Source(faucet.blocks[1].hash().unwrap().to_owned()),
+        );
+        let (source, destination) = states(&faucet).await;
+        add(&source, &[greedy]).await;
+
+        // Receiving from a block that isn't a send.
+        let private = test_util::private(1);
+        let bogus = block(
+            &private,
+            None,
+            1,
+            Link::Source(faucet.open().hash().unwrap().to_owned()),
+        );
+        add(&source, &[bogus]).await;
+
+        let stats = sync_from(&source, &destination, Network::Test)
+            .await
+            .unwrap();
+        assert_eq!(stats.invalid, 2);
+    }
+
+    #[tokio::test]
+    async fn persists_blocks_and_pending() {
+        use crate::node::pending::Pending;
+        use std::path::PathBuf;
+        use std::time::Duration;
+
+        /// Sled's background threads can hold the lock for a moment after the last handle is
+        /// dropped.
+        async fn persist_again(source: &ArcState, path: &Path) -> PersistResponse {
+            for _ in 0..100 {
+                if let Ok(response) = persist(source, Network::Test, path).await {
+                    return response;
+                }
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+            persist(source, Network::Test, path).await.unwrap()
+        }
+
+        let path = PathBuf::from("persists_blocks_and_pending.db");
+        if path.exists() {
+            std::fs::remove_dir_all(&path).unwrap();
+        }
+        let mut faucet = Faucet::new();
+        let sender = faucet.chain(0, 2);
+        let receiver = faucet.chain(1, 1);
+        let (source, _) = states(&faucet).await;
+        add(&source, &sender).await;
+        let pending = Pending {
+            destination: receiver[0].account().to_owned(),
+            source: faucet.blocks[2].hash().unwrap().to_owned(),
+            amount: Raw::from(1),
+        };
+        source.add_pending(&pending).await.unwrap();
+        {
+            let db = SledDiskState::open(Network::Test, &path).unwrap();
+            db.add_block(faucet.open()).await.unwrap();
+            db.flush().await.unwrap();
+        }
+
+        let response = persist_again(&source, &path).await;
+        assert_eq!((response.blocks_copied, response.pending_copied), (4, 1));
+
+        // The receiver has since received it.
+        add(&source, &receiver).await;
+        source
+            .remove_pending(&pending.destination, &pending.source)
+            .await
+            .unwrap();
+        let response = persist_again(&source, &path).await;
+        assert_eq!((response.up_to_date, response.blocks_copied), (2, 1));
+
+        // The entry is gone from the database, which has a pending entry from the source only
+        // when it's still in the source.
+        source.add_pending(&pending).await.unwrap();
+        let response = persist_again(&source, &path).await;
+        assert_eq!((response.up_to_date, response.blocks_copied), (3, 0));
+        assert_eq!(response.pending_copied, 1);
+        std::fs::remove_dir_all(&path).unwrap();
+    }
+}
@@ -0,0 +1,341 @@
+//! Estimating how far our ledger is behind the network, from the block counts that peers report
+//! in their telemetry.
+//!
+//! Only telemetry from a peer that completed the handshake, signed by the same node ID, with our
+//! genesis block, is counted. Telemetry signed by another node ID, or with a bad signature, is
+//! spoofed, and a peer that sends it [MAX_SPOOFED] times is disconnected. The estimate is the median of the latest report from each node, so a few
+//! nodes reporting silly numbers can't move it far.
+//!
+//! Our own telemetry is built from the state when a peer asks for it, and signed with the node ID
+//! that we answer handshakes with, which is new each time the node starts.
+use crate::node::messages::telemetry_ack::TelemetryAck;
+use crate::node::protocol_version::ProtocolVersion;
+use crate::node::state::ArcState;
+use crate::rpc::calls::{PeerTelemetry, SyncStatusResponse};
+use crate::{Network, Private, Public, Seed, Signature};
+use chrono::Utc;
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// How often peers are asked for their telemetry.
+pub const TELEMETRY_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Spoofed telemetry acks a peer can send before it's disconnected.
+pub const MAX_SPOOFED: usize = 3;
+
+/// Reports older than this are left out of the estimate, e.g. from peers that went away.
+const MAX_AGE: Duration = Duration::from_secs(5 * 60);
+
+/// The maker byte of our telemetry. The reference node is `0`.
+const MAKER: u8 = 0xfe;
+
+#[derive(Debug, Clone)]
+struct Report {
+    telemetry: PeerTelemetry,
+    received: Instant,
+}
+
+/// The median block counts of the nodes that sent us telemetry recently.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NetworkCounts {
+    pub block_count: u64,
+    pub cemented_count: u64,
+
+    /// How many nodes the counts are from.
+    pub nodes: usize,
+}
+
+/// The latest telemetry reported by each node, and our own node ID, shared with every peer.
+#[derive(Debug, Clone)]
+pub struct NetworkTelemetry {
+    reports: Arc<Mutex<HashMap<Public, Report>>>,
+    node_key: Arc<Private>,
+    started: Instant,
+}
+
+impl Default for NetworkTelemetry {
+    fn default() -> Self {
+        Self {
+            reports: Default::default(),
+            node_key: Arc::new(Seed::random().derive(0)),
+            started: Instant::now(),
+        }
+    }
+}
+
+impl NetworkTelemetry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The key of our node ID.
+    pub fn node_key(&self) -> &Private {
+        &self.node_key
+    }
+
+    /// Keep a verified telemetry ack from the peer at `address`.
+    pub fn record(&self, address: SocketAddr, ack: &TelemetryAck) {
+        self.record_at(address, ack, Instant::now());
+    }
+
+    fn record_at(&self, address: SocketAddr, ack: &TelemetryAck, at: Instant) {
+        let telemetry = PeerTelemetry {
+            address,
+            node_id: node_id_string(&ack.node_id),
+            block_count: ack.block_count,
+            cemented_count: ack.cemented_count,
+            unchecked_count: ack.unchecked_count,
+            account_count: ack.account_count,
+            bandwidth_cap: ack.bandwidth_cap,
+            peer_count: ack.peer_count,
+            protocol_version: ack.protocol_version,
+            version: format!(
+                "{}.{}.{}",
+                ack.major_version, ack.minor_version, ack.patch_version
+            ),
+            maker: ack.maker,
+            uptime: ack.uptime,
+            timestamp: u64::from_be_bytes(ack.timestamp),
+            received: Utc::now(),
+        };
+        self.reports.lock().unwrap().insert(
+            ack.node_id.to_owned(),
+            Report {
+                telemetry,
+                received: at,
+            },
+        );
+    }
+
+    /// The latest telemetry from each node, leaving out old reports, most blocks first.
+    pub fn reports(&self) -> Vec<PeerTelemetry> {
+        let now = Instant::now();
+        let mut reports: Vec<PeerTelemetry> = self
+            .reports
+            .lock()
+            .unwrap()
+            .values()
+            .filter(|report| now.saturating_duration_since(report.received) < MAX_AGE)
+            .map(|report| report.telemetry.to_owned())
+            .collect();
+        reports.sort_by_key(|t| std::cmp::Reverse(t.block_count));
+        reports
+    }
+
+    /// Our own telemetry from the state, signed with our node ID.
+    pub async fn ack(&self, network: Network, state: &ArcState) -> anyhow::Result<TelemetryAck> {
+        let (block_count, cemented_count, account_count, peer_count) = {
+            (
+                state.block_count().await?,
+                state.cemented_count().await?,
+                state.accounts().await?.len() as u64,
+                state.peers().await?.len() as u32,
+            )
+        };
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis() as u64;
+        let version = |idx: usize| {
+            env!("CARGO_PKG_VERSION")
+                .split(['.', '-'])
+                .nth(idx)
+                .and_then(|n| n.parse().ok())
+                .unwrap_or_default()
+        };
+        let mut ack = TelemetryAck {
+            signature: Signature::zero(),
+            node_id: self.node_key.to_public()?,
+            block_count,
+            cemented_count,
+            unchecked_count: 0,
+            account_count,
+            bandwidth_cap: 0,
+            uptime: self.started.elapsed().as_secs(),
+            peer_count,
+            protocol_version: ProtocolVersion::CURRENT.as_u8(),
+            genesis_block: network.genesis_hash(),
+            major_version: version(0),
+            minor_version: version(1),
+            patch_version: version(2),
+            prerelease_version: 0,
+            maker: MAKER,
+            timestamp: timestamp.to_be_bytes(),
+            active_difficulty: network.send_difficulty().as_u64().to_be_bytes(),
+            unknown_data: vec![],
+        };
+        ack.signature = self.node_key.sign(&ack.signed_bytes())?;
+        Ok(ack)
+    }
+
+    /// `None` until a node has sent telemetry.
+    pub fn estimate(&self) -> Option<NetworkCounts> {
+        self.estimate_at(Instant::now())
+    }
+
+    fn estimate_at(&self, now: Instant) -> Option<NetworkCounts> {
+        let mut reports = self.reports.lock().unwrap();
+        reports.retain(|_, report| now.saturating_duration_since(report.received) < MAX_AGE);
+        if reports.is_empty() {
+            return None;
+        }
+        let mut blocks: Vec<u64> = reports.values().map(|r| r.telemetry.block_count).collect();
+        let mut cemented: Vec<u64> = reports
+            .values()
+            .map(|r| r.telemetry.cemented_count)
+            .collect();
+        Some(NetworkCounts {
+            block_count: median(&mut blocks),
+            cemented_count: median(&mut cemented),
+            nodes: reports.len(),
+        })
+    }
+}
+
+/// How the reference node shows node IDs, e.g. `node_1y7j5...`.
+fn node_id_string(node_id: &Public) -> String {
+    node_id
+        .to_address()
+        .to_string()
+        .replacen("nano_", "node_", 1)
+}
+
+/// Rounded down, between the two middle values of an even number of them.
+fn median(values: &mut [u64]) -> u64 {
+    values.sort_unstable();
+    let middle = values.len() / 2;
+    if values.len() % 2 == 1 {
+        values[middle]
+    } else {
+        let (low, high) = (values[middle - 1], values[middle]);
+        low + (high - low) / 2
+    }
+}
+
+/// How much of `network` we have, up to 100%. We might be ahead of a network that's still
+/// catching up with a burst of blocks, which still counts as synced.
+pub fn percentage(local: u64, network: u64) -> f64 {
+    if network == 0 {
+        return 100.0;
+    }
+    (local as f64 / network as f64 * 100.0).min(100.0)
+}
+
+/// Our block counts next to the network's, for `feeless node status` and the `/health` report.
+pub async fn sync_status(
+    state: &ArcState,
+    telemetry: &NetworkTelemetry,
+) -> anyhow::Result<SyncStatusResponse> {
+    let (block_count, cemented_count) =
+        { (state.block_count().await?, state.cemented_count().await?) };
+    let network = telemetry.estimate();
+    Ok(SyncStatusResponse {
+        block_count,
+        cemented_count,
+        network_block_count: network.map(|n| n.block_count),
+        network_cemented_count: network.map(|n| n.cemented_count),
+        telemetry_nodes: network.map(|n| n.nodes).unwrap_or_default(),
+        sync_percentage: network.map(|n| percentage(block_count, n.block_count)),
+        cemented_percentage: network.map(|n| percentage(cemented_count, n.cemented_count)),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blocks::BlockHash;
+    use crate::node::state::MemoryState;
+    use std::net::{Ipv4Addr, SocketAddrV4};
+
+    fn address() -> SocketAddr {
+        SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::LOCALHOST, 7075))
+    }
+
+    fn ack(index: u32, block_count: u64, cemented_count: u64) -> TelemetryAck {
+        let private = Seed::zero().derive(index);
+        TelemetryAck::signed(&private, BlockHash::zero(), block_count, cemented_count)
+    }
+
+    #[test]
+    fn median_of_latest_reports() {
+        let telemetry = NetworkTelemetry::new();
+        let start = Instant::now();
+        assert_eq!(telemetry.estimate_at(start), None);
+
+        telemetry.record_at(address(), &ack(0, 100, 90), start);
+        telemetry.record_at(address(), &ack(1, 1_000_000, 1_000_000), start);
+        telemetry.record_at(address(), &ack(2, 200, 150), start);
+        assert_eq!(
+            telemetry.estimate_at(start),
+            Some(NetworkCounts {
+                block_count: 200,
+                cemented_count: 150,
+                nodes: 3
+            })
+        );
+
+        // A newer report from the same node replaces its old one.
+        telemetry.record_at(
+            address(),
+            &ack(0, 300, 250),
+            start + Duration::from_secs(60),
+        );
+        let estimate = telemetry.estimate_at(start + Duration::from_secs(60));
+        assert_eq!(estimate.unwrap().block_count, 300);
+
+        // Only the newer report is left once the others are too old.
+        let later = start + MAX_AGE + Duration::from_secs(1);
+        assert_eq!(telemetry.estimate_at(later).unwrap().nodes, 1);
+    }
+
+    #[test]
+    fn reports() {
+        let telemetry = NetworkTelemetry::new();
+        let mut small = ack(0, 10, 5);
+        small.major_version = 21;
+        small.minor_version = 3;
+        telemetry.record(address(), &small);
+        telemetry.record(address(), &ack(1, 20, 5));
+
+        let reports = telemetry.reports();
+        assert_eq!(reports.len(), 2);
+        assert_eq!(reports[0].block_count, 20);
+        assert_eq!(reports[1].version, "21.3.0");
+        assert!(reports[1].node_id.starts_with("node_"));
+    }
+
+    #[tokio::test]
+    async fn signs_own_telemetry() {
+        let network = Network::Live;
+        let state: ArcState = Arc::new(MemoryState::new(network));
+        state.add_peers(&[address()]).await.unwrap();
+
+        let telemetry = NetworkTelemetry::new();
+        let ack = telemetry.ack(network, &state).await.unwrap();
+        ack.verify().unwrap();
+        assert_eq!(ack.node_id, telemetry.node_key().to_public().unwrap());
+        assert_eq!(ack.genesis_block, network.genesis_hash());
+        assert_eq!(ack.peer_count, 1);
+        assert_eq!(ack.maker, MAKER);
+
+        // What a peer decodes from the wire checks out too.
+        use crate::node::wire::Wire;
+        let decoded = TelemetryAck::deserialize(None, &ack.serialize()).unwrap();
+        decoded.verify().unwrap();
+    }
+
+    #[test]
+    fn even_median() {
+        assert_eq!(median(&mut [4, 1, 3, 2]), 2);
+        assert_eq!(median(&mut [5]), 5);
+    }
+
+    #[test]
+    fn percentages() {
+        assert_eq!(percentage(50, 200), 25.0);
+        assert_eq!(percentage(300, 200), 100.0);
+        assert_eq!(percentage(0, 0), 100.0);
+    }
+}
@@ -0,0 +1,128 @@
+//! Signed account chains for the node's tests.
+use crate::blocks::{Block, BlockHash, Link, Previous, StateBlock};
+use crate::node::state::ArcState;
+use crate::{Network, Private, Public, Raw, Seed, Work};
+
+/// The key of the test account at `index`.
+pub(crate) fn private(index: u32) -> Private {
+    Seed::zero().derive(index)
+}
+
+pub(crate) fn account(index: u32) -> Public {
+    private(index).to_public().unwrap()
+}
+
+/// A state block signed by `private`, which is its own representative.
+pub(crate) fn block(
+    private: &Private,
+    previous: Previous,
+    balance: u128,
+    link: Link,
+) -> StateBlock {
+    let account = private.to_public().unwrap();
+    let mut block = StateBlock::new(
+        account.to_owned(),
+        previous,
+        account,
+        Raw::from(balance),
+        link,
+    );
+    block.signature = Some(private.sign(block.hash.as_bytes()).unwrap());
+    block
+}
+
+/// An account chain of `len` signed blocks for the test account at `index`, each with one less
+/// raw than the last, down to one raw.
+pub(crate) fn chain(index: u32, len: u128) -> Vec<StateBlock> {
+    let private = private(index);
+    let mut previous = Previous::Open;
+    (0..len)
+        .map(|i| {
+            let block = block(&private, previous.to_owned(), len - i, Link::Nothing);
+            previous = Previous::Block(block.hash.to_owned());
+            block
+        })
+        .collect()
+}
+
+/// [chain] as [Block]s.
+pub(crate) fn blocks(index: u32, len: u128) -> Vec<Block> {
+    chain(index, len)
+        .iter()
+        .map(Block::from_state_block)
+        .collect()
+}
+
+pub(crate) async fn add(state: &ArcState, blocks: &[Block]) {
+    for block in blocks {
+        state.add_block(block).await.unwrap();
+    }
+}
+
+/// A signed block with enough work on the test network for any subtype.
+pub(crate) fn worked(
+    private: &Private,
+    previous: Option<&Block>,
+    balance: u128,
+    link: Link,
+) -> Block {
+    let previous = match previous {
+        Some(previous) => Previous::Block(previous.hash().unwrap().to_owned()),
+        None => Previous::Open,
+    };
+    let mut block = block(private, previous, balance, link);
+    let threshold = Network::Test.send_difficulty();
+    block.work = Some(Work::generate(&block.work_root(), &threshold).unwrap());
+    Block::from_state_block(&block)
+}
+
+/// An account that opens the others with its sends. Its open block doesn't receive anything,
+/// so it has to be in a state before any of the others, like the genesis block.
+pub(crate) struct Faucet {
+    pub private: Private,
+    pub blocks: Vec<Block>,
+}
+
+impl Faucet {
+    pub fn new() -> Self {
+        let private = private(100);
+        let open = worked(&private, None, 1_000_000, Link::Source(BlockHash::zero()));
+        Self {
+            private,
+            blocks: vec![open],
+        }
+    }
+
+    pub fn public(&self) -> Public {
+        self.private.to_public().unwrap()
+    }
+
+    pub fn open(&self) -> &Block {
+        &self.blocks[0]
+    }
+
+    /// An account chain of `len` worked blocks for the test account at `index`, opened with
+    /// `len` raw sent from the faucet, and then sending one raw at a time back to it.
+    pub fn chain(&mut self, index: u32, len: u128) -> Vec<Block> {
+        let private = private(index);
+        let faucet = self.blocks.last().unwrap();
+        let send = worked(
+            &self.private,
+            Some(faucet),
+            faucet.balance().to_u128() - len,
+            Link::DestinationAccount(private.to_public().unwrap()),
+        );
+        let mut chain = vec![worked(
+            &private,
+            None,
+            len,
+            Link::Source(send.hash().unwrap().to_owned()),
+        )];
+        self.blocks.push(send);
+        for i in 1..len {
+            let link = Link::DestinationAccount(self.public());
+            chain.push(worked(&private, chain.last(), len - i, link));
+        }
+        chain
+    }
+}
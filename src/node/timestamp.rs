@@ -1,7 +1,7 @@
 use crate::encoding::len_err_msg;
 use anyhow::Context;
 use std::convert::TryFrom;
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 #[derive(Eq, PartialEq, Debug, Clone)]
 pub struct Timestamp(u64);
@@ -21,10 +21,17 @@ impl Timestamp {
         Self(s)
     }
 
-    fn to_u64(&self) -> u64 {
+    pub fn to_u64(&self) -> u64 {
         self.0
     }
 
+    /// The lower 4 bits of a vote timestamp are the vote duration, being `2^(bits + 4)`
+    /// milliseconds. Nodes that don't set a duration leave milliseconds there instead, so this
+    /// only means something for votes from newer nodes.
+    pub fn vote_duration(&self) -> Duration {
+        Duration::from_millis(1 << ((self.0 & 0xf) + 4))
+    }
+
     pub fn to_bytes(&self) -> [u8; Self::LEN] {
         self.0.to_le_bytes()
     }
@@ -54,6 +61,18 @@ mod tests {
         assert_eq!(state, b);
     }
 
+    #[test]
+    fn vote_duration() {
+        assert_eq!(
+            Timestamp::from_u64(0xFFFF_FFF0).vote_duration(),
+            Duration::from_millis(16)
+        );
+        assert_eq!(
+            Timestamp::from_u64(0xFFFF_FFFF).vote_duration(),
+            Duration::from_millis(524288)
+        );
+    }
+
     #[test]
     fn encoding() {
         let it = Timestamp::now();
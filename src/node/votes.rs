@@ -0,0 +1,220 @@
+//! Storage for the votes we generate as a representative.
+//!
+//! Every vote is written to disk before it's sent, so that after a crash we know what was voted
+//! for. A final vote commits to a block for its root for good, so once one is stored, any vote
+//! for a different block on that root is refused. The stored votes can be exported for
+//! auditing, and are checked against the final votes when the node starts.
+use crate::blocks::{BlockHash, Root};
+use crate::network::Network;
+use anyhow::{anyhow, Context};
+use serde::{Deserialize, Serialize};
+use sled::transaction::{ConflictableTransactionError, TransactionError, Transactional};
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::path::{Path, PathBuf};
+
+/// The timestamp of a final vote, as in the reference node.
+pub const FINAL_TIMESTAMP: u64 = u64::MAX;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OwnVote {
+    /// Milliseconds since the Unix epoch with the vote duration in the lower bits, or
+    /// [FINAL_TIMESTAMP].
+    pub timestamp: u64,
+    pub blocks: Vec<VotedBlock>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VotedBlock {
+    pub root: Root,
+    pub hash: BlockHash,
+}
+
+impl OwnVote {
+    pub fn is_final(&self) -> bool {
+        self.timestamp == FINAL_TIMESTAMP
+    }
+}
+
+#[derive(Debug, Default, PartialEq, Serialize)]
+pub struct VoteCheck {
+    pub votes: usize,
+    pub final_votes: usize,
+
+    /// Votes that disagree with a final vote, which should never happen.
+    pub conflicts: Vec<String>,
+}
+
+#[derive(Clone, Debug)]
+pub struct VoteStore {
+    db: sled::Db,
+
+    /// Every vote as JSON, keyed by an increasing id so they're in the order they were made.
+    votes: sled::Tree,
+
+    /// The block hash we voted for finally, by root.
+    final_votes: sled::Tree,
+}
+
+impl VoteStore {
+    pub fn default_path(network: Network) -> PathBuf {
+        format!("{:?}-votes.db", network)
+            .to_ascii_lowercase()
+            .into()
+    }
+
+    pub fn open(path: &Path) -> anyhow::Result<Self> {
+        let db =
+            sled::open(path).with_context(|| format!("Could not open vote store {:?}", path))?;
+        Ok(Self {
+            votes: db.open_tree("votes")?,
+            final_votes: db.open_tree("final_votes")?,
+            db,
+        })
+    }
+
+    /// Store a vote, and only once it's on disk return, so it's safe to send.
+    ///
+    /// Fails without storing anything if any of the roots already has a final vote for a
+    /// different block.
+    pub async fn record(&self, vote: &OwnVote) -> anyhow::Result<()> {
+        let id = self.db.generate_id()?;
+        let json = serde_json::to_vec(vote)?;
+        let result = (&self.votes, &self.final_votes).transaction(|(votes, final_votes)| {
+            for block in &vote.blocks {
+                match final_votes.get(block.root.as_bytes())? {
+                    Some(hash) if hash.as_ref() != block.hash.as_bytes() => {
+                        return Err(ConflictableTransactionError::Abort(format!(
+                            "Already voted finally for {:?} on root {:?}, not {:?}",
+                            BlockHash::try_from(hash.as_ref()).ok(),
+                            block.root,
+                            block.hash
+                        )));
+                    }
+                    _ => {}
+                }
+                if vote.is_final() {
+                    final_votes.insert(block.root.as_bytes(), block.hash.as_bytes())?;
+                }
+            }
+            votes.insert(&id.to_be_bytes(), json.as_slice())?;
+            Ok(())
+        });
+        match result {
+            Ok(()) => {}
+            Err(TransactionError::Abort(conflict)) => return Err(anyhow!(conflict)),
+            Err(TransactionError::Storage(err)) => return Err(err.into()),
+        }
+        self.db.flush_async().await?;
+        Ok(())
+    }
+
+    /// Every stored vote, oldest first.
+    pub fn votes(&self) -> impl Iterator<Item = anyhow::Result<OwnVote>> {
+        self.votes.iter().values().map(|value| {
+            let value = value?;
+            serde_json::from_slice(&value).context("Stored vote")
+        })
+    }
+
+    /// The block we voted for finally on `root`.
+    pub fn final_vote(&self, root: &Root) -> anyhow::Result<Option<BlockHash>> {
+        match self.final_votes.get(root.as_bytes())? {
+            Some(hash) => Ok(Some(BlockHash::try_from(hash.as_ref())?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Go through the votes in the order they were made, checking that nothing was voted for on
+    /// a root after a final vote for another block, and that the final votes are all there.
+    pub fn check(&self) -> anyhow::Result<VoteCheck> {
+        let mut check = VoteCheck::default();
+        let mut finals: HashMap<Root, BlockHash> = HashMap::new();
+        for vote in self.votes() {
+            let vote = vote?;
+            check.votes += 1;
+            for block in &vote.blocks {
+                match finals.get(&block.root) {
+                    Some(hash) if hash != &block.hash => check.conflicts.push(format!(
+                        "Voted for {:?} on root {:?} after a final vote for {:?}",
+                        block.hash, block.root, hash
+                    )),
+                    Some(_) => {}
+                    None if vote.is_final() => {
+                        finals.insert(block.root.to_owned(), block.hash.to_owned());
+                    }
+                    None => {}
+                }
+            }
+        }
+
+        for (root, hash) in &finals {
+            match self.final_vote(root)? {
+                Some(stored) if &stored == hash => {}
+                stored => check.conflicts.push(format!(
+                    "Final vote on root {:?} is stored as {:?}, but was for {:?}",
+                    root, stored, hash
+                )),
+            }
+        }
+        check.final_votes = self.final_votes.len();
+        if check.final_votes != finals.len() {
+            check.conflicts.push(format!(
+                "There are {} final votes stored, but {} were made",
+                check.final_votes,
+                finals.len()
+            ));
+        }
+        Ok(check)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn vote(timestamp: u64, root: u8, hash: u8) -> OwnVote {
+        let bytes = |b: u8| [b; 32];
+        OwnVote {
+            timestamp,
+            blocks: vec![VotedBlock {
+                root: Root::try_from(bytes(root).as_ref()).unwrap(),
+                hash: BlockHash::try_from(bytes(hash).as_ref()).unwrap(),
+            }],
+        }
+    }
+
+    #[tokio::test]
+    async fn refuses_conflicting_votes() {
+        let path = PathBuf::from("refuses_conflicting_votes.db");
+        if path.exists() {
+            fs::remove_dir_all(&path).unwrap();
+        }
+        {
+            let store = VoteStore::open(&path).unwrap();
+            // Votes can change until they're final.
+            store.record(&vote(1, 1, 1)).await.unwrap();
+            store.record(&vote(2, 1, 2)).await.unwrap();
+            store.record(&vote(FINAL_TIMESTAMP, 1, 2)).await.unwrap();
+            store.record(&vote(3, 1, 2)).await.unwrap();
+            let err = store.record(&vote(4, 1, 1)).await.unwrap_err();
+            assert!(err.to_string().contains("Already voted finally"));
+        }
+
+        // Reopening, as after a crash.
+        let store = VoteStore::open(&path).unwrap();
+        assert!(store.record(&vote(5, 1, 3)).await.is_err());
+        assert_eq!(store.votes().count(), 4);
+        let check = store.check().unwrap();
+        assert_eq!(check.votes, 4);
+        assert_eq!(check.final_votes, 1);
+        assert!(check.conflicts.is_empty());
+
+        // Losing a final vote is caught.
+        store.final_votes.clear().unwrap();
+        assert_eq!(store.check().unwrap().conflicts.len(), 2);
+        drop(store);
+        fs::remove_dir_all(&path).unwrap();
+    }
+}
@@ -0,0 +1,162 @@
+//! Voting as a representative, in answer to the confirm_reqs of peers.
+//!
+//! The node votes when the `[voting]` section of the config file has a representative key, e.g.
+//! ```toml
+//! [voting]
+//! private_key = "..."
+//! ```
+//! Each confirm_req is answered with confirm_acks voting for the requested blocks that are in
+//! our ledger under the requested root. Hashes are batched into votes of up to
+//! [MAX_VOTE_HASHES]. The timestamp of each vote is the time in milliseconds with the vote
+//! duration in its lower 4 bits, and always goes up so that peers replace our older votes.
+//! Every vote is written to the [VoteStore] before it's sent.
+use crate::node::messages::confirm_ack::{Confirm, ConfirmAck};
+use crate::node::messages::confirm_req::RootHashPair;
+use crate::node::timestamp::Timestamp;
+use crate::node::votes::{OwnVote, VoteStore, VotedBlock};
+use crate::rpc::calls::from_str;
+use crate::{Private, Public, Signature};
+use serde::Deserialize;
+use std::fmt::{Debug, Formatter};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+/// The most hashes in one vote, the same as the reference node.
+pub const MAX_VOTE_HASHES: usize = 12;
+
+/// The lower bits of a vote timestamp with the vote duration. See [Timestamp::vote_duration].
+const DURATION_BITS: u64 = 0xf;
+
+/// The `[voting]` section of the config file.
+#[derive(Clone, Deserialize)]
+pub struct VotingConfig {
+    /// The private key of the representative to vote as.
+    #[serde(deserialize_with = "from_str")]
+    pub private_key: Private,
+
+    /// Where to store our votes. Defaults to [VoteStore::default_path].
+    #[serde(default)]
+    pub vote_store: Option<PathBuf>,
+}
+
+impl Debug for VotingConfig {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("VotingConfig")
+            .field("account", &self.private_key.to_public().ok())
+            .field("vote_store", &self.vote_store)
+            .finish()
+    }
+}
+
+/// Signs and stores votes as a representative. Shared by every [crate::node::Peer].
+#[derive(Clone)]
+pub struct Voter {
+    private: Private,
+    account: Public,
+    store: Option<VoteStore>,
+    last_timestamp: Arc<Mutex<u64>>,
+}
+
+impl Voter {
+    /// Votes are only stored when there's a `store`, which should only be left out in tests.
+    pub fn new(private: Private, store: Option<VoteStore>) -> anyhow::Result<Self> {
+        Ok(Self {
+            account: private.to_public()?,
+            private,
+            store,
+            last_timestamp: Arc::new(Mutex::new(0)),
+        })
+    }
+
+    pub fn account(&self) -> &Public {
+        &self.account
+    }
+
+    /// Sign votes for `blocks`, batched into as few votes as possible, storing each one before
+    /// it's returned. Fails if any of them conflicts with a final vote we've made.
+    pub async fn vote(&self, blocks: &[RootHashPair]) -> anyhow::Result<Vec<ConfirmAck>> {
+        let mut acks = vec![];
+        for batch in blocks.chunks(MAX_VOTE_HASHES) {
+            let timestamp = self.next_timestamp();
+            if let Some(store) = &self.store {
+                let vote = OwnVote {
+                    timestamp,
+                    blocks: batch
+                        .iter()
+                        .map(|pair| VotedBlock {
+                            root: pair.root.to_owned(),
+                            hash: pair.hash.to_owned(),
+                        })
+                        .collect(),
+                };
+                store.record(&vote).await?;
+            }
+
+            let hashes = batch.iter().map(|pair| pair.hash.to_owned()).collect();
+            let mut ack = ConfirmAck::new(
+                self.account.to_owned(),
+                Signature::zero(),
+                Timestamp::from_u64(timestamp),
+                Confirm::VoteByHash(hashes),
+            );
+            ack.signature = self.private.sign(&ack.inner_hash())?;
+            acks.push(ack);
+        }
+        Ok(acks)
+    }
+
+    /// The time in milliseconds with the duration bits set, and higher than any before it.
+    fn next_timestamp(&self) -> u64 {
+        let now = Timestamp::now().to_u64() & !DURATION_BITS | DURATION_BITS;
+        let mut last = self.last_timestamp.lock().unwrap();
+        *last = now.max(*last + DURATION_BITS + 1);
+        *last
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blocks::{BlockHash, Root};
+    use crate::Seed;
+    use std::convert::TryFrom;
+
+    fn pair(byte: u8) -> RootHashPair {
+        RootHashPair {
+            hash: BlockHash::try_from([byte; BlockHash::LEN].as_ref()).unwrap(),
+            root: Root::try_from([byte + 100; Root::LEN].as_ref()).unwrap(),
+        }
+    }
+
+    #[tokio::test]
+    async fn batches_signed_votes() {
+        let voter = Voter::new(Seed::zero().derive(0), None).unwrap();
+        let pairs: Vec<RootHashPair> = (0..15).map(pair).collect();
+        let acks = voter.vote(&pairs).await.unwrap();
+        assert_eq!(acks.len(), 2);
+
+        let mut hashes = 0;
+        for ack in &acks {
+            ack.verify_signature().unwrap();
+            assert_eq!(&ack.account, voter.account());
+            if let Confirm::VoteByHash(h) = &ack.confirm {
+                hashes += h.len();
+            }
+        }
+        assert_eq!(hashes, 15);
+        assert!(acks[1].timestamp.to_u64() > acks[0].timestamp.to_u64());
+        assert_eq!(acks[0].timestamp.to_u64() & DURATION_BITS, DURATION_BITS);
+    }
+
+    #[test]
+    fn config() {
+        let private = Seed::zero().derive(0);
+        let toml = format!("private_key = \"{}\"", private);
+        let config: VotingConfig = toml::from_str(&toml).unwrap();
+        assert_eq!(
+            config.private_key.to_public().unwrap(),
+            private.to_public().unwrap()
+        );
+        assert!(!format!("{:?}", config).contains(&private.to_string()));
+    }
+}
@@ -1,9 +1,19 @@
 use std::fmt::Debug;
 
 use crate::node::header::Header;
+use bytes::BytesMut;
+use std::convert::TryInto;
+use std::net::Ipv6Addr;
 
 pub trait Wire: Debug {
-    fn serialize(&self) -> Vec<u8>;
+    /// Append the serialized message to `buf`, so that a buffer can be reused between messages.
+    fn serialize_into(&self, buf: &mut BytesMut);
+
+    fn serialize(&self) -> BytesMut {
+        let mut buf = BytesMut::new();
+        self.serialize_into(&mut buf);
+        buf
+    }
 
     /// `header` will be `None` when we're deserializing the header itself.
     fn deserialize(header: Option<&Header>, data: &[u8]) -> anyhow::Result<Self>
@@ -14,4 +24,91 @@ pub trait Wire: Debug {
     fn len(header: Option<&Header>) -> anyhow::Result<usize>
     where
         Self: Sized;
+
+    /// The layout of the serialized data, in order, for `--dump-wire`. The lengths should add up
+    /// to [Wire::len]. Bytes not covered by a field are dumped without a name.
+    fn fields(_header: Option<&Header>) -> anyhow::Result<Vec<Field>>
+    where
+        Self: Sized,
+    {
+        Ok(vec![])
+    }
+}
+
+/// A named run of bytes in a serialized message.
+#[derive(Debug, Clone)]
+pub struct Field {
+    pub name: String,
+    pub len: usize,
+
+    /// Shows the value of the bytes, for fields that aren't just hashes or keys.
+    pub decode: Option<fn(&[u8]) -> String>,
+}
+
+impl Field {
+    pub fn new(name: impl Into<String>, len: usize) -> Self {
+        Self {
+            name: name.into(),
+            len,
+            decode: None,
+        }
+    }
+
+    pub fn decoded(mut self, decode: fn(&[u8]) -> String) -> Self {
+        self.decode = Some(decode);
+        self
+    }
+
+    /// `fields` with their names under `prefix`, e.g. `block.account`.
+    pub fn within(prefix: &str, fields: Vec<Field>) -> Vec<Field> {
+        fields
+            .into_iter()
+            .map(|mut field| {
+                field.name = format!("{}.{}", prefix, field.name);
+                field
+            })
+            .collect()
+    }
+}
+
+/// Decoders for [Field::decoded]. They're only handed fields of the right length.
+pub mod decode {
+    use super::*;
+
+    pub fn u8(data: &[u8]) -> String {
+        data[0].to_string()
+    }
+
+    pub fn u16_le(data: &[u8]) -> String {
+        u16::from_le_bytes(data.try_into().unwrap()).to_string()
+    }
+
+    pub fn u32_le(data: &[u8]) -> String {
+        u32::from_le_bytes(data.try_into().unwrap()).to_string()
+    }
+
+    pub fn u32_be(data: &[u8]) -> String {
+        u32::from_be_bytes(data.try_into().unwrap()).to_string()
+    }
+
+    pub fn u64_be(data: &[u8]) -> String {
+        u64::from_be_bytes(data.try_into().unwrap()).to_string()
+    }
+
+    pub fn u64_le(data: &[u8]) -> String {
+        u64::from_le_bytes(data.try_into().unwrap()).to_string()
+    }
+
+    pub fn raw(data: &[u8]) -> String {
+        format!("{} raw", u128::from_be_bytes(data.try_into().unwrap()))
+    }
+
+    pub fn ipv6(data: &[u8]) -> String {
+        let octets: [u8; 16] = data.try_into().unwrap();
+        let ip = Ipv6Addr::from(octets);
+        match ip.to_ipv4_mapped() {
+            Some(v4) => v4.to_string(),
+            None => ip.to_string(),
+        }
+    }
 }
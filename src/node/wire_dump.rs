@@ -0,0 +1,321 @@
+//! Hexdumps of the messages sent to and received from peers, for `node --dump-wire`.
+//!
+//! Each message is dumped a field per line, with the offset of the field, its bytes and its name
+//! from the [Wire::fields] layout of the message type, e.g.
+//! ```text
+//! 0000  52 43 14 14 12 02 00 00                          header.magic
+//! ...
+//! 0008  00 00 00 00 00 00 00 00 00 00 ff ff 7f 00 00 01  peer[0].ip = 127.0.0.1
+//! 0018  c3 1b                                            peer[0].port = 7107
+//! ```
+//! Fields longer than 16 bytes carry on over the following lines. Bytes past the end of the
+//! layout are dumped without a name, so a length mismatch with the C++ node stands out.
+use crate::node::header::{Header, MessageType};
+use crate::node::messages::bulk_pull::BulkPull;
+use crate::node::messages::confirm_ack::ConfirmAck;
+use crate::node::messages::confirm_req::ConfirmReq;
+use crate::node::messages::frontier_req::FrontierReq;
+use crate::node::messages::handshake::Handshake;
+use crate::node::messages::keepalive::Keepalive;
+use crate::node::messages::publish::Publish;
+use crate::node::messages::telemetry_ack::TelemetryAck;
+use crate::node::wire::{Field, Wire};
+use std::fmt::Write;
+use std::str::FromStr;
+
+const BYTES_PER_LINE: usize = 16;
+
+/// Which messages to dump, and the message being sent so far.
+#[derive(Debug, Clone, Default)]
+pub struct WireDump {
+    /// `None` for every type.
+    types: Option<Vec<MessageType>>,
+
+    /// Messages are sent as a header and then one or more parts of the payload, so they're
+    /// collected here until the whole layout has been sent.
+    outgoing: Option<Outgoing>,
+}
+
+#[derive(Debug, Clone)]
+struct Outgoing {
+    header: Header,
+    layout: Vec<Field>,
+    data: Vec<u8>,
+}
+
+impl WireDump {
+    pub fn all() -> Self {
+        Self::default()
+    }
+
+    pub fn selects(&self, message_type: MessageType) -> bool {
+        match &self.types {
+            Some(types) => types.contains(&message_type),
+            None => true,
+        }
+    }
+
+    /// A message with `header` is about to be sent.
+    pub fn sending(&mut self, header: &Header) {
+        self.outgoing = if self.selects(header.message_type()) {
+            layout(header).ok().map(|layout| Outgoing {
+                header: *header,
+                layout,
+                data: vec![],
+            })
+        } else {
+            None
+        };
+    }
+
+    /// `data` was sent. Returns the dump once the whole message has been sent.
+    pub fn sent(&mut self, data: &[u8]) -> Option<String> {
+        let outgoing = self.outgoing.as_mut()?;
+        outgoing.data.extend_from_slice(data);
+        let len: usize = outgoing.layout.iter().map(|f| f.len).sum();
+        if outgoing.data.len() < len {
+            return None;
+        }
+        let outgoing = self.outgoing.take()?;
+        Some(dump(&outgoing.header, &outgoing.layout, &outgoing.data))
+    }
+
+    /// The dump of a received message, if its type is selected.
+    pub fn received(&self, header: &Header, payload: &[u8]) -> Option<String> {
+        if !self.selects(header.message_type()) {
+            return None;
+        }
+        let mut data = header.serialize();
+        data.extend_from_slice(payload);
+        let layout = layout(header).unwrap_or_else(|_| Header::fields(None).unwrap());
+        Some(dump(header, &layout, &data))
+    }
+}
+
+/// `all`, or a comma separated list of message types, e.g. `keepalive,confirm_ack`.
+impl FromStr for WireDump {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.trim() == "all" {
+            return Ok(Self::all());
+        }
+        let types = s
+            .split(',')
+            .map(MessageType::from_str)
+            .collect::<anyhow::Result<_>>()?;
+        Ok(Self {
+            types: Some(types),
+            outgoing: None,
+        })
+    }
+}
+
+/// The fields of the header and then the payload of a message.
+fn layout(header: &Header) -> anyhow::Result<Vec<Field>> {
+    let h = Some(header);
+    let payload = match header.message_type() {
+        MessageType::Keepalive => Keepalive::fields(h)?,
+        MessageType::Publish => Publish::fields(h)?,
+        MessageType::ConfirmReq => ConfirmReq::fields(h)?,
+        MessageType::ConfirmAck => ConfirmAck::fields(h)?,
+        MessageType::FrontierReq => FrontierReq::fields(h)?,
+        MessageType::Handshake => Handshake::fields(h)?,
+        MessageType::TelemetryAck => TelemetryAck::fields(h)?,
+        MessageType::BulkPull => BulkPull::fields(h)?,
+        MessageType::BulkPush | MessageType::BulkPullAccount | MessageType::TelemetryReq => vec![],
+    };
+    let mut fields = Field::within("header", Header::fields(None)?);
+    fields.extend(payload);
+    Ok(fields)
+}
+
+fn dump(header: &Header, layout: &[Field], data: &[u8]) -> String {
+    let mut s = format!("{} ({} bytes)", header.message_type().name(), data.len());
+    let mut offset = 0;
+    for field in layout {
+        if offset >= data.len() {
+            let _ = write!(s, "\n{:04x}  {:47}  {} (missing)", offset, "", field.name);
+            break;
+        }
+        let end = (offset + field.len).min(data.len());
+        let bytes = &data[offset..end];
+        let mut label = field.name.to_owned();
+        match field.decode {
+            Some(decode) if bytes.len() == field.len => {
+                let _ = write!(label, " = {}", decode(bytes));
+            }
+            _ if bytes.len() < field.len => label.push_str(" (truncated)"),
+            _ => {}
+        }
+        lines(&mut s, offset, bytes, &label);
+        offset = end;
+    }
+    if offset < data.len() {
+        lines(&mut s, offset, &data[offset..], "");
+    }
+    s
+}
+
+/// `bytes` at `offset`, 16 to a line, with `label` on the first line.
+fn lines(s: &mut String, offset: usize, bytes: &[u8], label: &str) {
+    for (i, chunk) in bytes.chunks(BYTES_PER_LINE).enumerate() {
+        let hex: Vec<String> = chunk.iter().map(|b| format!("{:02x}", b)).collect();
+        let label = if i == 0 { label } else { "" };
+        let line = format!(
+            "{:04x}  {:47}  {}",
+            offset + i * BYTES_PER_LINE,
+            hex.join(" "),
+            label
+        );
+        let _ = write!(s, "\n{}", line.trim_end());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::node::cookie::Cookie;
+    use crate::node::header::Extensions;
+    use crate::node::messages::handshake::HandshakeQuery;
+    use crate::node::peer_info::PeerInfo;
+    use crate::Network;
+    use std::net::SocketAddr;
+
+    #[test]
+    fn parse() {
+        assert!(WireDump::from_str("all")
+            .unwrap()
+            .selects(MessageType::Publish));
+        let dump = WireDump::from_str("keepalive, confirm_ack,handshake").unwrap();
+        assert!(dump.selects(MessageType::Keepalive));
+        assert!(dump.selects(MessageType::ConfirmAck));
+        assert!(dump.selects(MessageType::Handshake));
+        assert!(!dump.selects(MessageType::Publish));
+        assert!(WireDump::from_str("keepalive,nope").is_err());
+    }
+
+    #[test]
+    fn keepalive() {
+        let header = Header::new(Network::Live, MessageType::Keepalive, Extensions::new());
+        let peer = PeerInfo::from(SocketAddr::from_str("127.0.0.1:7075").unwrap());
+        let payload = Keepalive::new(vec![peer]).serialize();
+
+        let text = WireDump::all().received(&header, &payload).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines[0], "keepalive (152 bytes)");
+        assert!(lines[2].ends_with("header.network = Live"), "{}", lines[2]);
+        assert!(lines[6].ends_with("header.message_type = Keepalive"));
+        assert_eq!(
+            lines[8],
+            "0008  00 00 00 00 00 00 00 00 00 00 ff ff 7f 00 00 01  peer[0].ip = 127.0.0.1"
+        );
+        assert_eq!(
+            lines[9],
+            format!("0018  a3 1b{:42}  peer[0].port = 7075", "")
+        );
+        assert!(lines.last().unwrap().starts_with("0096  00 00"));
+        assert_eq!(lines.len(), 1 + 7 + 16);
+    }
+
+    #[test]
+    fn sent_in_parts() {
+        let mut dump = WireDump::from_str("node_id_handshake").unwrap();
+        let header = Header::new(
+            Network::Live,
+            MessageType::Handshake,
+            *Extensions::new().query(),
+        );
+        dump.sending(&header);
+        assert_eq!(dump.sent(&header.serialize()), None);
+        let text = dump
+            .sent(&HandshakeQuery::new(Cookie::random()).serialize())
+            .unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines[0], "node_id_handshake (40 bytes)");
+        assert!(lines[8].ends_with("query.cookie"));
+        assert!(lines[9].starts_with("0018  "));
+        assert_eq!(lines.len(), 10);
+
+        // Nothing is pending once the message is out.
+        assert_eq!(dump.sent(&[0]), None);
+
+        let keepalive = Header::new(Network::Live, MessageType::Keepalive, Extensions::new());
+        dump.sending(&keepalive);
+        assert_eq!(dump.sent(&keepalive.serialize()), None);
+    }
+
+    /// Layouts have to add up to the length that's read off the wire.
+    #[test]
+    fn layouts_match_lengths() {
+        use crate::blocks::{BlockHolder, BlockType};
+
+        let header = |message_type, ext: Extensions| Header::new(Network::Live, message_type, ext);
+        let total = |header: &Header| -> usize {
+            layout(header).unwrap().iter().map(|f| f.len).sum::<usize>() - Header::LEN
+        };
+
+        let keepalive = header(MessageType::Keepalive, Extensions::new());
+        assert_eq!(total(&keepalive), Keepalive::len(Some(&keepalive)).unwrap());
+        let handshake = header(
+            MessageType::Handshake,
+            *Extensions::new().query().response(),
+        );
+        assert_eq!(total(&handshake), Handshake::len(Some(&handshake)).unwrap());
+        let by_hash = header(
+            MessageType::ConfirmReq,
+            *Extensions::new()
+                .set_block_type(BlockType::NotABlock)
+                .set_item_count(3),
+        );
+        assert_eq!(total(&by_hash), ConfirmReq::len(Some(&by_hash)).unwrap());
+        let vote = header(
+            MessageType::ConfirmAck,
+            *Extensions::new()
+                .set_block_type(BlockType::NotABlock)
+                .set_item_count(12),
+        );
+        assert_eq!(total(&vote), ConfirmAck::len(Some(&vote)).unwrap());
+        for block_type in &[
+            BlockType::Send,
+            BlockType::Receive,
+            BlockType::Open,
+            BlockType::Change,
+            BlockType::State,
+        ] {
+            let publish = header(
+                MessageType::Publish,
+                *Extensions::new().set_block_type(block_type.to_owned()),
+            );
+            assert_eq!(
+                total(&publish),
+                BlockHolder::len(Some(&publish)).unwrap(),
+                "{:?}",
+                block_type
+            );
+        }
+        let telemetry = header(MessageType::TelemetryAck, Extensions::new());
+        assert_eq!(total(&telemetry), TelemetryAck::LEN);
+        let frontier_req = header(MessageType::FrontierReq, Extensions::new());
+        assert_eq!(total(&frontier_req), FrontierReq::LEN);
+        let bulk_pull = header(
+            MessageType::BulkPull,
+            *Extensions::new().set_count_present(),
+        );
+        assert_eq!(total(&bulk_pull), BulkPull::LEN + BulkPull::EXTENDED_LEN);
+    }
+
+    #[test]
+    fn extra_and_missing_bytes() {
+        let header = Header::new(Network::Live, MessageType::FrontierReq, Extensions::new());
+        let text = WireDump::all().received(&header, &[1u8; 42]).unwrap();
+        let last: Vec<&str> = text.lines().rev().take(2).collect();
+        assert!(last[1].ends_with("count = 16843009"));
+        assert_eq!(last[0], "0030  01 01");
+
+        let text = WireDump::all().received(&header, &[1u8; 34]).unwrap();
+        let last: Vec<&str> = text.lines().rev().take(2).collect();
+        assert!(last[1].ends_with("age (truncated)"));
+        assert!(last[0].ends_with("count (missing)"));
+    }
+}
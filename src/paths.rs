@@ -8,7 +8,7 @@ use std::path::{Path, PathBuf};
 /// CLI options for [Paths].
 #[derive(Clap)]
 pub(crate) struct PathsOpts {
-    #[clap(short = 'n', long, default_value = "live")]
+    #[clap(short = 'n', long, default_value = "live", env = "FEELESS_NETWORK")]
     network: Network,
 
     #[clap(long, env = "FEELESS_DATA_DIR")]
@@ -16,11 +16,31 @@ pub(crate) struct PathsOpts {
 }
 
 impl PathsOpts {
+    pub fn network(&self) -> Network {
+        self.network
+    }
+
     pub fn wallet_path(&self) -> anyhow::Result<PathBuf> {
-        let p = Paths::new_maybe_custom(self.network.clone(), self.data_dir.clone());
+        let p = Paths::new_maybe_custom(self.network, self.data_dir.clone());
         p.ensure_data_path()?;
         Ok(p.wallet_path())
     }
+
+    pub fn audit_log_path(&self) -> anyhow::Result<PathBuf> {
+        let p = Paths::new_maybe_custom(self.network, self.data_dir.clone());
+        p.ensure_data_path()?;
+        Ok(p.audit_log_path())
+    }
+
+    pub fn walletd_token_path(&self) -> anyhow::Result<PathBuf> {
+        let p = Paths::new_maybe_custom(self.network, self.data_dir.clone());
+        p.ensure_data_path()?;
+        Ok(p.walletd_token_path())
+    }
+
+    pub fn config_path(&self) -> PathBuf {
+        Paths::new_maybe_custom(self.network, self.data_dir.clone()).config_path()
+    }
 }
 
 /// Contains the base path to wallets, databases, etc.
@@ -77,6 +97,21 @@ impl Paths {
         self.data_path(Path::new("wallet"))
     }
 
+    /// Return the path to the wallet audit log.
+    pub fn audit_log_path(&self) -> PathBuf {
+        self.data_path(Path::new("wallet.audit"))
+    }
+
+    /// Return the path to the token that `feeless walletd` clients authenticate with.
+    pub fn walletd_token_path(&self) -> PathBuf {
+        self.data_path(Path::new("walletd.token"))
+    }
+
+    /// Return the path to the config file.
+    pub fn config_path(&self) -> PathBuf {
+        self.data_path(Path::new("feeless.toml"))
+    }
+
     /// Make sure the data path exists.
     pub fn ensure_data_path(&self) -> anyhow::Result<()> {
         create_dir_all(&self.data)?;
@@ -1,7 +1,7 @@
 use crate::network::Network;
-use crate::network::DEFAULT_PORT;
-use crate::node::{MemoryState, Packet, Peer};
+use crate::node::{EarlyMessages, MemoryState, Packet, Peer};
 use anyhow::Context;
+use bytes::BytesMut;
 use chrono::{DateTime, Utc};
 use etherparse::{InternetSlice, SlicedPacket};
 use etherparse::{Ipv4HeaderSlice, TcpHeaderSlice, TransportSlice};
@@ -11,7 +11,6 @@ use std::fs::File;
 use std::net::{IpAddr, Ipv4Addr, SocketAddr};
 use std::sync::Arc;
 use tokio::sync::mpsc::Sender;
-use tokio::sync::Mutex;
 use tokio::time::Duration;
 use tracing::{debug, error, info, trace, warn};
 
@@ -42,12 +41,15 @@ pub(crate) struct PcapDump {
     frontiers: HashSet<String>,
 
     /// per_stream_peers
-    peers: HashMap<String, Sender<Packet>>,
+    peers: HashMap<String, Sender<Packet<BytesMut>>>,
 
     pub start_at: Option<usize>,
     pub end_at: Option<usize>,
     pub filter_addr: Option<Ipv4Addr>,
 
+    /// Which network the capture is from, for the port to look at and the message headers.
+    pub network: Network,
+
     subject: Subject,
     found_subject: Option<Ipv4Addr>,
 
@@ -72,13 +74,14 @@ impl PcapDump {
             start_at: None,
             end_at: None,
             filter_addr: None,
+            network: Network::Live,
             peers: Default::default(),
         }
     }
 
     pub async fn dump(&mut self, path: &str) -> anyhow::Result<()> {
-        let network = Network::Live;
-        let state = Arc::new(Mutex::new(MemoryState::new(network)));
+        let network = self.network;
+        let state = Arc::new(MemoryState::new(network));
 
         info!("Loading dump: {}", path);
 
@@ -95,17 +98,17 @@ impl PcapDump {
                 .next()
                 .transpose()
                 .with_context(|| format!("Reading next packet: {}", self.packet_idx))?;
-            let packet = if packet.is_none() {
+            let packet = if let Some(packet) = packet {
+                packet
+            } else {
                 // EOF
                 debug!("No more packets in pcap. Waiting for cleanup, then exiting.");
                 // TODO: Do this a better way, maybe give the peer an internal only exit message.
                 tokio::time::sleep(Duration::from_secs(1)).await;
                 return Ok(());
-            } else {
-                packet.unwrap()
             };
             let timestamp: DateTime<Utc> = packet.timestamp.unwrap().into();
-            let packet = match SlicedPacket::from_ethernet(&packet.data).with_context(|| {
+            let packet = match SlicedPacket::from_ethernet(packet.data).with_context(|| {
                 format!(
                     "Parsing packet data to ethernet for packet {}",
                     self.packet_idx
@@ -156,12 +159,13 @@ impl PcapDump {
                 }
             }
 
-            if data.len() == 0 {
+            if data.is_empty() {
                 continue;
             }
 
-            // Only look at port 7075.
-            if tcp.destination_port() != DEFAULT_PORT && tcp.source_port() != DEFAULT_PORT {
+            // Only look at the node port of the network.
+            let port = self.network.default_port();
+            if tcp.destination_port() != port && tcp.source_port() != port {
                 continue;
             }
 
@@ -179,7 +183,7 @@ impl PcapDump {
                 tcp.destination_port()
             );
 
-            let mut connection_id = vec![
+            let mut connection_id = [
                 ip.source_addr().to_string(),
                 tcp.source_port().to_string(),
                 ip.destination_addr().to_string(),
@@ -210,7 +214,7 @@ impl PcapDump {
                     let peer_addr =
                         SocketAddr::new(IpAddr::V4(ip.destination_addr()), tcp.destination_port());
                     let (mut c, tx, mut rx) =
-                        Peer::new_with_channels(network, state_cloned, peer_addr.clone());
+                        Peer::new_with_channels(network, state_cloned, peer_addr);
 
                     // Discard all responses from the controller since we are just processing
                     // packets.
@@ -225,6 +229,8 @@ impl PcapDump {
 
                     tokio::spawn(async move {
                         c.validate_handshakes = false;
+                        c.early_messages = EarlyMessages::Allow;
+                        c.probe_peers = false;
                         let result = c.run().await;
                         if let Err(err) = result {
                             error!("Error on pcap controller {:?}: {:?}", peer_addr, err);
@@ -236,7 +242,7 @@ impl PcapDump {
                 }
             };
 
-            tx.send(Packet::new_with_annotation(Vec::from(data), annotation))
+            tx.send(Packet::new_with_annotation(data, annotation))
                 .await?;
         }
     }
@@ -257,7 +263,7 @@ impl PcapDump {
             return None;
         };
 
-        let data_len = ip.payload_len() as usize - tcp.slice().len() as usize;
+        let data_len = ip.payload_len() as usize - tcp.slice().len();
         Some((ip, tcp, &packet.payload[..data_len]))
     }
 }
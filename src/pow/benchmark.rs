@@ -0,0 +1,146 @@
+//! Measure how fast each way of generating work runs on this machine, for `feeless work
+//! benchmark`, to help size work servers.
+//!
+//! The backends are the CPU on one thread and on every thread. There's no GPU backend, so it
+//! can't be measured.
+use super::work::Search;
+use crate::blocks::{BlockHash, Root};
+use crate::{Difficulty, Network};
+use serde::Serialize;
+use std::fmt::{Display, Formatter};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Hashes between checking the clock.
+const ATTEMPTS_PER_CHECK: u64 = 1_000;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Benchmark {
+    pub network: String,
+    pub send_difficulty: Difficulty,
+    pub receive_difficulty: Difficulty,
+    pub results: Vec<BenchmarkResult>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BenchmarkResult {
+    pub backend: &'static str,
+    pub threads: usize,
+    pub hashes: u64,
+    pub seconds: f64,
+    pub hashes_per_second: f64,
+
+    /// The average time to find work at the network's send difficulty.
+    pub send_seconds: f64,
+
+    /// The average time to find work at the network's receive difficulty.
+    pub receive_seconds: f64,
+}
+
+impl Benchmark {
+    /// Hash for `duration` with each backend in turn, estimating how long work for `network`
+    /// takes. This blocks the thread for the whole time.
+    pub fn run(network: Network, duration: Duration) -> Self {
+        let send_difficulty = network.send_difficulty();
+        let receive_difficulty = network.receive_difficulty();
+        let cpus = num_cpus::get();
+        let mut backends = vec![("cpu", 1)];
+        if cpus > 1 {
+            backends.push(("cpu_all_threads", cpus));
+        }
+
+        let results = backends
+            .into_iter()
+            .map(|(backend, threads)| {
+                let started = Instant::now();
+                let hashes = hash_for(duration, threads);
+                let seconds = started.elapsed().as_secs_f64();
+                let hashes_per_second = hashes as f64 / seconds;
+                BenchmarkResult {
+                    backend,
+                    threads,
+                    hashes,
+                    seconds,
+                    hashes_per_second,
+                    send_seconds: send_difficulty.expected_attempts() / hashes_per_second,
+                    receive_seconds: receive_difficulty.expected_attempts() / hashes_per_second,
+                }
+            })
+            .collect();
+
+        Benchmark {
+            network: network.to_string(),
+            send_difficulty,
+            receive_difficulty,
+            results,
+        }
+    }
+}
+
+/// Search for work that can't be found on `threads` threads for `duration`, returning how many
+/// hashes were tried.
+fn hash_for(duration: Duration, threads: usize) -> u64 {
+    // No hash is over the hardest difficulty, so the search never stops early.
+    let impossible = Difficulty::new(u64::MAX);
+    let root = Root::from(BlockHash::zero());
+    let deadline = Instant::now() + duration;
+    let handles: Vec<_> = (0..threads)
+        .map(|_| {
+            let mut search = Search::new(&root);
+            let impossible = impossible.to_owned();
+            thread::spawn(move || {
+                while Instant::now() < deadline {
+                    // Can't fail or find anything.
+                    let _ = search.attempt(&impossible, ATTEMPTS_PER_CHECK);
+                }
+                search.attempts
+            })
+        })
+        .collect();
+    handles
+        .into_iter()
+        .map(|handle| handle.join().expect("Work benchmark thread panicked"))
+        .sum()
+}
+
+impl Display for Benchmark {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Network: {}", self.network)?;
+        for result in &self.results {
+            writeln!(
+                f,
+                "{} ({} threads): {:.0} hashes/s, send work in {:.1?}, receive work in {:.1?}",
+                result.backend,
+                result.threads,
+                result.hashes_per_second,
+                Duration::from_secs_f64(result.send_seconds),
+                Duration::from_secs_f64(result.receive_seconds),
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn measures_every_backend() {
+        let benchmark = Benchmark::run(Network::Live, Duration::from_millis(50));
+        assert_eq!(benchmark.send_difficulty, Difficulty::normal());
+        assert_eq!(benchmark.results[0].backend, "cpu");
+        assert_eq!(benchmark.results[0].threads, 1);
+        assert_eq!(benchmark.results.len(), num_cpus::get().min(2));
+        for result in &benchmark.results {
+            assert!(result.hashes > 0);
+            assert!(result.seconds >= 0.05);
+            assert!(result.send_seconds > result.receive_seconds);
+        }
+
+        let json = serde_json::to_value(&benchmark).unwrap();
+        assert_eq!(json["network"], "live");
+        assert_eq!(json["send_difficulty"], "FFFFFFF800000000");
+        assert!(json["results"][0]["hashes_per_second"].as_f64().unwrap() > 0.);
+    }
+}
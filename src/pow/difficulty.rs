@@ -1,14 +1,20 @@
-use crate::encoding::{deserialize_from_str, expect_len, to_hex};
-use crate::{Error, Result};
+use crate::encoding::{deserialize_from_str, deserialize_from_string, expect_len, to_hex};
+use crate::{Error, Network, Result};
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use std::cmp::Ordering;
 use std::convert::TryFrom;
-use std::fmt::{Debug, Formatter};
+use std::fmt::{Debug, Display, Formatter};
 use std::str::FromStr;
 
-#[derive(Eq, PartialEq, Clone)]
+/// A work threshold. Higher is harder.
+#[derive(Eq, PartialEq, Clone, Hash)]
 pub struct Difficulty(u64);
 
+/// How many times harder a [Difficulty] is than a base one, e.g. the network's send threshold.
+/// The Nano RPC sends these as strings, e.g. `"1.182623871097636"`.
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+pub struct Multiplier(f64);
+
 impl Difficulty {
     const LEN: usize = 8;
     const HEX_LEN: usize = Self::LEN * 2;
@@ -43,6 +49,112 @@ impl Difficulty {
     pub fn as_u64(&self) -> u64 {
         self.0
     }
+
+    /// How many times harder this difficulty is than `base`, e.g. 2.0 if it takes twice as much
+    /// work on average.
+    pub fn multiplier(&self, base: &Difficulty) -> f64 {
+        (u64::MAX - base.0) as f64 / (u64::MAX - self.0) as f64
+    }
+
+    /// The difficulty that is `multiplier` times harder than this one.
+    pub fn with_multiplier(&self, multiplier: f64) -> Difficulty {
+        let easiness = (u64::MAX - self.0) as f64 / multiplier;
+        Difficulty(u64::MAX - easiness as u64)
+    }
+
+    /// How many times harder this is than the send threshold of `network`, which is what the
+    /// RPC reports as `multiplier`.
+    pub fn to_multiplier(&self, network: Network) -> Multiplier {
+        Multiplier::between(self, &network.send_difficulty())
+    }
+
+    /// The difficulty that is `multiplier` times the send threshold of `network`.
+    pub fn from_multiplier(network: Network, multiplier: Multiplier) -> Difficulty {
+        multiplier.apply(&network.send_difficulty())
+    }
+
+    /// How many hashes it takes on average to find work over this threshold.
+    pub fn expected_attempts(&self) -> f64 {
+        2f64.powi(64) / ((u64::MAX - self.0) as f64 + 1.)
+    }
+
+    /// Whether work of this difficulty is enough for `threshold`.
+    pub fn meets(&self, threshold: &Difficulty) -> bool {
+        self >= threshold
+    }
+}
+
+impl Multiplier {
+    /// Fails unless `multiplier` is a positive number.
+    pub fn new(multiplier: f64) -> Result<Self> {
+        if !multiplier.is_finite() || multiplier <= 0.0 {
+            return Err(Error::InvalidMultiplier(multiplier.to_string()));
+        }
+        Ok(Self(multiplier))
+    }
+
+    /// How many times harder `difficulty` is than `base`.
+    pub fn between(difficulty: &Difficulty, base: &Difficulty) -> Self {
+        // The hardest difficulty is infinitely hard, which isn't worth failing over.
+        Self(difficulty.multiplier(base).min(f64::MAX))
+    }
+
+    /// The difficulty that is this many times harder than `base`.
+    pub fn apply(&self, base: &Difficulty) -> Difficulty {
+        base.with_multiplier(self.0)
+    }
+
+    pub fn as_f64(&self) -> f64 {
+        self.0
+    }
+}
+
+impl Display for Multiplier {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl FromStr for Multiplier {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let multiplier = f64::from_str(s.trim())
+            .map_err(|_| Error::InvalidMultiplier(format!("{:?} is not a number", s)))?;
+        Self::new(multiplier)
+    }
+}
+
+impl Serialize for Multiplier {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for Multiplier {
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserialize_from_string(deserializer)
+    }
+}
+
+#[cfg(feature = "schema")]
+impl schemars::JsonSchema for Multiplier {
+    fn schema_name() -> String {
+        "Multiplier".into()
+    }
+
+    fn json_schema(_: &mut schemars::gen::SchemaGenerator) -> schemars::schema::Schema {
+        crate::schema::string(
+            "How many times harder than the base difficulty, as a decimal string",
+            r"^[0-9]+(\.[0-9]+)?$",
+        )
+    }
 }
 
 impl Debug for Difficulty {
@@ -67,7 +179,13 @@ impl FromStr for Difficulty {
 
 impl PartialOrd for Difficulty {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        self.0.partial_cmp(&other.0)
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Difficulty {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.cmp(&other.0)
     }
 }
 
@@ -92,6 +210,17 @@ impl<'de> Deserialize<'de> for Difficulty {
     }
 }
 
+#[cfg(feature = "schema")]
+impl schemars::JsonSchema for Difficulty {
+    fn schema_name() -> String {
+        "Difficulty".into()
+    }
+
+    fn json_schema(_: &mut schemars::gen::SchemaGenerator) -> schemars::schema::Schema {
+        crate::schema::hex("difficulty", Self::LEN)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -104,6 +233,53 @@ mod tests {
         );
     }
 
+    #[test]
+    fn multiplier() {
+        let base = Difficulty::normal();
+        let doubled = base.with_multiplier(2.0);
+        assert_eq!(doubled, Difficulty::from_str("fffffffc00000000").unwrap());
+        assert!((doubled.multiplier(&base) - 2.0).abs() < 1e-6);
+        assert!((base.multiplier(&base) - 1.0).abs() < 1e-6);
+        assert!(Difficulty::receive().multiplier(&base) < 1.0);
+
+        assert_eq!(base.expected_attempts(), 2f64.powi(29));
+        assert_eq!(Difficulty::receive().expected_attempts(), 2f64.powi(23));
+        assert_eq!(Difficulty::new(0).expected_attempts(), 1.);
+    }
+
+    #[test]
+    fn network_multipliers() {
+        let live = Difficulty::normal();
+        assert!((live.to_multiplier(Network::Live).as_f64() - 1.0).abs() < 1e-9);
+
+        let doubled = Difficulty::from_multiplier(Network::Beta, Multiplier::new(2.0).unwrap());
+        assert!(doubled.meets(&Network::Beta.send_difficulty()));
+        assert!(!Network::Beta.send_difficulty().meets(&doubled));
+        assert!((doubled.to_multiplier(Network::Beta).as_f64() - 2.0).abs() < 1e-6);
+        assert_eq!(
+            std::cmp::max(live.to_owned(), doubled.to_owned()),
+            live,
+            "The live threshold is harder than twice beta's"
+        );
+
+        // The hardest possible difficulty doesn't make an infinite multiplier.
+        assert!(Difficulty::new(u64::MAX)
+            .to_multiplier(Network::Live)
+            .as_f64()
+            .is_finite());
+    }
+
+    #[test]
+    fn multiplier_strings() {
+        let m: Multiplier = serde_json::from_str(r#""1.182623871097636""#).unwrap();
+        assert_eq!(m.as_f64(), 1.182623871097636);
+        assert_eq!(serde_json::to_string(&m).unwrap(), r#""1.182623871097636""#);
+
+        for bad in &["0", "-1.5", "NaN", "inf", "x"] {
+            assert!(Multiplier::from_str(bad).is_err(), "{}", bad);
+        }
+    }
+
     #[test]
     fn dont_panic() {
         // These have unwraps in them and so this is a sanity check to make sure it doesn't panic.
@@ -0,0 +1,209 @@
+//! Raising the work of blocks that were already built.
+//!
+//! Work isn't part of a block's hash, so a signed block can be given harder work without
+//! signing it again. The [WorkManager] runs one search per block at a time: asking for a harder
+//! difficulty while a search is running cancels it and starts again at the new difficulty, and
+//! asking for one that's no harder leaves the running search alone.
+use crate::blocks::{BlockHash, StateBlock};
+use crate::pow::{Difficulty, Work};
+use crate::CancellationToken;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use tracing::debug;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Upgrade {
+    /// The block's work already met the difficulty.
+    Unchanged,
+
+    /// The block has new work of this difficulty.
+    Upgraded(Difficulty),
+
+    /// A search for at least this difficulty is running for another caller, or this search was
+    /// cancelled, either for a harder one or with [WorkManager::cancel]. The block is untouched.
+    Superseded,
+}
+
+/// Shared by everything that upgrades work, so searches for the same block know about each
+/// other.
+#[derive(Debug, Clone, Default)]
+pub struct WorkManager {
+    inner: Arc<Mutex<HashMap<BlockHash, Search>>>,
+    next_id: Arc<AtomicU64>,
+}
+
+#[derive(Debug)]
+struct Search {
+    id: u64,
+    target: Difficulty,
+    cancel: CancellationToken,
+}
+
+impl WorkManager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Give `block` work of at least `target` difficulty, keeping its hash and signature.
+    pub async fn upgrade(
+        &self,
+        block: &mut StateBlock,
+        target: &Difficulty,
+    ) -> anyhow::Result<Upgrade> {
+        let root = block.work_root();
+        if let Some(work) = &block.work {
+            if work.difficulty(&root)?.meets(target) {
+                return Ok(Upgrade::Unchanged);
+            }
+        }
+
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let cancel = {
+            let mut inner = self.inner.lock().unwrap();
+            if let Some(running) = inner.get(&block.hash) {
+                if running.target.meets(target) {
+                    return Ok(Upgrade::Superseded);
+                }
+                debug!(
+                    "Cancelling work for {:?} at {:?} for {:?}",
+                    block.hash, running.target, target
+                );
+                running.cancel.cancel();
+            }
+            let cancel = CancellationToken::new();
+            inner.insert(
+                block.hash.to_owned(),
+                Search {
+                    id,
+                    target: target.to_owned(),
+                    cancel: cancel.clone(),
+                },
+            );
+            cancel
+        };
+
+        let result = Work::generate_async(&root, target, &cancel, |_| {}).await;
+
+        {
+            // Only this search's entry is removed, not one that replaced it.
+            let mut inner = self.inner.lock().unwrap();
+            if inner.get(&block.hash).is_some_and(|s| s.id == id) {
+                inner.remove(&block.hash);
+            }
+        }
+
+        let work = match result {
+            Ok(work) => work,
+            Err(err) if is_cancelled(&err) => return Ok(Upgrade::Superseded),
+            Err(err) => return Err(err),
+        };
+        let difficulty = work.difficulty(&root)?;
+        block.work = Some(work);
+        Ok(Upgrade::Upgraded(difficulty))
+    }
+
+    /// Stop searching for work for a block, e.g. once it's confirmed.
+    pub fn cancel(&self, hash: &BlockHash) {
+        if let Some(search) = self.inner.lock().unwrap().remove(hash) {
+            search.cancel.cancel();
+        }
+    }
+
+    /// The difficulty being searched for a block, if there's a search running.
+    pub fn in_flight(&self, hash: &BlockHash) -> Option<Difficulty> {
+        self.inner
+            .lock()
+            .unwrap()
+            .get(hash)
+            .map(|s| s.target.to_owned())
+    }
+}
+
+fn is_cancelled(err: &anyhow::Error) -> bool {
+    matches!(err.downcast_ref(), Some(crate::Error::Cancelled))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blocks::{Link, Previous};
+    use crate::{Raw, Seed};
+    use std::time::Duration;
+
+    fn block() -> StateBlock {
+        let account = Seed::zero().derive(0).to_public().unwrap();
+        StateBlock::new(
+            account.clone(),
+            Previous::Open,
+            account,
+            Raw::from(1),
+            Link::Nothing,
+        )
+    }
+
+    /// Too hard to be found while a test runs.
+    fn unreachable(n: u64) -> Difficulty {
+        Difficulty::new(u64::MAX - n)
+    }
+
+    async fn running(manager: &WorkManager, hash: &BlockHash, target: Difficulty) {
+        while manager.in_flight(hash).as_ref() != Some(&target) {
+            tokio::time::sleep(Duration::from_millis(1)).await;
+        }
+    }
+
+    #[tokio::test]
+    async fn upgrades_keeping_the_hash() {
+        let manager = WorkManager::new();
+        let mut block = block();
+        let hash = block.hash.to_owned();
+
+        let target = Difficulty::new(0xff00_0000_0000_0000);
+        let upgrade = manager.upgrade(&mut block, &target).await.unwrap();
+        let difficulty = match upgrade {
+            Upgrade::Upgraded(difficulty) => difficulty,
+            upgrade => panic!("{:?}", upgrade),
+        };
+        assert!(difficulty.meets(&target));
+        assert_eq!(block.hash, hash);
+        assert_eq!(manager.in_flight(&hash), None);
+
+        assert_eq!(
+            manager.upgrade(&mut block, &target).await.unwrap(),
+            Upgrade::Unchanged
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn harder_targets_cancel_easier_searches() {
+        let manager = WorkManager::new();
+        let hash = block().hash;
+
+        let easier = {
+            let manager = manager.clone();
+            tokio::spawn(async move { manager.upgrade(&mut block(), &unreachable(2)).await })
+        };
+        running(&manager, &hash, unreachable(2)).await;
+
+        // No harder than what's running, so it's left to finish.
+        assert_eq!(
+            manager
+                .upgrade(&mut block(), &unreachable(3))
+                .await
+                .unwrap(),
+            Upgrade::Superseded
+        );
+
+        let harder = {
+            let manager = manager.clone();
+            tokio::spawn(async move { manager.upgrade(&mut block(), &unreachable(1)).await })
+        };
+        assert_eq!(easier.await.unwrap().unwrap(), Upgrade::Superseded);
+        running(&manager, &hash, unreachable(1)).await;
+
+        manager.cancel(&hash);
+        assert_eq!(harder.await.unwrap().unwrap(), Upgrade::Superseded);
+        assert_eq!(manager.in_flight(&hash), None);
+    }
+}
@@ -1,5 +1,19 @@
+mod benchmark;
 mod difficulty;
+mod manager;
+mod thresholds;
+mod watcher;
 mod work;
+#[cfg(feature = "rpc_client")]
+mod work_server;
 
-pub use difficulty::Difficulty;
-pub use work::{Subject, Work};
+pub use benchmark::{Benchmark, BenchmarkResult};
+pub use difficulty::{Difficulty, Multiplier};
+pub use manager::{Upgrade, WorkManager};
+pub use thresholds::WorkThresholds;
+#[cfg(feature = "rpc_client")]
+pub use watcher::RPCWorkPublisher;
+pub use watcher::{WatchOutcome, WorkPublisher, WorkWatcher};
+pub use work::Work;
+#[cfg(feature = "rpc_client")]
+pub use work_server::{WorkConfig, WorkPeers, WorkServerClient, WorkServerError};
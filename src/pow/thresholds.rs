@@ -0,0 +1,102 @@
+//! The difficulty that work is generated at for each kind of block, which can be set in the
+//! `[wallet.work]` section of the config:
+//! ```toml
+//! [wallet.work]
+//! send = "fffffff800000000"
+//! receive = "fffffe0000000000"
+//! extra_multiplier = 1.5
+//! ```
+use crate::blocks::Subtype;
+use crate::pow::Difficulty;
+use crate::Network;
+use serde::Deserialize;
+
+/// Overrides of the network's base thresholds. Anything unset uses the base threshold.
+#[derive(Debug, Clone, Default, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct WorkThresholds {
+    /// For send blocks, and change blocks unless `change` is set.
+    pub send: Option<Difficulty>,
+
+    /// For receive, open and epoch blocks.
+    pub receive: Option<Difficulty>,
+
+    pub change: Option<Difficulty>,
+
+    /// How many times harder than the threshold to make the work, as a margin in case the
+    /// network raises its threshold. Less than 1 is treated as 1.
+    pub extra_multiplier: Option<f64>,
+}
+
+impl WorkThresholds {
+    /// Use the base thresholds of `network` for anything unset, instead of the live network's.
+    pub fn with_base(mut self, network: Network) -> Self {
+        self.send.get_or_insert_with(|| network.send_difficulty());
+        self.receive
+            .get_or_insert_with(|| network.receive_difficulty());
+        self
+    }
+
+    /// The difficulty to generate work for a block of `subtype` at.
+    pub fn difficulty(&self, subtype: &Subtype) -> Difficulty {
+        let send = || self.send.to_owned().unwrap_or_else(Difficulty::normal);
+        let threshold = match subtype {
+            Subtype::Send => send(),
+            Subtype::Change => self.change.to_owned().unwrap_or_else(send),
+            Subtype::Receive | Subtype::Open | Subtype::Epoch => {
+                self.receive.to_owned().unwrap_or_else(Difficulty::receive)
+            }
+        };
+        match self.extra_multiplier {
+            Some(multiplier) if multiplier > 1.0 => threshold.with_multiplier(multiplier),
+            _ => threshold,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn defaults_and_overrides() {
+        let defaults = WorkThresholds::default();
+        assert_eq!(defaults.difficulty(&Subtype::Send), Difficulty::normal());
+        assert_eq!(defaults.difficulty(&Subtype::Change), Difficulty::normal());
+        assert_eq!(defaults.difficulty(&Subtype::Open), Difficulty::receive());
+
+        let thresholds: WorkThresholds = toml::from_str(
+            r#"
+            send = "fffffff000000000"
+            extra_multiplier = 2.0
+            "#,
+        )
+        .unwrap();
+        let send = Difficulty::from_str("fffffff000000000").unwrap();
+        assert_eq!(
+            thresholds.difficulty(&Subtype::Change),
+            send.with_multiplier(2.0)
+        );
+        assert_eq!(
+            thresholds.difficulty(&Subtype::Receive),
+            Difficulty::receive().with_multiplier(2.0)
+        );
+
+        let beta = WorkThresholds::default().with_base(Network::Beta);
+        assert_eq!(
+            beta.difficulty(&Subtype::Change),
+            Network::Beta.send_difficulty()
+        );
+        assert_eq!(
+            thresholds.to_owned().with_base(Network::Beta).send,
+            Some(send.to_owned())
+        );
+
+        let easier = WorkThresholds {
+            extra_multiplier: Some(0.5),
+            ..WorkThresholds::default()
+        };
+        assert_eq!(easier.difficulty(&Subtype::Send), Difficulty::normal());
+    }
+}
@@ -0,0 +1,270 @@
+//! Keeping published blocks moving when the network is saturated.
+//!
+//! During congestion, representatives prioritise blocks with more work, so a block published
+//! with the minimum difficulty can sit unconfirmed for a long time. The [WorkWatcher] keeps an
+//! eye on a published block and regenerates its work at the network's active difficulty,
+//! republishing it until it's confirmed.
+use crate::blocks::{BlockHash, StateBlock};
+use crate::pow::{Difficulty, Upgrade, WorkManager};
+use async_trait::async_trait;
+use std::time::Duration;
+use tokio::time::{sleep, Instant};
+use tracing::{debug, info};
+
+/// Where the [WorkWatcher] gets the state of the network from and republishes to.
+#[async_trait]
+pub trait WorkPublisher {
+    async fn is_confirmed(&self, hash: &BlockHash) -> anyhow::Result<bool>;
+
+    /// The difficulty blocks like the watched one currently need to be prioritised.
+    async fn active_difficulty(&self) -> anyhow::Result<Difficulty>;
+
+    async fn republish(&self, block: &StateBlock) -> anyhow::Result<()>;
+}
+
+#[derive(Debug, Clone)]
+pub struct WorkWatcher {
+    /// How long to wait between checking on the block.
+    pub interval: Duration,
+
+    /// Never generate work harder than this many times the base difficulty, no matter how
+    /// saturated the network is.
+    pub max_multiplier: f64,
+
+    /// Give up watching after this long. The block stays published.
+    pub timeout: Option<Duration>,
+
+    /// Share one with anything else regenerating work for the same blocks.
+    pub manager: WorkManager,
+}
+
+impl Default for WorkWatcher {
+    fn default() -> Self {
+        Self {
+            interval: Duration::from_secs(5),
+            max_multiplier: 8.0,
+            timeout: None,
+            manager: WorkManager::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum WatchOutcome {
+    Confirmed { republished: usize },
+    TimedOut { republished: usize },
+}
+
+impl WorkWatcher {
+    /// Watch a published block until it's confirmed.
+    ///
+    /// `base` is the difficulty the block was originally published with. The block's work is
+    /// updated in place each time it's republished.
+    pub async fn watch<P>(
+        &self,
+        publisher: &P,
+        block: &mut StateBlock,
+        base: &Difficulty,
+    ) -> anyhow::Result<WatchOutcome>
+    where
+        P: WorkPublisher + Sync,
+    {
+        let started = Instant::now();
+        let ceiling = base.with_multiplier(self.max_multiplier);
+        let root = block.work_root();
+        let mut republished = 0;
+
+        loop {
+            sleep(self.interval).await;
+
+            if publisher.is_confirmed(&block.hash).await? {
+                debug!("{:?} confirmed after {:?}", block.hash, started.elapsed());
+                return Ok(WatchOutcome::Confirmed { republished });
+            }
+            if self.timeout.is_some_and(|t| started.elapsed() >= t) {
+                return Ok(WatchOutcome::TimedOut { republished });
+            }
+
+            let active = publisher.active_difficulty().await?;
+            let target = if active > ceiling {
+                ceiling.clone()
+            } else {
+                active
+            };
+            let current = match &block.work {
+                Some(work) => work.difficulty(&root)?,
+                None => Difficulty::new(0),
+            };
+            if current >= target {
+                continue;
+            }
+
+            info!(
+                "{:?} is unconfirmed, regenerating work at {:.2}x",
+                block.hash,
+                target.multiplier(base)
+            );
+            match self.manager.upgrade(block, &target).await? {
+                Upgrade::Upgraded(_) => {}
+                Upgrade::Unchanged | Upgrade::Superseded => continue,
+            }
+            publisher.republish(block).await?;
+            republished += 1;
+        }
+    }
+}
+
+/// Watches blocks that were published through an RPC server.
+#[cfg(feature = "rpc_client")]
+pub struct RPCWorkPublisher<'a> {
+    client: &'a crate::rpc::client::RPCClient,
+    subtype: crate::blocks::Subtype,
+}
+
+#[cfg(feature = "rpc_client")]
+impl<'a> RPCWorkPublisher<'a> {
+    pub fn new(client: &'a crate::rpc::client::RPCClient, subtype: crate::blocks::Subtype) -> Self {
+        Self { client, subtype }
+    }
+}
+
+#[cfg(feature = "rpc_client")]
+#[async_trait]
+impl WorkPublisher for RPCWorkPublisher<'_> {
+    async fn is_confirmed(&self, hash: &BlockHash) -> anyhow::Result<bool> {
+        use crate::rpc::calls::BlockInfoRequest;
+        use crate::rpc::client::RPCRequest;
+
+        Ok((&BlockInfoRequest::new(hash.to_owned()))
+            .call(self.client)
+            .await?
+            .confirmed)
+    }
+
+    async fn active_difficulty(&self) -> anyhow::Result<Difficulty> {
+        use crate::rpc::calls::ActiveDifficultyRequest;
+        use crate::rpc::client::RPCRequest;
+
+        use crate::blocks::Subtype;
+
+        let response = (&ActiveDifficultyRequest::new()).call(self.client).await?;
+        Ok(match self.subtype {
+            Subtype::Receive | Subtype::Open => response.network_receive_current,
+            _ => response.network_current,
+        })
+    }
+
+    async fn republish(&self, block: &StateBlock) -> anyhow::Result<()> {
+        use crate::rpc::calls::ProcessRequest;
+        use crate::rpc::client::RPCRequest;
+
+        (&ProcessRequest::new(self.subtype, block)?)
+            .call(self.client)
+            .await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blocks::{Link, Previous};
+    use crate::{Raw, Seed};
+    use std::sync::Mutex;
+
+    /// Confirms the block after `confirm_after` checks, with a network difficulty of `active`.
+    struct FakePublisher {
+        confirm_after: usize,
+        active: Difficulty,
+        checks: Mutex<usize>,
+        published: Mutex<Vec<StateBlock>>,
+    }
+
+    impl FakePublisher {
+        fn new(confirm_after: usize, active: Difficulty) -> Self {
+            Self {
+                confirm_after,
+                active,
+                checks: Mutex::new(0),
+                published: Mutex::new(vec![]),
+            }
+        }
+    }
+
+    #[async_trait]
+    impl WorkPublisher for FakePublisher {
+        async fn is_confirmed(&self, _hash: &BlockHash) -> anyhow::Result<bool> {
+            let mut checks = self.checks.lock().unwrap();
+            *checks += 1;
+            Ok(*checks > self.confirm_after)
+        }
+
+        async fn active_difficulty(&self) -> anyhow::Result<Difficulty> {
+            Ok(self.active.clone())
+        }
+
+        async fn republish(&self, block: &StateBlock) -> anyhow::Result<()> {
+            self.published.lock().unwrap().push(block.clone());
+            Ok(())
+        }
+    }
+
+    fn block() -> StateBlock {
+        let account = Seed::zero().derive(0).to_public().unwrap();
+        StateBlock::new(
+            account.clone(),
+            Previous::Open,
+            account,
+            Raw::from(1),
+            Link::Nothing,
+        )
+    }
+
+    fn watcher() -> WorkWatcher {
+        WorkWatcher {
+            interval: Duration::from_millis(1),
+            max_multiplier: 4.0,
+            timeout: Some(Duration::from_secs(10)),
+            manager: WorkManager::new(),
+        }
+    }
+
+    #[tokio::test]
+    async fn republishes_until_confirmed() {
+        // Very low difficulties so the test doesn't spend long generating work.
+        let base = Difficulty::new(0);
+        let active = base.with_multiplier(2.0);
+        let publisher = FakePublisher::new(1, active.clone());
+        let mut block = block();
+
+        let outcome = watcher()
+            .watch(&publisher, &mut block, &base)
+            .await
+            .unwrap();
+        assert_eq!(outcome, WatchOutcome::Confirmed { republished: 1 });
+
+        let published = publisher.published.lock().unwrap();
+        assert_eq!(published.len(), 1);
+        let root = block.work_root();
+        let work = published[0].work.as_ref().unwrap();
+        assert!(work.difficulty(&root).unwrap() >= active);
+    }
+
+    #[tokio::test]
+    async fn capped_at_max_multiplier() {
+        let base = Difficulty::new(0);
+        let publisher = FakePublisher::new(100, base.with_multiplier(1000.0));
+        let mut block = block();
+        let mut watcher = watcher();
+        watcher.timeout = Some(Duration::from_millis(50));
+
+        let outcome = watcher.watch(&publisher, &mut block, &base).await.unwrap();
+        assert!(matches!(outcome, WatchOutcome::TimedOut { .. }));
+
+        // Work at the ceiling is good enough, so it's only regenerated once.
+        assert_eq!(publisher.published.lock().unwrap().len(), 1);
+        let root = block.work_root();
+        let difficulty = block.work.unwrap().difficulty(&root).unwrap();
+        assert!(difficulty >= base.with_multiplier(4.0));
+    }
+}
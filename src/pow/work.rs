@@ -1,28 +1,18 @@
-use crate::blocks::BlockHash;
+use crate::blocks::Root;
 use crate::encoding::{blake2b, blake2b_callback};
+use crate::hexify;
 use crate::pow::difficulty::Difficulty;
-use crate::{hexify, Public};
 use bytes::Buf;
 use rand::RngCore;
 use std::convert::TryFrom;
-use std::fmt::Debug;
-
-#[derive(Debug)]
-pub enum Subject {
-    Hash(BlockHash),
-    Public(Public),
-}
-
-impl Subject {
-    pub fn as_bytes(&self) -> &[u8] {
-        match self {
-            Subject::Hash(h) => h.as_bytes(),
-            Subject::Public(p) => p.as_bytes(),
-        }
-    }
-}
+use tokio_util::sync::CancellationToken;
 
 /// The result of some proof of work (PoW). Can verify and inefficiently generate PoW using the CPU.
+///
+/// The bytes are kept in the same order as the 16 character hex used in JSON for blocks and RPC.
+/// The work hash, and legacy blocks on the wire, use the reverse: little-endian. State blocks on
+/// the wire are in the same order as the hex. Use [Work::from_hex] and [Work::from_le_bytes] to
+/// make it clear which one you have.
 #[derive(Clone, PartialEq, Eq)]
 pub struct Work([u8; Work::LEN]);
 
@@ -41,88 +31,143 @@ impl Work {
         s
     }
 
-    /// Block and generate forever until we find a solution.
-    pub fn generate(subject: &Subject, threshold: &Difficulty) -> anyhow::Result<Work> {
-        let mut work_and_subject = [0u8; 40];
-
-        // We can place the subject in the second part of the slice which will not change.
-        let subject_slice = &mut work_and_subject[Self::LEN..];
-
-        // This will panic if the source slice is too big. The assertion here is a safety measure
-        // in debug builds.
-        debug_assert_eq!(subject.as_bytes().len(), subject_slice.len());
-        subject.as_bytes().copy_to_slice(subject_slice);
-
-        let mut difficulty: Difficulty = Difficulty::new(0);
+    /// Little-endian bytes, as used in the work hash and legacy blocks.
+    pub fn from_le_bytes(bytes: &[u8]) -> crate::Result<Self> {
+        let mut work = Self::try_from(bytes)?;
+        work.0.reverse();
+        Ok(work)
+    }
 
-        // Fill the first 8 bytes with the random work.
-        let work_slice = &mut work_and_subject[0..Self::LEN];
-        rand::thread_rng().fill_bytes(work_slice);
+    pub fn to_le_bytes(&self) -> [u8; Self::LEN] {
+        let mut bytes = self.0;
+        bytes.reverse();
+        bytes
+    }
 
+    /// Block and generate forever until we find a solution.
+    pub fn generate(root: &Root, threshold: &Difficulty) -> anyhow::Result<Work> {
+        let mut search = Search::new(root);
         loop {
-            // Pick a random byte position and increment.
-            // I'm guessing this is slightly faster than using fill_bytes for a new set of numbers.
-            // TODO: Bench this guess.
-            let idx = (rand::random::<u8>() % (Self::LEN as u8)) as usize;
-            let c = work_and_subject[idx];
-            work_and_subject[idx] = if c == 0xff { 0 } else { c + 1 };
-
-            blake2b_callback(Self::LEN, &work_and_subject, |b| {
-                difficulty = Difficulty::from_le_slice(b).unwrap();
-            });
-            // TODO: Check if this is > or >=
-            if &difficulty > threshold {
-                break;
+            if let Some(work) = search.attempt(threshold, u64::MAX)? {
+                return Ok(work);
             }
         }
+    }
 
-        let work_slice = &work_and_subject[0..Self::LEN];
-        let mut work_bytes = Vec::from(work_slice);
-        work_bytes.reverse();
-        let work = Work::try_from(work_bytes.as_slice()).unwrap();
-        return Ok(work);
+    /// Generate like [Work::generate], but yield to the runtime every few milliseconds so other
+    /// tasks on the same thread can run. `progress` is called with the total
+    /// number of attempts each time, and once more when a solution is found.
+    ///
+    /// Fails with [crate::Error::Cancelled] once `cancel` is cancelled.
+    pub async fn generate_async<F>(
+        root: &Root,
+        threshold: &Difficulty,
+        cancel: &CancellationToken,
+        progress: F,
+    ) -> anyhow::Result<Work>
+    where
+        F: Fn(u64),
+    {
+        let mut search = Search::new(root);
+        loop {
+            if cancel.is_cancelled() {
+                return Err(crate::Error::Cancelled.into());
+            }
+            let found = search.attempt(threshold, ATTEMPTS_PER_YIELD)?;
+            progress(search.attempts);
+            if let Some(work) = found {
+                return Ok(work);
+            }
+            // Newer compilers apply this version of tokio's `must_use` to the awaited `()`.
+            let () = tokio::task::yield_now().await;
+        }
     }
 
-    pub fn hash(work_and_subject: &[u8]) -> Box<[u8]> {
-        blake2b(Self::LEN, work_and_subject)
+    pub fn hash(work_and_root: &[u8]) -> Box<[u8]> {
+        blake2b(Self::LEN, work_and_root)
     }
 
-    pub fn verify(&self, subject: &Subject, threshold: &Difficulty) -> anyhow::Result<bool> {
-        let difficulty = self.difficulty(subject)?;
+    pub fn verify(&self, root: &Root, threshold: &Difficulty) -> anyhow::Result<bool> {
+        let difficulty = self.difficulty(root)?;
         Ok(&difficulty > threshold)
     }
 
-    pub fn difficulty(&self, subject: &Subject) -> anyhow::Result<Difficulty> {
-        let mut work_and_subject = Vec::with_capacity(40);
-
-        // For some reason this is reversed!
-        let mut reversed_work = self.0.to_vec();
-        reversed_work.reverse();
+    pub fn difficulty(&self, root: &Root) -> anyhow::Result<Difficulty> {
+        self.difficulty_of(root.as_bytes())
+    }
 
-        work_and_subject.extend_from_slice(&reversed_work);
-        work_and_subject.extend_from_slice(subject.as_bytes());
-        let hash = Self::hash(&work_and_subject);
+    fn difficulty_of(&self, root: &[u8]) -> anyhow::Result<Difficulty> {
+        let mut work_and_root = Vec::with_capacity(40);
+        work_and_root.extend_from_slice(&self.to_le_bytes());
+        work_and_root.extend_from_slice(root);
+        let hash = Self::hash(&work_and_root);
         Difficulty::from_le_slice(hash.as_ref())
     }
+}
 
-    pub fn difficulty_block_hash(&self, block_hash: &BlockHash) -> anyhow::Result<Difficulty> {
-        let mut work_and_block_hash = Vec::with_capacity(40);
+/// How many hashes [Work::generate_async] tries before yielding, which takes a few milliseconds.
+const ATTEMPTS_PER_YIELD: u64 = 50_000;
 
-        // For some reason this is reversed!
-        let mut reversed_work = self.0.to_vec();
-        reversed_work.reverse();
+/// The state of a search for work, which can be carried on in steps.
+pub(super) struct Search {
+    work_and_root: [u8; 40],
+    pub(super) attempts: u64,
+}
 
-        work_and_block_hash.extend_from_slice(&reversed_work);
-        work_and_block_hash.extend_from_slice(block_hash.as_bytes());
-        let hash = Self::hash(&work_and_block_hash);
-        Difficulty::from_le_slice(hash.as_ref())
+impl Search {
+    pub(super) fn new(root: &Root) -> Self {
+        let mut work_and_root = [0u8; 40];
+
+        // We can place the root in the second part of the slice which will not change.
+        let root_slice = &mut work_and_root[Work::LEN..];
+        root.as_bytes().copy_to_slice(root_slice);
+
+        // Fill the first 8 bytes with the random work.
+        let work_slice = &mut work_and_root[0..Work::LEN];
+        rand::thread_rng().fill_bytes(work_slice);
+
+        Self {
+            work_and_root,
+            attempts: 0,
+        }
+    }
+
+    /// Try up to `limit` more times, returning the work if it's found.
+    pub(super) fn attempt(
+        &mut self,
+        threshold: &Difficulty,
+        limit: u64,
+    ) -> anyhow::Result<Option<Work>> {
+        let mut difficulty: Difficulty = Difficulty::new(0);
+        for _ in 0..limit {
+            self.attempts += 1;
+
+            // Pick a random byte position and increment.
+            // I'm guessing this is slightly faster than using fill_bytes for a new set of numbers.
+            // TODO: Bench this guess.
+            let idx = (rand::random::<u8>() % (Work::LEN as u8)) as usize;
+            let c = self.work_and_root[idx];
+            self.work_and_root[idx] = if c == 0xff { 0 } else { c + 1 };
+
+            blake2b_callback(Work::LEN, &self.work_and_root, |b| {
+                difficulty = Difficulty::from_le_slice(b).unwrap();
+            });
+            // TODO: Check if this is > or >=
+            if &difficulty > threshold {
+                return Ok(Some(Work::from_le_bytes(
+                    &self.work_and_root[0..Work::LEN],
+                )?));
+            }
+        }
+        Ok(None)
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::Seed;
+    use crate::blocks::BlockHash;
+    use crate::{HexBytes, Seed};
     use std::str::FromStr;
 
     #[test]
@@ -161,13 +206,13 @@ mod tests {
         for fixture in fixtures {
             let (hash, work, expected_difficulty, is_enough_work) = &fixture;
             let hash = BlockHash::from_str(hash).unwrap();
-            let subject = Subject::Hash(hash);
+            let root = Root::from(hash);
             let work = Work::from_str(work).unwrap();
             let expected_difficulty = Difficulty::from_str(expected_difficulty).unwrap();
-            let difficulty = work.difficulty(&subject).unwrap();
+            let difficulty = work.difficulty(&root).unwrap();
             assert_eq!(difficulty, expected_difficulty, "{:?}", &fixture);
             assert_eq!(
-                work.verify(&subject, &threshold).unwrap(),
+                work.verify(&root, &threshold).unwrap(),
                 *is_enough_work,
                 "{:?}",
                 &fixture
@@ -175,6 +220,22 @@ mod tests {
         }
     }
 
+    #[test]
+    fn hex_and_bytes() {
+        let work = Work::from_hex("2bf29ef00786a6bc").unwrap();
+        assert_eq!(work.to_hex_lower(), "2bf29ef00786a6bc");
+        assert_eq!(Work::from_hex("2BF29EF00786A6BC").unwrap(), work);
+        assert!(Work::from_hex("2bf29ef00786a6").is_err());
+
+        let le = work.to_le_bytes();
+        assert_eq!(le, [0xbc, 0xa6, 0x86, 0x07, 0xf0, 0x9e, 0xf2, 0x2b]);
+        assert_eq!(Work::from_le_bytes(&le).unwrap(), work);
+
+        let json = serde_json::to_string(&work).unwrap();
+        assert_eq!(json.to_lowercase(), r#""2bf29ef00786a6bc""#);
+        assert_eq!(serde_json::from_str::<Work>(&json).unwrap(), work);
+    }
+
     #[test]
     fn generate_work() {
         // Let's use a low difficulty in debug mode so doesn't take forever.
@@ -188,9 +249,47 @@ mod tests {
 
         let public = Seed::zero().derive(0).to_public().unwrap();
         dbg!(&public);
-        let subject = Subject::Public(public);
-        let work = Work::generate(&subject, &threshold).unwrap();
+        let root = Root::from(public);
+        let work = Work::generate(&root, &threshold).unwrap();
         dbg!(&work);
-        assert!(work.verify(&subject, &threshold).unwrap());
+        assert!(work.verify(&root, &threshold).unwrap());
+    }
+
+    #[tokio::test]
+    async fn generate_async() {
+        let threshold = Difficulty::from_str("fff0000000000000").unwrap();
+        let root = Root::from(Seed::zero().derive(0).to_public().unwrap());
+        let reports = std::sync::Mutex::new(vec![]);
+        let work = Work::generate_async(&root, &threshold, &CancellationToken::new(), |attempts| {
+            reports.lock().unwrap().push(attempts)
+        })
+        .await
+        .unwrap();
+        assert!(work.verify(&root, &threshold).unwrap());
+
+        let reports = reports.into_inner().unwrap();
+        assert!(!reports.is_empty());
+        let (last, batches) = reports.split_last().unwrap();
+        for (i, attempts) in batches.iter().enumerate() {
+            assert_eq!(*attempts, (i as u64 + 1) * ATTEMPTS_PER_YIELD);
+        }
+        assert!(*last <= reports.len() as u64 * ATTEMPTS_PER_YIELD);
+    }
+
+    #[tokio::test]
+    async fn generate_async_cancelled() {
+        let impossible = Difficulty::new(u64::MAX);
+        let root = Root::from(Seed::zero().derive(0).to_public().unwrap());
+        let cancel = CancellationToken::new();
+        let result = Work::generate_async(&root, &impossible, &cancel, |attempts| {
+            if attempts >= ATTEMPTS_PER_YIELD * 2 {
+                cancel.cancel();
+            }
+        })
+        .await;
+        assert!(matches!(
+            result.unwrap_err().downcast_ref(),
+            Some(crate::Error::Cancelled)
+        ));
     }
 }
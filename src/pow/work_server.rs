@@ -0,0 +1,325 @@
+//! A client for the standalone work server protocol, as used by nano-work-server and the GPU work
+//! farms built on it.
+//!
+//! Requests are JSON POSTed to the server. `work_generate` only responds once work has been
+//! found, and `work_cancel` makes a pending `work_generate` for the same root respond straight
+//! away with an error. [WorkPeers] sends each request to every configured server at once, takes
+//! the first valid work and cancels the rest, like the reference node does with its `work_peers`.
+use crate::blocks::Root;
+use crate::pow::{Difficulty, Work};
+use crate::HexBytes;
+use anyhow::anyhow;
+use futures::stream::{FuturesUnordered, StreamExt};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tracing::{debug, warn};
+
+/// The `[work]` section of the config.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+pub struct WorkConfig {
+    /// Work servers to generate work with instead of the CPU, as `host:port` or URLs.
+    #[serde(default)]
+    pub work_peers: Vec<String>,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum WorkServerError {
+    #[error("Work was cancelled")]
+    Cancelled,
+
+    #[error("Work server error: {0}")]
+    Server(String),
+
+    #[error("Work server responded with {status}: {body}")]
+    Status {
+        status: reqwest::StatusCode,
+        body: String,
+    },
+
+    #[error("Unexpected response from work server: {0}")]
+    BadResponse(String),
+
+    #[error("Work server returned {0:?}, which isn't valid for the root and difficulty")]
+    InvalidWork(Work),
+
+    #[error(transparent)]
+    Http(#[from] reqwest::Error),
+}
+
+#[derive(Debug, Deserialize)]
+struct GenerateResponse {
+    work: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct WorkServerClient {
+    url: String,
+    client: reqwest::Client,
+}
+
+impl WorkServerClient {
+    /// `url` can also be a bare `host:port`, as it's written in the reference node's config.
+    pub fn new<S: Into<String>>(url: S) -> Self {
+        let url = url.into();
+        let url = if url.contains("://") {
+            url
+        } else {
+            format!("http://{}", url)
+        };
+        Self {
+            url,
+            client: reqwest::Client::new(),
+        }
+    }
+
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Wait for the server to find work for `root` that's at least `difficulty`.
+    ///
+    /// The work is checked before it's returned, since a misbehaving server would otherwise
+    /// get blocks rejected by the network.
+    pub async fn generate(
+        &self,
+        root: &Root,
+        difficulty: &Difficulty,
+    ) -> Result<Work, WorkServerError> {
+        let response: GenerateResponse = self
+            .call(json!({
+                "action": "work_generate",
+                "hash": root.to_hex_lower(),
+                "difficulty": format!("{:016x}", difficulty.as_u64()),
+            }))
+            .await?;
+        let work = Work::from_hex(&response.work)
+            .map_err(|err| WorkServerError::BadResponse(err.to_string()))?;
+        match work.verify(root, difficulty) {
+            Ok(true) => Ok(work),
+            _ => Err(WorkServerError::InvalidWork(work)),
+        }
+    }
+
+    /// Stop working on `root`. A `work_generate` waiting on it fails with
+    /// [WorkServerError::Cancelled].
+    pub async fn cancel(&self, root: &Root) -> Result<(), WorkServerError> {
+        let _: Value = self
+            .call(json!({
+                "action": "work_cancel",
+                "hash": root.to_hex_lower(),
+            }))
+            .await?;
+        Ok(())
+    }
+
+    async fn call<T: serde::de::DeserializeOwned>(
+        &self,
+        body: Value,
+    ) -> Result<T, WorkServerError> {
+        debug!("SEND {}: {}", self.url, body);
+        let response = self
+            .client
+            .post(&self.url)
+            .header("Content-Type", "application/json")
+            .body(body.to_string())
+            .send()
+            .await?;
+        let status = response.status();
+        let text = response.text().await?;
+        debug!("RECV {}: {}", self.url, text);
+
+        // Errors come back as `{"error": "..."}`, sometimes with a successful status.
+        let value: Value = match serde_json::from_str(&text) {
+            Ok(value) => value,
+            Err(_) if !status.is_success() => {
+                return Err(WorkServerError::Status { status, body: text })
+            }
+            Err(err) => return Err(WorkServerError::BadResponse(err.to_string())),
+        };
+        if let Some(error) = value.get("error") {
+            let error = error
+                .as_str()
+                .map(str::to_owned)
+                .unwrap_or_else(|| error.to_string());
+            return Err(if error.to_ascii_lowercase().contains("cancel") {
+                WorkServerError::Cancelled
+            } else {
+                WorkServerError::Server(error)
+            });
+        }
+        if !status.is_success() {
+            return Err(WorkServerError::Status { status, body: text });
+        }
+        serde_json::from_value(value).map_err(|err| WorkServerError::BadResponse(err.to_string()))
+    }
+}
+
+/// Generates work by racing all of the configured work servers.
+#[derive(Debug, Clone)]
+pub struct WorkPeers {
+    peers: Vec<WorkServerClient>,
+}
+
+impl WorkPeers {
+    pub fn new(config: &WorkConfig) -> Self {
+        Self {
+            peers: config
+                .work_peers
+                .iter()
+                .map(WorkServerClient::new)
+                .collect(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.peers.is_empty()
+    }
+
+    /// Ask every peer for work at once. Once one of them returns valid work, the others are
+    /// cancelled.
+    pub async fn generate(&self, root: &Root, difficulty: &Difficulty) -> anyhow::Result<Work> {
+        if self.peers.is_empty() {
+            return Err(anyhow!("There are no work peers configured"));
+        }
+
+        let mut pending: FuturesUnordered<_> = self
+            .peers
+            .iter()
+            .map(|peer| async move { (peer, peer.generate(root, difficulty).await) })
+            .collect();
+        let mut errors = vec![];
+        while let Some((peer, result)) = pending.next().await {
+            match result {
+                Ok(work) => {
+                    drop(pending);
+                    self.cancel_others(peer, root).await;
+                    return Ok(work);
+                }
+                Err(err) => {
+                    warn!("Work peer {} failed: {}", peer.url, err);
+                    errors.push(format!("{}: {}", peer.url, err));
+                }
+            }
+        }
+        Err(anyhow!(
+            "None of the work peers generated work: {}",
+            errors.join(", ")
+        ))
+    }
+
+    async fn cancel_others(&self, winner: &WorkServerClient, root: &Root) {
+        let cancels =
+            self.peers
+                .iter()
+                .filter(|peer| peer.url != winner.url)
+                .map(|peer| async move {
+                    if let Err(err) = peer.cancel(root).await {
+                        debug!("Could not cancel work on {}: {}", peer.url, err);
+                    }
+                });
+        futures::future::join_all(cancels).await;
+    }
+}
+
+#[cfg(all(test, feature = "rpc_server"))]
+mod tests {
+    use super::*;
+    use std::net::SocketAddr;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+    use tokio::sync::Notify;
+    use warp::Filter;
+
+    /// How a fake work server responds to work_generate.
+    #[derive(Clone)]
+    enum Behaviour {
+        /// Respond with this work.
+        Work(Work),
+
+        /// Respond with an error.
+        Error(&'static str),
+
+        /// Wait for a work_cancel.
+        Slow,
+    }
+
+    #[derive(Default)]
+    struct Cancel {
+        notify: Notify,
+        received: AtomicBool,
+    }
+
+    fn serve(behaviour: Behaviour, cancel: Arc<Cancel>) -> SocketAddr {
+        let route = warp::post()
+            .and(warp::body::json())
+            .and_then(move |body: Value| {
+                let behaviour = behaviour.clone();
+                let cancel = cancel.clone();
+                async move {
+                    let reply = match (body["action"].as_str(), behaviour) {
+                        (Some("work_cancel"), _) => {
+                            cancel.received.store(true, Ordering::SeqCst);
+                            cancel.notify.notify_one();
+                            json!({})
+                        }
+                        (_, Behaviour::Work(work)) => json!({ "work": work.to_hex_lower() }),
+                        (_, Behaviour::Error(error)) => json!({ "error": error }),
+                        (_, Behaviour::Slow) => {
+                            cancel.notify.notified().await;
+                            json!({ "error": "Cancelled" })
+                        }
+                    };
+                    Ok::<_, warp::Rejection>(warp::reply::json(&reply))
+                }
+            });
+        let (address, server) = warp::serve(route).bind_ephemeral(([127, 0, 0, 1], 0));
+        tokio::spawn(server);
+        address
+    }
+
+    fn root() -> Root {
+        Root::from(crate::blocks::BlockHash::zero())
+    }
+
+    #[tokio::test]
+    async fn first_valid_work_wins() {
+        let easy = Difficulty::new(0);
+        let slow_cancel = Arc::new(Cancel::default());
+        let slow = serve(Behaviour::Slow, slow_cancel.clone());
+        let fast = serve(Behaviour::Work(Work::zero()), Arc::default());
+
+        let peers = WorkPeers::new(&WorkConfig {
+            work_peers: vec![slow.to_string(), format!("http://{}", fast)],
+        });
+        let work = peers.generate(&root(), &easy).await.unwrap();
+        assert_eq!(work, Work::zero());
+        assert!(slow_cancel.received.load(Ordering::SeqCst));
+    }
+
+    #[tokio::test]
+    async fn errors() {
+        let client =
+            |behaviour| WorkServerClient::new(serve(behaviour, Arc::default()).to_string());
+        let hard = Difficulty::new(u64::MAX);
+
+        let result = client(Behaviour::Error("Cancelled"))
+            .generate(&root(), &hard)
+            .await;
+        assert!(matches!(result, Err(WorkServerError::Cancelled)));
+
+        let result = client(Behaviour::Error("Invalid hash"))
+            .generate(&root(), &hard)
+            .await;
+        assert!(matches!(result, Err(WorkServerError::Server(e)) if e == "Invalid hash"));
+
+        let result = client(Behaviour::Work(Work::zero()))
+            .generate(&root(), &hard)
+            .await;
+        assert!(matches!(result, Err(WorkServerError::InvalidWork(_))));
+
+        let peers = WorkPeers::new(&WorkConfig {
+            work_peers: vec![client(Behaviour::Error("Nope")).url().to_owned()],
+        });
+        assert!(peers.generate(&root(), &hard).await.is_err());
+    }
+}
@@ -0,0 +1,555 @@
+//! QR codes for showing addresses and [NanoUri](crate::NanoUri)s in a terminal.
+//!
+//! Only what a payment request needs is supported: byte mode, the low error correction level,
+//! and versions 1 to 10, which is up to 271 bytes. The mask with the lowest penalty is used,
+//! following ISO/IEC 18004 without the finder-like pattern rule.
+//!
+//! ```
+//! use feeless::qr::QrCode;
+//!
+//! # fn main() -> anyhow::Result<()> {
+//! let code = QrCode::encode(b"nano:nano_3i1aq1cchnmbn9x5rsbap8b15akfh7wj7pwskuzi7ahz8oq6cobd99d4r3b7")?;
+//! assert_eq!(code.version(), 4);
+//! println!("{}", code.to_terminal_string());
+//! # Ok(())
+//! # }
+//! ```
+use anyhow::anyhow;
+
+/// Codewords of each version at the low error correction level: error correction codewords per
+/// block, then the number of blocks and data codewords per block of each group.
+const VERSIONS: [(usize, Group, Group); 10] = [
+    (7, (1, 19), (0, 0)),
+    (10, (1, 34), (0, 0)),
+    (15, (1, 55), (0, 0)),
+    (20, (1, 80), (0, 0)),
+    (26, (1, 108), (0, 0)),
+    (18, (2, 68), (0, 0)),
+    (20, (2, 78), (0, 0)),
+    (24, (2, 97), (0, 0)),
+    (30, (2, 116), (0, 0)),
+    (18, (2, 68), (2, 69)),
+];
+
+type Group = (usize, usize);
+
+/// Centres of the alignment patterns for each version, across and down.
+const ALIGNMENT: [&[usize]; 10] = [
+    &[],
+    &[6, 18],
+    &[6, 22],
+    &[6, 26],
+    &[6, 30],
+    &[6, 34],
+    &[6, 22, 38],
+    &[6, 24, 42],
+    &[6, 26, 46],
+    &[6, 28, 50],
+];
+
+/// Modules of light space to leave around the code so it can be found.
+const QUIET_ZONE: usize = 4;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct QrCode {
+    version: usize,
+    size: usize,
+
+    /// Dark modules, by row.
+    modules: Vec<Vec<bool>>,
+}
+
+impl QrCode {
+    /// Encode `data` in the smallest version it fits.
+    pub fn encode(data: &[u8]) -> anyhow::Result<Self> {
+        let version = (1..=VERSIONS.len())
+            .find(|&v| data.len() <= capacity(v))
+            .ok_or_else(|| {
+                anyhow!(
+                    "{} bytes is too long for a QR code, the limit is {}",
+                    data.len(),
+                    capacity(VERSIONS.len())
+                )
+            })?;
+
+        let mut code = Self::blank(version);
+        let codewords = interleave(version, &data_codewords(version, data));
+        code.draw_codewords(&codewords);
+
+        let function = code.function_modules();
+        let (_, best) = (0..8)
+            .map(|mask| {
+                let mut masked = code.clone();
+                masked.apply_mask(mask, &function);
+                masked.draw_format(mask);
+                (masked.penalty(), masked)
+            })
+            .min_by_key(|(penalty, _)| *penalty)
+            .unwrap();
+        Ok(best)
+    }
+
+    pub fn version(&self) -> usize {
+        self.version
+    }
+
+    /// Modules across and down, without the quiet zone.
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    pub fn is_dark(&self, x: usize, y: usize) -> bool {
+        self.modules[y][x]
+    }
+
+    /// Two rows of modules per line, with light modules drawn in the foreground colour, which
+    /// scans on a terminal with a dark background.
+    pub fn to_terminal_string(&self) -> String {
+        let total = self.size + QUIET_ZONE * 2;
+        let light = |x: usize, y: usize| {
+            if x < QUIET_ZONE || y < QUIET_ZONE {
+                return true;
+            }
+            let (x, y) = (x - QUIET_ZONE, y - QUIET_ZONE);
+            x >= self.size || y >= self.size || !self.modules[y][x]
+        };
+
+        let mut s = String::new();
+        for y in (0..total).step_by(2) {
+            for x in 0..total {
+                let bottom = y + 1 < total && light(x, y + 1);
+                s.push(match (light(x, y), bottom) {
+                    (true, true) => '█',
+                    (true, false) => '▀',
+                    (false, true) => '▄',
+                    (false, false) => ' ',
+                });
+            }
+            s.push('\n');
+        }
+        s
+    }
+
+    /// A code with the function patterns drawn and the format areas left light.
+    fn blank(version: usize) -> Self {
+        let size = version * 4 + 17;
+        let mut code = Self {
+            version,
+            size,
+            modules: vec![vec![false; size]; size],
+        };
+        for (x, y, dark) in code.function_pattern() {
+            code.modules[y][x] = dark;
+        }
+        code
+    }
+
+    /// Every module that isn't data, with its colour. The format modules are light here.
+    fn function_pattern(&self) -> Vec<(usize, usize, bool)> {
+        let size = self.size;
+        let mut modules = vec![];
+
+        for i in 0..size {
+            modules.push((6, i, i.is_multiple_of(2)));
+            modules.push((i, 6, i.is_multiple_of(2)));
+        }
+
+        // Finder patterns with their separators.
+        for &(cx, cy) in &[(3, 3), (size - 4, 3), (3, size - 4)] {
+            for dy in -4i32..=4 {
+                for dx in -4i32..=4 {
+                    let (x, y) = (cx as i32 + dx, cy as i32 + dy);
+                    if x < 0 || y < 0 || x >= size as i32 || y >= size as i32 {
+                        continue;
+                    }
+                    let distance = dx.abs().max(dy.abs());
+                    modules.push((x as usize, y as usize, distance != 2 && distance != 4));
+                }
+            }
+        }
+
+        let centres = ALIGNMENT[self.version - 1];
+        let last = centres.len().saturating_sub(1);
+        for (i, &cx) in centres.iter().enumerate() {
+            for (j, &cy) in centres.iter().enumerate() {
+                // These would be on top of the finder patterns.
+                if (i == 0 && (j == 0 || j == last)) || (i == last && j == 0) {
+                    continue;
+                }
+                for dy in -2i32..=2 {
+                    for dx in -2i32..=2 {
+                        let x = (cx as i32 + dx) as usize;
+                        let y = (cy as i32 + dy) as usize;
+                        modules.push((x, y, dx.abs().max(dy.abs()) != 1));
+                    }
+                }
+            }
+        }
+
+        modules.extend(
+            format_positions(size)
+                .into_iter()
+                .map(|(x, y, _)| (x, y, false)),
+        );
+        modules.push((8, size - 8, true));
+
+        if self.version >= 7 {
+            let bits = version_bits(self.version);
+            for i in 0..18 {
+                let dark = (bits >> i) & 1 == 1;
+                let (a, b) = (size - 11 + i % 3, i / 3);
+                modules.push((a, b, dark));
+                modules.push((b, a, dark));
+            }
+        }
+
+        modules
+    }
+
+    fn function_modules(&self) -> Vec<Vec<bool>> {
+        let mut function = vec![vec![false; self.size]; self.size];
+        for (x, y, _) in self.function_pattern() {
+            function[y][x] = true;
+        }
+        function
+    }
+
+    /// Fill the data area in the zigzag order, two columns at a time from the bottom right.
+    fn draw_codewords(&mut self, codewords: &[u8]) {
+        let function = self.function_modules();
+        let bits = codewords.len() * 8;
+        let mut i = 0;
+        let mut right = self.size - 1;
+        loop {
+            // The vertical timing pattern is skipped over.
+            if right == 6 {
+                right = 5;
+            }
+            let upward = (right + 1) & 2 == 0;
+            for vertical in 0..self.size {
+                let y = if upward {
+                    self.size - 1 - vertical
+                } else {
+                    vertical
+                };
+                for x in &[right, right - 1] {
+                    if !function[y][*x] && i < bits {
+                        self.modules[y][*x] = (codewords[i / 8] >> (7 - i % 8)) & 1 == 1;
+                        i += 1;
+                    }
+                }
+            }
+            if right < 2 {
+                break;
+            }
+            right -= 2;
+        }
+    }
+
+    fn apply_mask(&mut self, mask: u8, function: &[Vec<bool>]) {
+        for (y, row) in self.modules.iter_mut().enumerate() {
+            for (x, dark) in row.iter_mut().enumerate() {
+                if !function[y][x] && masked(mask, x, y) {
+                    *dark = !*dark;
+                }
+            }
+        }
+    }
+
+    fn draw_format(&mut self, mask: u8) {
+        let bits = format_bits(mask);
+        for (x, y, bit) in format_positions(self.size) {
+            self.modules[y][x] = (bits >> bit) & 1 == 1;
+        }
+    }
+
+    fn penalty(&self) -> usize {
+        let size = self.size;
+        let mut penalty = 0;
+
+        // Runs of five or more modules of the same colour, across and down.
+        for line in 0..size {
+            for across in &[true, false] {
+                let mut run = 0;
+                let mut colour = None;
+                for i in 0..size {
+                    let dark = if *across {
+                        self.modules[line][i]
+                    } else {
+                        self.modules[i][line]
+                    };
+                    if Some(dark) == colour {
+                        run += 1;
+                    } else {
+                        if run >= 5 {
+                            penalty += run - 2;
+                        }
+                        colour = Some(dark);
+                        run = 1;
+                    }
+                }
+                if run >= 5 {
+                    penalty += run - 2;
+                }
+            }
+        }
+
+        // Blocks of 2x2 modules of the same colour.
+        for y in 0..size - 1 {
+            for x in 0..size - 1 {
+                let dark = self.modules[y][x];
+                if self.modules[y][x + 1] == dark
+                    && self.modules[y + 1][x] == dark
+                    && self.modules[y + 1][x + 1] == dark
+                {
+                    penalty += 3;
+                }
+            }
+        }
+
+        // How far the dark modules are from half, in steps of 5%.
+        let dark = self.modules.iter().flatten().filter(|d| **d).count();
+        let percent = dark * 100 / (size * size);
+        penalty + (percent.max(50) - percent.min(50)) / 5 * 10
+    }
+}
+
+/// The most bytes that fit in `version`.
+fn capacity(version: usize) -> usize {
+    let header_bits = 4 + count_bits(version);
+    (data_len(version) * 8 - header_bits) / 8
+}
+
+/// Bits for the length of the data in byte mode.
+fn count_bits(version: usize) -> usize {
+    if version < 10 {
+        8
+    } else {
+        16
+    }
+}
+
+fn data_len(version: usize) -> usize {
+    let (_, (blocks1, len1), (blocks2, len2)) = VERSIONS[version - 1];
+    blocks1 * len1 + blocks2 * len2
+}
+
+/// The data in byte mode, padded to the capacity of `version`.
+fn data_codewords(version: usize, data: &[u8]) -> Vec<u8> {
+    let mut bits: Vec<bool> = vec![];
+    let mut push = |value: usize, len: usize| {
+        for i in (0..len).rev() {
+            bits.push((value >> i) & 1 == 1);
+        }
+    };
+    push(0b0100, 4);
+    push(data.len(), count_bits(version));
+    for byte in data {
+        push(*byte as usize, 8);
+    }
+
+    let capacity = data_len(version) * 8;
+    let terminator = (capacity - bits.len()).min(4);
+    bits.extend(std::iter::repeat_n(false, terminator));
+    while !bits.len().is_multiple_of(8) {
+        bits.push(false);
+    }
+
+    let mut codewords: Vec<u8> = bits
+        .chunks(8)
+        .map(|byte| byte.iter().fold(0, |acc, bit| (acc << 1) | *bit as u8))
+        .collect();
+    for pad in [0xEC, 0x11].iter().cycle() {
+        if codewords.len() == data_len(version) {
+            break;
+        }
+        codewords.push(*pad);
+    }
+    codewords
+}
+
+/// Split the data into blocks, add error correction to each, and interleave them.
+fn interleave(version: usize, data: &[u8]) -> Vec<u8> {
+    let (ec_len, (blocks1, len1), (blocks2, len2)) = VERSIONS[version - 1];
+    let generator = generator(ec_len);
+
+    let mut blocks = vec![];
+    let mut rest = data;
+    for len in std::iter::repeat_n(len1, blocks1).chain(std::iter::repeat_n(len2, blocks2)) {
+        let (block, remaining) = rest.split_at(len);
+        blocks.push((block, remainder(block, &generator)));
+        rest = remaining;
+    }
+
+    let mut codewords = vec![];
+    for i in 0..len1.max(len2) {
+        codewords.extend(blocks.iter().filter_map(|(block, _)| block.get(i)));
+    }
+    for i in 0..ec_len {
+        codewords.extend(blocks.iter().map(|(_, ec)| ec[i]));
+    }
+    codewords
+}
+
+/// Multiply in GF(2^8) with the QR code polynomial.
+fn gf_mul(a: u8, b: u8) -> u8 {
+    let mut product: u16 = 0;
+    for i in (0..8).rev() {
+        product = (product << 1) ^ ((product >> 7) * 0x11D);
+        product ^= ((b as u16 >> i) & 1) * a as u16;
+    }
+    product as u8
+}
+
+/// The Reed-Solomon generator polynomial of `degree`, without its leading 1.
+fn generator(degree: usize) -> Vec<u8> {
+    let mut coefficients = vec![0u8; degree];
+    coefficients[degree - 1] = 1;
+    let mut root = 1u8;
+    for _ in 0..degree {
+        for j in 0..degree {
+            coefficients[j] = gf_mul(coefficients[j], root);
+            if j + 1 < degree {
+                coefficients[j] ^= coefficients[j + 1];
+            }
+        }
+        root = gf_mul(root, 2);
+    }
+    coefficients
+}
+
+/// The error correction codewords of `data`.
+fn remainder(data: &[u8], generator: &[u8]) -> Vec<u8> {
+    let mut remainder = vec![0u8; generator.len()];
+    for byte in data {
+        let factor = byte ^ remainder.remove(0);
+        remainder.push(0);
+        for (r, g) in remainder.iter_mut().zip(generator) {
+            *r ^= gf_mul(*g, factor);
+        }
+    }
+    remainder
+}
+
+fn masked(mask: u8, x: usize, y: usize) -> bool {
+    match mask {
+        0 => (x + y).is_multiple_of(2),
+        1 => y.is_multiple_of(2),
+        2 => x.is_multiple_of(3),
+        3 => (x + y).is_multiple_of(3),
+        4 => (x / 3 + y / 2).is_multiple_of(2),
+        5 => x * y % 2 + x * y % 3 == 0,
+        6 => (x * y % 2 + x * y % 3).is_multiple_of(2),
+        _ => ((x + y) % 2 + x * y % 3).is_multiple_of(2),
+    }
+}
+
+/// The low error correction level and `mask`, with their BCH code.
+fn format_bits(mask: u8) -> u16 {
+    let data = (0b01 << 3) | mask as u16;
+    let mut remainder = data;
+    for _ in 0..10 {
+        remainder = (remainder << 1) ^ ((remainder >> 9) * 0x537);
+    }
+    ((data << 10) | remainder) ^ 0x5412
+}
+
+/// Where each bit of the format goes, both copies.
+fn format_positions(size: usize) -> Vec<(usize, usize, usize)> {
+    let mut positions = vec![];
+    for i in 0..6 {
+        positions.push((8, i, i));
+    }
+    positions.extend([(8, 7, 6), (8, 8, 7), (7, 8, 8)].iter().copied());
+    for i in 9..15 {
+        positions.push((14 - i, 8, i));
+    }
+    for i in 0..8 {
+        positions.push((size - 1 - i, 8, i));
+    }
+    for i in 8..15 {
+        positions.push((8, size - 15 + i, i));
+    }
+    positions
+}
+
+/// The version with its BCH code, for versions 7 and up.
+fn version_bits(version: usize) -> u32 {
+    let mut remainder = version as u32;
+    for _ in 0..12 {
+        remainder = (remainder << 1) ^ ((remainder >> 11) * 0x1F25);
+    }
+    ((version as u32) << 12) | remainder
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bch_codes() {
+        // From the tables in the standard.
+        assert_eq!(format_bits(0), 0b111011111000100);
+        assert_eq!(format_bits(7), 0b110100101110110);
+        assert_eq!(version_bits(7), 0x07C94);
+        assert_eq!(version_bits(10), 0x0A4D3);
+    }
+
+    #[test]
+    fn error_correction() {
+        // Every codeword with its error correction is a multiple of the generator, so it's zero
+        // at each of its roots.
+        let data: Vec<u8> = (0..55).collect();
+        let ec = remainder(&data, &generator(15));
+        let codeword: Vec<u8> = data.iter().chain(&ec).copied().collect();
+        let mut root = 1u8;
+        for _ in 0..15 {
+            let value = codeword.iter().fold(0, |acc, c| gf_mul(acc, root) ^ c);
+            assert_eq!(value, 0);
+            root = gf_mul(root, 2);
+        }
+    }
+
+    #[test]
+    fn versions() {
+        assert_eq!(capacity(1), 17);
+        assert_eq!(capacity(10), 271);
+        assert_eq!(QrCode::encode(&[0; 17]).unwrap().version(), 1);
+        assert_eq!(QrCode::encode(&[0; 18]).unwrap().version(), 2);
+
+        let code = QrCode::encode(&[b'a'; 271]).unwrap();
+        assert_eq!((code.version(), code.size()), (10, 57));
+        assert!(QrCode::encode(&[0; 272]).is_err());
+    }
+
+    #[test]
+    fn patterns() {
+        let code = QrCode::encode(b"nano").unwrap();
+        let row = |y: usize| -> String {
+            (0..code.size())
+                .map(|x| if code.is_dark(x, y) { '#' } else { '.' })
+                .collect()
+        };
+        assert_eq!(code.size(), 21);
+        assert!(row(0).starts_with("#######."));
+        assert!(row(0).ends_with(".#######"));
+        assert_eq!(&row(6)[6..15], "#.#.#.#.#");
+        // The dark module above the bottom left finder.
+        assert!(code.is_dark(8, 13));
+
+        // The format bits read back the same from both copies.
+        let first: Vec<bool> = format_positions(21)[..15]
+            .iter()
+            .map(|(x, y, _)| code.is_dark(*x, *y))
+            .collect();
+        let second: Vec<bool> = format_positions(21)[15..]
+            .iter()
+            .map(|(x, y, _)| code.is_dark(*x, *y))
+            .collect();
+        assert_eq!(first, second);
+
+        let text = code.to_terminal_string();
+        assert_eq!(text.lines().count(), 15);
+        assert!(text.lines().all(|line| line.chars().count() == 29));
+    }
+}
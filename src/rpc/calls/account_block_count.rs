@@ -6,7 +6,9 @@ use clap::Clap;
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Serialize, Deserialize, Clap)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct AccountBlockCountRequest {
+    #[clap(parse(try_from_str = crate::cli::parse::address))]
     pub account: Address,
 }
 
@@ -31,8 +33,10 @@ impl AccountBlockCountRequest {
 
 #[serde_with::serde_as]
 #[derive(Debug, Serialize, Deserialize, Eq, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct AccountBlockCountResponse {
     #[serde(deserialize_with = "from_str", serialize_with = "as_str")]
+    #[cfg_attr(feature = "schema", schemars(with = "String"))]
     block_count: u64,
 }
 
@@ -5,7 +5,9 @@ use clap::Clap;
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Serialize, Deserialize, Clap)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct AccountGetRequest {
+    #[clap(parse(try_from_str = crate::cli::parse::public))]
     pub key: Public,
 }
 
@@ -29,6 +31,7 @@ impl AccountGetRequest {
 }
 
 #[derive(Debug, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct AccountGetResponse {
     account: Address,
 }
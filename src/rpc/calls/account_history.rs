@@ -5,11 +5,15 @@ use crate::{Address, Raw, Result, Signature, Work};
 use async_trait::async_trait;
 use chrono::Utc;
 use clap::Clap;
+use futures::stream::{BoxStream, StreamExt};
 use serde::{Deserialize, Serialize};
 use serde_with::TimestampSeconds;
+use std::collections::VecDeque;
 
 #[derive(Debug, Serialize, Deserialize, Clap, Clone)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct AccountHistoryRequest {
+    #[clap(parse(try_from_str = crate::cli::parse::address))]
     pub account: Address,
 
     #[clap(long)]
@@ -19,9 +23,10 @@ pub struct AccountHistoryRequest {
     #[clap(short, long, default_value = "-1")]
     pub count: i64,
 
-    /// Start displaying blocks from this hash. Useful for pagination.
+    /// Start displaying blocks from this hash. Useful for pagination, or with `--all` to dump
+    /// everything before a block.
     #[serde(skip_serializing_if = "Option::is_none")]
-    #[clap(short, long)]
+    #[clap(short, long, parse(try_from_str = crate::cli::parse::block_hash))]
     head: Option<BlockHash>,
 
     /// Skips a number of blocks starting from head.
@@ -35,8 +40,14 @@ pub struct AccountHistoryRequest {
 
     /// Results will be filtered to only show sends/receives connected to the provided account(s).
     #[serde(skip_serializing_if = "Option::is_none")]
-    #[clap(short, long)]
+    #[clap(short, long, parse(try_from_str = crate::cli::parse::address))]
     account_filter: Option<Vec<Address>>,
+
+    /// Keep requesting pages of `count` blocks until the end of the history, printing each
+    /// entry as a line of JSON as it arrives. `--select` and `--jsonpath` apply to each entry.
+    #[serde(skip)]
+    #[clap(long)]
+    pub all: bool,
 }
 
 #[async_trait]
@@ -62,21 +73,80 @@ impl AccountHistoryRequest {
             offset: None,
             reverse: false,
             account_filter: None,
+            all: false,
         }
     }
+
+    pub fn head(mut self, head: BlockHash) -> Self {
+        self.head = Some(head);
+        self
+    }
+
+    /// Each entry of the history as it's parsed, instead of waiting for the whole response, which
+    /// can be many megabytes for a busy account.
+    pub async fn stream(
+        &self,
+        client: &RPCClient,
+    ) -> Result<BoxStream<'static, Result<AccountHistoryEntry>>> {
+        client.stream(&self, "history").await
+    }
+}
+
+impl RPCClient {
+    /// Every entry of the history from the request's head, making a request for each page of
+    /// `count` entries and following the cursor the node returns until there are no more.
+    pub fn account_history_stream(
+        &self,
+        request: AccountHistoryRequest,
+    ) -> BoxStream<'_, Result<AccountHistoryEntry>> {
+        let state = (Some(request), VecDeque::new());
+        futures::stream::try_unfold(state, move |(mut next, mut ready)| async move {
+            loop {
+                if let Some(entry) = ready.pop_front() {
+                    return Ok(Some((entry, (next, ready))));
+                }
+                let mut request = match next.take() {
+                    Some(request) => request,
+                    None => return Ok(None),
+                };
+                let response = (&request).call(self).await?;
+                let cursor = if request.reverse {
+                    response.next
+                } else {
+                    response.previous
+                };
+                // An empty page with a cursor would otherwise be requested forever.
+                if let (Some(head), false) = (cursor, response.history.is_empty()) {
+                    request.head = Some(head);
+                    // The offset is from the first head only.
+                    request.offset = None;
+                    next = Some(request);
+                }
+                ready.extend(response.history);
+            }
+        })
+        .boxed()
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, Eq, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct AccountHistoryResponse {
     pub account: Address,
     pub history: Vec<AccountHistoryEntry>,
 
+    /// The head of the next page, when there are more blocks than `count`.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub previous: Option<BlockHash>,
+
+    /// Like `previous`, for `reverse` requests.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next: Option<BlockHash>,
 }
 
 #[serde_with::serde_as]
 #[derive(Debug, Serialize, Deserialize, Eq, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct AccountHistoryEntry {
     #[serde(rename = "type")]
     pub block_type: BlockType,
@@ -90,9 +160,11 @@ pub struct AccountHistoryEntry {
     pub amount: Option<Raw>,
 
     #[serde_as(as = "TimestampSeconds<String>")]
+    #[cfg_attr(feature = "schema", schemars(with = "String"))]
     pub local_timestamp: chrono::DateTime<Utc>,
 
     #[serde(deserialize_with = "from_str", serialize_with = "as_str")]
+    #[cfg_attr(feature = "schema", schemars(with = "String"))]
     pub height: u64,
 
     pub hash: BlockHash,
@@ -182,7 +254,77 @@ mod tests {
                     )
                     .unwrap()
                 ),
+                next: None,
             }
         );
     }
+
+    #[cfg(feature = "rpc_server")]
+    #[tokio::test]
+    async fn pages() {
+        use futures::TryStreamExt;
+        use warp::Filter;
+
+        fn hash(n: u8) -> BlockHash {
+            BlockHash::from_str(&format!("{:02X}", n).repeat(32)).unwrap()
+        }
+
+        fn entry(height: u8) -> AccountHistoryEntry {
+            AccountHistoryEntry {
+                block_type: BlockType::Send,
+                account: None,
+                amount: None,
+                local_timestamp: DateTime::<Utc>::from_str("2021-02-26T08:15:55Z").unwrap(),
+                height: height as u64,
+                hash: hash(height),
+                subtype: None,
+                previous: None,
+                signature: None,
+                work: None,
+                representative: None,
+                balance: None,
+                link: None,
+            }
+        }
+
+        let account = crate::Seed::zero().derive(0).to_address().unwrap();
+        // Five blocks from height 5 down, two to a page.
+        let route = warp::post()
+            .and(warp::body::json())
+            .map(move |request: serde_json::Value| {
+                let head = match request["head"].as_str() {
+                    Some(head) => BlockHash::from_str(head).unwrap().as_bytes()[0],
+                    None => 5,
+                };
+                let count = request["count"].as_i64().unwrap();
+                let heights: Vec<u8> = (1..=head).rev().take(count as usize).collect();
+                let last = *heights.last().unwrap();
+                warp::reply::json(&AccountHistoryResponse {
+                    account: crate::Seed::zero().derive(0).to_address().unwrap(),
+                    history: heights.into_iter().map(entry).collect(),
+                    previous: if last > 1 { Some(hash(last - 1)) } else { None },
+                    next: None,
+                })
+            });
+        let (address, server) = warp::serve(route).bind_ephemeral(([127, 0, 0, 1], 0));
+        tokio::spawn(server);
+
+        let client = RPCClient::new(format!("http://{}", address));
+        let heights = |request| async {
+            client
+                .account_history_stream(request)
+                .map_ok(|e| e.height)
+                .try_collect::<Vec<_>>()
+                .await
+                .unwrap()
+        };
+        assert_eq!(
+            heights(AccountHistoryRequest::new(account.to_owned(), 2)).await,
+            vec![5, 4, 3, 2, 1]
+        );
+        assert_eq!(
+            heights(AccountHistoryRequest::new(account, 2).head(hash(3))).await,
+            vec![3, 2, 1]
+        );
+    }
 }
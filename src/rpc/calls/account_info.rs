@@ -1,3 +1,9 @@
+#[cfg(feature = "node")]
+use crate::node::{NodeCommand, NodeCommandSender};
+
+#[cfg(feature = "node")]
+use crate::rpc::NodeHandler;
+
 use crate::blocks::BlockHash;
 use crate::rpc::calls::{as_str, from_str};
 use crate::rpc::client::{RPCClient, RPCRequest};
@@ -8,8 +14,10 @@ use clap::Clap;
 use serde::{Deserialize, Serialize};
 use serde_with::TimestampSeconds;
 
-#[derive(Debug, Serialize, Deserialize, Clap)]
+#[derive(Debug, Clone, Serialize, Deserialize, Clap)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct AccountInfoRequest {
+    #[clap(parse(try_from_str = crate::cli::parse::address))]
     pub account: Address,
 
     /// Do not request the account representative.
@@ -42,6 +50,22 @@ impl RPCRequest for &AccountInfoRequest {
     }
 }
 
+#[cfg(feature = "node")]
+#[async_trait]
+impl NodeHandler for &AccountInfoRequest {
+    type Response = AccountInfoResponse;
+
+    async fn handle(&self, node_tx: NodeCommandSender) -> Result<AccountInfoResponse> {
+        use tokio::sync::oneshot;
+        let (tx, rx) = oneshot::channel();
+        node_tx
+            .send(NodeCommand::AccountInfo((*self).clone(), tx))
+            .await?;
+        rx.await?
+            .map_err(|err| crate::Error::RPCError(format!("{:#}", err).into()))
+    }
+}
+
 impl AccountInfoRequest {
     pub fn new(account: Address) -> Self {
         Self {
@@ -55,6 +79,7 @@ impl AccountInfoRequest {
 
 #[serde_with::serde_as]
 #[derive(Debug, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct AccountInfoResponse {
     pub frontier: BlockHash,
     pub open_block: BlockHash,
@@ -62,27 +87,31 @@ pub struct AccountInfoResponse {
     pub balance: Raw,
 
     #[serde_as(as = "TimestampSeconds<String>")]
+    #[cfg_attr(feature = "schema", schemars(with = "String"))]
     pub modified_timestamp: chrono::DateTime<Utc>,
 
     #[serde(deserialize_with = "from_str", serialize_with = "as_str")]
-    block_count: u64,
+    #[cfg_attr(feature = "schema", schemars(with = "String"))]
+    pub block_count: u64,
 
     #[serde(deserialize_with = "from_str", serialize_with = "as_str")]
-    confirmation_height: u64,
+    #[cfg_attr(feature = "schema", schemars(with = "String"))]
+    pub confirmation_height: u64,
 
-    confirmation_height_frontier: BlockHash,
+    pub confirmation_height_frontier: BlockHash,
 
     #[serde(deserialize_with = "from_str", serialize_with = "as_str")]
-    account_version: u64,
+    #[cfg_attr(feature = "schema", schemars(with = "String"))]
+    pub account_version: u64,
 
     #[serde(skip_serializing_if = "Option::is_none")]
-    representative: Option<Address>,
+    pub representative: Option<Address>,
 
     #[serde(skip_serializing_if = "Option::is_none")]
-    weight: Option<Raw>,
+    pub weight: Option<Raw>,
 
     #[serde(skip_serializing_if = "Option::is_none")]
-    pending: Option<Raw>,
+    pub pending: Option<Raw>,
 }
 
 #[cfg(test)]
@@ -5,7 +5,9 @@ use clap::Clap;
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Serialize, Deserialize, Clap)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct AccountKeyRequest {
+    #[clap(parse(try_from_str = crate::cli::parse::address))]
     pub account: Address,
 }
 
@@ -29,6 +31,7 @@ impl AccountKeyRequest {
 }
 
 #[derive(Debug, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct AccountKeyResponse {
     key: Public,
 }
@@ -5,7 +5,9 @@ use clap::Clap;
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Serialize, Deserialize, Clap)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct AccountWeightRequest {
+    #[clap(parse(try_from_str = crate::cli::parse::address))]
     pub account: Address,
 }
 
@@ -14,7 +16,7 @@ impl RPCRequest for &AccountWeightRequest {
     type Response = AccountWeightResponse;
 
     fn action(&self) -> &str {
-        "account_block_count"
+        "account_weight"
     }
 
     async fn call(&self, client: &RPCClient) -> Result<AccountWeightResponse> {
@@ -29,6 +31,7 @@ impl AccountWeightRequest {
 }
 
 #[derive(Debug, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct AccountWeightResponse {
     weight: Raw,
 }
@@ -6,7 +6,9 @@ use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
 #[derive(Debug, Serialize, Deserialize, Clap)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct AccountsBalancesRequest {
+    #[clap(parse(try_from_str = crate::cli::parse::address))]
     pub accounts: Vec<Address>,
 }
 
@@ -30,14 +32,16 @@ impl AccountsBalancesRequest {
 }
 
 #[derive(Debug, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct AccountsBalancesResponse {
-    balances: HashMap<Address, AccountsBalancesEntry>,
+    pub balances: HashMap<Address, AccountsBalancesEntry>,
 }
 
 #[derive(Debug, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct AccountsBalancesEntry {
-    balance: Raw,
-    pending: Raw,
+    pub balance: Raw,
+    pub pending: Raw,
 }
 
 #[cfg(test)]
@@ -7,7 +7,9 @@ use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
 #[derive(Debug, Serialize, Deserialize, Clap)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct AccountsFrontiersRequest {
+    #[clap(parse(try_from_str = crate::cli::parse::address))]
     pub accounts: Vec<Address>,
 }
 
@@ -31,8 +33,9 @@ impl AccountsFrontiersRequest {
 }
 
 #[derive(Debug, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct AccountsFrontiersResponse {
-    frontiers: HashMap<Address, BlockHash>,
+    pub frontiers: HashMap<Address, BlockHash>,
 }
 
 #[cfg(test)]
@@ -1,3 +1,9 @@
+#[cfg(feature = "node")]
+use crate::node::{NodeCommand, NodeCommandSender};
+
+#[cfg(feature = "node")]
+use crate::rpc::NodeHandler;
+
 use crate::blocks::BlockHash;
 use crate::rpc::client::{RPCClient, RPCRequest};
 use crate::{Address, Raw, Result};
@@ -7,28 +13,30 @@ use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
 #[derive(Debug, Serialize, Deserialize, Clap, Clone)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct AccountsPendingRequest {
-    accounts: Vec<Address>,
+    #[clap(parse(try_from_str = crate::cli::parse::address))]
+    pub accounts: Vec<Address>,
 
     /// Limit the number of results to `count`.
     #[clap(short, long, default_value = "1")]
-    count: u64,
+    pub count: u64,
 
     #[serde(skip_serializing_if = "Option::is_none")]
-    #[clap(short, long)]
-    threshold: Option<Raw>,
+    #[clap(short, long, parse(try_from_str = crate::cli::parse::amount))]
+    pub threshold: Option<Raw>,
 
     #[clap(long)]
-    source: bool,
+    pub source: bool,
 
     #[clap(long)]
-    include_active: bool,
+    pub include_active: bool,
 
     #[clap(long)]
-    sorting: bool,
+    pub sorting: bool,
 
     #[clap(long)]
-    include_only_confirmed: bool,
+    pub include_only_confirmed: bool,
 }
 
 #[async_trait]
@@ -44,6 +52,22 @@ impl RPCRequest for &AccountsPendingRequest {
     }
 }
 
+#[cfg(feature = "node")]
+#[async_trait]
+impl NodeHandler for &AccountsPendingRequest {
+    type Response = AccountsPendingResponse;
+
+    async fn handle(&self, node_tx: NodeCommandSender) -> Result<AccountsPendingResponse> {
+        use tokio::sync::oneshot;
+        let (tx, rx) = oneshot::channel();
+        node_tx
+            .send(NodeCommand::AccountsPending((*self).clone(), tx))
+            .await?;
+        rx.await?
+            .map_err(|err| crate::Error::RPCError(format!("{:#}", err).into()))
+    }
+}
+
 impl AccountsPendingRequest {
     pub fn new(accounts: Vec<Address>, count: u64) -> Self {
         Self {
@@ -56,9 +80,22 @@ impl AccountsPendingRequest {
             include_only_confirmed: false,
         }
     }
+
+    /// Only return pending blocks of at least `threshold`, along with their amounts.
+    pub fn threshold(mut self, threshold: Raw) -> Self {
+        self.threshold = Some(threshold);
+        self
+    }
+
+    /// Leave out blocks that aren't confirmed yet.
+    pub fn only_confirmed(mut self) -> Self {
+        self.include_only_confirmed = true;
+        self
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, Eq, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[serde(untagged)]
 pub enum AccountsPendingResponse {
     OnlyBlockHash {
@@ -73,9 +110,10 @@ pub enum AccountsPendingResponse {
 }
 
 #[derive(Debug, Serialize, Deserialize, Eq, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct BlockEntry {
-    amount: Raw,
-    source: Address,
+    pub amount: Raw,
+    pub source: Address,
 }
 
 #[cfg(test)]
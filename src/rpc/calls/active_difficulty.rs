@@ -7,6 +7,7 @@ use clap::Clap;
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Serialize, Deserialize, Clap)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct ActiveDifficultyRequest {}
 
 #[async_trait]
@@ -22,6 +23,12 @@ impl RPCRequest for &ActiveDifficultyRequest {
     }
 }
 
+impl Default for ActiveDifficultyRequest {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl ActiveDifficultyRequest {
     pub fn new() -> Self {
         Self {}
@@ -29,8 +36,10 @@ impl ActiveDifficultyRequest {
 }
 
 #[derive(Debug, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct ActiveDifficultyResponse {
     #[serde(deserialize_with = "from_str", serialize_with = "as_str")]
+    #[cfg_attr(feature = "schema", schemars(with = "String"))]
     pub multiplier: f64,
 
     pub network_current: Difficulty,
@@ -6,6 +6,7 @@ use clap::Clap;
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Serialize, Deserialize, Clap)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 // should it be pub struct AvailableSupplyRequest;?
 pub struct AvailableSupplyRequest {}
 
@@ -22,6 +23,12 @@ impl RPCRequest for &AvailableSupplyRequest {
     }
 }
 
+impl Default for AvailableSupplyRequest {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl AvailableSupplyRequest {
     pub fn new() -> Self {
         Self {}
@@ -29,6 +36,7 @@ impl AvailableSupplyRequest {
 }
 
 #[derive(Debug, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct AvailableSupplyResponse {
     available: Raw,
 }
@@ -6,7 +6,9 @@ use clap::Clap;
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Serialize, Deserialize, Clap)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct BlockAccountRequest {
+    #[clap(parse(try_from_str = crate::cli::parse::block_hash))]
     hash: BlockHash,
 }
 
@@ -30,6 +32,7 @@ impl BlockAccountRequest {
 }
 
 #[derive(Debug, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct BlockAccountResponse {
     account: Address,
 }
@@ -7,7 +7,9 @@ use clap::Clap;
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Serialize, Deserialize, Clap)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct BlockConfirmRequest {
+    #[clap(parse(try_from_str = crate::cli::parse::block_hash))]
     hash: BlockHash,
 }
 
@@ -31,8 +33,10 @@ impl BlockConfirmRequest {
 }
 
 #[derive(Debug, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct BlockConfirmResponse {
     #[serde(deserialize_with = "from_str", serialize_with = "as_str")]
+    #[cfg_attr(feature = "schema", schemars(with = "String"))]
     started: u8,
 }
 
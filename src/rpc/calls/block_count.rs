@@ -1,11 +1,11 @@
 use crate::rpc::calls::{as_str, as_str_option, from_str, from_str_option};
 use crate::rpc::client::{RPCClient, RPCRequest};
-use crate::Result;
 use async_trait::async_trait;
 use clap::Clap;
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Serialize, Deserialize, Clap)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct BlockCountRequest {
     #[clap(long)]
     include_cemented: bool,
@@ -19,11 +19,17 @@ impl RPCRequest for &BlockCountRequest {
         "block_count"
     }
 
-    async fn call(&self, client: &RPCClient) -> Result<BlockCountResponse> {
+    async fn call(&self, client: &RPCClient) -> crate::Result<BlockCountResponse> {
         client.rpc(self).await
     }
 }
 
+impl Default for BlockCountRequest {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl BlockCountRequest {
     pub fn new() -> Self {
         Self {
@@ -33,15 +39,19 @@ impl BlockCountRequest {
 }
 
 #[derive(Debug, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct BlockCountResponse {
     #[serde(deserialize_with = "from_str", serialize_with = "as_str")]
+    #[cfg_attr(feature = "schema", schemars(with = "String"))]
     count: u64,
 
     #[serde(deserialize_with = "from_str", serialize_with = "as_str")]
+    #[cfg_attr(feature = "schema", schemars(with = "String"))]
     unchecked: u64,
 
     #[serde(default)]
     #[serde(serialize_with = "as_str_option", deserialize_with = "from_str_option")]
+    #[cfg_attr(feature = "schema", schemars(with = "Option<String>"))]
     cemented: Option<u64>,
 }
 
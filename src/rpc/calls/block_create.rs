@@ -8,6 +8,7 @@ use clap::Clap;
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Serialize, Deserialize, Clap)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct BlockCreateRequest {
     // We only support json_block being true.
     #[clap(skip)]
@@ -19,7 +20,7 @@ pub struct BlockCreateRequest {
     block_type: BlockType,
 
     /// Final balance for account after block creation.
-    #[clap(short, long)]
+    #[clap(short, long, parse(try_from_str = crate::cli::parse::amount))]
     pub balance: Raw,
 
     /// The wallet ID that the account the block is being created for is in.
@@ -28,7 +29,7 @@ pub struct BlockCreateRequest {
     pub wallet: Option<WalletId>,
 
     /// The account the block is being created for.
-    #[clap(short, long)]
+    #[clap(short, long, parse(try_from_str = crate::cli::parse::address))]
     #[serde(skip_serializing_if = "Option::is_none")]
     pub account: Option<Address>,
 
@@ -54,7 +55,7 @@ pub struct BlockCreateRequest {
     pub link: Option<Link>,
 
     /// The account that block account will use as its representative.
-    #[clap(short, long)]
+    #[clap(short, long, parse(try_from_str = crate::cli::parse::address))]
     pub representative: Address,
 
     /// The block hash of the previous block on this account's block chain.
@@ -62,17 +63,18 @@ pub struct BlockCreateRequest {
     #[clap(
         short,
         long,
-        default_value = "0000000000000000000000000000000000000000000000000000000000000000"
+        default_value = "0000000000000000000000000000000000000000000000000000000000000000",
+        parse(try_from_str = crate::cli::parse::block_hash)
     )]
     pub previous: BlockHash,
 
     /// Specify own work.
-    #[clap(short, long, group = "work_or_difficulty")]
+    #[clap(short, long, group = "work_or_difficulty", parse(try_from_str = crate::cli::parse::work))]
     #[serde(skip_serializing_if = "Option::is_none")]
     pub work: Option<Work>,
 
     /// Uses difficulty value to generate work. Only used if optional work is not given.
-    #[clap(short = 'f', long, group = "work_or_difficulty")]
+    #[clap(short = 'f', long, group = "work_or_difficulty", parse(try_from_str = crate::cli::parse::difficulty))]
     #[serde(skip_serializing_if = "Option::is_none")]
     pub difficulty: Option<Difficulty>,
 }
@@ -117,6 +119,7 @@ impl BlockCreateRequest {
 
 #[serde_with::serde_as]
 #[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct BlockCreateResponse {
     hash: BlockHash,
     difficulty: Difficulty,
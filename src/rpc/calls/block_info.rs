@@ -1,5 +1,11 @@
-use crate::blocks::{BlockHash, BlockHolder, Subtype};
-use crate::rpc::calls::{as_str, from_str};
+#[cfg(feature = "node")]
+use crate::node::{NodeCommand, NodeCommandSender};
+
+#[cfg(feature = "node")]
+use crate::rpc::NodeHandler;
+
+use crate::blocks::{BlockHash, Subtype};
+use crate::rpc::calls::{as_str, from_str, JsonBlock};
 use crate::rpc::client::{RPCClient, RPCRequest};
 use crate::rpc::AlwaysTrue;
 use crate::{Address, Raw, Result};
@@ -10,7 +16,9 @@ use serde::{Deserialize, Serialize};
 use serde_with::TimestampSeconds;
 
 #[derive(Debug, Serialize, Deserialize, Clap)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct BlockInfoRequest {
+    #[clap(parse(try_from_str = crate::cli::parse::block_hash))]
     pub hash: BlockHash,
 
     // We only support json_block being true.
@@ -31,6 +39,22 @@ impl RPCRequest for &BlockInfoRequest {
     }
 }
 
+#[cfg(feature = "node")]
+#[async_trait]
+impl NodeHandler for &BlockInfoRequest {
+    type Response = BlockInfoResponse;
+
+    async fn handle(&self, node_tx: NodeCommandSender) -> Result<BlockInfoResponse> {
+        use tokio::sync::oneshot;
+        let (tx, rx) = oneshot::channel();
+        node_tx
+            .send(NodeCommand::BlockInfo(self.hash.to_owned(), tx))
+            .await?;
+        rx.await?
+            .map_err(|err| crate::Error::RPCError(format!("{:#}", err).into()))
+    }
+}
+
 impl BlockInfoRequest {
     pub fn new(hash: BlockHash) -> Self {
         Self {
@@ -42,36 +66,35 @@ impl BlockInfoRequest {
 
 #[serde_with::serde_as]
 #[derive(Debug, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct BlockInfoResponse {
     pub block_account: Address,
     pub amount: Raw,
     pub balance: Raw,
 
     #[serde(deserialize_with = "from_str", serialize_with = "as_str")]
+    #[cfg_attr(feature = "schema", schemars(with = "String"))]
     pub height: u64,
 
     #[serde_as(as = "TimestampSeconds<String>")]
+    #[cfg_attr(feature = "schema", schemars(with = "String"))]
     pub local_timestamp: chrono::DateTime<Utc>,
 
     #[serde(deserialize_with = "from_str", serialize_with = "as_str")]
+    #[cfg_attr(feature = "schema", schemars(with = "String"))]
     pub confirmed: bool,
 
     #[serde(skip_serializing_if = "Option::is_none")]
     pub subtype: Option<Subtype>,
 
-    pub contents: BlockHolder,
-    // TODO: json_block
+    pub contents: JsonBlock,
 }
 
-//TODO
-/*#[cfg(test)]
+#[cfg(test)]
 mod tests {
     use super::*;
+    use chrono::{DateTime, TimeZone};
     use std::str::FromStr;
-    use chrono::DateTime;
-    use crate::Public;
-    use crate::blocks::{Link, StateBlock};
-    use crate::Address;
 
     #[test]
     fn decode() {
@@ -100,23 +123,16 @@ mod tests {
         let r = serde_json::from_str::<BlockInfoResponse>(s).unwrap();
 
         assert_eq!(
-            r,
-            BlockInfoResponse {
-                block_account: Address::from_str("nano_1ipx847tk8o46pwxt5qjdbncjqcbwcc1rrmqnkztrfjy5k7z4imsrata9est").unwrap(),
-                amount: Rai::from(30000000000000000000000000000000000),
-                balance: Rai::from(5606157000000000000000000000000000000),
-                height: 58,
-                local_timestamp: DateTime::<Utc>::from_str("1970-01-01T00:00:00Z").unwrap(),
-                confirmed: true,
-                contents: BlockHolder::State(StateBlock::new(
-                    Address::from_str("nano_1ipx847tk8o46pwxt5qjdbncjqcbwcc1rrmqnkztrfjy5k7z4imsrata9est").unwrap().to_public(),
-                    BlockHash::from_str("CE898C131AAEE25E05362F247760F8A3ACF34A9796A5AE0D9204E86B0637965E").unwrap(),
-                    Address::from_str("nano_1stofnrxuz3cai7ze75o174bpm7scwj9jn3nxsn8ntzg784jf1gzn1jjdkou").unwrap().to_public(),
-                    Rai::from(5606157000000000000000000000000000000),
-                    Link::DestinationAccount(Address::from_str("nano_1qato4k7z3spc8gq1zyd8xeqfbzsoxwo36a45ozbrxcatut7up8ohyardu1z").unwrap().to_public()),
-                )),
-                subtype: Some(BlockType::Send),
-            }
-        )
+            r.block_account,
+            Address::from_str("nano_1ipx847tk8o46pwxt5qjdbncjqcbwcc1rrmqnkztrfjy5k7z4imsrata9est")
+                .unwrap()
+        );
+        assert_eq!(r.amount, Raw::from(30000000000000000000000000000000000));
+        assert_eq!(r.height, 58);
+        let epoch: DateTime<Utc> = Utc.timestamp_opt(0, 0).unwrap();
+        assert_eq!(r.local_timestamp, epoch);
+        assert!(r.confirmed);
+        assert_eq!(r.subtype, Some(Subtype::Send));
+        assert!(matches!(r.contents, JsonBlock::State { .. }));
     }
-}*/
+}
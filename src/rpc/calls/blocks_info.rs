@@ -0,0 +1,115 @@
+use crate::blocks::BlockHash;
+use crate::rpc::calls::BlockInfoResponse;
+use crate::rpc::client::{RPCClient, RPCRequest};
+use crate::rpc::AlwaysTrue;
+use crate::Result;
+use async_trait::async_trait;
+use clap::Clap;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+#[derive(Debug, Serialize, Deserialize, Clap)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct BlocksInfoRequest {
+    #[clap(required = true, parse(try_from_str = crate::cli::parse::block_hash))]
+    pub hashes: Vec<BlockHash>,
+
+    /// List the hashes the node doesn't have instead of failing.
+    #[clap(long)]
+    pub include_not_found: bool,
+
+    // We only support json_block being true.
+    #[clap(skip)]
+    json_block: AlwaysTrue,
+}
+
+#[async_trait]
+impl RPCRequest for &BlocksInfoRequest {
+    type Response = BlocksInfoResponse;
+
+    fn action(&self) -> &str {
+        "blocks_info"
+    }
+
+    async fn call(&self, client: &RPCClient) -> Result<BlocksInfoResponse> {
+        client.rpc(self).await
+    }
+}
+
+impl BlocksInfoRequest {
+    pub fn new(hashes: Vec<BlockHash>) -> Self {
+        Self {
+            hashes,
+            include_not_found: false,
+            json_block: Default::default(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct BlocksInfoResponse {
+    pub blocks: HashMap<BlockHash, BlockInfoResponse>,
+
+    /// Only with [BlocksInfoRequest::include_not_found].
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub blocks_not_found: Vec<BlockHash>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blocks::Subtype;
+    use crate::rpc::calls::JsonBlock;
+    use crate::{Address, Raw};
+    use std::str::FromStr;
+
+    #[test]
+    fn decode() {
+        let s = r#" {
+            "blocks": {
+                "87434F8041869A01C8F6F263B87972D7BA443A72E0A97D7A3FD0CCC2358FD6F9": {
+                    "block_account": "nano_1ipx847tk8o46pwxt5qjdbncjqcbwcc1rrmqnkztrfjy5k7z4imsrata9est",
+                    "amount": "1000000000000000000000000000000",
+                    "balance": "5606157000000000000000000000000000000",
+                    "height": "58",
+                    "local_timestamp": "1600000000",
+                    "confirmed": "true",
+                    "contents": {
+                        "type": "state",
+                        "account": "nano_1ipx847tk8o46pwxt5qjdbncjqcbwcc1rrmqnkztrfjy5k7z4imsrata9est",
+                        "previous": "CE898C131AAEE25E05362F247760F8A3ACF34A9796A5AE0D9204E86B0637965E",
+                        "representative": "nano_1stofnrxuz3cai7ze75o174bpm7scwj9jn3nxsn8ntzg784jf1gzn1jjdkou",
+                        "balance": "5606157000000000000000000000000000000",
+                        "link": "5D1AA8A45F8736519D707FCB375976A7F9AF795091021D7E9C7548D6F45DD8D5",
+                        "link_as_account": "nano_1qato4k7z3spc8gq1zyd8xeqfbzsoxwo36a45ozbrxcatut7up8ohyardu1z",
+                        "signature": "82D41BC16F313E4B2243D14DFFA2FB04679C540C2095FEE7EAE0F2F26880AD56DD48D87A7CC5DD760C5B2D76EE2C205506AA557BF00B60D8DEE312EC7343A501",
+                        "work": "8a142e07a10996d5"
+                    },
+                    "subtype": "send"
+                }
+            },
+            "blocks_not_found": [
+                "0000000000000000000000000000000000000000000000000000000000000001"
+            ]
+        }
+        "#;
+
+        let r = serde_json::from_str::<BlocksInfoResponse>(s).unwrap();
+        let hash =
+            BlockHash::from_str("87434F8041869A01C8F6F263B87972D7BA443A72E0A97D7A3FD0CCC2358FD6F9")
+                .unwrap();
+        let info = &r.blocks[&hash];
+        assert_eq!(
+            info.block_account,
+            Address::from_str("nano_1ipx847tk8o46pwxt5qjdbncjqcbwcc1rrmqnkztrfjy5k7z4imsrata9est")
+                .unwrap()
+        );
+        assert_eq!(info.amount, Raw::from(1000000000000000000000000000000));
+        assert_eq!(info.height, 58);
+        assert!(info.confirmed);
+        assert_eq!(info.subtype, Some(Subtype::Send));
+        assert!(matches!(info.contents, JsonBlock::State { .. }));
+        assert_eq!(r.blocks_not_found.len(), 1);
+    }
+}
@@ -0,0 +1,104 @@
+#[cfg(feature = "node")]
+use crate::node::{NodeCommand, NodeCommandSender};
+
+#[cfg(feature = "node")]
+use crate::rpc::NodeHandler;
+
+use crate::rpc::client::{RPCClient, RPCRequest};
+use crate::Result;
+use async_trait::async_trait;
+use clap::Clap;
+use serde::{Deserialize, Serialize};
+use std::net::SocketAddr;
+
+/// Progress of the parallel frontier bootstrap of a feeless node.
+#[derive(Debug, Serialize, Deserialize, Clap)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct BootstrapStatusRequest {}
+
+#[async_trait]
+impl RPCRequest for &BootstrapStatusRequest {
+    type Response = BootstrapStatusResponse;
+
+    fn action(&self) -> &str {
+        "bootstrap_status"
+    }
+
+    async fn call(&self, client: &RPCClient) -> Result<BootstrapStatusResponse> {
+        client.rpc(self).await
+    }
+}
+
+#[cfg(feature = "node")]
+#[async_trait]
+impl NodeHandler for &BootstrapStatusRequest {
+    type Response = BootstrapStatusResponse;
+
+    async fn handle(&self, node_tx: NodeCommandSender) -> Result<BootstrapStatusResponse> {
+        use tokio::sync::oneshot;
+        let (tx, rx) = oneshot::channel();
+        node_tx.send(NodeCommand::BootstrapStatus(tx)).await?;
+        Ok(rx.await?)
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct BootstrapStatusResponse {
+    pub running: bool,
+
+    /// The account space is split into ranges, which increase in number as ranges are stolen
+    /// by faster peers.
+    pub ranges_total: usize,
+    pub ranges_done: usize,
+
+    /// Number of accounts with a known frontier.
+    pub frontiers: usize,
+
+    /// How many times a range was split to give to a faster peer.
+    pub steals: usize,
+
+    pub peers: Vec<BootstrapPeer>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct BootstrapPeer {
+    pub address: SocketAddr,
+    pub frontiers: usize,
+    pub frontiers_per_second: f64,
+
+    /// Currently pulling a range.
+    pub active: bool,
+
+    /// Dropped because of an error.
+    pub failed: bool,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let status = BootstrapStatusResponse {
+            running: true,
+            ranges_total: 5,
+            ranges_done: 2,
+            frontiers: 1000,
+            steals: 1,
+            peers: vec![BootstrapPeer {
+                address: "127.0.0.1:7075".parse().unwrap(),
+                frontiers: 1000,
+                frontiers_per_second: 250.,
+                active: true,
+                failed: false,
+            }],
+        };
+        let json = serde_json::to_string(&status).unwrap();
+        assert_eq!(
+            serde_json::from_str::<BootstrapStatusResponse>(&json).unwrap(),
+            status
+        );
+    }
+}
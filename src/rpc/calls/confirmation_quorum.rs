@@ -0,0 +1,115 @@
+use crate::rpc::calls::{as_str, from_str};
+use crate::rpc::client::{RPCClient, RPCRequest};
+use crate::{Address, Raw, Result};
+use async_trait::async_trait;
+use clap::Clap;
+use serde::{Deserialize, Serialize};
+use std::net::SocketAddr;
+
+/// The voting weight needed to confirm a block, and the weight of the representatives online.
+#[derive(Debug, Serialize, Deserialize, Clap)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct ConfirmationQuorumRequest {
+    /// Include the representatives of the peers and their weight.
+    #[clap(short, long)]
+    pub peer_details: bool,
+}
+
+#[async_trait]
+impl RPCRequest for &ConfirmationQuorumRequest {
+    type Response = ConfirmationQuorumResponse;
+
+    fn action(&self) -> &str {
+        "confirmation_quorum"
+    }
+
+    async fn call(&self, client: &RPCClient) -> Result<ConfirmationQuorumResponse> {
+        client.rpc(self).await
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct ConfirmationQuorumResponse {
+    /// The weight of votes needed to confirm a block.
+    pub quorum_delta: Raw,
+
+    #[serde(deserialize_with = "from_str", serialize_with = "as_str")]
+    #[cfg_attr(feature = "schema", schemars(with = "String"))]
+    pub online_weight_quorum_percent: u8,
+
+    pub online_weight_minimum: Raw,
+    pub online_stake_total: Raw,
+
+    /// The weight of the representatives of the peers we're connected to.
+    pub peers_stake_total: Raw,
+
+    /// Missing from nodes older than V21.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trended_stake_total: Option<Raw>,
+
+    /// Only with [ConfirmationQuorumRequest::peer_details].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub peers: Option<Vec<QuorumPeer>>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct QuorumPeer {
+    pub account: Address,
+    pub ip: SocketAddr,
+    pub weight: Raw,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn decode() {
+        let s = r#" {
+            "quorum_delta": "41469707173777717318245825935516662250",
+            "online_weight_quorum_percent": "50",
+            "online_weight_minimum": "60000000000000000000000000000000000000",
+            "online_stake_total": "82939414347555434636491651871033324568",
+            "peers_stake_total": "69026910610720098597176027400951402360",
+            "trended_stake_total": "81939414347555434636491651871033324568",
+            "peers": [
+                {
+                    "account": "nano_1111111111111111111111111111111111111111111111111117353trpda",
+                    "ip": "[::ffff:127.0.0.1]:7075",
+                    "weight": "11999999999999999918751838129509869131"
+                }
+            ]
+        }
+        "#;
+
+        let r = serde_json::from_str::<ConfirmationQuorumResponse>(s).unwrap();
+        assert_eq!(r.online_weight_quorum_percent, 50);
+        assert_eq!(
+            r.quorum_delta,
+            Raw::from(41469707173777717318245825935516662250)
+        );
+        assert!(r.trended_stake_total.is_some());
+        let peers = r.peers.unwrap();
+        assert_eq!(
+            peers[0].account,
+            Address::from_str("nano_1111111111111111111111111111111111111111111111111117353trpda")
+                .unwrap()
+        );
+        assert_eq!(peers[0].ip.port(), 7075);
+
+        let s = r#" {
+            "quorum_delta": "1",
+            "online_weight_quorum_percent": "50",
+            "online_weight_minimum": "2",
+            "online_stake_total": "3",
+            "peers_stake_total": "4"
+        }
+        "#;
+        let r = serde_json::from_str::<ConfirmationQuorumResponse>(s).unwrap();
+        assert_eq!(r.trended_stake_total, None);
+        assert_eq!(r.peers, None);
+    }
+}
@@ -0,0 +1,148 @@
+#[cfg(feature = "node")]
+use crate::node::{NodeCommand, NodeCommandSender};
+
+#[cfg(feature = "node")]
+use crate::rpc::NodeHandler;
+
+use crate::rpc::client::{RPCClient, RPCRequest};
+use crate::Result;
+use async_trait::async_trait;
+use clap::Clap;
+use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter};
+
+/// How much work went into the blocks a feeless node added recently, as multipliers of the
+/// epoch 2 threshold for each kind of block.
+#[derive(Debug, Serialize, Deserialize, Clap)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct DifficultyStatsRequest {
+    /// Only count blocks added in the last this many seconds.
+    #[clap(long, default_value = "3600")]
+    #[serde(default = "default_seconds")]
+    pub seconds: u64,
+}
+
+fn default_seconds() -> u64 {
+    3600
+}
+
+#[async_trait]
+impl RPCRequest for &DifficultyStatsRequest {
+    type Response = DifficultyStatsResponse;
+
+    fn action(&self) -> &str {
+        "difficulty_stats"
+    }
+
+    async fn call(&self, client: &RPCClient) -> Result<DifficultyStatsResponse> {
+        client.rpc(self).await
+    }
+}
+
+#[cfg(feature = "node")]
+#[async_trait]
+impl NodeHandler for &DifficultyStatsRequest {
+    type Response = DifficultyStatsResponse;
+
+    async fn handle(&self, node_tx: NodeCommandSender) -> Result<DifficultyStatsResponse> {
+        use tokio::sync::oneshot;
+        let (tx, rx) = oneshot::channel();
+        node_tx
+            .send(NodeCommand::DifficultyStats(self.seconds, tx))
+            .await?;
+        rx.await?
+            .map_err(|err| crate::Error::RPCError(format!("{:#}", err).into()))
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct DifficultyStatsResponse {
+    pub seconds: u64,
+
+    /// Send and change blocks.
+    pub send: MultiplierStats,
+
+    /// Receive and open blocks, which have a lower threshold.
+    pub receive: MultiplierStats,
+
+    /// Blocks stored without their work, which aren't counted.
+    pub without_work: usize,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct MultiplierStats {
+    pub blocks: usize,
+
+    /// `None` when there are no blocks.
+    pub percentiles: Option<MultiplierPercentiles>,
+
+    /// Blocks grouped by multiplier. Each bucket ends where the next one starts.
+    pub buckets: Vec<MultiplierBucket>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct MultiplierPercentiles {
+    pub min: f64,
+    pub p50: f64,
+    pub p90: f64,
+    pub max: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct MultiplierBucket {
+    pub min: f64,
+    pub blocks: usize,
+}
+
+impl Display for DifficultyStatsResponse {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Blocks added in the last {} seconds", self.seconds)?;
+        for (name, stats) in &[("Send and change", &self.send), ("Receive", &self.receive)] {
+            writeln!(f, "{}: {} blocks", name, stats.blocks)?;
+            if let Some(p) = &stats.percentiles {
+                writeln!(
+                    f,
+                    "  Multiplier: min {:.2}, median {:.2}, 90% {:.2}, max {:.2}",
+                    p.min, p.p50, p.p90, p.max
+                )?;
+            }
+            for bucket in &stats.buckets {
+                writeln!(f, "  >= {}x: {} blocks", bucket.min, bucket.blocks)?;
+            }
+        }
+        writeln!(f, "Without work: {} blocks", self.without_work)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let stats = DifficultyStatsResponse {
+            seconds: 60,
+            send: MultiplierStats {
+                blocks: 1,
+                percentiles: Some(MultiplierPercentiles {
+                    min: 1.5,
+                    p50: 1.5,
+                    p90: 1.5,
+                    max: 1.5,
+                }),
+                buckets: vec![MultiplierBucket { min: 1., blocks: 1 }],
+            },
+            receive: MultiplierStats::default(),
+            without_work: 0,
+        };
+        let json = serde_json::to_string(&stats).unwrap();
+        assert_eq!(
+            serde_json::from_str::<DifficultyStatsResponse>(&json).unwrap(),
+            stats
+        );
+    }
+}
@@ -0,0 +1,90 @@
+#[cfg(feature = "node")]
+use crate::node::{NodeCommand, NodeCommandSender};
+
+#[cfg(feature = "node")]
+use crate::rpc::NodeHandler;
+
+use crate::blocks::BlockHash;
+use crate::rpc::client::{RPCClient, RPCRequest};
+use crate::Result;
+use async_trait::async_trait;
+use clap::Clap;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Blocks published by peers that a feeless node dropped, and why.
+#[derive(Debug, Serialize, Deserialize, Clap)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct DroppedBlocksRequest {}
+
+#[async_trait]
+impl RPCRequest for &DroppedBlocksRequest {
+    type Response = DroppedBlocksResponse;
+
+    fn action(&self) -> &str {
+        "dropped_blocks"
+    }
+
+    async fn call(&self, client: &RPCClient) -> Result<DroppedBlocksResponse> {
+        client.rpc(self).await
+    }
+}
+
+#[cfg(feature = "node")]
+#[async_trait]
+impl NodeHandler for &DroppedBlocksRequest {
+    type Response = DroppedBlocksResponse;
+
+    async fn handle(&self, node_tx: NodeCommandSender) -> Result<DroppedBlocksResponse> {
+        use tokio::sync::oneshot;
+        let (tx, rx) = oneshot::channel();
+        node_tx.send(NodeCommand::DroppedBlocks(tx)).await?;
+        Ok(rx.await?)
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct DroppedBlocksResponse {
+    /// Every block dropped since the node started, by the kind of reason.
+    pub counts: BTreeMap<String, usize>,
+
+    /// The latest dropped blocks, newest first.
+    pub recent: Vec<DroppedBlock>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct DroppedBlock {
+    pub hash: BlockHash,
+
+    /// e.g. `receive_amount_mismatch`, the same as the key in `counts`.
+    pub kind: String,
+
+    /// The reason with its details.
+    pub reason: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let mut counts = BTreeMap::new();
+        counts.insert("zero_send".to_owned(), 2);
+        let dropped = DroppedBlocksResponse {
+            counts,
+            recent: vec![DroppedBlock {
+                hash: BlockHash::zero(),
+                kind: "zero_send".to_owned(),
+                reason: "Send of zero raw".to_owned(),
+            }],
+        };
+        let json = serde_json::to_string(&dropped).unwrap();
+        assert_eq!(
+            serde_json::from_str::<DroppedBlocksResponse>(&json).unwrap(),
+            dropped
+        );
+    }
+}
@@ -0,0 +1,114 @@
+use crate::blocks::BlockHash;
+use crate::rpc::client::{RPCClient, RPCRequest};
+use crate::{Address, Result};
+use async_trait::async_trait;
+use clap::Clap;
+use futures::stream::BoxStream;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// The frontier of each account in the ledger, in order of their public keys.
+#[derive(Debug, Serialize, Deserialize, Clap)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct FrontiersRequest {
+    /// Start from this account.
+    #[clap(parse(try_from_str = crate::cli::parse::address))]
+    pub account: Address,
+
+    /// Limit the number of frontiers to `count`.
+    #[clap(short, long, default_value = "1000")]
+    pub count: u64,
+}
+
+#[async_trait]
+impl RPCRequest for &FrontiersRequest {
+    type Response = FrontiersResponse;
+
+    fn action(&self) -> &str {
+        "frontiers"
+    }
+
+    async fn call(&self, client: &RPCClient) -> Result<FrontiersResponse> {
+        client.rpc(self).await
+    }
+}
+
+impl FrontiersRequest {
+    pub fn new(account: Address, count: u64) -> Self {
+        Self { account, count }
+    }
+
+    /// Each account and its frontier as they're parsed, instead of waiting for the whole
+    /// response, which can be many megabytes with a large `count`.
+    pub async fn stream(
+        &self,
+        client: &RPCClient,
+    ) -> Result<BoxStream<'static, Result<(Address, BlockHash)>>> {
+        client.stream(&self, "frontiers").await
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct FrontiersResponse {
+    pub frontiers: HashMap<Address, BlockHash>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn decode() {
+        let s = r#"{
+            "frontiers": {
+                "nano_1111111111111111111111111111111111111111111111111111hifc8npp": "023B94B7D27B311666C8636954FE17F1FD2EAA97A8BAC27DE5084FBBD5C6B02C"
+            }
+        }"#;
+        let r = serde_json::from_str::<FrontiersResponse>(s).unwrap();
+        let address =
+            Address::from_str("nano_1111111111111111111111111111111111111111111111111111hifc8npp")
+                .unwrap();
+        assert_eq!(
+            r.frontiers[&address],
+            BlockHash::from_str("023B94B7D27B311666C8636954FE17F1FD2EAA97A8BAC27DE5084FBBD5C6B02C")
+                .unwrap()
+        );
+    }
+
+    #[cfg(feature = "rpc_server")]
+    #[tokio::test]
+    async fn stream() {
+        use futures::TryStreamExt;
+        use warp::Filter;
+
+        let accounts: Vec<Address> = (0..500)
+            .map(|i| crate::Seed::zero().derive(i).to_address().unwrap())
+            .collect();
+        let body = serde_json::to_string(&FrontiersResponse {
+            frontiers: accounts
+                .iter()
+                .map(|a| (a.to_owned(), BlockHash::zero()))
+                .collect(),
+        })
+        .unwrap();
+        let route = warp::post().map(move || body.to_owned());
+        let (address, server) = warp::serve(route).bind_ephemeral(([127, 0, 0, 1], 0));
+        tokio::spawn(server);
+
+        let client = RPCClient::new(format!("http://{}", address));
+        let frontiers: Vec<(Address, BlockHash)> =
+            FrontiersRequest::new(accounts[0].to_owned(), 500)
+                .stream(&client)
+                .await
+                .unwrap()
+                .try_collect()
+                .await
+                .unwrap();
+        assert_eq!(frontiers.len(), 500);
+        assert!(frontiers
+            .iter()
+            .all(|(a, h)| accounts.contains(a) && h == &BlockHash::zero()));
+    }
+}
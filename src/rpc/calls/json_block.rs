@@ -0,0 +1,180 @@
+use crate::blocks::{Block, BlockHash, BlockType, Previous};
+use crate::units::raw::{deserialize_from_hex, serialize_to_hex};
+use crate::{Address, Public, Raw, Signature, Work};
+use serde::{Deserialize, Serialize};
+use std::convert::TryFrom;
+
+/// The contents of a block as the node's RPC shows it with `json_block`, e.g. in `block_info`.
+///
+/// This differs from [crate::blocks::BlockHolder], which is how blocks are stored: the hash
+/// isn't included, zero is written out instead of leaving out the previous block of an open
+/// state block, and the balance of a legacy send block is in hex.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum JsonBlock {
+    Send {
+        previous: BlockHash,
+        destination: Address,
+
+        #[serde(
+            serialize_with = "serialize_to_hex",
+            deserialize_with = "deserialize_from_hex"
+        )]
+        balance: Raw,
+
+        work: Work,
+        signature: Signature,
+    },
+    Receive {
+        previous: BlockHash,
+        source: BlockHash,
+        work: Work,
+        signature: Signature,
+    },
+    Open {
+        source: BlockHash,
+        representative: Address,
+        account: Address,
+        work: Work,
+        signature: Signature,
+    },
+    Change {
+        previous: BlockHash,
+        representative: Address,
+        work: Work,
+        signature: Signature,
+    },
+    State {
+        account: Address,
+
+        /// Zero for the first block of an account.
+        previous: BlockHash,
+
+        representative: Address,
+        balance: Raw,
+        link: BlockHash,
+
+        /// The link as an address, which is only meaningful for sends.
+        link_as_account: Address,
+
+        signature: Signature,
+        work: Work,
+    },
+}
+
+impl JsonBlock {
+    /// The block as `block_info` shows it. Fails for a block without a signature or work.
+    pub fn from_block(block: &Block) -> anyhow::Result<Self> {
+        let work = block.require_work()?.to_owned();
+        let signature = block.require_signed()?.to_owned();
+        let previous = match block.previous() {
+            Previous::Block(hash) => hash.to_owned(),
+            Previous::Open => BlockHash::zero(),
+        };
+        Ok(match block.block_type() {
+            BlockType::Send => JsonBlock::Send {
+                previous,
+                destination: block.destination()?.to_address(),
+                balance: block.balance().to_owned(),
+                work,
+                signature,
+            },
+            BlockType::Receive => JsonBlock::Receive {
+                previous,
+                source: block.source()?.to_owned(),
+                work,
+                signature,
+            },
+            BlockType::Open => JsonBlock::Open {
+                source: block.source()?.to_owned(),
+                representative: block.representative().to_address(),
+                account: block.account().to_address(),
+                work,
+                signature,
+            },
+            BlockType::Change => JsonBlock::Change {
+                previous,
+                representative: block.representative().to_address(),
+                work,
+                signature,
+            },
+            BlockType::State => {
+                let link = block.link().as_bytes();
+                JsonBlock::State {
+                    account: block.account().to_address(),
+                    previous,
+                    representative: block.representative().to_address(),
+                    balance: block.balance().to_owned(),
+                    link: BlockHash::try_from(link)?,
+                    link_as_account: Public::try_from(link)?.to_address(),
+                    signature,
+                    work,
+                }
+            }
+            block_type => return Err(anyhow::anyhow!("No JSON for {:?} blocks", block_type)),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn decode_state() {
+        let s = r#" {
+            "type": "state",
+            "account": "nano_1ipx847tk8o46pwxt5qjdbncjqcbwcc1rrmqnkztrfjy5k7z4imsrata9est",
+            "previous": "CE898C131AAEE25E05362F247760F8A3ACF34A9796A5AE0D9204E86B0637965E",
+            "representative": "nano_1stofnrxuz3cai7ze75o174bpm7scwj9jn3nxsn8ntzg784jf1gzn1jjdkou",
+            "balance": "5606157000000000000000000000000000000",
+            "link": "5D1AA8A45F8736519D707FCB375976A7F9AF795091021D7E9C7548D6F45DD8D5",
+            "link_as_account": "nano_1qato4k7z3spc8gq1zyd8xeqfbzsoxwo36a45ozbrxcatut7up8ohyardu1z",
+            "signature": "82D41BC16F313E4B2243D14DFFA2FB04679C540C2095FEE7EAE0F2F26880AD56DD48D87A7CC5DD760C5B2D76EE2C205506AA557BF00B60D8DEE312EC7343A501",
+            "work": "8a142e07a10996d5"
+        }
+        "#;
+
+        let block = serde_json::from_str::<JsonBlock>(s).unwrap();
+        match &block {
+            JsonBlock::State {
+                balance, previous, ..
+            } => {
+                assert_eq!(balance, &Raw::from(5606157000000000000000000000000000000));
+                assert_eq!(
+                    previous,
+                    &BlockHash::from_str(
+                        "CE898C131AAEE25E05362F247760F8A3ACF34A9796A5AE0D9204E86B0637965E"
+                    )
+                    .unwrap()
+                );
+            }
+            _ => panic!("Expected a state block: {:?}", block),
+        }
+
+        let json = serde_json::to_string(&block).unwrap();
+        assert_eq!(serde_json::from_str::<JsonBlock>(&json).unwrap(), block);
+    }
+
+    #[test]
+    fn decode_legacy_send() {
+        let s = r#" {
+            "type": "send",
+            "previous": "991CF190094C00F0B68E2E5F75F6BEE95A2E0BD93CEAA4A6734DB9F19B728948",
+            "destination": "nano_13ezf4od79h1tgj9aiu4djzcmmguendtjfuhwfukhuucboua8cpoihmh8byo",
+            "balance": "FD89D89D89D89D89D89D89D89D89D89D",
+            "work": "3c82cc724905ee95",
+            "signature": "5B11B17DB9C8FE0CC58CAC6A6EECEF9CB122DA8A81C6D3DB1B5EE3AB065AA8F8CB1D6765C8EB91B58530C5FF5987AD95E6D34BB57F44257E20795EE412E61600"
+        }
+        "#;
+
+        match serde_json::from_str::<JsonBlock>(s).unwrap() {
+            JsonBlock::Send { balance, .. } => {
+                assert_eq!(balance, Raw::from(0xFD89D89D89D89D89D89D89D89D89D89D))
+            }
+            block => panic!("Expected a send block: {:?}", block),
+        }
+    }
+}
@@ -0,0 +1,200 @@
+#[cfg(feature = "node")]
+use crate::node::{NodeCommand, NodeCommandSender};
+
+#[cfg(feature = "node")]
+use crate::rpc::NodeHandler;
+
+use crate::blocks::BlockType;
+use crate::rpc::client::{RPCClient, RPCRequest};
+use crate::{Raw, Result};
+use async_trait::async_trait;
+use clap::Clap;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
+
+/// Supply, balance and activity statistics of the ledger held by a feeless node.
+#[derive(Debug, Serialize, Deserialize, Clap)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct LedgerStatsRequest {}
+
+#[async_trait]
+impl RPCRequest for &LedgerStatsRequest {
+    type Response = LedgerStatsResponse;
+
+    fn action(&self) -> &str {
+        "ledger_stats"
+    }
+
+    async fn call(&self, client: &RPCClient) -> Result<LedgerStatsResponse> {
+        client.rpc(self).await
+    }
+}
+
+#[cfg(feature = "node")]
+#[async_trait]
+impl NodeHandler for &LedgerStatsRequest {
+    type Response = LedgerStatsResponse;
+
+    async fn handle(&self, node_tx: NodeCommandSender) -> Result<LedgerStatsResponse> {
+        use tokio::sync::oneshot;
+        let (tx, rx) = oneshot::channel();
+        node_tx.send(NodeCommand::LedgerStats(tx)).await?;
+        rx.await?
+            .map_err(|err| crate::Error::RPCError(format!("{:#}", err).into()))
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct LedgerStatsResponse {
+    pub accounts: usize,
+    pub blocks: usize,
+    pub block_types: BTreeMap<BlockType, usize>,
+    pub supply: SupplyStats,
+
+    /// Accounts grouped by balance, from the smallest balances up.
+    pub balances: Vec<BalanceBucket>,
+
+    pub dormant: DormantStats,
+
+    /// Accounts with blocks missing from their chain, e.g. when older blocks have been evicted
+    /// from memory. Their blocks are only counted down to the gap.
+    pub incomplete_chains: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct SupplyStats {
+    /// Everything there is, which was all in the genesis account at first.
+    pub genesis: Raw,
+
+    /// The sum of the frontier balances of every account.
+    pub balances: Raw,
+
+    /// The balance of the burn account, which nobody can spend.
+    pub burned: Raw,
+
+    /// Balances that can be spent, which are all of them apart from `burned`.
+    pub circulating: Raw,
+
+    /// Sent but not received yet, or held by accounts this node doesn't know about.
+    pub unaccounted: Raw,
+
+    /// False when the balances add up to more than the genesis supply, which means the ledger
+    /// has invalid blocks in it.
+    pub valid: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct BalanceBucket {
+    /// The smallest balance in this bucket. The bucket ends where the next one starts.
+    pub min: Raw,
+    pub accounts: usize,
+    pub total: Raw,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct DormantStats {
+    /// Each bucket counts every account that hasn't changed for at least that long, so they
+    /// overlap.
+    pub buckets: Vec<DormantBucket>,
+
+    /// Accounts without a last modified time.
+    pub unknown: usize,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct DormantBucket {
+    pub days: u64,
+    pub accounts: usize,
+}
+
+impl Display for LedgerStatsResponse {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Accounts: {}", self.accounts)?;
+        writeln!(f, "Blocks: {}", self.blocks)?;
+        for (block_type, count) in &self.block_types {
+            writeln!(f, "  {:?}: {}", block_type, count)?;
+        }
+        if self.incomplete_chains > 0 {
+            writeln!(f, "Incomplete chains: {}", self.incomplete_chains)?;
+        }
+
+        let supply = &self.supply;
+        writeln!(
+            f,
+            "Supply: {}",
+            if supply.valid { "valid" } else { "INVALID" }
+        )?;
+        writeln!(f, "  Genesis: {} Nano", supply.genesis.to_nano())?;
+        writeln!(f, "  Balances: {} Nano", supply.balances.to_nano())?;
+        writeln!(f, "  Burned: {} Nano", supply.burned.to_nano())?;
+        writeln!(f, "  Circulating: {} Nano", supply.circulating.to_nano())?;
+        writeln!(f, "  Unaccounted: {} Nano", supply.unaccounted.to_nano())?;
+
+        writeln!(f, "Balances:")?;
+        for bucket in &self.balances {
+            writeln!(
+                f,
+                "  >= {} Nano: {} accounts, {} Nano",
+                bucket.min.to_nano(),
+                bucket.accounts,
+                bucket.total.to_nano()
+            )?;
+        }
+
+        writeln!(f, "Dormant:")?;
+        for bucket in &self.dormant.buckets {
+            writeln!(f, "  {}+ days: {} accounts", bucket.days, bucket.accounts)?;
+        }
+        writeln!(f, "  Unknown: {} accounts", self.dormant.unknown)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let mut block_types = BTreeMap::new();
+        block_types.insert(BlockType::State, 3);
+        block_types.insert(BlockType::Open, 1);
+        let stats = LedgerStatsResponse {
+            accounts: 1,
+            blocks: 4,
+            block_types,
+            supply: SupplyStats {
+                genesis: Raw::max(),
+                balances: Raw::from(10),
+                burned: Raw::zero(),
+                circulating: Raw::from(10),
+                unaccounted: Raw::from(u128::MAX - 10),
+                valid: true,
+            },
+            balances: vec![BalanceBucket {
+                min: Raw::zero(),
+                accounts: 1,
+                total: Raw::from(10),
+            }],
+            dormant: DormantStats {
+                buckets: vec![DormantBucket {
+                    days: 30,
+                    accounts: 0,
+                }],
+                unknown: 0,
+            },
+            incomplete_chains: 0,
+        };
+        let json = serde_json::to_string(&stats).unwrap();
+        assert!(json.contains(r#""block_types":{"open":1,"state":3}"#));
+        assert_eq!(
+            serde_json::from_str::<LedgerStatsResponse>(&json).unwrap(),
+            stats
+        );
+    }
+}
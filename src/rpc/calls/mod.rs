@@ -16,8 +16,24 @@ mod block_confirm;
 mod block_count;
 mod block_create;
 mod block_info;
+mod blocks_info;
+mod bootstrap_status;
+mod confirmation_quorum;
+mod difficulty_stats;
+mod dropped_blocks;
+mod frontiers;
+mod json_block;
+mod ledger_stats;
+mod peer_table;
+mod peer_telemetry;
 mod peers;
+mod pending;
+mod persist;
 mod process;
+mod representatives;
+mod sync_status;
+mod version;
+mod work_generate;
 mod work_validate;
 
 #[cfg(feature = "node")]
@@ -39,7 +55,7 @@ pub use account_representative::{AccountRepresentativeRequest, AccountRepresenta
 pub use account_weight::{AccountWeightRequest, AccountWeightResponse};
 pub use accounts_balances::{AccountsBalancesRequest, AccountsBalancesResponse};
 pub use accounts_frontiers::{AccountsFrontiersRequest, AccountsFrontiersResponse};
-pub use accounts_pending::{AccountsPendingRequest, AccountsPendingResponse};
+pub use accounts_pending::{AccountsPendingRequest, AccountsPendingResponse, BlockEntry};
 pub use active_difficulty::{ActiveDifficultyRequest, ActiveDifficultyResponse};
 pub use available_supply::{AvailableSupplyRequest, AvailableSupplyResponse};
 pub use block_account::{BlockAccountRequest, BlockAccountResponse};
@@ -47,16 +63,38 @@ pub use block_confirm::{BlockConfirmRequest, BlockConfirmResponse};
 pub use block_count::{BlockCountRequest, BlockCountResponse};
 pub use block_create::{BlockCreateRequest, BlockCreateResponse};
 pub use block_info::{BlockInfoRequest, BlockInfoResponse};
+pub use blocks_info::{BlocksInfoRequest, BlocksInfoResponse};
+pub use bootstrap_status::{BootstrapPeer, BootstrapStatusRequest, BootstrapStatusResponse};
 use clap::Clap;
+pub use confirmation_quorum::{ConfirmationQuorumRequest, ConfirmationQuorumResponse, QuorumPeer};
+pub use difficulty_stats::{
+    DifficultyStatsRequest, DifficultyStatsResponse, MultiplierBucket, MultiplierPercentiles,
+    MultiplierStats,
+};
+pub use dropped_blocks::{DroppedBlock, DroppedBlocksRequest, DroppedBlocksResponse};
+pub use frontiers::{FrontiersRequest, FrontiersResponse};
+pub use json_block::JsonBlock;
+pub use ledger_stats::{
+    BalanceBucket, DormantBucket, DormantStats, LedgerStatsRequest, LedgerStatsResponse,
+    SupplyStats,
+};
+pub use peer_table::{KnownPeer, PeerTableRequest, PeerTableResponse};
+pub use peer_telemetry::{PeerTelemetry, PeerTelemetryRequest, PeerTelemetryResponse};
 pub use peers::{DetailedPeerInfo, Peers, PeersRequest, PeersResponse};
+pub use pending::{PendingRequest, PendingResponse};
+pub use persist::{PersistRequest, PersistResponse};
 pub use process::{ProcessRequest, ProcessResponse};
+pub use representatives::{RepresentativesRequest, RepresentativesResponse};
 use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
 use std::fmt::Display;
 use std::ops::Deref;
 use std::str::FromStr;
+pub use sync_status::{SyncStatusRequest, SyncStatusResponse};
+pub use version::{VersionRequest, VersionResponse};
+pub use work_generate::{WorkGenerateRequest, WorkGenerateResponse};
 pub use work_validate::{WorkValidateRequest, WorkValidateResponse};
 
-#[cfg(any(feature = "node"))]
+#[cfg(feature = "node")]
 #[async_trait]
 pub trait NodeHandler {
     type Response: Serialize;
@@ -65,6 +103,7 @@ pub trait NodeHandler {
 }
 
 #[derive(Debug, Clap, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[serde(tag = "action", rename_all = "snake_case")]
 pub enum RpcCommand {
     AccountBalance(AccountBalanceRequest),
@@ -84,9 +123,24 @@ pub enum RpcCommand {
     BlockCount(BlockCountRequest),
     BlockCreate(BlockCreateRequest),
     BlockInfo(BlockInfoRequest),
+    BlocksInfo(BlocksInfoRequest),
     BlockConfirm(BlockConfirmRequest),
+    BootstrapStatus(BootstrapStatusRequest),
+    ConfirmationQuorum(ConfirmationQuorumRequest),
+    DifficultyStats(DifficultyStatsRequest),
+    DroppedBlocks(DroppedBlocksRequest),
+    Frontiers(FrontiersRequest),
+    LedgerStats(LedgerStatsRequest),
+    PeerTable(PeerTableRequest),
+    PeerTelemetry(PeerTelemetryRequest),
     Peers(PeersRequest),
+    Pending(PendingRequest),
+    Persist(PersistRequest),
     Process(ProcessRequest),
+    Representatives(RepresentativesRequest),
+    SyncStatus(SyncStatusRequest),
+    Version(VersionRequest),
+    WorkGenerate(WorkGenerateRequest),
     WorkValidate(WorkValidateRequest),
 }
 
@@ -134,6 +188,7 @@ where
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub(crate) struct AlwaysTrue(bool);
 
 impl Default for AlwaysTrue {
@@ -0,0 +1,97 @@
+#[cfg(feature = "node")]
+use crate::node::{NodeCommand, NodeCommandSender};
+
+#[cfg(feature = "node")]
+use crate::rpc::NodeHandler;
+
+use crate::rpc::client::{RPCClient, RPCRequest};
+use crate::Result;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use clap::Clap;
+use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter};
+use std::net::SocketAddr;
+
+/// Every peer a feeless node knows about, from the initial peers and keepalives, and when each
+/// was last seen.
+#[derive(Debug, Serialize, Deserialize, Clap)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct PeerTableRequest {}
+
+#[async_trait]
+impl RPCRequest for &PeerTableRequest {
+    type Response = PeerTableResponse;
+
+    fn action(&self) -> &str {
+        "peer_table"
+    }
+
+    async fn call(&self, client: &RPCClient) -> Result<PeerTableResponse> {
+        client.rpc(self).await
+    }
+}
+
+#[cfg(feature = "node")]
+#[async_trait]
+impl NodeHandler for &PeerTableRequest {
+    type Response = PeerTableResponse;
+
+    async fn handle(&self, node_tx: NodeCommandSender) -> Result<PeerTableResponse> {
+        use tokio::sync::oneshot;
+        let (tx, rx) = oneshot::channel();
+        node_tx.send(NodeCommand::PeerTable(tx)).await?;
+        rx.await?
+            .map_err(|err| crate::Error::RPCError(format!("{:#}", err).into()))
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct PeerTableResponse {
+    /// Most recently seen first.
+    pub peers: Vec<KnownPeer>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct KnownPeer {
+    pub address: SocketAddr,
+
+    /// When the peer was added, sent us a keepalive, or was listed in a keepalive from another
+    /// peer.
+    pub last_seen: DateTime<Utc>,
+}
+
+impl Display for PeerTableResponse {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let now = Utc::now();
+        for peer in &self.peers {
+            writeln!(
+                f,
+                "{} seen {}s ago",
+                peer.address,
+                (now - peer.last_seen).num_seconds().max(0)
+            )?;
+        }
+        writeln!(f, "{} peers", self.peers.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decode() {
+        let s = r#"{
+            "peers": [
+                { "address": "[::ffff:172.17.0.1]:7075", "last_seen": "2021-06-01T00:00:00Z" }
+            ]
+        }"#;
+        let response: PeerTableResponse = serde_json::from_str(s).unwrap();
+        assert_eq!(response.peers.len(), 1);
+        assert_eq!(response.peers[0].address.port(), 7075);
+        assert!(response.to_string().ends_with("1 peers\n"));
+    }
+}
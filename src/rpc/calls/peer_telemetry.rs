@@ -0,0 +1,133 @@
+#[cfg(feature = "node")]
+use crate::node::{NodeCommand, NodeCommandSender};
+
+#[cfg(feature = "node")]
+use crate::rpc::NodeHandler;
+
+use crate::rpc::client::{RPCClient, RPCRequest};
+use crate::Result;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use clap::Clap;
+use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter};
+use std::net::SocketAddr;
+
+/// The latest telemetry from each node a feeless node is connected to.
+#[derive(Debug, Serialize, Deserialize, Clap)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct PeerTelemetryRequest {}
+
+#[async_trait]
+impl RPCRequest for &PeerTelemetryRequest {
+    type Response = PeerTelemetryResponse;
+
+    fn action(&self) -> &str {
+        "peer_telemetry"
+    }
+
+    async fn call(&self, client: &RPCClient) -> Result<PeerTelemetryResponse> {
+        client.rpc(self).await
+    }
+}
+
+#[cfg(feature = "node")]
+#[async_trait]
+impl NodeHandler for &PeerTelemetryRequest {
+    type Response = PeerTelemetryResponse;
+
+    async fn handle(&self, node_tx: NodeCommandSender) -> Result<PeerTelemetryResponse> {
+        use tokio::sync::oneshot;
+        let (tx, rx) = oneshot::channel();
+        node_tx.send(NodeCommand::PeerTelemetry(tx)).await?;
+        Ok(PeerTelemetryResponse { nodes: rx.await? })
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct PeerTelemetryResponse {
+    /// Most blocks first.
+    pub nodes: Vec<PeerTelemetry>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct PeerTelemetry {
+    /// The peer the telemetry came from.
+    pub address: SocketAddr,
+
+    /// The node ID from the handshake, which signed the telemetry, e.g. `node_1y7j5...`.
+    pub node_id: String,
+
+    pub block_count: u64,
+    pub cemented_count: u64,
+    pub unchecked_count: u64,
+    pub account_count: u64,
+    pub bandwidth_cap: u64,
+    pub peer_count: u32,
+    pub protocol_version: u8,
+
+    /// `major.minor.patch` of the node's software.
+    pub version: String,
+
+    /// Who made the node's software, where `0` is the reference node.
+    pub maker: u8,
+
+    /// Seconds since the node started.
+    pub uptime: u64,
+
+    /// When the node sent it by its own clock, in milliseconds since the Unix epoch.
+    pub timestamp: u64,
+
+    pub received: DateTime<Utc>,
+}
+
+impl Display for PeerTelemetryResponse {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for node in &self.nodes {
+            writeln!(
+                f,
+                "{} {} v{} blocks {} cemented {} peers {} up {}s",
+                node.address,
+                node.node_id,
+                node.version,
+                node.block_count,
+                node.cemented_count,
+                node.peer_count,
+                node.uptime
+            )?;
+        }
+        writeln!(f, "{} nodes", self.nodes.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decode() {
+        let s = r#"{
+            "nodes": [{
+                "address": "[::ffff:172.17.0.1]:7075",
+                "node_id": "node_1y7j5rdqhg99uyab1145gu3yur1ax35a3b6qr417yt8cd6n86uiw3d4whty3",
+                "block_count": 100,
+                "cemented_count": 90,
+                "unchecked_count": 0,
+                "account_count": 10,
+                "bandwidth_cap": 10485760,
+                "peer_count": 5,
+                "protocol_version": 18,
+                "version": "21.3.0",
+                "maker": 0,
+                "uptime": 60,
+                "timestamp": 1622505600000,
+                "received": "2021-06-01T00:00:00Z"
+            }]
+        }"#;
+        let response: PeerTelemetryResponse = serde_json::from_str(s).unwrap();
+        assert_eq!(response.nodes[0].block_count, 100);
+        assert!(response.to_string().ends_with("1 nodes\n"));
+    }
+}
@@ -15,6 +15,7 @@ use std::collections::HashMap;
 use std::net::SocketAddr;
 
 #[derive(Debug, Serialize, Deserialize, Clap)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct PeersRequest {
     /// Returns a list of peers IPv6:port with its node protocol network version and node ID.
     #[clap(short, long)]
@@ -43,20 +44,20 @@ impl NodeHandler for &PeersRequest {
     async fn handle(&self, node_tx: NodeCommandSender) -> Result<PeersResponse> {
         use tokio::sync::oneshot;
         let (tx, rx) = oneshot::channel();
-        node_tx.send(NodeCommand::PeerInfo(tx)).await.expect("TODO");
-        Ok(PeersResponse {
-            peers: rx.await.expect("TODO"),
-        })
+        node_tx.send(NodeCommand::PeerInfo(tx)).await?;
+        Ok(PeersResponse { peers: rx.await? })
     }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct PeersResponse {
     /// The type in peers depends on the value set in [PeersRequest::peer_details].
     peers: Peers,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[serde(untagged)]
 pub enum Peers {
     Simple(Vec<SocketAddr>),
@@ -64,8 +65,10 @@ pub enum Peers {
 }
 
 #[derive(Debug, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct DetailedPeerInfo {
     #[serde(deserialize_with = "from_str", serialize_with = "as_str")]
+    #[cfg_attr(feature = "schema", schemars(with = "String"))]
     protocol_version: Version,
 
     node_id: String, // TODO: NodeId type. It might be used in the node handshake!
@@ -75,6 +78,7 @@ pub struct DetailedPeerInfo {
 }
 
 #[derive(Debug, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[serde(rename_all = "snake_case")]
 pub enum NetType {
     Tcp,
@@ -103,7 +107,7 @@ mod tests {
             );
             assert_eq!(peers[0], socket_addr);
         } else {
-            assert!(false, "Did not parse a simple list");
+            panic!("Did not parse a simple list");
         };
     }
 
@@ -136,7 +140,7 @@ mod tests {
                 }
             );
         } else {
-            assert!(false, "Did not parse detailed peers");
+            panic!("Did not parse detailed peers");
         };
     }
 }
@@ -0,0 +1,167 @@
+#[cfg(feature = "node")]
+use crate::node::{NodeCommand, NodeCommandSender};
+
+#[cfg(feature = "node")]
+use crate::rpc::NodeHandler;
+
+use crate::blocks::BlockHash;
+use crate::rpc::calls::BlockEntry;
+use crate::rpc::client::{RPCClient, RPCRequest};
+use crate::{Address, Raw, Result};
+use async_trait::async_trait;
+use clap::Clap;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// The blocks waiting to be received by one account, like `accounts_pending` for many.
+#[derive(Debug, Serialize, Deserialize, Clap, Clone)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct PendingRequest {
+    #[clap(parse(try_from_str = crate::cli::parse::address))]
+    pub account: Address,
+
+    /// Limit the number of results to `count`.
+    #[clap(short, long, default_value = "1")]
+    pub count: u64,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[clap(short, long, parse(try_from_str = crate::cli::parse::amount))]
+    pub threshold: Option<Raw>,
+
+    #[clap(long)]
+    pub source: bool,
+
+    #[clap(long)]
+    pub include_active: bool,
+
+    #[clap(long)]
+    pub sorting: bool,
+
+    #[clap(long)]
+    pub include_only_confirmed: bool,
+}
+
+#[async_trait]
+impl RPCRequest for &PendingRequest {
+    type Response = PendingResponse;
+
+    fn action(&self) -> &str {
+        "pending"
+    }
+
+    async fn call(&self, client: &RPCClient) -> Result<Self::Response> {
+        client.rpc(self).await
+    }
+}
+
+#[cfg(feature = "node")]
+#[async_trait]
+impl NodeHandler for &PendingRequest {
+    type Response = PendingResponse;
+
+    async fn handle(&self, node_tx: NodeCommandSender) -> Result<PendingResponse> {
+        use tokio::sync::oneshot;
+        let (tx, rx) = oneshot::channel();
+        node_tx
+            .send(NodeCommand::Pending((*self).clone(), tx))
+            .await?;
+        rx.await?
+            .map_err(|err| crate::Error::RPCError(format!("{:#}", err).into()))
+    }
+}
+
+impl PendingRequest {
+    pub fn new(account: Address, count: u64) -> Self {
+        Self {
+            account,
+            count,
+            threshold: None,
+            source: false,
+            include_active: false,
+            sorting: false,
+            include_only_confirmed: false,
+        }
+    }
+
+    /// Only return pending blocks of at least `threshold`, along with their amounts.
+    pub fn threshold(mut self, threshold: Raw) -> Self {
+        self.threshold = Some(threshold);
+        self
+    }
+
+    /// Return the amount and source account of each block.
+    pub fn source(mut self) -> Self {
+        self.source = true;
+        self
+    }
+}
+
+/// The shape depends on [PendingRequest::threshold] and [PendingRequest::source], like
+/// [crate::rpc::calls::AccountsPendingResponse].
+#[derive(Debug, Serialize, Deserialize, Eq, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(untagged)]
+pub enum PendingResponse {
+    OnlyBlockHash {
+        blocks: Vec<BlockHash>,
+    },
+    Threshold {
+        blocks: HashMap<BlockHash, Raw>,
+    },
+    Source {
+        blocks: HashMap<BlockHash, BlockEntry>,
+    },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn decode() {
+        let hash = "000D1BAEC8EC208142C99059B393051BAC8380F9B5A2E6B2489A277D81789F3F";
+        let s = format!(r#"{{ "blocks": ["{}"] }}"#, hash);
+        assert_eq!(
+            serde_json::from_str::<PendingResponse>(&s).unwrap(),
+            PendingResponse::OnlyBlockHash {
+                blocks: vec![BlockHash::from_str(hash).unwrap()]
+            }
+        );
+
+        let s = format!(
+            r#"{{ "blocks": {{ "{}": "6000000000000000000000000000000" }} }}"#,
+            hash
+        );
+        let mut blocks = HashMap::new();
+        blocks.insert(
+            BlockHash::from_str(hash).unwrap(),
+            Raw::from(6000000000000000000000000000000),
+        );
+        assert_eq!(
+            serde_json::from_str::<PendingResponse>(&s).unwrap(),
+            PendingResponse::Threshold { blocks }
+        );
+
+        let source = "nano_3dcfozsmekr1tr9skf1oa5wbgmxt81qepfdnt7zicq5x3hk65fg4fqj58mbr";
+        let s = format!(
+            r#"{{ "blocks": {{ "{}": {{
+                "amount": "6000000000000000000000000000000",
+                "source": "{}"
+            }} }} }}"#,
+            hash, source
+        );
+        let mut blocks = HashMap::new();
+        blocks.insert(
+            BlockHash::from_str(hash).unwrap(),
+            BlockEntry {
+                amount: Raw::from(6000000000000000000000000000000),
+                source: Address::from_str(source).unwrap(),
+            },
+        );
+        assert_eq!(
+            serde_json::from_str::<PendingResponse>(&s).unwrap(),
+            PendingResponse::Source { blocks }
+        );
+    }
+}
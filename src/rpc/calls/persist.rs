@@ -0,0 +1,119 @@
+#[cfg(feature = "node")]
+use crate::node::{NodeCommand, NodeCommandSender};
+
+#[cfg(feature = "node")]
+use crate::rpc::NodeHandler;
+
+use crate::rpc::client::{RPCClient, RPCRequest};
+use crate::Result;
+use async_trait::async_trait;
+use clap::Clap;
+use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter};
+use std::path::PathBuf;
+
+/// Copy the ledger a feeless node holds in memory into a database on disk, so it's kept after
+/// the node stops. Blocks already in the database are skipped, so this can be repeated.
+#[derive(Debug, Serialize, Deserialize, Clap)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct PersistRequest {
+    /// Path to the database on the node's machine, relative to the `persist_dir` it's configured
+    /// with.
+    #[clap(long)]
+    pub to: PathBuf,
+}
+
+#[async_trait]
+impl RPCRequest for &PersistRequest {
+    type Response = PersistResponse;
+
+    fn action(&self) -> &str {
+        "persist"
+    }
+
+    async fn call(&self, client: &RPCClient) -> Result<PersistResponse> {
+        client.rpc(self).await
+    }
+}
+
+#[cfg(feature = "node")]
+#[async_trait]
+impl NodeHandler for &PersistRequest {
+    type Response = PersistResponse;
+
+    async fn handle(&self, node_tx: NodeCommandSender) -> Result<PersistResponse> {
+        use tokio::sync::oneshot;
+        let (tx, rx) = oneshot::channel();
+        node_tx
+            .send(NodeCommand::Persist(self.to.to_owned(), tx))
+            .await?;
+        rx.await?
+            .map_err(|err| crate::Error::RPCError(format!("{:#}", err).into()))
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct PersistResponse {
+    pub path: PathBuf,
+    pub accounts: usize,
+
+    /// Accounts that already had the same frontier in the database.
+    pub up_to_date: usize,
+
+    pub blocks_copied: usize,
+
+    /// Pending entries of the copied blocks.
+    pub pending_copied: usize,
+
+    /// Accounts where the database has a block the node doesn't. They're left alone.
+    pub diverged: usize,
+
+    /// Accounts with a block that failed to verify, or older blocks that have been evicted from
+    /// memory. They aren't copied.
+    pub invalid: usize,
+}
+
+impl Display for PersistResponse {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Persisted to {:?}", self.path)?;
+        writeln!(
+            f,
+            "Compared {} accounts, {} already up to date",
+            self.accounts, self.up_to_date
+        )?;
+        writeln!(
+            f,
+            "Copied {} blocks and {} pending entries",
+            self.blocks_copied, self.pending_copied
+        )?;
+        writeln!(
+            f,
+            "Skipped {} diverged and {} invalid accounts",
+            self.diverged, self.invalid
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decode() {
+        let s = r#"{
+            "path": "/var/lib/feeless/live.db",
+            "accounts": 3,
+            "up_to_date": 1,
+            "blocks_copied": 5,
+            "pending_copied": 2,
+            "diverged": 0,
+            "invalid": 1
+        }"#;
+        let response: PersistResponse = serde_json::from_str(s).unwrap();
+        assert_eq!(response.blocks_copied, 5);
+        assert!(response
+            .to_string()
+            .contains("Copied 5 blocks and 2 pending entries"));
+    }
+}
@@ -1,5 +1,11 @@
+#[cfg(feature = "node")]
+use crate::node::{NodeCommand, NodeCommandSender};
+
+#[cfg(feature = "node")]
+use crate::rpc::NodeHandler;
+
 use crate::blocks::{deserialize_to_unsure_link, BlockType, StateBlock};
-use crate::blocks::{BlockHash, Link, Subtype};
+use crate::blocks::{BlockHash, Link, Previous, Subtype};
 use crate::rpc::client::{RPCClient, RPCRequest};
 use crate::rpc::AlwaysTrue;
 use crate::{Address, Raw, Result, Signature, Work};
@@ -8,28 +14,29 @@ use clap::Clap;
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Clap)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct StateBlockRequest {
     #[clap(short = 't', long, default_value = "state")]
     #[serde(rename = "type")]
     pub block_type: BlockType,
 
-    #[clap(short, long)]
+    #[clap(short, long, parse(try_from_str = crate::cli::parse::address))]
     pub account: Address,
 
-    #[clap(short, long)]
+    #[clap(short, long, parse(try_from_str = crate::cli::parse::block_hash))]
     pub previous: BlockHash,
 
-    #[clap(short, long)]
+    #[clap(short, long, parse(try_from_str = crate::cli::parse::address))]
     pub representative: Address,
 
-    #[clap(short, long)]
+    #[clap(short, long, parse(try_from_str = crate::cli::parse::amount))]
     pub balance: Raw,
 
     #[serde(deserialize_with = "deserialize_to_unsure_link")]
     #[clap(short, long)]
     pub link: Link,
 
-    #[clap(short, long)]
+    #[clap(short, long, parse(try_from_str = crate::cli::parse::work))]
     #[serde(skip_serializing_if = "Option::is_none")]
     pub work: Option<Work>,
 
@@ -39,6 +46,7 @@ pub struct StateBlockRequest {
 }
 
 #[derive(Debug, Serialize, Deserialize, Clap)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct ProcessRequest {
     // We only support json_block being true.
     #[clap(skip)]
@@ -63,11 +71,116 @@ impl RPCRequest for &ProcessRequest {
     }
 }
 
+#[cfg(feature = "node")]
+#[async_trait]
+impl NodeHandler for &ProcessRequest {
+    type Response = ProcessResponse;
+
+    async fn handle(&self, node_tx: NodeCommandSender) -> Result<ProcessResponse> {
+        use tokio::sync::oneshot;
+        let (tx, rx) = oneshot::channel();
+        node_tx
+            .send(NodeCommand::Process(
+                Box::new(self.block.to_state_block()),
+                tx,
+            ))
+            .await?;
+        rx.await?
+            .map_err(|err| crate::Error::RPCError(format!("{:#}", err).into()))
+    }
+}
+
 impl ProcessRequest {
-    pub fn new(_subtype: Subtype, _block: StateBlock) -> Self {
-        todo!()
+    /// Blocks need to be signed and have work before they can be published, so this will fail
+    /// for unsigned block templates.
+    pub fn new(subtype: Subtype, block: &StateBlock) -> Result<Self> {
+        let signature = block.require_signed()?;
+        let work = block.require_work()?;
+        let previous = match &block.previous {
+            Previous::Block(hash) => hash.to_owned(),
+            Previous::Open => BlockHash::zero(),
+        };
+
+        Ok(Self {
+            json_block: Default::default(),
+            subtype,
+            block: StateBlockRequest {
+                block_type: BlockType::State,
+                account: block.account.to_address(),
+                previous,
+                representative: block.representative.to_address(),
+                balance: block.balance.to_owned(),
+                link: block.link.to_owned(),
+                work: Some(work.to_owned()),
+                signature: Some(signature.to_owned()),
+            },
+        })
+    }
+}
+
+impl StateBlockRequest {
+    /// The block. A link that came in as JSON is still to be worked out from the previous
+    /// block with [StateBlock::subtype] and [Link::for_subtype].
+    pub fn to_state_block(&self) -> StateBlock {
+        let previous = if self.previous == BlockHash::zero() {
+            Previous::Open
+        } else {
+            Previous::Block(self.previous.to_owned())
+        };
+        let mut block = StateBlock::new(
+            self.account.to_public(),
+            previous,
+            self.representative.to_public(),
+            self.balance.to_owned(),
+            self.link.to_owned(),
+        );
+        block.work = self.work.to_owned();
+        block.signature = self.signature.to_owned();
+        block
     }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
-pub struct ProcessResponse {}
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct ProcessResponse {
+    /// The hash of the block that was processed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hash: Option<BlockHash>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Error, Seed};
+    use std::str::FromStr;
+
+    #[test]
+    fn requires_signed_block_with_work() {
+        let private = Seed::random().derive(0);
+        let account = private.to_public().unwrap();
+        let mut block = StateBlock::new(
+            account.clone(),
+            Previous::Open,
+            account,
+            Raw::from(1),
+            Link::Nothing,
+        );
+
+        assert!(matches!(
+            ProcessRequest::new(Subtype::Change, &block),
+            Err(Error::MissingSignature)
+        ));
+
+        block.signature = Some(private.sign(block.hash.as_bytes()).unwrap());
+        assert!(matches!(
+            ProcessRequest::new(Subtype::Change, &block),
+            Err(Error::MissingWork)
+        ));
+
+        block.work = Some(Work::from_str("8073a2031b9a3a6a").unwrap());
+        let request = ProcessRequest::new(Subtype::Change, &block).unwrap();
+        assert_eq!(request.block.previous, BlockHash::zero());
+        assert_eq!(request.block.work, block.work);
+        assert_eq!(request.block.to_state_block(), block);
+    }
+}
@@ -0,0 +1,86 @@
+use crate::rpc::client::{RPCClient, RPCRequest};
+use crate::{Address, Raw, Result};
+use async_trait::async_trait;
+use clap::Clap;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Every representative and its voting weight.
+#[derive(Debug, Serialize, Deserialize, Clap)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct RepresentativesRequest {
+    /// Limit the number of results to `count`.
+    #[clap(short, long)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub count: Option<u64>,
+
+    /// Sort by weight, largest first. The order is lost in the response, since accounts are
+    /// keys of a map.
+    #[clap(long)]
+    pub sorting: bool,
+}
+
+#[async_trait]
+impl RPCRequest for &RepresentativesRequest {
+    type Response = RepresentativesResponse;
+
+    fn action(&self) -> &str {
+        "representatives"
+    }
+
+    async fn call(&self, client: &RPCClient) -> Result<RepresentativesResponse> {
+        client.rpc(self).await
+    }
+}
+
+impl Default for RepresentativesRequest {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RepresentativesRequest {
+    pub fn new() -> Self {
+        Self {
+            count: None,
+            sorting: false,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct RepresentativesResponse {
+    pub representatives: HashMap<Address, Raw>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn decode() {
+        let s = r#" {
+            "representatives": {
+                "nano_1111111111111111111111111111111111111111111111111117353trpda": "3822372327060170000000000000000000000",
+                "nano_1111111111111111111111111111111111111111111111111awsq94gtecn": "30999999999999999999999999000000"
+            }
+        }
+        "#;
+
+        let r = serde_json::from_str::<RepresentativesResponse>(s).unwrap();
+        let mut representatives = HashMap::new();
+        representatives.insert(
+            Address::from_str("nano_1111111111111111111111111111111111111111111111111117353trpda")
+                .unwrap(),
+            Raw::from(3822372327060170000000000000000000000),
+        );
+        representatives.insert(
+            Address::from_str("nano_1111111111111111111111111111111111111111111111111awsq94gtecn")
+                .unwrap(),
+            Raw::from(30999999999999999999999999000000),
+        );
+        assert_eq!(r, RepresentativesResponse { representatives });
+    }
+}
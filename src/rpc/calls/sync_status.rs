@@ -0,0 +1,131 @@
+#[cfg(feature = "node")]
+use crate::node::{NodeCommand, NodeCommandSender};
+
+#[cfg(feature = "node")]
+use crate::rpc::NodeHandler;
+
+use crate::rpc::client::{RPCClient, RPCRequest};
+use crate::Result;
+use async_trait::async_trait;
+use clap::Clap;
+use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter};
+
+/// How far the ledger of a feeless node is from the block counts peers report in telemetry.
+#[derive(Debug, Serialize, Deserialize, Clap)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct SyncStatusRequest {}
+
+#[async_trait]
+impl RPCRequest for &SyncStatusRequest {
+    type Response = SyncStatusResponse;
+
+    fn action(&self) -> &str {
+        "sync_status"
+    }
+
+    async fn call(&self, client: &RPCClient) -> Result<SyncStatusResponse> {
+        client.rpc(self).await
+    }
+}
+
+#[cfg(feature = "node")]
+#[async_trait]
+impl NodeHandler for &SyncStatusRequest {
+    type Response = SyncStatusResponse;
+
+    async fn handle(&self, node_tx: NodeCommandSender) -> Result<SyncStatusResponse> {
+        use tokio::sync::oneshot;
+        let (tx, rx) = oneshot::channel();
+        node_tx.send(NodeCommand::SyncStatus(tx)).await?;
+        rx.await?
+            .map_err(|err| crate::Error::RPCError(format!("{:#}", err).into()))
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct SyncStatusResponse {
+    /// Blocks in our ledger.
+    pub block_count: u64,
+    pub cemented_count: u64,
+
+    /// The median of the counts reported by peers. `None` until a peer has sent telemetry.
+    pub network_block_count: Option<u64>,
+    pub network_cemented_count: Option<u64>,
+
+    /// How many nodes the network counts are from.
+    pub telemetry_nodes: usize,
+
+    /// How much of the network's blocks are in our ledger, up to 100.
+    pub sync_percentage: Option<f64>,
+    pub cemented_percentage: Option<f64>,
+}
+
+impl Display for SyncStatusResponse {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        fn line(
+            f: &mut Formatter<'_>,
+            name: &str,
+            local: u64,
+            network: Option<u64>,
+            percentage: Option<f64>,
+        ) -> std::fmt::Result {
+            match (network, percentage) {
+                (Some(network), Some(percentage)) => writeln!(
+                    f,
+                    "{:<9} {} of {} ({:.2}%)",
+                    name, local, network, percentage
+                ),
+                _ => writeln!(f, "{:<9} {}", name, local),
+            }
+        }
+        line(
+            f,
+            "Blocks:",
+            self.block_count,
+            self.network_block_count,
+            self.sync_percentage,
+        )?;
+        line(
+            f,
+            "Cemented:",
+            self.cemented_count,
+            self.network_cemented_count,
+            self.cemented_percentage,
+        )?;
+        if self.telemetry_nodes == 0 {
+            writeln!(f, "No telemetry from peers yet")
+        } else {
+            writeln!(f, "From the telemetry of {} nodes", self.telemetry_nodes)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn display() {
+        let status = SyncStatusResponse {
+            block_count: 50,
+            cemented_count: 40,
+            network_block_count: Some(200),
+            network_cemented_count: Some(160),
+            telemetry_nodes: 3,
+            sync_percentage: Some(25.0),
+            cemented_percentage: Some(25.0),
+        };
+        assert_eq!(
+            status.to_string(),
+            "Blocks:   50 of 200 (25.00%)\n\
+             Cemented: 40 of 160 (25.00%)\n\
+             From the telemetry of 3 nodes\n"
+        );
+        assert_eq!(
+            SyncStatusResponse::default().to_string(),
+            "Blocks:   0\nCemented: 0\nNo telemetry from peers yet\n"
+        );
+    }
+}
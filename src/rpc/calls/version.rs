@@ -0,0 +1,82 @@
+use crate::rpc::calls::{as_str, from_str};
+use crate::rpc::client::{RPCClient, RPCRequest};
+use crate::Result;
+use async_trait::async_trait;
+use clap::Clap;
+use serde::{Deserialize, Serialize};
+
+/// The versions of the node, its RPC server and its database.
+#[derive(Debug, Serialize, Deserialize, Clap)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct VersionRequest {}
+
+#[async_trait]
+impl RPCRequest for &VersionRequest {
+    type Response = VersionResponse;
+
+    fn action(&self) -> &str {
+        "version"
+    }
+
+    async fn call(&self, client: &RPCClient) -> Result<VersionResponse> {
+        client.rpc(self).await
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct VersionResponse {
+    #[serde(deserialize_with = "from_str", serialize_with = "as_str")]
+    #[cfg_attr(feature = "schema", schemars(with = "String"))]
+    pub rpc_version: u32,
+
+    #[serde(deserialize_with = "from_str", serialize_with = "as_str")]
+    #[cfg_attr(feature = "schema", schemars(with = "String"))]
+    pub store_version: u32,
+
+    #[serde(deserialize_with = "from_str", serialize_with = "as_str")]
+    #[cfg_attr(feature = "schema", schemars(with = "String"))]
+    pub protocol_version: u8,
+
+    /// e.g. `Nano V21.3`.
+    pub node_vendor: String,
+
+    /// e.g. `LMDB 0.9.25`.
+    pub store_vendor: String,
+
+    /// `live`, `beta` or `test`.
+    pub network: String,
+
+    /// The hash of the genesis block.
+    pub network_identifier: String,
+
+    /// e.g. `Build Info <git hash> "<compiler> version " "<compiler version string>" "BOOST <boost version>" BUILT "<build date>"`.
+    pub build_info: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decode() {
+        let s = r#" {
+            "rpc_version": "1",
+            "store_version": "14",
+            "protocol_version": "17",
+            "node_vendor": "Nano V21.3",
+            "store_vendor": "LMDB 0.9.25",
+            "network": "live",
+            "network_identifier": "991CF190094C00F0B68E2E5F75F6BEE95A2E0BD93CEAA4A6734DB9F19B728948",
+            "build_info": "Build Info 6d4b2d8 \"GNU version \" \"9.3.0\" \"BOOST 107000\" BUILT \"Feb 20 2021\""
+        }
+        "#;
+
+        let r = serde_json::from_str::<VersionResponse>(s).unwrap();
+        assert_eq!(r.rpc_version, 1);
+        assert_eq!(r.store_version, 14);
+        assert_eq!(r.protocol_version, 17);
+        assert_eq!(r.node_vendor, "Nano V21.3");
+        assert_eq!(r.network, "live");
+    }
+}
@@ -0,0 +1,162 @@
+#[cfg(feature = "node")]
+use crate::node::NodeCommandSender;
+
+#[cfg(feature = "node")]
+use crate::rpc::NodeHandler;
+
+use crate::blocks::BlockHash;
+#[cfg(feature = "node")]
+use crate::blocks::Root;
+use crate::rpc::client::{RPCClient, RPCRequest};
+use crate::{Address, Difficulty, Multiplier, Work};
+use async_trait::async_trait;
+use clap::Clap;
+use serde::{Deserialize, Serialize};
+
+/// Generate work on the node, or on the work peers it's configured with.
+#[derive(Debug, Serialize, Deserialize, Clap)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct WorkGenerateRequest {
+    /// The root of the block, which is the previous block, or the public key of an open block.
+    #[clap(parse(try_from_str = crate::cli::parse::block_hash))]
+    pub hash: BlockHash,
+
+    /// Defaults to the node's current network difficulty.
+    #[clap(short, long, parse(try_from_str = crate::cli::parse::difficulty))]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub difficulty: Option<Difficulty>,
+
+    /// A multiplier of the base difficulty, instead of a difficulty.
+    #[clap(short, long, conflicts_with = "difficulty")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub multiplier: Option<Multiplier>,
+
+    /// The account the block is for, which lets the node pick the epoch 2 difficulty.
+    #[clap(short, long, parse(try_from_str = crate::cli::parse::address))]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub account: Option<Address>,
+}
+
+#[async_trait]
+impl RPCRequest for &WorkGenerateRequest {
+    type Response = WorkGenerateResponse;
+
+    fn action(&self) -> &str {
+        "work_generate"
+    }
+
+    async fn call(&self, client: &RPCClient) -> crate::Result<WorkGenerateResponse> {
+        client.rpc(self).await
+    }
+}
+
+/// Work is generated on the node's own CPU rather than sent to the node task, since it can take
+/// a while.
+#[cfg(feature = "node")]
+#[async_trait]
+impl NodeHandler for &WorkGenerateRequest {
+    type Response = WorkGenerateResponse;
+
+    async fn handle(&self, _node_tx: NodeCommandSender) -> crate::Result<WorkGenerateResponse> {
+        let normal = Difficulty::normal();
+        let threshold = match (&self.difficulty, self.multiplier) {
+            (Some(difficulty), _) => difficulty.to_owned(),
+            (None, Some(multiplier)) => multiplier.apply(&normal),
+            (None, None) => normal.to_owned(),
+        };
+        let hash = self.hash.to_owned();
+        let root = Root::from(&hash);
+        let work = tokio::task::spawn_blocking(move || {
+            let work = Work::generate(&root, &threshold)?;
+            let difficulty = work.difficulty(&root)?;
+            Ok::<_, anyhow::Error>((work, difficulty))
+        })
+        .await
+        .expect("Work generation panicked");
+        let (work, difficulty) =
+            work.map_err(|err| crate::Error::RPCError(format!("{:#}", err).into()))?;
+        Ok(WorkGenerateResponse {
+            work,
+            multiplier: Multiplier::between(&difficulty, &normal),
+            difficulty,
+            hash,
+        })
+    }
+}
+
+impl WorkGenerateRequest {
+    pub fn new(hash: BlockHash) -> Self {
+        Self {
+            hash,
+            difficulty: None,
+            multiplier: None,
+            account: None,
+        }
+    }
+
+    pub fn difficulty(mut self, difficulty: Difficulty) -> Self {
+        self.difficulty = Some(difficulty);
+        self
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct WorkGenerateResponse {
+    pub work: Work,
+    pub difficulty: Difficulty,
+
+    pub multiplier: Multiplier,
+
+    pub hash: BlockHash,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn encode() {
+        let hash =
+            BlockHash::from_str("718CC2121C3E641059BC1C2CFC45666C99E8AE922F7A807B7D07B62C995D79E2")
+                .unwrap();
+        let request = WorkGenerateRequest::new(hash.clone());
+        assert_eq!(
+            serde_json::to_string(&request).unwrap(),
+            r#"{"hash":"718CC2121C3E641059BC1C2CFC45666C99E8AE922F7A807B7D07B62C995D79E2"}"#
+        );
+        let request = WorkGenerateRequest {
+            multiplier: Some(Multiplier::new(1.5).unwrap()),
+            ..WorkGenerateRequest::new(hash)
+        };
+        assert!(serde_json::to_string(&request)
+            .unwrap()
+            .contains(r#""multiplier":"1.5""#));
+    }
+
+    #[test]
+    fn decode() {
+        let s = r#" {
+            "work": "2b3d689bbcb21dca",
+            "difficulty": "fffffff93c41ec94",
+            "multiplier": "1.182623871097636",
+            "hash": "718CC2121C3E641059BC1C2CFC45666C99E8AE922F7A807B7D07B62C995D79E2"
+        }
+        "#;
+
+        let r = serde_json::from_str::<WorkGenerateResponse>(s).unwrap();
+        assert_eq!(
+            r,
+            WorkGenerateResponse {
+                work: Work::from_str("2b3d689bbcb21dca").unwrap(),
+                difficulty: Difficulty::from_str("fffffff93c41ec94").unwrap(),
+                multiplier: Multiplier::new(1.182623871097636).unwrap(),
+                hash: BlockHash::from_str(
+                    "718CC2121C3E641059BC1C2CFC45666C99E8AE922F7A807B7D07B62C995D79E2"
+                )
+                .unwrap(),
+            }
+        );
+    }
+}
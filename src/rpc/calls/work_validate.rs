@@ -1,14 +1,16 @@
 use crate::blocks::BlockHash;
-use crate::rpc::calls::{as_str, from_str};
 use crate::rpc::client::{RPCClient, RPCRequest};
-use crate::{Difficulty, Result, Work};
+use crate::{Difficulty, Multiplier, Result, Work};
 use async_trait::async_trait;
 use clap::Clap;
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Serialize, Deserialize, Clap)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct WorkValidateRequest {
+    #[clap(parse(try_from_str = crate::cli::parse::block_hash))]
     pub hash: BlockHash,
+    #[clap(parse(try_from_str = crate::cli::parse::work))]
     pub work: Work,
 }
 
@@ -32,15 +34,14 @@ impl WorkValidateRequest {
 }
 
 #[derive(Debug, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct WorkValidateResponse {
     // TODO: This is meant to be a bool as a number in a string?
     valid_all: String,
     valid_receive: String,
     difficulty: Difficulty,
 
-    // TODO: Make multiplier a type? It's used in multiple areas.
-    #[serde(deserialize_with = "from_str", serialize_with = "as_str")]
-    multiplier: f64,
+    multiplier: Multiplier,
 }
 
 #[cfg(test)]
@@ -66,7 +67,7 @@ mod tests {
                 valid_all: String::from("1"),
                 valid_receive: String::from("1"),
                 difficulty: Difficulty::from_str("fffffff93c41ec94").unwrap(),
-                multiplier: 1.182623871097636,
+                multiplier: Multiplier::new(1.182623871097636).unwrap(),
             }
         );
     }
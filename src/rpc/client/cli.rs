@@ -1,8 +1,11 @@
-use crate::rpc::calls::RpcCommand;
+use crate::rpc::calls::{AccountHistoryRequest, RpcCommand};
+use crate::rpc::client::filter::{json_path, select};
 use crate::rpc::client::{RPCClient, RPCRequest};
 use clap::Clap;
 use colored_json::ToColoredJson;
+use futures::TryStreamExt;
 use serde::Serialize;
+use serde_json::Value;
 
 #[derive(Clap)]
 pub(crate) struct RPCClientOpts {
@@ -19,17 +22,34 @@ pub(crate) struct RPCClientOpts {
     #[clap(long, short, env = "FEELESS_RPC_AUTH")]
     auth: Option<String>,
 
+    /// Comma separated list of top level fields to show from the response, e.g. `balance,pending`.
+    #[clap(long, use_delimiter = true, conflicts_with = "jsonpath")]
+    select: Option<Vec<String>>,
+
+    /// Only show the part of the response matching a JSONPath, e.g. `$.history[*].hash`.
+    ///
+    /// Supports `.field`, `['field']`, `[index]` and the `*` wildcard. A string result is printed
+    /// without quotes.
+    #[clap(long)]
+    jsonpath: Option<String>,
+
     /// The RPC call to make.
     #[clap(subcommand)]
     command: RpcCommand,
 }
 
 impl RPCClientOpts {
-    pub(crate) async fn handle(&self) -> crate::Result<()> {
+    /// Every call but `persist` only reads from the server, or is `process`, which is rehearsed.
+    pub(crate) fn can_dry_run(&self) -> bool {
+        !matches!(self.command, RpcCommand::Persist(_))
+    }
+
+    pub(crate) async fn handle(&self, dry_run: bool) -> crate::Result<()> {
         match &self.command {
             RpcCommand::AccountBalance(c) => self.show(c).await?,
             RpcCommand::AccountBlockCount(c) => self.show(c).await?,
             RpcCommand::AccountGet(c) => self.show(c).await?,
+            RpcCommand::AccountHistory(c) if c.all => self.show_history(c).await?,
             RpcCommand::AccountHistory(c) => self.show(c).await?,
             RpcCommand::AccountInfo(c) => self.show(c).await?,
             RpcCommand::AccountKey(c) => self.show(c).await?,
@@ -45,8 +65,24 @@ impl RPCClientOpts {
             RpcCommand::BlockCount(c) => self.show(c).await?,
             RpcCommand::BlockCreate(c) => self.show(c).await?,
             RpcCommand::BlockInfo(c) => self.show(c).await?,
+            RpcCommand::BlocksInfo(c) => self.show(c).await?,
+            RpcCommand::BootstrapStatus(c) => self.show(c).await?,
+            RpcCommand::ConfirmationQuorum(c) => self.show(c).await?,
+            RpcCommand::DroppedBlocks(c) => self.show(c).await?,
+            RpcCommand::DifficultyStats(c) => self.show(c).await?,
+            RpcCommand::Frontiers(c) => self.show(c).await?,
+            RpcCommand::LedgerStats(c) => self.show(c).await?,
+            RpcCommand::PeerTable(c) => self.show(c).await?,
+            RpcCommand::PeerTelemetry(c) => self.show(c).await?,
             RpcCommand::Peers(c) => self.show(c).await?,
+            RpcCommand::Pending(c) => self.show(c).await?,
+            RpcCommand::Persist(c) => self.show(c).await?,
+            RpcCommand::Process(c) if dry_run => self.rehearse(c),
             RpcCommand::Process(c) => self.show(c).await?,
+            RpcCommand::Representatives(c) => self.show(c).await?,
+            RpcCommand::SyncStatus(c) => self.show(c).await?,
+            RpcCommand::Version(c) => self.show(c).await?,
+            RpcCommand::WorkGenerate(c) => self.show(c).await?,
             RpcCommand::WorkValidate(c) => self.show(c).await?,
         };
         Ok(())
@@ -56,19 +92,67 @@ impl RPCClientOpts {
     where
         T: Serialize + RPCRequest,
     {
-        let mut client = RPCClient::new(&self.url);
-        if let Some(a) = &self.auth {
-            client.authorization(a);
+        let client = self.client();
+        let response = request.call(&client).await?;
+        let response =
+            self.filter(serde_json::to_value(&response).expect("Could not serialize"))?;
+        match response {
+            Value::String(s) => println!("{}", s),
+            response => println!(
+                "{}",
+                serde_json::to_string_pretty(&response)
+                    .expect("Could not serialize")
+                    .to_colored_json_auto()
+                    .expect("Could not colorize")
+            ),
         }
+        Ok(())
+    }
 
-        let response = request.call(&client).await?;
+    /// Print the request instead of sending it.
+    fn rehearse<T>(&self, request: T)
+    where
+        T: Serialize + RPCRequest,
+    {
         println!(
             "{}",
-            serde_json::to_string_pretty(&response)
+            serde_json::to_string_pretty(&RPCClient::body(&request))
                 .expect("Could not serialize")
                 .to_colored_json_auto()
                 .expect("Could not colorize")
         );
+        eprintln!("Dry run: nothing was sent to {}", self.url);
+    }
+
+    /// Every page of the history, one entry per line.
+    async fn show_history(&self, request: &AccountHistoryRequest) -> crate::Result<()> {
+        let client = self.client();
+        let mut entries = client.account_history_stream(request.to_owned());
+        while let Some(entry) = entries.try_next().await? {
+            let entry = self.filter(serde_json::to_value(&entry).expect("Could not serialize"))?;
+            match entry {
+                Value::String(s) => println!("{}", s),
+                entry => println!("{}", entry),
+            }
+        }
         Ok(())
     }
+
+    fn client(&self) -> RPCClient {
+        let mut client = RPCClient::new(&self.url);
+        if let Some(a) = &self.auth {
+            client.authorization(a);
+        }
+        client
+    }
+
+    fn filter(&self, response: Value) -> crate::Result<Value> {
+        if let Some(fields) = &self.select {
+            select(&response, fields)
+        } else if let Some(path) = &self.jsonpath {
+            json_path(&response, path)
+        } else {
+            Ok(response)
+        }
+    }
 }
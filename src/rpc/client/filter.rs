@@ -0,0 +1,238 @@
+//! Filtering of RPC responses for the CLI, so that simple extractions don't need `jq`.
+use crate::{Error, Result};
+use serde_json::{Map, Value};
+
+/// Keep only the given top level fields of an object.
+pub(crate) fn select(value: &Value, fields: &[String]) -> Result<Value> {
+    let object = value.as_object().ok_or_else(|| {
+        Error::InvalidOutputFilter(format!(
+            "Can only select fields from an object, got {}",
+            kind(value)
+        ))
+    })?;
+
+    let mut selected = Map::new();
+    for field in fields {
+        let v = object
+            .get(field)
+            .ok_or_else(|| missing_field("$", field, object))?;
+        selected.insert(field.to_owned(), v.to_owned());
+    }
+    Ok(Value::Object(selected))
+}
+
+#[derive(Debug, PartialEq)]
+enum Segment {
+    Key(String),
+    Index(usize),
+    Wildcard,
+}
+
+/// Evaluate a small subset of JSONPath: `$`, `.field`, `['field']`, `[0]`, `[*]` and `.*`.
+///
+/// When the path has a wildcard, the matches are returned as an array.
+pub(crate) fn json_path(value: &Value, path: &str) -> Result<Value> {
+    let segments = parse(path)?;
+    let has_wildcard = segments.contains(&Segment::Wildcard);
+
+    let mut current = vec![value];
+    let mut location = "$".to_string();
+    for segment in &segments {
+        let mut next = vec![];
+        for v in current {
+            match segment {
+                Segment::Key(key) => {
+                    let object = v.as_object().ok_or_else(|| {
+                        Error::InvalidOutputFilter(format!(
+                            "{}: Expecting an object to get {:?} from, got {}",
+                            location,
+                            key,
+                            kind(v)
+                        ))
+                    })?;
+                    next.push(
+                        object
+                            .get(key)
+                            .ok_or_else(|| missing_field(&location, key, object))?,
+                    );
+                }
+                Segment::Index(idx) => {
+                    let array = v.as_array().ok_or_else(|| {
+                        Error::InvalidOutputFilter(format!(
+                            "{}: Expecting an array to index, got {}",
+                            location,
+                            kind(v)
+                        ))
+                    })?;
+                    next.push(array.get(*idx).ok_or_else(|| {
+                        Error::InvalidOutputFilter(format!(
+                            "{}: Index {} is out of bounds, the array has {} items",
+                            location,
+                            idx,
+                            array.len()
+                        ))
+                    })?);
+                }
+                Segment::Wildcard => match v {
+                    Value::Array(a) => next.extend(a.iter()),
+                    Value::Object(o) => next.extend(o.values()),
+                    _ => {
+                        return Err(Error::InvalidOutputFilter(format!(
+                            "{}: Can't use a wildcard on {}",
+                            location,
+                            kind(v)
+                        )))
+                    }
+                },
+            }
+        }
+        current = next;
+        location.push_str(&match segment {
+            Segment::Key(key) => format!(".{}", key),
+            Segment::Index(idx) => format!("[{}]", idx),
+            Segment::Wildcard => "[*]".into(),
+        });
+    }
+
+    if has_wildcard {
+        Ok(Value::Array(current.into_iter().cloned().collect()))
+    } else {
+        // Without a wildcard there is always exactly one match.
+        Ok(current[0].to_owned())
+    }
+}
+
+fn parse(path: &str) -> Result<Vec<Segment>> {
+    let bad = |reason: &str| Error::InvalidOutputFilter(format!("{:?}: {}", path, reason));
+
+    let mut rest = path.trim();
+    rest = rest.strip_prefix('$').unwrap_or(rest);
+    // Allow `history[0]` as a shortcut for `$.history[0]`.
+    let mut needs_separator = path.trim().starts_with('$');
+
+    let mut segments = vec![];
+    while !rest.is_empty() {
+        if let Some(r) = rest.strip_prefix('[') {
+            let end = r.find(']').ok_or_else(|| bad("Missing closing ]"))?;
+            let inner = r[..end].trim();
+            rest = &r[end + 1..];
+            segments.push(if inner == "*" {
+                Segment::Wildcard
+            } else if let Some(key) = unquote(inner) {
+                Segment::Key(key.to_owned())
+            } else {
+                Segment::Index(
+                    inner
+                        .parse()
+                        .map_err(|_| bad(&format!("Invalid index {:?}", inner)))?,
+                )
+            });
+        } else {
+            if let Some(r) = rest.strip_prefix('.') {
+                rest = r;
+            } else if needs_separator {
+                return Err(bad(&format!("Expecting . or [ before {:?}", rest)));
+            }
+            let end = rest.find(['.', '[']).unwrap_or(rest.len());
+            let key = &rest[..end];
+            rest = &rest[end..];
+            segments.push(match key {
+                "" => return Err(bad("Empty field name")),
+                "*" => Segment::Wildcard,
+                key => Segment::Key(key.to_owned()),
+            });
+        }
+        needs_separator = true;
+    }
+    Ok(segments)
+}
+
+fn unquote(s: &str) -> Option<&str> {
+    for quote in &['\'', '"'] {
+        if let Some(inner) = s.strip_prefix(*quote).and_then(|s| s.strip_suffix(*quote)) {
+            return Some(inner);
+        }
+    }
+    None
+}
+
+fn missing_field(location: &str, field: &str, object: &Map<String, Value>) -> Error {
+    let available: Vec<&str> = object.keys().map(|k| k.as_str()).collect();
+    Error::InvalidOutputFilter(format!(
+        "{}: No field {:?}. Available fields: {}",
+        location,
+        field,
+        available.join(", ")
+    ))
+}
+
+fn kind(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "a boolean",
+        Value::Number(_) => "a number",
+        Value::String(_) => "a string",
+        Value::Array(_) => "an array",
+        Value::Object(_) => "an object",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn response() -> Value {
+        json!({
+            "balance": "100",
+            "pending": "5",
+            "history": [
+                {"hash": "A", "amount": "1"},
+                {"hash": "B", "amount": "2"},
+            ],
+        })
+    }
+
+    #[test]
+    fn select_fields() {
+        let selected = select(&response(), &["balance".into(), "pending".into()]).unwrap();
+        assert_eq!(selected, json!({"balance": "100", "pending": "5"}));
+
+        let err = select(&response(), &["nope".into()]).unwrap_err();
+        assert!(err.to_string().contains("balance, history, pending"));
+    }
+
+    #[test]
+    fn paths() {
+        let r = response();
+        assert_eq!(json_path(&r, "$.balance").unwrap(), json!("100"));
+        assert_eq!(json_path(&r, "balance").unwrap(), json!("100"));
+        assert_eq!(json_path(&r, "$.history[1].hash").unwrap(), json!("B"));
+        assert_eq!(
+            json_path(&r, "$['history'][0]['hash']").unwrap(),
+            json!("A")
+        );
+        assert_eq!(
+            json_path(&r, "$.history[*].hash").unwrap(),
+            json!(["A", "B"])
+        );
+        assert_eq!(
+            json_path(&r, "$.history.*.amount").unwrap(),
+            json!(["1", "2"])
+        );
+        assert_eq!(json_path(&r, "$").unwrap(), r);
+    }
+
+    #[test]
+    fn bad_paths() {
+        let r = response();
+        let err = |path| json_path(&r, path).unwrap_err().to_string();
+        assert!(err("$.history[5]").contains("out of bounds"));
+        assert!(err("$.history[0].nope").contains("$.history[0]: No field \"nope\""));
+        assert!(err("$.balance[0]").contains("got a string"));
+        assert!(err("$.history[x]").contains("Invalid index"));
+        assert!(err("$.history[0").contains("Missing closing ]"));
+        assert!(err("$balance").contains("Expecting . or ["));
+        assert!(err("$..balance").contains("Empty field name"));
+    }
+}
@@ -1,4 +1,6 @@
 mod cli;
+mod filter;
+mod stream;
 
 use crate::{Error, Result};
 use async_trait::async_trait;
@@ -58,24 +60,7 @@ impl RPCClient {
         S: Sized + Serialize + RPCRequest,
         R: Sized + DeserializeOwned + Debug,
     {
-        let action = request.action();
-        let client = reqwest::Client::new();
-
-        let body = Request::new(action, request);
-        let body = serde_json::to_string(&body).expect("Could not serialize request");
-        debug!("SEND: {}", body);
-
-        let mut request = client.post(&self.url);
-        if let Some(auth) = &self.authorization {
-            request = request.header("Authorization", auth);
-        }
-        let res = request
-            .header("Content-Type", "application/json")
-            .header("Accept", "application/json")
-            .body(body)
-            .send()
-            .await?;
-
+        let res = self.send(request).await?;
         let text = res.text().await?;
         debug!("RECV: {}", text);
 
@@ -93,7 +78,7 @@ impl RPCClient {
             Ok(t) => Ok(t),
             Err(err) => {
                 match serde_json::from_str::<RPCError>(&text) {
-                    Ok(err) => Err(Error::RPCError(err.error)),
+                    Ok(err) => Err(Error::RPCError(err.error.into())),
                     Err(_) => {
                         // We have an error in both matching R and RPCError, let's return the error
                         // given by from_str::<R>.
@@ -103,4 +88,34 @@ impl RPCClient {
             }
         }
     }
+
+    /// POST the request, returning the response before its body is read.
+    /// The JSON sent to the server for `request`, e.g. to show what a dry run would have sent.
+    pub(crate) fn body<S>(request: &S) -> serde_json::Value
+    where
+        S: Sized + Serialize + RPCRequest,
+    {
+        serde_json::to_value(Request::new(request.action(), request))
+            .expect("Could not serialize request")
+    }
+
+    async fn send<S>(&self, request: &S) -> Result<reqwest::Response>
+    where
+        S: Sized + Serialize + RPCRequest,
+    {
+        let client = reqwest::Client::new();
+        let body = Self::body(request).to_string();
+        debug!("SEND: {}", body);
+
+        let mut request = client.post(&self.url);
+        if let Some(auth) = &self.authorization {
+            request = request.header("Authorization", auth);
+        }
+        Ok(request
+            .header("Content-Type", "application/json")
+            .header("Accept", "application/json")
+            .body(body)
+            .send()
+            .await?)
+    }
 }
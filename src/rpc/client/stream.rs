@@ -0,0 +1,359 @@
+//! Decode the entries of one array or object field of a response as the body arrives, so a
+//! history or frontiers response of many megabytes isn't held in memory all at once.
+//!
+//! [EntryScanner] splits the bytes of the field into the JSON of each entry without parsing the
+//! rest of the body. An object's members come out as `[key, value]` arrays, which deserialize
+//! into tuples.
+use super::{RPCClient, RPCError, RPCRequest};
+use crate::{Error, Result};
+use futures::stream::{BoxStream, StreamExt};
+use serde::de::{DeserializeOwned, Error as _};
+use serde::Serialize;
+use std::collections::VecDeque;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Phase {
+    /// Looking for the field among the top level keys.
+    Seeking,
+
+    /// Past the field's colon, waiting for the value to start.
+    Value,
+
+    Array,
+    Object,
+
+    /// The field's value has ended.
+    Done,
+}
+
+pub(crate) struct EntryScanner {
+    field: Vec<u8>,
+    phase: Phase,
+
+    /// Nesting below the top level while seeking, or below the field's value after.
+    depth: usize,
+    in_string: bool,
+    escaped: bool,
+
+    /// The body so far while seeking, which is kept for the error when the field is missing,
+    /// then the entry being scanned.
+    buf: Vec<u8>,
+
+    /// Where the last string started in `buf` while seeking.
+    string_start: usize,
+
+    /// Whether the last top level string was the field's name.
+    matched: bool,
+
+    /// Where the colon between the key and value of an object member is in `buf`.
+    colon: Option<usize>,
+}
+
+impl EntryScanner {
+    pub fn new(field: &str) -> Self {
+        Self {
+            field: field.as_bytes().to_vec(),
+            phase: Phase::Seeking,
+            depth: 0,
+            in_string: false,
+            escaped: false,
+            buf: vec![],
+            string_start: 0,
+            matched: false,
+            colon: None,
+        }
+    }
+
+    /// Scan the next chunk of the body, returning the JSON of each entry that ended in it.
+    pub fn push(&mut self, chunk: &[u8]) -> Result<Vec<Vec<u8>>> {
+        let mut entries = vec![];
+        for &b in chunk {
+            match self.phase {
+                Phase::Seeking => self.seek(b),
+                Phase::Value => self.start_value(b)?,
+                Phase::Array | Phase::Object => {
+                    if let Some(entry) = self.scan(b)? {
+                        entries.push(entry);
+                    }
+                }
+                Phase::Done => break,
+            }
+        }
+        Ok(entries)
+    }
+
+    /// Whether every entry has been returned, so the rest of the body can be skipped.
+    pub fn is_done(&self) -> bool {
+        self.phase == Phase::Done
+    }
+
+    /// The body has ended. Fails with the RPC error when the field never came up, which is how
+    /// the node answers a bad request.
+    pub fn finish(&self) -> Result<()> {
+        match self.phase {
+            Phase::Done => Ok(()),
+            Phase::Seeking => {
+                let text = String::from_utf8_lossy(&self.buf).to_string();
+                if let Ok(err) = serde_json::from_str::<RPCError>(&text) {
+                    return Err(Error::RPCError(err.error.into()));
+                }
+                Err(self.bad(format!("No `{}` in the response", self.field()), text))
+            }
+            _ => Err(self.bad(
+                format!("The response ended inside `{}`", self.field()),
+                String::from_utf8_lossy(&self.buf).to_string(),
+            )),
+        }
+    }
+
+    fn seek(&mut self, b: u8) {
+        self.buf.push(b);
+        if self.in_string {
+            if self.escaped {
+                self.escaped = false;
+            } else if b == b'\\' {
+                self.escaped = true;
+            } else if b == b'"' {
+                self.in_string = false;
+                self.matched = self.depth == 1
+                    && self.buf[self.string_start..self.buf.len() - 1] == self.field;
+            }
+            return;
+        }
+        match b {
+            b'"' => {
+                self.in_string = true;
+                self.string_start = self.buf.len();
+            }
+            b'{' | b'[' => self.depth += 1,
+            b'}' | b']' => self.depth = self.depth.saturating_sub(1),
+            b':' if self.depth == 1 && self.matched => {
+                self.phase = Phase::Value;
+                self.buf.clear();
+            }
+            _ => {}
+        }
+    }
+
+    fn start_value(&mut self, b: u8) -> Result<()> {
+        self.depth = 0;
+        match b {
+            b'[' => self.phase = Phase::Array,
+            b'{' => self.phase = Phase::Object,
+            // The node sends an empty string instead of an empty array, e.g. for the history of
+            // an account without blocks.
+            b'"' => self.phase = Phase::Done,
+            b if b.is_ascii_whitespace() => {}
+            b => {
+                return Err(self.bad(
+                    format!("`{}` isn't an array or object", self.field()),
+                    (b as char).to_string(),
+                ))
+            }
+        }
+        Ok(())
+    }
+
+    fn scan(&mut self, b: u8) -> Result<Option<Vec<u8>>> {
+        if self.in_string {
+            self.buf.push(b);
+            if self.escaped {
+                self.escaped = false;
+            } else if b == b'\\' {
+                self.escaped = true;
+            } else if b == b'"' {
+                self.in_string = false;
+            }
+            return Ok(None);
+        }
+        match b {
+            b'"' => self.in_string = true,
+            b'{' | b'[' => self.depth += 1,
+            b'}' | b']' if self.depth == 0 => {
+                let entry = self.entry();
+                self.phase = Phase::Done;
+                return entry;
+            }
+            b'}' | b']' => self.depth -= 1,
+            b',' if self.depth == 0 => return self.entry(),
+            b':' if self.depth == 0 && self.phase == Phase::Object => {
+                self.colon = Some(self.buf.len())
+            }
+            b if b.is_ascii_whitespace() && self.buf.is_empty() => return Ok(None),
+            _ => {}
+        }
+        self.buf.push(b);
+        Ok(None)
+    }
+
+    /// Take the entry in `buf`, if it isn't the nothing in an empty array or object.
+    fn entry(&mut self) -> Result<Option<Vec<u8>>> {
+        let mut entry = std::mem::take(&mut self.buf);
+        while entry.last().is_some_and(|b| b.is_ascii_whitespace()) {
+            entry.pop();
+        }
+        if entry.is_empty() {
+            return Ok(None);
+        }
+        if self.phase == Phase::Object && self.colon.is_none() {
+            return Err(self.bad(
+                format!("A member of `{}` without a value", self.field()),
+                String::from_utf8_lossy(&entry).to_string(),
+            ));
+        }
+        Ok(Some(match self.colon.take() {
+            Some(colon) => {
+                let mut pair = Vec::with_capacity(entry.len() + 2);
+                pair.push(b'[');
+                pair.extend(&entry[..colon]);
+                pair.push(b',');
+                pair.extend(&entry[colon + 1..]);
+                pair.push(b']');
+                pair
+            }
+            None => entry,
+        }))
+    }
+
+    fn field(&self) -> String {
+        String::from_utf8_lossy(&self.field).to_string()
+    }
+
+    fn bad(&self, message: String, response: String) -> Error {
+        Error::BadRPCResponse {
+            err: serde_json::Error::custom(message),
+            response,
+        }
+    }
+}
+
+fn decode<T: DeserializeOwned>(entry: &[u8]) -> Result<T> {
+    serde_json::from_slice(entry).map_err(|err| Error::BadRPCResponse {
+        err,
+        response: String::from_utf8_lossy(entry).to_string(),
+    })
+}
+
+impl RPCClient {
+    /// Make the request, and decode each entry of `field` in the response as it arrives.
+    pub(crate) async fn stream<S, T>(
+        &self,
+        request: &S,
+        field: &str,
+    ) -> Result<BoxStream<'static, Result<T>>>
+    where
+        S: Sized + Serialize + RPCRequest,
+        T: DeserializeOwned + Send + 'static,
+    {
+        let response = self.send(request).await?;
+        let state = (
+            response,
+            EntryScanner::new(field),
+            VecDeque::<Vec<u8>>::new(),
+        );
+        Ok(futures::stream::try_unfold(
+            state,
+            |(mut response, mut scanner, mut ready)| async move {
+                loop {
+                    if let Some(entry) = ready.pop_front() {
+                        let entry: T = decode(&entry)?;
+                        return Ok(Some((entry, (response, scanner, ready))));
+                    }
+                    if scanner.is_done() {
+                        return Ok(None);
+                    }
+                    match response.chunk().await? {
+                        Some(chunk) => ready.extend(scanner.push(&chunk)?),
+                        None => {
+                            scanner.finish()?;
+                            return Ok(None);
+                        }
+                    }
+                }
+            },
+        )
+        .boxed())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Feed the body in chunks of `size` bytes, returning the entries as strings.
+    fn scan(field: &str, body: &str, size: usize) -> Result<Vec<String>> {
+        let mut scanner = EntryScanner::new(field);
+        let mut entries = vec![];
+        for chunk in body.as_bytes().chunks(size) {
+            for entry in scanner.push(chunk)? {
+                entries.push(String::from_utf8(entry).unwrap());
+            }
+        }
+        scanner.finish()?;
+        Ok(entries)
+    }
+
+    #[test]
+    fn array() {
+        let body = r#"{
+            "account": "history",
+            "other": {"history": [1]},
+            "history": [
+                {"hash": "A", "nested": [1, {"x": "],"}]},
+                {"hash": "B\"}", "n": 2}
+            ],
+            "previous": "C"
+        }"#;
+        for size in &[1, 3, 7, body.len()] {
+            assert_eq!(
+                scan("history", body, *size).unwrap(),
+                vec![
+                    r#"{"hash": "A", "nested": [1, {"x": "],"}]}"#,
+                    r#"{"hash": "B\"}", "n": 2}"#,
+                ],
+                "Chunks of {}",
+                size
+            );
+        }
+    }
+
+    #[test]
+    fn object() {
+        let body = r#"{"frontiers": {"a": "1", "b" : {"c": 2}}}"#;
+        for size in &[1, 5, body.len()] {
+            assert_eq!(
+                scan("frontiers", body, *size).unwrap(),
+                vec![r#"["a", "1"]"#, r#"["b" , {"c": 2}]"#]
+            );
+        }
+    }
+
+    #[test]
+    fn empty() {
+        assert!(scan("history", r#"{"history": []}"#, 1).unwrap().is_empty());
+        assert!(scan("history", r#"{"history": ""}"#, 1).unwrap().is_empty());
+        assert!(scan("frontiers", r#"{"frontiers": {}}"#, 1)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn errors() {
+        assert!(matches!(
+            scan("history", r#"{"error": "Bad account number"}"#, 4),
+            Err(Error::RPCError(_))
+        ));
+        assert!(matches!(
+            scan("history", r#"{"account": "x"}"#, 4),
+            Err(Error::BadRPCResponse { .. })
+        ));
+        assert!(matches!(
+            scan("history", r#"{"history": [{"a": 1}, {"b""#, 4),
+            Err(Error::BadRPCResponse { .. })
+        ));
+        assert!(matches!(
+            scan("history", r#"{"history": 5}"#, 4),
+            Err(Error::BadRPCResponse { .. })
+        ));
+    }
+}
@@ -7,5 +7,8 @@ pub mod client;
 #[cfg(feature = "rpc_server")]
 pub mod server;
 
+#[cfg(feature = "rpc_client")]
+pub mod websocket;
+
 #[cfg(any(feature = "rpc_client", feature = "rpc_server"))]
 pub use calls::*;
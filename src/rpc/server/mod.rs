@@ -1,16 +1,67 @@
-use crate::node::{ArcState, NodeCommandReceiver, NodeCommandSender};
+use crate::node::{ArcState, HealthReport, NodeCommand, NodeCommandReceiver, NodeCommandSender};
 use crate::rpc::client::RPCError;
 use crate::rpc::{NodeHandler, RpcCommand};
-use crate::Result;
-use serde::Serialize;
-use tokio::sync::mpsc;
+use crate::{Error, Result};
+use anyhow::anyhow;
+use serde::{Deserialize, Serialize};
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+use tokio::sync::{mpsc, oneshot};
 use tracing::{info, trace};
 use warp::http::StatusCode;
 use warp::Filter;
 
+/// Actions answered when the config doesn't list any. None of them write to the ledger or
+/// broadcast, so `process`, `persist` and the diagnostics have to be enabled explicitly.
+pub const DEFAULT_ACTIONS: &[&str] = &["account_info", "block_info", "work_generate"];
+
+/// The `[rpc]` section of the config file.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct RpcServerConfig {
+    /// Only answer these actions, e.g. `["account_info", "block_info", "process"]` to let light
+    /// wallets publish blocks. [DEFAULT_ACTIONS] are allowed when this is missing.
+    #[serde(default)]
+    pub actions: Option<Vec<String>>,
+
+    /// The directory `persist` creates databases in. `persist` is refused when this is missing,
+    /// since it would otherwise write wherever the caller asks.
+    #[serde(default)]
+    pub persist_dir: Option<PathBuf>,
+}
+
+impl RpcServerConfig {
+    pub fn allows(&self, action: &str) -> bool {
+        match &self.actions {
+            Some(actions) => actions.iter().any(|a| a == action),
+            None => DEFAULT_ACTIONS.contains(&action),
+        }
+    }
+
+    /// Where `persist` should write the database named `to`. It has to be a relative path that
+    /// stays inside [RpcServerConfig::persist_dir].
+    pub fn persist_path(&self, to: &Path) -> anyhow::Result<PathBuf> {
+        let dir = self
+            .persist_dir
+            .as_ref()
+            .ok_or_else(|| anyhow!("persist_dir is not set in the [rpc] config"))?;
+        let inside = to.components().next().is_some()
+            && to
+                .components()
+                .all(|component| matches!(component, Component::Normal(_)));
+        if !inside {
+            return Err(anyhow!(
+                "{:?} is not a relative path inside persist_dir",
+                to
+            ));
+        }
+        Ok(dir.join(to))
+    }
+}
+
 pub struct RPCServer {
     state: ArcState,
     node_cmd_tx: NodeCommandSender,
+    config: Arc<RpcServerConfig>,
 }
 
 impl RPCServer {
@@ -19,24 +70,63 @@ impl RPCServer {
         let s = Self {
             node_cmd_tx: tx,
             state,
+            config: Default::default(),
         };
         (s, rx)
     }
 
+    pub fn config(mut self, config: RpcServerConfig) -> Self {
+        self.config = Arc::new(config);
+        self
+    }
+
     pub async fn run(self) -> anyhow::Result<()> {
         info!("Starting RPC server");
         let rpc = warp::post()
             .and(warp::body::content_length_limit(1024 * 16))
             .and(with_state(self.state.clone()))
             .and(with_node_tx(self.node_cmd_tx.clone()))
+            .and(with_config(self.config.clone()))
             .and(warp::body::json())
-            .and_then(Self::handle);
+            .and_then(Self::handle_json);
+        let health = warp::get()
+            .and(warp::path!("health"))
+            .and(with_node_tx(self.node_cmd_tx.clone()))
+            .and_then(|node_tx| Self::probe(node_tx, |report| report.healthy));
+        let ready = warp::get()
+            .and(warp::path!("ready"))
+            .and(with_node_tx(self.node_cmd_tx.clone()))
+            .and_then(|node_tx| Self::probe(node_tx, |report| report.ready));
 
         // TODO: Configurable
-        warp::serve(rpc).run(([127, 0, 0, 1], 7076)).await;
+        warp::serve(health.or(ready).or(rpc))
+            .run(([127, 0, 0, 1], 7076))
+            .await;
         Ok(())
     }
 
+    /// Decode the command once its action is known to be allowed, so a refused action doesn't
+    /// depend on the request being valid.
+    async fn handle_json(
+        state: ArcState,
+        node_tx: NodeCommandSender,
+        config: Arc<RpcServerConfig>,
+        body: serde_json::Value,
+    ) -> std::result::Result<Box<dyn warp::Reply>, warp::Rejection> {
+        let action = match body.get("action").and_then(|a| a.as_str()) {
+            Some(action) => action,
+            None => return error("Unable to parse JSON"),
+        };
+        if !config.allows(action) {
+            info!("Refusing RPC action {}", action);
+            return error(&format!("Action {} is not enabled", action));
+        }
+        match serde_json::from_value::<RpcCommand>(body) {
+            Ok(cmd) => Self::handle(state, node_tx, cmd).await,
+            Err(err) => error(&err.to_string()),
+        }
+    }
+
     async fn handle(
         _state: ArcState,
         node_tx: NodeCommandSender,
@@ -54,7 +144,20 @@ impl RPCServer {
             // }),
             // RpcCommand::Peers(c) => json_result(handle_peers(state, tx, c).await),
             RpcCommand::Peers(c) => json_result(c.handle(node_tx).await),
-            // RpcCommand::Process(c) => json_result(handle_process(state, tx, c).await),
+            RpcCommand::AccountInfo(c) => json_result(c.handle(node_tx).await),
+            RpcCommand::Pending(c) => json_result(c.handle(node_tx).await),
+            RpcCommand::AccountsPending(c) => json_result(c.handle(node_tx).await),
+            RpcCommand::BlockInfo(c) => json_result(c.handle(node_tx).await),
+            RpcCommand::Process(c) => json_result(c.handle(node_tx).await),
+            RpcCommand::WorkGenerate(c) => json_result(c.handle(node_tx).await),
+            RpcCommand::BootstrapStatus(c) => json_result(c.handle(node_tx).await),
+            RpcCommand::DroppedBlocks(c) => json_result(c.handle(node_tx).await),
+            RpcCommand::DifficultyStats(c) => json_result(c.handle(node_tx).await),
+            RpcCommand::LedgerStats(c) => json_result(c.handle(node_tx).await),
+            RpcCommand::PeerTable(c) => json_result(c.handle(node_tx).await),
+            RpcCommand::PeerTelemetry(c) => json_result(c.handle(node_tx).await),
+            RpcCommand::Persist(c) => json_result(c.handle(node_tx).await),
+            RpcCommand::SyncStatus(c) => json_result(c.handle(node_tx).await),
             action => json_result(Ok(RPCError {
                 error: format!("This action is unhandled by the RPC server: {:?}", action),
             })),
@@ -62,12 +165,50 @@ impl RPCServer {
     }
 }
 
+impl RPCServer {
+    /// The health report, with a 503 status when `ok` isn't true of it.
+    async fn probe(
+        node_tx: NodeCommandSender,
+        ok: fn(&HealthReport) -> bool,
+    ) -> std::result::Result<Box<dyn warp::Reply>, warp::Rejection> {
+        let (tx, rx) = oneshot::channel();
+        let report = match node_tx.send(NodeCommand::Health(tx)).await {
+            Ok(()) => rx.await.ok(),
+            Err(_) => None,
+        };
+        let (body, status) = match report {
+            Some(report) => {
+                let status = if ok(&report) {
+                    StatusCode::OK
+                } else {
+                    StatusCode::SERVICE_UNAVAILABLE
+                };
+                (serde_json::to_string(&report), status)
+            }
+            None => (
+                serde_json::to_string(&RPCError {
+                    error: "The node isn't running".into(),
+                }),
+                StatusCode::SERVICE_UNAVAILABLE,
+            ),
+        };
+        let body = body.expect("Could not serialize the health report.");
+        Ok(Box::new(warp::reply::with_status(body, status)))
+    }
+}
+
 fn with_node_tx(
     node_cmd_tx: NodeCommandSender,
 ) -> impl Filter<Extract = (NodeCommandSender,), Error = std::convert::Infallible> + Clone {
     warp::any().map(move || node_cmd_tx.clone())
 }
 
+fn with_config(
+    config: Arc<RpcServerConfig>,
+) -> impl Filter<Extract = (Arc<RpcServerConfig>,), Error = std::convert::Infallible> + Clone {
+    warp::any().map(move || config.clone())
+}
+
 fn with_state(
     state: ArcState,
 ) -> impl Filter<Extract = (ArcState,), Error = std::convert::Infallible> + Clone {
@@ -80,10 +221,18 @@ where
 {
     match &result {
         Ok(o) => json(o),
-        Err(err) => todo!("{:?}", err),
+        // Errors are answered like the reference node does, with a 200 status.
+        Err(Error::RPCError(kind)) => error(&kind.to_string()),
+        Err(err) => error(&err.to_string()),
     }
 }
 
+fn error(message: &str) -> std::result::Result<Box<dyn warp::Reply>, warp::Rejection> {
+    json(&RPCError {
+        error: message.to_owned(),
+    })
+}
+
 fn json<T>(o: &T) -> std::result::Result<Box<dyn warp::Reply>, warp::Rejection>
 where
     T: ?Sized + Serialize,
@@ -102,3 +251,40 @@ where
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn allowed_actions() {
+        let default = RpcServerConfig::default();
+        assert!(default.allows("account_info"));
+        assert!(!default.allows("process"));
+        assert!(!default.allows("persist"));
+
+        let config: RpcServerConfig =
+            toml::from_str(r#"actions = ["account_info", "process"]"#).unwrap();
+        assert!(config.allows("process"));
+        assert!(!config.allows("persist"));
+    }
+
+    #[test]
+    fn persist_path() {
+        assert!(RpcServerConfig::default()
+            .persist_path(Path::new("backup.db"))
+            .is_err());
+
+        let config: RpcServerConfig =
+            toml::from_str(r#"persist_dir = "/var/lib/feeless""#).unwrap();
+        assert_eq!(
+            config.persist_path(Path::new("backup.db")).unwrap(),
+            Path::new("/var/lib/feeless/backup.db")
+        );
+        assert!(config.persist_path(Path::new("2021/backup.db")).is_ok());
+        assert!(config.persist_path(Path::new("")).is_err());
+        assert!(config.persist_path(Path::new("/etc/backup.db")).is_err());
+        assert!(config.persist_path(Path::new("../backup.db")).is_err());
+        assert!(config.persist_path(Path::new("a/../../backup.db")).is_err());
+    }
+}
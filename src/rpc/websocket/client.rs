@@ -0,0 +1,99 @@
+use crate::rpc::websocket::{FrontierEvent, Incoming, Outgoing, SubscribeOptions, Topic};
+use crate::Address;
+use futures::{SinkExt, StreamExt};
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio_tungstenite::tungstenite::Message;
+use tracing::{debug, warn};
+
+/// How long to wait before reconnecting by default.
+const RETRY: Duration = Duration::from_secs(5);
+
+pub struct WebSocketClient {
+    url: String,
+    retry: Duration,
+}
+
+impl WebSocketClient {
+    pub fn new<S: Into<String>>(url: S) -> Self {
+        Self {
+            url: url.into(),
+            retry: RETRY,
+        }
+    }
+
+    /// How long to wait before reconnecting after the connection fails or is closed.
+    pub fn retry(&mut self, retry: Duration) {
+        self.retry = retry;
+    }
+
+    /// Follow frontier changes of `accounts`, or of every account when `None`.
+    ///
+    /// The connection is made in the background, and made again with the same subscription
+    /// whenever it's lost, so events keep arriving across node restarts. Events from while it
+    /// was disconnected are missed. Drop the receiver to stop.
+    pub fn frontiers(&self, accounts: Option<Vec<Address>>) -> mpsc::Receiver<FrontierEvent> {
+        let (tx, rx) = mpsc::channel(100);
+        let subscribe = Incoming::Subscribe {
+            topic: Topic::Frontiers,
+            options: SubscribeOptions { accounts },
+            ack: false,
+            id: None,
+        };
+        let subscribe = serde_json::to_string(&subscribe).expect("Could not serialize subscribe");
+        let url = self.url.to_owned();
+        let retry = self.retry;
+
+        tokio::spawn(async move {
+            loop {
+                match stream(&url, &subscribe, &tx).await {
+                    Ok(()) => debug!("WebSocket {} closed", url),
+                    Err(err) => warn!("WebSocket {} failed: {:?}", url, err),
+                }
+                if tx.is_closed() {
+                    return;
+                }
+                tokio::time::sleep(retry).await;
+            }
+        });
+        rx
+    }
+}
+
+/// Subscribe and pass events on until the connection ends or `tx` is closed.
+async fn stream(
+    url: &str,
+    subscribe: &str,
+    tx: &mpsc::Sender<FrontierEvent>,
+) -> anyhow::Result<()> {
+    let (mut socket, _) = tokio_tungstenite::connect_async(url).await?;
+    socket.send(Message::Text(subscribe.to_owned())).await?;
+    debug!("Subscribed to frontiers on {}", url);
+
+    loop {
+        let message = tokio::select! {
+            message = socket.next() => message,
+            _ = tx.closed() => break,
+        };
+        let message = match message {
+            Some(message) => message?,
+            None => break,
+        };
+        let text = match message {
+            Message::Text(text) => text,
+            Message::Close(_) => break,
+            _ => continue,
+        };
+        match serde_json::from_str::<Outgoing>(&text) {
+            Ok(Outgoing::Event { message, .. }) => {
+                if tx.send(message).await.is_err() {
+                    break;
+                }
+            }
+            Ok(Outgoing::Error { error }) => warn!("WebSocket {}: {}", url, error),
+            Ok(Outgoing::Ack { .. }) => {}
+            Err(err) => warn!("Unexpected WebSocket message {:?}: {:?}", text, err),
+        }
+    }
+    Ok(())
+}
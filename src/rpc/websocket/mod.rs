@@ -0,0 +1,165 @@
+//! A WebSocket interface for following events on a node, separate from the request/response RPC.
+//!
+//! Clients send JSON messages with an `action` to manage subscriptions to a topic, e.g.:
+//!
+//! ```json
+//! {"action": "subscribe", "topic": "frontiers", "options": {"accounts": ["nano_1..."]}}
+//! {"action": "update", "topic": "frontiers", "options": {"accounts_add": ["nano_3..."]}}
+//! {"action": "unsubscribe", "topic": "frontiers"}
+//! ```
+//!
+//! Leaving out `accounts` when subscribing follows every account. Add `"ack": true` to have the
+//! server confirm each message, along with an optional `id` that's sent back.
+//!
+//! While subscribed, the server sends `{"topic": "frontiers", "time": "...", "message": {...}}`
+//! with a [FrontierEvent] for each change.
+#[cfg(feature = "rpc_client")]
+mod client;
+#[cfg(feature = "rpc_server")]
+mod server;
+
+use crate::blocks::BlockHash;
+use crate::Address;
+#[cfg(feature = "rpc_client")]
+pub use client::WebSocketClient;
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "rpc_server")]
+pub use server::WebSocketServer;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum Topic {
+    Frontiers,
+}
+
+/// The latest block of an account has changed.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct FrontierEvent {
+    pub account: Address,
+    pub hash: BlockHash,
+    pub source: FrontierSource,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum FrontierSource {
+    /// A block was added to the ledger.
+    Block,
+
+    /// A peer reported the frontier while bootstrapping.
+    Bootstrap,
+}
+
+/// Messages from a client.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum Incoming {
+    Subscribe {
+        topic: Topic,
+
+        #[serde(default)]
+        options: SubscribeOptions,
+
+        #[serde(default)]
+        ack: bool,
+
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        id: Option<String>,
+    },
+    Update {
+        topic: Topic,
+
+        #[serde(default)]
+        options: UpdateOptions,
+
+        #[serde(default)]
+        ack: bool,
+
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        id: Option<String>,
+    },
+    Unsubscribe {
+        topic: Topic,
+
+        #[serde(default)]
+        ack: bool,
+
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        id: Option<String>,
+    },
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct SubscribeOptions {
+    /// Only send events for these accounts. Every account when `None`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub accounts: Option<Vec<Address>>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct UpdateOptions {
+    #[serde(default)]
+    pub accounts_add: Vec<Address>,
+
+    #[serde(default)]
+    pub accounts_del: Vec<Address>,
+}
+
+/// Messages from the server.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(untagged)]
+pub enum Outgoing {
+    Event {
+        topic: Topic,
+
+        /// Milliseconds since the Unix epoch.
+        time: String,
+
+        message: FrontierEvent,
+    },
+    Ack {
+        /// The action being acknowledged.
+        ack: String,
+
+        time: String,
+
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        id: Option<String>,
+    },
+    Error {
+        error: String,
+    },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn messages() {
+        let subscribe: Incoming =
+            serde_json::from_str(r#"{"action": "subscribe", "topic": "frontiers"}"#).unwrap();
+        assert_eq!(
+            subscribe,
+            Incoming::Subscribe {
+                topic: Topic::Frontiers,
+                options: SubscribeOptions::default(),
+                ack: false,
+                id: None,
+            }
+        );
+
+        let ack: Outgoing =
+            serde_json::from_str(r#"{"ack": "subscribe", "time": "1", "id": "a"}"#).unwrap();
+        assert!(matches!(ack, Outgoing::Ack { id: Some(_), .. }));
+        let error: Outgoing = serde_json::from_str(r#"{"error": "Nope"}"#).unwrap();
+        assert!(matches!(error, Outgoing::Error { .. }));
+    }
+}
@@ -0,0 +1,281 @@
+use crate::node::FrontierEvents;
+use crate::rpc::websocket::{FrontierEvent, Incoming, Outgoing, Topic};
+use crate::Address;
+use chrono::Utc;
+use futures::{SinkExt, StreamExt};
+use std::collections::HashSet;
+use std::net::SocketAddr;
+use tokio::sync::broadcast;
+use tracing::{debug, info, warn};
+use warp::ws::{Message, WebSocket};
+use warp::Filter;
+
+pub struct WebSocketServer {
+    frontiers: FrontierEvents,
+}
+
+/// What a connection is subscribed to.
+#[derive(Debug, Default)]
+struct Subscription {
+    /// `None` when not subscribed, and an empty filter when following every account.
+    frontiers: Option<AccountFilter>,
+}
+
+#[derive(Debug, Default)]
+struct AccountFilter(Option<HashSet<Address>>);
+
+impl AccountFilter {
+    fn matches(&self, account: &Address) -> bool {
+        self.0
+            .as_ref()
+            .is_none_or(|accounts| accounts.contains(account))
+    }
+}
+
+impl WebSocketServer {
+    pub fn new(frontiers: FrontierEvents) -> Self {
+        Self { frontiers }
+    }
+
+    pub async fn run(self, address: SocketAddr) -> anyhow::Result<()> {
+        info!("Starting WebSocket server on {}", address);
+        warp::serve(self.routes()).run(address).await;
+        Ok(())
+    }
+
+    pub(crate) fn routes(
+        &self,
+    ) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+        let frontiers = self.frontiers.clone();
+        warp::ws().map(move |ws: warp::ws::Ws| {
+            let events = frontiers.subscribe();
+            ws.on_upgrade(move |socket| connection(socket, events))
+        })
+    }
+}
+
+async fn connection(mut socket: WebSocket, mut events: broadcast::Receiver<FrontierEvent>) {
+    let mut subscription = Subscription::default();
+    loop {
+        let reply = tokio::select! {
+            message = socket.next() => match message {
+                Some(Ok(message)) if message.is_close() => break,
+                Some(Ok(message)) => match message.to_str() {
+                    Ok(text) => handle(&mut subscription, text),
+                    // Pings are answered by warp, and there's nothing to do with binary data.
+                    Err(()) => None,
+                },
+                Some(Err(err)) => {
+                    debug!("WebSocket error: {:?}", err);
+                    break;
+                }
+                None => break,
+            },
+            event = events.recv() => match event {
+                Ok(event) => match &subscription.frontiers {
+                    Some(filter) if filter.matches(&event.account) => Some(Outgoing::Event {
+                        topic: Topic::Frontiers,
+                        time: now(),
+                        message: event,
+                    }),
+                    _ => None,
+                },
+                Err(broadcast::error::RecvError::Lagged(missed)) => {
+                    warn!("WebSocket client fell behind and missed {} events", missed);
+                    Some(Outgoing::Error {
+                        error: format!("Missed {} events from falling behind", missed),
+                    })
+                }
+                // The node is shutting down.
+                Err(broadcast::error::RecvError::Closed) => break,
+            },
+        };
+
+        if let Some(reply) = reply {
+            let json = match serde_json::to_string(&reply) {
+                Ok(json) => json,
+                Err(err) => {
+                    warn!("Could not serialize {:?}: {:?}", reply, err);
+                    continue;
+                }
+            };
+            if socket.send(Message::text(json)).await.is_err() {
+                break;
+            }
+        }
+    }
+    let _ = socket.close().await;
+}
+
+/// Apply a message from the client, returning what to reply with, if anything.
+fn handle(subscription: &mut Subscription, text: &str) -> Option<Outgoing> {
+    let incoming: Incoming = match serde_json::from_str(text) {
+        Ok(incoming) => incoming,
+        Err(err) => {
+            return Some(Outgoing::Error {
+                error: format!("Could not parse message: {}", err),
+            })
+        }
+    };
+
+    let (action, ack, id) = match incoming {
+        Incoming::Subscribe {
+            topic: Topic::Frontiers,
+            options,
+            ack,
+            id,
+        } => {
+            subscription.frontiers = Some(AccountFilter(
+                options
+                    .accounts
+                    .map(|accounts| accounts.into_iter().collect()),
+            ));
+            ("subscribe", ack, id)
+        }
+        Incoming::Update {
+            topic: Topic::Frontiers,
+            options,
+            ack,
+            id,
+        } => {
+            let filter = match &mut subscription.frontiers {
+                Some(filter) => filter,
+                None => {
+                    return Some(Outgoing::Error {
+                        error: "Can't update frontiers without subscribing first".into(),
+                    })
+                }
+            };
+            // Adding to a subscription for every account has no effect.
+            if let Some(accounts) = &mut filter.0 {
+                accounts.extend(options.accounts_add);
+                for account in &options.accounts_del {
+                    accounts.remove(account);
+                }
+            }
+            ("update", ack, id)
+        }
+        Incoming::Unsubscribe {
+            topic: Topic::Frontiers,
+            ack,
+            id,
+        } => {
+            subscription.frontiers = None;
+            ("unsubscribe", ack, id)
+        }
+    };
+
+    if ack {
+        Some(Outgoing::Ack {
+            ack: action.into(),
+            time: now(),
+            id,
+        })
+    } else {
+        None
+    }
+}
+
+fn now() -> String {
+    Utc::now().timestamp_millis().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rpc::websocket::{FrontierSource, WebSocketClient};
+    use crate::{Public, Seed};
+    use std::time::Duration;
+    use tokio::sync::{mpsc, oneshot};
+    use tokio::task::JoinHandle;
+
+    fn account(index: u32) -> Public {
+        Seed::zero().derive(index).to_public().unwrap()
+    }
+
+    /// Serve until the returned sender is dropped, after which the task finishes.
+    fn serve(
+        events: &FrontierEvents,
+        address: SocketAddr,
+    ) -> (SocketAddr, oneshot::Sender<()>, JoinHandle<()>) {
+        let (tx, rx) = oneshot::channel::<()>();
+        let (address, server) = warp::serve(WebSocketServer::new(events.clone()).routes())
+            .bind_with_graceful_shutdown(address, async {
+                let _ = rx.await;
+            });
+        (address, tx, tokio::spawn(server))
+    }
+
+    /// Keep publishing until the client gets something, since it might still be (re)connecting.
+    async fn next(
+        events: &FrontierEvents,
+        rx: &mut mpsc::Receiver<FrontierEvent>,
+    ) -> FrontierEvent {
+        for _ in 0..100 {
+            let hash = crate::blocks::BlockHash::zero();
+            events.publish(&account(1), &hash, FrontierSource::Block);
+            events.publish(&account(0), &hash, FrontierSource::Bootstrap);
+            if let Ok(event) = tokio::time::timeout(Duration::from_millis(50), rx.recv()).await {
+                return event.unwrap();
+            }
+        }
+        panic!("No events");
+    }
+
+    fn send(subscription: &mut Subscription, json: serde_json::Value) -> Option<Outgoing> {
+        handle(subscription, &json.to_string())
+    }
+
+    #[test]
+    fn filters() {
+        let mut subscription = Subscription::default();
+        let address = account(0).to_address();
+        let update = serde_json::json!({
+            "action": "update", "topic": "frontiers",
+            "options": {"accounts_add": [address.to_string()]}
+        });
+        assert!(send(&mut subscription, update.clone()).is_some());
+
+        let ack = send(
+            &mut subscription,
+            serde_json::json!({
+                "action": "subscribe", "topic": "frontiers", "ack": true, "id": "x",
+                "options": {"accounts": []}
+            }),
+        );
+        assert!(matches!(ack, Some(Outgoing::Ack { id: Some(id), .. }) if id == "x"));
+        let matches = |s: &Subscription| s.frontiers.as_ref().unwrap().matches(&address);
+        assert!(!matches(&subscription));
+
+        assert!(send(&mut subscription, update).is_none());
+        assert!(matches(&subscription));
+
+        send(
+            &mut subscription,
+            serde_json::json!({"action": "unsubscribe", "topic": "frontiers"}),
+        );
+        assert!(subscription.frontiers.is_none());
+    }
+
+    #[tokio::test]
+    async fn resubscribes_after_reconnecting() {
+        let events = FrontierEvents::new();
+        let (address, shutdown, server) = serve(&events, ([127, 0, 0, 1], 0).into());
+        let mut client = WebSocketClient::new(format!("ws://{}", address));
+        client.retry(Duration::from_millis(20));
+        let mut rx = client.frontiers(Some(vec![account(0).to_address()]));
+
+        let event = next(&events, &mut rx).await;
+        assert_eq!(event.account, account(0).to_address());
+        assert_eq!(event.source, FrontierSource::Bootstrap);
+
+        // Dropping the events closes the open connections, like a node shutting down.
+        drop(shutdown);
+        server.await.unwrap();
+        drop(events);
+        let events = FrontierEvents::new();
+        let _server = serve(&events, address);
+        let event = next(&events, &mut rx).await;
+        assert_eq!(event.account, account(0).to_address());
+    }
+}
@@ -0,0 +1,167 @@
+//! JSON Schema documents for the JSON this crate reads and writes, for `feeless schema export`.
+//!
+//! The schemas are generated from the serde types with [schemars], so they follow any change to
+//! the types. Types with their own serde implementations, like the hex encoded keys and hashes,
+//! describe themselves as strings with a pattern.
+use crate::blocks::{ChangeBlock, OpenBlock, ReceiveBlock, SendBlock, StateBlock};
+use crate::rpc::calls::*;
+use crate::rpc::websocket::{FrontierEvent, Incoming, Outgoing};
+use schemars::schema::{
+    InstanceType, Metadata, RootSchema, Schema, SchemaObject, StringValidation,
+};
+use schemars::schema_for;
+use std::collections::BTreeMap;
+
+/// A string matching `pattern`.
+pub(crate) fn string(description: &str, pattern: &str) -> Schema {
+    SchemaObject {
+        instance_type: Some(InstanceType::String.into()),
+        metadata: Some(Box::new(Metadata {
+            description: Some(description.to_owned()),
+            ..Default::default()
+        })),
+        string: Some(Box::new(StringValidation {
+            pattern: Some(pattern.to_owned()),
+            ..Default::default()
+        })),
+        ..Default::default()
+    }
+    .into()
+}
+
+/// A hex string of `len` bytes.
+pub(crate) fn hex(description: &str, len: usize) -> Schema {
+    string(
+        &format!("A {} as {} hex characters", description, len * 2),
+        &format!("^[0-9A-Fa-f]{{{}}}$", len * 2),
+    )
+}
+
+/// Every schema by name, e.g. `state_block` or `account_info_response`.
+pub fn export() -> BTreeMap<&'static str, RootSchema> {
+    let mut schemas = BTreeMap::new();
+    let mut add = |name, schema| {
+        schemas.insert(name, schema);
+    };
+
+    add("state_block", schema_for!(StateBlock));
+    add("send_block", schema_for!(SendBlock));
+    add("open_block", schema_for!(OpenBlock));
+    add("receive_block", schema_for!(ReceiveBlock));
+    add("change_block", schema_for!(ChangeBlock));
+
+    // Requests are all in one, told apart by their `action`.
+    add("rpc_request", schema_for!(RpcCommand));
+    add(
+        "account_balance_response",
+        schema_for!(AccountBalanceResponse),
+    );
+    add(
+        "account_block_count_response",
+        schema_for!(AccountBlockCountResponse),
+    );
+    add("account_get_response", schema_for!(AccountGetResponse));
+    add(
+        "account_history_response",
+        schema_for!(AccountHistoryResponse),
+    );
+    add("account_info_response", schema_for!(AccountInfoResponse));
+    add("account_key_response", schema_for!(AccountKeyResponse));
+    add(
+        "account_representative_response",
+        schema_for!(AccountRepresentativeResponse),
+    );
+    add(
+        "account_weight_response",
+        schema_for!(AccountWeightResponse),
+    );
+    add(
+        "accounts_balances_response",
+        schema_for!(AccountsBalancesResponse),
+    );
+    add(
+        "accounts_frontiers_response",
+        schema_for!(AccountsFrontiersResponse),
+    );
+    add(
+        "accounts_pending_response",
+        schema_for!(AccountsPendingResponse),
+    );
+    add(
+        "active_difficulty_response",
+        schema_for!(ActiveDifficultyResponse),
+    );
+    add(
+        "available_supply_response",
+        schema_for!(AvailableSupplyResponse),
+    );
+    add("block_account_response", schema_for!(BlockAccountResponse));
+    add("block_confirm_response", schema_for!(BlockConfirmResponse));
+    add("block_count_response", schema_for!(BlockCountResponse));
+    add("block_create_response", schema_for!(BlockCreateResponse));
+    add("block_info_response", schema_for!(BlockInfoResponse));
+    add("blocks_info_response", schema_for!(BlocksInfoResponse));
+    add(
+        "bootstrap_status_response",
+        schema_for!(BootstrapStatusResponse),
+    );
+    add(
+        "confirmation_quorum_response",
+        schema_for!(ConfirmationQuorumResponse),
+    );
+    add(
+        "difficulty_stats_response",
+        schema_for!(DifficultyStatsResponse),
+    );
+    add(
+        "dropped_blocks_response",
+        schema_for!(DroppedBlocksResponse),
+    );
+    add("frontiers_response", schema_for!(FrontiersResponse));
+    add("ledger_stats_response", schema_for!(LedgerStatsResponse));
+    add("peer_table_response", schema_for!(PeerTableResponse));
+    add(
+        "peer_telemetry_response",
+        schema_for!(PeerTelemetryResponse),
+    );
+    add("peers_response", schema_for!(PeersResponse));
+    add("pending_response", schema_for!(PendingResponse));
+    add("persist_response", schema_for!(PersistResponse));
+    add("process_response", schema_for!(ProcessResponse));
+    add(
+        "representatives_response",
+        schema_for!(RepresentativesResponse),
+    );
+    add("sync_status_response", schema_for!(SyncStatusResponse));
+    add("version_response", schema_for!(VersionResponse));
+    add("work_generate_response", schema_for!(WorkGenerateResponse));
+    add("work_validate_response", schema_for!(WorkValidateResponse));
+
+    add("websocket_incoming", schema_for!(Incoming));
+    add("websocket_outgoing", schema_for!(Outgoing));
+    add("frontier_event", schema_for!(FrontierEvent));
+    schemas
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn state_block() {
+        let schemas = export();
+        let json = serde_json::to_value(&schemas["state_block"]).unwrap();
+        let required = json["required"].as_array().unwrap();
+        for field in &["account", "previous", "representative", "balance", "link"] {
+            assert!(required.contains(&(*field).into()), "{}", field);
+        }
+        assert_eq!(
+            json["properties"]["account"]["$ref"],
+            "#/definitions/Address"
+        );
+        assert_eq!(
+            json["definitions"]["BlockHash"]["pattern"],
+            "^[0-9A-Fa-f]{64}$"
+        );
+    }
+}
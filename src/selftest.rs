@@ -0,0 +1,298 @@
+//! Known-answer tests and throughput measurements, for `feeless selftest`.
+//!
+//! The known answers come from the Nano documentation and the live network, so a build that
+//! passes them hashes, signs, derives keys and encodes addresses the same way as every other
+//! Nano implementation. This is mostly useful for cross-compiled builds, where a broken
+//! dependency can produce wrong results instead of failing to build.
+//!
+//! The throughput is measured on one thread, and used to estimate how long work generation and
+//! vanity searches would take on this machine.
+use crate::blocks::{BlockHash, Root};
+use crate::encoding::blake2b;
+use crate::{Address, Difficulty, HexBytes, Network, Private, Public, Seed, Signature, Work};
+use anyhow::{anyhow, Context};
+use std::convert::TryFrom;
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+type CheckFn = fn() -> anyhow::Result<()>;
+
+pub struct Check {
+    pub name: &'static str,
+    pub result: anyhow::Result<()>,
+}
+
+/// Run every known-answer test, carrying on after failures.
+pub fn known_answers() -> Vec<Check> {
+    let checks: [(&'static str, CheckFn); 6] = [
+        ("blake2b", check_blake2b),
+        ("seed derivation", check_derivation),
+        ("ed25519-blake2b public keys", check_public_key),
+        ("ed25519-blake2b signatures", check_signatures),
+        ("address encoding", check_address),
+        ("work validation", check_work),
+    ];
+    checks
+        .iter()
+        .map(|(name, check)| Check {
+            name,
+            result: check(),
+        })
+        .collect()
+}
+
+fn expect_eq<T: PartialEq + Display>(what: &str, got: T, expected: T) -> anyhow::Result<()> {
+    if got != expected {
+        return Err(anyhow!("{}: expected {} but got {}", what, expected, got));
+    }
+    Ok(())
+}
+
+/// Test vectors from RFC 7693, and the hash of the live genesis block.
+fn check_blake2b() -> anyhow::Result<()> {
+    expect_eq(
+        "BLAKE2b-512 of \"abc\"",
+        hex::encode(blake2b(64, b"abc")),
+        "ba80a53f981c4d0d6a2797b69f12f6e94c212f14685ac4b74b12bb6fdbffa2d1\
+         7d87c5392aab792dc252d5de4533cc9518d38aa8dbf1925ab92386edd4009923"
+            .into(),
+    )?;
+    expect_eq(
+        "BLAKE2b-256 of nothing",
+        hex::encode(blake2b(32, b"")),
+        "0e5751c026e543b2e8ab2eb06099daa1d1e5df47778f7787faab45cdf12fe3a8".into(),
+    )?;
+    let network = Network::Live;
+    expect_eq(
+        "Genesis block hash",
+        network.genesis_block().hash()?,
+        &network.genesis_hash(),
+    )
+}
+
+/// From the key derivation example in the Nano documentation.
+fn check_derivation() -> anyhow::Result<()> {
+    let private = Seed::zero().derive(0);
+    expect_eq(
+        "Private key of the zero seed at index 0",
+        private.to_string(),
+        "9F0E444C69F77A49BD0BE89DB92C38FE713E0963165CCA12FAF5712D7657120F".into(),
+    )
+}
+
+fn check_public_key() -> anyhow::Result<()> {
+    expect_eq(
+        "Public key of index 0 of the zero seed",
+        Seed::zero().derive(0).to_public()?.to_string(),
+        "C008B814A7D269A1FA3C6528B19201A24D797912DB9996FF02A1FF356E45552B".into(),
+    )?;
+    // A result of 3B6A27BC... would mean sha512 is used instead of blake2b.
+    let zero = Private::try_from([0u8; Private::LEN].as_ref())?;
+    expect_eq(
+        "Public key of the zero private key",
+        zero.to_public()?.to_string(),
+        "19D3D919475DEED4696B5D13018151D1AF88B2BD3BCFF048B45031C1F36D1858".into(),
+    )
+}
+
+/// The signature of the live genesis block, and a round trip with a signature of our own.
+fn check_signatures() -> anyhow::Result<()> {
+    let genesis = Network::Live.genesis_block();
+    genesis
+        .verify_signature(genesis.account())
+        .context("Genesis block signature")?;
+
+    let private = Seed::zero().derive(0);
+    let public = private.to_public()?;
+    let signature = private.sign(b"feeless")?;
+    public
+        .verify(b"feeless", &signature)
+        .context("Our own signature")?;
+    if public.verify(b"feeles", &signature).is_ok() {
+        return Err(anyhow!("A signature verified for the wrong message"));
+    }
+    let zero = Signature::try_from([0u8; Signature::LEN].as_ref())?;
+    if public.verify(b"feeless", &zero).is_ok() {
+        return Err(anyhow!("A zero signature verified"));
+    }
+    Ok(())
+}
+
+fn check_address() -> anyhow::Result<()> {
+    let address = "nano_3i1aq1cchnmbn9x5rsbap8b15akfh7wj7pwskuzi7ahz8oq6cobd99d4r3b7";
+    let public =
+        Public::from_str("C008B814A7D269A1FA3C6528B19201A24D797912DB9996FF02A1FF356E45552B")?;
+    expect_eq(
+        "Address",
+        Address::from(&public).to_string(),
+        address.into(),
+    )?;
+    expect_eq(
+        "Public key of the address",
+        Address::from_str(address)?.to_public(),
+        public,
+    )?;
+    // The last character is part of the checksum.
+    let bad_checksum = format!("{}8", &address[..address.len() - 1]);
+    if Address::from_str(&bad_checksum).is_ok() {
+        return Err(anyhow!("An address with a bad checksum was accepted"));
+    }
+    Ok(())
+}
+
+/// The first fixture of the work tests.
+fn check_work() -> anyhow::Result<()> {
+    let root = Root::from(BlockHash::from_str(
+        "2387767168f9453db0eca227c79d7e7a31b78cafb58bd9cdee630881c70979b8",
+    )?);
+    let work = Work::from_hex("c3f097857cc7106b")?;
+    expect_eq(
+        "Work difficulty",
+        work.difficulty(&root)?.as_u64(),
+        0xfffffff867b3146b,
+    )?;
+    if !work.verify(&root, &Difficulty::normal())? {
+        return Err(anyhow!("Valid work was rejected"));
+    }
+    if Work::zero().verify(&root, &Difficulty::normal())? {
+        return Err(anyhow!("Zero work was accepted"));
+    }
+    Ok(())
+}
+
+/// How many operations of each kind one thread can do per second.
+#[derive(Debug, Clone)]
+pub struct Throughput {
+    /// Seed to address, as done by each attempt of a vanity search.
+    pub derivations: f64,
+    pub signatures: f64,
+    pub verifications: f64,
+    /// Work hashes, as done by each attempt of work generation.
+    pub work_hashes: f64,
+    pub threads: usize,
+}
+
+impl Throughput {
+    /// Measure each operation for about `duration`.
+    pub fn measure(duration: Duration) -> anyhow::Result<Self> {
+        let seed = Seed::random();
+        let mut index = 0;
+        let derivations = per_second(duration, || {
+            index += 1;
+            seed.derive(index).to_address()?;
+            Ok(())
+        })?;
+
+        let private = seed.derive(0);
+        let public = private.to_public()?;
+        let message = [0u8; 32];
+        let signatures = per_second(duration, || {
+            private.sign(&message)?;
+            Ok(())
+        })?;
+        let signature = private.sign(&message)?;
+        let verifications = per_second(duration, || Ok(public.verify(&message, &signature)?))?;
+
+        let root = Root::from(BlockHash::zero());
+        let work = Work::random();
+        let work_hashes = per_second(duration, || {
+            work.difficulty(&root)?;
+            Ok(())
+        })?;
+
+        Ok(Self {
+            derivations,
+            signatures,
+            verifications,
+            work_hashes,
+            threads: num_cpus::get(),
+        })
+    }
+
+    /// Average time to find work for `difficulty` using every thread.
+    pub fn work_time(&self, difficulty: &Difficulty) -> Duration {
+        let attempts = difficulty.expected_attempts();
+        Duration::from_secs_f64(attempts / (self.work_hashes * self.threads as f64))
+    }
+
+    /// Average time for a vanity search for `characters` fixed characters using every thread.
+    pub fn vanity_time(&self, characters: u32) -> Duration {
+        let attempts = 32f64.powi(characters as i32);
+        Duration::from_secs_f64(attempts / (self.derivations * self.threads as f64))
+    }
+}
+
+impl Display for Throughput {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Per thread, with {} threads:", self.threads)?;
+        writeln!(f, "  Key derivations: {:.0}/s", self.derivations)?;
+        writeln!(f, "  Signatures: {:.0}/s", self.signatures)?;
+        writeln!(f, "  Verifications: {:.0}/s", self.verifications)?;
+        writeln!(f, "  Work hashes: {:.0}/s", self.work_hashes)?;
+        writeln!(f, "Estimates using every thread:")?;
+        writeln!(
+            f,
+            "  Send or change work: {:.1?}",
+            self.work_time(&Difficulty::normal())
+        )?;
+        writeln!(
+            f,
+            "  Receive work: {:.1?}",
+            self.work_time(&Difficulty::receive())
+        )?;
+        for characters in &[4, 6] {
+            writeln!(
+                f,
+                "  Vanity address with {} characters: {:.1?}",
+                characters,
+                self.vanity_time(*characters)
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Call `f` repeatedly for about `duration`, checking the clock every so often.
+fn per_second(
+    duration: Duration,
+    mut f: impl FnMut() -> anyhow::Result<()>,
+) -> anyhow::Result<f64> {
+    const BATCH: usize = 64;
+    let started = Instant::now();
+    let mut count = 0;
+    while started.elapsed() < duration {
+        for _ in 0..BATCH {
+            f()?;
+        }
+        count += BATCH;
+    }
+    Ok(count as f64 / started.elapsed().as_secs_f64())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn known_answers_pass() {
+        for check in known_answers() {
+            assert!(check.result.is_ok(), "{}: {:?}", check.name, check.result);
+        }
+    }
+
+    #[test]
+    fn estimates() {
+        let throughput = Throughput {
+            derivations: 1000.,
+            signatures: 0.,
+            verifications: 0.,
+            work_hashes: 1_000_000.,
+            threads: 2,
+        };
+        // Receive work takes 2^23 attempts on average.
+        let receive = throughput.work_time(&Difficulty::receive()).as_secs_f64();
+        assert!((receive - 4.194304).abs() < 0.001, "{}", receive);
+        assert_eq!(throughput.vanity_time(2), Duration::from_secs_f64(0.512));
+    }
+}
@@ -0,0 +1,236 @@
+//! Account balances at a point in time, and the net flow of each account between two of them.
+//!
+//! A [Snapshot] is taken from a node database, or over RPC for a given list of accounts, and
+//! saved as JSON. Comparing two snapshots with [Snapshot::diff] gives how much went in or out
+//! of each account in between, e.g. to audit the movements between the cold and hot wallets of
+//! an exchange. An account missing from a snapshot counts as having a zero balance.
+use crate::{Address, Raw};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fmt::{Display, Formatter};
+use std::path::Path;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub taken: DateTime<Utc>,
+    pub balances: HashMap<Address, Raw>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Direction {
+    In,
+    Out,
+}
+
+/// The change in balance of an account between two snapshots.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Flow {
+    pub account: Address,
+    pub before: Raw,
+    pub after: Raw,
+    pub direction: Direction,
+
+    /// How much the balance went up or down by.
+    pub amount: Raw,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SnapshotDiff {
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+
+    /// Accounts whose balance changed, biggest change first.
+    pub flows: Vec<Flow>,
+
+    pub total_in: Raw,
+    pub total_out: Raw,
+}
+
+impl Snapshot {
+    pub fn new(balances: HashMap<Address, Raw>) -> Self {
+        Self {
+            taken: Utc::now(),
+            balances,
+        }
+    }
+
+    /// The balance of every account in a node database.
+    #[cfg(feature = "node")]
+    pub async fn from_state(state: &crate::node::ArcState) -> anyhow::Result<Self> {
+        let mut balances = HashMap::new();
+        for account in state.accounts().await? {
+            let hash = match state.get_latest_block_hash_for_account(&account).await? {
+                Some(hash) => hash,
+                None => continue,
+            };
+            let block = state
+                .get_block_by_hash(&hash)
+                .await?
+                .ok_or_else(|| anyhow::anyhow!("Missing frontier {:?}", hash))?;
+            balances.insert(account.to_address(), block.balance().to_owned());
+        }
+        Ok(Self::new(balances))
+    }
+
+    /// The balances of `accounts` from an RPC server, asked for in batches.
+    #[cfg(feature = "rpc_client")]
+    pub async fn from_rpc(
+        client: &crate::rpc::client::RPCClient,
+        accounts: &[Address],
+    ) -> anyhow::Result<Self> {
+        use crate::rpc::calls::AccountsBalancesRequest;
+        use crate::rpc::client::RPCRequest;
+
+        const BATCH: usize = 1000;
+        let mut balances = HashMap::new();
+        for batch in accounts.chunks(BATCH) {
+            let response = (&AccountsBalancesRequest::new(batch.to_vec()))
+                .call(client)
+                .await?;
+            for (account, entry) in response.balances {
+                balances.insert(account, entry.balance);
+            }
+        }
+        Ok(Self::new(balances))
+    }
+
+    pub async fn load(path: &Path) -> anyhow::Result<Self> {
+        let data = tokio::fs::read(path).await?;
+        Ok(serde_json::from_slice(&data)?)
+    }
+
+    pub async fn save(&self, path: &Path) -> anyhow::Result<()> {
+        Ok(tokio::fs::write(path, serde_json::to_vec_pretty(self)?).await?)
+    }
+
+    /// How each account changed from this snapshot to a `later` one.
+    pub fn diff(&self, later: &Snapshot) -> SnapshotDiff {
+        let accounts: HashSet<&Address> =
+            self.balances.keys().chain(later.balances.keys()).collect();
+        let mut flows = vec![];
+        let mut total_in = 0u128;
+        let mut total_out = 0u128;
+        for account in accounts {
+            let before = self
+                .balances
+                .get(account)
+                .cloned()
+                .unwrap_or_else(Raw::zero);
+            let after = later
+                .balances
+                .get(account)
+                .cloned()
+                .unwrap_or_else(Raw::zero);
+            let (direction, amount) = if after > before {
+                (Direction::In, after.to_u128() - before.to_u128())
+            } else if after < before {
+                (Direction::Out, before.to_u128() - after.to_u128())
+            } else {
+                continue;
+            };
+            match direction {
+                Direction::In => total_in += amount,
+                Direction::Out => total_out += amount,
+            }
+            flows.push(Flow {
+                account: account.to_owned(),
+                before,
+                after,
+                direction,
+                amount: Raw::from(amount),
+            });
+        }
+        flows.sort_by(|a, b| {
+            b.amount
+                .to_u128()
+                .cmp(&a.amount.to_u128())
+                .then_with(|| a.account.to_string().cmp(&b.account.to_string()))
+        });
+
+        SnapshotDiff {
+            from: self.taken,
+            to: later.taken,
+            flows,
+            total_in: Raw::from(total_in),
+            total_out: Raw::from(total_out),
+        }
+    }
+}
+
+impl Display for SnapshotDiff {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "From {} to {}",
+            self.from.to_rfc3339(),
+            self.to.to_rfc3339()
+        )?;
+        for flow in &self.flows {
+            let sign = match flow.direction {
+                Direction::In => "+",
+                Direction::Out => "-",
+            };
+            writeln!(
+                f,
+                "{} {}{} Nano ({} -> {} Nano)",
+                flow.account,
+                sign,
+                flow.amount.to_nano(),
+                flow.before.to_nano(),
+                flow.after.to_nano()
+            )?;
+        }
+        writeln!(
+            f,
+            "{} accounts changed, {} Nano in, {} Nano out",
+            self.flows.len(),
+            self.total_in.to_nano(),
+            self.total_out.to_nano()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Seed;
+
+    fn address(index: u32) -> Address {
+        Seed::zero().derive(index).to_address().unwrap()
+    }
+
+    #[test]
+    fn net_flows() {
+        let mut before = HashMap::new();
+        before.insert(address(0), Raw::from(100));
+        before.insert(address(1), Raw::from(50));
+        before.insert(address(2), Raw::from(7));
+        let mut after = HashMap::new();
+        after.insert(address(0), Raw::from(30));
+        after.insert(address(1), Raw::from(50));
+        after.insert(address(2), Raw::from(7));
+        after.insert(address(3), Raw::from(60));
+
+        let diff = Snapshot::new(before).diff(&Snapshot::new(after));
+        assert_eq!(diff.flows.len(), 2);
+        assert_eq!(diff.flows[0].account, address(0));
+        assert_eq!(diff.flows[0].direction, Direction::Out);
+        assert_eq!(diff.flows[0].amount, Raw::from(70));
+        assert_eq!(diff.flows[1].account, address(3));
+        assert_eq!(diff.flows[1].direction, Direction::In);
+        assert_eq!(diff.flows[1].before, Raw::zero());
+        assert_eq!(diff.total_in, Raw::from(60));
+        assert_eq!(diff.total_out, Raw::from(70));
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut balances = HashMap::new();
+        balances.insert(address(0), Raw::from(100));
+        let snapshot = Snapshot::new(balances);
+        let json = serde_json::to_string(&snapshot).unwrap();
+        assert_eq!(serde_json::from_str::<Snapshot>(&json).unwrap(), snapshot);
+    }
+}
@@ -0,0 +1,218 @@
+use crate::Error;
+use std::env;
+use std::str::FromStr;
+
+/// How to group digits and what to separate the fraction with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Locale {
+    /// `1234567.891`, as amounts are written in JSON and to CSV files.
+    Plain,
+
+    /// `1,234,567.891`
+    En,
+
+    /// `1.234.567,891`
+    De,
+
+    /// `1 234 567,891`, with a narrow no-break space.
+    Fr,
+
+    /// `1'234'567.891`
+    Ch,
+}
+
+impl Locale {
+    /// The locale from `LC_ALL`, `LC_NUMERIC` or `LANG`, e.g. `de_DE.UTF-8`, falling back to
+    /// [Locale::Plain] when none of them are set to one we know.
+    pub fn from_env() -> Self {
+        ["LC_ALL", "LC_NUMERIC", "LANG"]
+            .iter()
+            .filter_map(|var| env::var(var).ok())
+            .find(|value| !value.is_empty())
+            .and_then(|value| Locale::from_str(&value).ok())
+            .unwrap_or(Locale::Plain)
+    }
+
+    fn separators(&self) -> (Option<&'static str>, &'static str) {
+        match self {
+            Locale::Plain => (None, "."),
+            Locale::En => (Some(","), "."),
+            Locale::De => (Some("."), ","),
+            Locale::Fr => (Some("\u{202f}"), ","),
+            Locale::Ch => (Some("'"), "."),
+        }
+    }
+}
+
+/// Either the name of a locale, e.g. `en`, or a POSIX locale like `de_CH.UTF-8`.
+impl FromStr for Locale {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.split('.').next().unwrap_or_default().to_lowercase();
+        let (language, country) = match s.split_once(['_', '-']) {
+            Some((language, country)) => (language, Some(country)),
+            None => (s.as_str(), None),
+        };
+        Ok(match (language, country) {
+            ("plain" | "c" | "posix", _) => Locale::Plain,
+            ("ch", None) | ("de" | "fr" | "it", Some("ch")) => Locale::Ch,
+            ("en" | "ja" | "ko" | "zh" | "he" | "th", _) => Locale::En,
+            ("de" | "nl" | "it" | "es" | "pt" | "id" | "tr" | "da", _) => Locale::De,
+            ("fr" | "ru" | "pl" | "cs" | "sv" | "fi" | "nb" | "uk", _) => Locale::Fr,
+            _ => {
+                return Err(Error::InvalidLocale(format!(
+                    "{:?}, use plain, en, de, fr or ch",
+                    s
+                )))
+            }
+        })
+    }
+}
+
+/// Options for showing an amount to people, e.g. `1234567.891000000000000000000000000000` Mnano
+/// as `1,234,567.891`.
+///
+/// ```
+/// use feeless::units::{AmountFormat, Locale, Mnano};
+/// use std::str::FromStr;
+///
+/// # fn main() -> anyhow::Result<()> {
+/// let mnano = Mnano::from_str("1234567.891000000000000000000000000000")?;
+/// assert_eq!(mnano.format(&AmountFormat::new()), "1234567.891");
+///
+/// let format = AmountFormat::new().locale(Locale::En).decimals(2);
+/// assert_eq!(mnano.format(&format), "1,234,567.89");
+///
+/// let format = AmountFormat::new().locale(Locale::De).decimals(6).keep_zeros();
+/// assert_eq!(mnano.format(&format), "1.234.567,891000");
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AmountFormat {
+    decimals: Option<usize>,
+    keep_zeros: bool,
+    locale: Locale,
+}
+
+impl Default for AmountFormat {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AmountFormat {
+    /// Every decimal place that isn't a trailing zero, without digit grouping.
+    pub fn new() -> Self {
+        Self {
+            decimals: None,
+            keep_zeros: false,
+            locale: Locale::Plain,
+        }
+    }
+
+    /// Show at most `decimals` decimal places. Extra places are cut off rather than rounded, so
+    /// that a balance is never shown as more than it is.
+    pub fn decimals(mut self, decimals: usize) -> Self {
+        self.decimals = Some(decimals);
+        self
+    }
+
+    /// Pad the fraction with zeros up to [AmountFormat::decimals] places, instead of trimming
+    /// trailing zeros.
+    pub fn keep_zeros(mut self) -> Self {
+        self.keep_zeros = true;
+        self
+    }
+
+    pub fn locale(mut self, locale: Locale) -> Self {
+        self.locale = locale;
+        self
+    }
+
+    /// Format a plain decimal number, e.g. `-1234.5000`.
+    pub fn format_decimal(&self, number: &str) -> String {
+        let (sign, number) = match number.strip_prefix('-') {
+            Some(number) => ("-", number),
+            None => ("", number),
+        };
+        let (integer, fraction) = number.split_once('.').unwrap_or((number, ""));
+
+        let mut fraction = fraction.to_owned();
+        if let Some(decimals) = self.decimals {
+            fraction.truncate(decimals);
+            if self.keep_zeros {
+                while fraction.len() < decimals {
+                    fraction.push('0');
+                }
+            }
+        }
+        if !self.keep_zeros {
+            fraction.truncate(fraction.trim_end_matches('0').len());
+        }
+
+        let (group, point) = self.locale.separators();
+        let integer = match group {
+            Some(group) => {
+                let digits: Vec<char> = integer.chars().collect();
+                digits
+                    .rchunks(3)
+                    .rev()
+                    .map(|chunk| chunk.iter().collect::<String>())
+                    .collect::<Vec<_>>()
+                    .join(group)
+            }
+            None => integer.to_owned(),
+        };
+
+        // Nothing was left to show of a negative amount, e.g. -0.0001 to 2 places.
+        let sign = if integer.chars().all(|c| c == '0') && fraction.chars().all(|c| c == '0') {
+            ""
+        } else {
+            sign
+        };
+        if fraction.is_empty() {
+            format!("{}{}", sign, integer)
+        } else {
+            format!("{}{}{}{}", sign, integer, point, fraction)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn format() {
+        let plain = AmountFormat::new();
+        assert_eq!(plain.format_decimal("1234567.891000"), "1234567.891");
+        assert_eq!(plain.format_decimal("1.000"), "1");
+        assert_eq!(plain.format_decimal("0"), "0");
+
+        let en = AmountFormat::new().locale(Locale::En);
+        assert_eq!(en.format_decimal("1234567.891000"), "1,234,567.891");
+        assert_eq!(en.format_decimal("123"), "123");
+        assert_eq!(en.format_decimal("-1234"), "-1,234");
+
+        let two = en.clone().decimals(2);
+        assert_eq!(two.format_decimal("9.999"), "9.99");
+        assert_eq!(two.format_decimal("9.5"), "9.5");
+        assert_eq!(two.format_decimal("-0.001"), "0");
+        assert_eq!(two.keep_zeros().format_decimal("9.5"), "9.50");
+
+        let fr = AmountFormat::new().locale(Locale::Fr);
+        assert_eq!(fr.format_decimal("1234.5"), "1\u{202f}234,5");
+    }
+
+    #[test]
+    fn locales() {
+        assert_eq!(Locale::from_str("en_US.UTF-8").unwrap(), Locale::En);
+        assert_eq!(Locale::from_str("de_DE.UTF-8").unwrap(), Locale::De);
+        assert_eq!(Locale::from_str("de_CH").unwrap(), Locale::Ch);
+        assert_eq!(Locale::from_str("fr").unwrap(), Locale::Fr);
+        assert_eq!(Locale::from_str("C").unwrap(), Locale::Plain);
+        assert!(Locale::from_str("xx_XX").is_err());
+    }
+}
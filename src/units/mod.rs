@@ -1,6 +1,7 @@
 //! Units of Nano, i.e.
 //! [Raw],
 //! [Mnano] (10<sup>30</sup>),
+//! [Knano] (10<sup>27</sup>),
 //! [Nano] (10<sup>24</sup>).
 //!
 //! See https://docs.nano.org/protocol-design/distribution-and-units/#unit-dividers for the unit
@@ -54,6 +55,36 @@
 //! assert_eq!(nano, Nano::new(1));
 //! ```
 //!
+//! # Checked arithmetic
+//! The arithmetic operators never fail, so an amount can go negative or above [u128::MAX] raw
+//! along the way. `checked_add`, `checked_sub` and `checked_mul` instead return `None` when the
+//! result isn't a valid amount of [Raw], which is what you want when working out balances.
+//!
+//! ## Example
+//! ```
+//! use feeless::units::{Knano, Mnano};
+//! use std::str::FromStr;
+//!
+//! # fn main() -> anyhow::Result<()> {
+//! let balance = Mnano::from_str("1.5")?;
+//! let sent = Knano::new(500).to_mnano();
+//! assert_eq!(balance.checked_sub(&sent), Some(Mnano::new(1)));
+//! assert_eq!(sent.checked_sub(&balance), None);
+//! assert_eq!(sent.checked_mul(3), Some(balance));
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! # Serde
+//! Amounts are serialized as decimal strings, e.g. `"1.5"` for [Mnano], as JSON numbers can't
+//! hold every amount exactly. Integers are accepted when deserializing too. For [Raw], use
+//! [serialize_to_integer] and [deserialize_from_integer] when an API wants a number.
+//!
+//! # Formatting
+//! [Display] shows every decimal place that the number has, which after a conversion from raw is
+//! usually a long tail of zeros. Use [AmountFormat] to show amounts to people, with a number of
+//! decimal places and the digit grouping of a [Locale].
+//!
 //! # Working with floats (f32, f64)
 //! The general recommendation is to never use floats when dealing with money due to inaccuracies
 //! with floating point precision. You can however do it with [BigDecimal]—see the example below.
@@ -83,15 +114,19 @@
 //! # Ok(())
 //! # }
 //! ```
+mod format;
 pub(crate) mod raw;
 
 use crate::Error;
 use bigdecimal::BigDecimal;
 use bigdecimal::ToPrimitive;
 use doc_comment::doc_comment;
+pub use format::{AmountFormat, Locale};
 use once_cell::sync::Lazy;
-pub use raw::Raw;
+pub use raw::{deserialize_from_integer, serialize_to_integer, Raw};
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
 use std::convert::TryFrom;
+use std::fmt::{Display, Formatter};
 use std::str::FromStr;
 
 /// This macro creates a struct to handle a specific denomination with arithmetic and conversions
@@ -165,6 +200,10 @@ See the [module documentation](crate::units) for more information as this is gen
                 Mnano::new_with_raw(self.to_raw_big_decimal())
             }
 
+            pub fn to_knano(&self) -> Knano {
+                Knano::new_with_raw(self.to_raw_big_decimal())
+            }
+
             pub fn to_nano(&self) -> Nano {
                 Nano::new_with_raw(self.to_raw_big_decimal())
             }
@@ -206,11 +245,53 @@ See the [module documentation](crate::units) for more information as this is gen
             pub fn to_raw_big_decimal(&self) -> BigDecimal {
                 &self.0 * &*Self::lazy_multiplier()
             }
+
+            /// The amount for people to read. See [AmountFormat].
+            pub fn format(&self, format: &AmountFormat) -> String {
+                format.format_decimal(&self.0.to_string())
+            }
+
+            /// Adds `rhs`, returning `None` if the sum isn't a valid amount of [Raw].
+            pub fn checked_add(&self, rhs: &Self) -> Option<Self> {
+                Self::new(&self.0 + &rhs.0).checked()
+            }
+
+            /// Subtracts `rhs`, returning `None` if the result is negative.
+            pub fn checked_sub(&self, rhs: &Self) -> Option<Self> {
+                Self::new(&self.0 - &rhs.0).checked()
+            }
+
+            /// Multiplies by `rhs`, returning `None` if the product isn't a valid amount of [Raw].
+            pub fn checked_mul<T: Into<BigDecimal>>(&self, rhs: T) -> Option<Self> {
+                Self::new(&self.0 * rhs.into()).checked()
+            }
+
+            fn checked(self) -> Option<Self> {
+                self.to_raw().ok().map(|_| self)
+            }
+        }
+
+        impl Serialize for $struct_name {
+            fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+            where
+                S: Serializer,
+            {
+                serializer.serialize_str(&self.0.to_string())
+            }
         }
 
-        impl ToString for $struct_name {
-            fn to_string(&self) -> String {
-                self.0.to_string()
+        impl<'de> Deserialize<'de> for $struct_name {
+            fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+            where
+                D: Deserializer<'de>,
+            {
+                deserializer.deserialize_any(DecimalVisitor).map(Self)
+            }
+        }
+
+        impl Display for $struct_name {
+            fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+                write!(f, "{}", self.0)
             }
         }
 
@@ -297,9 +378,77 @@ See the [module documentation](crate::units) for more information as this is gen
 }
 
 unit!(Mnano, 30);
+unit!(Knano, 27);
 unit!(Nano, 24);
 unit!(UnboundedRaw, 0);
 
+/// Deserializes a decimal string or an integer. Floats are refused as they can't be exact.
+struct DecimalVisitor;
+
+impl<'de> de::Visitor<'de> for DecimalVisitor {
+    type Value = BigDecimal;
+
+    fn expecting(&self, f: &mut Formatter) -> std::fmt::Result {
+        write!(f, "a decimal string or an integer")
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<Self::Value, E> {
+        BigDecimal::from_str(v).map_err(E::custom)
+    }
+
+    fn visit_i64<E: de::Error>(self, v: i64) -> Result<Self::Value, E> {
+        Ok(BigDecimal::from(v))
+    }
+
+    fn visit_u64<E: de::Error>(self, v: u64) -> Result<Self::Value, E> {
+        Ok(BigDecimal::from(v))
+    }
+
+    fn visit_u128<E: de::Error>(self, v: u128) -> Result<Self::Value, E> {
+        self.visit_str(&v.to_string())
+    }
+
+    fn visit_f64<E: de::Error>(self, v: f64) -> Result<Self::Value, E> {
+        Err(E::custom(format!(
+            "{} is a float, use a string for fractional or very big amounts",
+            v
+        )))
+    }
+}
+
+/// Parse an amount with a unit suffix into [Raw], e.g. `10mnano`, `2.5nano` or `1000raw`.
+///
+/// A number without a suffix is in raw. The older names `mrai`, `krai` and `rai` work too.
+pub fn parse_amount(s: &str) -> Result<Raw, Error> {
+    let s = s.trim();
+    let lower = s.to_lowercase();
+    let split = lower
+        .find(|c: char| c.is_ascii_alphabetic())
+        .unwrap_or(lower.len());
+    let (number, unit) = lower.split_at(split);
+    let number = BigDecimal::from_str(number.trim())
+        .map_err(|_| Error::InvalidAmount(format!("{:?} doesn't start with a number", s)))?;
+    let raw = match unit {
+        "mnano" | "mrai" => Mnano::new(number).to_raw_big_decimal(),
+        "knano" | "krai" => Knano::new(number).to_raw_big_decimal(),
+        "nano" | "rai" => Nano::new(number).to_raw_big_decimal(),
+        "" | "raw" => number,
+        unit => {
+            return Err(Error::InvalidAmount(format!(
+                "Unknown unit {:?}, use raw, nano, knano or mnano",
+                unit
+            )))
+        }
+    };
+    if !raw.is_integer() {
+        return Err(Error::InvalidAmount(format!(
+            "{:?} isn't a whole number of raw",
+            s
+        )));
+    }
+    Raw::try_from(&raw)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -318,6 +467,38 @@ mod tests {
             .to_mnano()
             .to_nano();
         assert_eq!(nano, Nano::new(1));
+
+        assert_eq!(Mnano::new(1).to_knano(), Knano::new(1000));
+        assert_eq!(Knano::new(1).to_nano(), Nano::new(1000));
+        assert_eq!(
+            Raw::new(1_000_000_000_000_000_000_000_000_000u128).to_knano(),
+            Knano::new(1)
+        );
+    }
+
+    #[test]
+    fn parse() {
+        assert_eq!(
+            parse_amount("10mnano").unwrap(),
+            Mnano::new(10).to_raw().unwrap()
+        );
+        assert_eq!(
+            parse_amount("2.5 Nano").unwrap().to_u128(),
+            2_500_000_000_000_000_000_000_000
+        );
+        assert_eq!(
+            parse_amount("3knano").unwrap(),
+            Nano::new(3000).to_raw().unwrap()
+        );
+        assert_eq!(
+            parse_amount("1Mrai").unwrap(),
+            Mnano::new(1).to_raw().unwrap()
+        );
+        assert_eq!(parse_amount("1000raw").unwrap(), Raw::from(1000));
+        assert_eq!(parse_amount("1000").unwrap(), Raw::from(1000));
+        assert!(parse_amount("1.5raw").is_err());
+        assert!(parse_amount("10xno").is_err());
+        assert!(parse_amount("nano").is_err());
     }
 
     #[test]
@@ -379,4 +560,32 @@ mod tests {
         n *= Nano::new(4);
         assert_eq!(n, Nano::new(8));
     }
+
+    #[test]
+    fn checked_arithmetic() {
+        let max = Raw::max().to_mnano();
+        assert_eq!(Nano::new(1).checked_add(&Nano::new(2)), Some(Nano::new(3)));
+        assert_eq!(max.checked_add(&Mnano::new_with_raw(1)), None);
+        assert_eq!(max.checked_add(&Mnano::new(0)), Some(max.clone()));
+
+        assert_eq!(Nano::new(2).checked_sub(&Nano::new(2)), Some(Nano::new(0)));
+        assert_eq!(Nano::new(1).checked_sub(&Nano::new(2)), None);
+
+        assert_eq!(Knano::new(2).checked_mul(3), Some(Knano::new(6)));
+        assert_eq!(max.checked_mul(2), None);
+        assert_eq!(Knano::new(2).checked_mul(-1), None);
+    }
+
+    #[test]
+    fn serde() {
+        let mnano = Mnano::from_str("1.5").unwrap();
+        let json = serde_json::to_string(&mnano).unwrap();
+        assert_eq!(json, r#""1.5""#);
+        assert_eq!(serde_json::from_str::<Mnano>(&json).unwrap(), mnano);
+
+        assert_eq!(serde_json::from_str::<Nano>("12").unwrap(), Nano::new(12));
+        assert_eq!(serde_json::from_str::<Nano>("-12").unwrap(), Nano::new(-12));
+        assert!(serde_json::from_str::<Nano>("1.5").is_err());
+        assert!(serde_json::from_str::<Nano>(r#""abc""#).is_err());
+    }
 }
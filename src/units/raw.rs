@@ -1,4 +1,4 @@
-use super::{Mnano, Nano, UnboundedRaw};
+use super::{AmountFormat, Knano, Mnano, Nano, UnboundedRaw};
 use crate::encoding::{expect_len, to_hex};
 use crate::Error;
 use bigdecimal::BigDecimal;
@@ -41,7 +41,7 @@ impl Raw {
             msg: String::from("Decoding hex raw"),
             source: e,
         })?;
-        Ok(Raw::try_from(vec.as_slice())?)
+        Raw::try_from(vec.as_slice())
     }
 
     pub fn zero() -> Self {
@@ -56,6 +56,10 @@ impl Raw {
         Mnano::from(self)
     }
 
+    pub fn to_knano(&self) -> Knano {
+        Knano::from(self)
+    }
+
     pub fn to_nano(&self) -> Nano {
         Nano::from(self)
     }
@@ -76,6 +80,11 @@ impl Raw {
         self.0
     }
 
+    /// The number of raw for people to read, e.g. with digit grouping. See [AmountFormat].
+    pub fn format(&self, format: &AmountFormat) -> String {
+        format.format_decimal(&self.0.to_string())
+    }
+
     pub fn to_big_decimal(&self) -> BigDecimal {
         // TODO: Don't know why from_u128() doesn't work.
         BigDecimal::from_str(&self.0.to_string()).unwrap()
@@ -88,6 +97,10 @@ impl Raw {
     pub fn checked_sub(&self, rhs: &Self) -> Option<Self> {
         self.0.checked_sub(rhs.0).map(Raw::from)
     }
+
+    pub fn checked_mul(&self, rhs: u128) -> Option<Self> {
+        self.0.checked_mul(rhs).map(Raw::from)
+    }
 }
 
 impl FromStr for Raw {
@@ -98,8 +111,9 @@ impl FromStr for Raw {
     }
 }
 
-/// This serializer and deserializer are for strings with decimal numbers. See serialize_to_hex
-/// and deserialize_from_hex if you expect your strings to be hex.
+/// This serializer is for strings with decimal numbers, and the deserializer also accepts
+/// integers. See serialize_to_hex and deserialize_from_hex if you expect your strings to be hex,
+/// or serialize_to_integer and deserialize_from_integer for numbers bigger than [u64::MAX].
 impl Serialize for Raw {
     fn serialize<S>(&self, serializer: S) -> Result<<S as Serializer>::Ok, <S as Serializer>::Error>
     where
@@ -114,8 +128,53 @@ impl<'de> Deserialize<'de> for Raw {
     where
         D: Deserializer<'de>,
     {
-        let s: &str = Deserialize::deserialize(deserializer)?;
-        Ok(Raw::from_str(s).map_err(de::Error::custom)?)
+        deserializer.deserialize_any(RawVisitor)
+    }
+}
+
+struct RawVisitor;
+
+impl<'de> de::Visitor<'de> for RawVisitor {
+    type Value = Raw;
+
+    fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "a decimal string or a positive integer of raw")
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<Self::Value, E> {
+        Raw::from_str(v).map_err(E::custom)
+    }
+
+    fn visit_i64<E: de::Error>(self, v: i64) -> Result<Self::Value, E> {
+        u128::try_from(v)
+            .map(Raw)
+            .map_err(|_| E::custom(format!("{} raw is negative", v)))
+    }
+
+    fn visit_u64<E: de::Error>(self, v: u64) -> Result<Self::Value, E> {
+        Ok(Raw(v.into()))
+    }
+
+    fn visit_u128<E: de::Error>(self, v: u128) -> Result<Self::Value, E> {
+        Ok(Raw(v))
+    }
+
+    fn visit_f64<E: de::Error>(self, v: f64) -> Result<Self::Value, E> {
+        Err(E::custom(format!(
+            "{} is a float, use a string or deserialize_from_integer for big amounts",
+            v
+        )))
+    }
+}
+
+#[cfg(feature = "schema")]
+impl schemars::JsonSchema for Raw {
+    fn schema_name() -> String {
+        "Raw".into()
+    }
+
+    fn json_schema(_: &mut schemars::gen::SchemaGenerator) -> schemars::schema::Schema {
+        crate::schema::string("An amount of raw, in decimal", "^[0-9]{1,39}$")
     }
 }
 
@@ -134,7 +193,26 @@ where
     D: Deserializer<'de>,
 {
     let s: &str = Deserialize::deserialize(deserializer)?;
-    Ok(Raw::from_hex(s).map_err(de::Error::custom)?)
+    Raw::from_hex(s).map_err(de::Error::custom)
+}
+
+pub fn serialize_to_integer<S>(
+    raw: &Raw,
+    serializer: S,
+) -> Result<<S as Serializer>::Ok, <S as Serializer>::Error>
+where
+    S: Serializer,
+{
+    serializer.serialize_u128(raw.0)
+}
+
+pub fn deserialize_from_integer<'de, D>(
+    deserializer: D,
+) -> Result<Raw, <D as Deserializer<'de>>::Error>
+where
+    D: Deserializer<'de>,
+{
+    u128::deserialize(deserializer).map(Raw)
 }
 
 impl Display for Raw {
@@ -336,6 +414,45 @@ mod tests {
         );
     }
 
+    #[test]
+    fn integer_json() {
+        assert_eq!(
+            serde_json::from_str::<Raw>("1000").unwrap(),
+            Raw::from(1000)
+        );
+        assert!(serde_json::from_str::<Raw>("-1").is_err());
+        assert!(serde_json::from_str::<Raw>("1.5").is_err());
+
+        #[derive(Serialize, Deserialize)]
+        struct IntegerRaw {
+            #[serde(
+                serialize_with = "serialize_to_integer",
+                deserialize_with = "deserialize_from_integer"
+            )]
+            integer_raw: Raw,
+        }
+        let integer_raw = IntegerRaw {
+            integer_raw: Raw::max(),
+        };
+        let json = serde_json::to_string(&integer_raw).unwrap();
+        assert_eq!(
+            json,
+            r#"{"integer_raw":340282366920938463463374607431768211455}"#
+        );
+        assert_eq!(
+            serde_json::from_str::<IntegerRaw>(&json)
+                .unwrap()
+                .integer_raw,
+            Raw::max()
+        );
+    }
+
+    #[test]
+    fn checked_mul() {
+        assert_eq!(Raw::from(3).checked_mul(4), Some(Raw::from(12)));
+        assert_eq!(Raw::max().checked_mul(2), None);
+    }
+
     #[test]
     fn negative_unbounded() {
         let mut v = Raw::zero().to_unbounded();
@@ -0,0 +1,204 @@
+//! Payment requests as `nano:` URIs, which wallets open to fill in a send.
+//!
+//! ```
+//! use feeless::{NanoUri, Raw};
+//! use std::str::FromStr;
+//!
+//! # fn main() -> anyhow::Result<()> {
+//! let address = "nano_3i1aq1cchnmbn9x5rsbap8b15akfh7wj7pwskuzi7ahz8oq6cobd99d4r3b7".parse()?;
+//! let uri = NanoUri::from((address, Raw::from(1000))).label("Coffee");
+//! assert_eq!(
+//!     uri.to_string(),
+//!     "nano:nano_3i1aq1cchnmbn9x5rsbap8b15akfh7wj7pwskuzi7ahz8oq6cobd99d4r3b7?amount=1000&label=Coffee"
+//! );
+//! assert_eq!(NanoUri::from_str(&uri.to_string())?, uri);
+//! # Ok(())
+//! # }
+//! ```
+use crate::{Address, Raw};
+use anyhow::{anyhow, Context};
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+const SCHEME: &str = "nano:";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct NanoUri {
+    pub address: Address,
+
+    /// In raw, as the URI has it.
+    pub amount: Option<Raw>,
+
+    /// Who is asking to be paid, e.g. a shop.
+    pub label: Option<String>,
+
+    /// What the payment is for.
+    pub message: Option<String>,
+}
+
+impl NanoUri {
+    pub fn new(address: Address) -> Self {
+        Self {
+            address,
+            amount: None,
+            label: None,
+            message: None,
+        }
+    }
+
+    pub fn amount(mut self, amount: Raw) -> Self {
+        self.amount = Some(amount);
+        self
+    }
+
+    pub fn label<S: Into<String>>(mut self, label: S) -> Self {
+        self.label = Some(label.into());
+        self
+    }
+
+    pub fn message<S: Into<String>>(mut self, message: S) -> Self {
+        self.message = Some(message.into());
+        self
+    }
+}
+
+impl From<Address> for NanoUri {
+    fn from(address: Address) -> Self {
+        Self::new(address)
+    }
+}
+
+impl From<(Address, Raw)> for NanoUri {
+    fn from((address, amount): (Address, Raw)) -> Self {
+        Self::new(address).amount(amount)
+    }
+}
+
+impl From<NanoUri> for Address {
+    fn from(uri: NanoUri) -> Self {
+        uri.address
+    }
+}
+
+impl Display for NanoUri {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}{}", SCHEME, self.address)?;
+        let mut params = vec![];
+        if let Some(amount) = &self.amount {
+            params.push(format!("amount={}", amount));
+        }
+        if let Some(label) = &self.label {
+            params.push(format!("label={}", encode(label)));
+        }
+        if let Some(message) = &self.message {
+            params.push(format!("message={}", encode(message)));
+        }
+        if !params.is_empty() {
+            write!(f, "?{}", params.join("&"))?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for NanoUri {
+    type Err = anyhow::Error;
+
+    /// Unknown parameters are ignored, so newer wallets can add their own.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let rest = s
+            .strip_prefix(SCHEME)
+            .ok_or_else(|| anyhow!("{:?} doesn't start with {:?}", s, SCHEME))?;
+        let (address, query) = match rest.split_once('?') {
+            Some((address, query)) => (address, query),
+            None => (rest, ""),
+        };
+        let mut uri = NanoUri::new(Address::from_str(address)?);
+        for param in query.split('&').filter(|p| !p.is_empty()) {
+            let (key, value) = param.split_once('=').unwrap_or((param, ""));
+            let value = decode(value).with_context(|| format!("Parameter {:?}", key))?;
+            match key {
+                "amount" => {
+                    uri.amount =
+                        Some(Raw::from_str(&value).with_context(|| format!("Amount {:?}", value))?)
+                }
+                "label" => uri.label = Some(value),
+                "message" => uri.message = Some(value),
+                _ => {}
+            }
+        }
+        Ok(uri)
+    }
+}
+
+/// Percent encode everything but the unreserved characters of RFC 3986.
+fn encode(s: &str) -> String {
+    s.bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                (b as char).to_string()
+            }
+            b => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+fn decode(s: &str) -> anyhow::Result<String> {
+    let mut bytes = Vec::with_capacity(s.len());
+    let mut iter = s.bytes();
+    while let Some(b) = iter.next() {
+        match b {
+            b'%' => {
+                let hex: Vec<u8> = iter.by_ref().take(2).collect();
+                let hex = std::str::from_utf8(&hex)?;
+                bytes.push(u8::from_str_radix(hex, 16).with_context(|| format!("%{}", hex))?);
+            }
+            b'+' => bytes.push(b' '),
+            b => bytes.push(b),
+        }
+    }
+    Ok(String::from_utf8(bytes)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ADDRESS: &str = "nano_3i1aq1cchnmbn9x5rsbap8b15akfh7wj7pwskuzi7ahz8oq6cobd99d4r3b7";
+
+    #[test]
+    fn round_trip() {
+        let uri = NanoUri::new(ADDRESS.parse().unwrap())
+            .amount(Raw::from(10u128.pow(30)))
+            .message("Order #12 & co");
+        let s = uri.to_string();
+        assert_eq!(
+            s,
+            format!(
+                "nano:{}?amount=1000000000000000000000000000000&message=Order%20%2312%20%26%20co",
+                ADDRESS
+            )
+        );
+        assert_eq!(NanoUri::from_str(&s).unwrap(), uri);
+
+        let bare = NanoUri::from_str(&format!("nano:{}", ADDRESS)).unwrap();
+        assert_eq!(bare, NanoUri::new(ADDRESS.parse().unwrap()));
+        let extra = NanoUri::from_str(&format!("nano:{}?label=A+B&foo=1", ADDRESS)).unwrap();
+        assert_eq!(extra.label.as_deref(), Some("A B"));
+    }
+
+    #[test]
+    fn conversions() {
+        let address: Address = ADDRESS.parse().unwrap();
+        let uri = NanoUri::from((address.to_owned(), Raw::from(5)));
+        assert_eq!(uri.to_string(), format!("nano:{}?amount=5", ADDRESS));
+        assert_eq!(Address::from(uri), address);
+        assert_eq!(NanoUri::from(address.to_owned()), NanoUri::new(address));
+    }
+
+    #[test]
+    fn bad() {
+        assert!(NanoUri::from_str(ADDRESS).is_err());
+        assert!(NanoUri::from_str(&format!("nano:{}?amount=1.5", ADDRESS)).is_err());
+        assert!(NanoUri::from_str(&format!("nano:{}?label=%G1", ADDRESS)).is_err());
+    }
+}
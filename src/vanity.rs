@@ -3,9 +3,12 @@ use crate::phrase::{Language, MnemonicType};
 use crate::{Address, Phrase, Private, Seed};
 use anyhow::anyhow;
 use regex::Regex;
+use std::fmt::{Display, Formatter};
 use std::sync::{Arc, RwLock};
 use std::thread;
+use std::time::{Duration, Instant};
 use tokio::sync::mpsc::{Receiver, Sender};
+use tokio_util::sync::CancellationToken;
 use tracing::{info, trace};
 
 #[derive(Clone)]
@@ -29,12 +32,15 @@ pub enum Secret {
 pub struct SecretResult {
     pub secret: Secret,
     pub address: Address,
-}
 
-impl SecretResult {
-    fn new(secret: Secret, address: Address) -> Self {
-        Self { secret, address }
-    }
+    /// The first pattern the address matched.
+    pub matched: Match,
+
+    /// The spelling of a [Match::Fuzzy] that matched, otherwise the same as `matched`.
+    pub hit: String,
+
+    /// The derivation index of `address` from the seed or phrase, or 0 for a private key.
+    pub index: u32,
 }
 
 #[derive(Clone, Copy)]
@@ -46,8 +52,11 @@ enum SearchOffset {
 #[derive(Clone)]
 pub struct Vanity {
     secret_type: SecretType,
-    matches: Match,
-    index: u32,
+    matches: Vec<Match>,
+
+    /// How many addresses to derive from each seed or phrase, from index 0.
+    indexes: u32,
+
     tasks: Option<usize>,
     search_offset: SearchOffset,
 
@@ -59,10 +68,18 @@ pub struct Vanity {
 
 impl Vanity {
     pub fn new(secret_type: SecretType, matches: Match) -> Self {
+        Self::any_of(secret_type, vec![matches])
+    }
+
+    /// Search for addresses matching any of `matches`, e.g. a list of words.
+    ///
+    /// Each attempt derives one address and checks it against every pattern, which is much faster
+    /// than a search for each pattern.
+    pub fn any_of(secret_type: SecretType, matches: Vec<Match>) -> Self {
         Self {
             secret_type,
             matches,
-            index: 0, // TODO: Make this a user option, maybe allow to scan up to N too.
+            indexes: 1,
             tasks: None,
             check_count: 10000,
             search_offset: SearchOffset::SkipFirstDigit,
@@ -75,6 +92,16 @@ impl Vanity {
         self
     }
 
+    /// Check the addresses at derivation indexes `0..n` of each seed or phrase, instead of only
+    /// index 0.
+    ///
+    /// Generating a phrase is much slower than deriving from it, so this checks many more
+    /// addresses a second in phrase mode, at the cost of the match being at a later index.
+    pub fn indexes(&mut self, n: u32) -> &mut Vanity {
+        self.indexes = n;
+        self
+    }
+
     /// Should the search include the first number after `nano_` (1 or 3)?
     pub fn include_first_digit(&mut self, v: bool) -> &mut Vanity {
         self.search_offset = if v {
@@ -88,8 +115,12 @@ impl Vanity {
     /// Spawn some tasks to try to find a vanity address.
     ///
     /// This returns a [Receiver] containing [SecretResult]s for each found address, and a
-    /// [Arc] [RwLock] counter of attempts.
-    pub async fn start(self) -> anyhow::Result<(Receiver<SecretResult>, Arc<RwLock<usize>>)> {
+    /// [Arc] [RwLock] counter of attempts, each being an address checked. The tasks stop when `cancel` is cancelled or the
+    /// receiver is dropped, after which the receiver returns `None`.
+    pub async fn start(
+        self,
+        cancel: &CancellationToken,
+    ) -> anyhow::Result<(Receiver<SecretResult>, Arc<RwLock<usize>>)> {
         self.validate()?;
         let cpus = num_cpus::get();
         let attempts = Arc::new(RwLock::new(0usize));
@@ -100,89 +131,207 @@ impl Vanity {
             let v = self.clone();
             let tx_ = tx.clone();
             let counter_ = attempts.clone();
+            let cancel_ = cancel.clone();
             thread::spawn(move || {
-                v.single_threaded_worker(tx_, counter_);
+                v.single_threaded_worker(tx_, counter_, cancel_);
             });
         }
         Ok((rx, attempts))
     }
 
-    pub fn validate(&self) -> anyhow::Result<()> {
-        let s = match &self.matches {
-            Match::StartOrEnd(s) => s,
-            Match::Start(s) => s,
-            Match::End(s) => s,
-            // TODO: Extract literals from regexp, or just ignore regexp characters (.$^{}[] etc)
-            Match::Regex(_) => return Ok(()),
+    /// Like [Vanity::start], also sending [Progress] every `interval`.
+    ///
+    /// Progress stops when `cancel` is cancelled or its receiver is dropped.
+    pub async fn start_with_progress(
+        self,
+        interval: Duration,
+        cancel: &CancellationToken,
+    ) -> anyhow::Result<(Receiver<SecretResult>, Receiver<Progress>)> {
+        let probability = self.probability();
+        let (rx, attempts) = self.start(cancel).await?;
+        let (tx, progress) = tokio::sync::mpsc::channel::<Progress>(16);
+        let cancel = cancel.clone();
+        let started = Instant::now();
+        tokio::spawn(async move {
+            let mut ticks =
+                tokio::time::interval_at(tokio::time::Instant::now() + interval, interval);
+            loop {
+                tokio::select! {
+                    _ = cancel.cancelled() => break,
+                    _ = ticks.tick() => {
+                        let attempts = *attempts.read().expect("Could not lock counter for reading");
+                        let progress = Progress::new(attempts, started.elapsed(), probability);
+                        if tx.send(progress).await.is_err() {
+                            break;
+                        }
+                    }
+                }
+            }
+            trace!("Exiting vanity progress task.");
+        });
+        Ok((rx, progress))
+    }
+
+    /// The chance of a single attempt matching any pattern, or `None` if there's a regular
+    /// expression.
+    ///
+    /// Each character after the first digit is one of 32, so every fixed character makes a match
+    /// 32 times less likely. The first digit is only ever `1` or `3`.
+    pub fn probability(&self) -> Option<f64> {
+        self.any_probability(&self.matches)
+    }
+
+    fn any_probability(&self, matches: &[Match]) -> Option<f64> {
+        let mut any = 0.;
+        for matches in matches {
+            let p = self.match_probability(matches)?;
+            // Rather than `1 - (1 - p)...`, which rounds tiny chances away.
+            any += p - any * p;
+        }
+        Some(any)
+    }
+
+    fn match_probability(&self, matches: &Match) -> Option<f64> {
+        let fixed = |chars: usize| 32f64.powi(-(chars as i32));
+        let (s, start, end) = match matches {
+            Match::StartOrEnd(s) => (s, true, true),
+            Match::Start(s) => (s, true, false),
+            Match::End(s) => (s, false, true),
+            Match::Regex(_) => return None,
+            Match::Fuzzy { expansions, .. } => return self.any_probability(expansions),
         };
+        let len = s.chars().count();
+        let at_start = match (self.search_offset, s.chars().next()) {
+            (SearchOffset::FirstDigit, Some('1')) | (SearchOffset::FirstDigit, Some('3')) => {
+                0.5 * fixed(len - 1)
+            }
+            (SearchOffset::FirstDigit, Some(_)) => 0.,
+            _ => fixed(len),
+        };
+        let at_end = fixed(len);
+        Some(match (start, end) {
+            (true, true) => at_start + at_end - at_start * at_end,
+            (true, false) => at_start,
+            _ => at_end,
+        })
+    }
+
+    pub fn validate(&self) -> anyhow::Result<()> {
+        if self.matches.is_empty() {
+            return Err(anyhow!("There are no patterns to search for."));
+        }
+        if self.indexes == 0 {
+            return Err(anyhow!("At least one index has to be checked."));
+        }
+        if self.indexes > 1 {
+            if let SecretType::Private = self.secret_type {
+                return Err(anyhow!(
+                    "Private keys don't have derivation indexes, only seeds and phrases do."
+                ));
+            }
+        }
         let re = regex::Regex::new(&format!("^[{}]*$", ALPHABET)).unwrap();
-        if re.is_match(s) {
-            Ok(())
-        } else {
-            Err(anyhow!("Your search won't ever match because it has characters that aren't valid. Valid characters: {}", ALPHABET))
+        for matches in &self.matches {
+            let s = match matches {
+                Match::StartOrEnd(s) => s,
+                Match::Start(s) => s,
+                Match::End(s) => s,
+                // TODO: Extract literals from regexp, or just ignore regexp characters (.$^{}[] etc)
+                Match::Regex(_) => continue,
+                // Only valid spellings are kept.
+                Match::Fuzzy { .. } => continue,
+            };
+            if !re.is_match(s) {
+                return Err(anyhow!("Your search for {:?} won't ever match because it has characters that aren't valid. Valid characters: {}", s, ALPHABET));
+            }
         }
+        Ok(())
     }
 
-    fn single_threaded_worker(&self, tx: Sender<SecretResult>, counter: Arc<RwLock<usize>>) {
-        while !tx.is_closed() {
+    fn single_threaded_worker(
+        &self,
+        tx: Sender<SecretResult>,
+        counter: Arc<RwLock<usize>>,
+        cancel: CancellationToken,
+    ) {
+        while !tx.is_closed() && !cancel.is_cancelled() {
             for _ in 0..self.check_count {
                 if let Some(result) = self.single_attempt() {
-                    if let Err(_) = tx.blocking_send(result) {
+                    if tx.blocking_send(result).is_err() {
                         trace!("Exiting vanity task due to closed channel while sending.");
                         return;
                     }
                 }
             }
             let mut c = counter.write().expect("Could not lock counter for writing");
-            *c += self.check_count;
+            *c += self.check_count * self.indexes as usize;
             drop(c);
         }
-        trace!("Exiting vanity task due to closed channel.");
+        trace!("Exiting vanity task due to closed channel or cancellation.");
     }
 
     fn single_attempt(&self) -> Option<SecretResult> {
-        let result = match &self.secret_type {
+        // These should never panic because the public keys come from legit private keys.
+        let (secret, addresses) = match &self.secret_type {
             SecretType::Seed => {
                 let seed = Seed::random();
-                // This should never panic because the public key comes from a legit private key.
-                let address = seed.derive(self.index).to_address().unwrap();
-                SecretResult::new(Secret::Seed(seed), address)
+                let addresses = (0..self.indexes)
+                    .map(|index| seed.derive(index).to_address().unwrap())
+                    .collect();
+                (Secret::Seed(seed), addresses)
             }
             SecretType::Private => {
                 let private = Private::random();
-                // This should never panic because the public key comes from a legit private key.
                 let address = private.to_address().unwrap();
-                SecretResult::new(Secret::Private(private), address)
+                (Secret::Private(private), vec![address])
             }
             SecretType::Phrase { language, words } => {
-                // This should never panic because the public key comes from a legit private key.
                 let phrase = Phrase::random(words.to_owned(), language.to_owned());
-                let address = phrase.to_private(0, "").unwrap().to_address().unwrap();
-                SecretResult::new(Secret::Phrase(phrase), address)
+                let addresses = phrase
+                    .to_privates(0..self.indexes, "")
+                    .unwrap()
+                    .iter()
+                    .map(|private| private.to_address().unwrap())
+                    .collect();
+                (Secret::Phrase(phrase), addresses)
             }
         };
 
-        let addr = &result.address.to_string();
         let offset = self.search_offset as usize;
-        let searchable = &addr[offset..];
-
-        let good = match &self.matches {
-            Match::StartOrEnd(s) => searchable.starts_with(s) || searchable.ends_with(s),
-            Match::Start(s) => searchable.starts_with(s),
-            Match::End(s) => searchable.ends_with(s),
-            Match::Regex(re) => re.is_match(searchable),
-        };
-
-        if good {
-            Some(result)
-        } else {
-            None
-        }
+        // The lowest index wins, being the first a wallet shows.
+        let (index, address, matched, hit) =
+            addresses
+                .into_iter()
+                .enumerate()
+                .find_map(|(index, address)| {
+                    let addr = address.to_string();
+                    let searchable = &addr[offset..];
+                    let (matched, hit) = self
+                        .matches
+                        .iter()
+                        .find_map(|matches| Some((matches, matches.hit(searchable)?)))?;
+                    Some((index, address, matched, hit.to_owned()))
+                })?;
+
+        Some(SecretResult {
+            hit,
+            secret,
+            address,
+            matched: matched.to_owned(),
+            index: index as u32,
+        })
     }
 
-    /// Block until all results are collected up to a size of `limit`.
-    pub async fn collect(self, mut limit: usize) -> anyhow::Result<Vec<SecretResult>> {
-        let (mut rx, _) = self.start().await?;
+    /// Block until all results are collected up to a size of `limit`, or until `cancel` is
+    /// cancelled, returning what was found so far.
+    pub async fn collect(
+        self,
+        mut limit: usize,
+        cancel: &CancellationToken,
+    ) -> anyhow::Result<Vec<SecretResult>> {
+        // Stop the tasks once we have enough, without cancelling the caller's token.
+        let done = cancel.child_token();
+        let (mut rx, _) = self.start(&done).await?;
         let mut collected = vec![];
         while let Some(result) = rx.recv().await {
             collected.push(result);
@@ -191,16 +340,111 @@ impl Vanity {
                 break;
             }
         }
+        done.cancel();
         Ok(collected)
     }
 }
 
-#[derive(Clone)]
+/// How a search started with [Vanity::start_with_progress] is going.
+#[derive(Debug, Clone)]
+pub struct Progress {
+    /// Addresses checked.
+    pub attempts: usize,
+    pub elapsed: Duration,
+
+    /// Attempts per second since the search started.
+    pub rate: f64,
+
+    /// The chance of each attempt matching, see [Vanity::probability].
+    pub probability: Option<f64>,
+
+    /// The expected time until the next match at the current rate.
+    ///
+    /// Attempts are independent, so this doesn't get shorter the longer a search has run.
+    pub eta: Option<Duration>,
+}
+
+impl Progress {
+    pub fn new(attempts: usize, elapsed: Duration, probability: Option<f64>) -> Self {
+        let rate = attempts as f64 / elapsed.as_secs_f64();
+        let eta = match probability {
+            Some(p) if p > 0. && rate > 0. && rate.is_finite() => {
+                Some(Duration::from_secs_f64(1. / (p * rate)))
+            }
+            _ => None,
+        };
+        Self {
+            attempts,
+            elapsed,
+            rate,
+            probability,
+            eta,
+        }
+    }
+}
+
+impl Display for Progress {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Attempted: {}, Rate: {:.0} attempts/s",
+            self.attempts, self.rate
+        )?;
+        match self.probability {
+            Some(p) if p > 0. => write!(f, ", Chance: 1 in {:.0}", 1. / p)?,
+            Some(_) => write!(f, ", Chance: none")?,
+            None => {}
+        }
+        if let Some(eta) = self.eta {
+            write!(f, ", ETA: {:.1?}", eta)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone)]
 pub enum Match {
     StartOrEnd(String),
     Start(String),
     End(String),
     Regex(Regex),
+
+    /// Any spelling of `query` in the address alphabet, see [Match::fuzzy].
+    Fuzzy {
+        query: String,
+        expansions: Vec<Match>,
+    },
+}
+
+/// The most spellings [Match::fuzzy] will search for.
+pub const MAX_EXPANSIONS: usize = 4096;
+
+/// The characters that can stand in for `c`, itself first, or `None` if only `c` itself can.
+///
+/// `0`, `2`, `l` and `v` can't appear in an address, so they're only ever replaced.
+fn lookalikes(c: char) -> Option<&'static str> {
+    Some(match c {
+        'a' => "a4",
+        '4' => "4a",
+        'b' => "b8",
+        '8' => "8b",
+        'e' => "e3",
+        '3' => "3e",
+        'g' => "g96",
+        '9' => "9g",
+        '6' => "6g",
+        'i' => "i1",
+        '1' => "1i",
+        'l' => "1i",
+        's' => "s5",
+        '5' => "5s",
+        't' => "t7",
+        '7' => "7t",
+        'o' | '0' => "o",
+        'z' | '2' => "z",
+        'v' => "u",
+        _ => return None,
+    })
 }
 
 impl Match {
@@ -217,9 +461,82 @@ impl Match {
     }
 
     pub fn regex(s: &str) -> anyhow::Result<Self> {
-        let r = regex::Regex::new(s.into())?;
+        let r = regex::Regex::new(s)?;
         Ok(Match::Regex(r))
     }
+
+    /// Match `matches` however it's spelt, ignoring case and swapping lookalike characters, e.g.
+    /// `nano` also matches `n4n0` as `n4no`, and `hello` matches `he11o`.
+    ///
+    /// `matches` is a [Match::Start], [Match::End] or [Match::StartOrEnd], and the spellings are
+    /// matched in the same place.
+    pub fn fuzzy(matches: Match) -> anyhow::Result<Self> {
+        let query = matches.as_str().to_lowercase();
+        let mut spellings = vec![String::new()];
+        for c in query.chars() {
+            let options = lookalikes(c).map_or_else(|| c.to_string(), String::from);
+            let valid: Vec<char> = options.chars().filter(|c| ALPHABET.contains(*c)).collect();
+            if valid.is_empty() {
+                return Err(anyhow!(
+                    "{:?} in {:?} has no lookalike in the address alphabet: {}",
+                    c,
+                    query,
+                    ALPHABET
+                ));
+            }
+            if spellings.len() * valid.len() > MAX_EXPANSIONS {
+                return Err(anyhow!(
+                    "{:?} has more than {} spellings, try a shorter word",
+                    query,
+                    MAX_EXPANSIONS
+                ));
+            }
+            spellings = spellings
+                .iter()
+                .flat_map(|spelling| valid.iter().map(move |c| format!("{}{}", spelling, c)))
+                .collect();
+        }
+
+        let place: fn(&str) -> Match = match matches {
+            Match::StartOrEnd(_) => Match::start_or_end,
+            Match::Start(_) => Match::start,
+            Match::End(_) => Match::end,
+            Match::Regex(_) | Match::Fuzzy { .. } => {
+                return Err(anyhow!("Only plain strings can be matched fuzzily"))
+            }
+        };
+        Ok(Match::Fuzzy {
+            query,
+            expansions: spellings.iter().map(|s| place(s)).collect(),
+        })
+    }
+
+    /// The string or regular expression being matched.
+    pub fn as_str(&self) -> &str {
+        match self {
+            Match::StartOrEnd(s) | Match::Start(s) | Match::End(s) => s,
+            Match::Regex(re) => re.as_str(),
+            Match::Fuzzy { query, .. } => query,
+        }
+    }
+
+    /// What matched in `searchable`, see [SecretResult::hit].
+    fn hit(&self, searchable: &str) -> Option<&str> {
+        let hit = match self {
+            Match::StartOrEnd(s) => searchable.starts_with(s) || searchable.ends_with(s),
+            Match::Start(s) => searchable.starts_with(s),
+            Match::End(s) => searchable.ends_with(s),
+            Match::Regex(re) => re.is_match(searchable),
+            Match::Fuzzy { expansions, .. } => {
+                return expansions.iter().find_map(|m| m.hit(searchable))
+            }
+        };
+        if hit {
+            Some(self.as_str())
+        } else {
+            None
+        }
+    }
 }
 
 #[cfg(test)]
@@ -230,7 +547,10 @@ mod tests {
     async fn vanitize_start_or_end() {
         let vanity = Vanity::new(SecretType::Seed, Match::start_or_end("g"));
         let limit = 20; // Should be enough for 1 in a million chance of this test failing.
-        let results = vanity.collect(limit).await.unwrap();
+        let results = vanity
+            .collect(limit, &CancellationToken::new())
+            .await
+            .unwrap();
         assert_eq!(results.len(), limit);
         let mut has_start = false;
         let mut has_end = false;
@@ -250,7 +570,7 @@ mod tests {
     #[tokio::test(flavor = "multi_thread")]
     async fn vanitize_start() {
         let results = Vanity::new(SecretType::Seed, Match::start("z"))
-            .collect(1)
+            .collect(1, &CancellationToken::new())
             .await
             .unwrap();
         assert_eq!(&results[0].address.to_string()[6..7], "z");
@@ -259,7 +579,7 @@ mod tests {
     #[tokio::test(flavor = "multi_thread")]
     async fn vanitize_end() {
         let results = Vanity::new(SecretType::Seed, Match::end("z"))
-            .collect(1)
+            .collect(1, &CancellationToken::new())
             .await
             .unwrap();
         assert!(&results[0].address.to_string().ends_with("z"));
@@ -268,7 +588,7 @@ mod tests {
     #[tokio::test(flavor = "multi_thread")]
     async fn vanitize_regex() {
         let results = Vanity::new(SecretType::Seed, Match::regex("z.*z.*z").unwrap())
-            .collect(1)
+            .collect(1, &CancellationToken::new())
             .await
             .unwrap();
         let addr = &results[0].address.to_string();
@@ -279,7 +599,7 @@ mod tests {
     #[tokio::test(flavor = "multi_thread")]
     async fn vanitize_private() {
         let results = Vanity::new(SecretType::Private, Match::end("zz"))
-            .collect(1)
+            .collect(1, &CancellationToken::new())
             .await
             .unwrap();
         let result = &results[0];
@@ -290,7 +610,7 @@ mod tests {
         if let Secret::Private(private) = &result.secret {
             assert_eq!(addr, &private.to_address().unwrap().to_string());
         } else {
-            assert!(false, "Did not get a private key");
+            panic!("Did not get a private key");
         }
     }
 
@@ -298,7 +618,7 @@ mod tests {
     async fn vanitize_first_digit() {
         let mut vanity = Vanity::new(SecretType::Private, Match::start("1z"));
         vanity.include_first_digit(true);
-        let results = vanity.collect(1).await.unwrap();
+        let results = vanity.collect(1, &CancellationToken::new()).await.unwrap();
         let result = &results[0];
 
         let addr = &result.address.to_string();
@@ -306,6 +626,82 @@ mod tests {
         assert_eq!(&addr[5..7], "1z");
     }
 
+    #[tokio::test(flavor = "multi_thread")]
+    async fn cancel() {
+        let cancel = CancellationToken::new();
+        let impossible = Vanity::new(SecretType::Seed, Match::start("zzzzzzzzzzzz"));
+        let (mut rx, _) = impossible.start(&cancel).await.unwrap();
+        cancel.cancel();
+        // Every task has stopped when the channel closes.
+        assert!(rx.recv().await.is_none());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn vanitize_indexes() {
+        let mut vanity = Vanity::new(SecretType::Seed, Match::end("z"));
+        vanity.indexes(16);
+        let results = vanity.collect(20, &CancellationToken::new()).await.unwrap();
+        for result in &results {
+            assert!(result.index < 16);
+            let seed = match &result.secret {
+                Secret::Seed(seed) => seed,
+                _ => panic!("Did not get a seed"),
+            };
+            let address = seed.derive(result.index).to_address().unwrap();
+            assert_eq!(address, result.address);
+            assert!(result.address.to_string().ends_with('z'));
+        }
+        // With 16 chances of 1 in 32 each, most seeds don't match at index 0.
+        assert!(results.iter().any(|r| r.index > 0));
+
+        let mut private = Vanity::new(SecretType::Private, Match::end("z"));
+        private.indexes(2);
+        assert!(private.validate().is_err());
+        assert!(Vanity::new(SecretType::Seed, Match::end("z"))
+            .indexes(0)
+            .validate()
+            .is_err());
+    }
+
+    #[test]
+    fn probability() {
+        let mut vanity = Vanity::new(SecretType::Seed, Match::start("zz"));
+        assert_eq!(vanity.probability(), Some(1. / 1024.));
+        vanity.include_first_digit(true);
+        assert_eq!(vanity.probability(), Some(0.));
+        let mut vanity = Vanity::new(SecretType::Seed, Match::start("3z"));
+        vanity.include_first_digit(true);
+        assert_eq!(vanity.probability(), Some(1. / 64.));
+
+        let p = Vanity::new(SecretType::Seed, Match::start_or_end("z"))
+            .probability()
+            .unwrap();
+        assert!((p - (2. / 32. - 1. / 1024.)).abs() < f64::EPSILON);
+        let regex = Match::regex("z").unwrap();
+        assert_eq!(Vanity::new(SecretType::Seed, regex).probability(), None);
+
+        let progress = Progress::new(1000, Duration::from_secs(2), Some(1. / 1024.));
+        assert_eq!(progress.rate, 500.);
+        assert_eq!(progress.eta, Some(Duration::from_secs_f64(2.048)));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn progress() {
+        let cancel = CancellationToken::new();
+        let impossible = Vanity::new(SecretType::Seed, Match::start("zzzzzzzzzzzz"));
+        let (_rx, mut progress) = impossible
+            .start_with_progress(Duration::from_millis(10), &cancel)
+            .await
+            .unwrap();
+        let first = progress.recv().await.unwrap();
+        assert_eq!(first.probability, Some(32f64.powi(-12)));
+        let second = progress.recv().await.unwrap();
+        assert!(second.elapsed > first.elapsed);
+        assert!(second.attempts >= first.attempts);
+        cancel.cancel();
+        while progress.recv().await.is_some() {}
+    }
+
     // Phrase is waaaay to slow to test.
     // #[tokio::test(flavor = "multi_thread")]
     // async fn vanitize_phrase() {
@@ -335,13 +731,90 @@ mod tests {
     //                 .to_string()
     //         );
     //     } else {
-    //         assert!(false, "Did not get a phrase");
+    //         panic!("Did not get a phrase");
     //     }
     // }
 
+    #[tokio::test(flavor = "multi_thread")]
+    async fn vanitize_any_of() {
+        let patterns = vec![
+            Match::end("zz"),
+            Match::start("a"),
+            Match::end("zzzzzzzzzz"),
+        ];
+        let results = Vanity::any_of(SecretType::Seed, patterns)
+            .collect(10, &CancellationToken::new())
+            .await
+            .unwrap();
+        for result in results {
+            let addr = result.address.to_string();
+            match result.matched.as_str() {
+                "zz" => assert!(addr.ends_with("zz")),
+                "a" => assert!(addr[6..].starts_with('a')),
+                other => panic!("Matched {:?} with {}", other, addr),
+            }
+        }
+
+        let patterns = vec![Match::start("z"), Match::end("zz")];
+        let p = Vanity::any_of(SecretType::Seed, patterns)
+            .probability()
+            .unwrap();
+        assert!((p - (1. - (31. / 32.) * (1023. / 1024.))).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn fuzzy() {
+        let nano = Match::fuzzy(Match::start("NaNo")).unwrap();
+        let mut spellings: Vec<_> = match &nano {
+            Match::Fuzzy { query, expansions } => {
+                assert_eq!(query, "nano");
+                expansions.iter().map(|m| m.as_str().to_owned()).collect()
+            }
+            _ => panic!("Not fuzzy"),
+        };
+        spellings.sort();
+        assert_eq!(spellings, vec!["n4no", "nano"]);
+        assert_eq!(nano.hit("n4nozzz"), Some("n4no"));
+        assert_eq!(nano.hit("zzzn4no"), None);
+
+        // Lookalikes stand in for characters that can't be in an address.
+        let hello = Match::fuzzy(Match::end("hello")).unwrap();
+        assert_eq!(hello.hit("zzzhe11o"), Some("he11o"));
+        assert_eq!(hello.hit("zzzhei1o"), Some("hei1o"));
+        assert!(Match::fuzzy(Match::end("h@")).is_err());
+        assert!(Match::fuzzy(Match::regex("a").unwrap()).is_err());
+        assert!(Match::fuzzy(Match::end(&"a".repeat(13))).is_err());
+
+        let vanity = Vanity::new(SecretType::Seed, Match::fuzzy(Match::end("ab")).unwrap());
+        vanity.validate().unwrap();
+        let p = vanity.probability().unwrap();
+        assert!((p - (1. - (1023f64 / 1024.).powi(4))).abs() < 1e-12);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn vanitize_fuzzy() {
+        let results = Vanity::new(SecretType::Seed, Match::fuzzy(Match::end("sb")).unwrap())
+            .collect(4, &CancellationToken::new())
+            .await
+            .unwrap();
+        for result in results {
+            assert_eq!(result.matched.as_str(), "sb");
+            assert!(["sb", "s8", "5b", "58"].contains(&result.hit.as_str()));
+            assert!(result.address.to_string().ends_with(&result.hit));
+        }
+    }
+
     #[test]
     fn validate() {
         let v = Vanity::new(SecretType::Private, Match::start("l"));
         assert!(v.validate().is_err());
+        let v = Vanity::any_of(
+            SecretType::Private,
+            vec![Match::start("z"), Match::end("l")],
+        );
+        assert!(v.validate().is_err());
+        assert!(Vanity::any_of(SecretType::Private, vec![])
+            .validate()
+            .is_err());
     }
 }
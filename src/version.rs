@@ -12,7 +12,7 @@ pub enum Version {
 
 impl Display for Version {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.to_string()[1..].to_owned())
+        write!(f, "{}", *self as u8)
     }
 }
 
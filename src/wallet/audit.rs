@@ -0,0 +1,231 @@
+use crate::blocks::BlockHash;
+use crate::wallet::WalletId;
+use crate::{Address, Raw};
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter};
+use std::path::PathBuf;
+use tokio::fs::OpenOptions;
+use tokio::io::AsyncWriteExt;
+
+/// An append-only log of operations done with wallet keys, stored as one JSON object per line.
+///
+/// Entries are never rewritten, so the log can be tailed or shipped elsewhere while it's in use.
+pub struct AuditLog {
+    path: PathBuf,
+}
+
+impl AuditLog {
+    pub fn new<P: Into<PathBuf>>(path: P) -> Self {
+        Self { path: path.into() }
+    }
+
+    /// Append an entry, creating the log if it doesn't exist.
+    pub async fn record(&self, entry: &AuditEntry) -> anyhow::Result<()> {
+        let mut line = serde_json::to_string(entry)?;
+        line.push('\n');
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .await
+            .with_context(|| format!("Opening audit log {:?}", &self.path))?;
+        file.write_all(line.as_bytes()).await?;
+        file.sync_data().await?;
+        Ok(())
+    }
+
+    /// All entries in the order they were recorded. A missing log has no entries.
+    pub async fn entries(&self) -> anyhow::Result<Vec<AuditEntry>> {
+        if !self.path.exists() {
+            return Ok(vec![]);
+        }
+
+        let data = tokio::fs::read_to_string(&self.path)
+            .await
+            .with_context(|| format!("Reading audit log {:?}", &self.path))?;
+        data.lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(idx, line)| {
+                serde_json::from_str(line)
+                    .with_context(|| format!("Audit log {:?} line {}", &self.path, idx + 1))
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditOperation {
+    /// A message was signed.
+    Sign,
+
+    /// An address ownership proof was created.
+    Prove,
+
+    /// A block was signed.
+    SignBlock,
+
+    /// A block was sent to the network.
+    Broadcast,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditResult {
+    Ok,
+    Error(String),
+}
+
+impl<T, E: Display> From<&Result<T, E>> for AuditResult {
+    fn from(result: &Result<T, E>) -> Self {
+        match result {
+            Ok(_) => AuditResult::Ok,
+            Err(err) => AuditResult::Error(err.to_string()),
+        }
+    }
+}
+
+/// One operation in the [AuditLog]. Fields that don't apply to the operation are left out.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AuditEntry {
+    pub at: DateTime<Utc>,
+    pub wallet: WalletId,
+    pub operation: AuditOperation,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub account: Option<Address>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub block: Option<BlockHash>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub amount: Option<Raw>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub destination: Option<Address>,
+
+    /// The RPC server the operation was sent to.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rpc: Option<String>,
+
+    pub result: AuditResult,
+}
+
+impl AuditEntry {
+    pub fn new(wallet: WalletId, operation: AuditOperation, result: AuditResult) -> Self {
+        Self {
+            at: Utc::now(),
+            wallet,
+            operation,
+            account: None,
+            block: None,
+            amount: None,
+            destination: None,
+            rpc: None,
+            result,
+        }
+    }
+
+    pub fn account(mut self, account: Address) -> Self {
+        self.account = Some(account);
+        self
+    }
+
+    pub fn block(mut self, block: BlockHash) -> Self {
+        self.block = Some(block);
+        self
+    }
+
+    pub fn amount(mut self, amount: Raw) -> Self {
+        self.amount = Some(amount);
+        self
+    }
+
+    pub fn destination(mut self, destination: Address) -> Self {
+        self.destination = Some(destination);
+        self
+    }
+
+    pub fn rpc<S: Into<String>>(mut self, rpc: S) -> Self {
+        self.rpc = Some(rpc.into());
+        self
+    }
+}
+
+impl Display for AuditEntry {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} {} {:?}",
+            self.at.to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+            self.wallet,
+            self.operation
+        )?;
+        if let Some(account) = &self.account {
+            write!(f, " account={}", account)?;
+        }
+        if let Some(block) = &self.block {
+            write!(f, " block={}", block)?;
+        }
+        if let Some(amount) = &self.amount {
+            write!(f, " amount={}", amount)?;
+        }
+        if let Some(destination) = &self.destination {
+            write!(f, " destination={}", destination)?;
+        }
+        if let Some(rpc) = &self.rpc {
+            write!(f, " rpc={}", rpc)?;
+        }
+        match &self.result {
+            AuditResult::Ok => write!(f, " OK"),
+            AuditResult::Error(err) => write!(f, " ERROR: {}", err),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Seed;
+    use std::fs::remove_file;
+
+    #[tokio::test]
+    async fn append_and_read() {
+        let path = PathBuf::from("append_and_read.audit");
+        if path.exists() {
+            remove_file(&path).unwrap();
+        }
+        let log = AuditLog::new(&path);
+        assert!(log.entries().await.unwrap().is_empty());
+
+        let account = Seed::zero().derive(0).to_public().unwrap().to_address();
+        let sign = AuditEntry::new(WalletId::zero(), AuditOperation::Sign, AuditResult::Ok)
+            .account(account.clone());
+        let failed: Result<(), String> = Err("Connection refused".into());
+        let broadcast = AuditEntry::new(
+            WalletId::zero(),
+            AuditOperation::Broadcast,
+            (&failed).into(),
+        )
+        .account(account.clone())
+        .block(BlockHash::zero())
+        .amount(Raw::from(1000u128))
+        .destination(account)
+        .rpc("http://localhost:7076");
+
+        log.record(&sign).await.unwrap();
+        log.record(&broadcast).await.unwrap();
+        let entries = log.entries().await.unwrap();
+        remove_file(&path).unwrap();
+
+        assert_eq!(entries, vec![sign, broadcast.clone()]);
+        let line = broadcast.to_string();
+        assert!(line.contains(" Broadcast "));
+        assert!(line.contains("amount=1000"));
+        assert!(line.ends_with("ERROR: Connection refused"));
+    }
+}
@@ -0,0 +1,1141 @@
+use crate::blocks::BlockHash;
+use crate::rpc::calls::AccountHistoryEntry;
+use crate::wallet::{
+    AuditEntry, AuditLog, AuditOperation, AuditResult, Payer, RPCPayer, SendGuard, Wallet,
+    WalletId, WalletManager,
+};
+use crate::{Address, Private, Raw};
+use anyhow::anyhow;
+use async_trait::async_trait;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::Mutex;
+use tracing::{info, warn};
+
+/// The most pending blocks received by one `receive` command.
+const RECEIVE_BATCH: u64 = 64;
+
+/// What [WalletDaemon] needs from the network, on top of sending payments.
+#[async_trait]
+pub trait WalletBackend: Payer {
+    /// Receive what has been sent to the account of `to`, returning the hashes of the new blocks.
+    async fn receive(&self, to: &Private) -> anyhow::Result<Vec<BlockHash>>;
+
+    /// The balance of `account`, which is zero for an account that hasn't been opened.
+    async fn balance(&self, account: &Address) -> anyhow::Result<Raw>;
+
+    /// Whether `account` has an open block, even if its balance has since been sent away.
+    async fn is_opened(&self, account: &Address) -> anyhow::Result<bool>;
+
+    async fn history(
+        &self,
+        account: &Address,
+        count: i64,
+    ) -> anyhow::Result<Vec<AccountHistoryEntry>>;
+}
+
+#[async_trait]
+impl WalletBackend for RPCPayer {
+    /// Opens the account if needed, with the account as its own representative.
+    async fn receive(&self, to: &Private) -> anyhow::Result<Vec<BlockHash>> {
+        let received = self
+            .receive_pending(to, &Raw::from(1), RECEIVE_BATCH)
+            .await?;
+        Ok(received.into_iter().map(|(hash, _)| hash).collect())
+    }
+
+    async fn balance(&self, account: &Address) -> anyhow::Result<Raw> {
+        use crate::rpc::calls::AccountInfoRequest;
+        use crate::rpc::client::RPCRequest;
+
+        match (&AccountInfoRequest::new(account.to_owned()))
+            .call(&self.client)
+            .await
+        {
+            Ok(info) => Ok(info.balance),
+            Err(crate::Error::RPCError(crate::RpcErrorKind::AccountNotFound)) => Ok(Raw::zero()),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    async fn is_opened(&self, account: &Address) -> anyhow::Result<bool> {
+        use crate::rpc::calls::AccountInfoRequest;
+        use crate::rpc::client::RPCRequest;
+
+        match (&AccountInfoRequest::new(account.to_owned()))
+            .call(&self.client)
+            .await
+        {
+            Ok(_) => Ok(true),
+            Err(crate::Error::RPCError(crate::RpcErrorKind::AccountNotFound)) => Ok(false),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    async fn history(
+        &self,
+        account: &Address,
+        count: i64,
+    ) -> anyhow::Result<Vec<AccountHistoryEntry>> {
+        use crate::rpc::calls::AccountHistoryRequest;
+        use crate::rpc::client::RPCRequest;
+
+        let response = (&AccountHistoryRequest::new(account.to_owned(), count))
+            .call(&self.client)
+            .await?;
+        Ok(response.history)
+    }
+}
+
+impl RPCPayer {
+    /// Receive up to `count` blocks sent to the account of `to`, skipping blocks of less than
+    /// `minimum`, which should be at least 1 raw. Opens the account if needed, with the account
+    /// as its own representative. Returns the hash of each new block with the amount received.
+    pub async fn receive_pending(
+        &self,
+        to: &Private,
+        minimum: &Raw,
+        count: u64,
+    ) -> anyhow::Result<Vec<(BlockHash, Raw)>> {
+        use crate::blocks::{Link, Previous, StateBlock, Subtype};
+        use crate::rpc::calls::{
+            AccountInfoRequest, AccountsPendingRequest, AccountsPendingResponse,
+        };
+        use crate::rpc::client::RPCRequest;
+        use anyhow::Context;
+
+        let account = to.to_address()?;
+        let request = AccountsPendingRequest::new(vec![account.to_owned()], count)
+            .threshold(minimum.to_owned());
+        let pending = match (&request).call(&self.client).await? {
+            AccountsPendingResponse::Threshold { mut blocks } => {
+                blocks.remove(&account).unwrap_or_default()
+            }
+            AccountsPendingResponse::OnlyBlockHash { blocks }
+                if blocks.values().all(|hashes| hashes.is_empty()) =>
+            {
+                HashMap::new()
+            }
+            response => return Err(anyhow!("Pending blocks without amounts: {:?}", response)),
+        };
+        if pending.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let (mut previous, mut balance, representative) =
+            match (&AccountInfoRequest::new(account.to_owned()))
+                .call(&self.client)
+                .await
+            {
+                Ok(info) => (
+                    Previous::Block(info.frontier),
+                    info.balance,
+                    info.representative.unwrap_or_else(|| account.to_owned()),
+                ),
+                Err(crate::Error::RPCError(crate::RpcErrorKind::AccountNotFound)) => {
+                    (Previous::Open, Raw::zero(), account.to_owned())
+                }
+                Err(err) => {
+                    return Err(err)
+                        .with_context(|| format!("Getting account info for {}", account))
+                }
+            };
+
+        let mut received = vec![];
+        for (source, amount) in pending {
+            balance = balance
+                .checked_add(&amount)
+                .ok_or_else(|| anyhow!("Receiving {} raw would overflow the balance", amount))?;
+            let subtype = match previous {
+                Previous::Open => Subtype::Open,
+                Previous::Block(_) => Subtype::Receive,
+            };
+            let block = StateBlock::new(
+                account.to_public(),
+                previous,
+                representative.to_public(),
+                balance.to_owned(),
+                Link::Source(source),
+            );
+            let hash = self.publish(subtype, block, to).await?;
+            previous = Previous::Block(hash.to_owned());
+            received.push((hash, amount));
+        }
+        Ok(received)
+    }
+
+    /// Receive everything pending for the account of `from`, then send its whole balance to
+    /// `to`. Returns the send with the amount, or nothing when the account is empty.
+    pub async fn sweep(
+        &self,
+        from: &Private,
+        to: &Address,
+    ) -> anyhow::Result<Option<(BlockHash, Raw)>> {
+        use crate::blocks::{Link, Previous, StateBlock, Subtype};
+        use crate::rpc::calls::AccountInfoRequest;
+        use crate::rpc::client::RPCRequest;
+        use anyhow::Context;
+
+        while self
+            .receive_pending(from, &Raw::from(1), RECEIVE_BATCH)
+            .await?
+            .len() as u64
+            == RECEIVE_BATCH
+        {}
+
+        let account = from.to_address()?;
+        let info = match (&AccountInfoRequest::new(account.to_owned()))
+            .call(&self.client)
+            .await
+        {
+            Ok(info) => info,
+            Err(crate::Error::RPCError(crate::RpcErrorKind::AccountNotFound)) => return Ok(None),
+            Err(err) => {
+                return Err(err).with_context(|| format!("Getting account info for {}", account))
+            }
+        };
+        if info.balance == Raw::zero() {
+            return Ok(None);
+        }
+        let representative = info.representative.unwrap_or_else(|| account.to_owned());
+        let block = StateBlock::new(
+            account.to_public(),
+            Previous::Block(info.frontier),
+            representative.to_public(),
+            Raw::zero(),
+            Link::DestinationAccount(to.to_public()),
+        );
+        let hash = self.publish(Subtype::Send, block, from).await?;
+        Ok(Some((hash, info.balance)))
+    }
+}
+
+/// A command from a control socket client, as one line of JSON, e.g.
+/// `{"command": "unlock", "wallet": "00..00", "seconds": 300}`.
+///
+/// Every connection has to start with [ControlRequest::Auth].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "command", rename_all = "snake_case")]
+pub enum ControlRequest {
+    Auth {
+        token: String,
+    },
+
+    /// Every wallet in the file, and whether it's unlocked.
+    List,
+
+    /// Read the keys of a wallet into memory, optionally locking it again after `seconds`.
+    Unlock {
+        wallet: WalletId,
+        #[serde(default)]
+        seconds: Option<u64>,
+    },
+
+    /// Forget the keys of a wallet.
+    Lock {
+        wallet: WalletId,
+    },
+
+    Address {
+        wallet: WalletId,
+        #[serde(default)]
+        index: u32,
+    },
+
+    /// Send `amount` raw to an address or `@contact`.
+    ///
+    /// With a [SendGuard], a send of more than its amount needs a TOTP `code`. Without one the
+    /// send waits for a [ControlRequest::Confirm].
+    Send {
+        wallet: WalletId,
+        #[serde(default)]
+        index: u32,
+        to: String,
+        amount: Raw,
+        #[serde(default)]
+        code: Option<String>,
+    },
+
+    /// Sends that are waiting to be confirmed.
+    Confirmations,
+
+    /// Make a send that is waiting to be confirmed.
+    Confirm {
+        id: u64,
+    },
+
+    /// Drop a send that is waiting to be confirmed.
+    Reject {
+        id: u64,
+    },
+
+    /// Receive what has been sent to an account.
+    Receive {
+        wallet: WalletId,
+        #[serde(default)]
+        index: u32,
+    },
+
+    History {
+        wallet: WalletId,
+        #[serde(default)]
+        index: u32,
+        #[serde(default = "default_history_count")]
+        count: i64,
+    },
+}
+
+fn default_history_count() -> i64 {
+    20
+}
+
+/// The reply to each [ControlRequest], as one line of JSON.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ControlResponse {
+    Ok(serde_json::Value),
+    Error(String),
+}
+
+impl From<anyhow::Result<serde_json::Value>> for ControlResponse {
+    fn from(result: anyhow::Result<serde_json::Value>) -> Self {
+        match result {
+            Ok(value) => ControlResponse::Ok(value),
+            Err(err) => ControlResponse::Error(format!("{:#}", err)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WalletStatus {
+    pub wallet: WalletId,
+    pub unlocked: bool,
+}
+
+/// What a client authenticated as.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Role {
+    Client,
+
+    /// Authenticated with the confirm token of the [SendGuard], so it can confirm sends.
+    Confirmer,
+}
+
+/// The result of [ControlRequest::Send].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SendOutcome {
+    Sent(BlockHash),
+    AwaitingConfirmation(PendingSend),
+}
+
+/// A send that is waiting to be confirmed.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PendingSend {
+    pub id: u64,
+    pub wallet: WalletId,
+    pub index: u32,
+    pub to: Address,
+    pub amount: Raw,
+
+    /// Seconds left to confirm the send in.
+    pub expires_in: u64,
+}
+
+struct Unlocked {
+    wallet: Wallet,
+    until: Option<Instant>,
+}
+
+struct Waiting {
+    send: PendingSend,
+    until: Instant,
+}
+
+/// Serves the wallets of a wallet file to local clients over a control socket.
+///
+/// Wallets start out locked, and their keys are only read from the file and kept in memory once
+/// a client unlocks them. Clients authenticate with a token, see [load_or_create_token].
+pub struct WalletDaemon<B> {
+    manager: WalletManager,
+    backend: B,
+    token: String,
+    audit: Option<AuditLog>,
+    guard: Option<SendGuard>,
+    unlocked: Mutex<HashMap<WalletId, Unlocked>>,
+    waiting: Mutex<HashMap<u64, Waiting>>,
+    next_id: AtomicU64,
+}
+
+impl<B: WalletBackend + Send + Sync + 'static> WalletDaemon<B> {
+    pub fn new(manager: WalletManager, backend: B, token: String) -> Self {
+        Self {
+            manager,
+            backend,
+            token,
+            audit: None,
+            guard: None,
+            unlocked: Mutex::new(HashMap::new()),
+            waiting: Mutex::new(HashMap::new()),
+            next_id: AtomicU64::new(1),
+        }
+    }
+
+    /// Hold back large sends until they're confirmed.
+    pub fn guard(mut self, guard: SendGuard) -> Self {
+        self.guard = Some(guard);
+        self
+    }
+
+    /// Record each send and receive in an audit log.
+    pub fn audit(mut self, log: AuditLog) -> Self {
+        self.audit = Some(log);
+        self
+    }
+
+    /// Accept clients on `listener` forever, each on its own task.
+    pub async fn serve(self: Arc<Self>, listener: TcpListener) -> anyhow::Result<()> {
+        loop {
+            let (stream, peer) = listener.accept().await?;
+            let daemon = self.clone();
+            tokio::spawn(async move {
+                if let Err(err) = daemon.connection(stream).await {
+                    warn!("Control connection from {} failed: {:#}", peer, err);
+                }
+            });
+        }
+    }
+
+    /// Answer each line from a client until it disconnects. The connection is closed after a
+    /// failed authentication, or a command before authenticating.
+    async fn connection(&self, stream: TcpStream) -> anyhow::Result<()> {
+        let (read, mut write) = stream.into_split();
+        let mut lines = BufReader::new(read).lines();
+        let mut role = None;
+        while let Some(line) = lines.next_line().await? {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let (response, close) = match serde_json::from_str::<ControlRequest>(&line) {
+                Err(err) => (
+                    ControlResponse::Error(format!("Bad command: {}", err)),
+                    false,
+                ),
+                Ok(ControlRequest::Auth { token }) => {
+                    role = self.authenticate(&token);
+                    match role {
+                        Some(_) => (ControlResponse::Ok(serde_json::Value::Null), false),
+                        None => (ControlResponse::Error("Wrong token".into()), true),
+                    }
+                }
+                Ok(request) => match role {
+                    Some(role) => (self.handle(role, request).await.into(), false),
+                    None => (ControlResponse::Error("Authenticate first".into()), true),
+                },
+            };
+
+            let mut json = serde_json::to_string(&response)?;
+            json.push('\n');
+            write.write_all(json.as_bytes()).await?;
+            if close {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    fn authenticate(&self, token: &str) -> Option<Role> {
+        let confirm_token = self.guard.as_ref().and_then(|g| g.confirm_token());
+        if same_token(token, &self.token) {
+            Some(Role::Client)
+        } else if matches!(confirm_token, Some(t) if same_token(token, t)) {
+            Some(Role::Confirmer)
+        } else {
+            None
+        }
+    }
+
+    /// Do what an authenticated client asked for.
+    pub async fn handle(
+        &self,
+        role: Role,
+        request: ControlRequest,
+    ) -> anyhow::Result<serde_json::Value> {
+        let value = match request {
+            ControlRequest::Auth { .. } => serde_json::Value::Null,
+            ControlRequest::List => {
+                let mut unlocked = self.unlocked.lock().await;
+                let statuses: Vec<_> = self
+                    .manager
+                    .ids()
+                    .await?
+                    .into_iter()
+                    .map(|wallet| WalletStatus {
+                        unlocked: unlocked_wallet(&mut unlocked, &wallet).is_some(),
+                        wallet,
+                    })
+                    .collect();
+                serde_json::to_value(statuses)?
+            }
+            ControlRequest::Unlock { wallet, seconds } => {
+                let unlocked = Unlocked {
+                    wallet: self.manager.wallet(&wallet).await?,
+                    until: seconds.map(|s| Instant::now() + Duration::from_secs(s)),
+                };
+                info!("Unlocked wallet {:?}", wallet);
+                self.unlocked.lock().await.insert(wallet, unlocked);
+                serde_json::Value::Null
+            }
+            ControlRequest::Lock { wallet } => {
+                if self.unlocked.lock().await.remove(&wallet).is_some() {
+                    info!("Locked wallet {:?}", wallet);
+                }
+                serde_json::Value::Null
+            }
+            ControlRequest::Address { wallet, index } => {
+                serde_json::to_value(self.wallet(&wallet).await?.address(index)?)?
+            }
+            ControlRequest::Send {
+                wallet,
+                index,
+                to,
+                amount,
+                code,
+            } => {
+                // Check the wallet is unlocked before anything waits for confirmation.
+                self.wallet(&wallet).await?.private(index)?;
+                let to = self.manager.resolve(&to).await?;
+                let guard = match &self.guard {
+                    Some(guard) if guard.needs_confirmation(&amount) => guard,
+                    _ => {
+                        let hash = self.send(&wallet, index, &to, &amount).await?;
+                        return Ok(serde_json::to_value(SendOutcome::Sent(hash))?);
+                    }
+                };
+                if let Some(code) = code {
+                    let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+                    guard.check_code(&code, now)?;
+                    let hash = self.send(&wallet, index, &to, &amount).await?;
+                    return Ok(serde_json::to_value(SendOutcome::Sent(hash))?);
+                }
+
+                let send = PendingSend {
+                    id: self.next_id.fetch_add(1, Ordering::Relaxed),
+                    wallet,
+                    index,
+                    to,
+                    amount,
+                    expires_in: guard.timeout().as_secs(),
+                };
+                info!(
+                    "Send #{} of {} raw to {} is waiting to be confirmed",
+                    send.id, send.amount, send.to
+                );
+                self.waiting.lock().await.insert(
+                    send.id,
+                    Waiting {
+                        send: send.to_owned(),
+                        until: Instant::now() + guard.timeout(),
+                    },
+                );
+                serde_json::to_value(SendOutcome::AwaitingConfirmation(send))?
+            }
+            ControlRequest::Confirmations => {
+                let mut waiting = self.waiting.lock().await;
+                let now = Instant::now();
+                waiting.retain(|_, w| w.until > now);
+                let mut sends: Vec<_> = waiting
+                    .values()
+                    .map(|w| PendingSend {
+                        expires_in: (w.until - now).as_secs(),
+                        ..w.send.to_owned()
+                    })
+                    .collect();
+                sends.sort_by_key(|s| s.id);
+                serde_json::to_value(sends)?
+            }
+            ControlRequest::Confirm { id } => {
+                self.can_confirm(role)?;
+                let send = self.take_waiting(id).await?;
+                info!("Send #{} was confirmed", id);
+                let hash = self
+                    .send(&send.wallet, send.index, &send.to, &send.amount)
+                    .await?;
+                serde_json::to_value(SendOutcome::Sent(hash))?
+            }
+            ControlRequest::Reject { id } => {
+                self.can_confirm(role)?;
+                self.take_waiting(id).await?;
+                info!("Send #{} was rejected", id);
+                serde_json::Value::Null
+            }
+            ControlRequest::Receive { wallet, index } => {
+                let private = self.wallet(&wallet).await?.private(index)?;
+                let result = self.backend.receive(&private).await;
+                match &result {
+                    Ok(hashes) => {
+                        for hash in hashes {
+                            let entry = AuditEntry::new(
+                                wallet.to_owned(),
+                                AuditOperation::Broadcast,
+                                AuditResult::Ok,
+                            )
+                            .account(private.to_address()?)
+                            .block(hash.to_owned());
+                            self.record(entry).await?;
+                        }
+                    }
+                    Err(_) => {
+                        let entry = AuditEntry::new(
+                            wallet,
+                            AuditOperation::Broadcast,
+                            audit_result(&result),
+                        )
+                        .account(private.to_address()?);
+                        self.record(entry).await?;
+                    }
+                }
+                serde_json::to_value(result?)?
+            }
+            ControlRequest::History {
+                wallet,
+                index,
+                count,
+            } => {
+                let address = self.wallet(&wallet).await?.address(index)?;
+                serde_json::to_value(self.backend.history(&address, count).await?)?
+            }
+        };
+        Ok(value)
+    }
+
+    /// Send from an unlocked wallet, recording it in the audit log.
+    async fn send(
+        &self,
+        wallet: &WalletId,
+        index: u32,
+        to: &Address,
+        amount: &Raw,
+    ) -> anyhow::Result<BlockHash> {
+        let private = self.wallet(wallet).await?.private(index)?;
+        let result = self.backend.pay(&private, to, amount).await;
+        let entry = AuditEntry::new(
+            wallet.to_owned(),
+            AuditOperation::Broadcast,
+            audit_result(&result),
+        )
+        .account(private.to_address()?)
+        .amount(amount.to_owned())
+        .destination(to.to_owned());
+        let entry = match &result {
+            Ok(hash) => entry.block(hash.to_owned()),
+            Err(_) => entry,
+        };
+        self.record(entry).await?;
+        result
+    }
+
+    fn can_confirm(&self, role: Role) -> anyhow::Result<()> {
+        let needs_token = matches!(&self.guard, Some(g) if g.confirm_token().is_some());
+        if needs_token && role != Role::Confirmer {
+            return Err(anyhow!(
+                "Only clients with the confirm token can confirm sends"
+            ));
+        }
+        Ok(())
+    }
+
+    async fn take_waiting(&self, id: u64) -> anyhow::Result<PendingSend> {
+        match self.waiting.lock().await.remove(&id) {
+            Some(w) if w.until > Instant::now() => Ok(w.send),
+            Some(_) => Err(anyhow!("Send #{} wasn't confirmed in time", id)),
+            None => Err(anyhow!("There is no send #{} waiting to be confirmed", id)),
+        }
+    }
+
+    /// An unlocked wallet, or an error if it's locked.
+    async fn wallet(&self, id: &WalletId) -> anyhow::Result<Wallet> {
+        let mut unlocked = self.unlocked.lock().await;
+        unlocked_wallet(&mut unlocked, id).ok_or_else(|| anyhow!("Wallet {:?} is locked", id))
+    }
+
+    async fn record(&self, mut entry: AuditEntry) -> anyhow::Result<()> {
+        let log = match &self.audit {
+            Some(log) => log,
+            None => return Ok(()),
+        };
+        entry.rpc = self.backend.describe();
+        log.record(&entry).await
+    }
+}
+
+/// The wallet if it's unlocked, locking it first if its time is up.
+fn unlocked_wallet(unlocked: &mut HashMap<WalletId, Unlocked>, id: &WalletId) -> Option<Wallet> {
+    match unlocked.get(id) {
+        Some(Unlocked {
+            until: Some(until), ..
+        }) if *until <= Instant::now() => {
+            info!("Wallet {:?} locked after its unlock time ran out", id);
+            unlocked.remove(id);
+            None
+        }
+        Some(u) => Some(u.wallet.to_owned()),
+        None => None,
+    }
+}
+
+fn audit_result<T>(result: &anyhow::Result<T>) -> AuditResult {
+    match result {
+        Ok(_) => AuditResult::Ok,
+        Err(err) => AuditResult::Error(format!("{:#}", err)),
+    }
+}
+
+/// Compare tokens in time that only depends on their length.
+fn same_token(a: &str, b: &str) -> bool {
+    a.len() == b.len()
+        && a.bytes()
+            .zip(b.bytes())
+            .fold(0, |acc, (x, y)| acc | (x ^ y))
+            == 0
+}
+
+/// Read the control socket token from `path`, or create a random one that only the current user
+/// can read.
+pub fn load_or_create_token(path: &Path) -> anyhow::Result<String> {
+    use anyhow::Context;
+
+    if path.exists() {
+        let token = std::fs::read_to_string(path)
+            .with_context(|| format!("Reading walletd token {:?}", path))?;
+        return Ok(token.trim().to_owned());
+    }
+
+    let mut bytes = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    let token = hex::encode(bytes);
+
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options
+        .open(path)
+        .with_context(|| format!("Creating walletd token {:?}", path))?;
+    file.write_all(token.as_bytes())?;
+    Ok(token)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+    use std::sync::Mutex as StdMutex;
+
+    #[derive(Default)]
+    struct FakeBackend {
+        paid: StdMutex<Vec<(Address, Address, Raw)>>,
+    }
+
+    #[async_trait]
+    impl Payer for FakeBackend {
+        async fn pay(
+            &self,
+            from: &Private,
+            to: &Address,
+            amount: &Raw,
+        ) -> anyhow::Result<BlockHash> {
+            self.paid
+                .lock()
+                .unwrap()
+                .push((from.to_address()?, to.to_owned(), amount.to_owned()));
+            Ok(BlockHash::zero())
+        }
+    }
+
+    #[async_trait]
+    impl WalletBackend for FakeBackend {
+        async fn receive(&self, _: &Private) -> anyhow::Result<Vec<BlockHash>> {
+            Ok(vec![BlockHash::zero()])
+        }
+
+        async fn balance(&self, _: &Address) -> anyhow::Result<Raw> {
+            Ok(Raw::zero())
+        }
+
+        async fn is_opened(&self, _: &Address) -> anyhow::Result<bool> {
+            Ok(false)
+        }
+
+        async fn history(&self, _: &Address, _: i64) -> anyhow::Result<Vec<AccountHistoryEntry>> {
+            Ok(vec![])
+        }
+    }
+
+    async fn daemon(path: &Path) -> (WalletDaemon<FakeBackend>, WalletId, WalletId) {
+        if path.exists() {
+            std::fs::remove_file(path).unwrap();
+        }
+        let manager = WalletManager::new(path);
+        manager.ensure().await.unwrap();
+        let (first, second) = (WalletId::zero(), WalletId::random());
+        manager.add_random_seed(first.to_owned()).await.unwrap();
+        manager.add_random_seed(second.to_owned()).await.unwrap();
+        let daemon = WalletDaemon::new(
+            WalletManager::new(path),
+            FakeBackend::default(),
+            "secret".into(),
+        );
+        (daemon, first, second)
+    }
+
+    #[tokio::test]
+    async fn unlock_per_wallet() {
+        let path = PathBuf::from("unlock_per_wallet.wallet");
+        let (daemon, first, second) = daemon(&path).await;
+        let to = crate::Seed::zero().derive(1).to_address().unwrap();
+        let send = |wallet: &WalletId| ControlRequest::Send {
+            wallet: wallet.to_owned(),
+            index: 0,
+            to: to.to_string(),
+            amount: Raw::from(5),
+            code: None,
+        };
+
+        let err = daemon.handle(Role::Client, send(&first)).await.unwrap_err();
+        assert!(err.to_string().contains("locked"), "{}", err);
+
+        daemon
+            .handle(
+                Role::Client,
+                ControlRequest::Unlock {
+                    wallet: first.to_owned(),
+                    seconds: None,
+                },
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            daemon.handle(Role::Client, send(&first)).await.unwrap(),
+            serde_json::to_value(SendOutcome::Sent(BlockHash::zero())).unwrap()
+        );
+        assert!(daemon.handle(Role::Client, send(&second)).await.is_err());
+        assert_eq!(daemon.backend.paid.lock().unwrap().len(), 1);
+
+        let list = daemon
+            .handle(Role::Client, ControlRequest::List)
+            .await
+            .unwrap();
+        let list: Vec<WalletStatus> = serde_json::from_value(list).unwrap();
+        assert_eq!(list.len(), 2);
+        assert!(list.iter().all(|s| s.unlocked == (s.wallet == first)));
+
+        // An unlock that has run out locks the wallet again.
+        daemon
+            .handle(
+                Role::Client,
+                ControlRequest::Unlock {
+                    wallet: second.to_owned(),
+                    seconds: Some(0),
+                },
+            )
+            .await
+            .unwrap();
+        assert!(daemon.handle(Role::Client, send(&second)).await.is_err());
+
+        daemon
+            .handle(
+                Role::Client,
+                ControlRequest::Lock {
+                    wallet: first.to_owned(),
+                },
+            )
+            .await
+            .unwrap();
+        assert!(daemon
+            .handle(
+                Role::Client,
+                ControlRequest::Address {
+                    wallet: first,
+                    index: 0
+                }
+            )
+            .await
+            .is_err());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn guard_holds_back_large_sends() {
+        let path = PathBuf::from("guard_holds_back_large_sends.wallet");
+        let (daemon, first, _) = daemon(&path).await;
+        let config = crate::wallet::WalletdConfig {
+            confirm_above: Some(Raw::from(10)),
+            totp_secret: Some("GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ".into()),
+            confirm_token: Some("confirm".into()),
+            confirm_timeout: 60,
+        };
+        let daemon = daemon.guard(SendGuard::from_config(&config).unwrap().unwrap());
+        assert_eq!(daemon.authenticate("confirm"), Some(Role::Confirmer));
+        daemon
+            .handle(
+                Role::Client,
+                ControlRequest::Unlock {
+                    wallet: first.to_owned(),
+                    seconds: None,
+                },
+            )
+            .await
+            .unwrap();
+
+        let to = crate::Seed::zero().derive(1).to_address().unwrap();
+        let send = |amount: u128, code: Option<&str>| ControlRequest::Send {
+            wallet: first.to_owned(),
+            index: 0,
+            to: to.to_string(),
+            amount: Raw::from(amount),
+            code: code.map(|c| c.to_owned()),
+        };
+        // Raw only deserializes from borrowed strings, so go through a string.
+        let outcome = |value: serde_json::Value| {
+            serde_json::from_str::<SendOutcome>(&value.to_string()).unwrap()
+        };
+
+        let small = daemon.handle(Role::Client, send(10, None)).await.unwrap();
+        assert_eq!(outcome(small), SendOutcome::Sent(BlockHash::zero()));
+
+        let id = match outcome(daemon.handle(Role::Client, send(11, None)).await.unwrap()) {
+            SendOutcome::AwaitingConfirmation(pending) => pending.id,
+            o => panic!("{:?}", o),
+        };
+        assert_eq!(daemon.backend.paid.lock().unwrap().len(), 1);
+        let err = daemon
+            .handle(Role::Client, ControlRequest::Confirm { id })
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("confirm token"), "{}", err);
+        let waiting = daemon
+            .handle(Role::Client, ControlRequest::Confirmations)
+            .await
+            .unwrap();
+        assert_eq!(waiting.as_array().unwrap().len(), 1);
+
+        daemon
+            .handle(Role::Confirmer, ControlRequest::Confirm { id })
+            .await
+            .unwrap();
+        assert_eq!(daemon.backend.paid.lock().unwrap().len(), 2);
+        assert!(daemon
+            .handle(Role::Confirmer, ControlRequest::Confirm { id })
+            .await
+            .is_err());
+
+        // A wrong code is refused rather than waiting for confirmation.
+        assert!(daemon
+            .handle(Role::Client, send(11, Some("000000")))
+            .await
+            .is_err());
+        assert_eq!(daemon.backend.paid.lock().unwrap().len(), 2);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn control_socket_needs_token() {
+        let path = PathBuf::from("control_socket_needs_token.wallet");
+        let (daemon, first, _) = daemon(&path).await;
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(Arc::new(daemon).serve(listener));
+
+        async fn talk(addr: std::net::SocketAddr, lines: &[String]) -> Vec<ControlResponse> {
+            let stream = TcpStream::connect(addr).await.unwrap();
+            let (read, mut write) = stream.into_split();
+            for line in lines {
+                write
+                    .write_all(format!("{}\n", line).as_bytes())
+                    .await
+                    .unwrap();
+            }
+            let mut responses = vec![];
+            let mut read = BufReader::new(read).lines();
+            while let Ok(Some(line)) = read.next_line().await {
+                responses.push(serde_json::from_str(&line).unwrap());
+                if responses.len() == lines.len() {
+                    break;
+                }
+            }
+            responses
+        }
+
+        let unlock = serde_json::to_string(&ControlRequest::Unlock {
+            wallet: first,
+            seconds: None,
+        })
+        .unwrap();
+        let responses = talk(addr, &[unlock.to_owned()]).await;
+        assert_eq!(
+            responses,
+            vec![ControlResponse::Error("Authenticate first".into())]
+        );
+
+        let wrong = r#"{"command": "auth", "token": "nope"}"#.to_owned();
+        let responses = talk(addr, &[wrong, unlock.to_owned()]).await;
+        assert_eq!(
+            responses,
+            vec![ControlResponse::Error("Wrong token".into())]
+        );
+
+        let auth = r#"{"command": "auth", "token": "secret"}"#.to_owned();
+        let responses = talk(addr, &[auth, unlock]).await;
+        assert_eq!(
+            responses,
+            vec![
+                ControlResponse::Ok(serde_json::Value::Null),
+                ControlResponse::Ok(serde_json::Value::Null)
+            ]
+        );
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[cfg(feature = "rpc_server")]
+    #[tokio::test]
+    async fn receive_pending_opens_account() {
+        use crate::rpc::client::RPCClient;
+        use crate::{Difficulty, Seed, WorkThresholds};
+        use serde_json::{json, Value};
+        use warp::Filter;
+
+        let to = Seed::zero().derive(0);
+        let account = to.to_address().unwrap();
+        let processed = Arc::new(StdMutex::new(vec![]));
+        let route = {
+            let account = account.to_owned();
+            let processed = processed.clone();
+            warp::post()
+                .and(warp::body::json())
+                .map(move |body: Value| match body["action"].as_str().unwrap() {
+                    "accounts_pending" => {
+                        assert_eq!(body["threshold"], "1000");
+                        let mut blocks = serde_json::Map::new();
+                        blocks.insert(
+                            account.to_string(),
+                            json!({ BlockHash::zero().to_string(): "5000" }),
+                        );
+                        warp::reply::json(&json!({ "blocks": blocks }))
+                    }
+                    "account_info" => warp::reply::json(&json!({"error": "Account not found"})),
+                    "process" => {
+                        processed.lock().unwrap().push(body);
+                        warp::reply::json(&json!({}))
+                    }
+                    action => panic!("Unexpected action {}", action),
+                })
+        };
+        let (address, server) = warp::serve(route).bind_ephemeral(([127, 0, 0, 1], 0));
+        tokio::spawn(server);
+
+        let url = format!("http://{}", address);
+        let thresholds = WorkThresholds {
+            receive: Some(Difficulty::new(0)),
+            ..Default::default()
+        };
+
+        // A dry run keeps the request instead of sending it.
+        let dry_run = RPCPayer::new(RPCClient::new(&url), url.to_owned())
+            .thresholds(thresholds.clone())
+            .dry_run(true);
+        dry_run
+            .receive_pending(&to, &Raw::from(1000), 1)
+            .await
+            .unwrap();
+        let rehearsed = dry_run.rehearsed();
+        assert_eq!(rehearsed.len(), 1);
+        assert_eq!(rehearsed[0]["action"], "process");
+        assert_eq!(rehearsed[0]["subtype"], "open");
+        assert!(rehearsed[0]["block"]["signature"].is_string());
+        assert!(processed.lock().unwrap().is_empty());
+
+        let payer = RPCPayer::new(RPCClient::new(&url), url).thresholds(thresholds);
+        let received = payer
+            .receive_pending(&to, &Raw::from(1000), 1)
+            .await
+            .unwrap();
+        assert_eq!(received.len(), 1);
+        assert_eq!(received[0].1, Raw::from(5000));
+
+        let processed = processed.lock().unwrap();
+        assert_eq!(processed.len(), 1);
+        assert_eq!(processed[0]["subtype"], "open");
+        assert_eq!(processed[0]["block"]["balance"], "5000");
+    }
+
+    #[cfg(feature = "rpc_server")]
+    #[tokio::test]
+    async fn sweep_empties_account() {
+        use crate::rpc::client::RPCClient;
+        use crate::{Difficulty, Seed, WorkThresholds};
+        use serde_json::{json, Value};
+        use warp::Filter;
+
+        let from = Seed::zero().derive(0);
+        let account = from.to_address().unwrap();
+        let cold = Seed::zero().derive(1).to_address().unwrap();
+        let processed = Arc::new(StdMutex::new(vec![]));
+        let route = {
+            let account = account.to_owned();
+            let processed = processed.clone();
+            warp::post()
+                .and(warp::body::json())
+                .map(move |body: Value| match body["action"].as_str().unwrap() {
+                    "accounts_pending" => {
+                        let mut blocks = serde_json::Map::new();
+                        blocks.insert(account.to_string(), json!({}));
+                        warp::reply::json(&json!({ "blocks": blocks }))
+                    }
+                    "account_info" => warp::reply::json(&json!({
+                        "frontier": BlockHash::zero(),
+                        "open_block": BlockHash::zero(),
+                        "representative_block": BlockHash::zero(),
+                        "balance": "7000",
+                        "modified_timestamp": "1600000000",
+                        "block_count": "1",
+                        "confirmation_height": "1",
+                        "confirmation_height_frontier": BlockHash::zero(),
+                        "account_version": "2",
+                    })),
+                    "process" => {
+                        processed.lock().unwrap().push(body);
+                        warp::reply::json(&json!({}))
+                    }
+                    action => panic!("Unexpected action {}", action),
+                })
+        };
+        let (address, server) = warp::serve(route).bind_ephemeral(([127, 0, 0, 1], 0));
+        tokio::spawn(server);
+
+        let url = format!("http://{}", address);
+        let payer = RPCPayer::new(RPCClient::new(&url), url).thresholds(WorkThresholds {
+            send: Some(Difficulty::new(0)),
+            ..Default::default()
+        });
+        let (_, amount) = payer.sweep(&from, &cold).await.unwrap().unwrap();
+        assert_eq!(amount, Raw::from(7000));
+
+        let processed = processed.lock().unwrap();
+        assert_eq!(processed.len(), 1);
+        assert_eq!(processed[0]["subtype"], "send");
+        assert_eq!(processed[0]["block"]["balance"], "0");
+        assert_eq!(processed[0]["block"]["link"], cold.to_public().to_string());
+    }
+}
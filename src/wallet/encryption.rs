@@ -0,0 +1,138 @@
+//! Passphrase encryption of wallet files.
+//!
+//! An encrypted wallet file is a JSON object with an `encrypted` field, holding the plain wallet
+//! file encrypted with XChaCha20-Poly1305. The key is derived from the passphrase with Argon2id,
+//! and the salt and cost parameters are kept alongside the ciphertext so they can be raised
+//! later without breaking older files.
+use anyhow::anyhow;
+use argon2::{Algorithm, Argon2, Params, Version};
+use chacha20poly1305::aead::{Aead, NewAead};
+use chacha20poly1305::{Key, XChaCha20Poly1305, XNonce};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 24;
+const KEY_LEN: usize = 32;
+
+/// The Argon2id cost parameters, as stored in the file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub(super) struct KdfParams {
+    /// Memory in KiB.
+    m_cost: u32,
+    t_cost: u32,
+    p_cost: u32,
+}
+
+impl Default for KdfParams {
+    /// The minimum Argon2id costs that OWASP recommends.
+    fn default() -> Self {
+        Self {
+            m_cost: 19 * 1024,
+            t_cost: 2,
+            p_cost: 1,
+        }
+    }
+}
+
+/// The `encrypted` field of an encrypted wallet file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(super) struct Encrypted {
+    kdf: KdfParams,
+
+    /// Hex encoded.
+    salt: String,
+
+    /// Hex encoded.
+    nonce: String,
+
+    /// Hex encoded, including the authentication tag.
+    ciphertext: String,
+}
+
+/// Only the part of a wallet file that tells whether it's encrypted.
+#[derive(Debug, Deserialize)]
+pub(super) struct Envelope {
+    pub encrypted: Option<Encrypted>,
+}
+
+#[derive(Serialize)]
+struct EnvelopeRef<'a> {
+    encrypted: &'a Encrypted,
+}
+
+impl Encrypted {
+    /// Encrypt `plaintext` under `passphrase` with a fresh salt and nonce.
+    pub fn seal(passphrase: &str, plaintext: &[u8]) -> anyhow::Result<Self> {
+        let kdf = KdfParams::default();
+        let mut salt = [0u8; SALT_LEN];
+        let mut nonce = [0u8; NONCE_LEN];
+        rand::thread_rng().fill_bytes(&mut salt);
+        rand::thread_rng().fill_bytes(&mut nonce);
+
+        let cipher = cipher(passphrase, &salt, &kdf)?;
+        let ciphertext = cipher
+            .encrypt(XNonce::from_slice(&nonce), plaintext)
+            .map_err(|_| anyhow!("Encrypting the wallet file"))?;
+        Ok(Self {
+            kdf,
+            salt: hex::encode(salt),
+            nonce: hex::encode(nonce),
+            ciphertext: hex::encode(ciphertext),
+        })
+    }
+
+    pub fn open(&self, passphrase: &str) -> anyhow::Result<Vec<u8>> {
+        let salt = hex::decode(&self.salt)?;
+        let nonce = hex::decode(&self.nonce)?;
+        if nonce.len() != NONCE_LEN {
+            return Err(anyhow!(
+                "The wallet file nonce should be {} bytes",
+                NONCE_LEN
+            ));
+        }
+        let ciphertext = hex::decode(&self.ciphertext)?;
+        cipher(passphrase, &salt, &self.kdf)?
+            .decrypt(XNonce::from_slice(&nonce), ciphertext.as_slice())
+            .map_err(|_| anyhow!("Wrong passphrase, or the wallet file is corrupt"))
+    }
+
+    /// The whole file, as JSON.
+    pub fn to_json(&self) -> anyhow::Result<String> {
+        Ok(serde_json::to_string_pretty(&EnvelopeRef {
+            encrypted: self,
+        })?)
+    }
+}
+
+fn cipher(passphrase: &str, salt: &[u8], kdf: &KdfParams) -> anyhow::Result<XChaCha20Poly1305> {
+    let params = Params::new(kdf.m_cost, kdf.t_cost, kdf.p_cost, Some(KEY_LEN))
+        .map_err(|err| anyhow!("Bad key derivation parameters: {}", err))?;
+    let mut key = [0u8; KEY_LEN];
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|err| anyhow!("Deriving the wallet key: {}", err))?;
+    Ok(XChaCha20Poly1305::new(Key::from_slice(&key)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn seal_and_open() {
+        let sealed = Encrypted::seal("correct horse", b"{\"wallets\":{}}").unwrap();
+        assert_eq!(sealed.open("correct horse").unwrap(), b"{\"wallets\":{}}");
+        assert!(sealed.open("battery staple").is_err());
+
+        let json = sealed.to_json().unwrap();
+        let envelope: Envelope = serde_json::from_str(&json).unwrap();
+        let again = envelope.encrypted.unwrap();
+        assert_eq!(again.open("correct horse").unwrap(), b"{\"wallets\":{}}");
+
+        // A fresh salt and nonce every time.
+        let other = Encrypted::seal("correct horse", b"{\"wallets\":{}}").unwrap();
+        assert_ne!(other.salt, sealed.salt);
+        assert_ne!(other.ciphertext, sealed.ciphertext);
+    }
+}
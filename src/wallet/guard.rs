@@ -0,0 +1,201 @@
+use crate::units::parse_amount;
+use crate::Raw;
+use anyhow::anyhow;
+use hmac::{Hmac, Mac, NewMac};
+use serde::{Deserialize, Deserializer};
+use sha1::Sha1;
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// The `[walletd]` section of the config file.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct WalletdConfig {
+    /// Sends of more than this need a TOTP code or a confirmation, e.g. `"10nano"`.
+    #[serde(default, deserialize_with = "deserialize_amount")]
+    pub confirm_above: Option<Raw>,
+
+    /// The base32 secret shared with an authenticator app, for sends with a `code`.
+    #[serde(default)]
+    pub totp_secret: Option<String>,
+
+    /// Only clients that authenticate with this token can confirm sends. Without one, any client
+    /// can confirm, which guards against mistakes but not against a compromised client.
+    #[serde(default)]
+    pub confirm_token: Option<String>,
+
+    /// Seconds a send waits to be confirmed before it's dropped.
+    #[serde(default = "default_confirm_timeout")]
+    pub confirm_timeout: u64,
+}
+
+fn default_confirm_timeout() -> u64 {
+    120
+}
+
+fn deserialize_amount<'de, D>(deserializer: D) -> Result<Option<Raw>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let s: Option<String> = Option::deserialize(deserializer)?;
+    s.map(|s| parse_amount(&s))
+        .transpose()
+        .map_err(serde::de::Error::custom)
+}
+
+/// Time based one-time passwords as in RFC 6238, with the 30 second steps and 6 digits that
+/// authenticator apps use.
+pub struct Totp {
+    secret: Vec<u8>,
+}
+
+impl Totp {
+    const STEP: u64 = 30;
+    const DIGITS: u32 = 6;
+
+    /// A secret as shown when setting up an authenticator app, e.g. `JBSWY3DPEHPK3PXP`.
+    pub fn from_base32(s: &str) -> anyhow::Result<Self> {
+        const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+        let mut secret = vec![];
+        let (mut buffer, mut bits) = (0u32, 0);
+        for c in s.chars().filter(|c| !c.is_whitespace() && *c != '=') {
+            let value = ALPHABET
+                .iter()
+                .position(|a| *a as char == c.to_ascii_uppercase())
+                .ok_or_else(|| anyhow!("{:?} isn't a base32 character", c))?;
+            buffer = (buffer << 5) | value as u32;
+            bits += 5;
+            if bits >= 8 {
+                bits -= 8;
+                secret.push((buffer >> bits) as u8);
+            }
+        }
+        if secret.is_empty() {
+            return Err(anyhow!("The TOTP secret is empty"));
+        }
+        Ok(Self { secret })
+    }
+
+    /// The code for the 30 second step `step` since the Unix epoch.
+    pub fn code(&self, step: u64) -> String {
+        let mut mac = Hmac::<Sha1>::new_from_slice(&self.secret).expect("HMAC takes any key");
+        mac.update(&step.to_be_bytes());
+        let hash = mac.finalize().into_bytes();
+        let offset = (hash[hash.len() - 1] & 0xf) as usize;
+        let mut bytes = [0u8; 4];
+        bytes.copy_from_slice(&hash[offset..offset + 4]);
+        let value = (u32::from_be_bytes(bytes) & 0x7fff_ffff) % 10u32.pow(Self::DIGITS);
+        format!("{:0width$}", value, width = Self::DIGITS as usize)
+    }
+
+    /// The step that `code` is for at `now` seconds since the Unix epoch, allowing a step either
+    /// side for clocks that are a little off.
+    pub fn matching_step(&self, code: &str, now: u64) -> Option<u64> {
+        let current = now / Self::STEP;
+        (current.saturating_sub(1)..=current + 1).find(|step| self.code(*step) == code.trim())
+    }
+}
+
+/// Holds back sends of more than an amount until they're confirmed, by a TOTP code along with
+/// the send or from a control socket client afterwards.
+pub struct SendGuard {
+    above: Raw,
+    totp: Option<Totp>,
+    confirm_token: Option<String>,
+    timeout: Duration,
+
+    /// The step of the last code that was used, so a code can't be used twice.
+    last_step: Mutex<Option<u64>>,
+}
+
+impl SendGuard {
+    /// The guard described by `config`, if it has a `confirm_above` amount.
+    pub fn from_config(config: &WalletdConfig) -> anyhow::Result<Option<Self>> {
+        let above = match &config.confirm_above {
+            Some(above) => above.to_owned(),
+            None => return Ok(None),
+        };
+        Ok(Some(Self {
+            above,
+            totp: config
+                .totp_secret
+                .as_deref()
+                .map(Totp::from_base32)
+                .transpose()?,
+            confirm_token: config.confirm_token.to_owned(),
+            timeout: Duration::from_secs(config.confirm_timeout),
+            last_step: Mutex::new(None),
+        }))
+    }
+
+    pub fn above(&self) -> &Raw {
+        &self.above
+    }
+
+    pub fn needs_confirmation(&self, amount: &Raw) -> bool {
+        amount > &self.above
+    }
+
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    pub fn confirm_token(&self) -> Option<&str> {
+        self.confirm_token.as_deref()
+    }
+
+    /// Check a TOTP code at `now` seconds since the Unix epoch, using it up if it's right.
+    pub fn check_code(&self, code: &str, now: u64) -> anyhow::Result<()> {
+        let totp = self
+            .totp
+            .as_ref()
+            .ok_or_else(|| anyhow!("There is no TOTP secret set up to check codes with"))?;
+        let step = totp
+            .matching_step(code, now)
+            .ok_or_else(|| anyhow!("Wrong TOTP code"))?;
+        let mut last_step = self.last_step.lock().unwrap();
+        if matches!(*last_step, Some(last) if step <= last) {
+            return Err(anyhow!("The TOTP code has already been used"));
+        }
+        *last_step = Some(step);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The SHA-1 vectors of RFC 6238, cut down to 6 digits.
+    #[test]
+    fn rfc6238() {
+        let totp = Totp::from_base32("GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ").unwrap();
+        assert_eq!(totp.secret, b"12345678901234567890");
+        for (time, code) in &[
+            (59, "287082"),
+            (1111111109, "081804"),
+            (1234567890, "005924"),
+            (20000000000, "353130"),
+        ] {
+            assert_eq!(totp.code(time / Totp::STEP), *code, "{}", time);
+        }
+        assert_eq!(totp.matching_step("287082", 89), Some(1));
+        assert_eq!(totp.matching_step("287082", 120), None);
+    }
+
+    #[test]
+    fn codes_are_used_once() {
+        let config = WalletdConfig {
+            confirm_above: Some(Raw::from(10)),
+            totp_secret: Some("GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ".into()),
+            confirm_token: None,
+            confirm_timeout: 60,
+        };
+        let guard = SendGuard::from_config(&config).unwrap().unwrap();
+        assert!(!guard.needs_confirmation(&Raw::from(10)));
+        assert!(guard.needs_confirmation(&Raw::from(11)));
+
+        assert!(guard.check_code("000000", 59).is_err());
+        guard.check_code("287082", 59).unwrap();
+        assert!(guard.check_code("287082", 59).is_err());
+    }
+}
@@ -2,7 +2,9 @@
 //!
 //! # Manager
 //! A [WalletManager] is provided to store multiple [Wallet]s of different types. The supported
-//! wallets are [Wallet::Seed], [Wallet::Private], and (TODO) [Wallet::Phrase].
+//! wallets are [Wallet::Seed], [Wallet::Private], and (TODO) [Wallet::Phrase]. A wallet can be
+//! given a name with [WalletManager::set_name], and found again by name or ID with
+//! [WalletManager::find].
 //!
 //! ## Example usage
 //! ```
@@ -31,15 +33,83 @@
 //! # Ok(())
 //! # }
 //! ```
+//!
+//! # Encryption
+//! A wallet file can be encrypted under a passphrase with [WalletManager::with_passphrase] and
+//! [WalletManager::change_passphrase]. Every save replaces the whole file in one step, so the
+//! file is never left half written.
+//!
+//! # Rotation
+//! A [Rotation] moves the funds of a wallet to a fresh seed, which then replaces the old seed.
+//! The old secret is kept in the [WalletManager::archive].
+//!
+//! # Audit log
+//! Operations done with wallet keys can be recorded into an [AuditLog], which lives next to the
+//! wallet file.
+//!
+//! # Payment splitting
+//! A [PaymentSplitter] listens for [WalletEvent]s and forwards shares of each deposit to other
+//! accounts, as described by a [SplitPolicy].
+//!
+//! # Scheduled payments
+//! [ScheduledPayment]s are kept in the wallet file alongside the wallets, and a [PaymentDaemon]
+//! makes them as they become due.
+//!
+//! # Daemon
+//! A [WalletDaemon] serves the wallets of a wallet file to local clients, such as GUIs, over an
+//! authenticated control socket. Each wallet stays locked until a client unlocks it, and only
+//! then are its keys kept in memory. Unlocking only takes the control socket token, since the
+//! passphrase of an encrypted wallet file is given to the daemon when it starts.
+//!
+//! A [SendGuard] holds back sends of large amounts until they come with a TOTP code, or are
+//! confirmed by a client with its own token, so a compromised client can't drain a hot wallet.
+mod audit;
+#[cfg(feature = "rpc_client")]
+mod daemon;
+mod encryption;
+#[cfg(feature = "rpc_client")]
+mod guard;
+#[cfg(feature = "rpc_client")]
+mod rotate;
+mod schedule;
+mod split;
+
+pub use audit::{AuditEntry, AuditLog, AuditOperation, AuditResult};
+#[cfg(feature = "rpc_client")]
+pub use daemon::{
+    load_or_create_token, ControlRequest, ControlResponse, PendingSend, Role, SendOutcome,
+    WalletBackend, WalletDaemon, WalletStatus,
+};
+#[cfg(feature = "rpc_client")]
+pub use guard::{SendGuard, Totp, WalletdConfig};
+#[cfg(feature = "rpc_client")]
+pub use rotate::{MovedAccount, Rotation};
+#[cfg(feature = "rpc_client")]
+pub use schedule::RPCPayer;
+pub use schedule::{Interval, Payer, PaymentDaemon, ScheduledPayment};
+pub use split::{PaymentSplitter, SplitDestination, SplitPolicy, WalletEvent};
+
 use crate::phrase::{Language, MnemonicType};
 use crate::{hexify, Address, Error, Phrase, Private, Public, Seed};
 use anyhow::{anyhow, Context};
+use chrono::{DateTime, Utc};
+use encryption::{Encrypted, Envelope};
 use rand::RngCore;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::fmt::Debug;
 use std::path::PathBuf;
+use std::str::FromStr;
 use tokio::fs::File;
+use tokio::io::AsyncWriteExt;
+
+/// The `[wallet]` section of the config file.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct WalletConfig {
+    /// The difficulty that work for blocks made by wallets is generated at.
+    #[serde(default)]
+    pub work: crate::WorkThresholds,
+}
 
 /// Manages multiple [Wallet]s of different types of [Wallet]s. **Warning**: Wallet files are not
 /// locked (yet).
@@ -48,11 +118,22 @@ use tokio::fs::File;
 /// wallet that just needs to be used by a user without having to track a random [WalletId].
 pub struct WalletManager {
     path: PathBuf,
+    passphrase: Option<String>,
 }
 
 impl WalletManager {
     pub fn new<P: Into<PathBuf>>(path: P) -> Self {
-        Self { path: path.into() }
+        Self {
+            path: path.into(),
+            passphrase: None,
+        }
+    }
+
+    /// Read and write the wallet file encrypted under `passphrase`. A file that isn't encrypted
+    /// yet can still be read, and is encrypted the next time it's saved.
+    pub fn with_passphrase<S: Into<String>>(mut self, passphrase: S) -> Self {
+        self.passphrase = Some(passphrase.into());
+        self
     }
 
     /// This should be called to create the file if it doesn't exists.
@@ -61,29 +142,86 @@ impl WalletManager {
             return Ok(());
         }
 
-        let store = WalletStorage::new();
-        let file = File::create(&self.path).await?;
-        serde_json::to_writer_pretty(file.into_std().await, &store)?;
+        self.save(WalletStorage::new()).await
+    }
 
-        Ok(())
+    /// Whether the wallet file is encrypted with a passphrase.
+    pub async fn is_encrypted(&self) -> anyhow::Result<bool> {
+        let data = self.read().await?;
+        let envelope: Envelope = serde_json::from_str(&data)?;
+        Ok(envelope.encrypted.is_some())
+    }
+
+    /// Encrypt the wallet file under a new passphrase, or decrypt it with `None`.
+    ///
+    /// The file is replaced in one step, so it's never left half written.
+    pub async fn change_passphrase(&mut self, passphrase: Option<String>) -> anyhow::Result<()> {
+        let storage = self.load_unlocked().await?;
+        self.passphrase = passphrase;
+        self.save_unlocked(storage).await
+    }
+
+    async fn read(&self) -> anyhow::Result<String> {
+        tokio::fs::read_to_string(&self.path)
+            .await
+            .with_context(|| format!("Opening {:?}", &self.path))
     }
 
     /// An internal method for loading the wallet storage.
     ///
     /// TODO: There should be a file lock around this.
     async fn load_unlocked(&self) -> anyhow::Result<WalletStorage> {
-        let file = File::open(&self.path)
-            .await
-            .with_context(|| format!("Opening {:?}", &self.path))?;
-        let store: WalletStorage = serde_json::from_reader(&file.into_std().await)?;
+        // Read into a string first, as some types like [crate::Raw] borrow while deserializing.
+        let data = self.read().await?;
+        let envelope: Envelope =
+            serde_json::from_str(&data).with_context(|| format!("Parsing {:?}", &self.path))?;
+        let store: WalletStorage = match envelope.encrypted {
+            Some(encrypted) => {
+                let passphrase = self.passphrase.as_deref().ok_or_else(|| {
+                    anyhow!(
+                        "{:?} is encrypted, so it needs a passphrase to open",
+                        &self.path
+                    )
+                })?;
+                let plaintext = String::from_utf8(encrypted.open(passphrase)?)?;
+                serde_json::from_str(&plaintext)?
+            }
+            None => serde_json::from_str(&data)?,
+        };
         Ok(store)
     }
 
     /// An internal method for save the wallet storage.
     ///
+    /// The storage is written to a temporary file next to the wallet file, which then replaces
+    /// it, so a crash part way through leaves the previous file in place.
+    ///
     /// TODO: There should be a file lock around this.
-    async fn save_unlocked(&self, file: File, store: WalletStorage) -> anyhow::Result<()> {
-        Ok(serde_json::to_writer_pretty(file.into_std().await, &store)?)
+    async fn save_unlocked(&self, store: WalletStorage) -> anyhow::Result<()> {
+        let plain = serde_json::to_string_pretty(&store)?;
+        let data = match &self.passphrase {
+            Some(passphrase) => Encrypted::seal(passphrase, plain.as_bytes())?.to_json()?,
+            None => plain,
+        };
+
+        let mut name = self.path.file_name().unwrap_or_default().to_owned();
+        name.push(".tmp");
+        let temp = self.path.with_file_name(name);
+        let mut file = File::create(&temp)
+            .await
+            .with_context(|| format!("Creating file {:?}", &temp))?;
+        file.write_all(data.as_bytes()).await?;
+        file.sync_all().await?;
+        tokio::fs::rename(&temp, &self.path)
+            .await
+            .with_context(|| format!("Replacing {:?}", &self.path))?;
+        Ok(())
+    }
+
+    /// The ids of every wallet in the file, without reading their secrets into memory.
+    pub async fn ids(&self) -> anyhow::Result<Vec<WalletId>> {
+        let store = self.load_unlocked().await?;
+        Ok(store.wallets.keys().cloned().collect())
     }
 
     pub async fn wallet(&self, reference: &WalletId) -> anyhow::Result<Wallet> {
@@ -91,7 +229,7 @@ impl WalletManager {
         let store = self.load_unlocked().await?;
         Ok(store
             .wallets
-            .get(&reference)
+            .get(reference)
             .ok_or_else(|| anyhow!("Wallet reference not found: {:?}", &reference))?
             .to_owned())
     }
@@ -130,11 +268,7 @@ impl WalletManager {
         }
 
         storage.wallets.insert(reference.clone(), wallet);
-        let file = File::create(&self.path)
-            .await
-            .with_context(|| format!("Creating file {:?}", &self.path))?;
-        self.save_unlocked(file, storage).await?;
-        Ok(())
+        self.save(storage).await
     }
 
     /// If the wallet reference doesn't exist, there will be an error.
@@ -144,11 +278,194 @@ impl WalletManager {
             return Err(anyhow!("Wallet reference doesn't exist: {:?}", &reference));
         }
         storage.wallets.remove(reference);
-        let file = File::create(&self.path)
-            .await
-            .with_context(|| format!("Creating file {:?}", &self.path))?;
-        self.save_unlocked(file, storage).await?;
-        Ok(())
+        storage.names.retain(|_, id| id != reference);
+        self.save(storage).await
+    }
+
+    /// Find a wallet by its name, or by its ID in hex.
+    pub async fn find(&self, reference: &str) -> anyhow::Result<WalletId> {
+        let storage = self.load_unlocked().await?;
+        if let Some(id) = storage.names.get(reference) {
+            return Ok(id.to_owned());
+        }
+        match WalletId::from_str(reference) {
+            Ok(id) if storage.wallets.contains_key(&id) => Ok(id),
+            _ => Err(anyhow!(
+                "There's no wallet named or with the ID {:?}",
+                reference
+            )),
+        }
+    }
+
+    /// Name a wallet, so other commands can refer to it by name instead of by its ID. This
+    /// replaces any name the wallet already had.
+    pub async fn set_name(&self, reference: &WalletId, name: &str) -> anyhow::Result<()> {
+        if name.is_empty() || name.contains(char::is_whitespace) {
+            return Err(anyhow!("Invalid wallet name: {:?}", name));
+        }
+        if WalletId::from_str(name).is_ok() {
+            return Err(anyhow!(
+                "A wallet name can't look like a wallet ID: {:?}",
+                name
+            ));
+        }
+        let mut storage = self.load_unlocked().await?;
+        if !storage.wallets.contains_key(reference) {
+            return Err(anyhow!("Wallet reference doesn't exist: {:?}", &reference));
+        }
+        if matches!(storage.names.get(name), Some(id) if id != reference) {
+            return Err(anyhow!("Another wallet is already named {:?}", name));
+        }
+        storage.names.retain(|_, id| id != reference);
+        storage.names.insert(name.to_owned(), reference.to_owned());
+        self.save(storage).await
+    }
+
+    /// Every wallet in the file with its name and the kind of secret it holds, sorted by name
+    /// and then by ID.
+    pub async fn list(&self) -> anyhow::Result<Vec<WalletSummary>> {
+        let storage = self.load_unlocked().await?;
+        let mut summaries: Vec<WalletSummary> = storage
+            .wallets
+            .iter()
+            .map(|(id, wallet)| WalletSummary {
+                id: id.to_owned(),
+                name: storage
+                    .names
+                    .iter()
+                    .find(|(_, named)| *named == id)
+                    .map(|(name, _)| name.to_owned()),
+                kind: wallet.kind(),
+            })
+            .collect();
+        summaries.sort_by_key(|s| (s.name.is_none(), s.name.to_owned(), s.id.to_string()));
+        Ok(summaries)
+    }
+
+    /// Named addresses, so payments can be sent to `@name`.
+    pub async fn contacts(&self) -> anyhow::Result<BTreeMap<String, Address>> {
+        Ok(self.load_unlocked().await?.contacts)
+    }
+
+    /// Add or replace a contact.
+    pub async fn add_contact(&self, name: &str, address: Address) -> anyhow::Result<()> {
+        let name = name.trim_start_matches('@');
+        if name.is_empty() || name.contains(char::is_whitespace) {
+            return Err(anyhow!("Invalid contact name: {:?}", name));
+        }
+        let mut storage = self.load_unlocked().await?;
+        storage.contacts.insert(name.to_owned(), address);
+        self.save(storage).await
+    }
+
+    pub async fn delete_contact(&self, name: &str) -> anyhow::Result<()> {
+        let mut storage = self.load_unlocked().await?;
+        let name = name.trim_start_matches('@');
+        if storage.contacts.remove(name).is_none() {
+            return Err(anyhow!("Contact doesn't exist: {:?}", name));
+        }
+        self.save(storage).await
+    }
+
+    /// Resolve `@name` using the contacts, or parse an address.
+    pub async fn resolve(&self, destination: &str) -> anyhow::Result<Address> {
+        match destination.strip_prefix('@') {
+            Some(name) => self
+                .contacts()
+                .await?
+                .get(name)
+                .cloned()
+                .ok_or_else(|| anyhow!("Unknown contact: @{}", name)),
+            None => Ok(Address::from_str(destination)?),
+        }
+    }
+
+    pub async fn schedules(&self) -> anyhow::Result<Vec<ScheduledPayment>> {
+        Ok(self.load_unlocked().await?.schedules)
+    }
+
+    /// Add a scheduled payment, returning its new ID.
+    pub async fn add_schedule(&self, mut schedule: ScheduledPayment) -> anyhow::Result<u32> {
+        let mut storage = self.load_unlocked().await?;
+        if !storage.wallets.contains_key(&schedule.wallet) {
+            return Err(anyhow!(
+                "Wallet reference not found: {:?}",
+                &schedule.wallet
+            ));
+        }
+        schedule.id = storage.schedules.iter().map(|s| s.id).max().unwrap_or(0) + 1;
+        let id = schedule.id;
+        storage.schedules.push(schedule);
+        self.save(storage).await?;
+        Ok(id)
+    }
+
+    /// Replace the scheduled payment with the same ID.
+    pub async fn save_schedule(&self, schedule: &ScheduledPayment) -> anyhow::Result<()> {
+        let mut storage = self.load_unlocked().await?;
+        let existing = storage
+            .schedules
+            .iter_mut()
+            .find(|s| s.id == schedule.id)
+            .ok_or_else(|| anyhow!("Scheduled payment doesn't exist: #{}", schedule.id))?;
+        *existing = schedule.to_owned();
+        self.save(storage).await
+    }
+
+    pub async fn delete_schedule(&self, id: u32) -> anyhow::Result<()> {
+        let mut storage = self.load_unlocked().await?;
+        let len = storage.schedules.len();
+        storage.schedules.retain(|s| s.id != id);
+        if storage.schedules.len() == len {
+            return Err(anyhow!("Scheduled payment doesn't exist: #{}", id));
+        }
+        self.save(storage).await
+    }
+
+    /// Start rotating a wallet to a fresh seed, returning the new seed. The new seed is saved
+    /// before any funds are moved to it, and a rotation that was interrupted carries on with
+    /// the same seed.
+    pub async fn begin_rotation(&self, id: &WalletId) -> anyhow::Result<Wallet> {
+        let mut storage = self.load_unlocked().await?;
+        if !storage.wallets.contains_key(id) {
+            return Err(anyhow!("Wallet reference not found: {:?}", id));
+        }
+        if let Some(wallet) = storage.rotating.get(id) {
+            return Ok(wallet.to_owned());
+        }
+        let wallet = Wallet::Seed(Seed::random());
+        storage.rotating.insert(id.to_owned(), wallet.clone());
+        self.save(storage).await?;
+        Ok(wallet)
+    }
+
+    /// Replace the wallet with the seed from [WalletManager::begin_rotation], keeping the old
+    /// secret in the archive.
+    pub async fn finish_rotation(&self, id: &WalletId) -> anyhow::Result<()> {
+        let mut storage = self.load_unlocked().await?;
+        let new = storage
+            .rotating
+            .remove(id)
+            .ok_or_else(|| anyhow!("Wallet {:?} isn't being rotated", id))?;
+        let old = storage
+            .wallets
+            .insert(id.to_owned(), new)
+            .ok_or_else(|| anyhow!("Wallet reference not found: {:?}", id))?;
+        storage.archive.push(ArchivedWallet {
+            id: id.to_owned(),
+            wallet: old,
+            archived: Utc::now(),
+        });
+        self.save(storage).await
+    }
+
+    /// The secrets that were rotated out, oldest first.
+    pub async fn archive(&self) -> anyhow::Result<Vec<ArchivedWallet>> {
+        Ok(self.load_unlocked().await?.archive)
+    }
+
+    async fn save(&self, storage: WalletStorage) -> anyhow::Result<()> {
+        self.save_unlocked(storage).await
     }
 }
 
@@ -166,6 +483,15 @@ pub enum Wallet {
 }
 
 impl Wallet {
+    /// `seed`, `phrase` or `private`.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Wallet::Phrase(_) => "phrase",
+            Wallet::Seed(_) => "seed",
+            Wallet::Private(_) => "private",
+        }
+    }
+
     /// Derive or return a private key for this wallet.
     pub fn private(&self, index: u32) -> Result<Private, Error> {
         match &self {
@@ -176,7 +502,7 @@ impl Wallet {
                 }
                 Ok(private.to_owned())
             }
-            Wallet::Phrase(phrase) => Ok(phrase.to_private(index, "")?),
+            Wallet::Phrase(phrase) => Ok(phrase.to_bip44_private(index)?),
         }
     }
 
@@ -189,18 +515,71 @@ impl Wallet {
     pub fn address(&self, index: u32) -> anyhow::Result<Address> {
         Ok(self.public(index)?.to_address())
     }
+
+    /// The experimental camo keys of an account in this wallet. See [crate::camo].
+    #[cfg(feature = "camo")]
+    pub fn camo_keys(&self, index: u32) -> Result<crate::camo::CamoKeys, Error> {
+        crate::camo::CamoKeys::from_private(&self.private(index)?)
+    }
 }
 
 /// Storage for all wallets.
 #[derive(Debug, Serialize, Deserialize)]
 pub struct WalletStorage {
     wallets: HashMap<WalletId, Wallet>,
+
+    /// Names given to wallets, so they don't have to be referred to by ID.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    names: BTreeMap<String, WalletId>,
+
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    contacts: BTreeMap<String, Address>,
+
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    schedules: Vec<ScheduledPayment>,
+
+    /// The new seeds of wallets part way through being rotated.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    rotating: HashMap<WalletId, Wallet>,
+
+    /// Secrets that were rotated out, in case funds arrive after the rotation.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    archive: Vec<ArchivedWallet>,
+}
+
+/// A wallet as listed by [WalletManager::list], without its secret.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct WalletSummary {
+    pub id: WalletId,
+    pub name: Option<String>,
+
+    /// The [Wallet::kind].
+    pub kind: &'static str,
+}
+
+/// A secret that was replaced by [WalletManager::finish_rotation].
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ArchivedWallet {
+    pub id: WalletId,
+    pub wallet: Wallet,
+    pub archived: DateTime<Utc>,
+}
+
+impl Default for WalletStorage {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl WalletStorage {
     pub fn new() -> Self {
         Self {
             wallets: Default::default(),
+            names: Default::default(),
+            contacts: Default::default(),
+            schedules: Default::default(),
+            rotating: Default::default(),
+            archive: Default::default(),
         }
     }
 }
@@ -230,6 +609,7 @@ impl WalletId {
 mod tests {
     use super::*;
     use std::fs::remove_file;
+    use std::path::Path;
     use std::str::FromStr;
 
     /// Remove the wallet file when dropped.
@@ -258,6 +638,55 @@ mod tests {
         assert_eq!(w1.address(0).unwrap(), w2.address(0).unwrap())
     }
 
+    #[tokio::test]
+    async fn contacts() {
+        let (_clean, manager) = prepare("contacts.wallet").await;
+        let address = Seed::zero().derive(0).to_address().unwrap();
+        manager
+            .add_contact("@alice", address.clone())
+            .await
+            .unwrap();
+        assert_eq!(manager.resolve("@alice").await.unwrap(), address);
+        assert_eq!(
+            manager.resolve(&address.to_string()).await.unwrap(),
+            address
+        );
+        assert!(manager.resolve("@bob").await.is_err());
+
+        manager.delete_contact("alice").await.unwrap();
+        assert!(manager.contacts().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn names() {
+        let (_clean, manager) = prepare("names.wallet").await;
+        let id = WalletId::random();
+        manager.add_random_seed(id.to_owned()).await.unwrap();
+        manager.add_random_private(WalletId::zero()).await.unwrap();
+        manager.set_name(&id, "savings").await.unwrap();
+
+        assert_eq!(manager.find("savings").await.unwrap(), id);
+        assert_eq!(manager.find(&id.to_string()).await.unwrap(), id);
+        assert!(manager.find("spending").await.is_err());
+        assert!(manager
+            .set_name(&WalletId::zero(), "savings")
+            .await
+            .is_err());
+        assert!(manager.set_name(&id, "two words").await.is_err());
+
+        let list = manager.list().await.unwrap();
+        assert_eq!(list[0].name.as_deref(), Some("savings"));
+        assert_eq!(list[0].kind, "seed");
+        assert_eq!(list[1].id, WalletId::zero());
+        assert_eq!(list[1].name, None);
+
+        // Renaming drops the old name, and so does deleting.
+        manager.set_name(&id, "cold").await.unwrap();
+        assert!(manager.find("savings").await.is_err());
+        manager.delete(&id).await.unwrap();
+        assert!(manager.find("cold").await.is_err());
+    }
+
     #[tokio::test]
     async fn import_seed() {
         let (_clean, manager) = prepare("import_seed.wallet").await;
@@ -274,4 +703,32 @@ mod tests {
                 .unwrap()
         );
     }
+
+    #[tokio::test]
+    async fn change_passphrase() {
+        let (_clean, mut manager) = prepare("passphrase.wallet").await;
+        let wallet = manager.add_random_seed(WalletId::zero()).await.unwrap();
+        assert!(!manager.is_encrypted().await.unwrap());
+
+        manager
+            .change_passphrase(Some("hunter2".into()))
+            .await
+            .unwrap();
+        assert!(manager.is_encrypted().await.unwrap());
+        let contents = std::fs::read_to_string("passphrase.wallet").unwrap();
+        assert!(!contents.contains("wallets"));
+        assert!(!Path::new("passphrase.wallet.tmp").exists());
+
+        let locked = WalletManager::new("passphrase.wallet");
+        assert!(locked.wallet(&WalletId::zero()).await.is_err());
+        let wrong = WalletManager::new("passphrase.wallet").with_passphrase("hunter3");
+        assert!(wrong.wallet(&WalletId::zero()).await.is_err());
+        let unlocked = WalletManager::new("passphrase.wallet").with_passphrase("hunter2");
+        let opened = unlocked.wallet(&WalletId::zero()).await.unwrap();
+        assert_eq!(opened.address(0).unwrap(), wallet.address(0).unwrap());
+
+        manager.change_passphrase(None).await.unwrap();
+        assert!(!locked.is_encrypted().await.unwrap());
+        locked.wallet(&WalletId::zero()).await.unwrap();
+    }
 }
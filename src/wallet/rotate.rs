@@ -0,0 +1,327 @@
+use crate::blocks::BlockHash;
+use crate::wallet::{
+    AuditEntry, AuditLog, AuditOperation, AuditResult, Wallet, WalletBackend, WalletId,
+    WalletManager,
+};
+use crate::{Address, Private, Raw};
+use futures::future::join_all;
+use tracing::info;
+
+/// The funds of one account that were moved by a [Rotation].
+#[derive(Debug, Clone, PartialEq)]
+pub struct MovedAccount {
+    pub index: u32,
+    pub from: Address,
+    pub to: Address,
+    pub amount: Raw,
+    pub block: BlockHash,
+}
+
+/// Moves every account of a wallet over to a fresh seed, for `feeless wallet rotate`.
+///
+/// Accounts are looked at in batches, starting from index 0, until a run of unused accounts as
+/// long as the gap limit. An account is used once it has been opened, even if it has been
+/// emptied since. Each account first receives what is pending, then sends its whole
+/// balance to the account with the same index of the new seed, which receives it afterwards.
+/// Once everything is moved, the new seed replaces the old one under the same [WalletId] and
+/// the old secret is archived.
+pub struct Rotation<'a, B> {
+    manager: &'a WalletManager,
+    backend: &'a B,
+    gap: u32,
+    batch: usize,
+    audit: Option<AuditLog>,
+}
+
+impl<'a, B: WalletBackend + Sync> Rotation<'a, B> {
+    pub fn new(manager: &'a WalletManager, backend: &'a B) -> Self {
+        Self {
+            manager,
+            backend,
+            gap: 20,
+            batch: 8,
+            audit: None,
+        }
+    }
+
+    /// How many unused accounts in a row end the search for accounts to move.
+    pub fn gap(mut self, gap: u32) -> Self {
+        self.gap = gap.max(1);
+        self
+    }
+
+    /// How many accounts are moved at the same time.
+    pub fn batch(mut self, batch: usize) -> Self {
+        self.batch = batch.max(1);
+        self
+    }
+
+    /// Record each send in an audit log.
+    pub fn audit(mut self, log: AuditLog) -> Self {
+        self.audit = Some(log);
+        self
+    }
+
+    /// Rotate the wallet `id`, returning the accounts that had funds moved.
+    ///
+    /// When this fails part way through, running it again carries on with the same new seed.
+    pub async fn run(&self, id: &WalletId) -> anyhow::Result<Vec<MovedAccount>> {
+        let old = self.manager.wallet(id).await?;
+        let new = self.manager.begin_rotation(id).await?;
+
+        let mut moved = vec![];
+        let mut unused = 0;
+        let mut start = 0u32;
+        'search: while unused < self.gap {
+            let indexes = start..start.saturating_add(self.batch as u32);
+            start = indexes.end;
+            let results =
+                join_all(indexes.map(|index| self.move_account(id, &old, &new, index))).await;
+            for result in results {
+                match result? {
+                    // The end of a wallet with a single key.
+                    None => break 'search,
+                    Some((used, account)) => {
+                        unused = if used { 0 } else { unused + 1 };
+                        moved.extend(account);
+                        if unused >= self.gap {
+                            break 'search;
+                        }
+                    }
+                }
+            }
+        }
+
+        let receives = moved
+            .iter()
+            .map(|account| new.private(account.index))
+            .collect::<Result<Vec<_>, _>>()?;
+        for result in join_all(receives.iter().map(|to| self.receive_all(to))).await {
+            result?;
+        }
+
+        self.manager.finish_rotation(id).await?;
+        Ok(moved)
+    }
+
+    /// Move the funds of account `index`. Returns `None` when the wallet has no such account,
+    /// and otherwise whether the account has been used along with what was moved.
+    async fn move_account(
+        &self,
+        id: &WalletId,
+        old: &Wallet,
+        new: &Wallet,
+        index: u32,
+    ) -> anyhow::Result<Option<(bool, Option<MovedAccount>)>> {
+        let from = match old.private(index) {
+            Ok(private) => private,
+            Err(_) if index > 0 => return Ok(None),
+            Err(err) => return Err(err.into()),
+        };
+        let received = self.receive_all(&from).await?;
+        let account = from.to_address()?;
+        let balance = self.backend.balance(&account).await?;
+        if balance == Raw::zero() {
+            let used = received > 0 || self.backend.is_opened(&account).await?;
+            return Ok(Some((used, None)));
+        }
+
+        let to = new.address(index)?;
+        let result = self.backend.pay(&from, &to, &balance).await;
+        self.record(id, &account, &to, &balance, &result).await?;
+        let block = result?;
+        info!("Moved {} raw from {} to {}", balance, account, to);
+        Ok(Some((
+            true,
+            Some(MovedAccount {
+                index,
+                from: account,
+                to,
+                amount: balance,
+                block,
+            }),
+        )))
+    }
+
+    /// Receive everything pending, a batch of blocks at a time. Returns how many were received.
+    async fn receive_all(&self, to: &Private) -> anyhow::Result<usize> {
+        let mut received = 0;
+        loop {
+            let blocks = self.backend.receive(to).await?;
+            if blocks.is_empty() {
+                return Ok(received);
+            }
+            received += blocks.len();
+        }
+    }
+
+    async fn record(
+        &self,
+        id: &WalletId,
+        account: &Address,
+        to: &Address,
+        amount: &Raw,
+        result: &anyhow::Result<BlockHash>,
+    ) -> anyhow::Result<()> {
+        let log = match &self.audit {
+            Some(log) => log,
+            None => return Ok(()),
+        };
+        let result_entry = match result {
+            Ok(_) => AuditResult::Ok,
+            Err(err) => AuditResult::Error(format!("{:#}", err)),
+        };
+        let mut entry = AuditEntry::new(id.to_owned(), AuditOperation::Broadcast, result_entry)
+            .amount(amount.to_owned())
+            .destination(to.to_owned());
+        entry.account = Some(account.to_owned());
+        entry.block = result.as_ref().ok().cloned();
+        entry.rpc = self.backend.describe();
+        log.record(&entry).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rpc::calls::AccountHistoryEntry;
+    use crate::wallet::Payer;
+    use async_trait::async_trait;
+    use std::collections::{HashMap, HashSet};
+    use std::path::PathBuf;
+    use std::sync::Mutex;
+
+    /// Balances and pending amounts by account, which move around as blocks are made.
+    #[derive(Default)]
+    struct FakeLedger {
+        balances: Mutex<HashMap<Address, Raw>>,
+        pending: Mutex<HashMap<Address, Vec<Raw>>>,
+        opened: Mutex<HashSet<Address>>,
+    }
+
+    #[async_trait]
+    impl Payer for FakeLedger {
+        async fn pay(
+            &self,
+            from: &Private,
+            to: &Address,
+            amount: &Raw,
+        ) -> anyhow::Result<BlockHash> {
+            let from = from.to_address()?;
+            self.opened.lock().unwrap().insert(from.to_owned());
+            let mut balances = self.balances.lock().unwrap();
+            let balance = balances.get(&from).cloned().unwrap_or_else(Raw::zero);
+            balances.insert(from, balance.checked_sub(amount).unwrap());
+            self.pending
+                .lock()
+                .unwrap()
+                .entry(to.to_owned())
+                .or_default()
+                .push(amount.to_owned());
+            Ok(BlockHash::zero())
+        }
+    }
+
+    #[async_trait]
+    impl WalletBackend for FakeLedger {
+        /// Receives at most two blocks at a time.
+        async fn receive(&self, to: &Private) -> anyhow::Result<Vec<BlockHash>> {
+            let account = to.to_address()?;
+            let mut pending = self.pending.lock().unwrap();
+            let amounts = pending.entry(account.to_owned()).or_default();
+            let batch: Vec<Raw> = amounts.drain(..amounts.len().min(2)).collect();
+            if !batch.is_empty() {
+                self.opened.lock().unwrap().insert(account.to_owned());
+            }
+            let mut balances = self.balances.lock().unwrap();
+            let balance = balances.entry(account).or_insert_with(Raw::zero);
+            for amount in &batch {
+                *balance = balance.checked_add(amount).unwrap();
+            }
+            Ok(batch.iter().map(|_| BlockHash::zero()).collect())
+        }
+
+        async fn balance(&self, account: &Address) -> anyhow::Result<Raw> {
+            Ok(self
+                .balances
+                .lock()
+                .unwrap()
+                .get(account)
+                .cloned()
+                .unwrap_or_else(Raw::zero))
+        }
+
+        async fn is_opened(&self, account: &Address) -> anyhow::Result<bool> {
+            Ok(self.opened.lock().unwrap().contains(account))
+        }
+
+        async fn history(&self, _: &Address, _: i64) -> anyhow::Result<Vec<AccountHistoryEntry>> {
+            Ok(vec![])
+        }
+    }
+
+    #[tokio::test]
+    async fn moves_every_used_account() {
+        let path = PathBuf::from("rotate.wallet");
+        if path.exists() {
+            std::fs::remove_file(&path).unwrap();
+        }
+        let manager = WalletManager::new(&path);
+        manager.ensure().await.unwrap();
+        let old = manager.add_random_seed(WalletId::zero()).await.unwrap();
+
+        let ledger = FakeLedger::default();
+        let address = |index| old.address(index).unwrap();
+        ledger
+            .balances
+            .lock()
+            .unwrap()
+            .insert(address(0), Raw::from(100));
+        // Past a gap of two unused accounts, which is within the gap limit of three.
+        ledger
+            .pending
+            .lock()
+            .unwrap()
+            .insert(address(3), vec![Raw::from(1), Raw::from(2), Raw::from(3)]);
+        // Emptied, which still counts as used, so the gap starts again before account 8.
+        ledger.opened.lock().unwrap().insert(address(6));
+        ledger
+            .balances
+            .lock()
+            .unwrap()
+            .insert(address(8), Raw::from(50));
+        ledger
+            .pending
+            .lock()
+            .unwrap()
+            .insert(address(12), vec![Raw::from(1000)]);
+
+        let moved = Rotation::new(&manager, &ledger)
+            .gap(3)
+            .batch(2)
+            .run(&WalletId::zero())
+            .await
+            .unwrap();
+
+        let new = manager.wallet(&WalletId::zero()).await.unwrap();
+        let archive = manager.archive().await.unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(moved.len(), 3);
+        assert_eq!(moved[0].index, 0);
+        assert_eq!(moved[0].to, new.address(0).unwrap());
+        assert_eq!(moved[1].index, 3);
+        assert_eq!(moved[1].amount, Raw::from(6));
+        assert_eq!(moved[2].index, 8);
+        assert_eq!(archive.len(), 1);
+        assert_eq!(archive[0].wallet.address(0).unwrap(), address(0));
+
+        let balance = |account| ledger.balances.lock().unwrap().get(&account).cloned();
+        assert_eq!(balance(address(0)), Some(Raw::zero()));
+        assert_eq!(balance(new.address(0).unwrap()), Some(Raw::from(100)));
+        assert_eq!(balance(new.address(3).unwrap()), Some(Raw::from(6)));
+        assert_eq!(balance(new.address(8).unwrap()), Some(Raw::from(50)));
+        // Too far past the gap.
+        assert_eq!(balance(new.address(12).unwrap()), None);
+    }
+}
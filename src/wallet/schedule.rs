@@ -0,0 +1,541 @@
+use crate::blocks::BlockHash;
+use crate::wallet::{AuditEntry, AuditLog, AuditOperation, AuditResult, WalletId, WalletManager};
+use crate::{Address, Error, Private, Raw};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+use std::time::Duration;
+use tracing::{info, warn};
+
+/// The first retry of a failed payment is after this long, doubling after each failure.
+const RETRY_BACKOFF: Duration = Duration::from_secs(30);
+
+/// Failed payments are retried at least this often.
+const MAX_RETRY_BACKOFF: Duration = Duration::from_secs(60 * 60);
+
+/// How often a recurring payment is made, e.g. `7d`, `12h`, `2w`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Interval(u64);
+
+impl Interval {
+    const UNITS: [(char, u64); 5] = [
+        ('w', 7 * 24 * 60 * 60),
+        ('d', 24 * 60 * 60),
+        ('h', 60 * 60),
+        ('m', 60),
+        ('s', 1),
+    ];
+
+    pub fn from_secs(secs: u64) -> Self {
+        Self(secs)
+    }
+
+    pub fn as_secs(&self) -> u64 {
+        self.0
+    }
+
+    fn to_chrono(self) -> chrono::Duration {
+        chrono::Duration::seconds(self.0 as i64)
+    }
+}
+
+impl FromStr for Interval {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let unit = s
+            .chars()
+            .last()
+            .ok_or_else(|| Error::InvalidInterval("Empty interval".into()))?;
+        let multiplier = Self::UNITS
+            .iter()
+            .find(|(c, _)| *c == unit)
+            .map(|(_, secs)| *secs)
+            .ok_or_else(|| {
+                Error::InvalidInterval(format!("{:?} should end with one of w, d, h, m, s", s))
+            })?;
+        let count: u64 = s[..s.len() - 1]
+            .parse()
+            .map_err(|_| Error::InvalidInterval(format!("{:?} should be like 7d", s)))?;
+        if count == 0 {
+            return Err(Error::InvalidInterval("Interval can't be zero".into()));
+        }
+        Ok(Self(count * multiplier))
+    }
+}
+
+impl Display for Interval {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let (unit, secs) = Self::UNITS
+            .iter()
+            .find(|(_, secs)| self.0.is_multiple_of(*secs))
+            .expect("Everything is a multiple of a second");
+        write!(f, "{}{}", self.0 / secs, unit)
+    }
+}
+
+impl Serialize for Interval {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for Interval {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        crate::encoding::deserialize_from_string(deserializer)
+    }
+}
+
+/// A payment that is made every [Interval], stored in the wallet file.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScheduledPayment {
+    /// Assigned when the schedule is added to a [WalletManager].
+    pub id: u32,
+
+    pub wallet: WalletId,
+
+    /// The key index in the wallet to pay from.
+    pub index: u32,
+
+    pub to: Address,
+    pub amount: Raw,
+    pub every: Interval,
+
+    /// When the next payment should be made. If this is in the past, the payment is overdue.
+    pub next_due: DateTime<Utc>,
+
+    /// Number of times in a row the due payment has failed.
+    #[serde(default)]
+    pub failures: u32,
+
+    /// Don't try to make the payment again until this time.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retry_at: Option<DateTime<Utc>>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_error: Option<String>,
+}
+
+impl ScheduledPayment {
+    pub fn new(
+        wallet: WalletId,
+        index: u32,
+        to: Address,
+        amount: Raw,
+        every: Interval,
+        first_due: DateTime<Utc>,
+    ) -> Self {
+        Self {
+            id: 0,
+            wallet,
+            index,
+            to,
+            amount,
+            every,
+            next_due: first_due,
+            failures: 0,
+            retry_at: None,
+            last_error: None,
+        }
+    }
+
+    pub fn is_due(&self, now: DateTime<Utc>) -> bool {
+        self.next_due <= now && self.retry_at.is_none_or(|retry_at| retry_at <= now)
+    }
+
+    /// The due payment was made, so move on to the next one.
+    pub fn paid(&mut self) {
+        self.next_due = self.next_due + self.every.to_chrono();
+        self.failures = 0;
+        self.retry_at = None;
+        self.last_error = None;
+    }
+
+    /// The due payment failed, so back off before trying it again.
+    pub fn failed(&mut self, now: DateTime<Utc>, error: String) {
+        self.failures += 1;
+        let backoff = RETRY_BACKOFF
+            .checked_mul(1 << (self.failures - 1).min(16))
+            .unwrap_or(MAX_RETRY_BACKOFF)
+            .min(MAX_RETRY_BACKOFF);
+        self.retry_at =
+            Some(now + chrono::Duration::from_std(backoff).expect("Backoff fits in chrono"));
+        self.last_error = Some(error);
+    }
+}
+
+impl Display for ScheduledPayment {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "#{} {} raw from {}/{} to {} every {}, next due {}",
+            self.id,
+            self.amount,
+            self.wallet,
+            self.index,
+            self.to,
+            self.every,
+            self.next_due
+                .to_rfc3339_opts(chrono::SecondsFormat::Secs, true)
+        )?;
+        if let Some(error) = &self.last_error {
+            write!(f, " (failed {} times: {})", self.failures, error)?;
+        }
+        Ok(())
+    }
+}
+
+/// Something that can send a payment to the network.
+#[async_trait]
+pub trait Payer {
+    async fn pay(&self, from: &Private, to: &Address, amount: &Raw) -> anyhow::Result<BlockHash>;
+
+    /// Where payments are sent, for the audit log.
+    fn describe(&self) -> Option<String> {
+        None
+    }
+}
+
+/// Makes scheduled payments from a wallet file as they become due.
+pub struct PaymentDaemon<P> {
+    manager: WalletManager,
+    payer: P,
+    audit: Option<AuditLog>,
+}
+
+impl<P: Payer + Sync> PaymentDaemon<P> {
+    pub fn new(manager: WalletManager, payer: P) -> Self {
+        Self {
+            manager,
+            payer,
+            audit: None,
+        }
+    }
+
+    /// Record each payment in an audit log.
+    pub fn audit(mut self, log: AuditLog) -> Self {
+        self.audit = Some(log);
+        self
+    }
+
+    /// Make every payment that is due at `now`, returning the number of payments made.
+    ///
+    /// Payments that were missed while the daemon wasn't running are all caught up on, one
+    /// payment per missed interval. The schedule is saved after every payment so a crash can't
+    /// cause a payment to be made twice.
+    pub async fn run_due(&self, now: DateTime<Utc>) -> anyhow::Result<usize> {
+        let mut made = 0;
+        for mut schedule in self.manager.schedules().await? {
+            while schedule.is_due(now) {
+                let wallet = self.manager.wallet(&schedule.wallet).await?;
+                let result = match wallet.private(schedule.index) {
+                    Ok(private) => {
+                        self.payer
+                            .pay(&private, &schedule.to, &schedule.amount)
+                            .await
+                    }
+                    Err(err) => Err(err.into()),
+                };
+                self.record(&schedule, &wallet.address(schedule.index).ok(), &result)
+                    .await?;
+
+                let paid = result.is_ok();
+                match result {
+                    Ok(hash) => {
+                        info!("Scheduled payment #{} sent in {:?}", schedule.id, hash);
+                        schedule.paid();
+                        made += 1;
+                    }
+                    Err(err) => {
+                        warn!("Scheduled payment #{} failed: {:#}", schedule.id, err);
+                        schedule.failed(now, format!("{:#}", err));
+                    }
+                }
+                self.manager.save_schedule(&schedule).await?;
+                if !paid {
+                    break;
+                }
+            }
+        }
+        Ok(made)
+    }
+
+    /// Check for due payments every `poll` interval, forever.
+    pub async fn run(&self, poll: Duration) -> anyhow::Result<()> {
+        loop {
+            self.run_due(Utc::now()).await?;
+            tokio::time::sleep(poll).await;
+        }
+    }
+
+    async fn record(
+        &self,
+        schedule: &ScheduledPayment,
+        account: &Option<Address>,
+        result: &anyhow::Result<BlockHash>,
+    ) -> anyhow::Result<()> {
+        let log = match &self.audit {
+            Some(log) => log,
+            None => return Ok(()),
+        };
+        let result_entry = match result {
+            Ok(_) => AuditResult::Ok,
+            Err(err) => AuditResult::Error(format!("{:#}", err)),
+        };
+        let mut entry = AuditEntry::new(
+            schedule.wallet.to_owned(),
+            AuditOperation::Broadcast,
+            result_entry,
+        )
+        .amount(schedule.amount.to_owned())
+        .destination(schedule.to.to_owned());
+        entry.account = account.to_owned();
+        entry.block = result.as_ref().ok().cloned();
+        entry.rpc = self.payer.describe();
+        log.record(&entry).await
+    }
+}
+
+/// Sends payments through an RPC server, generating the work locally.
+#[cfg(feature = "rpc_client")]
+pub struct RPCPayer {
+    pub(super) client: crate::rpc::client::RPCClient,
+    url: String,
+    thresholds: crate::WorkThresholds,
+
+    /// The `process` requests that weren't sent because of [RPCPayer::dry_run].
+    rehearsed: Option<std::sync::Mutex<Vec<serde_json::Value>>>,
+}
+
+#[cfg(feature = "rpc_client")]
+impl RPCPayer {
+    pub fn new(client: crate::rpc::client::RPCClient, url: String) -> Self {
+        Self {
+            client,
+            url,
+            thresholds: crate::WorkThresholds::default(),
+            rehearsed: None,
+        }
+    }
+
+    /// Build, sign and generate work for blocks as usual, but keep the `process` requests
+    /// instead of sending them, for [RPCPayer::rehearsed]. The hashes returned are of blocks
+    /// that were never published.
+    pub fn dry_run(mut self, dry_run: bool) -> Self {
+        self.rehearsed = if dry_run {
+            Some(std::sync::Mutex::new(vec![]))
+        } else {
+            None
+        };
+        self
+    }
+
+    /// Take the requests that a dry run would have sent, oldest first.
+    pub fn rehearsed(&self) -> Vec<serde_json::Value> {
+        match &self.rehearsed {
+            Some(rehearsed) => std::mem::take(&mut *rehearsed.lock().unwrap()),
+            None => vec![],
+        }
+    }
+
+    /// The difficulty to generate work at for each kind of block, instead of the base
+    /// thresholds.
+    pub fn thresholds(mut self, thresholds: crate::WorkThresholds) -> Self {
+        self.thresholds = thresholds;
+        self
+    }
+}
+
+#[cfg(feature = "rpc_client")]
+impl RPCPayer {
+    /// Sign `block`, generate its work at the threshold for `subtype` and send it to the RPC
+    /// server.
+    pub(super) async fn publish(
+        &self,
+        subtype: crate::blocks::Subtype,
+        mut block: crate::blocks::StateBlock,
+        from: &Private,
+    ) -> anyhow::Result<BlockHash> {
+        use crate::rpc::calls::ProcessRequest;
+        use crate::rpc::client::RPCRequest;
+        use crate::Work;
+        use anyhow::Context;
+
+        block.signature = Some(from.sign(block.hash.as_bytes())?);
+        let root = block.work_root();
+        let difficulty = self.thresholds.difficulty(&subtype);
+        block.work = Some(
+            tokio::task::spawn_blocking(move || Work::generate(&root, &difficulty))
+                .await
+                .context("Generating work")??,
+        );
+
+        let request = ProcessRequest::new(subtype, &block)?;
+        match &self.rehearsed {
+            Some(rehearsed) => rehearsed
+                .lock()
+                .unwrap()
+                .push(crate::rpc::client::RPCClient::body(&&request)),
+            None => {
+                (&request).call(&self.client).await?;
+            }
+        }
+        Ok(block.hash)
+    }
+}
+
+#[cfg(feature = "rpc_client")]
+#[async_trait]
+impl Payer for RPCPayer {
+    async fn pay(&self, from: &Private, to: &Address, amount: &Raw) -> anyhow::Result<BlockHash> {
+        use crate::blocks::{Link, Previous, StateBlock, Subtype};
+        use crate::rpc::calls::AccountInfoRequest;
+        use crate::rpc::client::RPCRequest;
+        use anyhow::{anyhow, Context};
+
+        let account = from.to_address()?;
+        let info = (&AccountInfoRequest::new(account.to_owned()))
+            .call(&self.client)
+            .await
+            .with_context(|| format!("Getting account info for {}", account))?;
+        let balance = info.balance.checked_sub(amount).ok_or_else(|| {
+            anyhow!(
+                "{} has a balance of {} raw, which isn't enough to send {} raw",
+                account,
+                info.balance,
+                amount
+            )
+        })?;
+        let representative = info
+            .representative
+            .ok_or_else(|| anyhow!("Account info for {} has no representative", account))?;
+
+        let block = StateBlock::new(
+            account.to_public(),
+            Previous::Block(info.frontier.to_owned()),
+            representative.to_public(),
+            balance,
+            Link::DestinationAccount(to.to_public()),
+        );
+        self.publish(Subtype::Send, block, from).await
+    }
+
+    fn describe(&self) -> Option<String> {
+        Some(self.url.to_owned())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Seed;
+    use std::path::PathBuf;
+    use std::sync::Mutex;
+
+    #[test]
+    fn interval() {
+        assert_eq!(
+            Interval::from_str("7d").unwrap().as_secs(),
+            7 * 24 * 60 * 60
+        );
+        assert_eq!(Interval::from_str("14d").unwrap().to_string(), "2w");
+        assert_eq!(Interval::from_str("90m").unwrap().to_string(), "90m");
+        assert!(Interval::from_str("0d").is_err());
+        assert!(Interval::from_str("7").is_err());
+        assert!(Interval::from_str("d").is_err());
+    }
+
+    #[test]
+    fn backoff() {
+        let now = Utc::now();
+        let mut schedule = ScheduledPayment::new(
+            WalletId::zero(),
+            0,
+            Seed::zero().derive(1).to_address().unwrap(),
+            Raw::from(1),
+            Interval::from_secs(60),
+            now,
+        );
+        assert!(schedule.is_due(now));
+
+        schedule.failed(now, "Nope".into());
+        assert!(!schedule.is_due(now));
+        assert!(schedule.is_due(now + chrono::Duration::seconds(30)));
+        schedule.failed(now, "Nope".into());
+        assert_eq!(schedule.retry_at, Some(now + chrono::Duration::seconds(60)));
+        for _ in 0..20 {
+            schedule.failed(now, "Nope".into());
+        }
+        assert_eq!(schedule.retry_at, Some(now + chrono::Duration::hours(1)));
+
+        schedule.paid();
+        assert_eq!(schedule.failures, 0);
+        assert_eq!(schedule.next_due, now + chrono::Duration::seconds(60));
+    }
+
+    /// Fails the first `fail` payments.
+    struct FakePayer {
+        fail: Mutex<usize>,
+        paid: Mutex<Vec<(Address, Raw)>>,
+    }
+
+    #[async_trait]
+    impl Payer for FakePayer {
+        async fn pay(&self, _: &Private, to: &Address, amount: &Raw) -> anyhow::Result<BlockHash> {
+            let mut fail = self.fail.lock().unwrap();
+            if *fail > 0 {
+                *fail -= 1;
+                return Err(anyhow::anyhow!("Connection refused"));
+            }
+            self.paid
+                .lock()
+                .unwrap()
+                .push((to.to_owned(), amount.to_owned()));
+            Ok(BlockHash::zero())
+        }
+    }
+
+    #[tokio::test]
+    async fn catch_up_and_retry() {
+        let path = PathBuf::from("catch_up_and_retry.wallet");
+        if path.exists() {
+            std::fs::remove_file(&path).unwrap();
+        }
+        let manager = WalletManager::new(&path);
+        manager.ensure().await.unwrap();
+        manager.add_random_seed(WalletId::zero()).await.unwrap();
+
+        let now = Utc::now();
+        let to = Seed::zero().derive(1).to_address().unwrap();
+        let schedule = ScheduledPayment::new(
+            WalletId::zero(),
+            0,
+            to.to_owned(),
+            Raw::from(10),
+            Interval::from_secs(60),
+            now - chrono::Duration::seconds(130),
+        );
+        manager.add_schedule(schedule).await.unwrap();
+
+        let payer = FakePayer {
+            fail: Mutex::new(1),
+            paid: Mutex::new(vec![]),
+        };
+        let daemon = PaymentDaemon::new(WalletManager::new(&path), payer);
+
+        // The first attempt fails, so nothing is caught up on until the retry.
+        assert_eq!(daemon.run_due(now).await.unwrap(), 0);
+        let later = now + chrono::Duration::seconds(31);
+        assert_eq!(daemon.run_due(later).await.unwrap(), 3);
+        assert_eq!(daemon.run_due(later).await.unwrap(), 0);
+
+        let schedules = manager.schedules().await.unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(schedules[0].failures, 0);
+        assert_eq!(schedules[0].next_due, now + chrono::Duration::seconds(50));
+        assert_eq!(daemon.payer.paid.lock().unwrap().len(), 3);
+    }
+}
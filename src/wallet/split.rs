@@ -0,0 +1,373 @@
+use crate::blocks::{Link, Previous, StateBlock};
+use crate::pow::{Difficulty, Work, WorkPublisher};
+use crate::{Address, Private, Raw};
+use anyhow::{anyhow, Context};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use tokio::sync::broadcast;
+use tracing::{info, warn};
+
+/// Shares are worked out in hundredths of a percent so rounding is predictable.
+const BASIS_POINTS: u128 = 10_000;
+
+/// Things that happen to wallet accounts, sent over a [broadcast] channel so any number of
+/// listeners like the [PaymentSplitter] can react to them.
+#[derive(Debug, Clone)]
+pub enum WalletEvent {
+    /// A receive block for a deposit into one of our accounts was confirmed.
+    DepositConfirmed { block: StateBlock, amount: Raw },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SplitDestination {
+    pub address: Address,
+
+    /// Percentage of each deposit to send here, with up to two decimal places.
+    pub percent: f64,
+}
+
+/// Configuration for automatically forwarding shares of deposits to other accounts.
+///
+/// Stored as JSON, e.g.:
+/// ```json
+/// {
+///   "account": "nano_3t6k35gi95xu6tergt6p69ck76ogmitsa8mnijtpxm9fkcm736xtoncuohr3",
+///   "destinations": [
+///     {"address": "nano_1111111111111111111111111111111111111111111111111111hifc8npp", "percent": 20},
+///     {"address": "nano_1pu7p5n3ghq1i1p4rhmek41f5add1uh34xpb94nkbxe8g4a6x1p69emk8y1d", "percent": 2.5}
+///   ]
+/// }
+/// ```
+///
+/// Whatever isn't sent to a destination stays in the account.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SplitPolicy {
+    /// The account receiving the deposits that get split.
+    pub account: Address,
+
+    pub destinations: Vec<SplitDestination>,
+}
+
+impl SplitPolicy {
+    pub async fn load<P: AsRef<Path>>(path: P) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let data = tokio::fs::read_to_string(path)
+            .await
+            .with_context(|| format!("Reading split policy {:?}", path))?;
+        let policy: Self = serde_json::from_str(&data)
+            .with_context(|| format!("Parsing split policy {:?}", path))?;
+        policy.validate()?;
+        Ok(policy)
+    }
+
+    pub fn validate(&self) -> anyhow::Result<()> {
+        if self.destinations.is_empty() {
+            return Err(anyhow!("Split policy has no destinations"));
+        }
+
+        let mut total = 0;
+        for destination in &self.destinations {
+            if destination.address == self.account {
+                return Err(anyhow!(
+                    "Split policy sends to its own account {}",
+                    destination.address
+                ));
+            }
+            total += to_basis_points(destination.percent)?;
+        }
+        if total > BASIS_POINTS {
+            return Err(anyhow!(
+                "Split policy sends {:.2}% of each deposit, which is more than 100%",
+                total as f64 / 100.0
+            ));
+        }
+        Ok(())
+    }
+
+    /// The amount for each destination from a deposit, rounded down. Destinations whose share
+    /// rounds down to nothing are left out.
+    pub fn shares(&self, deposit: &Raw) -> anyhow::Result<Vec<(Address, Raw)>> {
+        let mut shares = vec![];
+        for destination in &self.destinations {
+            let points = to_basis_points(destination.percent)?;
+            // Split the multiplication so it can't overflow for large deposits.
+            let deposit = deposit.to_u128();
+            let amount =
+                deposit / BASIS_POINTS * points + deposit % BASIS_POINTS * points / BASIS_POINTS;
+            if amount > 0 {
+                shares.push((destination.address.to_owned(), Raw::from(amount)));
+            }
+        }
+        Ok(shares)
+    }
+
+    /// Unsigned send blocks without work for a deposit, chained on top of `frontier`, which
+    /// is the latest block of the account.
+    pub fn send_blocks(
+        &self,
+        frontier: &StateBlock,
+        deposit: &Raw,
+    ) -> anyhow::Result<Vec<StateBlock>> {
+        if frontier.account != self.account.to_public() {
+            return Err(anyhow!(
+                "Frontier {:?} isn't from the split account {}",
+                frontier.hash,
+                self.account
+            ));
+        }
+
+        let mut blocks: Vec<StateBlock> = vec![];
+        let mut balance = frontier.balance.to_owned();
+        let mut previous = frontier.hash.to_owned();
+        for (destination, amount) in self.shares(deposit)? {
+            balance = balance.checked_sub(&amount).ok_or_else(|| {
+                anyhow!("Not enough balance to send {} to {}", amount, destination)
+            })?;
+            let block = StateBlock::new(
+                frontier.account.to_owned(),
+                Previous::Block(previous),
+                frontier.representative.to_owned(),
+                balance.to_owned(),
+                Link::DestinationAccount(destination.to_public()),
+            );
+            previous = block.hash.to_owned();
+            blocks.push(block);
+        }
+        Ok(blocks)
+    }
+}
+
+fn to_basis_points(percent: f64) -> anyhow::Result<u128> {
+    if !(percent > 0.0 && percent <= 100.0) {
+        return Err(anyhow!(
+            "Split percentage {} should be more than 0 and at most 100",
+            percent
+        ));
+    }
+    Ok((percent * 100.0).round() as u128)
+}
+
+/// Sends shares of each confirmed deposit into an account according to a [SplitPolicy].
+pub struct PaymentSplitter {
+    policy: SplitPolicy,
+    private: Private,
+    difficulty: Difficulty,
+}
+
+impl PaymentSplitter {
+    pub fn new(
+        policy: SplitPolicy,
+        private: Private,
+        difficulty: Difficulty,
+    ) -> anyhow::Result<Self> {
+        policy.validate()?;
+        if private.to_public()? != policy.account.to_public() {
+            return Err(anyhow!(
+                "The key doesn't belong to the split account {}",
+                policy.account
+            ));
+        }
+        Ok(Self {
+            policy,
+            private,
+            difficulty,
+        })
+    }
+
+    /// Signed send blocks with work for a deposit, in the order they need to be published.
+    ///
+    /// The hash of every block is known before any work is done, so the work for all of them is
+    /// generated at the same time.
+    pub async fn split(
+        &self,
+        deposit: &StateBlock,
+        amount: &Raw,
+    ) -> anyhow::Result<Vec<StateBlock>> {
+        let mut blocks = self.policy.send_blocks(deposit, amount)?;
+
+        let jobs: Vec<_> = blocks
+            .iter()
+            .map(|block| {
+                let root = block.work_root();
+                let difficulty = self.difficulty.clone();
+                tokio::task::spawn_blocking(move || Work::generate(&root, &difficulty))
+            })
+            .collect();
+        for (block, job) in blocks.iter_mut().zip(futures::future::join_all(jobs).await) {
+            block.work = Some(job.context("Generating work")??);
+            block.signature = Some(self.private.sign(block.hash.as_bytes())?);
+        }
+        Ok(blocks)
+    }
+
+    /// Split deposits as they're confirmed, until the event channel is closed.
+    ///
+    /// Each block is sent to the network with [WorkPublisher::republish].
+    pub async fn run<P>(
+        &self,
+        mut events: broadcast::Receiver<WalletEvent>,
+        publisher: &P,
+    ) -> anyhow::Result<()>
+    where
+        P: WorkPublisher + Sync,
+    {
+        loop {
+            let event = match events.recv().await {
+                Ok(event) => event,
+                Err(broadcast::error::RecvError::Lagged(missed)) => {
+                    warn!("Payment splitter missed {} wallet events", missed);
+                    continue;
+                }
+                Err(broadcast::error::RecvError::Closed) => return Ok(()),
+            };
+
+            let WalletEvent::DepositConfirmed { block, amount } = event;
+            if block.account != self.policy.account.to_public() {
+                continue;
+            }
+
+            let sends = self.split(&block, &amount).await?;
+            for send in &sends {
+                publisher.republish(send).await?;
+            }
+            info!(
+                "Split a deposit of {} into {} with {} sends",
+                amount,
+                self.policy.account,
+                sends.len()
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blocks::BlockHash;
+    use crate::Seed;
+    use std::sync::Mutex;
+
+    fn address(index: u32) -> Address {
+        Seed::zero().derive(index).to_address().unwrap()
+    }
+
+    fn policy(percents: &[f64]) -> SplitPolicy {
+        SplitPolicy {
+            account: address(0),
+            destinations: percents
+                .iter()
+                .enumerate()
+                .map(|(idx, percent)| SplitDestination {
+                    address: address(idx as u32 + 1),
+                    percent: *percent,
+                })
+                .collect(),
+        }
+    }
+
+    fn deposit(balance: u128) -> StateBlock {
+        let account = address(0).to_public();
+        StateBlock::new(
+            account.clone(),
+            Previous::Block(BlockHash::zero()),
+            account,
+            Raw::from(balance),
+            Link::Source(BlockHash::zero()),
+        )
+    }
+
+    #[test]
+    fn validate() {
+        assert!(policy(&[50.0, 50.0]).validate().is_ok());
+        assert!(policy(&[60.0, 50.0]).validate().is_err());
+        assert!(policy(&[0.0]).validate().is_err());
+        assert!(policy(&[]).validate().is_err());
+
+        let mut to_self = policy(&[10.0]);
+        to_self.destinations[0].address = address(0);
+        assert!(to_self.validate().is_err());
+    }
+
+    #[test]
+    fn shares() {
+        let shares = policy(&[20.0, 2.5, 0.01]).shares(&Raw::from(1001)).unwrap();
+        assert_eq!(
+            shares,
+            vec![(address(1), Raw::from(200)), (address(2), Raw::from(25))]
+        );
+
+        let shares = policy(&[100.0]).shares(&Raw::max()).unwrap();
+        assert_eq!(shares, vec![(address(1), Raw::max())]);
+    }
+
+    #[test]
+    fn send_blocks() {
+        let frontier = deposit(5000);
+        let blocks = policy(&[20.0, 10.0])
+            .send_blocks(&frontier, &Raw::from(1000))
+            .unwrap();
+        assert_eq!(blocks.len(), 2);
+        assert_eq!(blocks[0].previous, Previous::Block(frontier.hash.clone()));
+        assert_eq!(blocks[0].balance, Raw::from(4800));
+        assert_eq!(blocks[1].previous, Previous::Block(blocks[0].hash.clone()));
+        assert_eq!(blocks[1].balance, Raw::from(4700));
+        assert_eq!(
+            blocks[1].link,
+            Link::DestinationAccount(address(2).to_public())
+        );
+    }
+
+    struct FakePublisher(Mutex<Vec<StateBlock>>);
+
+    #[async_trait::async_trait]
+    impl WorkPublisher for FakePublisher {
+        async fn is_confirmed(&self, _hash: &BlockHash) -> anyhow::Result<bool> {
+            Ok(true)
+        }
+
+        async fn active_difficulty(&self) -> anyhow::Result<Difficulty> {
+            Ok(Difficulty::new(0))
+        }
+
+        async fn republish(&self, block: &StateBlock) -> anyhow::Result<()> {
+            self.0.lock().unwrap().push(block.clone());
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn run() {
+        let splitter = PaymentSplitter::new(
+            policy(&[50.0, 25.0]),
+            Seed::zero().derive(0),
+            Difficulty::new(0),
+        )
+        .unwrap();
+        let publisher = FakePublisher(Mutex::new(vec![]));
+        let (sender, receiver) = broadcast::channel(4);
+
+        let mut someone_else = deposit(100);
+        someone_else.account = address(5).to_public();
+        for block in [someone_else, deposit(100)] {
+            sender
+                .send(WalletEvent::DepositConfirmed {
+                    block,
+                    amount: Raw::from(100),
+                })
+                .unwrap();
+        }
+        drop(sender);
+        splitter.run(receiver, &publisher).await.unwrap();
+
+        let published = publisher.0.lock().unwrap();
+        assert_eq!(published.len(), 2);
+        for block in published.iter() {
+            let work = block.work.as_ref().unwrap();
+            assert!(work
+                .verify(&block.work_root(), &Difficulty::new(0))
+                .unwrap());
+            block.verify_self_signature().unwrap();
+        }
+        assert_eq!(published[1].balance, Raw::from(25));
+    }
+}
@@ -0,0 +1,426 @@
+//! Balances of a set of accounts kept up to date in memory, for applications embedding the
+//! library that want to show balances without polling for them.
+//!
+//! A [BalanceTracker] follows either a node running in the same process, through its
+//! [FrontierEvents](crate::node::FrontierEvents) and state, or a remote node, through its RPC
+//! and WebSocket servers. Subscribers get a [BalanceChange] whenever a balance changes.
+//!
+//! Frontier changes only show up straight away for the tracked accounts themselves, and for
+//! sends to them from a node in this process. Everything else, like blocks being cemented or
+//! sends to the tracked accounts seen remotely, is picked up by refreshing every account on an
+//! interval.
+use crate::rpc::calls::AccountsBalancesRequest;
+use crate::rpc::client::{RPCClient, RPCRequest};
+use crate::rpc::websocket::WebSocketClient;
+use crate::{Address, Public, Raw};
+use anyhow::Context;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::broadcast;
+use tokio_util::sync::CancellationToken;
+use tracing::warn;
+#[cfg(feature = "node")]
+use {
+    crate::blocks::Previous,
+    crate::node::state::{ArcState, DynState},
+    crate::node::FrontierEvents,
+    std::convert::TryFrom,
+};
+
+/// Changes that haven't been received yet before the slowest subscriber starts missing them.
+const CAPACITY: usize = 256;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Balances {
+    /// The balance as of the highest cemented block.
+    pub confirmed: Raw,
+
+    /// Amounts sent to the account that it hasn't received yet.
+    pub receivable: Raw,
+}
+
+impl Default for Balances {
+    fn default() -> Self {
+        Self {
+            confirmed: Raw::zero(),
+            receivable: Raw::zero(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct BalanceChange {
+    pub account: Address,
+
+    /// `None` the first time the account's balances are known.
+    pub old: Option<Balances>,
+    pub new: Balances,
+}
+
+#[derive(Debug, Clone)]
+pub struct BalanceTracker {
+    /// `None` until each account is first refreshed.
+    balances: Arc<Mutex<HashMap<Public, Option<Balances>>>>,
+    tx: broadcast::Sender<BalanceChange>,
+}
+
+impl BalanceTracker {
+    pub fn new<I: IntoIterator<Item = Public>>(accounts: I) -> Self {
+        let (tx, _) = broadcast::channel(CAPACITY);
+        Self {
+            balances: Arc::new(Mutex::new(
+                accounts.into_iter().map(|a| (a, None)).collect(),
+            )),
+            tx,
+        }
+    }
+
+    /// Start tracking `account`, which is picked up by the next refresh.
+    pub fn track(&self, account: Public) {
+        self.balances.lock().unwrap().entry(account).or_default();
+    }
+
+    pub fn untrack(&self, account: &Public) {
+        self.balances.lock().unwrap().remove(account);
+    }
+
+    pub fn accounts(&self) -> Vec<Public> {
+        self.balances.lock().unwrap().keys().cloned().collect()
+    }
+
+    fn is_tracked(&self, account: &Public) -> bool {
+        self.balances.lock().unwrap().contains_key(account)
+    }
+
+    /// The latest balances of `account`, or `None` if it isn't tracked or hasn't been refreshed.
+    pub fn get(&self, account: &Public) -> Option<Balances> {
+        self.balances
+            .lock()
+            .unwrap()
+            .get(account)
+            .cloned()
+            .flatten()
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<BalanceChange> {
+        self.tx.subscribe()
+    }
+
+    /// Record the balances of a tracked account, notifying subscribers if they changed. Returns
+    /// whether they did.
+    pub fn update(&self, account: &Public, new: Balances) -> bool {
+        let old = {
+            let mut balances = self.balances.lock().unwrap();
+            let balances = match balances.get_mut(account) {
+                Some(balances) => balances,
+                None => return false,
+            };
+            if balances.as_ref() == Some(&new) {
+                return false;
+            }
+            balances.replace(new.to_owned())
+        };
+        // Nobody might be listening, which is fine.
+        let _ = self.tx.send(BalanceChange {
+            account: account.to_address(),
+            old,
+            new,
+        });
+        true
+    }
+
+    /// Follow a node running in this process until `cancel` is cancelled or the node stops,
+    /// refreshing every account each `interval`.
+    #[cfg(feature = "node")]
+    pub async fn follow_node(
+        &self,
+        state: ArcState,
+        events: &FrontierEvents,
+        interval: Duration,
+        cancel: &CancellationToken,
+    ) {
+        let mut events = events.subscribe();
+        let mut ticks = tokio::time::interval(interval);
+        loop {
+            let accounts = tokio::select! {
+                _ = cancel.cancelled() => return,
+                _ = ticks.tick() => self.accounts(),
+                event = events.recv() => match event {
+                    Ok(event) => match self.affected(&*state, &event).await {
+                        Ok(accounts) => accounts,
+                        Err(err) => {
+                            warn!("Could not look up {}: {:#}", event.hash, err);
+                            continue;
+                        }
+                    },
+                    Err(broadcast::error::RecvError::Lagged(_)) => self.accounts(),
+                    Err(broadcast::error::RecvError::Closed) => return,
+                },
+            };
+            if let Err(err) = self.refresh_from_state(&*state, &accounts).await {
+                warn!("Could not refresh balances: {:#}", err);
+            }
+        }
+    }
+
+    /// The tracked accounts whose balances `event` might have changed: its own account, and
+    /// the destination if it's a send.
+    #[cfg(feature = "node")]
+    async fn affected(
+        &self,
+        state: &DynState,
+        event: &crate::rpc::websocket::FrontierEvent,
+    ) -> anyhow::Result<Vec<Public>> {
+        let mut accounts = vec![];
+        let account = event.account.to_public();
+        if self.is_tracked(&account) {
+            accounts.push(account);
+        }
+        if let Some(block) = state.get_block_by_hash(&event.hash).await? {
+            // The link of a send is its destination. Other links can't be tracked accounts,
+            // short of a hash colliding with a public key.
+            if let Ok(destination) = Public::try_from(block.link().as_bytes()) {
+                if self.is_tracked(&destination) && !accounts.contains(&destination) {
+                    accounts.push(destination);
+                }
+            }
+        }
+        Ok(accounts)
+    }
+
+    /// Update `accounts` from the ledger of a node in this process.
+    #[cfg(feature = "node")]
+    pub async fn refresh_from_state(
+        &self,
+        state: &DynState,
+        accounts: &[Public],
+    ) -> anyhow::Result<()> {
+        if accounts.is_empty() {
+            return Ok(());
+        }
+        let mut receivable: HashMap<Public, Raw> = HashMap::new();
+        for pending in state.all_pending().await? {
+            if accounts.contains(&pending.destination) {
+                let sum = receivable
+                    .entry(pending.destination)
+                    .or_insert_with(Raw::zero);
+                *sum = sum
+                    .checked_add(&pending.amount)
+                    .context("Receivable balance overflowed")?;
+            }
+        }
+        for account in accounts {
+            let balances = Balances {
+                confirmed: confirmed_balance(state, account).await?,
+                receivable: receivable.remove(account).unwrap_or_else(Raw::zero),
+            };
+            self.update(account, balances);
+        }
+        Ok(())
+    }
+
+    /// Follow a remote node until `cancel` is cancelled, refreshing every account each
+    /// `interval`.
+    ///
+    /// The WebSocket subscription is for the accounts tracked when this is called, so accounts
+    /// tracked later are only refreshed on the interval.
+    pub async fn follow_rpc(
+        &self,
+        rpc: &RPCClient,
+        websocket: &WebSocketClient,
+        interval: Duration,
+        cancel: &CancellationToken,
+    ) {
+        let addresses = self.accounts().iter().map(|a| a.to_address()).collect();
+        let mut events = websocket.frontiers(Some(addresses));
+        let mut ticks = tokio::time::interval(interval);
+        loop {
+            let accounts = tokio::select! {
+                _ = cancel.cancelled() => return,
+                _ = ticks.tick() => self.accounts(),
+                event = events.recv() => match event {
+                    Some(event) => vec![event.account.to_public()],
+                    None => return,
+                },
+            };
+            if let Err(err) = self.refresh_from_rpc(rpc, &accounts).await {
+                warn!("Could not refresh balances: {:#}", err);
+            }
+        }
+    }
+
+    /// Update `accounts` from a remote node's `accounts_balances`, whose `balance` the reference
+    /// node only counts cemented blocks towards by default.
+    pub async fn refresh_from_rpc(
+        &self,
+        rpc: &RPCClient,
+        accounts: &[Public],
+    ) -> anyhow::Result<()> {
+        let accounts: Vec<_> = accounts.iter().filter(|a| self.is_tracked(a)).collect();
+        if accounts.is_empty() {
+            return Ok(());
+        }
+        let addresses = accounts.iter().map(|a| a.to_address()).collect();
+        let mut balances = (&AccountsBalancesRequest::new(addresses))
+            .call(rpc)
+            .await
+            .context("Getting balances")?
+            .balances;
+        for account in accounts {
+            let balances = match balances.remove(&account.to_address()) {
+                Some(entry) => Balances {
+                    confirmed: entry.balance,
+                    receivable: entry.pending,
+                },
+                None => Balances::default(),
+            };
+            self.update(account, balances);
+        }
+        Ok(())
+    }
+}
+
+/// The balance of `account` as of its highest cemented block, walking back from its frontier.
+#[cfg(feature = "node")]
+async fn confirmed_balance(state: &DynState, account: &Public) -> anyhow::Result<Raw> {
+    let info = match state.account_info(account).await? {
+        Some(info) => info,
+        None => return Ok(Raw::zero()),
+    };
+    let cemented = state.cemented_height(account).await?.unwrap_or(0);
+    if cemented == 0 {
+        return Ok(Raw::zero());
+    }
+    let mut hash = info.head;
+    for _ in cemented..info.block_count {
+        let block = state
+            .get_block_by_hash(&hash)
+            .await?
+            .with_context(|| format!("Block {:?} is missing", hash))?;
+        hash = match block.previous() {
+            Previous::Block(previous) => previous.to_owned(),
+            Previous::Open => return Ok(Raw::zero()),
+        };
+    }
+    let block = state
+        .get_block_by_hash(&hash)
+        .await?
+        .with_context(|| format!("Cemented block {:?} is missing", hash))?;
+    Ok(block.balance().to_owned())
+}
+
+#[cfg(all(test, feature = "node"))]
+mod tests {
+    use super::*;
+    use crate::blocks::{Block, BlockHash, Link, StateBlock};
+    use crate::node::state::{MemoryState, Pending, State};
+    use crate::rpc::websocket::FrontierSource;
+    use crate::Network;
+
+    fn account(index: u32) -> Public {
+        crate::Seed::zero().derive(index).to_public().unwrap()
+    }
+
+    fn block(account: &Public, previous: Previous, balance: u128, link: Link) -> Block {
+        Block::from_state_block(&StateBlock::new(
+            account.to_owned(),
+            previous,
+            account.to_owned(),
+            Raw::from(balance),
+            link,
+        ))
+    }
+
+    #[test]
+    fn notifies_changes() {
+        let (tracked, other) = (account(0), account(1));
+        let tracker = BalanceTracker::new(vec![tracked.to_owned()]);
+        let mut changes = tracker.subscribe();
+        let balances = Balances {
+            confirmed: Raw::from(5),
+            receivable: Raw::zero(),
+        };
+
+        assert!(tracker.update(&tracked, balances.to_owned()));
+        assert!(!tracker.update(&tracked, balances.to_owned()));
+        assert!(!tracker.update(&other, balances.to_owned()));
+        assert_eq!(tracker.get(&tracked), Some(balances.to_owned()));
+        assert_eq!(tracker.get(&other), None);
+
+        let change = changes.try_recv().unwrap();
+        assert_eq!(change.account, tracked.to_address());
+        assert_eq!(change.old, None);
+        assert_eq!(change.new, balances);
+        assert!(changes.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn follows_the_ledger() {
+        let (sender, receiver) = (account(0), account(1));
+        let open = block(&sender, Previous::Open, 10, Link::Source(BlockHash::zero()));
+        let send = block(
+            &sender,
+            Previous::Block(open.hash().unwrap().to_owned()),
+            4,
+            Link::DestinationAccount(receiver.to_owned()),
+        );
+        let state = MemoryState::new(Network::Test);
+        state.add_block(&open).await.unwrap();
+        state.add_block(&send).await.unwrap();
+        state
+            .add_pending(&Pending {
+                destination: receiver.to_owned(),
+                source: send.hash().unwrap().to_owned(),
+                amount: Raw::from(6),
+            })
+            .await
+            .unwrap();
+        state.set_cemented_height(&sender, 1).await.unwrap();
+        let state: ArcState = Arc::new(state);
+
+        let tracker = BalanceTracker::new(vec![receiver.to_owned()]);
+        tracker
+            .refresh_from_state(&*state, &[sender.to_owned(), receiver.to_owned()])
+            .await
+            .unwrap();
+        assert_eq!(tracker.get(&sender), None);
+        let expected = Balances {
+            confirmed: Raw::zero(),
+            receivable: Raw::from(6),
+        };
+        assert_eq!(tracker.get(&receiver), Some(expected));
+
+        tracker.track(sender.to_owned());
+        let mut changes = tracker.subscribe();
+        let events = FrontierEvents::new();
+        let cancel = CancellationToken::new();
+        let task = {
+            let (tracker, state, events, cancel) = (
+                tracker.clone(),
+                state.clone(),
+                events.clone(),
+                cancel.clone(),
+            );
+            tokio::spawn(async move {
+                let interval = Duration::from_secs(3600);
+                tracker.follow_node(state, &events, interval, &cancel).await
+            })
+        };
+
+        // The first refresh is straight away.
+        let change = changes.recv().await.unwrap();
+        assert_eq!(change.account, sender.to_address());
+        assert_eq!(change.new.confirmed, Raw::from(10));
+
+        state.set_cemented_height(&sender, 2).await.unwrap();
+        events.publish(&sender, send.hash().unwrap(), FrontierSource::Block);
+        let change = changes.recv().await.unwrap();
+        assert_eq!(change.old.unwrap().confirmed, Raw::from(10));
+        assert_eq!(change.new.confirmed, Raw::from(4));
+
+        cancel.cancel();
+        task.await.unwrap();
+    }
+}
@@ -0,0 +1,601 @@
+//! Alerts when something happens to accounts you care about.
+//!
+//! A [BalanceWatcher] polls an RPC server for the balances and new blocks of the accounts in the
+//! `[watch]` section of the [Config](crate::Config), and sends an [Alert] to each [Notifier]
+//! when a balance crosses a threshold, or when an account sends to somewhere it shouldn't.
+//!
+//! A [BalanceTracker] keeps the balances of accounts up to date in memory instead, for
+//! applications embedding the library.
+//!
+//! ## Example config
+//! ```toml
+//! [watch]
+//! interval = 30
+//!
+//! [[watch.account]]
+//! name = "hot wallet"
+//! address = "nano_3t6k35gi95xu6tergt6p69ck76ogmitsa8mnijtpxm9fkcm736xtoncuohr3"
+//! below = "10mnano"
+//! above = "1000mnano"
+//! alert_on_send = true
+//! allowed_destinations = ["nano_1111111111111111111111111111111111111111111111111111hifc8npp"]
+//!
+//! [[watch.notify]]
+//! type = "webhook"
+//! url = "https://example.com/alerts"
+//!
+//! [[watch.notify]]
+//! type = "command"
+//! command = "/usr/local/bin/page-someone"
+//! args = ["--urgent"]
+//! ```
+mod balances;
+mod notify;
+
+pub use balances::{BalanceChange, BalanceTracker, Balances};
+pub use notify::Notifier;
+
+use crate::blocks::{Block, BlockHash, BlockType, Root, Subtype};
+#[cfg(feature = "node")]
+use crate::node::ForkEvent;
+use crate::rpc::calls::{AccountHistoryRequest, AccountsBalancesRequest, AccountsFrontiersRequest};
+use crate::rpc::client::{RPCClient, RPCRequest};
+use crate::units::parse_amount;
+use crate::{Address, Raw};
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Deserializer, Serialize};
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::time::Duration;
+#[cfg(feature = "node")]
+use tokio::sync::broadcast;
+use tokio_util::sync::CancellationToken;
+use tracing::{info, warn};
+
+/// How many blocks to look back through for sends when an account's frontier changes.
+const HISTORY_LIMIT: i64 = 100;
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct WatchConfig {
+    /// Seconds between each check of the accounts.
+    #[serde(default = "default_interval")]
+    pub interval: u64,
+
+    /// The RPC server to poll. Can be overridden on the command line.
+    #[serde(default)]
+    pub rpc_url: Option<String>,
+
+    #[serde(default, rename = "account")]
+    pub accounts: Vec<WatchedAccount>,
+
+    #[serde(default, rename = "notify")]
+    pub notifiers: Vec<Notifier>,
+}
+
+fn default_interval() -> u64 {
+    30
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct WatchedAccount {
+    pub address: Address,
+
+    /// A name to show in alerts instead of just the address.
+    #[serde(default)]
+    pub name: Option<String>,
+
+    /// Alert when the balance drops below this amount, e.g. `"10mnano"`.
+    #[serde(default, deserialize_with = "deserialize_amount")]
+    pub below: Option<Raw>,
+
+    /// Alert when the balance goes above this amount.
+    #[serde(default, deserialize_with = "deserialize_amount")]
+    pub above: Option<Raw>,
+
+    /// Alert when this account sends to anywhere but the `allowed_destinations`.
+    #[serde(default)]
+    pub alert_on_send: bool,
+
+    #[serde(default)]
+    pub allowed_destinations: Vec<Address>,
+}
+
+fn deserialize_amount<'de, D>(deserializer: D) -> Result<Option<Raw>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let s: Option<String> = Option::deserialize(deserializer)?;
+    s.map(|s| parse_amount(&s))
+        .transpose()
+        .map_err(serde::de::Error::custom)
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Alert {
+    pub at: DateTime<Utc>,
+    pub account: Address,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+
+    pub balance: Raw,
+
+    #[serde(flatten)]
+    pub kind: AlertKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "alert", rename_all = "snake_case")]
+pub enum AlertKind {
+    BalanceBelow {
+        threshold: Raw,
+    },
+    BalanceAbove {
+        threshold: Raw,
+    },
+    UnexpectedSend {
+        block: BlockHash,
+        destination: Option<Address>,
+        amount: Option<Raw>,
+    },
+
+    /// A node running in this process saw another block published for the same root as one of
+    /// the account's, e.g. a double spend. Sent again with the `winner` once it's confirmed.
+    Fork {
+        root: Root,
+        existing: Box<Block>,
+        competing: Box<Block>,
+        winner: Option<BlockHash>,
+    },
+}
+
+impl Display for Alert {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match &self.name {
+            Some(name) => write!(f, "{} ({})", name, self.account)?,
+            None => write!(f, "{}", self.account)?,
+        }
+        match &self.kind {
+            AlertKind::BalanceBelow { threshold } => write!(
+                f,
+                " balance {} raw is below {} raw",
+                self.balance, threshold
+            ),
+            AlertKind::BalanceAbove { threshold } => write!(
+                f,
+                " balance {} raw is above {} raw",
+                self.balance, threshold
+            ),
+            AlertKind::UnexpectedSend {
+                block,
+                destination,
+                amount,
+            } => {
+                write!(f, " made an unexpected send in {}", block)?;
+                if let Some(amount) = amount {
+                    write!(f, " of {} raw", amount)?;
+                }
+                if let Some(destination) = destination {
+                    write!(f, " to {}", destination)?;
+                }
+                Ok(())
+            }
+            AlertKind::Fork {
+                root,
+                existing,
+                competing,
+                winner,
+            } => {
+                let hash = |block: &Block| block.hash().map(|h| h.to_string()).unwrap_or_default();
+                write!(
+                    f,
+                    " has a fork at {}: {} competes with {}",
+                    root,
+                    hash(competing),
+                    hash(existing)
+                )?;
+                if let Some(winner) = winner {
+                    write!(f, ", {} won", winner)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// What an account looks like right now.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AccountSnapshot {
+    pub address: Address,
+    pub balance: Raw,
+
+    /// `None` if the account hasn't been opened.
+    pub frontier: Option<BlockHash>,
+
+    /// Send blocks since the frontier of the previous snapshot.
+    pub sends: Vec<SentBlock>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SentBlock {
+    pub hash: BlockHash,
+    pub destination: Option<Address>,
+    pub amount: Option<Raw>,
+}
+
+#[derive(Debug, Default)]
+struct AccountState {
+    below: bool,
+    above: bool,
+    frontier: Option<BlockHash>,
+}
+
+pub struct BalanceWatcher {
+    config: WatchConfig,
+    state: HashMap<Address, AccountState>,
+}
+
+impl BalanceWatcher {
+    pub fn new(config: WatchConfig) -> Self {
+        Self {
+            config,
+            state: HashMap::new(),
+        }
+    }
+
+    pub fn config(&self) -> &WatchConfig {
+        &self.config
+    }
+
+    /// Compare a snapshot with the last one of the same account, returning the alerts to send.
+    ///
+    /// Threshold alerts are only sent when the threshold is crossed, or on the first snapshot if
+    /// the balance is already past it, so a low balance doesn't alert on every check.
+    pub fn check(&mut self, snapshot: &AccountSnapshot, now: DateTime<Utc>) -> Vec<Alert> {
+        let account = match self
+            .config
+            .accounts
+            .iter()
+            .find(|a| a.address == snapshot.address)
+        {
+            Some(account) => account,
+            None => return vec![],
+        };
+        let first = !self.state.contains_key(&snapshot.address);
+        let state = self.state.entry(snapshot.address.to_owned()).or_default();
+
+        let alert = |kind| Alert {
+            at: now,
+            account: account.address.to_owned(),
+            name: account.name.to_owned(),
+            balance: snapshot.balance.to_owned(),
+            kind,
+        };
+        let mut alerts = vec![];
+
+        let below = account
+            .below
+            .as_ref()
+            .filter(|threshold| &snapshot.balance < *threshold);
+        if let Some(threshold) = below {
+            if !state.below {
+                alerts.push(alert(AlertKind::BalanceBelow {
+                    threshold: threshold.to_owned(),
+                }));
+            }
+        }
+        state.below = below.is_some();
+
+        let above = account
+            .above
+            .as_ref()
+            .filter(|threshold| &snapshot.balance > *threshold);
+        if let Some(threshold) = above {
+            if !state.above {
+                alerts.push(alert(AlertKind::BalanceAbove {
+                    threshold: threshold.to_owned(),
+                }));
+            }
+        }
+        state.above = above.is_some();
+
+        // Sends can only be unexpected once we know where the account was.
+        if account.alert_on_send && !first {
+            for send in &snapshot.sends {
+                let allowed = send
+                    .destination
+                    .as_ref()
+                    .is_some_and(|d| account.allowed_destinations.contains(d));
+                if !allowed {
+                    alerts.push(alert(AlertKind::UnexpectedSend {
+                        block: send.hash.to_owned(),
+                        destination: send.destination.to_owned(),
+                        amount: send.amount.to_owned(),
+                    }));
+                }
+            }
+        }
+        state.frontier = snapshot.frontier.to_owned();
+
+        alerts
+    }
+
+    /// Fetch a snapshot of every watched account.
+    pub async fn snapshots(&self, client: &RPCClient) -> anyhow::Result<Vec<AccountSnapshot>> {
+        let addresses: Vec<Address> = self
+            .config
+            .accounts
+            .iter()
+            .map(|a| a.address.to_owned())
+            .collect();
+        let balances = (&AccountsBalancesRequest::new(addresses.to_owned()))
+            .call(client)
+            .await
+            .context("Getting balances")?
+            .balances;
+        let frontiers = (&AccountsFrontiersRequest::new(addresses))
+            .call(client)
+            .await
+            .context("Getting frontiers")?
+            .frontiers;
+
+        let mut snapshots = vec![];
+        for account in &self.config.accounts {
+            let address = &account.address;
+            let balance = balances
+                .get(address)
+                .map(|entry| entry.balance.to_owned())
+                .unwrap_or_else(Raw::zero);
+            let frontier = frontiers.get(address).cloned();
+            let previous = self.state.get(address).and_then(|s| s.frontier.as_ref());
+
+            let mut sends = vec![];
+            if account.alert_on_send && previous.is_some() && frontier.as_ref() != previous {
+                let history = (&AccountHistoryRequest::new(address.to_owned(), HISTORY_LIMIT))
+                    .call(client)
+                    .await
+                    .with_context(|| format!("Getting history of {}", address))?
+                    .history;
+                for entry in history {
+                    if Some(&entry.hash) == previous {
+                        break;
+                    }
+                    let is_send =
+                        entry.block_type == BlockType::Send || entry.subtype == Some(Subtype::Send);
+                    if is_send {
+                        sends.push(SentBlock {
+                            hash: entry.hash,
+                            destination: entry.account,
+                            amount: entry.amount,
+                        });
+                    }
+                }
+            }
+
+            snapshots.push(AccountSnapshot {
+                address: address.to_owned(),
+                balance,
+                frontier,
+                sends,
+            });
+        }
+        Ok(snapshots)
+    }
+
+    /// Check every account once, sending alerts to the notifiers.
+    pub async fn poll(&mut self, client: &RPCClient) -> anyhow::Result<Vec<Alert>> {
+        let now = Utc::now();
+        let mut alerts = vec![];
+        for snapshot in self.snapshots(client).await? {
+            alerts.extend(self.check(&snapshot, now));
+        }
+        for alert in &alerts {
+            info!("Alert: {}", alert);
+            self.notify(alert).await;
+        }
+        Ok(alerts)
+    }
+
+    /// Send an alert to every notifier. A failing notifier doesn't stop the others.
+    pub async fn notify(&self, alert: &Alert) {
+        for notifier in &self.config.notifiers {
+            if let Err(err) = notifier.notify(alert).await {
+                warn!("{} failed: {:#}", notifier, err);
+            }
+        }
+    }
+
+    /// Send an alert for each fork of a watched account seen by a node in this process, until
+    /// the node stops.
+    #[cfg(feature = "node")]
+    pub async fn notify_forks(&self, mut forks: broadcast::Receiver<ForkEvent>) {
+        loop {
+            let event = match forks.recv().await {
+                Ok(event) => event,
+                Err(broadcast::error::RecvError::Lagged(missed)) => {
+                    warn!("Missed alerts for {} forks", missed);
+                    continue;
+                }
+                Err(broadcast::error::RecvError::Closed) => return,
+            };
+            let name = self
+                .config
+                .accounts
+                .iter()
+                .find(|a| a.address == event.account)
+                .and_then(|a| a.name.to_owned());
+            let alert = Alert {
+                at: Utc::now(),
+                name,
+                balance: event.existing.balance().to_owned(),
+                account: event.account,
+                kind: AlertKind::Fork {
+                    root: event.root,
+                    existing: Box::new(event.existing),
+                    competing: Box::new(event.competing),
+                    winner: event.winner,
+                },
+            };
+            warn!("Alert: {}", alert);
+            self.notify(&alert).await;
+        }
+    }
+
+    /// Poll until `cancel` is cancelled. Failed polls are logged and retried at the next
+    /// interval.
+    pub async fn run(&mut self, client: &RPCClient, cancel: &CancellationToken) {
+        let interval = Duration::from_secs(self.config.interval);
+        loop {
+            let result = tokio::select! {
+                result = self.poll(client) => result,
+                _ = cancel.cancelled() => return,
+            };
+            if let Err(err) = result {
+                warn!("Checking watched accounts failed: {:#}", err);
+            }
+            tokio::select! {
+                _ = tokio::time::sleep(interval) => {}
+                _ = cancel.cancelled() => return,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Config;
+    use crate::Seed;
+
+    fn address(index: u32) -> Address {
+        Seed::zero().derive(index).to_address().unwrap()
+    }
+
+    fn config() -> WatchConfig {
+        let toml = format!(
+            r#"
+            [watch]
+            [[watch.account]]
+            address = "{}"
+            name = "hot wallet"
+            below = "100raw"
+            above = "1nano"
+            alert_on_send = true
+            allowed_destinations = ["{}"]
+
+            [[watch.notify]]
+            type = "command"
+            command = "true"
+            "#,
+            address(0),
+            address(1)
+        );
+        Config::from_toml(&toml).unwrap().watch.unwrap()
+    }
+
+    fn snapshot(balance: u128, sends: Vec<SentBlock>) -> AccountSnapshot {
+        AccountSnapshot {
+            address: address(0),
+            balance: Raw::from(balance),
+            frontier: Some(BlockHash::zero()),
+            sends,
+        }
+    }
+
+    #[test]
+    fn parse_config() {
+        let config = config();
+        assert_eq!(config.interval, 30);
+        assert_eq!(config.accounts[0].below, Some(Raw::from(100)));
+        assert_eq!(
+            config.accounts[0].above,
+            Some(Raw::from(1_000_000_000_000_000_000_000_000))
+        );
+        assert_eq!(
+            config.notifiers,
+            vec![Notifier::Command {
+                command: "true".into(),
+                args: vec![]
+            }]
+        );
+        let bad_amount = format!(
+            "[watch]\n[[watch.account]]\naddress = \"{}\"\nbelow = \"lots\"",
+            address(0)
+        );
+        assert!(Config::from_toml(&bad_amount).is_err());
+    }
+
+    #[tokio::test]
+    async fn run_stops_when_cancelled() {
+        let mut watcher = BalanceWatcher::new(config());
+        // Nothing is listening here, so each poll fails and waits for the next interval.
+        let client = RPCClient::new("http://127.0.0.1:9");
+        let cancel = CancellationToken::new();
+        let canceller = cancel.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            canceller.cancel();
+        });
+        tokio::time::timeout(Duration::from_secs(5), watcher.run(&client, &cancel))
+            .await
+            .unwrap();
+    }
+
+    #[test]
+    fn thresholds() {
+        let mut watcher = BalanceWatcher::new(config());
+        let now = Utc::now();
+
+        // Already below on the first check.
+        let alerts = watcher.check(&snapshot(50, vec![]), now);
+        assert_eq!(
+            alerts[0].kind,
+            AlertKind::BalanceBelow {
+                threshold: Raw::from(100)
+            }
+        );
+
+        // Still below, so nothing new.
+        assert!(watcher.check(&snapshot(60, vec![]), now).is_empty());
+        assert!(watcher.check(&snapshot(500, vec![]), now).is_empty());
+        assert_eq!(watcher.check(&snapshot(99, vec![]), now).len(), 1);
+
+        let rich = 2_000_000_000_000_000_000_000_000;
+        let alerts = watcher.check(&snapshot(rich, vec![]), now);
+        assert!(matches!(alerts[0].kind, AlertKind::BalanceAbove { .. }));
+        assert!(alerts[0].to_string().starts_with("hot wallet (nano_"));
+    }
+
+    #[test]
+    fn unexpected_sends() {
+        let mut watcher = BalanceWatcher::new(config());
+        let now = Utc::now();
+        let send = |destination| SentBlock {
+            hash: BlockHash::zero(),
+            destination: Some(destination),
+            amount: Some(Raw::from(5)),
+        };
+
+        // Sends in the first snapshot are from before we started watching.
+        assert!(watcher
+            .check(&snapshot(500, vec![send(address(2))]), now)
+            .is_empty());
+
+        let alerts = watcher.check(
+            &snapshot(500, vec![send(address(1)), send(address(2))]),
+            now,
+        );
+        assert_eq!(alerts.len(), 1);
+        assert_eq!(
+            alerts[0].kind,
+            AlertKind::UnexpectedSend {
+                block: BlockHash::zero(),
+                destination: Some(address(2)),
+                amount: Some(Raw::from(5)),
+            }
+        );
+        let json = serde_json::to_value(&alerts[0]).unwrap();
+        assert_eq!(json["alert"], "unexpected_send");
+    }
+}
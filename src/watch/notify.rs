@@ -0,0 +1,112 @@
+use crate::watch::Alert;
+use anyhow::anyhow;
+use serde::Deserialize;
+use std::fmt::{Display, Formatter};
+
+/// Somewhere to send [Alert]s, configured with a `[[watch.notify]]` section.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Notifier {
+    /// POST the alert as JSON to a URL.
+    Webhook { url: String },
+
+    /// Run a command with the alert in the environment: `FEELESS_ALERT` is the alert as JSON,
+    /// `FEELESS_ALERT_ACCOUNT` is the account address and `FEELESS_ALERT_MESSAGE` is a human
+    /// readable description.
+    Command {
+        command: String,
+
+        #[serde(default)]
+        args: Vec<String>,
+    },
+
+    /// Show a notification on the desktop.
+    #[cfg(feature = "desktop_notifications")]
+    Desktop,
+}
+
+impl Notifier {
+    pub async fn notify(&self, alert: &Alert) -> anyhow::Result<()> {
+        match self {
+            Notifier::Webhook { url } => {
+                let response = reqwest::Client::new()
+                    .post(url)
+                    .header("Content-Type", "application/json")
+                    .body(serde_json::to_string(alert)?)
+                    .send()
+                    .await?;
+                if !response.status().is_success() {
+                    return Err(anyhow!("Webhook responded with {}", response.status()));
+                }
+            }
+            Notifier::Command { command, args } => {
+                let status = tokio::process::Command::new(command)
+                    .args(args)
+                    .env("FEELESS_ALERT", serde_json::to_string(alert)?)
+                    .env("FEELESS_ALERT_ACCOUNT", alert.account.to_string())
+                    .env("FEELESS_ALERT_MESSAGE", alert.to_string())
+                    .status()
+                    .await?;
+                if !status.success() {
+                    return Err(anyhow!("{:?} exited with {}", command, status));
+                }
+            }
+            #[cfg(feature = "desktop_notifications")]
+            Notifier::Desktop => {
+                let message = alert.to_string();
+                tokio::task::spawn_blocking(move || {
+                    notify_rust::Notification::new()
+                        .summary("feeless")
+                        .body(&message)
+                        .show()
+                        .map(|_| ())
+                })
+                .await??;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Display for Notifier {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Notifier::Webhook { url } => write!(f, "Webhook {}", url),
+            Notifier::Command { command, .. } => write!(f, "Command {:?}", command),
+            #[cfg(feature = "desktop_notifications")]
+            Notifier::Desktop => write!(f, "Desktop notification"),
+        }
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::watch::AlertKind;
+    use crate::{Raw, Seed};
+    use chrono::Utc;
+
+    #[tokio::test]
+    async fn command() {
+        let account = Seed::zero().derive(0).to_address().unwrap();
+        let alert = Alert {
+            at: Utc::now(),
+            account: account.to_owned(),
+            name: None,
+            balance: Raw::from(1),
+            kind: AlertKind::BalanceBelow {
+                threshold: Raw::from(2),
+            },
+        };
+        let check = |script: &str| Notifier::Command {
+            command: "sh".into(),
+            args: vec!["-c".into(), script.into()],
+        };
+
+        check(&format!("test \"$FEELESS_ALERT_ACCOUNT\" = {}", account))
+            .notify(&alert)
+            .await
+            .unwrap();
+        assert!(check("exit 3").notify(&alert).await.is_err());
+    }
+}